@@ -0,0 +1,23 @@
+//! AI turn processing with many AI nations. Needs the headless harness, run with
+//! `cargo bench --features test_utils`.
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use eu6_simulation::test_utils::peace_offered_to_ai_nations;
+
+const NATIONS: usize = 32;
+
+fn peace_decisions(c: &mut Criterion) {
+    c.bench_function("AI peace decisions", |b| {
+        b.iter_batched(
+            || peace_offered_to_ai_nations(NATIONS),
+            |mut game| {
+                game.app.update();
+                game
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, peace_decisions);
+criterion_main!(benches);
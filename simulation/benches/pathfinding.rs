@@ -0,0 +1,30 @@
+//! Hex math and army pathfinding over a large map. Run with `cargo bench`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use eu6_simulation::bench::BenchMap;
+use std::hint::black_box;
+
+const RADIUS: i32 = 60;
+
+fn hex_math(c: &mut Criterion) {
+    let map = BenchMap::new(RADIUS);
+    c.bench_function("hex world to axial", |b| {
+        b.iter(|| black_box(&map).world_to_axial())
+    });
+    c.bench_function("hex distance", |b| b.iter(|| black_box(&map).distances()));
+    c.bench_function("hex neighbors", |b| b.iter(|| black_box(&map).neighbors()));
+}
+
+fn pathfinding(c: &mut Criterion) {
+    let map = BenchMap::new(RADIUS);
+    c.bench_function("flow field build", |b| {
+        b.iter(|| map.flow_field(black_box(RADIUS / 2), black_box(0)))
+    });
+    let field = map.flow_field(RADIUS / 2, 0);
+    c.bench_function("flow field follow", |b| {
+        b.iter(|| field.follow(black_box(&map)))
+    });
+}
+
+criterion_group!(benches, hex_math, pathfinding);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Lists the game data in the assets directory for web builds, which compile it into the binary
+//! as they can't read the directory from disk. See `EMBEDDED_DATA` in `src/mods.rs`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The game data sits at the workspace root, next to the binary crate serving it.
+const ASSETS_DIRECTORY: &str = "../assets";
+/// Extensions of the data files, images and sounds are served by the asset server instead.
+const DATA_EXTENSIONS: &[&str] = &["json", "ron", "rhai"];
+
+fn main() -> io::Result<()> {
+    println!("cargo::rerun-if-changed={}", ASSETS_DIRECTORY);
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(ASSETS_DIRECTORY);
+    let mut files = Vec::new();
+    find_data_files(&root, &mut files)?;
+    files.sort();
+
+    let mut entries = String::new();
+    for file in files {
+        let relative = file.strip_prefix(&root).unwrap();
+        let path: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        entries.push_str(&format!(
+            "    ({:?}, include_str!({:?})),\n",
+            path.join("/"),
+            file.display().to_string()
+        ));
+    }
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_data.rs");
+    fs::write(
+        out,
+        format!("const EMBEDDED_DATA: &[(&str, &str)] = &[\n{}];\n", entries),
+    )
+}
+
+fn find_data_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_data_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| DATA_EXTENSIONS.iter().any(|data| ext == *data))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
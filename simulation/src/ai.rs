@@ -0,0 +1,208 @@
+﻿use crate::army::{ActivePath, Army, ArmyComposition, Following, HexPos, InBattle, MoveArmyEvent};
+use crate::buildings::Building;
+use crate::country::{Country, DisplayName};
+use crate::forts::has_fort;
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::player::Player;
+use crate::supply::SupplyLines;
+use crate::turns::{GameState, TurnSet};
+use crate::war::{Occupied, SiegeProgress, WarRelations};
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::EnemyTurn),
+            plan_invasions.in_set(TurnSet::EnemyMoves),
+        );
+    }
+}
+
+/// Weight of a target's distance to the country's own territory, so that the AI works its way in
+/// from the border instead of striking deep.
+const BORDER_DISTANCE_WEIGHT: i32 = 3;
+/// Bonus of forts, they have to be taken before the army can march past their zone of control.
+const FORT_PRIORITY: i32 = 4;
+/// Penalty of targets beyond the country's supply lines, where the army would waste away.
+const OUT_OF_SUPPLY_PENALTY: i32 = 20;
+
+/// Enemy province an AI army can march on and besiege.
+struct InvasionTarget {
+    hex: Hex,
+    fort: bool,
+    /// Steps from the closest province the country controls.
+    border_distance: i32,
+    supplied: bool,
+}
+
+impl InvasionTarget {
+    /// How good a target it is for an army standing at `from`, lower is better.
+    fn cost(&self, from: &Hex) -> i32 {
+        let mut cost = from.distance(&self.hex) + BORDER_DISTANCE_WEIGHT * self.border_distance;
+        if self.fort {
+            cost -= FORT_PRIORITY;
+        }
+        if !self.supplied {
+            cost += OUT_OF_SUPPLY_PENALTY;
+        }
+        cost
+    }
+}
+
+/// Sends the idle armies of AI countries at war to besiege enemy provinces. Targets are picked
+/// along the country's supply lines, border forts first, and every army of a country gets a
+/// target of its own. Armies holding a siege stay put until it's done.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn plan_invasions(
+    player: Res<Player>,
+    countries: Query<(Entity, &DisplayName, &WarRelations), With<Country>>,
+    armies: Query<
+        (Entity, &HexPos, &Owner, &ArmyComposition),
+        (
+            With<Army>,
+            Without<ActivePath>,
+            Without<InBattle>,
+            Without<Following>,
+        ),
+    >,
+    provinces: Query<(
+        &Province,
+        Option<&Owner>,
+        Option<&Occupied>,
+        Option<&SiegeProgress>,
+        Option<&Children>,
+    )>,
+    buildings: Query<&Building>,
+    supply_lines: SupplyLines,
+    mut move_events: MessageWriter<MoveArmyEvent>,
+) {
+    // Entities are numbered in whatever order the world was generated or loaded, so countries and
+    // armies take their turns by what they are and a loaded game plans the same as the saved one.
+    let mut at_war: Vec<_> = countries
+        .iter()
+        .filter(|(country, _, relations)| {
+            !player.is_human(*country) && !relations.at_war_with.is_empty()
+        })
+        .collect();
+    at_war.sort_by(|(_, a, _), (_, b, _)| a.0.cmp(&b.0));
+    let owner_name = |owner: &Owner| {
+        countries
+            .get(owner.0)
+            .map_or("", |(_, name, _)| name.0.as_str())
+    };
+    let mut armies: Vec<_> = armies.iter().collect();
+    armies.sort_by_key(|(_, pos, owner, composition)| {
+        (
+            pos.0.q(),
+            pos.0.r(),
+            owner_name(owner),
+            composition.total_size(),
+        )
+    });
+
+    for (country, _, relations) in at_war {
+        let idle_armies: Vec<_> = armies
+            .iter()
+            .filter(|(_, _, owner, _)| owner.0 == country)
+            .collect();
+        if idle_armies.is_empty() {
+            continue;
+        }
+
+        let controlled: Vec<Hex> = provinces
+            .iter()
+            .filter(|(_, owner, occupied, _, _)| controller(*owner, *occupied) == Some(country))
+            .map(|(province, ..)| *province.get_hex())
+            .collect();
+        let reach = supply_lines.reach(country);
+        let mut besieging = HashSet::new();
+        let mut targets = Vec::new();
+        for (province, owner, occupied, siege, children) in &provinces {
+            let hex = *province.get_hex();
+            if siege.is_some_and(|siege| siege.is_besieged_by(country)) {
+                besieging.insert(hex);
+                continue;
+            }
+            let hostile = controller(owner, occupied)
+                .is_some_and(|controller| relations.is_at_war_with(controller));
+            if !hostile || !province.is_ownable() {
+                continue;
+            }
+            targets.push(InvasionTarget {
+                hex,
+                fort: has_fort(children, &buildings),
+                border_distance: controlled
+                    .iter()
+                    .map(|own| own.distance(&hex))
+                    .min()
+                    .unwrap_or(0),
+                supplied: reach.contains(&hex),
+            });
+        }
+
+        for &(army, pos, _, _) in idle_armies {
+            if besieging.contains(&pos.0) {
+                continue;
+            }
+            let best = targets
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, target)| (target.cost(&pos.0), target.hex.q(), target.hex.r()))
+                .map(|(index, _)| index);
+            let Some(index) = best else {
+                break;
+            };
+            // Every army gets a target of its own.
+            let target = targets.swap_remove(index);
+            info!("AI army {:?} marches on {:?}", army, target.hex);
+            move_events.write(MoveArmyEvent::new(army, HexPos(target.hex)));
+        }
+    }
+}
+
+/// Country controlling a province: its occupier, or its owner when it isn't occupied.
+fn controller(owner: Option<&Owner>, occupied: Option<&Occupied>) -> Option<Entity> {
+    occupied.map(|o| o.occupier).or(owner.map(|o| o.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buildings::BuildingType;
+    use crate::test_utils::TestGame;
+    use crate::war::siege_soldiers_required;
+
+    #[test]
+    fn ai_armies_take_border_forts_first() {
+        let mut game = TestGame::new();
+        let ai = game.spawn_country("AI");
+        let enemy = game.spawn_country("Enemy");
+        game.spawn_province("Home", Hex::new(0, 0), Some(ai));
+        game.spawn_province("Village", Hex::new(1, 0), Some(enemy));
+        let fort = game.spawn_province("Fort", Hex::new(1, -1), Some(enemy));
+        game.spawn_province("Capital", Hex::new(2, -1), Some(enemy));
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Fort,
+            },
+            ChildOf(fort),
+        ));
+        game.declare_war(ai, enemy);
+        // Strong enough to besiege the fort once it gets there.
+        let first = game.spawn_army(ai, Hex::new(0, 0), siege_soldiers_required(1) + 1000);
+
+        game.end_turn();
+        let path = &game.get::<ActivePath>(first).unwrap().path;
+        assert_eq!(path.back(), Some(&Hex::new(1, -1)));
+
+        // A second army doesn't follow the first one, it takes the next target.
+        let second = game.spawn_army(ai, Hex::new(0, 0), 3000);
+        game.end_turn();
+        let path = &game.get::<ActivePath>(second).unwrap().path;
+        assert_eq!(path.back(), Some(&Hex::new(1, 0)));
+    }
+}
@@ -0,0 +1,2163 @@
+﻿use crate::country::{Country, DisplayName};
+use crate::diplomacy::{Borders, MilitaryAccess};
+use crate::elevation;
+use crate::forts::FortZones;
+use crate::hex::Hex;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::notifications::{Notification, NotificationTarget, Notifications};
+use crate::player::Player;
+use crate::rules::{GameRng, GameRules};
+use crate::terrain::{Terrain, TerrainDef};
+use crate::turns::{GameState, TurnSet};
+use crate::units::{UnitRegistry, UnitType};
+use crate::weather::Weather;
+use crate::world::GenerateWorld;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::error::Result;
+use bevy::ecs::system::SystemParam;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use pathfinding::prelude::{dijkstra_all, dijkstra_reach};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::DerefMut;
+
+pub struct ArmyPlugin;
+
+impl Plugin for ArmyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ArmyHexMap::default())
+            .insert_resource(PathCache::default())
+            .init_resource::<MarchPreferences>()
+            .register_diagnostic(Diagnostic::new(MOVE_ACTIVE_ARMIES_TIME).with_suffix("ms"))
+            .add_message::<MoveArmyEvent>()
+            .add_message::<ArmiesMergedEvent>()
+            .add_message::<BattleStartedEvent>()
+            .add_message::<BattleJoinedEvent>()
+            .add_message::<BattleEndedEvent>()
+            .add_systems(
+                GenerateWorld,
+                spawn_initial_armies.after(crate::country::assign_province_ownership),
+            )
+            .add_systems(
+                Update,
+                (invalidate_path_cache, army_movement_system).chain(),
+            )
+            .add_systems(Update, sync_army_hex_map)
+            .add_systems(
+                OnEnter(GameState::Processing),
+                move_active_armies.in_set(TurnSet::Resolve),
+            )
+            .add_systems(
+                OnEnter(GameState::PlayerTurn),
+                follow_targets.in_set(TurnSet::Begin),
+            )
+            // One battle round per turn, armies arriving this turn fight right away.
+            .add_systems(
+                OnEnter(GameState::Processing),
+                (
+                    pull_in_reinforcements.after(move_active_armies),
+                    resolve_battles.after(pull_in_reinforcements),
+                )
+                    .in_set(TurnSet::Resolve),
+            );
+    }
+}
+
+/// Time spent in [`move_active_armies`], measured once per turn.
+pub const MOVE_ACTIVE_ARMIES_TIME: DiagnosticPath =
+    DiagnosticPath::const_new("game/move_active_armies");
+
+/// How the player wants their armies to march, following the game's settings.
+#[derive(Resource, Default)]
+pub struct MarchPreferences {
+    /// Let the player's armies march around enemy armies in their way instead of attacking them.
+    pub march_around_enemies: bool,
+    /// Stop the player's armies next to another of their armies they march into, and ask before
+    /// merging them.
+    pub confirm_army_merges: bool,
+}
+
+/// Index of armies by hex position. [`HexPos`] is the authoritative position of an army, this map
+/// is derived from it by [`sync_army_hex_map`] and only used to look armies up by location.
+/// Armies moving onto a friendly army merge with it, so a hex holds several armies only while a
+/// battle is fought there or after one was won by several armies.
+#[derive(Resource, Default)]
+pub struct ArmyHexMap {
+    tiles: HashMap<HexPos, Vec<Entity>>,
+    positions: HashMap<Entity, HexPos>,
+}
+
+impl ArmyHexMap {
+    /// Records the army at the given position, removing it from where it was before.
+    pub fn track(&mut self, army: Entity, pos: HexPos) {
+        self.forget(army);
+        self.tiles.entry(pos).or_default().push(army);
+        self.positions.insert(army, pos);
+    }
+
+    /// Removes the army from the index.
+    pub fn forget(&mut self, army: Entity) {
+        let Some(pos) = self.positions.remove(&army) else {
+            return;
+        };
+        if let Some(armies) = self.tiles.get_mut(&pos) {
+            armies.retain(|&a| a != army);
+            if armies.is_empty() {
+                self.tiles.remove(&pos);
+            }
+        }
+    }
+
+    /// Returns the army standing on the hex, the first one to arrive if there are several.
+    pub fn get(&self, pos: &HexPos) -> Option<&Entity> {
+        self.tiles.get(pos).and_then(|armies| armies.first())
+    }
+
+    /// Returns every army standing on the hex, in the order they arrived.
+    pub fn armies_at(&self, pos: &HexPos) -> &[Entity] {
+        self.tiles.get(pos).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the hex the army stands on.
+    pub fn hex_of(&self, army: Entity) -> Option<HexPos> {
+        self.positions.get(&army).copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.positions.clear();
+    }
+}
+
+#[derive(Component)]
+pub struct ActivePath {
+    pub path: VecDeque<Hex>,
+}
+
+/// Army of a human player that stopped next to another of their armies it marched into, waiting
+/// for the player to merge them or keep them apart. See [`MarchPreferences::confirm_army_merges`].
+#[derive(Component)]
+pub struct PendingMerge {
+    pub into: Entity,
+}
+
+/// Army the player sent to merge into another of their armies, it merges without asking.
+#[derive(Component)]
+pub struct MergeApproved;
+
+/// Movement points a marching army saved up towards the next hex of its path, when its terrain
+/// costs more than the army had left at the end of the turn.
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq)]
+pub struct Movement {
+    pub points: u32,
+}
+
+/// Turns an army marching `speed` movement points per turn, with `saved` points to start with,
+/// takes to enter hexes of the given movement costs one after the other.
+pub fn turns_to_march(costs: impl IntoIterator<Item = u32>, speed: u32, saved: u32) -> u32 {
+    let speed = speed.max(1);
+    let mut turns = 0;
+    let mut points = saved;
+    for cost in costs {
+        if turns == 0 {
+            turns = 1;
+            points += speed;
+        }
+        while points < cost {
+            turns += 1;
+            points += speed;
+        }
+        points -= cost;
+    }
+    turns
+}
+
+#[derive(Component)]
+pub struct Army {}
+
+/// Order to follow another friendly army, the army marches next to it every turn.
+#[derive(Component)]
+pub struct Following {
+    pub target: Entity,
+}
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct HexPos(pub Hex);
+
+impl HexPos {
+    pub fn new(hex: Hex) -> Self {
+        Self(hex)
+    }
+}
+/// Soldiers of each unit type in an army. Unit types without soldiers are left out.
+#[derive(Component, Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ArmyComposition {
+    soldiers: BTreeMap<UnitType, u32>,
+}
+
+pub const REGIMENT_SIZE: u32 = 1000;
+
+impl ArmyComposition {
+    pub fn with(mut self, unit: UnitType, soldiers: u32) -> Self {
+        self.add_soldiers(unit, soldiers);
+        self
+    }
+
+    pub fn get(&self, unit: &UnitType) -> u32 {
+        self.soldiers.get(unit).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&UnitType, u32)> {
+        self.soldiers
+            .iter()
+            .map(|(unit, &soldiers)| (unit, soldiers))
+    }
+
+    pub fn total_size(&self) -> u32 {
+        self.soldiers.values().sum()
+    }
+
+    pub fn add(&mut self, other: &ArmyComposition) {
+        for (unit, soldiers) in other.iter() {
+            self.add_soldiers(unit.clone(), soldiers);
+        }
+    }
+
+    pub fn add_unit(&mut self, unit: UnitType) {
+        self.add_soldiers(unit, REGIMENT_SIZE);
+    }
+
+    fn add_soldiers(&mut self, unit: UnitType, soldiers: u32) {
+        if soldiers > 0 {
+            *self.soldiers.entry(unit).or_default() += soldiers;
+        }
+    }
+
+    /// Brings depleted regiments back toward full strength, by up to `per_regiment` soldiers each
+    /// and `available` in all. Returns how many soldiers were added.
+    pub fn reinforce(&mut self, per_regiment: u32, available: u32) -> u32 {
+        let mut added = 0;
+        for soldiers in self.soldiers.values_mut() {
+            let regiments = soldiers.div_ceil(REGIMENT_SIZE);
+            let missing = regiments * REGIMENT_SIZE - *soldiers;
+            let reinforcements = missing.min(regiments * per_regiment).min(available - added);
+            *soldiers += reinforcements;
+            added += reinforcements;
+        }
+        added
+    }
+
+    /// Removes up to `soldiers` of the unit type, returning how many were removed.
+    pub fn remove(&mut self, unit: &UnitType, soldiers: u32) -> u32 {
+        let Some(present) = self.soldiers.get_mut(unit) else {
+            return 0;
+        };
+        let removed = soldiers.min(*present);
+        *present -= removed;
+        if *present == 0 {
+            self.soldiers.remove(unit);
+        }
+        removed
+    }
+}
+
+#[derive(Component)]
+pub struct InBattle {
+    pub battle_entity: Entity,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BattleSide {
+    Attacker,
+    Defender,
+}
+
+#[derive(Component)]
+pub struct Battle {
+    /// All armies on the attacking side
+    pub attackers: Vec<Entity>,
+    /// All armies on the defending side
+    pub defenders: Vec<Entity>,
+    /// Country that initiated the attack
+    pub attacker_country: Entity,
+    /// Country that is defending
+    pub defender_country: Entity,
+    pub location: Hex,
+    /// Hex the attack came from, the attacker fights at its elevation.
+    pub attacked_from: Hex,
+    pub round: u32,
+    pub last_damage_attacker: u32,
+    pub last_damage_defender: u32,
+}
+
+#[derive(Bundle)]
+pub struct ArmyBundle {
+    pub marker: Army,
+    pub pos: HexPos,
+    pub owner: Owner,
+    pub composition: ArmyComposition,
+}
+
+#[derive(Message)]
+pub struct MoveArmyEvent {
+    pub army: Entity,
+    pub to: HexPos,
+    /// Stop on the last hex before `to`, e.g. next to a followed army instead of merging with it.
+    pub stop_next_to: bool,
+}
+
+impl MoveArmyEvent {
+    pub fn new(army: Entity, to: HexPos) -> Self {
+        Self {
+            army,
+            to,
+            stop_next_to: false,
+        }
+    }
+
+    pub fn next_to(army: Entity, to: HexPos) -> Self {
+        Self {
+            army,
+            to,
+            stop_next_to: true,
+        }
+    }
+}
+
+/// Message sent when two hostile armies collide and a new battle begins.
+#[derive(Message)]
+pub struct BattleStartedEvent {
+    pub location: Hex,
+    pub attacker: Entity,
+    pub defender: Entity,
+    pub attacker_country: Entity,
+    pub defender_country: Entity,
+}
+
+/// Message sent when an army marches into a battle that is already being fought.
+#[derive(Message)]
+pub struct BattleJoinedEvent {
+    pub location: Hex,
+    pub army: Entity,
+    pub country: Entity,
+}
+
+/// Message sent when a battle is resolved. `winner` is `None` on mutual destruction, `survivors`
+/// lists the armies of the winning side that are still alive.
+#[derive(Message)]
+pub struct BattleEndedEvent {
+    pub location: Hex,
+    pub attacker_country: Entity,
+    pub defender_country: Entity,
+    pub winner: Option<BattleSide>,
+    pub survivors: Vec<Entity>,
+}
+
+/// Message sent when an army marched into another army of its country and merged into it.
+/// `source` is despawned by then.
+#[derive(Message)]
+pub struct ArmiesMergedEvent {
+    pub source: Entity,
+    pub target: Entity,
+}
+
+/// Flow field leading to a destination hex. Every hex that can reach the destination maps to the
+/// next hex to step on and the movement cost left from there, climbing costing more than level
+/// ground.
+pub struct FlowField {
+    destination: Hex,
+    reachable: bool,
+    steps: HashMap<Hex, (Hex, usize)>,
+}
+
+impl FlowField {
+    pub fn build(
+        destination: Hex,
+        is_passable: impl Fn(&Hex) -> bool,
+        can_step: impl Fn(&Hex, &Hex) -> bool,
+        elevation: impl Fn(&Hex) -> f32,
+    ) -> Self {
+        let reachable = is_passable(&destination);
+        let steps = if reachable {
+            // The search runs backwards from the destination, an edge to `n` is a step from `n`.
+            dijkstra_all(&destination, |hex| {
+                hex.neighbors()
+                    .into_iter()
+                    .filter(|n| is_passable(n) && can_step(n, hex))
+                    .map(|n| (n, elevation::step_cost(elevation(&n), elevation(hex))))
+                    .collect::<Vec<_>>()
+            })
+        } else {
+            HashMap::new()
+        };
+        Self {
+            destination,
+            reachable,
+            steps,
+        }
+    }
+
+    fn distance(&self, hex: &Hex) -> Option<usize> {
+        if !self.reachable {
+            None
+        } else if *hex == self.destination {
+            Some(0)
+        } else {
+            self.steps.get(hex).map(|&(_, distance)| distance)
+        }
+    }
+
+    /// Shortest path from `start` to the destination, without `start` itself.
+    pub fn path_from(&self, start: Hex) -> Option<VecDeque<Hex>> {
+        let mut path = VecDeque::new();
+        let mut current = start;
+        if self.distance(&start).is_none() {
+            // The field only covers passable hexes, an army standing elsewhere steps onto its
+            // closest passable neighbour first.
+            current = start
+                .neighbors()
+                .into_iter()
+                .filter_map(|n| self.distance(&n).map(|distance| (n, distance)))
+                .min_by_key(|&(_, distance)| distance)?
+                .0;
+            path.push_back(current);
+        }
+        while let Some(&(next, _)) = self.steps.get(&current) {
+            path.push_back(next);
+            current = next;
+        }
+        Some(path)
+    }
+}
+
+/// Flow fields by destination and country, so armies of a country heading to the same hex share a
+/// single search. Countries get their own fields since hostile forts and closed borders block them
+/// differently. Cleared by [`invalidate_path_cache`] whenever the map, province ownership,
+/// occupation, forts, wars or military access change.
+#[derive(Resource, Default)]
+pub struct PathCache {
+    flow_fields: HashMap<(Hex, Entity), FlowField>,
+}
+
+impl PathCache {
+    pub fn path(
+        &mut self,
+        from: Hex,
+        to: Hex,
+        country: Entity,
+        is_passable: impl Fn(&Hex) -> bool,
+        can_step: impl Fn(&Hex, &Hex) -> bool,
+        elevation: impl Fn(&Hex) -> f32,
+    ) -> Option<VecDeque<Hex>> {
+        self.flow_fields
+            .entry((to, country))
+            .or_insert_with(|| FlowField::build(to, is_passable, can_step, elevation))
+            .path_from(from)
+    }
+
+    pub fn clear(&mut self) {
+        self.flow_fields.clear();
+    }
+}
+
+/// What army pathfinding needs to know about the map: which hexes are passable and how high they
+/// lie, the hostile forts and the closed borders.
+#[derive(SystemParam)]
+pub struct Pathing<'w, 's> {
+    province_map: Res<'w, ProvinceHexMap>,
+    provinces: Query<'w, 's, &'static Province>,
+    fort_zones: FortZones<'w, 's>,
+    borders: Borders<'w, 's>,
+}
+
+impl Pathing<'_, '_> {
+    fn province_at(&self, hex: &Hex) -> Option<&Province> {
+        self.province_map
+            .get_entity(hex)
+            .and_then(|&entity| self.provinces.get(entity).ok())
+    }
+
+    fn passable(&self, hex: &Hex) -> bool {
+        self.province_at(hex)
+            .is_some_and(|province| province.is_passable())
+    }
+
+    fn elevation(&self, hex: &Hex) -> f32 {
+        self.province_at(hex)
+            .map_or(0.0, |province| province.elevation())
+    }
+
+    /// Movement points an army spends to enter the hex.
+    fn movement_cost(&self, hex: &Hex) -> u32 {
+        self.province_at(hex)
+            .map_or(1, |province| province.terrain().movement_cost)
+    }
+
+    /// Path for an army of `country` that keeps off the `avoid` hexes. Unlike the paths of the
+    /// [`PathCache`], it is searched anew on every call.
+    fn detour(
+        &self,
+        from: Hex,
+        to: Hex,
+        country: Entity,
+        avoid: &HashSet<Hex>,
+    ) -> Option<VecDeque<Hex>> {
+        let zones = self.fort_zones.hostile_to(country);
+        let closed = self.borders.closed_to(country);
+        FlowField::build(
+            to,
+            |hex| self.passable(hex) && closed.allows(hex) && !avoid.contains(hex),
+            |from, to| zones.allows_step(from, to),
+            |hex| self.elevation(hex),
+        )
+        .path_from(from)
+    }
+
+    /// Hexes an army of `country` on `from` can enter with `points` movement points, with the
+    /// points it spends to get there. The search doesn't march on from the `stops`, where other
+    /// armies would halt the army, and never enters hexes that aren't `enterable`.
+    pub fn reach(
+        &self,
+        from: Hex,
+        country: Entity,
+        points: u32,
+        enterable: impl Fn(&Hex) -> bool,
+        stops: impl Fn(&Hex) -> bool,
+    ) -> HashMap<Hex, u32> {
+        let zones = self.fort_zones.hostile_to(country);
+        let closed = self.borders.closed_to(country);
+        dijkstra_reach(&from, |hex| {
+            let marches_on = *hex == from || !stops(hex);
+            hex.neighbors()
+                .into_iter()
+                .filter(|n| {
+                    marches_on
+                        && self.passable(n)
+                        && enterable(n)
+                        && closed.allows(n)
+                        && zones.allows_step(hex, n)
+                })
+                .map(|n| (n, self.movement_cost(&n)))
+                .collect::<Vec<_>>()
+        })
+        .take_while(|item| item.total_cost <= points)
+        .map(|item| (item.node, item.total_cost))
+        .collect()
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn invalidate_path_cache(
+    mut path_cache: ResMut<PathCache>,
+    province_map: Res<ProvinceHexMap>,
+    provinces: Query<(), With<Province>>,
+    changed_owners: Query<(), (With<Province>, Changed<Owner>)>,
+    new_occupations: Query<(), Added<crate::war::Occupied>>,
+    new_sieges: Query<(), Added<crate::war::SiegeProgress>>,
+    new_buildings: Query<(), Added<crate::buildings::Building>>,
+    changed_wars: Query<(), Changed<crate::war::WarRelations>>,
+    changed_access: Query<(), Changed<MilitaryAccess>>,
+    mut removed_owners: RemovedComponents<Owner>,
+    mut removed_occupations: RemovedComponents<crate::war::Occupied>,
+    mut removed_sieges: RemovedComponents<crate::war::SiegeProgress>,
+) {
+    // Armies carry an owner too, only provinces losing theirs matter.
+    let owner_removed = removed_owners
+        .read()
+        .filter(|&entity| provinces.contains(entity))
+        .count()
+        > 0;
+    let occupation_removed = removed_occupations.read().count() > 0;
+    // Forts block armies again once a siege is lifted.
+    let siege_removed = removed_sieges.read().count() > 0;
+
+    if province_map.is_changed()
+        || owner_removed
+        || occupation_removed
+        || siege_removed
+        || !changed_owners.is_empty()
+        || !new_occupations.is_empty()
+        || !new_sieges.is_empty()
+        || !new_buildings.is_empty()
+        || !changed_wars.is_empty()
+        || !changed_access.is_empty()
+    {
+        path_cache.clear();
+    }
+}
+
+/// Starts armies on their way. Armies only march through their own land, unowned land, the land of
+/// countries they are at war with and of those that granted them military access. The player is
+/// told who is in the way when a destination can't be reached because of it.
+#[allow(clippy::too_many_arguments)]
+pub fn army_movement_system(
+    mut commands: Commands,
+    mut move_events: MessageReader<MoveArmyEvent>,
+    army_hex_map: Res<ArmyHexMap>,
+    mut path_cache: ResMut<PathCache>,
+    owners: Query<&Owner, With<Army>>,
+    pathing: Pathing,
+    player: Res<Player>,
+    names: Query<&DisplayName>,
+    mut notifications: ResMut<Notifications>,
+) -> Result {
+    for event in move_events.read() {
+        let from_pos = match army_hex_map.hex_of(event.army) {
+            Some(pos) => pos,
+            None => {
+                warn!(
+                    "Army movement event for unknown army entity: {:?}",
+                    event.army
+                );
+                continue;
+            }
+        };
+
+        if from_pos == event.to {
+            continue;
+        }
+
+        let Ok(&Owner(country)) = owners.get(event.army) else {
+            continue;
+        };
+        let zones = pathing.fort_zones.hostile_to(country);
+        let closed = pathing.borders.closed_to(country);
+        let path = path_cache.path(
+            from_pos.0,
+            event.to.0,
+            country,
+            |hex| pathing.passable(hex) && closed.allows(hex),
+            |from, to| zones.allows_step(from, to),
+            |hex| pathing.elevation(hex),
+        );
+
+        if let Some(mut deck) = path {
+            if event.stop_next_to {
+                deck.pop_back();
+            }
+            if !deck.is_empty() {
+                commands
+                    .entity(event.army)
+                    .insert(ActivePath { path: deck.clone() });
+                info!(
+                    "Army {:?} started moving to {:?}, path length: {}",
+                    event.army,
+                    event.to,
+                    deck.len()
+                );
+            }
+        } else {
+            warn!(
+                "No path found for army {:?} from {:?} to {:?}",
+                event.army, from_pos, event.to
+            );
+            if player.country != Some(country) {
+                continue;
+            }
+            // Retrace the march as if all borders were open to find who stands in the way.
+            let barrier = closed.barred_by(&event.to.0).or_else(|| {
+                FlowField::build(
+                    event.to.0,
+                    |hex| pathing.passable(hex),
+                    |from, to| zones.allows_step(from, to),
+                    |hex| pathing.elevation(hex),
+                )
+                .path_from(from_pos.0)
+                .and_then(|path| path.iter().find_map(|hex| closed.barred_by(hex)))
+            });
+            if let Some(barrier) = barrier {
+                let name = names.get(barrier).map_or("Unknown", |n| n.0.as_str());
+                notifications.push(Notification {
+                    title: "🛡 No military access".to_string(),
+                    text: format!(
+                        "Our army can't march through the land of {}. We have to be at war with \
+                         them or be granted military access.",
+                        name
+                    ),
+                    target: Some(NotificationTarget::Army {
+                        army: event.army,
+                        location: from_pos.0,
+                    }),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn move_active_armies(
+    mut commands: Commands,
+    mut army_hex_map: ResMut<ArmyHexMap>,
+    mut armies_query: Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    war_relations: Query<&crate::war::WarRelations>,
+    mut battles: Query<&mut Battle>,
+    saved_movement: Query<&Movement>,
+    merge_approved: Query<(), With<MergeApproved>>,
+    units: Res<UnitRegistry>,
+    weather: Res<Weather>,
+    preferences: Res<MarchPreferences>,
+    player: Res<Player>,
+    pathing: Pathing,
+    mut diagnostics: Diagnostics,
+) {
+    let start = Instant::now();
+    // Armies that stopped marching lose the points they saved up.
+    for (army, _, _, _, path, _) in &armies_query {
+        if path.is_none() && saved_movement.contains(army) {
+            commands.entity(army).remove::<Movement>();
+        }
+    }
+    // Armies of human players ask before merging, unless they were sent to merge.
+    let asks_before_merging: HashSet<Entity> = armies_query
+        .iter()
+        .filter(|(army, owner, ..)| {
+            preferences.confirm_army_merges
+                && player.is_human(owner.0)
+                && !merge_approved.contains(*army)
+        })
+        .map(|(army, ..)| army)
+        .collect();
+    // Armies caught in weather that halts movement wait it out.
+    let movers: Vec<Entity> = armies_query
+        .iter()
+        .filter(|(_, _, _, pos, path, _)| path.is_some() && !weather.halts_movement(pos.0))
+        .map(|(e, ..)| e)
+        .collect();
+
+    for entity in movers {
+        let speed = armies_query
+            .get(entity)
+            .map_or(1, |(_, _, composition, ..)| units.movement(composition));
+        let mut points = saved_movement.get(entity).map_or(0, |m| m.points) + speed;
+        // The army marches on while it has the points to enter the next hex, and saves what it
+        // has left when it doesn't.
+        let saved = loop {
+            if preferences.march_around_enemies {
+                reroute_around_enemies(
+                    &army_hex_map,
+                    &mut armies_query,
+                    &war_relations,
+                    &player,
+                    &pathing,
+                    entity,
+                );
+            }
+            if let Ok((_, _, _, _, Some(active_path), _)) = armies_query.get(entity)
+                && let Some(&next) = active_path.path.front()
+            {
+                let cost = pathing.movement_cost(&next);
+                if cost > points || weather.halts_movement(next) {
+                    // Waiting out the weather doesn't save up more than the hex costs.
+                    break Some(points.min(cost.saturating_sub(1)));
+                }
+                points -= cost;
+            }
+            if !process_army_movement(
+                &mut commands,
+                &mut army_hex_map,
+                &mut armies_query,
+                &war_relations,
+                &mut battles,
+                &asks_before_merging,
+                entity,
+            ) {
+                break None;
+            }
+        };
+        // Merged armies are already despawned.
+        if let Some(points) = saved {
+            commands.entity(entity).try_insert(Movement { points });
+        } else {
+            commands.entity(entity).try_remove::<Movement>();
+        }
+    }
+
+    diagnostics.add_measurement(&MOVE_ACTIVE_ARMIES_TIME, || {
+        start.elapsed().as_secs_f64() * 1000.0
+    });
+}
+
+/// Moves the army a step along its path, unless it joins a battle, attacks or merges into the
+/// army standing there. Returns whether the army marched on.
+#[allow(clippy::too_many_arguments)]
+fn process_army_movement(
+    commands: &mut Commands,
+    army_hex_map: &mut ResMut<ArmyHexMap>,
+    armies_query: &mut Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    war_relations: &Query<&crate::war::WarRelations>,
+    battles: &mut Query<&mut Battle>,
+    asks_before_merging: &HashSet<Entity>,
+    entity: Entity,
+) -> bool {
+    let Some(next_hex) = get_next_move(armies_query, commands, entity) else {
+        return false;
+    };
+    let next_pos = HexPos(next_hex);
+
+    if try_join_battle(
+        commands,
+        army_hex_map,
+        armies_query,
+        war_relations,
+        battles,
+        entity,
+        next_hex,
+    ) {
+        return false;
+    }
+
+    if try_handle_collision(
+        commands,
+        army_hex_map,
+        armies_query,
+        war_relations,
+        asks_before_merging,
+        entity,
+        next_hex,
+        next_pos,
+    ) {
+        return false;
+    }
+
+    execute_movement(commands, army_hex_map, armies_query, entity, next_pos);
+    true
+}
+
+/// Sends a player's army around a hostile army standing in its way, when the player would rather
+/// not attack it. Enemies holding the destination are still attacked, and so are those there is no
+/// way around.
+fn reroute_around_enemies(
+    army_hex_map: &ArmyHexMap,
+    armies_query: &mut Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    war_relations: &Query<&crate::war::WarRelations>,
+    player: &Player,
+    pathing: &Pathing,
+    entity: Entity,
+) {
+    let Ok((_, &Owner(country), _, pos, Some(active_path), _)) = armies_query.get(entity) else {
+        return;
+    };
+    let (Some(&next), Some(&destination)) = (active_path.path.front(), active_path.path.back())
+    else {
+        return;
+    };
+    let from = pos.0;
+    if !player.is_human(country) || next == destination {
+        return;
+    }
+    let is_hostile = |owner: &Owner| crate::war::are_at_war(country, owner.0, war_relations);
+    let blocked = army_hex_map
+        .get(&HexPos(next))
+        .and_then(|&occupant| armies_query.get(occupant).ok())
+        .is_some_and(|(_, owner, ..)| is_hostile(owner));
+    if !blocked {
+        return;
+    }
+
+    let enemies: HashSet<Hex> = armies_query
+        .iter()
+        .filter(|(_, owner, ..)| is_hostile(owner))
+        .map(|(_, _, _, pos, ..)| pos.0)
+        .filter(|hex| *hex != destination)
+        .collect();
+    let Some(detour) = pathing.detour(from, destination, country, &enemies) else {
+        info!(
+            "Army {:?} finds no way around the enemy at {:?}",
+            entity, next
+        );
+        return;
+    };
+    if let Ok((_, _, _, _, Some(mut active_path), _)) = armies_query.get_mut(entity) {
+        info!("Army {:?} marches around the enemy at {:?}", entity, next);
+        active_path.path = detour;
+    }
+}
+
+fn get_next_move(
+    armies_query: &Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    commands: &mut Commands,
+    entity: Entity,
+) -> Option<Hex> {
+    if let Ok((_, _, _, _, Some(active_path), _)) = armies_query.get(entity) {
+        if let Some(h) = active_path.path.front() {
+            Some(*h)
+        } else {
+            commands.entity(entity).remove::<ActivePath>();
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn try_join_battle(
+    commands: &mut Commands,
+    army_hex_map: &mut ResMut<ArmyHexMap>,
+    armies_query: &mut Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    war_relations: &Query<&crate::war::WarRelations>,
+    battles: &mut Query<&mut Battle>,
+    entity: Entity,
+    next_hex: Hex,
+) -> bool {
+    let battle_at_location = find_battle_at_location(armies_query, battles, next_hex);
+
+    let Some(battle_entity) = battle_at_location else {
+        return false;
+    };
+
+    let Ok((_, owner, _, _, _, _)) = armies_query.get(entity) else {
+        return false;
+    };
+    let Ok(mut battle) = battles.get_mut(battle_entity) else {
+        return false;
+    };
+
+    let owner_entity = owner.0;
+    let side = determine_battle_side(owner_entity, &battle, war_relations);
+
+    let Some(side) = side else {
+        return false;
+    };
+
+    info!(
+        "Army {:?} joins battle at {:?} on {:?} side",
+        entity, next_hex, side
+    );
+
+    match side {
+        BattleSide::Attacker => battle.attackers.push(entity),
+        BattleSide::Defender => battle.defenders.push(entity),
+    }
+
+    commands.entity(entity).remove::<ActivePath>();
+    commands.entity(entity).insert(InBattle { battle_entity });
+    commands.write_message(BattleJoinedEvent {
+        location: next_hex,
+        army: entity,
+        country: owner_entity,
+    });
+
+    if let Ok((_, _, _, mut pos, _, _)) = armies_query.get_mut(entity) {
+        *pos = HexPos(next_hex);
+        army_hex_map.track(entity, *pos);
+    }
+    true
+}
+
+fn find_battle_at_location(
+    armies_query: &Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    battles: &Query<&mut Battle>,
+    hex: Hex,
+) -> Option<Entity> {
+    for (_, _, _, _, _, maybe_in_battle) in armies_query.iter() {
+        if let Some(in_battle) = maybe_in_battle {
+            if let Ok(battle) = battles.get(in_battle.battle_entity) {
+                if battle.location == hex {
+                    return Some(in_battle.battle_entity);
+                }
+            }
+        }
+    }
+    None
+}
+
+pub fn determine_battle_side(
+    owner: Entity,
+    battle: &Battle,
+    war_relations: &Query<&crate::war::WarRelations>,
+) -> Option<BattleSide> {
+    if owner == battle.attacker_country {
+        Some(BattleSide::Attacker)
+    } else if owner == battle.defender_country {
+        Some(BattleSide::Defender)
+    } else if crate::war::are_at_war(owner, battle.defender_country, war_relations) {
+        Some(BattleSide::Attacker)
+    } else if crate::war::are_at_war(owner, battle.attacker_country, war_relations) {
+        Some(BattleSide::Defender)
+    } else {
+        None
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn try_handle_collision(
+    commands: &mut Commands,
+    army_hex_map: &mut ResMut<ArmyHexMap>,
+    armies_query: &mut Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    war_relations: &Query<&crate::war::WarRelations>,
+    asks_before_merging: &HashSet<Entity>,
+    entity: Entity,
+    next_hex: Hex,
+    next_pos: HexPos,
+) -> bool {
+    let Some(&occupant_entity) = army_hex_map.get(&next_pos) else {
+        return false;
+    };
+
+    if armies_query.get(occupant_entity).is_err() {
+        army_hex_map.forget(occupant_entity);
+        return false;
+    }
+
+    let Ok(
+        [
+            (e1, owner1, comp1, pos1, _, _),
+            (e2, owner2, mut comp2, _, _, _),
+        ],
+    ) = armies_query.get_many_mut([entity, occupant_entity])
+    else {
+        return true;
+    };
+
+    if owner1.0 == owner2.0 {
+        if asks_before_merging.contains(&e1) {
+            info!("Army {:?} waits for the order to merge into {:?}", e1, e2);
+            commands
+                .entity(e1)
+                .remove::<ActivePath>()
+                .insert(PendingMerge { into: e2 });
+            return true;
+        }
+        merge_armies(commands, army_hex_map, e1, e2, &comp1, &mut comp2);
+        return true;
+    }
+
+    if !crate::war::are_at_war(owner1.0, owner2.0, war_relations) {
+        info!(
+            "Cannot attack: {:?} and {:?} are not at war",
+            owner1.0, owner2.0
+        );
+        commands.entity(e1).remove::<ActivePath>();
+        return true;
+    }
+
+    start_battle(commands, e1, e2, owner1.0, owner2.0, next_hex, pos1.0);
+    true
+}
+
+fn merge_armies(
+    commands: &mut Commands,
+    army_hex_map: &mut ResMut<ArmyHexMap>,
+    source: Entity,
+    target: Entity,
+    source_comp: &ArmyComposition,
+    target_comp: &mut ArmyComposition,
+) {
+    info!("Merging army {:?} into {:?}", source, target);
+    target_comp.add(source_comp);
+    army_hex_map.forget(source);
+    commands.entity(source).despawn();
+
+    commands.write_message(ArmiesMergedEvent { source, target });
+}
+
+fn start_battle(
+    commands: &mut Commands,
+    attacker: Entity,
+    defender: Entity,
+    attacker_country: Entity,
+    defender_country: Entity,
+    location: Hex,
+    attacked_from: Hex,
+) {
+    info!(
+        "Battle started between {:?} and {:?} at {:?}",
+        attacker, defender, location
+    );
+    commands.entity(attacker).remove::<ActivePath>();
+
+    let battle_id = commands
+        .spawn(Battle {
+            attackers: vec![attacker],
+            defenders: vec![defender],
+            attacker_country,
+            defender_country,
+            location,
+            attacked_from,
+            round: 0,
+            last_damage_attacker: 0,
+            last_damage_defender: 0,
+        })
+        .id();
+
+    commands.entity(attacker).insert(InBattle {
+        battle_entity: battle_id,
+    });
+    commands.entity(defender).insert(InBattle {
+        battle_entity: battle_id,
+    });
+    commands.write_message(BattleStartedEvent {
+        location,
+        attacker,
+        defender,
+        attacker_country,
+        defender_country,
+    });
+}
+
+fn execute_movement(
+    commands: &mut Commands,
+    army_hex_map: &mut ResMut<ArmyHexMap>,
+    armies_query: &mut Query<
+        (
+            Entity,
+            &Owner,
+            &mut ArmyComposition,
+            &mut HexPos,
+            Option<&mut ActivePath>,
+            Option<&InBattle>,
+        ),
+        With<Army>,
+    >,
+    entity: Entity,
+    next_pos: HexPos,
+) {
+    if let Ok((_, _, _, mut pos, Some(mut active_path), _)) = armies_query.get_mut(entity) {
+        active_path.path.pop_front();
+        *pos = next_pos;
+        // Armies later in this batch have to see the move, before the sync system catches up.
+        army_hex_map.track(entity, next_pos);
+
+        if active_path.path.is_empty() {
+            commands.entity(entity).remove::<ActivePath>();
+            info!("Army {:?} arrived at destination {:?}", entity, next_pos);
+        }
+    }
+}
+
+/// Sends every army with a [`Following`] order towards its target at the start of the turn, and
+/// drops the order once the target is gone.
+pub fn follow_targets(
+    mut commands: Commands,
+    followers: Query<(Entity, &Following, &HexPos, Option<&InBattle>), With<Army>>,
+    targets: Query<&HexPos, With<Army>>,
+    mut move_events: MessageWriter<MoveArmyEvent>,
+) {
+    for (army, following, pos, in_battle) in &followers {
+        let Ok(target_pos) = targets.get(following.target) else {
+            info!("Army {:?} lost the army it was following", army);
+            commands.entity(army).remove::<Following>();
+            continue;
+        };
+        if in_battle.is_none() && pos.0.distance(&target_pos.0) > 1 {
+            move_events.write(MoveArmyEvent::next_to(army, *target_pos));
+        }
+    }
+}
+
+/// Keeps [`ArmyHexMap`] in sync with the armies' [`HexPos`], including spawned and despawned
+/// armies.
+#[allow(clippy::type_complexity)]
+pub fn sync_army_hex_map(
+    mut army_hex_map: ResMut<ArmyHexMap>,
+    moved_armies: Query<(Entity, &HexPos), (With<Army>, Changed<HexPos>)>,
+    mut removed_armies: RemovedComponents<HexPos>,
+) {
+    for army in removed_armies.read() {
+        army_hex_map.forget(army);
+    }
+    for (army, pos) in &moved_armies {
+        army_hex_map.track(army, *pos);
+    }
+}
+
+pub fn spawn_army(
+    commands: &mut Commands,
+    position: Hex,
+    owner: Entity,
+    composition: ArmyComposition,
+) -> Entity {
+    commands
+        .spawn(ArmyBundle {
+            marker: Army {},
+            pos: HexPos(position),
+            owner: Owner(owner),
+            composition,
+        })
+        .id()
+}
+
+pub fn spawn_initial_armies(
+    mut commands: Commands,
+    countries: Query<Entity, With<Country>>,
+    provinces: Query<(&Owner, &Province)>,
+    units: Res<UnitRegistry>,
+) {
+    let mut country_provinces: HashMap<Entity, Vec<Hex>> = HashMap::new();
+
+    for (owner, province) in provinces.iter() {
+        country_provinces
+            .entry(owner.0)
+            .or_default()
+            .push(*province.get_hex());
+    }
+
+    for country in countries.iter() {
+        if let Some(province_hexes) = country_provinces.get(&country)
+            && let Some(&start_hex) = province_hexes.first()
+        {
+            spawn_army(&mut commands, start_hex, country, units.starting_army());
+        }
+    }
+}
+
+/// Pulls the idle armies of the warring sides standing within
+/// [`GameRules::reinforcement_radius`] of a battle into it, before its next round is fought. They
+/// fight from where they stand, armies on the move are left alone.
+#[allow(clippy::type_complexity)]
+pub fn pull_in_reinforcements(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    mut battles: Query<(Entity, &mut Battle)>,
+    armies: Query<
+        (Entity, &HexPos, &Owner, &ArmyComposition),
+        (With<Army>, Without<InBattle>, Without<ActivePath>),
+    >,
+    war_relations: Query<&crate::war::WarRelations>,
+    names: Query<&DisplayName>,
+) {
+    if rules.reinforcement_radius == 0 {
+        return;
+    }
+    // Entity numbering depends on the spawn and load order, which differs between the games of a
+    // multiplayer session, so order by what the players see instead.
+    let owner_name = |owner: &Owner| names.get(owner.0).map_or("", |name| name.0.as_str());
+    let mut idle: Vec<_> = armies.iter().collect();
+    idle.sort_by_key(|(_, pos, owner, composition)| {
+        (
+            pos.0.q(),
+            pos.0.r(),
+            owner_name(owner),
+            composition.total_size(),
+        )
+    });
+    let mut battles: Vec<_> = battles.iter_mut().collect();
+    battles.sort_by_key(|(_, battle)| (battle.location.q(), battle.location.r()));
+
+    for (battle_entity, mut battle) in battles {
+        // An army close to several battles joins the first one.
+        idle.retain(|&(army, pos, owner, _)| {
+            if pos.0.distance(&battle.location) > rules.reinforcement_radius as i32 {
+                return true;
+            }
+            let Some(side) = determine_battle_side(owner.0, &battle, &war_relations) else {
+                return true;
+            };
+            info!(
+                "Army {:?} reinforces the {:?} side of the battle at {:?}",
+                army, side, battle.location
+            );
+            match side {
+                BattleSide::Attacker => battle.attackers.push(army),
+                BattleSide::Defender => battle.defenders.push(army),
+            }
+            commands.entity(army).insert(InBattle { battle_entity });
+            commands.write_message(BattleJoinedEvent {
+                location: battle.location,
+                army,
+                country: owner.0,
+            });
+            false
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_battles(
+    mut commands: Commands,
+    mut battles: Query<(Entity, &mut Battle)>,
+    mut armies: Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
+    province_map: Res<ProvinceHexMap>,
+    provinces: Query<(&Province, &Owner)>,
+    mut rng: ResMut<GameRng>,
+    mut battle_ended_events: MessageWriter<BattleEndedEvent>,
+    units: Res<UnitRegistry>,
+    weather: Res<Weather>,
+) {
+    // Each battle rolls the shared RNG, so fight them in the same order in every game.
+    let mut battles: Vec<_> = battles.iter_mut().collect();
+    battles.sort_by_key(|(_, battle)| (battle.location.q(), battle.location.r()));
+    for (battle_entity, mut battle) in battles {
+        // Clean up dead armies from the battle
+        battle.attackers.retain(|&e| {
+            armies
+                .get(e)
+                .map(|(_, comp, _, _)| comp.total_size() > 0)
+                .unwrap_or(false)
+        });
+        battle.defenders.retain(|&e| {
+            armies
+                .get(e)
+                .map(|(_, comp, _, _)| comp.total_size() > 0)
+                .unwrap_or(false)
+        });
+
+        // Check if battle should end
+        if battle.attackers.is_empty() && battle.defenders.is_empty() {
+            info!(
+                "Battle at {:?} ended in mutual destruction after {} rounds",
+                battle.location, battle.round
+            );
+            battle_ended_events.write(BattleEndedEvent {
+                location: battle.location,
+                attacker_country: battle.attacker_country,
+                defender_country: battle.defender_country,
+                winner: None,
+                survivors: Vec::new(),
+            });
+            commands.entity(battle_entity).despawn();
+            continue;
+        } else if battle.attackers.is_empty() {
+            info!(
+                "Defenders won battle at {:?} after {} rounds",
+                battle.location, battle.round
+            );
+            end_battle_multi(
+                &mut commands,
+                &mut armies,
+                battle_entity,
+                &battle,
+                BattleSide::Defender,
+            );
+            battle_ended_events.write(BattleEndedEvent {
+                location: battle.location,
+                attacker_country: battle.attacker_country,
+                defender_country: battle.defender_country,
+                winner: Some(BattleSide::Defender),
+                survivors: battle.defenders.clone(),
+            });
+            continue;
+        } else if battle.defenders.is_empty() {
+            info!(
+                "Attackers won battle at {:?} after {} rounds",
+                battle.location, battle.round
+            );
+            end_battle_multi(
+                &mut commands,
+                &mut armies,
+                battle_entity,
+                &battle,
+                BattleSide::Attacker,
+            );
+            battle_ended_events.write(BattleEndedEvent {
+                location: battle.location,
+                attacker_country: battle.attacker_country,
+                defender_country: battle.defender_country,
+                winner: Some(BattleSide::Attacker),
+                survivors: battle.attackers.clone(),
+            });
+            continue;
+        }
+
+        // Get terrain at battle location for combat modifiers
+        let terrain = province_map
+            .get_entity(&battle.location)
+            .and_then(|&e| provinces.get(e).ok())
+            .map(|(p, _)| p.terrain().clone())
+            .unwrap_or_else(|| Terrain::Plains.def());
+        let elevation_at = |hex: &Hex| {
+            province_map
+                .get_entity(hex)
+                .and_then(|&e| provinces.get(e).ok())
+                .map_or(0.0, |(p, _)| p.elevation())
+        };
+
+        // The defender holds the high ground when standing above the hex the attack came from.
+        let defender_terrain_bonus = terrain.defender_bonus
+            * elevation::high_ground_bonus(
+                elevation_at(&battle.location),
+                elevation_at(&battle.attacked_from),
+            );
+
+        // Log terrain effects on first round
+        if battle.round == 0 {
+            info!(
+                "Battle at {:?} on {} terrain - Attackers: {} armies, Defenders: {} armies",
+                battle.location,
+                terrain.name,
+                battle.attackers.len(),
+                battle.defenders.len()
+            );
+        }
+
+        // Calculate combined strength for each side
+        fn calc_side_damage(
+            armies: &Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
+            army_list: &[Entity],
+            units: &UnitRegistry,
+            terrain: &TerrainDef,
+        ) -> f32 {
+            let mut total_damage = 0.0;
+            for &army_entity in army_list {
+                if let Ok((_, comp, _, _)) = armies.get(army_entity) {
+                    total_damage += units.damage(comp, terrain);
+                }
+            }
+            total_damage
+        }
+
+        let att_roll: f32 = rng.random_range(0.8..1.2);
+        let def_roll: f32 = rng.random_range(0.8..1.2);
+
+        let att_base_dmg = calc_side_damage(&armies, &battle.attackers, &units, &terrain);
+        let def_base_dmg = calc_side_damage(&armies, &battle.defenders, &units, &terrain);
+
+        // Apply terrain bonuses, bad weather dampens both sides
+        let weather_modifier = weather.combat_modifier(battle.location);
+        let att_dmg = att_base_dmg * att_roll * weather_modifier / defender_terrain_bonus;
+        let def_dmg = def_base_dmg * def_roll * weather_modifier * defender_terrain_bonus;
+
+        // Distribute damage across the armies of each side by their hit points, so every army
+        // loses the same share of its soldiers.
+        fn apply_damage_to_side(
+            armies: &mut Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
+            army_list: &[Entity],
+            total_damage: f32,
+            units: &UnitRegistry,
+        ) -> u32 {
+            let side_hit_points: f32 = army_list
+                .iter()
+                .filter_map(|&e| armies.get(e).ok())
+                .map(|(_, comp, _, _)| units.hit_points(comp))
+                .sum();
+            if side_hit_points <= 0.0 {
+                return 0;
+            }
+
+            let mut total_lost = 0;
+            for &army_entity in army_list {
+                if let Ok((_, mut comp, _, _)) = armies.get_mut(army_entity) {
+                    let share = units.hit_points(&comp) / side_hit_points;
+                    total_lost +=
+                        apply_damage_to_composition(&mut comp, total_damage * share, units);
+                }
+            }
+            total_lost
+        }
+
+        let att_lost = apply_damage_to_side(&mut armies, &battle.attackers, def_dmg, &units);
+        let def_lost = apply_damage_to_side(&mut armies, &battle.defenders, att_dmg, &units);
+
+        battle.last_damage_attacker = att_lost;
+        battle.last_damage_defender = def_lost;
+        battle.round += 1;
+
+        info!(
+            "Battle round {} at {:?}: Attackers lost {}, Defenders lost {}",
+            battle.round, battle.location, att_lost, def_lost
+        );
+
+        // Despawn dead armies, the hex map forgets them on its own
+        let mut to_despawn = Vec::new();
+        for &army_entity in battle.attackers.iter().chain(battle.defenders.iter()) {
+            if let Ok((_, comp, _, _)) = armies.get(army_entity)
+                && comp.total_size() == 0
+            {
+                to_despawn.push(army_entity);
+            }
+        }
+        for army_entity in to_despawn {
+            commands.entity(army_entity).despawn();
+        }
+
+        // Battle continues next turn - don't end it here
+    }
+}
+
+/// Kills the soldiers the damage is enough for. The share of an army lost in a round is the ratio
+/// between the damage it takes and its hit points, so small and large stacks alike lose soldiers in
+/// proportion. The damage is spread over the unit types by their hit points, artillery taking a
+/// little less, so armies keep their composition through long battles. Any damage kills at least
+/// one soldier. Returns the soldiers killed.
+fn apply_damage_to_composition(
+    comp: &mut ArmyComposition,
+    damage: f32,
+    units: &UnitRegistry,
+) -> u32 {
+    let mut remaining_damage = damage;
+    let mut lost = 0;
+    let mut exposed = units.casualty_order(comp);
+    let total_hit_points = |unit: &UnitType, comp: &ArmyComposition| {
+        comp.get(unit) as f32 * units.soldier_hit_points(unit)
+    };
+    let share_of = |unit: &UnitType, comp: &ArmyComposition, exposed: &[UnitType], damage: f32| {
+        let weight: f32 = exposed
+            .iter()
+            .map(|unit| units.exposed_hit_points(unit, comp.get(unit)))
+            .sum();
+        damage * units.exposed_hit_points(unit, comp.get(unit)) / weight
+    };
+
+    // Unit types whose share is enough to kill all of them are wiped out first, and what is left
+    // of their share is spread over the others.
+    while let Some(index) = exposed.iter().position(|unit| {
+        share_of(unit, comp, &exposed, remaining_damage) >= total_hit_points(unit, comp)
+    }) {
+        let unit = exposed.remove(index);
+        remaining_damage -= total_hit_points(&unit, comp);
+        lost += comp.remove(&unit, comp.get(&unit));
+    }
+    if exposed.is_empty() || remaining_damage <= 0.0 {
+        return lost;
+    }
+
+    let mut kills: Vec<(UnitType, u32, f32)> = exposed
+        .iter()
+        .map(|unit| {
+            let soldiers =
+                share_of(unit, comp, &exposed, remaining_damage) / units.soldier_hit_points(unit);
+            (unit.clone(), soldiers as u32, soldiers.fract())
+        })
+        .collect();
+    // Damage left over from whole soldiers goes to the unit types closest to losing another one.
+    let mut leftover = remaining_damage
+        - kills
+            .iter()
+            .map(|(unit, killed, _)| *killed as f32 * units.soldier_hit_points(unit))
+            .sum::<f32>();
+    kills.sort_by(|a, b| b.2.total_cmp(&a.2));
+    for (unit, killed, _) in &mut kills {
+        let hit_points = units.soldier_hit_points(unit);
+        if leftover >= hit_points / 2.0 && *killed < comp.get(unit) {
+            *killed += 1;
+            leftover -= hit_points;
+        }
+    }
+    // Even a scratch kills someone.
+    if lost == 0
+        && kills.iter().all(|(_, killed, _)| *killed == 0)
+        && let Some((_, killed, _)) = kills.first_mut()
+    {
+        *killed = 1;
+    }
+    for (unit, killed, _) in kills {
+        lost += comp.remove(&unit, killed);
+    }
+    lost
+}
+
+/// Kills the share of an army's soldiers lost to attrition. Like battle casualties, the losses are
+/// spread over the unit types, in proportion to their soldiers, so armies keep their composition
+/// while they waste away. Rounded down, and at least one soldier is always left, so attrition
+/// alone never wipes out an army, whatever share a script asks for. Armies losing nobody aren't
+/// marked as changed. Returns the soldiers killed.
+pub fn apply_attrition(mut comp: impl DerefMut<Target = ArmyComposition>, share: f32) -> u32 {
+    let total = comp.total_size();
+    let lost = ((total as f32 * share.clamp(0.0, 1.0)) as u32).min(total.saturating_sub(1));
+    if lost == 0 {
+        return 0;
+    }
+
+    let mut kills: Vec<(UnitType, u32, f32)> = comp
+        .iter()
+        .map(|(unit, soldiers)| {
+            let killed = soldiers as f32 * lost as f32 / total as f32;
+            (unit.clone(), killed as u32, killed.fract())
+        })
+        .collect();
+    // Soldiers left over from rounding are taken from the unit types closest to losing another one.
+    let mut leftover = lost - kills.iter().map(|(_, killed, _)| killed).sum::<u32>();
+    kills.sort_by(|a, b| b.2.total_cmp(&a.2));
+    for (unit, killed, _) in &mut kills {
+        if leftover > 0 && *killed < comp.get(unit) {
+            *killed += 1;
+            leftover -= 1;
+        }
+    }
+    kills
+        .into_iter()
+        .map(|(unit, killed, _)| comp.remove(&unit, killed))
+        .sum()
+}
+
+fn end_battle_multi(
+    commands: &mut Commands,
+    armies: &mut Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
+    battle_entity: Entity,
+    battle: &Battle,
+    winner_side: BattleSide,
+) {
+    let battle_location = battle.location;
+    let (winners, losers) = match winner_side {
+        BattleSide::Attacker => (&battle.attackers, &battle.defenders),
+        BattleSide::Defender => (&battle.defenders, &battle.attackers),
+    };
+
+    // Despawn the losers
+    for &army_entity in losers {
+        info!("Removed defeated army {:?}", army_entity);
+        commands.entity(army_entity).remove::<InBattle>();
+        commands.entity(army_entity).despawn();
+    }
+
+    // Remove InBattle from all surviving armies and move them to the battle location
+    for &army_entity in winners {
+        commands.entity(army_entity).remove::<InBattle>();
+
+        if let Ok((_, _, mut pos, _)) = armies.get_mut(army_entity) {
+            *pos = HexPos(battle_location);
+        }
+    }
+
+    // Winners still have to besiege the province to occupy it.
+    commands.entity(battle_entity).despawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player_command::PlayerCommand;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn larger_army_wins_battle_and_takes_the_hex() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("West", Hex::new(0, 0), Some(attacker));
+        game.spawn_province("East", Hex::new(1, 0), Some(defender));
+        let strong = game.spawn_army(attacker, Hex::new(0, 0), 50);
+        let weak = game.spawn_army(defender, Hex::new(1, 0), 2);
+        game.declare_war(attacker, defender);
+
+        game.move_army(strong, Hex::new(1, 0));
+        game.end_turn();
+        assert_eq!(game.count::<Battle>(), 1);
+
+        game.end_turns(5);
+        assert_eq!(game.count::<Battle>(), 0);
+        assert!(game.world().get_entity(weak).is_err());
+        assert_eq!(game.get::<HexPos>(strong), Some(&HexPos(Hex::new(1, 0))));
+        assert!(game.get::<InBattle>(strong).is_none());
+    }
+
+    #[test]
+    fn battles_fight_one_round_per_turn() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("West", Hex::new(0, 0), Some(attacker));
+        game.spawn_province("East", Hex::new(1, 0), Some(defender));
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 1000);
+        game.spawn_army(defender, Hex::new(1, 0), 1000);
+        game.declare_war(attacker, defender);
+
+        game.move_army(army, Hex::new(1, 0));
+        game.end_turn();
+        let battle = game.get::<InBattle>(army).unwrap().battle_entity;
+        assert_eq!(game.get::<Battle>(battle).unwrap().round, 1);
+
+        // Frames between turns don't advance the battle.
+        for _ in 0..10 {
+            game.app.update();
+        }
+        assert_eq!(game.get::<Battle>(battle).unwrap().round, 1);
+
+        game.end_turn();
+        assert_eq!(game.get::<Battle>(battle).unwrap().round, 2);
+    }
+
+    #[test]
+    fn casualties_follow_the_damage_to_hit_points_ratio() {
+        let units = UnitRegistry::load(&crate::mods::VirtualFs::new(&[]));
+        let infantry = UnitType::new("infantry");
+        let regiment = || ArmyComposition::default().with(infantry.clone(), REGIMENT_SIZE);
+
+        // A regiment of infantry has 20 hit points per soldier.
+        let mut army = regiment();
+        assert_eq!(apply_damage_to_composition(&mut army, 500.0, &units), 25);
+        assert_eq!(army.total_size(), REGIMENT_SIZE - 25);
+
+        // A scratch still kills someone, overwhelming damage kills everyone and no more.
+        let mut army = regiment();
+        assert_eq!(apply_damage_to_composition(&mut army, 0.1, &units), 1);
+        let mut army = regiment();
+        assert_eq!(
+            apply_damage_to_composition(&mut army, 1.0e9, &units),
+            REGIMENT_SIZE
+        );
+        assert_eq!(army.total_size(), 0);
+
+        // Huge stacks lose the same share of their soldiers as small ones.
+        let mut huge = ArmyComposition::default().with(infantry.clone(), 1000 * REGIMENT_SIZE);
+        assert_eq!(
+            apply_damage_to_composition(&mut huge, 500_000.0, &units),
+            25 * REGIMENT_SIZE
+        );
+    }
+
+    #[test]
+    fn battle_losses_keep_the_army_composition() {
+        let units = UnitRegistry::load(&crate::mods::VirtualFs::new(&[]));
+        let infantry = UnitType::new("infantry");
+        let cavalry = UnitType::new("cavalry");
+        let artillery = UnitType::new("artillery");
+        let mut army = ArmyComposition::default()
+            .with(infantry.clone(), 6000)
+            .with(cavalry.clone(), 2000)
+            .with(artillery.clone(), 2000);
+
+        // A tenth of the army's exposed hit points: infantry and cavalry lose a tenth of their
+        // soldiers, the artillery behind them a little less.
+        let lost = apply_damage_to_composition(&mut army, 19_250.0, &units);
+        assert_eq!(lost, 950);
+        assert!((5399..=5401).contains(&army.get(&infantry)));
+        assert!((1799..=1801).contains(&army.get(&cavalry)));
+        assert!((1849..=1851).contains(&army.get(&artillery)));
+
+        // Long battles don't leave pure artillery behind.
+        for _ in 0..20 {
+            let damage = units.hit_points(&army) / 10.0;
+            apply_damage_to_composition(&mut army, damage, &units);
+        }
+        let share = |unit: &UnitType| army.get(unit) as f32 / army.total_size() as f32;
+        assert!(share(&infantry) > 0.45);
+        assert!(share(&artillery) < 0.4);
+    }
+
+    #[test]
+    fn attrition_keeps_the_army_composition() {
+        let infantry = UnitType::new("infantry");
+        let cavalry = UnitType::new("cavalry");
+        let mut army = ArmyComposition::default()
+            .with(infantry.clone(), 2999)
+            .with(cavalry.clone(), 1001);
+
+        assert_eq!(apply_attrition(&mut army, 0.1), 400);
+        assert_eq!(army.get(&infantry), 2699);
+        assert_eq!(army.get(&cavalry), 901);
+
+        // Too little to kill anyone.
+        assert_eq!(apply_attrition(&mut army, 0.0001), 0);
+        assert_eq!(army.total_size(), 3600);
+    }
+
+    #[test]
+    fn attrition_leaves_a_soldier_standing() {
+        let mut army = ArmyComposition::default()
+            .with(UnitType::new("infantry"), 300)
+            .with(UnitType::new("cavalry"), 100);
+
+        assert_eq!(apply_attrition(&mut army, 1.0), 399);
+        assert_eq!(army.total_size(), 1);
+
+        // The last soldier holds out too.
+        assert_eq!(apply_attrition(&mut army, 1.0), 0);
+        assert_eq!(army.total_size(), 1);
+    }
+
+    #[test]
+    fn huge_stacks_crush_single_regiments() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("West", Hex::new(0, 0), Some(attacker));
+        game.spawn_province("East", Hex::new(1, 0), Some(defender));
+        let huge = game.spawn_army(attacker, Hex::new(0, 0), 100 * REGIMENT_SIZE);
+        let regiment = game.spawn_army(defender, Hex::new(1, 0), REGIMENT_SIZE);
+        game.declare_war(attacker, defender);
+
+        game.move_army(huge, Hex::new(1, 0));
+        game.end_turn();
+        assert!(game.world().get_entity(regiment).is_err());
+        // Ten times the supply limit of the province, the stack loses a tenth to attrition.
+        let survivors = game.get::<ArmyComposition>(huge).unwrap().total_size();
+        assert!(survivors > 90 * REGIMENT_SIZE - 50);
+    }
+
+    #[test]
+    fn flow_field_paths_around_impassable_hexes() {
+        let blocked = Hex::new(1, 0);
+        let passable = |hex: &Hex| *hex != blocked && hex.q().abs() <= 3 && hex.r().abs() <= 3;
+        let field = FlowField::build(Hex::new(2, 0), passable, |_, _| true, |_| 0.0);
+
+        let path = field.path_from(Hex::new(0, 0)).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.back(), Some(&Hex::new(2, 0)));
+        assert!(!path.contains(&blocked));
+
+        // Nothing leads into an impassable destination.
+        let field = FlowField::build(blocked, passable, |_, _| true, |_| 0.0);
+        assert!(field.path_from(Hex::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn flow_field_paths_around_steep_climbs() {
+        let hill = Hex::new(1, 0);
+        let passable = |hex: &Hex| hex.q().abs() <= 3 && hex.r().abs() <= 3;
+        let elevation = |hex: &Hex| if *hex == hill { 2000.0 } else { 0.0 };
+        let field = FlowField::build(Hex::new(2, 0), passable, |_, _| true, elevation);
+
+        let path = field.path_from(Hex::new(0, 0)).unwrap();
+        assert_eq!(path.len(), 3);
+        assert!(!path.contains(&hill));
+    }
+
+    #[test]
+    fn armies_share_cached_paths_until_ownership_changes() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let provinces = game.spawn_provinces(4, Some(country));
+        let first = game.spawn_army(country, Hex::new(0, 0), 1);
+        let second = game.spawn_army(country, Hex::new(1, 0), 1);
+
+        game.move_army(first, Hex::new(3, 0));
+        game.move_army(second, Hex::new(3, 0));
+        assert_eq!(game.world().resource::<PathCache>().flow_fields.len(), 1);
+        assert_eq!(game.get::<ActivePath>(second).unwrap().path.len(), 2);
+
+        game.world_mut().entity_mut(provinces[2]).remove::<Owner>();
+        game.app.update();
+        assert!(game.world().resource::<PathCache>().flow_fields.is_empty());
+    }
+
+    #[test]
+    fn hostile_forts_block_armies_until_besieged() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("Border", Hex::new(0, 0), Some(attacker));
+        let provinces: Vec<Entity> = (1..=3)
+            .map(|q| game.spawn_province("Land", Hex::new(q, 0), Some(defender)))
+            .collect();
+        game.world_mut().spawn((
+            crate::buildings::Building {
+                building_type: crate::buildings::BuildingType::Fort,
+            },
+            ChildOf(provinces[1]),
+        ));
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 5 * REGIMENT_SIZE);
+        game.declare_war(attacker, defender);
+
+        game.move_army(army, Hex::new(3, 0));
+        assert!(game.get::<ActivePath>(army).is_none());
+
+        // Once the army lays siege to the fort, it can march on.
+        game.move_army(army, Hex::new(2, 0));
+        game.end_turns(2);
+        assert!(
+            game.get::<crate::war::SiegeProgress>(provinces[1])
+                .is_some()
+        );
+        game.move_army(army, Hex::new(3, 0));
+        assert_eq!(
+            game.get::<ActivePath>(army).unwrap().path,
+            VecDeque::from([Hex::new(3, 0)])
+        );
+    }
+
+    #[test]
+    fn armies_need_military_access_to_cross_foreign_land() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let neighbour = game.spawn_country("Neighbour");
+        game.spawn_province("West", Hex::new(0, 0), Some(country));
+        game.spawn_province("Corridor", Hex::new(1, 0), Some(neighbour));
+        game.spawn_province("East", Hex::new(2, 0), Some(country));
+        let army = game.spawn_army(country, Hex::new(0, 0), 10);
+
+        game.move_army(army, Hex::new(2, 0));
+        assert!(game.get::<ActivePath>(army).is_none());
+
+        // Countries of the same faith let each other's armies through.
+        game.world_mut()
+            .write_message(crate::diplomacy::MilitaryAccessRequestEvent {
+                from: country,
+                to: neighbour,
+            });
+        game.app.update();
+        assert!(
+            game.get::<MilitaryAccess>(country)
+                .is_some_and(|access| access.has_access_to(neighbour))
+        );
+        game.move_army(army, Hex::new(2, 0));
+        assert_eq!(game.get::<ActivePath>(army).unwrap().path.len(), 2);
+    }
+
+    #[test]
+    fn idle_armies_next_to_a_battle_reinforce_it() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_provinces(4, Some(attacker));
+        game.declare_war(attacker, defender);
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 1000);
+        let enemy = game.spawn_army(defender, Hex::new(1, 0), 1000);
+        let reserve = game.spawn_army(attacker, Hex::new(2, 0), 1000);
+        let distant = game.spawn_army(attacker, Hex::new(3, 0), 1000);
+
+        game.move_army(army, Hex::new(1, 0));
+        game.end_turn();
+        let battle = game.get::<InBattle>(enemy).unwrap().battle_entity;
+        assert_eq!(
+            game.get::<Battle>(battle).unwrap().attackers,
+            vec![army, reserve]
+        );
+        assert!(game.get::<InBattle>(distant).is_none());
+
+        // Without reinforcements only the armies moved onto the battle hex join it.
+        let mut game = TestGame::new();
+        game.world_mut()
+            .resource_mut::<GameRules>()
+            .reinforcement_radius = 0;
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_provinces(3, Some(attacker));
+        game.declare_war(attacker, defender);
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 1000);
+        game.spawn_army(defender, Hex::new(1, 0), 1000);
+        let reserve = game.spawn_army(attacker, Hex::new(2, 0), 1000);
+
+        game.move_army(army, Hex::new(1, 0));
+        game.end_turn();
+        assert!(game.get::<InBattle>(reserve).is_none());
+    }
+
+    #[test]
+    fn armies_attack_enemies_standing_in_their_path() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("Border", Hex::new(0, 0), Some(attacker));
+        for q in 1..=3 {
+            game.spawn_province("Land", Hex::new(q, 0), Some(defender));
+        }
+        game.declare_war(attacker, defender);
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 1000);
+        game.spawn_army(defender, Hex::new(2, 0), 1000);
+
+        game.move_army(army, Hex::new(3, 0));
+        game.end_turns(2);
+        let battle = game.get::<InBattle>(army).unwrap().battle_entity;
+        assert_eq!(game.get::<Battle>(battle).unwrap().location, Hex::new(2, 0));
+        assert!(game.get::<ActivePath>(army).is_none());
+    }
+
+    #[test]
+    fn armies_march_around_enemies_when_set_to() {
+        let mut game = TestGame::new();
+        game.world_mut()
+            .resource_mut::<MarchPreferences>()
+            .march_around_enemies = true;
+        let country = game.spawn_country("Country");
+        let enemy = game.spawn_country("Enemy");
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+        for hex in [(0, 0), (1, 0), (2, 0), (3, 0), (1, -1), (2, -1)] {
+            game.spawn_province("Land", Hex::new(hex.0, hex.1), Some(country));
+        }
+        game.declare_war(country, enemy);
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        let blocker = game.spawn_army(enemy, Hex::new(1, 0), 1000);
+
+        game.move_army(army, Hex::new(3, 0));
+        game.end_turn();
+        assert_eq!(game.count::<Battle>(), 0);
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(1, -1))));
+
+        // Enemies holding the destination are still attacked.
+        game.move_army(army, Hex::new(1, 0));
+        game.end_turn();
+        assert!(game.get::<InBattle>(blocker).is_some());
+    }
+
+    #[test]
+    fn armies_not_at_war_do_not_fight() {
+        let mut game = TestGame::new();
+        let first = game.spawn_country("First");
+        let second = game.spawn_country("Second");
+        game.spawn_province("West", Hex::new(0, 0), Some(first));
+        game.spawn_province("East", Hex::new(1, 0), Some(second));
+        let army = game.spawn_army(first, Hex::new(0, 0), 10);
+        game.spawn_army(second, Hex::new(1, 0), 10);
+
+        game.move_army(army, Hex::new(1, 0));
+        game.end_turn();
+
+        assert_eq!(game.count::<Battle>(), 0);
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(0, 0))));
+    }
+
+    #[test]
+    fn friendly_armies_merge() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        // Unowned land, so that the armies aren't reinforced.
+        game.spawn_provinces(2, None);
+        let source = game.spawn_army(country, Hex::new(0, 0), 3);
+        let target = game.spawn_army(country, Hex::new(1, 0), 4);
+
+        game.move_army(source, Hex::new(1, 0));
+        game.end_turn();
+
+        assert!(game.world().get_entity(source).is_err());
+        assert_eq!(
+            game.get::<ArmyComposition>(target)
+                .unwrap()
+                .get(&UnitType::new("infantry")),
+            7
+        );
+    }
+
+    #[test]
+    fn players_are_asked_before_their_armies_merge() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_provinces(3, None);
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+        game.world_mut()
+            .resource_mut::<MarchPreferences>()
+            .confirm_army_merges = true;
+        let source = game.spawn_army(country, Hex::new(0, 0), 3);
+        let target = game.spawn_army(country, Hex::new(2, 0), 4);
+
+        game.move_army(source, Hex::new(2, 0));
+        game.end_turns(2);
+        assert_eq!(game.get::<HexPos>(source), Some(&HexPos(Hex::new(1, 0))));
+        assert_eq!(
+            game.get::<PendingMerge>(source).map(|merge| merge.into),
+            Some(target)
+        );
+
+        game.world_mut()
+            .write_message(crate::player_command::PlayerCommand::MergeArmy {
+                country: "Country".to_string(),
+                army: Hex::new(1, 0),
+                target: Hex::new(2, 0),
+            });
+        game.app.update();
+        game.end_turn();
+        assert!(game.world().get_entity(source).is_err());
+        assert_eq!(
+            game.get::<ArmyComposition>(target)
+                .unwrap()
+                .get(&UnitType::new("infantry")),
+            7
+        );
+    }
+
+    #[test]
+    fn armies_march_as_far_as_their_movement_points_allow() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let provinces = game.spawn_provinces(4, Some(country));
+        game.world_mut()
+            .get_mut::<Province>(provinces[3])
+            .unwrap()
+            .set_terrain(crate::terrain::Terrain::Mountains.def());
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.world_mut()
+            .entity_mut(army)
+            .insert(ArmyComposition::default().with(UnitType::new("cavalry"), 1000));
+        assert_eq!(turns_to_march([1, 1, 3], 2, 0), 3);
+
+        // Cavalry crosses two provinces of plains a turn...
+        game.move_army(army, Hex::new(3, 0));
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(2, 0))));
+
+        // ...and saves up its movement points to climb the mountains.
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(2, 0))));
+        assert_eq!(game.get::<Movement>(army), Some(&Movement { points: 2 }));
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(3, 0))));
+        assert!(game.get::<Movement>(army).is_none());
+    }
+
+    #[test]
+    fn attached_armies_march_next_to_their_target() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_provinces(6, Some(country));
+        let follower = game.spawn_army(country, Hex::new(0, 0), 3);
+        let target = game.spawn_army(country, Hex::new(3, 0), 4);
+
+        game.world_mut().write_message(PlayerCommand::AttachArmy {
+            country: "Country".to_string(),
+            army: Hex::new(0, 0),
+            target: Some(Hex::new(3, 0)),
+        });
+        game.app.update();
+        game.end_turns(3);
+        assert_eq!(game.get::<HexPos>(follower), Some(&HexPos(Hex::new(2, 0))));
+
+        // The follower keeps up with the target as it marches on, without merging into it.
+        game.move_army(target, Hex::new(5, 0));
+        game.end_turns(3);
+        assert_eq!(game.get::<HexPos>(follower), Some(&HexPos(Hex::new(4, 0))));
+        assert_eq!(game.count::<Army>(), 2);
+    }
+}
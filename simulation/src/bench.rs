@@ -0,0 +1,82 @@
+//! Workloads of the benchmarks in `benches/`, which only see the public items of the library.
+
+use crate::army::FlowField;
+use crate::consts::HEX_SIZE;
+use crate::hex::Hex;
+use bevy::math::Vec2;
+
+/// Map the benchmarks run on, every hex within `radius` steps of the origin with scattered
+/// impassable ones in between.
+pub struct BenchMap {
+    radius: i32,
+    hexes: Vec<Hex>,
+    /// A point inside each hex of the map, off its center, in world coordinates.
+    positions: Vec<Vec2>,
+}
+
+impl BenchMap {
+    pub fn new(radius: i32) -> Self {
+        let hexes: Vec<Hex> = (-radius..=radius)
+            .flat_map(|q| {
+                ((-radius).max(-q - radius)..=radius.min(-q + radius)).map(move |r| Hex::new(q, r))
+            })
+            .collect();
+        let positions = hexes
+            .iter()
+            .map(|hex| hex.axial_to_world(HEX_SIZE) + Vec2::new(HEX_SIZE, -HEX_SIZE) * 0.4)
+            .collect();
+        Self {
+            radius,
+            hexes,
+            positions,
+        }
+    }
+
+    fn is_passable(&self, hex: &Hex) -> bool {
+        hex.distance(&Hex::new(0, 0)) <= self.radius && (hex.q() * 7 + hex.r() * 13) % 11 != 0
+    }
+
+    /// Finds the hex under a point of every hex of the map, returning how many were found.
+    pub fn world_to_axial(&self) -> usize {
+        self.positions
+            .iter()
+            .zip(&self.hexes)
+            .filter(|&(&position, hex)| Hex::world_to_axial(position, HEX_SIZE) == *hex)
+            .count()
+    }
+
+    /// Steps from the origin to every hex of the map, summed.
+    pub fn distances(&self) -> i32 {
+        let origin = Hex::new(0, 0);
+        self.hexes.iter().map(|hex| origin.distance(hex)).sum()
+    }
+
+    /// Neighbors of every hex of the map, counted.
+    pub fn neighbors(&self) -> usize {
+        self.hexes.iter().map(|hex| hex.neighbors().len()).sum()
+    }
+
+    /// Flow field leading to the hex at `q`, `r` over level ground.
+    pub fn flow_field(&self, q: i32, r: i32) -> BenchFlowField {
+        BenchFlowField(FlowField::build(
+            Hex::new(q, r),
+            |hex| self.is_passable(hex),
+            |_, _| true,
+            |_| 0.0,
+        ))
+    }
+}
+
+pub struct BenchFlowField(FlowField);
+
+impl BenchFlowField {
+    /// Follows the field from every sixth hex of the map in both directions, returning how many
+    /// of them found a path.
+    pub fn follow(&self, map: &BenchMap) -> usize {
+        map.hexes
+            .iter()
+            .filter(|hex| hex.q() % 6 == 0 && hex.r() % 6 == 0 && map.is_passable(hex))
+            .filter_map(|&hex| self.0.path_from(hex))
+            .count()
+    }
+}
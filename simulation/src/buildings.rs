@@ -0,0 +1,190 @@
+﻿use crate::map::{Owner, Province};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct BuildingsPlugin;
+
+impl Plugin for BuildingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, sync_building_owners);
+    }
+}
+
+/// Different types of buildings that can be constructed in provinces
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BuildingType {
+    Market,
+    Workshop,
+    Temple,
+    Fort,
+    Barracks,
+    /// Extends the sea range the country colonizes and explores from its ports.
+    University,
+    /// Extends the supply range of the country controlling the province.
+    Depot,
+}
+
+impl BuildingType {
+    pub fn name(&self) -> &str {
+        match self {
+            BuildingType::Market => "Market",
+            BuildingType::Workshop => "Workshop",
+            BuildingType::Temple => "Temple",
+            BuildingType::Fort => "Fort",
+            BuildingType::Barracks => "Barracks",
+            BuildingType::University => "University",
+            BuildingType::Depot => "Supply Depot",
+        }
+    }
+
+    pub fn cost(&self) -> f32 {
+        match self {
+            BuildingType::Market => 100.0,
+            BuildingType::Workshop => 150.0,
+            BuildingType::Temple => 200.0,
+            BuildingType::Fort => 300.0,
+            BuildingType::Barracks => 250.0,
+            BuildingType::University => 400.0,
+            BuildingType::Depot => 120.0,
+        }
+    }
+
+    pub fn income_bonus(&self) -> f32 {
+        match self {
+            BuildingType::Market => 5.0,
+            BuildingType::Workshop => 8.0,
+            BuildingType::Temple => 3.0,
+            BuildingType::Fort => 0.0,
+            BuildingType::Barracks => 0.0,
+            BuildingType::University => 0.0,
+            BuildingType::Depot => 0.0,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            BuildingType::Market => "Increases income by 5",
+            BuildingType::Workshop => "Increases income by 8",
+            BuildingType::Temple => "Increases income by 3",
+            BuildingType::Fort => "Keeps enemy armies from marching past it",
+            BuildingType::Barracks => "Allows recruiting cavalry and artillery",
+            BuildingType::University => "Extends the colonial range of the country by 1",
+            BuildingType::Depot => "Supplies armies further from home",
+        }
+    }
+
+    pub fn all_types() -> [BuildingType; 7] {
+        [
+            BuildingType::Market,
+            BuildingType::Workshop,
+            BuildingType::Temple,
+            BuildingType::Fort,
+            BuildingType::Barracks,
+            BuildingType::University,
+            BuildingType::Depot,
+        ]
+    }
+}
+
+/// Component marking a building in a province
+#[derive(Component)]
+pub struct Building {
+    pub building_type: BuildingType,
+}
+
+/// Puts up a building of the type in the province.
+pub fn spawn_building(
+    commands: &mut Commands,
+    province: Entity,
+    building_type: BuildingType,
+    owner: Entity,
+) {
+    commands.entity(province).with_children(|parent| {
+        parent.spawn((
+            Building { building_type },
+            Income::new(building_type.income_bonus()),
+            Owner(owner),
+        ));
+    });
+}
+
+/// Whether a building of the type stands in the province with these children.
+pub fn has_building(
+    children: Option<&Children>,
+    buildings: &Query<&Building>,
+    building_type: BuildingType,
+) -> bool {
+    children.is_some_and(|children| {
+        children.iter().any(|child| {
+            buildings
+                .get(child)
+                .is_ok_and(|building| building.building_type == building_type)
+        })
+    })
+}
+
+/// Hands the buildings of provinces that changed hands, e.g. in a peace deal, over to their new
+/// owner.
+pub fn sync_building_owners(
+    provinces: Query<(&Owner, &Children), (With<Province>, Changed<Owner>)>,
+    mut buildings: Query<&mut Owner, (With<Building>, Without<Province>)>,
+) {
+    for (owner, children) in &provinces {
+        for child in children.iter() {
+            if let Ok(mut building_owner) = buildings.get_mut(child)
+                && building_owner.0 != owner.0
+            {
+                building_owner.0 = owner.0;
+            }
+        }
+    }
+}
+
+/// Component representing income from a single source. Can be added to provinces, building, ....
+#[derive(Component)]
+pub struct Income(f32);
+
+impl Income {
+    pub fn new(base_income: f32) -> Self {
+        Self(base_income)
+    }
+
+    pub fn get(&self) -> f32 {
+        self.0
+    }
+
+    pub fn set(&mut self, income: f32) {
+        self.0 = income;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn buildings_change_hands_with_their_province() {
+        let mut game = TestGame::new();
+        let loser = game.spawn_country("Loser");
+        let winner = game.spawn_country("Winner");
+        let province = game.spawn_province("Province", Hex::new(0, 0), Some(loser));
+        let market = game
+            .world_mut()
+            .spawn((
+                Building {
+                    building_type: BuildingType::Market,
+                },
+                Income::new(BuildingType::Market.income_bonus()),
+                Owner(loser),
+                ChildOf(province),
+            ))
+            .id();
+
+        game.world_mut().entity_mut(province).insert(Owner(winner));
+        game.app.update();
+
+        assert_eq!(game.get::<Owner>(market).unwrap().0, winner);
+    }
+}
@@ -0,0 +1,264 @@
+﻿use crate::army::{Army, HexPos};
+use crate::buildings::{Building, BuildingType};
+use crate::diplomacy::Alliances;
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::player::Player;
+use crate::trade::{Fleet, SeaChart};
+use crate::turns::Turn;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+pub struct ColonizationPlugin;
+
+impl Plugin for ColonizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Explored::default()).add_systems(
+            Update,
+            explore_map.run_if(
+                resource_changed::<Player>
+                    .or(resource_changed::<Turn>)
+                    .or(alliances_changed),
+            ),
+        );
+    }
+}
+
+/// Sea hexes a country's colonists and explorers reach from its ports.
+const BASE_COLONIAL_RANGE: u32 = 3;
+/// Colonial range each University of the country adds.
+const UNIVERSITY_COLONIAL_RANGE: u32 = 1;
+pub const COLONIZE_COST: f32 = 50.0;
+
+/// Hexes the player's country and the allies sharing their vision with it have explored so far.
+/// Everything else is terra incognita on the map. Exploration is only ever lost when the player
+/// picks another country.
+#[derive(Resource, Default)]
+pub struct Explored {
+    country: Option<Entity>,
+    hexes: HashSet<Hex>,
+}
+
+impl Explored {
+    /// Whether the player has explored the hex. Without a country the whole map is shown.
+    pub fn contains(&self, hex: &Hex) -> bool {
+        self.country.is_none() || self.hexes.contains(hex)
+    }
+}
+
+/// Looks up how far over sea countries reach from their ports, see [`ColonialRange::reach`].
+#[derive(SystemParam)]
+pub struct ColonialRange<'w, 's> {
+    sea_chart: Res<'w, SeaChart>,
+    provinces: Query<
+        'w,
+        's,
+        (
+            &'static Province,
+            Option<&'static Owner>,
+            Option<&'static Children>,
+        ),
+    >,
+    buildings: Query<'w, 's, &'static Building>,
+}
+
+impl ColonialRange<'_, '_> {
+    fn owned_by(&self, country: Entity) -> impl Iterator<Item = (&Province, Option<&Children>)> {
+        self.provinces
+            .iter()
+            .filter(move |(_, owner, _)| owner.is_some_and(|owner| owner.0 == country))
+            .map(|(province, _, children)| (province, children))
+    }
+
+    /// Sea hexes the country's ships reach from its ports, growing with its Universities.
+    pub fn range(&self, country: Entity) -> u32 {
+        let universities = self
+            .owned_by(country)
+            .filter_map(|(_, children)| children)
+            .flat_map(|children| children.iter())
+            .filter(|&child| {
+                self.buildings
+                    .get(child)
+                    .is_ok_and(|b| b.building_type == BuildingType::University)
+            })
+            .count() as u32;
+        BASE_COLONIAL_RANGE + universities * UNIVERSITY_COLONIAL_RANGE
+    }
+
+    /// Hexes within range of the country's ports: the sea hexes counted in steps over sea, and
+    /// the coasts along them.
+    pub fn reach(&self, country: Entity) -> HashSet<Hex> {
+        let range = self.range(country);
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+        for (province, _) in self.owned_by(country) {
+            let hex = *province.get_hex();
+            if !self.sea_chart.is_port(&hex) {
+                continue;
+            }
+            for neighbor in hex.neighbors() {
+                if self.sea_chart.is_sea(&neighbor) && reached.insert(neighbor) {
+                    queue.push_back((neighbor, 1));
+                }
+            }
+        }
+
+        let mut coasts = HashSet::new();
+        while let Some((hex, steps)) = queue.pop_front() {
+            for neighbor in hex.neighbors() {
+                if !self.sea_chart.is_sea(&neighbor) {
+                    coasts.insert(neighbor);
+                } else if steps < range && reached.insert(neighbor) {
+                    queue.push_back((neighbor, steps + 1));
+                }
+            }
+        }
+        reached.extend(coasts);
+        reached
+    }
+
+    /// Whether the country can settle the province: an unowned province it can own, next to its
+    /// own provinces or within reach of its ports.
+    pub fn can_colonize(&self, country: Entity, province: Entity) -> bool {
+        let Ok((province, owner, _)) = self.provinces.get(province) else {
+            return false;
+        };
+        if owner.is_some() || !province.is_ownable() {
+            return false;
+        }
+        let hex = province.get_hex();
+        self.owned_by(country)
+            .any(|(owned, _)| owned.get_hex().distance(hex) == 1)
+            || self.reach(country).contains(hex)
+    }
+}
+
+fn alliances_changed(alliances: Query<(), Changed<Alliances>>) -> bool {
+    !alliances.is_empty()
+}
+
+/// Extends the player's explored hexes with what their country reaches this turn, and the hexes
+/// around its provinces, armies and fleets. Allies sharing their vision explore for the player
+/// too.
+pub fn explore_map(
+    player: Res<Player>,
+    mut explored: ResMut<Explored>,
+    colonial_range: ColonialRange,
+    armies: Query<(&HexPos, &Owner), With<Army>>,
+    fleets: Query<(&Fleet, &Owner)>,
+    alliances: Query<&Alliances>,
+) {
+    if explored.country != player.country {
+        *explored = Explored {
+            country: player.country,
+            hexes: HashSet::new(),
+        };
+    }
+    let Some(country) = player.country else {
+        return;
+    };
+    let mut explorers = vec![country];
+    if let Ok(alliances) = alliances.get(country) {
+        explorers.extend(alliances.vision_sharers());
+    }
+
+    let explored = &mut explored.hexes;
+    for explorer in explorers {
+        let mut sighted: Vec<Hex> = colonial_range
+            .owned_by(explorer)
+            .map(|(province, _)| *province.get_hex())
+            .collect();
+        sighted.extend(
+            armies
+                .iter()
+                .filter(|(_, owner)| owner.0 == explorer)
+                .map(|(pos, _)| pos.0),
+        );
+        sighted.extend(
+            fleets
+                .iter()
+                .filter(|(_, owner)| owner.0 == explorer)
+                .map(|(fleet, _)| fleet.hex),
+        );
+
+        explored.extend(colonial_range.reach(explorer));
+        for hex in sighted {
+            explored.insert(hex);
+            explored.extend(hex.neighbors());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diplomacy::{AllianceRequestEvent, ShareVisionEvent};
+    use crate::test_utils::TestGame;
+    use bevy::ecs::system::SystemState;
+
+    #[test]
+    fn colonial_range_grows_with_universities() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let port = game.spawn_province("Port", Hex::new(0, 0), Some(country));
+        let near = game.spawn_province("Near", Hex::new(3, -1), None);
+        let far = game.spawn_province("Far", Hex::new(6, -1), None);
+        let sea = (1..=6).map(|q| Hex::new(q, 0)).collect();
+        game.world_mut().insert_resource(SeaChart::new(
+            sea,
+            [Hex::new(0, 0), Hex::new(3, -1), Hex::new(6, -1)],
+        ));
+
+        let mut state = SystemState::<ColonialRange>::new(game.world_mut());
+        let colonial_range = state.get(game.world());
+        assert!(colonial_range.can_colonize(country, near));
+        assert!(!colonial_range.can_colonize(country, far));
+
+        for _ in 0..2 {
+            game.world_mut().spawn((
+                Building {
+                    building_type: BuildingType::University,
+                },
+                ChildOf(port),
+            ));
+        }
+        let colonial_range = state.get(game.world());
+        assert_eq!(colonial_range.range(country), BASE_COLONIAL_RANGE + 2);
+        assert!(colonial_range.can_colonize(country, far));
+    }
+
+    #[test]
+    fn allies_sharing_vision_explore_together() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let ally = game.spawn_country("Ally");
+        game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        game.spawn_province("Abroad", Hex::new(10, 0), Some(ally));
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+        let explored =
+            |game: &TestGame, hex: Hex| game.world().resource::<Explored>().contains(&hex);
+        game.app.update();
+        assert!(!explored(&game, Hex::new(10, 0)));
+
+        game.world_mut().write_message(AllianceRequestEvent {
+            from: country,
+            to: ally,
+        });
+        // The alliance is formed at the end of the frame and explored from the frame after.
+        game.app.update();
+        game.app.update();
+        assert!(explored(&game, Hex::new(10, 0)));
+        assert!(explored(&game, Hex::new(11, 0)));
+
+        // Once the allies stop sharing, their new land stays unexplored.
+        game.world_mut().write_message(ShareVisionEvent {
+            from: country,
+            to: ally,
+            shared: false,
+        });
+        game.spawn_province("Colony", Hex::new(20, 0), Some(ally));
+        game.app.update();
+        assert!(!explored(&game, Hex::new(20, 0)));
+    }
+}
@@ -1,2 +1,2 @@
 ﻿/// Size of hexagonal tiles used in the map grid.
-pub(crate) const HEX_SIZE: f32 = 50f32;
+pub const HEX_SIZE: f32 = 50f32;
@@ -0,0 +1,344 @@
+﻿use crate::hot_reload::DataFileChangedEvent;
+use crate::manpower::Manpower;
+use crate::map::{CountryDef, MapData, Owner, Province, load_map_from_file};
+use crate::modifiers::CountryModifiers;
+use crate::mods::VirtualFs;
+use crate::religion::Faith;
+use crate::rules::GameRules;
+use crate::war::WarExhaustion;
+use crate::world::GenerateWorld;
+use bevy::color::color_difference::EuclideanDistance;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+pub struct CountryPlugin;
+
+impl Plugin for CountryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GenerateWorld,
+            setup_countries_from_map
+                .after(crate::map::MapGeneration)
+                .run_if(resource_exists::<MapData>),
+        )
+        .add_systems(
+            GenerateWorld,
+            assign_province_ownership
+                .after(crate::map::MapGeneration)
+                .after(setup_countries_from_map)
+                .run_if(resource_exists::<MapData>),
+        )
+        .add_systems(Update, reload_countries.run_if(resource_exists::<MapData>));
+    }
+}
+
+/// Marker component for country entities. No data as I am trying to do ECS :P.
+#[derive(Component)]
+pub struct Country {}
+
+/// Components representing the name and map color of a faction. They are not attached to the
+/// country entity, as things like rebels may have names/colors but not be countries.
+#[derive(Component)]
+pub struct DisplayName(pub String);
+#[derive(Component)]
+pub struct MapColor(pub Color);
+
+/// Map colors closer than this in the Oklab color space are hard to tell apart on the map.
+const MIN_COLOR_DISTANCE: f32 = 0.06;
+/// Shifts tried on a color too close to another before it's left as it is.
+const COLOR_SHIFT_ATTEMPTS: u32 = 24;
+
+/// Whether two countries in these map colors are hard to tell apart on the map.
+pub fn colors_clash(a: Color, b: Color) -> bool {
+    Oklaba::from(a).distance(&Oklaba::from(b)) < MIN_COLOR_DISTANCE
+}
+
+/// Shifts every color clashing with one listed before it until it stands out from all of them,
+/// and returns the indices of the shifted colors.
+fn separate_colors(colors: &mut [Color]) -> Vec<usize> {
+    let mut shifted = Vec::new();
+    for index in 1..colors.len() {
+        let (earlier, rest) = colors.split_at_mut(index);
+        let clashes = |color: Color| earlier.iter().any(|other| colors_clash(color, *other));
+        if !clashes(rest[0]) {
+            continue;
+        }
+        if let Some(color) = (1..=COLOR_SHIFT_ATTEMPTS)
+            .map(|step| shift_color(rest[0], step))
+            .find(|color| !clashes(*color))
+        {
+            rest[0] = color;
+            shifted.push(index);
+        }
+    }
+    shifted
+}
+
+/// The color shifted by `step` steps: its hue turns by the golden angle every step, so that the
+/// steps spread around the color wheel, and it alternates between lighter and darker.
+fn shift_color(color: Color, step: u32) -> Color {
+    let oklch = Oklcha::from(color);
+    let lightness = oklch.lightness + [0.0, 0.1, -0.1][step as usize % 3];
+    let shifted = Oklcha::new(
+        lightness.clamp(0.35, 0.9),
+        // Grays get some chroma, or turning their hue wouldn't change them.
+        oklch.chroma.max(0.08),
+        (oklch.hue + 137.5 * step as f32).rem_euclid(360.0),
+        1.0,
+    );
+    let [r, g, b] = Color::from(shifted).to_srgba().to_f32_array_no_alpha();
+    Color::srgb(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0))
+}
+
+/// Map colors of the countries of a map file. A country in nearly the color of one listed before
+/// it gets a shifted color, so that the political map stays readable when mods add countries.
+fn country_colors(defs: &[CountryDef]) -> Vec<Color> {
+    let mut colors: Vec<Color> = defs
+        .iter()
+        .map(|def| Color::srgb(def.color[0], def.color[1], def.color[2]))
+        .collect();
+    for index in separate_colors(&mut colors) {
+        warn!(
+            "{} has nearly the map color of another country, shifting it",
+            defs[index].name
+        );
+    }
+    colors
+}
+
+/// Map color of a country created during the game, shifted like those of [`country_colors`]
+/// when it's nearly one of the colors already taken.
+pub fn color_apart(def: &CountryDef, taken: impl IntoIterator<Item = Color>) -> Color {
+    let color = Color::srgb(def.color[0], def.color[1], def.color[2]);
+    let mut colors: Vec<Color> = taken.into_iter().collect();
+    colors.push(color);
+    separate_colors(&mut colors);
+    colors.pop().unwrap_or(color)
+}
+
+/// Path of a country's flag image in the game data, mods can replace it.
+#[derive(Component)]
+pub struct FlagFile(pub String);
+
+/// Component representing the amount of gold a country has.
+#[derive(Component)]
+pub struct Coffer(pub f32);
+
+impl Coffer {
+    pub fn add_ducats(&mut self, ducats: f32) {
+        self.0 += ducats;
+    }
+
+    /// Takes the ducats out of the coffer if it holds enough of them, spending never drives it
+    /// below zero.
+    pub fn spend(&mut self, ducats: f32) -> Result<(), InsufficientFunds> {
+        if self.0 < ducats {
+            return Err(InsufficientFunds {
+                needed: ducats,
+                available: self.0,
+            });
+        }
+        self.0 -= ducats;
+        Ok(())
+    }
+
+    pub fn get_ducats(&self) -> f32 {
+        self.0
+    }
+}
+
+/// Error of spending more ducats than a coffer holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InsufficientFunds {
+    pub needed: f32,
+    pub available: f32,
+}
+
+impl std::fmt::Display for InsufficientFunds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} ducats needed, {:.0} available",
+            self.needed, self.available
+        )
+    }
+}
+
+#[derive(Bundle)]
+pub struct CountryBundle {
+    country: Country,
+    name: DisplayName,
+    color: MapColor,
+    coffer: Coffer,
+    faith: Faith,
+    modifiers: CountryModifiers,
+    war_exhaustion: WarExhaustion,
+    manpower: Manpower,
+}
+
+impl CountryBundle {
+    pub fn new(name: &str, color: Color) -> Self {
+        CountryBundle {
+            country: Country {},
+            name: DisplayName(name.to_string()),
+            color: MapColor(color),
+            coffer: Coffer(0.0),
+            faith: Faith::default(),
+            modifiers: CountryModifiers::default(),
+            war_exhaustion: WarExhaustion::default(),
+            manpower: Manpower::default(),
+        }
+    }
+}
+
+/// Setup countries from map data - creates country entities based on what's in the map file
+pub fn setup_countries_from_map(mut commands: Commands, map_data: Res<MapData>) {
+    info!(
+        "Setting up {} countries from map data",
+        map_data.countries.len()
+    );
+
+    let colors = country_colors(&map_data.countries);
+    for (country_def, color) in map_data.countries.iter().zip(colors) {
+        let entity = commands
+            .spawn(CountryBundle::new(&country_def.name, color))
+            .insert((FlagFile(country_def.flag.clone()), country_def.faith))
+            .id();
+
+        info!(
+            "Created country: {} ({:?}) with flag: {}",
+            country_def.name, entity, country_def.flag
+        );
+    }
+}
+
+/// Re-applies country names, colors and flags when the map file changes. Countries are matched
+/// to their definitions by position in the file, so a renamed country keeps its provinces, armies
+/// and coffer. Added or removed countries need a new game.
+pub fn reload_countries(
+    mut events: MessageReader<DataFileChangedEvent>,
+    rules: Res<GameRules>,
+    vfs: Res<VirtualFs>,
+    mut map_data: ResMut<MapData>,
+    mut countries: Query<(&mut DisplayName, &mut MapColor, &mut FlagFile), With<Country>>,
+) {
+    if !events.read().any(|event| event.0 == rules.map_path) {
+        return;
+    }
+    let Some(map_file) = load_map_from_file(&vfs, &rules.map_path) else {
+        return;
+    };
+    if map_file.countries.len() != map_data.countries.len() {
+        warn!("Countries were added or removed, start a new game to apply it");
+    }
+
+    let new_colors = country_colors(&map_file.countries);
+    for ((old_def, new_def), new_color) in map_data
+        .countries
+        .iter_mut()
+        .zip(map_file.countries)
+        .zip(new_colors)
+    {
+        let Some((mut name, mut color, mut flag)) = countries
+            .iter_mut()
+            .find(|(name, _, _)| name.0 == old_def.name)
+        else {
+            continue;
+        };
+
+        if name.0 != new_def.name {
+            info!("Renaming {} to {}", name.0, new_def.name);
+            name.0 = new_def.name.clone();
+        }
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+        if old_def.flag != new_def.flag {
+            flag.0 = new_def.flag.clone();
+        }
+        *old_def = new_def;
+    }
+    info!("Reloaded countries from {}", rules.map_path);
+}
+
+/// System to assign province ownership to countries based on map data.
+/// This runs after both countries and provinces have been spawned.
+pub fn assign_province_ownership(
+    mut commands: Commands,
+    provinces: Query<(Entity, &Province)>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    map_data: Res<MapData>,
+) {
+    // Create a lookup from country name to entity
+    let country_lookup: HashMap<&str, Entity> = countries
+        .iter()
+        .map(|(entity, name)| (name.0.as_str(), entity))
+        .collect();
+
+    if country_lookup.is_empty() {
+        warn!("No countries found for province assignment!");
+        return;
+    }
+
+    // Assign provinces based on map data
+    for (province_entity, province) in provinces.iter() {
+        let hex = province.get_hex();
+
+        // Look up the owner from map data, unknown owners are reported when the map is loaded
+        if let Some(owner_name) = map_data.province_owners.get(hex)
+            && let Some(&owner_entity) = country_lookup.get(owner_name.as_str())
+        {
+            commands.entity(province_entity).insert(Owner(owner_entity));
+        }
+        // If no owner in map data, province stays unowned
+    }
+
+    info!("Province ownership assigned from map data");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countries_in_nearly_the_same_color_are_told_apart() {
+        let red = Color::srgb(0.8, 0.1, 0.1);
+        let blue = Color::srgb(0.1, 0.2, 0.8);
+        let mut colors = [red, red, Color::srgb(0.81, 0.1, 0.1), blue];
+
+        assert_eq!(separate_colors(&mut colors), vec![1, 2]);
+        assert_eq!(colors[0], red);
+        assert_eq!(colors[3], blue);
+        for (i, a) in colors.iter().enumerate() {
+            for b in &colors[i + 1..] {
+                assert!(!colors_clash(*a, *b));
+            }
+        }
+    }
+
+    #[test]
+    fn map_countries_and_released_nations_get_colors_apart() {
+        let def = |name: &str, color: [f32; 3]| CountryDef {
+            name: name.to_string(),
+            color,
+            flag: String::new(),
+            faith: Faith::default(),
+        };
+        let defs = [
+            def("Italy", [0.0, 0.6, 0.3]),
+            def("Tuscany", [0.0, 0.6, 0.3]),
+            def("France", [0.2, 0.3, 0.8]),
+        ];
+
+        let colors = country_colors(&defs);
+        assert_eq!(colors[0], Color::srgb(0.0, 0.6, 0.3));
+        assert!(!colors_clash(colors[0], colors[1]));
+        assert_eq!(colors[2], Color::srgb(0.2, 0.3, 0.8));
+
+        let released = color_apart(&def("Savoy", [0.01, 0.6, 0.3]), colors.clone());
+        assert!(colors.iter().all(|&taken| !colors_clash(released, taken)));
+        // A nation in a color of its own keeps it.
+        let spain = color_apart(&def("Spain", [0.9, 0.6, 0.1]), colors);
+        assert_eq!(spain, Color::srgb(0.9, 0.6, 0.1));
+    }
+}
@@ -0,0 +1,774 @@
+﻿use crate::buildings::Income;
+use crate::colonization::COLONIZE_COST;
+use crate::country::{Coffer, DisplayName};
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::notifications::{Notification, Notifications};
+use crate::player::Player;
+use crate::religion::Faith;
+use crate::war::{DeclareWarEvent, Occupied, WarRelations, cede_province};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+pub struct DiplomacyPlugin;
+
+impl Plugin for DiplomacyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ProvinceOfferEvent>()
+            .add_message::<AcceptProvinceOfferEvent>()
+            .add_message::<MilitaryAccessRequestEvent>()
+            .add_message::<AllianceRequestEvent>()
+            .add_message::<BreakAllianceEvent>()
+            .add_message::<AnswerCallToArmsEvent>()
+            .add_message::<ShareVisionEvent>()
+            .add_systems(
+                Update,
+                (
+                    handle_province_offers,
+                    ai_handle_province_offers,
+                    handle_accept_province_offer,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, handle_military_access_requests)
+            .add_systems(
+                Update,
+                (
+                    handle_alliance_requests,
+                    ai_answer_calls_to_arms,
+                    handle_call_to_arms_answers,
+                    handle_broken_alliances,
+                    handle_shared_vision,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Turns of a province's income the AI is willing to pay for it, on top of what colonizing it
+/// would cost.
+const PROVINCE_PAYBACK_TURNS: f32 = 50.0;
+
+// ============================================================================
+// DATA STRUCTURES
+// ============================================================================
+
+/// Offer to hand a province over to another country at peace, for `price` ducats or as a gift
+/// when the price is zero.
+#[derive(Component)]
+pub struct ProvinceOffer {
+    pub from: Entity,
+    pub to: Entity,
+    pub province: Entity,
+    pub price: f32,
+}
+
+/// Countries that granted this country military access. Its armies may march through their land.
+#[derive(Component, Default)]
+pub struct MilitaryAccess {
+    granted_by: HashSet<Entity>,
+}
+
+impl MilitaryAccess {
+    pub fn new(granted_by: HashSet<Entity>) -> Self {
+        Self { granted_by }
+    }
+
+    pub fn has_access_to(&self, country: Entity) -> bool {
+        self.granted_by.contains(&country)
+    }
+
+    pub fn granted_by(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.granted_by.iter().copied()
+    }
+}
+
+/// Countries this country is allied with. Alliances go both ways, and allies are called to arms
+/// when war is declared on them.
+#[derive(Component, Default)]
+pub struct Alliances {
+    allies: HashSet<Entity>,
+    /// Allies that explore the map together with this country, see [`ShareVisionEvent`].
+    shared_vision: HashSet<Entity>,
+}
+
+impl Alliances {
+    /// Alliances as they were saved. Allies share vision only if they are in `shared_vision`.
+    pub fn new(allies: HashSet<Entity>, shared_vision: HashSet<Entity>) -> Self {
+        Self {
+            shared_vision: shared_vision.intersection(&allies).copied().collect(),
+            allies,
+        }
+    }
+
+    pub fn is_allied_with(&self, country: Entity) -> bool {
+        self.allies.contains(&country)
+    }
+
+    pub fn shares_vision_with(&self, country: Entity) -> bool {
+        self.shared_vision.contains(&country)
+    }
+
+    pub fn allies(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.allies.iter().copied()
+    }
+
+    /// Allies sharing their vision with this country.
+    pub fn vision_sharers(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.shared_vision.iter().copied()
+    }
+
+    /// Allies share their vision from the start.
+    fn add(&mut self, ally: Entity) {
+        self.allies.insert(ally);
+        self.shared_vision.insert(ally);
+    }
+
+    fn remove(&mut self, ally: Entity) {
+        self.allies.remove(&ally);
+        self.shared_vision.remove(&ally);
+    }
+}
+
+/// Call of a country at war, `from`, asking its ally `to` to join the war against `enemy`.
+#[derive(Component)]
+pub struct CallToArms {
+    pub from: Entity,
+    pub to: Entity,
+    pub enemy: Entity,
+}
+
+/// Land the armies of a country can't enter, see [`Borders`].
+#[derive(Default)]
+pub struct ClosedBorders {
+    closed: HashMap<Hex, Entity>,
+}
+
+impl ClosedBorders {
+    pub fn allows(&self, hex: &Hex) -> bool {
+        !self.closed.contains_key(hex)
+    }
+
+    /// Country keeping the armies out of the hex.
+    pub fn barred_by(&self, hex: &Hex) -> Option<Entity> {
+        self.closed.get(hex).copied()
+    }
+}
+
+/// Looks up where the armies of a country may march: unowned land, land the country owns or
+/// occupies, the land of countries it is at war with and of those that granted it military
+/// access.
+#[derive(SystemParam)]
+pub struct Borders<'w, 's> {
+    provinces: Query<'w, 's, (&'static Province, &'static Owner, Option<&'static Occupied>)>,
+    war_relations: Query<'w, 's, &'static WarRelations>,
+    access: Query<'w, 's, &'static MilitaryAccess>,
+}
+
+impl Borders<'_, '_> {
+    pub fn closed_to(&self, country: Entity) -> ClosedBorders {
+        let relations = self.war_relations.get(country).ok();
+        let access = self.access.get(country).ok();
+        let is_open = |other: Entity| {
+            other == country
+                || relations.is_some_and(|r| r.is_at_war_with(other))
+                || access.is_some_and(|a| a.has_access_to(other))
+        };
+        let closed = self
+            .provinces
+            .iter()
+            .filter(|(_, owner, occupied)| {
+                !is_open(owner.0) && !occupied.is_some_and(|o| is_open(o.occupier))
+            })
+            .map(|(province, owner, _)| (*province.get_hex(), owner.0))
+            .collect();
+        ClosedBorders { closed }
+    }
+}
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+#[derive(Message)]
+pub struct ProvinceOfferEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub province: Entity,
+    pub price: f32,
+}
+
+#[derive(Message)]
+pub struct AcceptProvinceOfferEvent {
+    pub offer_entity: Entity,
+}
+
+#[derive(Message)]
+pub struct MilitaryAccessRequestEvent {
+    pub from: Entity,
+    pub to: Entity,
+}
+
+#[derive(Message)]
+pub struct AllianceRequestEvent {
+    pub from: Entity,
+    pub to: Entity,
+}
+
+#[derive(Message)]
+pub struct BreakAllianceEvent {
+    pub from: Entity,
+    pub to: Entity,
+}
+
+/// Starts or stops sharing vision between two allies, on both sides.
+#[derive(Message)]
+pub struct ShareVisionEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub shared: bool,
+}
+
+#[derive(Message)]
+pub struct AnswerCallToArmsEvent {
+    pub call: Entity,
+    pub accepted: bool,
+}
+
+// ============================================================================
+// PROVINCE OFFERS
+// ============================================================================
+
+/// A country has a single offer of each province waiting, the latest, so answers can name the
+/// offer by its province.
+pub fn handle_province_offers(
+    mut commands: Commands,
+    mut events: MessageReader<ProvinceOfferEvent>,
+    offers: Query<(Entity, &ProvinceOffer)>,
+) {
+    let mut latest: Vec<&ProvinceOfferEvent> = Vec::new();
+    for event in events.read() {
+        latest.retain(|offer| (offer.to, offer.province) != (event.to, event.province));
+        latest.push(event);
+    }
+    for event in latest {
+        for (entity, offer) in &offers {
+            if (offer.to, offer.province) == (event.to, event.province) {
+                commands.entity(entity).despawn();
+            }
+        }
+        commands.spawn(ProvinceOffer {
+            from: event.from,
+            to: event.to,
+            province: event.province,
+            price: event.price,
+        });
+        info!(
+            "Province {:?} offered by {:?} to {:?} for {:.0} ducats",
+            event.province, event.from, event.to, event.price
+        );
+    }
+}
+
+/// Ducats a province is worth to the AI: what colonizing it would cost, plus its income over
+/// [`PROVINCE_PAYBACK_TURNS`].
+pub fn province_value(income: f32) -> f32 {
+    COLONIZE_COST + income * PROVINCE_PAYBACK_TURNS
+}
+
+/// Answers the province offers sent to AI countries. Gifts are always taken, sales only when the
+/// price is fair and the treasury can pay it.
+pub fn ai_handle_province_offers(
+    mut commands: Commands,
+    offers: Query<(Entity, &ProvinceOffer)>,
+    player: Res<Player>,
+    incomes: Query<&Income, With<Province>>,
+    coffers: Query<&Coffer>,
+    mut accept_events: MessageWriter<AcceptProvinceOfferEvent>,
+) {
+    for (offer_entity, offer) in &offers {
+        if player.is_human(offer.to) {
+            continue;
+        }
+        let income = incomes
+            .get(offer.province)
+            .map_or(0.0, |income| income.get());
+        let can_afford = coffers
+            .get(offer.to)
+            .is_ok_and(|coffer| coffer.get_ducats() >= offer.price);
+        if offer.price <= 0.0 || (can_afford && offer.price <= province_value(income)) {
+            info!("AI country {:?} accepts province offer", offer.to);
+            accept_events.write(AcceptProvinceOfferEvent { offer_entity });
+        } else {
+            info!("AI country {:?} rejects province offer", offer.to);
+            commands.entity(offer_entity).despawn();
+        }
+    }
+}
+
+/// Hands the province over once the offer is accepted, if the seller still owns it and the buyer
+/// can still pay for it.
+pub fn handle_accept_province_offer(
+    mut commands: Commands,
+    mut events: MessageReader<AcceptProvinceOfferEvent>,
+    offers: Query<&ProvinceOffer>,
+    owners: Query<&Owner, (With<Province>, Without<Occupied>)>,
+    mut coffers: Query<&mut Coffer>,
+) {
+    for event in events.read() {
+        let Ok(offer) = offers.get(event.offer_entity) else {
+            warn!("Province offer entity not found: {:?}", event.offer_entity);
+            continue;
+        };
+        commands.entity(event.offer_entity).despawn();
+
+        if !owners
+            .get(offer.province)
+            .is_ok_and(|owner| owner.0 == offer.from)
+        {
+            warn!("Province {:?} can no longer be traded", offer.province);
+            continue;
+        }
+        if offer.price > 0.0 {
+            let Ok(mut buyer) = coffers.get_mut(offer.to) else {
+                continue;
+            };
+            if let Err(e) = buyer.spend(offer.price) {
+                warn!("{:?} can't pay for the province: {}", offer.to, e);
+                continue;
+            }
+            if let Ok(mut seller) = coffers.get_mut(offer.from) {
+                seller.add_ducats(offer.price);
+            }
+        }
+        cede_province(&mut commands, offer.province, offer.to);
+    }
+}
+
+// ============================================================================
+// MILITARY ACCESS
+// ============================================================================
+
+/// Answers requests for military access. Countries let the armies of those sharing their faith
+/// through, as long as they aren't at war with each other.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_military_access_requests(
+    mut commands: Commands,
+    mut events: MessageReader<MilitaryAccessRequestEvent>,
+    faiths: Query<&Faith>,
+    war_relations: Query<&WarRelations>,
+    mut access: Query<&mut MilitaryAccess>,
+    names: Query<&DisplayName>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for event in events.read() {
+        let same_faith = faiths
+            .get(event.from)
+            .ok()
+            .is_some_and(|faith| faiths.get(event.to).ok() == Some(faith));
+        let at_war = war_relations
+            .get(event.from)
+            .is_ok_and(|r| r.is_at_war_with(event.to));
+        let granted = same_faith && !at_war;
+        if granted {
+            match access.get_mut(event.from) {
+                Ok(mut access) => {
+                    access.granted_by.insert(event.to);
+                }
+                Err(_) => {
+                    commands.entity(event.from).insert(MilitaryAccess {
+                        granted_by: HashSet::from([event.to]),
+                    });
+                }
+            }
+        }
+        info!(
+            "Military access of {:?} through {:?}: {}",
+            event.from,
+            event.to,
+            if granted { "granted" } else { "refused" }
+        );
+
+        if player.country == Some(event.from) {
+            let name = names.get(event.to).map_or("Unknown", |n| n.0.as_str());
+            let text = if granted {
+                format!("{} lets our armies march through their land.", name)
+            } else {
+                format!(
+                    "{} refuses to let our armies through, they only trust those of their faith.",
+                    name
+                )
+            };
+            notifications.push(Notification {
+                title: "🛡 Military access".to_string(),
+                text,
+                target: None,
+            });
+        }
+    }
+}
+
+// ============================================================================
+// ALLIANCES
+// ============================================================================
+
+/// Answers alliance proposals. Like military access, countries ally with those sharing their
+/// faith, as long as they aren't at war with each other.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_alliance_requests(
+    mut commands: Commands,
+    mut events: MessageReader<AllianceRequestEvent>,
+    faiths: Query<&Faith>,
+    war_relations: Query<&WarRelations>,
+    mut alliances: Query<&mut Alliances>,
+    names: Query<&DisplayName>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for event in events.read() {
+        let same_faith = faiths
+            .get(event.from)
+            .ok()
+            .is_some_and(|faith| faiths.get(event.to).ok() == Some(faith));
+        let at_war = war_relations
+            .get(event.from)
+            .is_ok_and(|r| r.is_at_war_with(event.to));
+        let accepted = event.from != event.to && same_faith && !at_war;
+        if accepted {
+            for (country, ally) in [(event.from, event.to), (event.to, event.from)] {
+                match alliances.get_mut(country) {
+                    Ok(mut alliances) => alliances.add(ally),
+                    Err(_) => {
+                        let mut alliances = Alliances::default();
+                        alliances.add(ally);
+                        commands.entity(country).insert(alliances);
+                    }
+                }
+            }
+        }
+        info!(
+            "Alliance of {:?} with {:?}: {}",
+            event.from,
+            event.to,
+            if accepted { "formed" } else { "refused" }
+        );
+
+        if player.country == Some(event.from) {
+            let name = names.get(event.to).map_or("Unknown", |n| n.0.as_str());
+            let text = if accepted {
+                format!("{} is now our ally.", name)
+            } else {
+                format!(
+                    "{} turns down our alliance, they only trust those of their faith.",
+                    name
+                )
+            };
+            notifications.push(Notification {
+                title: "🤝 Alliance".to_string(),
+                text,
+                target: None,
+            });
+        }
+    }
+}
+
+/// Ends alliances on both sides.
+pub fn handle_broken_alliances(
+    mut events: MessageReader<BreakAllianceEvent>,
+    mut alliances: Query<&mut Alliances>,
+    names: Query<&DisplayName>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for event in events.read() {
+        for (country, ally) in [(event.from, event.to), (event.to, event.from)] {
+            if let Ok(mut alliances) = alliances.get_mut(country) {
+                alliances.remove(ally);
+            }
+        }
+        info!("Alliance of {:?} with {:?} broken", event.from, event.to);
+
+        if player.country == Some(event.to) {
+            let name = names.get(event.from).map_or("Unknown", |n| n.0.as_str());
+            notifications.push(Notification {
+                title: "🤝 Alliance broken".to_string(),
+                text: format!("{} is no longer our ally.", name),
+                target: None,
+            });
+        }
+    }
+}
+
+/// Starts or stops sharing vision between allies. Countries that aren't allied share nothing.
+pub fn handle_shared_vision(
+    mut events: MessageReader<ShareVisionEvent>,
+    mut alliances: Query<&mut Alliances>,
+) {
+    for event in events.read() {
+        for (country, ally) in [(event.from, event.to), (event.to, event.from)] {
+            let Ok(mut alliances) = alliances.get_mut(country) else {
+                continue;
+            };
+            if !alliances.is_allied_with(ally) {
+                continue;
+            }
+            if event.shared {
+                alliances.shared_vision.insert(ally);
+            } else {
+                alliances.shared_vision.remove(&ally);
+            }
+        }
+        info!(
+            "Vision of {:?} and {:?} shared: {}",
+            event.from, event.to, event.shared
+        );
+    }
+}
+
+/// Calls the allies of the defender to arms against the attacker, except those already at war
+/// with it.
+pub fn call_allies_to_arms(
+    commands: &mut Commands,
+    alliances: &Query<&Alliances>,
+    war_relations: &Query<&mut WarRelations>,
+    event: &DeclareWarEvent,
+) {
+    let Ok(defender_alliances) = alliances.get(event.defender) else {
+        return;
+    };
+    let mut allies: Vec<Entity> = defender_alliances.allies.iter().copied().collect();
+    allies.sort_unstable();
+    for ally in allies {
+        let at_war = war_relations
+            .get(ally)
+            .is_ok_and(|r| r.is_at_war_with(event.attacker));
+        if ally == event.attacker || at_war {
+            continue;
+        }
+        commands.spawn(CallToArms {
+            from: event.defender,
+            to: ally,
+            enemy: event.attacker,
+        });
+        info!(
+            "{:?} calls {:?} to arms against {:?}",
+            event.defender, ally, event.attacker
+        );
+    }
+}
+
+/// Answers the calls to arms sent to AI countries. They honor their alliances, unless they are
+/// allied with the enemy as well.
+pub fn ai_answer_calls_to_arms(
+    calls: Query<(Entity, &CallToArms)>,
+    player: Res<Player>,
+    alliances: Query<&Alliances>,
+    mut answers: MessageWriter<AnswerCallToArmsEvent>,
+) {
+    for (call, call_to_arms) in &calls {
+        if player.is_human(call_to_arms.to) {
+            continue;
+        }
+        let torn = alliances
+            .get(call_to_arms.to)
+            .is_ok_and(|a| a.is_allied_with(call_to_arms.enemy));
+        answers.write(AnswerCallToArmsEvent {
+            call,
+            accepted: !torn,
+        });
+    }
+}
+
+/// Brings the ally into the war when it answers the call, declining ends the alliance.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_call_to_arms_answers(
+    mut commands: Commands,
+    mut events: MessageReader<AnswerCallToArmsEvent>,
+    calls: Query<&CallToArms>,
+    mut war_events: MessageWriter<DeclareWarEvent>,
+    mut broken_alliances: MessageWriter<BreakAllianceEvent>,
+    names: Query<&DisplayName>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for event in events.read() {
+        let Ok(call) = calls.get(event.call) else {
+            warn!("Call to arms entity not found: {:?}", event.call);
+            continue;
+        };
+        commands.entity(event.call).despawn();
+
+        if event.accepted {
+            war_events.write(DeclareWarEvent::joining(call.to, call.enemy));
+        } else {
+            broken_alliances.write(BreakAllianceEvent {
+                from: call.to,
+                to: call.from,
+            });
+        }
+        info!(
+            "{:?} {} the call to arms of {:?}",
+            call.to,
+            if event.accepted {
+                "answers"
+            } else {
+                "declines"
+            },
+            call.from
+        );
+
+        if player.country == Some(call.from) && event.accepted {
+            let name = |country: Entity| names.get(country).map_or("Unknown", |n| n.0.as_str());
+            notifications.push(Notification {
+                title: "🤝 Call to arms".to_string(),
+                text: format!(
+                    "{} honors our alliance and joins the war against {}.",
+                    name(call.to),
+                    name(call.enemy)
+                ),
+                target: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::player_command::PlayerCommand;
+    use crate::terrain::Terrain;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn ai_buys_provinces_at_a_fair_price() {
+        let mut game = TestGame::new();
+        let seller = game.spawn_country("Seller");
+        let buyer = game.spawn_country("Buyer");
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(seller));
+        game.world_mut().get_mut::<Coffer>(buyer).unwrap().0 = 200.0;
+        let seller_ducats = game.ducats(seller);
+        let value = province_value(Terrain::Plains.def().income);
+        let offer = |game: &mut TestGame, price: f32| {
+            game.world_mut().write_message(ProvinceOfferEvent {
+                from: seller,
+                to: buyer,
+                province,
+                price,
+            });
+            game.app.update();
+        };
+
+        offer(&mut game, value + 10.0);
+        assert_eq!(game.get::<Owner>(province).unwrap().0, seller);
+        assert_eq!(game.count::<ProvinceOffer>(), 0);
+
+        offer(&mut game, value);
+        assert_eq!(game.get::<Owner>(province).unwrap().0, buyer);
+        assert_eq!(game.ducats(buyer), 200.0 - value);
+        assert_eq!(game.ducats(seller), seller_ducats + value);
+    }
+
+    #[test]
+    fn players_answer_province_offers_through_commands() {
+        let mut game = TestGame::new();
+        let seller = game.spawn_country("Seller");
+        let buyer = game.spawn_country("Buyer");
+        game.world_mut().resource_mut::<Player>().country = Some(buyer);
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(seller));
+        let offer = |game: &mut TestGame| {
+            game.world_mut().write_message(ProvinceOfferEvent {
+                from: seller,
+                to: buyer,
+                province,
+                price: 0.0,
+            });
+            game.app.update();
+        };
+        let answer = |accept| PlayerCommand::AnswerProvinceOffer {
+            country: "Buyer".to_string(),
+            province: Hex::new(0, 0),
+            accept,
+        };
+
+        offer(&mut game);
+        game.world_mut().write_message(answer(false));
+        game.app.update();
+        assert_eq!(game.count::<ProvinceOffer>(), 0);
+        assert_eq!(game.get::<Owner>(province).unwrap().0, seller);
+
+        offer(&mut game);
+        // Answering twice in a frame only counts the first answer.
+        game.world_mut().write_message(answer(true));
+        game.world_mut().write_message(answer(false));
+        game.app.update();
+        assert_eq!(game.count::<ProvinceOffer>(), 0);
+        assert_eq!(game.get::<Owner>(province).unwrap().0, buyer);
+    }
+
+    #[test]
+    fn allies_join_wars_declared_on_each_other() {
+        let mut game = TestGame::new();
+        let defender = game.spawn_country("Defender");
+        let ally = game.spawn_country("Ally");
+        let attacker = game.spawn_country("Attacker");
+        game.world_mut().write_message(AllianceRequestEvent {
+            from: defender,
+            to: ally,
+        });
+        game.app.update();
+        let alliances = game.get::<Alliances>(ally).unwrap();
+        assert!(alliances.is_allied_with(defender));
+
+        game.declare_war(attacker, defender);
+        assert_eq!(game.count::<CallToArms>(), 1);
+        game.app.update();
+        game.app.update();
+
+        assert_eq!(game.count::<CallToArms>(), 0);
+        let relations = game.get::<WarRelations>(ally).unwrap();
+        assert!(relations.is_at_war_with(attacker));
+    }
+
+    #[test]
+    fn players_answer_calls_to_arms_through_commands() {
+        let mut game = TestGame::new();
+        let defender = game.spawn_country("Defender");
+        let ally = game.spawn_country("Ally");
+        let attacker = game.spawn_country("Attacker");
+        let raider = game.spawn_country("Raider");
+        game.world_mut().resource_mut::<Player>().country = Some(ally);
+        game.world_mut().write_message(AllianceRequestEvent {
+            from: defender,
+            to: ally,
+        });
+        game.app.update();
+        let answer = |enemy: &str, accept| PlayerCommand::AnswerCallToArms {
+            country: "Ally".to_string(),
+            from: "Defender".to_string(),
+            enemy: enemy.to_string(),
+            accept,
+        };
+
+        game.declare_war(attacker, defender);
+        // Answering twice in a frame only counts the first answer.
+        game.world_mut().write_message(answer("Attacker", true));
+        game.world_mut().write_message(answer("Attacker", false));
+        game.app.update();
+        game.app.update();
+        assert_eq!(game.count::<CallToArms>(), 0);
+        let relations = game.get::<WarRelations>(ally).unwrap();
+        assert!(relations.is_at_war_with(attacker));
+        let alliances = game.get::<Alliances>(ally).unwrap();
+        assert!(alliances.is_allied_with(defender));
+
+        game.declare_war(raider, defender);
+        game.world_mut().write_message(answer("Raider", false));
+        game.app.update();
+        assert_eq!(game.count::<CallToArms>(), 0);
+        let alliances = game.get::<Alliances>(ally).unwrap();
+        assert!(!alliances.is_allied_with(defender));
+    }
+}
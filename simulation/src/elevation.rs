@@ -0,0 +1,86 @@
+﻿use crate::hex::Hex;
+use bevy::color::{Color, Mix};
+
+/// Cost of stepping onto a neighbouring hex on level ground, in pathfinding cost units.
+pub const STEP_COST: usize = 10;
+/// Meters climbed per extra cost unit when moving uphill.
+const METERS_PER_CLIMB_COST: f32 = 100.0;
+/// Elevation at which the map shading is the strongest.
+const MAX_SHADED_ELEVATION: f32 = 3000.0;
+/// How much the highest provinces are lightened on the map.
+const HIGHLAND_LIGHTENING: f32 = 0.35;
+/// Defender damage bonus per kilometer the defender stands above the attacker.
+const HIGH_GROUND_BONUS_PER_KM: f32 = 0.2;
+/// Cap of the high ground bonus, and of the malus when the attacker is the one above.
+const MAX_HIGH_GROUND_BONUS: f32 = 0.3;
+/// Spread of the generated elevation around the terrain's elevation.
+const GENERATED_VARIATION: f32 = 400.0;
+
+/// Elevation of a hex the map doesn't set one for: the terrain's elevation, varied by smooth
+/// noise so neighbouring hexes of the same terrain don't form flat plateaus. The noise only
+/// depends on the coordinates, so every player generates the same elevation.
+pub fn generate(hex: Hex, terrain_elevation: f32) -> f32 {
+    (terrain_elevation + (noise(hex) - 0.5) * GENERATED_VARIATION).max(0.0)
+}
+
+/// Value noise in `[0, 1)`, the hex's own value weighted double against its neighbours'.
+fn noise(hex: Hex) -> f32 {
+    let neighbors: f32 = hex.neighbors().into_iter().map(hash).sum();
+    (2.0 * hash(hex) + neighbors) / 8.0
+}
+
+/// Pseudo-random value in `[0, 1)` for the coordinates.
+fn hash(hex: Hex) -> f32 {
+    let mut x = (hex.q() as u32 as u64) << 32 | hex.r() as u32 as u64;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^= x >> 33;
+    (x >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Cost of moving between neighbouring hexes, going uphill costs more than level ground.
+pub fn step_cost(from: f32, to: f32) -> usize {
+    STEP_COST + ((to - from).max(0.0) / METERS_PER_CLIMB_COST) as usize
+}
+
+/// Multiplier of the defender's damage from the height difference with the attacker, on top of
+/// the terrain's defender bonus. Values below 1.0 mean the attacker holds the high ground.
+pub fn high_ground_bonus(defender: f32, attacker: f32) -> f32 {
+    let bonus = (defender - attacker) / 1000.0 * HIGH_GROUND_BONUS_PER_KM;
+    1.0 + bonus.clamp(-MAX_HIGH_GROUND_BONUS, MAX_HIGH_GROUND_BONUS)
+}
+
+/// Lightens the color of higher provinces, so the relief shows on the map.
+pub fn shade(color: Color, elevation: f32) -> Color {
+    let height = (elevation / MAX_SHADED_ELEVATION).clamp(0.0, 1.0);
+    color.mix(&Color::WHITE, height * HIGHLAND_LIGHTENING)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_elevation_stays_around_the_terrain_elevation() {
+        let hex = Hex::new(4, -7);
+        assert_eq!(generate(hex, 1000.0), generate(hex, 1000.0));
+        for neighbor in hex.neighbors() {
+            let difference = (generate(neighbor, 1000.0) - 1000.0).abs();
+            assert!(difference <= GENERATED_VARIATION / 2.0);
+        }
+        assert_eq!(generate(hex, -GENERATED_VARIATION), 0.0);
+    }
+
+    #[test]
+    fn climbing_costs_more_than_descending() {
+        assert_eq!(step_cost(500.0, 500.0), STEP_COST);
+        assert_eq!(step_cost(1500.0, 500.0), STEP_COST);
+        assert_eq!(step_cost(500.0, 1500.0), STEP_COST + 10);
+
+        assert!(high_ground_bonus(800.0, 200.0) > 1.0);
+        assert!(high_ground_bonus(200.0, 800.0) < 1.0);
+        assert_eq!(high_ground_bonus(5000.0, 0.0), 1.0 + MAX_HIGH_GROUND_BONUS);
+    }
+}
@@ -0,0 +1,36 @@
+﻿use bevy::prelude::*;
+
+/// Failure that has to be shown to the player, e.g. a corrupt save or a missing map file. Fallible
+/// systems return it and are piped into [`report_errors`], the presentation shows it on the error
+/// screen.
+#[derive(Message, Clone, Debug)]
+pub struct GameError {
+    pub title: String,
+    pub details: String,
+    /// The game can't continue after a fatal error, the only way out is quitting.
+    pub fatal: bool,
+}
+
+impl GameError {
+    pub fn new(title: &str, details: impl Into<String>) -> Self {
+        Self {
+            title: title.to_string(),
+            details: details.into(),
+            fatal: false,
+        }
+    }
+
+    pub fn fatal(title: &str, details: impl Into<String>) -> Self {
+        Self {
+            fatal: true,
+            ..Self::new(title, details)
+        }
+    }
+}
+
+/// Piped after fallible systems to show their errors on the error screen instead of panicking.
+pub fn report_errors(In(result): In<Result<(), GameError>>, mut errors: MessageWriter<GameError>) {
+    if let Err(err) = result {
+        errors.write(err);
+    }
+}
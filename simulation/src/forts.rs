@@ -0,0 +1,291 @@
+﻿use crate::army::{
+    ArmyComposition, Battle, BattleJoinedEvent, BattleSide, BattleStartedEvent, InBattle,
+    REGIMENT_SIZE, determine_battle_side, spawn_army,
+};
+use crate::buildings::{Building, BuildingType};
+use crate::hex::Hex;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::turns::{GameState, TurnSet};
+use crate::units::UnitRegistry;
+use crate::war::{Occupied, SiegeProgress, WarRelations};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+pub struct FortsPlugin;
+
+impl Plugin for FortsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Processing),
+            (
+                sortie_garrisons
+                    .after(crate::army::move_active_armies)
+                    .before(crate::army::resolve_battles),
+                return_sorties.after(crate::army::resolve_battles),
+            )
+                .in_set(TurnSet::Resolve),
+        );
+    }
+}
+
+/// Hexes around a fort it keeps enemy armies from moving through.
+const ZONE_OF_CONTROL_RADIUS: i32 = 1;
+/// Soldiers in the garrison of a fort at full strength.
+pub const FORT_GARRISON: u32 = 3 * REGIMENT_SIZE;
+/// Soldiers a garrison recovers every turn it isn't besieged.
+const GARRISON_RECOVERY: u32 = REGIMENT_SIZE;
+
+/// Soldiers left in the garrison of a fort, it is at [`FORT_GARRISON`] while missing.
+#[derive(Component)]
+pub struct Garrison(pub u32);
+
+/// Marks an army the garrison of a besieged fort sent out to fight a battle at its walls. It
+/// returns into the fort once the battle is won.
+#[derive(Component)]
+pub struct Sortie {
+    pub province: Entity,
+}
+
+/// Zones of control of the forts hostile to a country. Armies of that country can enter a zone,
+/// but can't move from one hex of a zone to another one except into the fort itself, where they
+/// stay until they lay siege to it. They have to take the fort before advancing past it.
+#[derive(Default)]
+pub struct ZonesOfControl {
+    forts: HashSet<Hex>,
+}
+
+impl ZonesOfControl {
+    fn zone_contains(fort: &Hex, hex: &Hex) -> bool {
+        fort.distance(hex) <= ZONE_OF_CONTROL_RADIUS
+    }
+
+    /// Whether an army may step from `from` to the neighbouring hex `to`.
+    pub fn allows_step(&self, from: &Hex, to: &Hex) -> bool {
+        if self.forts.contains(to) {
+            return true;
+        }
+        !self
+            .forts
+            .iter()
+            .any(|fort| Self::zone_contains(fort, from) && Self::zone_contains(fort, to))
+    }
+}
+
+/// Looks up the forts hostile to a country, see [`ZonesOfControl`].
+#[derive(SystemParam)]
+pub struct FortZones<'w, 's> {
+    provinces: Query<
+        'w,
+        's,
+        (
+            &'static Province,
+            Option<&'static Owner>,
+            Option<&'static Occupied>,
+            &'static Children,
+        ),
+        Without<SiegeProgress>,
+    >,
+    buildings: Query<'w, 's, &'static Building>,
+    war_relations: Query<'w, 's, &'static WarRelations>,
+}
+
+impl FortZones<'_, '_> {
+    /// Zones of control of the forts held by countries at war with `country`. A fort is held by
+    /// its occupier, or its owner when it isn't occupied. Forts under siege don't block anyone.
+    pub fn hostile_to(&self, country: Entity) -> ZonesOfControl {
+        let Ok(relations) = self.war_relations.get(country) else {
+            return ZonesOfControl::default();
+        };
+        let forts = self
+            .provinces
+            .iter()
+            .filter(|(_, owner, occupied, _)| {
+                occupied
+                    .map(|o| o.occupier)
+                    .or(owner.map(|o| o.0))
+                    .is_some_and(|controller| relations.is_at_war_with(controller))
+            })
+            .filter(|(_, _, _, children)| {
+                children.iter().any(|child| {
+                    self.buildings
+                        .get(child)
+                        .is_ok_and(|b| b.building_type == BuildingType::Fort)
+                })
+            })
+            .map(|(province, _, _, _)| *province.get_hex())
+            .collect();
+        ZonesOfControl { forts }
+    }
+}
+
+pub fn has_fort(children: Option<&Children>, buildings: &Query<&Building>) -> bool {
+    children.is_some_and(|children| {
+        children.iter().any(|child| {
+            buildings
+                .get(child)
+                .is_ok_and(|b| b.building_type == BuildingType::Fort)
+        })
+    })
+}
+
+/// Level of a province's forts, one for every fort built in it.
+pub fn fort_level(children: Option<&Children>, buildings: &Query<&Building>) -> u32 {
+    children.map_or(0, |children| {
+        children
+            .iter()
+            .filter(|&child| {
+                buildings
+                    .get(child)
+                    .is_ok_and(|b| b.building_type == BuildingType::Fort)
+            })
+            .count() as u32
+    })
+}
+
+/// Sends the garrison of a besieged fort out when a battle starts at its walls, e.g. a relief
+/// army attacking the besiegers. The garrison joins whichever side fights for its owner.
+#[allow(clippy::too_many_arguments)]
+pub fn sortie_garrisons(
+    mut commands: Commands,
+    mut events: MessageReader<BattleStartedEvent>,
+    province_map: Res<ProvinceHexMap>,
+    mut provinces: Query<
+        (&Owner, Option<&Children>, Option<&mut Garrison>),
+        (With<Province>, With<SiegeProgress>),
+    >,
+    buildings: Query<&Building>,
+    in_battle: Query<&InBattle>,
+    mut battles: Query<&mut Battle>,
+    war_relations: Query<&WarRelations>,
+    units: Res<UnitRegistry>,
+) {
+    let Some(infantry) = units.basic_infantry() else {
+        return;
+    };
+    for event in events.read() {
+        let Some(&province) = province_map.get_entity(&event.location) else {
+            continue;
+        };
+        let Ok((owner, children, mut garrison)) = provinces.get_mut(province) else {
+            continue;
+        };
+        if !has_fort(children, &buildings) {
+            continue;
+        }
+        let soldiers = garrison.as_ref().map_or(FORT_GARRISON, |g| g.0);
+        if soldiers == 0 {
+            continue;
+        }
+        let Ok(battle_entity) = in_battle.get(event.attacker).map(|b| b.battle_entity) else {
+            continue;
+        };
+        let Ok(mut battle) = battles.get_mut(battle_entity) else {
+            continue;
+        };
+        let Some(side) = determine_battle_side(owner.0, &battle, &war_relations) else {
+            continue;
+        };
+
+        let army = spawn_army(
+            &mut commands,
+            event.location,
+            owner.0,
+            ArmyComposition::default().with(infantry.clone(), soldiers),
+        );
+        commands
+            .entity(army)
+            .insert((InBattle { battle_entity }, Sortie { province }));
+        match side {
+            BattleSide::Attacker => battle.attackers.push(army),
+            BattleSide::Defender => battle.defenders.push(army),
+        }
+        match garrison.as_mut() {
+            Some(garrison) => garrison.0 = 0,
+            None => {
+                commands.entity(province).insert(Garrison(0));
+            }
+        }
+        commands.write_message(BattleJoinedEvent {
+            location: event.location,
+            army,
+            country: owner.0,
+        });
+        info!(
+            "Garrison of {:?} sallies out with {} soldiers",
+            province, soldiers
+        );
+    }
+}
+
+/// Brings the survivors of won sorties back into their fort, and lets garrisons that aren't
+/// besieged recover.
+pub fn return_sorties(
+    mut commands: Commands,
+    sorties: Query<(Entity, &Sortie, &ArmyComposition), Without<InBattle>>,
+    mut garrisons: Query<(&mut Garrison, Has<SiegeProgress>)>,
+) {
+    for (army, sortie, composition) in &sorties {
+        if let Ok((mut garrison, _)) = garrisons.get_mut(sortie.province) {
+            garrison.0 = (garrison.0 + composition.total_size()).min(FORT_GARRISON);
+        }
+        commands.entity(army).despawn();
+    }
+    for (mut garrison, besieged) in &mut garrisons {
+        if !besieged && garrison.0 < FORT_GARRISON {
+            garrison.0 = (garrison.0 + GARRISON_RECOVERY).min(FORT_GARRISON);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn armies_cannot_move_along_a_hostile_fort() {
+        let zones = ZonesOfControl {
+            forts: HashSet::from([Hex::new(0, 0)]),
+        };
+        // Into the zone and into the fort.
+        assert!(zones.allows_step(&Hex::new(-2, 0), &Hex::new(-1, 0)));
+        assert!(zones.allows_step(&Hex::new(-1, 0), &Hex::new(0, 0)));
+        // Around the fort, inside its zone.
+        assert!(!zones.allows_step(&Hex::new(-1, 0), &Hex::new(0, -1)));
+        // Out of the fort, past it.
+        assert!(!zones.allows_step(&Hex::new(0, 0), &Hex::new(1, 0)));
+    }
+
+    #[test]
+    fn garrison_joins_the_battle_of_a_relief_army() {
+        let mut game = TestGame::new();
+        let defender = game.spawn_country("Defender");
+        let besieger = game.spawn_country("Besieger");
+        game.declare_war(besieger, defender);
+        game.spawn_province("Camp", Hex::new(0, 0), Some(defender));
+        let fort = game.spawn_province("Fort", Hex::new(1, 0), Some(defender));
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Fort,
+            },
+            ChildOf(fort),
+        ));
+        game.spawn_army(besieger, Hex::new(1, 0), 5 * REGIMENT_SIZE);
+        game.end_turn();
+        assert!(game.get::<SiegeProgress>(fort).is_some());
+
+        let relief = game.spawn_army(defender, Hex::new(0, 0), 5 * REGIMENT_SIZE);
+        game.move_army(relief, Hex::new(1, 0));
+        game.end_turn();
+
+        let battle = game
+            .world_mut()
+            .query::<&Battle>()
+            .single(game.world())
+            .unwrap();
+        assert_eq!(battle.attackers.len(), 2);
+        assert_eq!(game.count::<Sortie>(), 1);
+        assert_eq!(game.get::<Garrison>(fort).unwrap().0, 0);
+    }
+}
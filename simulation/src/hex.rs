@@ -0,0 +1,219 @@
+﻿use bevy::math::{Mat2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul, Sub};
+
+/// Struct representing a hexagonal tile in axial coordinates. Uses pointy top orientation.
+/// Serializes as `{"q": .., "r": ..}`, which `#[serde(flatten)]` turns into plain `q` and `r`
+/// fields of the containing struct.
+/// See: https://www.redblobgames.com/grids/hexagons/#basics
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Hex {
+    q: i32,
+    r: i32,
+}
+
+/// # Size parameter
+/// In all methods below, `size` is the circumradius of the hex:
+/// the distance from the hex center to any vertex.
+impl Hex {
+    /// Square root of 3 constant.
+    const SQRT_3: f32 = 1.732_050_8;
+
+    /// Directions to neighboring hexes in axial coordinates in (q,r) format. Starts from the right top
+    /// neighbor and goes clockwise.
+    const NEIGHBOR_DIR: [(i32, i32); 6] = [(1, -1), (1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1)];
+
+    /// Matrix to convert axial coordinates to world coordinates.
+    /// | √3   √3/2 |
+    /// |  0   3/2  |
+    const AXIAL_TO_WORLD_MATRIX: Mat2 =
+        Mat2::from_cols_array(&[Self::SQRT_3, 0.0, Self::SQRT_3 / 2.0, 1.5]);
+
+    /// Matrix to convert world coordinates back to fractional axial coordinates, the inverse of
+    /// [`Hex::AXIAL_TO_WORLD_MATRIX`].
+    /// | √3/3  -1/3 |
+    /// |  0     2/3 |
+    const WORLD_TO_AXIAL_MATRIX: Mat2 =
+        Mat2::from_cols_array(&[Self::SQRT_3 / 3.0, 0.0, -1.0 / 3.0, 2.0 / 3.0]);
+
+    /// Converts axial coordinates to world coordinates. Used for displaying hexes on the map.
+    /// This assumes origin point at (0,0) - if the map origin is elsewhere, an offset should be applied.
+    pub fn axial_to_world(&self, size: f32) -> Vec2 {
+        Self::AXIAL_TO_WORLD_MATRIX.mul_vec2(Vec2::new(self.q as f32 * size, self.r as f32 * size))
+    }
+
+    /// Returns the hex containing the world position, the inverse of [`Hex::axial_to_world`].
+    pub fn world_to_axial(position: Vec2, size: f32) -> Hex {
+        let fractional = Self::WORLD_TO_AXIAL_MATRIX.mul_vec2(position / size);
+        Self::round(fractional.x, fractional.y)
+    }
+
+    /// Rounds fractional axial coordinates to the nearest hex, by rounding in cube coordinates and
+    /// resetting the coordinate with the largest rounding error.
+    /// See: https://www.redblobgames.com/grids/hexagons/#rounding
+    pub fn round(q: f32, r: f32) -> Hex {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Hex::new(rq as i32, rr as i32)
+    }
+
+    /// Creates a new Hex with the given axial coordinates.
+    pub fn new(q: i32, r: i32) -> Self {
+        Hex { q, r }
+    }
+
+    /// Returns the q axial coordinate.
+    pub fn q(&self) -> i32 {
+        self.q
+    }
+
+    /// Returns the r axial coordinate.
+    pub fn r(&self) -> i32 {
+        self.r
+    }
+
+    /// Returns the neighboring hex in the specified direction (0 to 5). See [`Hex::NEIGHBOR_DIR`]
+    /// for mapping.
+    pub fn neighbor(&self, direction: usize) -> Hex {
+        let (dq, dr) = Self::NEIGHBOR_DIR[direction % 6];
+        Hex {
+            q: self.q + dq,
+            r: self.r + dr,
+        }
+    }
+
+    pub fn neighbors(&self) -> Vec<Hex> {
+        (0..6).map(|dir| self.neighbor(dir)).collect()
+    }
+
+    /// Ends of the edge this hex shares with the neighboring hex `other`, in world coordinates.
+    pub fn shared_edge(&self, other: &Hex, size: f32) -> (Vec2, Vec2) {
+        let center = self.axial_to_world(size);
+        let across = other.axial_to_world(size) - center;
+        let midpoint = center + across / 2.0;
+        let along = across.perp().normalize() * size / 2.0;
+        (midpoint - along, midpoint + along)
+    }
+
+    /// Number of steps between the two hexes.
+    pub fn distance(&self, other: &Hex) -> i32 {
+        let dq = self.q - other.q;
+        let dr = self.r - other.r;
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    /// Returns the hex `t` of the way from this hex to `other`, `t` going from 0 to 1. Stepping
+    /// `t` by `1 / distance` walks the hexes of the line between them.
+    /// See: https://www.redblobgames.com/grids/hexagons/#line-drawing
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn lerp(&self, other: &Hex, t: f32) -> Hex {
+        let q = self.q as f32 + (other.q - self.q) as f32 * t;
+        let r = self.r as f32 + (other.r - self.r) as f32 * t;
+        Self::round(q, r)
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, factor: i32) -> Hex {
+        Hex::new(self.q * factor, self.r * factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every hex within `radius` steps of the origin.
+    fn hexes_within(radius: i32) -> Vec<Hex> {
+        (-radius..=radius)
+            .flat_map(|q| {
+                ((-radius).max(-q - radius)..=radius.min(-q + radius)).map(move |r| Hex::new(q, r))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn world_to_axial_inverts_axial_to_world() {
+        for hex in hexes_within(10) {
+            let center = hex.axial_to_world(50.0);
+            assert_eq!(Hex::world_to_axial(center, 50.0), hex);
+            // Anywhere well inside the hex maps back to it too.
+            assert_eq!(
+                Hex::world_to_axial(center + Vec2::new(20.0, -20.0), 50.0),
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn neighbors_share_an_edge_between_two_corners() {
+        let hex = Hex::new(2, -1);
+        for neighbor in hex.neighbors() {
+            let (start, end) = hex.shared_edge(&neighbor, 50.0);
+            for corner in [start, end] {
+                assert!((corner.distance(hex.axial_to_world(50.0)) - 50.0).abs() < 1e-3);
+                assert!((corner.distance(neighbor.axial_to_world(50.0)) - 50.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_lerp() {
+        let a = Hex::new(1, -2);
+        let b = Hex::new(-3, 4);
+        assert_eq!(a + b, Hex::new(-2, 2));
+        assert_eq!(a - b, Hex::new(4, -6));
+        assert_eq!(b * 2, Hex::new(-6, 8));
+        assert_eq!(a.neighbor(1) - a, Hex::new(1, 0));
+
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(Hex::new(0, 0).lerp(&Hex::new(4, 0), 0.5), Hex::new(2, 0));
+        let steps = a.distance(&b);
+        let line: Vec<Hex> = (0..=steps)
+            .map(|i| a.lerp(&b, i as f32 / steps as f32))
+            .collect();
+        assert!(line.windows(2).all(|pair| pair[0].distance(&pair[1]) == 1));
+    }
+
+    #[test]
+    fn serializes_as_q_and_r() {
+        let json = serde_json::to_string(&Hex::new(3, -1)).unwrap();
+        assert_eq!(json, r#"{"q":3,"r":-1}"#);
+        assert_eq!(serde_json::from_str::<Hex>(&json).unwrap(), Hex::new(3, -1));
+    }
+
+    #[test]
+    fn distance_counts_steps() {
+        let origin = Hex::new(0, 0);
+        assert_eq!(origin.distance(&origin), 0);
+        assert!(origin.neighbors().iter().all(|n| origin.distance(n) == 1));
+        assert_eq!(origin.distance(&Hex::new(3, -1)), 3);
+        assert_eq!(Hex::new(-2, 4).distance(&Hex::new(2, -1)), 5);
+        assert_eq!(hexes_within(3).len(), 37);
+    }
+}
@@ -0,0 +1,69 @@
+﻿use crate::mods::VirtualFs;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<DataFileChangedEvent>()
+            .insert_resource(WatchedDataFiles::default())
+            .add_systems(Update, check_data_files);
+    }
+}
+
+/// How often the watched data files are checked for changes, in seconds.
+const DATA_RELOAD_INTERVAL: f32 = 1.0;
+
+/// Message sent when a watched data file was changed on disk, or is now provided by another mod.
+/// Holds the file's virtual path. Systems owning the data re-apply whatever is safe to change in
+/// a running game.
+#[derive(Message, Clone, Debug)]
+pub struct DataFileChangedEvent(pub String);
+
+/// Data files checked for changes, by virtual path, with the real file and modification time seen
+/// last.
+#[derive(Resource, Default)]
+pub struct WatchedDataFiles {
+    files: HashMap<String, Option<(PathBuf, SystemTime)>>,
+}
+
+impl WatchedDataFiles {
+    /// Starts watching the file. Its current version counts as seen, so watching a file that was
+    /// just loaded doesn't trigger a reload.
+    pub fn watch(&mut self, vfs: &VirtualFs, path: &str) {
+        self.files.insert(path.to_string(), file_stamp(vfs, path));
+    }
+}
+
+fn file_stamp(vfs: &VirtualFs, path: &str) -> Option<(PathBuf, SystemTime)> {
+    let file = vfs.resolve(path)?;
+    let modified = fs::metadata(&file).and_then(|m| m.modified()).ok()?;
+    Some((file, modified))
+}
+
+fn check_data_files(
+    mut watched: ResMut<WatchedDataFiles>,
+    vfs: Res<VirtualFs>,
+    time: Res<Time>,
+    mut changed_events: MessageWriter<DataFileChangedEvent>,
+    mut since_check: Local<f32>,
+) {
+    *since_check += time.delta_secs();
+    if *since_check < DATA_RELOAD_INTERVAL {
+        return;
+    }
+    *since_check = 0.0;
+
+    for (path, stamp) in watched.files.iter_mut() {
+        let current = file_stamp(&vfs, path);
+        if current != *stamp {
+            info!("Data file {} changed, reloading", path);
+            *stamp = current;
+            changed_events.write(DataFileChangedEvent(path.clone()));
+        }
+    }
+}
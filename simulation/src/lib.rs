@@ -0,0 +1,148 @@
+﻿//! The game's simulation as plugins: the world, countries, armies, wars, turns, saves, scripts and
+//! multiplayer. It only needs Bevy's ECS, states and app, so it runs in tests, benchmarks or a
+//! dedicated server as well as under the game's window and interface.
+
+pub mod ai;
+pub mod army;
+#[doc(hidden)]
+pub mod bench;
+pub mod buildings;
+pub mod colonization;
+pub mod consts;
+pub mod country;
+pub mod diplomacy;
+pub mod elevation;
+pub mod errors;
+pub mod forts;
+pub mod hex;
+pub mod hot_reload;
+pub mod manpower;
+pub mod map;
+pub mod menu;
+pub mod modifiers;
+pub mod mods;
+pub mod net;
+pub mod notifications;
+pub mod player;
+pub mod player_command;
+pub mod releasables;
+pub mod religion;
+pub mod rules;
+pub mod savegame;
+pub mod scoring;
+pub mod scripting;
+pub mod storage;
+pub mod supply;
+pub mod terrain;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+pub mod trade;
+pub mod turns;
+pub mod units;
+pub mod war;
+pub mod weather;
+pub mod world;
+
+use crate::ai::AiPlugin;
+use crate::army::ArmyPlugin;
+use crate::buildings::BuildingsPlugin;
+use crate::colonization::ColonizationPlugin;
+use crate::country::CountryPlugin;
+use crate::diplomacy::DiplomacyPlugin;
+use crate::forts::FortsPlugin;
+use crate::hot_reload::HotReloadPlugin;
+use crate::manpower::ManpowerPlugin;
+use crate::map::MapPlugin;
+use crate::modifiers::ModifiersPlugin;
+use crate::mods::ModsPlugin;
+use crate::net::MultiplayerPlugin;
+use crate::player::PlayerPlugin;
+use crate::player_command::PlayerCommandPlugin;
+use crate::releasables::ReleasablesPlugin;
+use crate::religion::ReligionPlugin;
+use crate::rules::GameRulesPlugin;
+use crate::savegame::SaveGamePlugin;
+use crate::scoring::ScoringPlugin;
+use crate::scripting::ScriptingPlugin;
+use crate::supply::SupplyPlugin;
+use crate::terrain::TerrainPlugin;
+use crate::trade::TradePlugin;
+use crate::turns::TurnsPlugin;
+use crate::units::UnitsPlugin;
+use crate::war::WarPlugin;
+use crate::weather::WeatherPlugin;
+use crate::world::WorldPlugin;
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+pub use crate::mods::{MODS_ASSET_SOURCE, MODS_DIRECTORY};
+
+/// The game itself: the world, countries, armies, wars, turns, saves, scripts and multiplayer.
+pub struct SimulationPlugins;
+
+impl PluginGroup for SimulationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(ModsPlugin)
+            .add(HotReloadPlugin)
+            .add(WorldPlugin)
+            .add(GameRulesPlugin)
+            .add(UnitsPlugin)
+            .add(TerrainPlugin)
+            .add(MapPlugin)
+            .add(CountryPlugin)
+            .add(PlayerPlugin)
+            .add(ArmyPlugin)
+            .add(WarPlugin)
+            .add(DiplomacyPlugin)
+            .add(FortsPlugin)
+            .add(AiPlugin)
+            .add(ReligionPlugin)
+            .add(ReleasablesPlugin)
+            .add(PlayerCommandPlugin)
+            .add(TurnsPlugin)
+            .add(ModifiersPlugin)
+            .add(WeatherPlugin)
+            .add(SupplyPlugin)
+            .add(ManpowerPlugin)
+            .add(TradePlugin)
+            .add(ColonizationPlugin)
+            .add(BuildingsPlugin)
+            .add(SaveGamePlugin)
+            .add(ScoringPlugin)
+            .add(ScriptingPlugin)
+            .add(MultiplayerPlugin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::army::Army;
+    use crate::map::Province;
+    use crate::menu::MenuState;
+    use crate::turns::{GameState, Turn};
+
+    #[test]
+    fn simulation_runs_without_a_renderer() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, SimulationPlugins));
+        // The world is generated from the map on the first frame.
+        app.update();
+        let world = app.world_mut();
+        assert!(world.query::<&Province>().iter(world).count() > 0);
+        assert!(world.query::<&Army>().iter(world).count() > 0);
+
+        app.world_mut()
+            .resource_mut::<NextState<MenuState>>()
+            .set(MenuState::InGame);
+        app.update();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Processing);
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<Turn>().current_turn(), 1);
+    }
+}
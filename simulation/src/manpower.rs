@@ -0,0 +1,120 @@
+﻿use crate::army::{Army, ArmyComposition, HexPos, InBattle, REGIMENT_SIZE};
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::turns::{GameState, TurnSet};
+use crate::war::Occupied;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+pub struct ManpowerPlugin;
+
+impl Plugin for ManpowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Processing),
+            (
+                grow_manpower,
+                reinforce_armies.after(crate::army::resolve_battles),
+            )
+                .chain()
+                .in_set(TurnSet::Resolve),
+        );
+    }
+}
+
+/// Manpower a country starts the game with, enough to raise a few regiments.
+pub const STARTING_MANPOWER: u32 = 5 * REGIMENT_SIZE;
+/// Manpower each province a country owns and holds adds to its pool per turn.
+const MANPOWER_PER_PROVINCE: u32 = 100;
+/// The pool stops growing at this much manpower per province.
+const MAX_MANPOWER_PER_PROVINCE: u32 = 2 * REGIMENT_SIZE;
+/// Soldiers a regiment gets back per turn in friendly territory, until it is at full strength.
+const REINFORCEMENT_PER_REGIMENT: u32 = REGIMENT_SIZE / 10;
+
+/// Men a country can raise regiments from and refill its armies with.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Manpower(pub u32);
+
+impl Default for Manpower {
+    fn default() -> Self {
+        Manpower(STARTING_MANPOWER)
+    }
+}
+
+/// Grows the manpower of countries from the provinces they own that aren't occupied, up to what
+/// those provinces can sustain.
+pub fn grow_manpower(
+    provinces: Query<&Owner, (With<Province>, Without<Occupied>)>,
+    mut pools: Query<&mut Manpower>,
+) {
+    let mut held: HashMap<Entity, u32> = HashMap::new();
+    for owner in &provinces {
+        *held.entry(owner.0).or_default() += 1;
+    }
+    for (country, provinces) in held {
+        let Ok(mut manpower) = pools.get_mut(country) else {
+            continue;
+        };
+        let cap = provinces * MAX_MANPOWER_PER_PROVINCE;
+        if manpower.0 < cap {
+            manpower.0 = (manpower.0 + provinces * MANPOWER_PER_PROVINCE).min(cap);
+        }
+    }
+}
+
+/// Refills the depleted regiments of armies resting in provinces their country holds, drawing on
+/// its manpower.
+pub fn reinforce_armies(
+    province_hex_map: Res<ProvinceHexMap>,
+    provinces: Query<&Owner, (With<Province>, Without<Occupied>)>,
+    mut armies: Query<(&HexPos, &Owner, &mut ArmyComposition), (With<Army>, Without<InBattle>)>,
+    mut pools: Query<&mut Manpower>,
+) {
+    for (pos, owner, mut composition) in &mut armies {
+        let friendly = province_hex_map
+            .get_entity(&pos.0)
+            .and_then(|&province| provinces.get(province).ok())
+            .is_some_and(|province_owner| province_owner.0 == owner.0);
+        if !friendly {
+            continue;
+        }
+        let Ok(mut manpower) = pools.get_mut(owner.0) else {
+            continue;
+        };
+        let added = composition.reinforce(REINFORCEMENT_PER_REGIMENT, manpower.0);
+        manpower.0 -= added;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::test_utils::TestGame;
+    use crate::units::UnitType;
+
+    #[test]
+    fn armies_reinforce_at_home_from_manpower() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        game.spawn_province("Abroad", Hex::new(1, 0), None);
+        let abroad = game.spawn_army(country, Hex::new(1, 0), 2 * REGIMENT_SIZE - 500);
+        let home = game.spawn_army(country, Hex::new(0, 0), 2 * REGIMENT_SIZE - 500);
+        game.world_mut().get_mut::<Manpower>(country).unwrap().0 = 150;
+
+        game.end_turn();
+
+        // The pool grows to 250 and two regiments take 100 each.
+        let infantry = UnitType::new("infantry");
+        let soldiers =
+            |game: &TestGame, army| game.get::<ArmyComposition>(army).unwrap().get(&infantry);
+        assert_eq!(soldiers(&game, home), 2 * REGIMENT_SIZE - 300);
+        assert_eq!(soldiers(&game, abroad), 2 * REGIMENT_SIZE - 500);
+        assert_eq!(game.get::<Manpower>(country), Some(&Manpower(50)));
+
+        // Reinforcements stop at full strength.
+        game.world_mut().get_mut::<Manpower>(country).unwrap().0 = 10_000;
+        game.end_turns(5);
+        assert_eq!(soldiers(&game, home), 2 * REGIMENT_SIZE);
+    }
+}
@@ -0,0 +1,717 @@
+﻿use crate::buildings::Income;
+use crate::consts;
+use crate::country::DisplayName;
+use crate::elevation;
+use crate::errors::{GameError, report_errors};
+use crate::hex::Hex;
+use crate::hot_reload::{DataFileChangedEvent, WatchedDataFiles};
+use crate::mods::VirtualFs;
+use crate::releasables::Releasables;
+use crate::religion::Faith;
+use crate::rules::GameRules;
+use crate::terrain::{Terrain, TerrainDef, TerrainRegistry};
+use crate::world::GenerateWorld;
+use bevy::color::Color;
+use bevy::log::info;
+use bevy::prelude::{
+    Changed, Commands, Component, Entity, MessageReader, Query, RemovedComponents, Res, ResMut,
+    Resource, Result, SystemSet, Vec2, With, warn,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub struct MapPlugin;
+
+impl bevy::prelude::Plugin for MapPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        use bevy::prelude::*;
+        app.insert_resource(ProvinceHexMap::default())
+            .add_systems(
+                GenerateWorld,
+                generate_map.pipe(report_errors).in_set(MapGeneration),
+            )
+            .add_systems(Update, watch_map_file.run_if(resource_changed::<GameRules>))
+            .add_systems(Update, reload_provinces.run_if(resource_exists::<MapData>))
+            .add_systems(Update, rename_provinces.after(reload_provinces));
+    }
+}
+
+/// Capital hex of every country. Countries don't have a designated capital, so the owned province
+/// closest to the middle of the country's territory is used.
+pub fn capitals<'a>(
+    provinces: impl IntoIterator<Item = (&'a Province, &'a Owner)>,
+) -> HashMap<Entity, Hex> {
+    let mut territories: HashMap<Entity, Vec<Hex>> = HashMap::new();
+    for (province, owner) in provinces {
+        territories.entry(owner.0).or_default().push(province.hex);
+    }
+    territories
+        .into_iter()
+        .filter_map(|(country, hexes)| {
+            let positions = hexes.iter().map(|hex| hex.axial_to_world(consts::HEX_SIZE));
+            let center = positions.sum::<Vec2>() / hexes.len() as f32;
+            let capital = hexes.into_iter().min_by(|a, b| {
+                let a = a.axial_to_world(consts::HEX_SIZE).distance_squared(center);
+                let b = b.axial_to_world(consts::HEX_SIZE).distance_squared(center);
+                a.total_cmp(&b)
+            })?;
+            Some((country, capital))
+        })
+        .collect()
+}
+
+/// System set building the provinces from the map file, the rest of the world is generated after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MapGeneration;
+
+/// Resource mapping hex coordinates to province entities. Allows clicking on hex tiles to find
+/// the corresponding province.
+#[derive(Resource, Default)]
+pub struct ProvinceHexMap {
+    tiles: HashMap<Hex, Entity>,
+}
+
+impl ProvinceHexMap {
+    pub fn get_entity(&self, hex: &Hex) -> Option<&Entity> {
+        self.tiles.get(hex)
+    }
+
+    pub fn insert(&mut self, hex: Hex, province: Entity) {
+        self.tiles.insert(hex, province);
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+}
+
+#[derive(Component, PartialEq)]
+pub struct Owner(pub Entity);
+
+/// What a province is called in the map file.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ProvinceNames {
+    /// Identifies the province to scripts and mods whatever it's called at the moment.
+    pub key: String,
+    pub default: String,
+    /// Names the province takes under particular owners, by country name.
+    pub by_owner: BTreeMap<String, String>,
+}
+
+impl ProvinceNames {
+    /// Name of the province under the owner, its default name when the owner has none for it.
+    pub fn under(&self, owner: Option<&str>) -> &str {
+        owner
+            .and_then(|owner| self.by_owner.get(owner))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Component representing a province on the map.
+#[derive(Component)]
+pub struct Province {
+    /// Name the province is shown by, what its owner calls it.
+    name: String,
+    names: ProvinceNames,
+    hex: Hex,
+    terrain: TerrainDef,
+    /// Height above sea level in meters.
+    elevation: f32,
+}
+
+impl Province {
+    /// Creates a province at its terrain's typical elevation.
+    pub fn new(name: &str, hex: Hex, terrain: TerrainDef) -> Self {
+        Self {
+            name: name.to_string(),
+            names: ProvinceNames {
+                key: name.to_string(),
+                default: name.to_string(),
+                by_owner: BTreeMap::new(),
+            },
+            hex,
+            elevation: terrain.elevation,
+            terrain,
+        }
+    }
+
+    pub fn with_names(mut self, names: ProvinceNames) -> Self {
+        self.set_names(names, None);
+        self
+    }
+
+    pub fn with_elevation(mut self, elevation: f32) -> Self {
+        self.elevation = elevation;
+        self
+    }
+
+    /// Returns the color associated with the province's terrain type, shaded by its elevation.
+    pub fn color(&self) -> Color {
+        elevation::shade(self.terrain.color(), self.elevation)
+    }
+
+    /// Returns the name of the province.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name scripts and mods know the province by, it doesn't change with its owner.
+    pub fn key(&self) -> &str {
+        &self.names.key
+    }
+
+    /// Replaces the names of the province, showing the one of the owner.
+    pub fn set_names(&mut self, names: ProvinceNames, owner: Option<&str>) {
+        self.names = names;
+        self.rename_for(owner);
+    }
+
+    /// Shows the province by the name its owner calls it.
+    pub fn rename_for(&mut self, owner: Option<&str>) {
+        let name = self.names.under(owner);
+        if self.name != name {
+            self.name = name.to_string();
+        }
+    }
+
+    /// Returns a reference to the hex coordinates of the province.
+    pub fn get_hex(&self) -> &Hex {
+        &self.hex
+    }
+
+    /// Returns the terrain type of the province.
+    pub fn terrain(&self) -> &TerrainDef {
+        &self.terrain
+    }
+
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    pub fn set_terrain(&mut self, terrain: TerrainDef) {
+        self.terrain = terrain;
+    }
+
+    /// Determines if the province can be owned by a country based on its terrain type.
+    pub fn is_ownable(&self) -> bool {
+        self.terrain.ownable
+    }
+    pub fn is_passable(&self) -> bool {
+        self.terrain.passable
+    }
+    pub fn is_navigable(&self) -> bool {
+        self.terrain.navigable
+    }
+    pub fn base_income(&self) -> f32 {
+        self.terrain.income
+    }
+}
+
+/// Path to the default map file
+pub const MAP_FILE_PATH: &str = "maps/map.json";
+
+/// JSON structures for map loading
+#[derive(Deserialize)]
+pub struct MapFile {
+    pub countries: Vec<CountryDef>,
+    provinces: Vec<ProvinceDef>,
+    #[serde(default)]
+    pub releasables: Vec<ReleasableDef>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CountryDef {
+    pub name: String,
+    pub color: [f32; 3],
+    pub flag: String,
+    #[serde(default)]
+    pub faith: Faith,
+}
+
+/// Nation missing from the map at the start that can be released from its provinces, given by
+/// their keys. The first province is its capital.
+#[derive(Deserialize, Clone)]
+pub struct ReleasableDef {
+    #[serde(flatten)]
+    pub country: CountryDef,
+    pub provinces: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ProvinceDef {
+    #[serde(flatten)]
+    hex: Hex,
+    terrain: String,
+    name: String,
+    /// Identifies the province to scripts and mods, its name when missing.
+    #[serde(default)]
+    key: Option<String>,
+    /// Names the province takes under particular owners, e.g. exonyms, by country name.
+    #[serde(default)]
+    names: BTreeMap<String, String>,
+    owner: Option<String>,
+    /// Overrides the base income of the province's terrain.
+    #[serde(default)]
+    income: Option<f32>,
+    /// Elevation in meters, generated from the terrain when missing.
+    #[serde(default)]
+    elevation: Option<f32>,
+}
+
+impl ProvinceDef {
+    fn income(&self, province: &Province) -> f32 {
+        self.income.unwrap_or_else(|| province.base_income())
+    }
+
+    fn names(&self) -> ProvinceNames {
+        ProvinceNames {
+            key: self.key.clone().unwrap_or_else(|| self.name.clone()),
+            default: self.name.clone(),
+            by_owner: self.names.clone(),
+        }
+    }
+}
+
+/// Resource storing loaded map data for use by other systems
+#[derive(Resource, Default)]
+pub struct MapData {
+    pub countries: Vec<CountryDef>,
+    pub province_owners: HashMap<Hex, String>,
+}
+
+/// Load map from JSON file, taken from the enabled mods if they provide it
+pub fn load_map_from_file(vfs: &VirtualFs, map_path: &str) -> Option<MapFile> {
+    match read_map_file(vfs, map_path) {
+        Ok((map, _)) => Some(map),
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    }
+}
+
+/// Reads and parses the map file, returning its text along with it for [`validate_map`].
+fn read_map_file(vfs: &VirtualFs, map_path: &str) -> Result<(MapFile, String), String> {
+    let content = match vfs.read_to_string(map_path) {
+        Ok(content) => content,
+        Err(e) => {
+            // Print current directory for debugging
+            if let Ok(cwd) = std::env::current_dir() {
+                warn!("Current working directory: {:?}", cwd);
+            }
+            return Err(format!(
+                "Could not find or load map file '{}': {}",
+                map_path, e
+            ));
+        }
+    };
+
+    match serde_json::from_str(&content) {
+        Ok(map) => {
+            info!(
+                "Successfully loaded map from '{:?}'",
+                vfs.resolve(map_path).unwrap_or_default()
+            );
+            Ok((map, content))
+        }
+        // The error tells the line and column
+        Err(e) => Err(format!("Failed to parse map file '{}': {}", map_path, e)),
+    }
+}
+
+/// Mistake in the map file, at the line of the country or province it is about.
+#[derive(Debug, PartialEq)]
+pub struct MapProblem {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Finds the mistakes of a map the game can still be played on, but likely not as intended:
+/// provinces sharing a hex, unknown owners and terrain, missing flags and land no army can reach.
+fn validate_map(
+    map: &MapFile,
+    content: &str,
+    terrains: &TerrainRegistry,
+    vfs: &VirtualFs,
+) -> Vec<MapProblem> {
+    let country_lines = element_lines(content, "countries");
+    let province_lines = element_lines(content, "provinces");
+    let releasable_lines = element_lines(content, "releasables");
+    let line = |lines: &[usize], index: usize| lines.get(index).copied().unwrap_or(1);
+    let mut problems = Vec::new();
+    let mut problem = |line: usize, message: String| problems.push(MapProblem { line, message });
+
+    // Assets are embedded or fetched on the web, only the files on disk can be checked.
+    if !cfg!(target_arch = "wasm32") {
+        let countries = map
+            .countries
+            .iter()
+            .enumerate()
+            .map(|(index, def)| (def, line(&country_lines, index)));
+        let releasables = map
+            .releasables
+            .iter()
+            .enumerate()
+            .map(|(index, def)| (&def.country, line(&releasable_lines, index)));
+        for (def, line) in countries.chain(releasables) {
+            if vfs.resolve(&def.flag).is_none() {
+                problem(
+                    line,
+                    format!("Flag {} of {} is missing", def.flag, def.name),
+                );
+            }
+        }
+    }
+
+    let country_names: HashSet<&str> = map.countries.iter().map(|c| c.name.as_str()).collect();
+    let mut provinces_by_hex: HashMap<Hex, &ProvinceDef> = HashMap::new();
+    for (index, prov_def) in map.provinces.iter().enumerate() {
+        let line = line(&province_lines, index);
+        if let Some(other) = provinces_by_hex.insert(prov_def.hex, prov_def) {
+            problem(
+                line,
+                format!(
+                    "{} is on hex ({}, {}), which {} is on already",
+                    prov_def.name,
+                    prov_def.hex.q(),
+                    prov_def.hex.r(),
+                    other.name
+                ),
+            );
+        }
+        if let Some(owner) = &prov_def.owner
+            && !country_names.contains(owner.as_str())
+        {
+            problem(
+                line,
+                format!("{} is owned by unknown country {}", prov_def.name, owner),
+            );
+        }
+        if terrains.get(&prov_def.terrain).is_none() {
+            problem(
+                line,
+                format!(
+                    "{} has unknown terrain {}, plains are used instead",
+                    prov_def.name, prov_def.terrain
+                ),
+            );
+        }
+    }
+
+    // Land is reachable when a country starts on it, or fleets can carry armies to its coast.
+    let plains = Terrain::Plains.def();
+    let terrain_by_hex: HashMap<Hex, &TerrainDef> = provinces_by_hex
+        .iter()
+        .map(|(hex, def)| (*hex, terrains.get(&def.terrain).unwrap_or(&plains)))
+        .collect();
+    let passable = |hex: &Hex| terrain_by_hex.get(hex).is_some_and(|t| t.passable);
+    let navigable = |hex: &Hex| terrain_by_hex.get(hex).is_some_and(|t| t.navigable);
+    let mut visited: HashSet<Hex> = HashSet::new();
+    for (index, prov_def) in map.provinces.iter().enumerate() {
+        if visited.contains(&prov_def.hex) || !passable(&prov_def.hex) {
+            continue;
+        }
+        let mut land = vec![prov_def.hex];
+        visited.insert(prov_def.hex);
+        let mut next = 0;
+        while let Some(hex) = land.get(next).copied() {
+            next += 1;
+            for neighbor in hex.neighbors() {
+                if passable(&neighbor) && visited.insert(neighbor) {
+                    land.push(neighbor);
+                }
+            }
+        }
+        let reachable = land.iter().any(|hex| {
+            provinces_by_hex[hex].owner.is_some() || hex.neighbors().iter().any(navigable)
+        });
+        if !reachable {
+            problem(
+                line(&province_lines, index),
+                format!(
+                    "No army can reach {} and the {} other province(s) of its land",
+                    prov_def.name,
+                    land.len() - 1
+                ),
+            );
+        }
+    }
+
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+/// Lines on which the elements of an array at the top of a JSON document start, the text having
+/// been parsed already.
+fn element_lines(content: &str, key: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut string = String::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    // The key is the last string before the value at the top.
+    let mut last_string = String::new();
+    let mut in_array = false;
+    for c in content.chars() {
+        if c == '\n' {
+            line += 1;
+        }
+        if in_string {
+            match c {
+                _ if escaped => {
+                    escaped = false;
+                    string.push(c);
+                }
+                '\\' => escaped = true,
+                '"' => {
+                    in_string = false;
+                    if depth == 1 {
+                        last_string = std::mem::take(&mut string);
+                    }
+                }
+                _ => string.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                string.clear();
+            }
+            '{' | '[' => {
+                if depth == 1 {
+                    in_array = c == '[' && last_string == key;
+                } else if depth == 2 && in_array {
+                    lines.push(line);
+                }
+                depth += 1;
+            }
+            '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// System to generate a hex map of provinces at startup from JSON file.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_map(
+    mut commands: Commands,
+    mut hex_map: ResMut<ProvinceHexMap>,
+    rules: Res<GameRules>,
+    terrains: Res<TerrainRegistry>,
+    vfs: Res<VirtualFs>,
+) -> Result<(), GameError> {
+    let (map_file, content) = match read_map_file(&vfs, &rules.map_path) {
+        Ok(map) => map,
+        Err(e) => {
+            // Don't let the rest of the world generation run on a previous map's data.
+            commands.remove_resource::<MapData>();
+            return Err(GameError::fatal(
+                "Map could not be loaded",
+                format!(
+                    "{}. Check that the game's assets are complete.",
+                    e.trim_end_matches('.')
+                ),
+            ));
+        }
+    };
+    let problems = validate_map(&map_file, &content, &terrains, &vfs);
+
+    let mut province_owners = HashMap::new();
+
+    for prov_def in &map_file.provinces {
+        let hex = prov_def.hex;
+        let terrain = terrains.resolve(&prov_def.terrain);
+
+        // Store owner info for later assignment
+        if let Some(owner) = &prov_def.owner {
+            province_owners.insert(hex, owner.clone());
+        }
+
+        let elevation = prov_def
+            .elevation
+            .unwrap_or_else(|| elevation::generate(hex, terrain.elevation));
+        let province = Province::new(&prov_def.name, hex, terrain)
+            .with_names(prov_def.names())
+            .with_elevation(elevation);
+        let income = prov_def.income(&province);
+
+        let province_id = commands.spawn((province, Income::new(income))).id();
+
+        hex_map.insert(hex, province_id);
+    }
+
+    // Insert map data resource for use by country system
+    commands.insert_resource(MapData {
+        countries: map_file.countries,
+        province_owners,
+    });
+    commands.insert_resource(Releasables(map_file.releasables));
+
+    info!("Map generation complete: {} provinces", hex_map.tiles.len());
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let details: Vec<String> = problems
+        .iter()
+        .map(|problem| format!("{}:{}: {}", rules.map_path, problem.line, problem.message))
+        .collect();
+    Err(GameError::new(
+        "Problems in the map",
+        format!(
+            "The game can go on, but may not play as the map intends.\n\n{}",
+            details.join("\n")
+        ),
+    ))
+}
+
+fn watch_map_file(
+    rules: Res<GameRules>,
+    vfs: Res<VirtualFs>,
+    mut watched: ResMut<WatchedDataFiles>,
+) {
+    watched.watch(&vfs, &rules.map_path);
+}
+
+/// Re-applies province names and incomes when the map file changes. Terrain and ownership are
+/// part of the running game and only change when a new game is started.
+fn reload_provinces(
+    mut events: MessageReader<DataFileChangedEvent>,
+    rules: Res<GameRules>,
+    vfs: Res<VirtualFs>,
+    hex_map: Res<ProvinceHexMap>,
+    mut provinces: Query<(&mut Province, &mut Income, Option<&Owner>)>,
+    countries: Query<&DisplayName>,
+) {
+    if !events.read().any(|event| event.0 == rules.map_path) {
+        return;
+    }
+    let Some(map_file) = load_map_from_file(&vfs, &rules.map_path) else {
+        return;
+    };
+
+    for prov_def in &map_file.provinces {
+        let hex = prov_def.hex;
+        let Some((mut province, mut income, owner)) = hex_map
+            .get_entity(&hex)
+            .and_then(|&entity| provinces.get_mut(entity).ok())
+        else {
+            warn!(
+                "Province {} was added to the map, start a new game to see it",
+                prov_def.name
+            );
+            continue;
+        };
+
+        if province.terrain().id != prov_def.terrain {
+            warn!(
+                "Terrain of {} changed, start a new game to apply it",
+                prov_def.name
+            );
+        }
+        let names = prov_def.names();
+        if province.names != names {
+            let owner = owner.and_then(|owner| countries.get(owner.0).ok());
+            province.set_names(names, owner.map(|name| name.0.as_str()));
+        }
+        let new_income = prov_def.income(&province);
+        if income.get() != new_income {
+            income.set(new_income);
+        }
+    }
+    info!("Reloaded provinces from {}", rules.map_path);
+}
+
+/// Renames the provinces that changed hands to what their new owners call them.
+fn rename_provinces(
+    mut provinces: Query<(&mut Province, Option<&Owner>)>,
+    changed_hands: Query<Entity, (With<Province>, Changed<Owner>)>,
+    mut lost_owners: RemovedComponents<Owner>,
+    countries: Query<&DisplayName>,
+) {
+    let renamed: Vec<Entity> = changed_hands.iter().chain(lost_owners.read()).collect();
+    for entity in renamed {
+        let Ok((mut province, owner)) = provinces.get_mut(entity) else {
+            continue;
+        };
+        let owner = owner.and_then(|owner| countries.get(owner.0).ok());
+        province.rename_for(owner.map(|name| name.0.as_str()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn provinces_go_by_the_name_their_owner_calls_them() {
+        let mut game = TestGame::new();
+        let italy = game.spawn_country("Italy");
+        let france = game.spawn_country("France");
+        let roma = game.spawn_province("Roma", Hex::new(0, 0), Some(italy));
+        let names = ProvinceNames {
+            key: "Roma".to_string(),
+            default: "Roma".to_string(),
+            by_owner: BTreeMap::from([("France".to_string(), "Rome".to_string())]),
+        };
+        game.world_mut()
+            .get_mut::<Province>(roma)
+            .unwrap()
+            .set_names(names, Some("Italy"));
+
+        game.world_mut().entity_mut(roma).insert(Owner(france));
+        game.app.update();
+        let province = game.get::<Province>(roma).unwrap();
+        assert_eq!((province.name(), province.key()), ("Rome", "Roma"));
+
+        // Unowned provinces go by their default name.
+        game.world_mut().entity_mut(roma).remove::<Owner>();
+        game.app.update();
+        assert_eq!(game.get::<Province>(roma).unwrap().name(), "Roma");
+    }
+
+    #[test]
+    fn map_problems_are_reported_at_their_lines() {
+        let content = r#"{
+  "countries": [
+    { "name": "Italy", "color": [0.0, 0.6, 0.3], "flag": "flags/italy.png" },
+    { "name": "Atlantis", "color": [0.1, 0.2, 0.9], "flag": "flags/atlantis.png" }
+  ],
+  "provinces": [
+    { "q": 0, "r": 0, "terrain": "Plains", "name": "Roma", "owner": "Italy" },
+    { "q": 1, "r": 0, "terrain": "Plains", "name": "Napoli", "owner": "Italia" },
+    { "q": 1, "r": 0, "terrain": "Swamp", "name": "Capri" },
+    { "q": 2, "r": 0, "terrain": "Wasteland", "name": "Etna" },
+    { "q": 3, "r": 0, "terrain": "Hills", "name": "Oasis" },
+    { "q": 4, "r": 0, "terrain": "Hills", "name": "Mirage" }
+  ]
+}"#;
+        let vfs = VirtualFs::new(&[]);
+        let map: MapFile = serde_json::from_str(content).unwrap();
+        let problems = validate_map(&map, content, &TerrainRegistry::load(&vfs), &vfs);
+
+        let problems: Vec<(usize, &str)> = problems
+            .iter()
+            .map(|problem| (problem.line, problem.message.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (4, "Flag flags/atlantis.png of Atlantis is missing"),
+                (8, "Napoli is owned by unknown country Italia"),
+                (9, "Capri is on hex (1, 0), which Napoli is on already"),
+                (
+                    9,
+                    "Capri has unknown terrain Swamp, plains are used instead"
+                ),
+                (
+                    11,
+                    "No army can reach Oasis and the 1 other province(s) of its land"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,18 @@
+﻿use bevy::prelude::*;
+
+/// Screen the game is on. Leaving a game for the main menu resets the world, see
+/// [`crate::world`].
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MenuState {
+    #[default]
+    MainMenu,
+    CountrySelection,
+    GameSetup,
+    InGame,
+    ModSelection,
+}
+
+/// Whether the pause menu is open. The game holds still meanwhile, see
+/// [`crate::turns::Modals`].
+#[derive(Resource)]
+pub struct PauseMenuOpen(pub bool);
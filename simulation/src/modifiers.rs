@@ -0,0 +1,322 @@
+﻿use crate::buildings::{Building, Income};
+use crate::map::Province;
+use crate::turns::{GameState, TurnSet};
+use crate::war::{Occupied, SiegeProgress};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct ModifiersPlugin;
+
+impl Plugin for ModifiersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Processing),
+            (tick_modifiers, devastate_provinces)
+                .after(crate::turns::handle_new_turn)
+                .in_set(TurnSet::Resolve),
+        );
+    }
+}
+
+/// Share of its income a province loses per point of unrest.
+const UNREST_INCOME_PENALTY: f32 = 0.1;
+/// Share of an occupied province's income its occupier collects, its owner collects none.
+pub const OCCUPIED_INCOME_SHARE: f32 = 0.5;
+/// Devastation a province suffers every turn it's besieged or occupied.
+const DEVASTATION_PER_TURN: u32 = 10;
+/// Devastation a province recovers from every turn of peace.
+const DEVASTATION_RECOVERY: u32 = 5;
+/// Devastation of a province laid waste entirely, it yields no income at all.
+const MAX_DEVASTATION: u32 = 100;
+
+/// Effect on a province's income lasting a number of turns, added by events.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Modifier {
+    pub name: String,
+    /// Added to the province's income multiplier, -0.5 halves its income.
+    pub income: f32,
+    pub turns_left: u32,
+}
+
+/// Modifiers currently applying to a province.
+#[derive(Component, Default)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+/// Discontent in a province. Every point costs it [`UNREST_INCOME_PENALTY`] of its income, and it
+/// calms down by a point every turn.
+#[derive(Component, Default)]
+pub struct Unrest(pub u32);
+
+/// Damage sieges and occupations did to a province, in percent of its income lost. It grows by
+/// [`DEVASTATION_PER_TURN`] while the province is besieged or occupied, and recovers slowly after.
+#[derive(Component, Default)]
+pub struct Devastation(pub u32);
+
+impl Devastation {
+    fn income_multiplier(&self) -> f32 {
+        1.0 - self.0.min(MAX_DEVASTATION) as f32 / MAX_DEVASTATION as f32
+    }
+}
+
+/// Lasting effect on a whole country, e.g. the handicaps chosen in the game setup.
+#[derive(Clone, Debug)]
+pub struct CountryModifier {
+    pub name: String,
+    /// Added to the income multiplier of each of the country's provinces.
+    pub income: f32,
+    /// Multiplies how belligerent the country's AI is, 1.0 leaves it unchanged.
+    pub aggression: f32,
+}
+
+/// Modifiers currently applying to a country.
+#[derive(Component, Default)]
+pub struct CountryModifiers(pub Vec<CountryModifier>);
+
+impl CountryModifiers {
+    pub fn income(&self) -> f32 {
+        self.0.iter().map(|m| m.income).sum()
+    }
+
+    pub fn aggression(&self) -> f32 {
+        self.0.iter().map(|m| m.aggression).product()
+    }
+
+    /// Replaces the modifier of the same name, or adds it.
+    pub fn set(&mut self, modifier: CountryModifier) {
+        self.0.retain(|m| m.name != modifier.name);
+        self.0.push(modifier);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|m| m.name != name);
+    }
+}
+
+/// Aggression of a country under its modifiers, 1.0 for countries without any.
+pub fn aggression(modifiers: Option<&CountryModifiers>) -> f32 {
+    modifiers.map_or(1.0, CountryModifiers::aggression)
+}
+
+/// Multiplier of a province's income under its modifiers, its unrest and the modifiers of its
+/// owner, never below zero.
+pub fn income_multiplier(
+    modifiers: Option<&Modifiers>,
+    unrest: Option<&Unrest>,
+    country: Option<&CountryModifiers>,
+) -> f32 {
+    let modifiers: f32 = modifiers
+        .map(|modifiers| modifiers.0.iter().map(|m| m.income).sum())
+        .unwrap_or(0.0);
+    let country = country.map_or(0.0, CountryModifiers::income);
+    let unrest = unrest.map_or(0, |unrest| unrest.0) as f32 * UNREST_INCOME_PENALTY;
+    (1.0 + modifiers + country - unrest).max(0.0)
+}
+
+/// What a province yields per turn and who collects it.
+pub struct ProvinceIncome {
+    /// The occupier of the province, or its owner.
+    pub collector: Entity,
+    /// Income of the province itself under its modifiers and unrest.
+    pub base: f32,
+    /// Income of its buildings.
+    pub buildings: f32,
+    /// Share of it left by the occupation and devastation of the province.
+    pub war_multiplier: f32,
+}
+
+impl ProvinceIncome {
+    pub fn total(&self) -> f32 {
+        (self.base + self.buildings) * self.war_multiplier
+    }
+
+    /// What the country collects from the province.
+    pub fn collected_by(&self, country: Entity) -> f32 {
+        if self.collector == country {
+            self.total()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Everything a province's income depends on beyond the province itself. The turn's income and
+/// the UI both go through [`IncomeSources::province`], so that they agree.
+#[derive(SystemParam)]
+pub struct IncomeSources<'w, 's> {
+    building_incomes: Query<'w, 's, &'static Income, With<Building>>,
+    country_modifiers: Query<'w, 's, &'static CountryModifiers>,
+}
+
+impl IncomeSources<'_, '_> {
+    /// Income of a province of `owner`. Occupied provinces pay [`OCCUPIED_INCOME_SHARE`] of it to
+    /// their occupier instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn province(
+        &self,
+        owner: Entity,
+        income: &Income,
+        children: Option<&Children>,
+        modifiers: Option<&Modifiers>,
+        unrest: Option<&Unrest>,
+        occupied: Option<&Occupied>,
+        devastation: Option<&Devastation>,
+    ) -> ProvinceIncome {
+        let country = self.country_modifiers.get(owner).ok();
+        let buildings: f32 = children
+            .into_iter()
+            .flatten()
+            .filter_map(|&child| self.building_incomes.get(child).ok())
+            .map(Income::get)
+            .sum();
+        let occupation = if occupied.is_some() {
+            OCCUPIED_INCOME_SHARE
+        } else {
+            1.0
+        };
+        ProvinceIncome {
+            collector: occupied.map_or(owner, |o| o.occupier),
+            base: income.get() * income_multiplier(modifiers, unrest, country),
+            buildings: buildings * income_multiplier(None, None, country),
+            war_multiplier: occupation * devastation.map_or(1.0, Devastation::income_multiplier),
+        }
+    }
+}
+
+/// Counts down the modifiers of every province and lets unrest calm down, once the turn's income
+/// was paid.
+pub fn tick_modifiers(mut modifiers: Query<&mut Modifiers>, mut unrest: Query<&mut Unrest>) {
+    for mut modifiers in &mut modifiers {
+        modifiers.0.retain_mut(|modifier| {
+            modifier.turns_left = modifier.turns_left.saturating_sub(1);
+            modifier.turns_left > 0
+        });
+    }
+    for mut unrest in &mut unrest {
+        if unrest.0 > 0 {
+            unrest.0 -= 1;
+        }
+    }
+}
+
+/// Lays waste to besieged and occupied provinces and lets the others recover, once the turn's
+/// income was paid.
+#[allow(clippy::type_complexity)]
+pub fn devastate_provinces(
+    mut commands: Commands,
+    mut provinces: Query<
+        (
+            Entity,
+            Has<Occupied>,
+            Has<SiegeProgress>,
+            Option<&mut Devastation>,
+        ),
+        With<Province>,
+    >,
+) {
+    for (entity, occupied, besieged, devastation) in &mut provinces {
+        let ravaged = occupied || besieged;
+        match devastation {
+            Some(mut devastation) if ravaged => {
+                devastation.0 = (devastation.0 + DEVASTATION_PER_TURN).min(MAX_DEVASTATION);
+            }
+            Some(mut devastation) => {
+                devastation.0 = devastation.0.saturating_sub(DEVASTATION_RECOVERY);
+            }
+            None if ravaged => {
+                commands
+                    .entity(entity)
+                    .insert(Devastation(DEVASTATION_PER_TURN));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::map::Province;
+    use crate::rules::{Difficulty, GameRules, Handicap};
+    use crate::terrain::Terrain;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn modifiers_and_unrest_change_income_until_they_wear_off() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let province = game.spawn_province("Province", Hex::new(0, 0), Some(country));
+        game.world_mut().entity_mut(province).insert((
+            Modifiers(vec![Modifier {
+                name: "Good harvest".to_string(),
+                income: 0.5,
+                turns_left: 1,
+            }]),
+            Unrest(2),
+        ));
+        let income = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        assert!((game.ducats(country) - 1.3 * income).abs() < 1e-4);
+        assert!(game.get::<Modifiers>(province).unwrap().0.is_empty());
+
+        game.end_turn();
+        assert!((game.ducats(country) - 2.2 * income).abs() < 1e-4);
+        assert_eq!(game.get::<Unrest>(province).unwrap().0, 0);
+    }
+
+    #[test]
+    fn handicaps_and_difficulty_raise_country_income() {
+        let mut game = TestGame::new();
+        {
+            let mut rules = game.world_mut().resource_mut::<GameRules>();
+            rules.difficulty = Difficulty::Hard;
+            rules.handicaps.insert(
+                "Favored".to_string(),
+                Handicap {
+                    income_bonus: 0.5,
+                    ..default()
+                },
+            );
+        }
+        let favored = game.spawn_country("Favored");
+        let rival = game.spawn_country("Rival");
+        game.spawn_province("Favored", Hex::new(0, 0), Some(favored));
+        game.spawn_province("Rival", Hex::new(2, 0), Some(rival));
+        let income = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        assert!((game.ducats(favored) - 1.75 * income).abs() < 1e-4);
+        assert!((game.ducats(rival) - 1.25 * income).abs() < 1e-4);
+    }
+
+    #[test]
+    fn occupied_provinces_pay_their_occupier_and_stay_devastated() {
+        let mut game = TestGame::new();
+        let owner = game.spawn_country("Owner");
+        let occupier = game.spawn_country("Occupier");
+        let province = game.spawn_province("Province", Hex::new(0, 0), Some(owner));
+        game.world_mut()
+            .entity_mut(province)
+            .insert(Occupied { occupier });
+        let income = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        assert_eq!(game.ducats(owner), 0.0);
+        assert!((game.ducats(occupier) - OCCUPIED_INCOME_SHARE * income).abs() < 1e-4);
+        assert_eq!(
+            game.get::<Devastation>(province).unwrap().0,
+            DEVASTATION_PER_TURN
+        );
+
+        // Freed, the province pays its owner again, less what it lost to devastation.
+        game.world_mut().entity_mut(province).remove::<Occupied>();
+        game.end_turn();
+        assert!((game.ducats(owner) - 0.9 * income).abs() < 1e-4);
+        assert_eq!(
+            game.get::<Devastation>(province).unwrap().0,
+            DEVASTATION_PER_TURN - DEVASTATION_RECOVERY
+        );
+    }
+}
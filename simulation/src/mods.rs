@@ -0,0 +1,293 @@
+﻿use crate::storage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct ModsPlugin;
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        let available = find_mods();
+        let enabled = load_enabled_mods(&available);
+        app.insert_resource(VirtualFs::new(&enabled.0))
+            .insert_resource(AvailableMods(available))
+            .insert_resource(enabled);
+    }
+}
+
+/// Directory holding the base game data.
+#[cfg(not(test))]
+const ASSETS_DIRECTORY: &str = "assets";
+/// Tests run from the crate's directory, the game data sits at the workspace root.
+#[cfg(test)]
+const ASSETS_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets");
+/// Directory scanned for mods. Every subdirectory is a mod laid out like the assets directory.
+#[cfg(not(test))]
+pub const MODS_DIRECTORY: &str = "mods";
+#[cfg(test)]
+pub const MODS_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../mods");
+/// Name of the asset source serving files from [`MODS_DIRECTORY`] to the asset server.
+pub const MODS_ASSET_SOURCE: &str = "mods";
+/// Optional file in a mod's directory describing it.
+const MOD_INFO_FILE: &str = "mod.json";
+/// File storing the enabled mods in load order.
+const ENABLED_MODS_FILE_PATH: &str = "enabled_mods.json";
+
+// Base game data compiled into web builds, which can't read the assets directory from disk. The
+// build script lists every data file in the directory as `EMBEDDED_DATA`, pairs of paths relative
+// to it and file contents.
+#[cfg(target_arch = "wasm32")]
+include!(concat!(env!("OUT_DIR"), "/embedded_data.rs"));
+
+/// Description of a mod, read from its [`MOD_INFO_FILE`].
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ModInfo {
+    /// Name of the mod's directory, used to refer to it in the load order.
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+/// Every mod found in [`MODS_DIRECTORY`], sorted by id.
+#[derive(Resource, Default)]
+pub struct AvailableMods(pub Vec<ModInfo>);
+
+/// Ids of the enabled mods in load order: files of later mods override those of earlier ones.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct EnabledMods(pub Vec<String>);
+
+/// Layered view over the base game data and the enabled mods. Every data file is loaded through
+/// it with a path relative to the assets directory (e.g. `maps/map.json`), and is read from the
+/// last enabled mod providing it, falling back to the base game.
+#[derive(Resource, Clone)]
+pub struct VirtualFs {
+    /// Enabled mods in load order, the base game is always the bottom layer.
+    mods: Vec<String>,
+}
+
+impl VirtualFs {
+    pub fn new(mods: &[String]) -> Self {
+        Self {
+            mods: mods.to_vec(),
+        }
+    }
+
+    pub fn mods(&self) -> &[String] {
+        &self.mods
+    }
+
+    /// Layers from the topmost mod down to the base game, with the mod owning each of them.
+    fn layers(&self) -> impl Iterator<Item = (Option<&str>, PathBuf)> {
+        self.mods
+            .iter()
+            .rev()
+            .map(|id| (Some(id.as_str()), Path::new(MODS_DIRECTORY).join(id)))
+            .chain(std::iter::once((None, PathBuf::from(ASSETS_DIRECTORY))))
+    }
+
+    /// Returns the real path of the file and the mod providing it, `None` for the base game.
+    fn find(&self, path: &str) -> Option<(Option<&str>, PathBuf)> {
+        // Paths saved by older versions still start with the assets directory.
+        let path = path.trim_start_matches("assets/");
+        self.layers()
+            .map(|(id, root)| (id, root.join(path)))
+            .find(|(_, file)| file.is_file())
+    }
+
+    /// Real path of the file on disk, taken from the topmost layer providing it.
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        self.find(path).map(|(_, file)| file)
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(content) = embedded(path) {
+            return Ok(content.as_bytes().to_vec());
+        }
+        let file = self
+            .resolve(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))?;
+        fs::read(file)
+    }
+
+    /// Reads a text file, without its byte order mark if it has one.
+    pub fn read_to_string(&self, path: &str) -> io::Result<String> {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(content) = embedded(path) {
+            return Ok(content.trim_start_matches('\u{feff}').to_string());
+        }
+        let file = self
+            .resolve(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))?;
+        let content = fs::read_to_string(file)?;
+        Ok(content.trim_start_matches('\u{feff}').to_string())
+    }
+
+    /// Lists the files with the given extension in a directory of every layer, as sorted virtual
+    /// paths. Mods can add files to a directory as well as replace them.
+    pub fn list(&self, directory: &str, extension: &str) -> Vec<String> {
+        let mut files: Vec<String> = self
+            .layers()
+            .filter_map(|(_, root)| fs::read_dir(root.join(directory)).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == extension))
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                Some(format!("{}/{}", directory, name))
+            })
+            .collect();
+        #[cfg(target_arch = "wasm32")]
+        files.extend(
+            EMBEDDED_DATA
+                .iter()
+                .filter(|(file, _)| file.starts_with(&format!("{}/", directory)))
+                .filter(|(file, _)| file.ends_with(&format!(".{}", extension)))
+                .map(|(file, _)| file.to_string()),
+        );
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Path to hand to the asset server for an image or sound, pointing into the mod providing it.
+    pub fn asset_path(&self, path: &str) -> String {
+        match self.find(path) {
+            Some((Some(id), _)) => format!(
+                "{}://{}/{}",
+                MODS_ASSET_SOURCE,
+                id,
+                path.trim_start_matches("assets/")
+            ),
+            _ => path.to_string(),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn embedded(path: &str) -> Option<&'static str> {
+    let path = path.trim_start_matches("assets/");
+    EMBEDDED_DATA
+        .iter()
+        .find(|(file, _)| *file == path)
+        .map(|(_, content)| *content)
+}
+
+/// Scans [`MODS_DIRECTORY`] for mods. Mods without a valid info file are named after their
+/// directory.
+fn find_mods() -> Vec<ModInfo> {
+    let Ok(entries) = fs::read_dir(MODS_DIRECTORY) else {
+        return Vec::new();
+    };
+
+    let mut mods: Vec<ModInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let id = path.file_name()?.to_string_lossy().into_owned();
+            let info = fs::read_to_string(path.join(MOD_INFO_FILE))
+                .ok()
+                .and_then(|content| {
+                    serde_json::from_str::<ModInfo>(content.trim_start_matches('\u{feff}'))
+                        .map_err(|e| warn!("Failed to parse info of mod {}: {}", id, e))
+                        .ok()
+                })
+                .unwrap_or_default();
+            Some(ModInfo {
+                name: if info.name.is_empty() {
+                    id.clone()
+                } else {
+                    info.name
+                },
+                id,
+                ..info
+            })
+        })
+        .collect();
+    mods.sort_by(|a, b| a.id.cmp(&b.id));
+    info!("Found {} mods", mods.len());
+    mods
+}
+
+/// Loads the load order, dropping mods that are no longer installed.
+fn load_enabled_mods(available: &[ModInfo]) -> EnabledMods {
+    let Ok(content) = storage::read(ENABLED_MODS_FILE_PATH) else {
+        return EnabledMods::default();
+    };
+    let enabled: EnabledMods = serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Failed to parse enabled mods, starting without mods: {}", e);
+        EnabledMods::default()
+    });
+    let installed = enabled
+        .0
+        .into_iter()
+        .filter(|id| {
+            let found = available.iter().any(|m| &m.id == id);
+            if !found {
+                warn!("Enabled mod {} is not installed", id);
+            }
+            found
+        })
+        .collect::<Vec<_>>();
+    info!("Enabled mods: {:?}", installed);
+    EnabledMods(installed)
+}
+
+pub fn save_enabled_mods(enabled: &EnabledMods) {
+    match serde_json::to_string_pretty(enabled) {
+        Ok(json) => {
+            if let Err(e) = storage::write(ENABLED_MODS_FILE_PATH, &json) {
+                error!("Failed to write enabled mods: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to serialize enabled mods: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mods_add_files_on_top_of_the_base_game() {
+        let vfs = VirtualFs::new(&["example_mod".to_string()]);
+        assert_eq!(
+            vfs.list("scripts", "rhai"),
+            vec![
+                "scripts/comet_sighted.rhai".to_string(),
+                "scripts/example_war_chest.rhai".to_string(),
+                "scripts/good_harvest.rhai".to_string(),
+                "scripts/heir_born.rhai".to_string(),
+                "scripts/plague.rhai".to_string(),
+                "scripts/scottish_independence.rhai".to_string(),
+                "scripts/spanish_silver_fleet.rhai".to_string(),
+            ]
+        );
+        assert_eq!(
+            vfs.resolve("scripts/spanish_silver_fleet.rhai"),
+            Some(Path::new(MODS_DIRECTORY).join("example_mod/scripts/spanish_silver_fleet.rhai"))
+        );
+        assert_eq!(
+            vfs.resolve("maps/map.json"),
+            Some(Path::new(ASSETS_DIRECTORY).join("maps/map.json"))
+        );
+        assert_eq!(vfs.asset_path("flags/spain.png"), "flags/spain.png");
+    }
+
+    #[test]
+    fn disabled_mods_are_not_read() {
+        let vfs = VirtualFs::new(&[]);
+        assert_eq!(vfs.resolve("scripts/spanish_silver_fleet.rhai"), None);
+        assert_eq!(
+            vfs.resolve("assets/maps/map.json"),
+            Some(Path::new(ASSETS_DIRECTORY).join("maps/map.json"))
+        );
+    }
+}
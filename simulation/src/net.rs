@@ -0,0 +1,766 @@
+﻿use crate::army::{Army, ArmyComposition, HexPos};
+use crate::country::{Coffer, Country, DisplayName};
+use crate::errors::GameError;
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::menu::MenuState;
+use crate::player::Player;
+use crate::player_command::PlayerCommand;
+use crate::rules::GameRules;
+use crate::turns::{GameState, Modals, Turn};
+use crate::world::RegenerateWorldEvent;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind, Read, Write};
+use std::mem;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub struct MultiplayerPlugin;
+
+impl Plugin for MultiplayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            poll_network
+                .before(crate::player_command::execute_player_commands)
+                .run_if(resource_exists::<NetSession>),
+        )
+        .add_systems(
+            OnTransition {
+                exited: MenuState::GameSetup,
+                entered: MenuState::InGame,
+            },
+            start_multiplayer_game
+                .before(crate::rules::apply_ai_nation_limit)
+                .run_if(resource_exists::<NetSession>),
+        );
+    }
+}
+
+/// Port the host listens on.
+pub const NET_PORT: u16 = 7777;
+
+/// How long joining waits for the host to answer.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Messages exchanged between the two players, one JSON object per line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum NetMessage {
+    /// First message of the joining player, with the country they picked.
+    Join { country: String },
+    /// Answer of the host when the joining player can't take part.
+    Rejected { reason: String },
+    /// Answer of the host with the country they play.
+    Welcome { country: String },
+    /// The host started the game with these rules.
+    Start { rules: GameRules },
+    /// Commands given during a turn, sent when the player ends it. Both games apply the commands
+    /// of both players in the same order before processing the turn, and the deterministic
+    /// simulation keeps them in sync. The checksum of the game the turn started from tells
+    /// whether it did.
+    EndTurn {
+        turn: u32,
+        checksum: u64,
+        orders: Vec<PlayerCommand>,
+    },
+}
+
+/// Connection to the other player, framing [`NetMessage`]s as lines of JSON.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    /// Bytes the socket didn't take yet, written on the next calls.
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Queues the message and sends as much of what is queued as the socket takes.
+    pub fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        self.outgoing.extend(serde_json::to_vec(message)?);
+        self.outgoing.push(b'\n');
+        self.flush()
+    }
+
+    /// Writes queued bytes until the socket would block.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the messages that arrived since the last call, fails once the connection is closed.
+    /// Sends what earlier calls to [`Connection::send`] left queued.
+    pub fn receive(&mut self) -> io::Result<Vec<NetMessage>> {
+        self.flush()?;
+        let mut buffer = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.incoming.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            messages.push(serde_json::from_slice(&line)?);
+        }
+        Ok(messages)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NetRole {
+    Host,
+    Client,
+}
+
+/// Resource present while a multiplayer game is set up or played. The game runs in lockstep: a
+/// turn is only processed once both players ended it, after exchanging their orders.
+///
+/// Every action a player takes is a [`PlayerCommand`], so all of them are exchanged.
+#[derive(Resource)]
+pub struct NetSession {
+    role: NetRole,
+    listener: Option<TcpListener>,
+    connection: Option<Connection>,
+    peer_country: Option<String>,
+    /// Set on the client when the host started the game, the game starts on the next frame so the
+    /// world can be regenerated for the host's map first.
+    start_pending: bool,
+    /// Commands the local player gave since ending the last turn.
+    local_orders: Vec<PlayerCommand>,
+    /// Set when the local player ends the turn, their orders are sent on the next poll.
+    local_ready: bool,
+    local_turn: Option<EndedTurn>,
+    peer_turn: Option<EndedTurn>,
+}
+
+/// What a player sent on ending the turn.
+struct EndedTurn {
+    checksum: u64,
+    orders: Vec<PlayerCommand>,
+}
+
+impl NetSession {
+    fn new(role: NetRole, listener: Option<TcpListener>, connection: Option<Connection>) -> Self {
+        Self {
+            role,
+            listener,
+            connection,
+            peer_country: None,
+            start_pending: false,
+            local_orders: Vec::new(),
+            local_ready: false,
+            local_turn: None,
+            peer_turn: None,
+        }
+    }
+
+    /// Starts listening for the other player.
+    pub fn host() -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", NET_PORT))?;
+        listener.set_nonblocking(true)?;
+        info!("Hosting a multiplayer game on port {}", NET_PORT);
+        Ok(Self::new(NetRole::Host, Some(listener), None))
+    }
+
+    /// Connects to a host, the port defaults to [`NET_PORT`].
+    pub fn join(address: &str, country: &str) -> io::Result<Self> {
+        let address = resolve_address(address)?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        let mut connection = Connection::new(stream)?;
+        connection.send(&NetMessage::Join {
+            country: country.to_string(),
+        })?;
+        info!("Joined the multiplayer game at {}", address);
+        Ok(Self::new(NetRole::Client, None, Some(connection)))
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.role == NetRole::Client
+    }
+
+    /// Whether the local player ended the turn and waits for the other player.
+    pub fn waiting_for_peer(&self) -> bool {
+        self.local_ready
+    }
+
+    /// Ends the local player's turn, the orders they gave during it are sent on the next poll.
+    pub fn end_turn(&mut self) {
+        self.local_ready = true;
+    }
+
+    /// Holds a command of the local player back until both players ended the turn. Commands given
+    /// while waiting for the other player are part of the next turn.
+    pub fn give(&mut self, command: PlayerCommand) {
+        self.local_orders.push(command);
+    }
+
+    /// Commands of the local player not applied yet, sent or still to be sent.
+    pub fn queued(&self) -> impl Iterator<Item = &PlayerCommand> {
+        self.local_turn
+            .iter()
+            .flat_map(|turn| &turn.orders)
+            .chain(&self.local_orders)
+    }
+
+    /// Takes the turns of both players once both ended it, the host's first.
+    fn take_turns(&mut self) -> Option<(EndedTurn, EndedTurn)> {
+        if self.local_turn.is_none() || self.peer_turn.is_none() {
+            return None;
+        }
+        let local = self.local_turn.take()?;
+        let peer = self.peer_turn.take()?;
+        self.local_ready = false;
+        Some(match self.role {
+            NetRole::Host => (local, peer),
+            NetRole::Client => (peer, local),
+        })
+    }
+
+    /// Every order of the local player not applied yet, for when the other player is gone.
+    fn take_local_orders(&mut self) -> Vec<PlayerCommand> {
+        let mut orders = self
+            .local_turn
+            .take()
+            .map(|turn| turn.orders)
+            .unwrap_or_default();
+        orders.append(&mut self.local_orders);
+        orders
+    }
+
+    fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        match &mut self.connection {
+            Some(connection) => connection.send(message),
+            None => Err(ErrorKind::NotConnected.into()),
+        }
+    }
+
+    pub fn status(&self) -> String {
+        match (self.role, &self.peer_country) {
+            (NetRole::Host, None) => format!("Waiting for a player on port {}...", NET_PORT),
+            (NetRole::Host, Some(country)) => format!("{} joined the game", country),
+            (NetRole::Client, None) => "Connecting...".to_string(),
+            (NetRole::Client, Some(country)) => {
+                format!("Connected, {} starts the game", country)
+            }
+        }
+    }
+}
+
+fn resolve_address(address: &str) -> io::Result<SocketAddr> {
+    let address = address.trim();
+    let with_port = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, NET_PORT)
+    };
+    with_port
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "unknown address"))
+}
+
+pub fn country_name(
+    countries: &Query<(Entity, &DisplayName), With<Country>>,
+    country: Option<Entity>,
+) -> Option<String> {
+    country
+        .and_then(|country| countries.get(country).ok())
+        .map(|(_, name)| name.0.clone())
+}
+
+fn find_country(
+    countries: &Query<(Entity, &DisplayName), With<Country>>,
+    name: &str,
+) -> Option<Entity> {
+    countries
+        .iter()
+        .find(|(_, country_name)| country_name.0 == name)
+        .map(|(entity, _)| entity)
+}
+
+/// The parts of the game both players must agree on, named the same way in both games.
+#[derive(SystemParam)]
+struct GameChecksum<'w, 's> {
+    turn: Res<'w, Turn>,
+    countries: Query<'w, 's, (Entity, &'static DisplayName, &'static Coffer), With<Country>>,
+    provinces: Query<'w, 's, (&'static Province, Option<&'static Owner>)>,
+    armies: Query<'w, 's, (&'static HexPos, &'static Owner, &'static ArmyComposition), With<Army>>,
+}
+
+impl GameChecksum<'_, '_> {
+    fn compute(&self) -> u64 {
+        let hex = |hex: Hex| (hex.q(), hex.r());
+        let name = |country: Entity| {
+            self.countries
+                .get(country)
+                .ok()
+                .map(|(_, name, _)| name.0.as_str())
+        };
+        let mut countries: Vec<(&str, u32)> = self
+            .countries
+            .iter()
+            .map(|(_, name, coffer)| (name.0.as_str(), coffer.0.to_bits()))
+            .collect();
+        let mut provinces: Vec<_> = self
+            .provinces
+            .iter()
+            .map(|(province, owner)| (hex(*province.get_hex()), owner.and_then(|o| name(o.0))))
+            .collect();
+        let mut armies: Vec<_> = self
+            .armies
+            .iter()
+            .map(|(pos, owner, composition)| (hex(pos.0), name(owner.0), composition.total_size()))
+            .collect();
+        // Queries don't return entities in the same order in both games.
+        countries.sort_unstable();
+        provinces.sort_unstable();
+        armies.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        (self.turn.current_turn(), countries, provinces, armies).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Accepts the other player, handles their messages and processes the turn once both players
+/// ended it.
+#[allow(clippy::too_many_arguments)]
+fn poll_network(
+    mut commands: Commands,
+    mut session: ResMut<NetSession>,
+    mut rules: ResMut<GameRules>,
+    mut player: ResMut<Player>,
+    turn: Res<Turn>,
+    checksum: GameChecksum,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    mut player_commands: MessageWriter<PlayerCommand>,
+    mut regenerate_events: MessageWriter<RegenerateWorldEvent>,
+    mut errors: MessageWriter<GameError>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    modals: Modals,
+) {
+    if session.start_pending {
+        session.start_pending = false;
+        next_menu_state.set(MenuState::InGame);
+    }
+
+    if session.connection.is_none()
+        && let Some(listener) = &session.listener
+    {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                info!("Player connected from {}", address);
+                match Connection::new(stream) {
+                    Ok(connection) => session.connection = Some(connection),
+                    Err(e) => warn!("Failed to set up the connection: {}", e),
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => warn!("Failed to accept a player: {}", e),
+        }
+    }
+
+    let received = match session.connection.as_mut().map(|c| c.receive()) {
+        Some(Ok(messages)) => messages,
+        Some(Err(e)) => {
+            errors.write(GameError::new(
+                "Connection lost",
+                format!(
+                    "The connection to the other player was lost ({}). Their country is now played by the AI.",
+                    e
+                ),
+            ));
+            player.remote_countries.clear();
+            player_commands.write_batch(session.take_local_orders());
+            if session.local_ready {
+                next_game_state.set(GameState::Processing);
+            }
+            commands.remove_resource::<NetSession>();
+            return;
+        }
+        None => Vec::new(),
+    };
+
+    for message in received {
+        match message {
+            NetMessage::Join { country } => {
+                let own_country = country_name(&countries, player.country);
+                let reply = if own_country.as_deref() == Some(country.as_str()) {
+                    NetMessage::Rejected {
+                        reason: format!("{} is already played by the host", country),
+                    }
+                } else if find_country(&countries, &country).is_none() {
+                    NetMessage::Rejected {
+                        reason: format!("{} isn't on the host's map", country),
+                    }
+                } else {
+                    session.peer_country = Some(country);
+                    NetMessage::Welcome {
+                        country: own_country.unwrap_or_default(),
+                    }
+                };
+                let rejected = matches!(reply, NetMessage::Rejected { .. });
+                if let Err(e) = session.send(&reply) {
+                    warn!("Failed to answer the joining player: {}", e);
+                }
+                if rejected {
+                    // Keep listening for another player.
+                    session.connection = None;
+                }
+            }
+            NetMessage::Rejected { reason } => {
+                errors.write(GameError::new("Couldn't join the game", reason));
+                commands.remove_resource::<NetSession>();
+                return;
+            }
+            NetMessage::Welcome { country } => {
+                session.peer_country = Some(country);
+            }
+            NetMessage::Start { rules: host_rules } => {
+                if host_rules.map_path != rules.map_path {
+                    regenerate_events.write(RegenerateWorldEvent);
+                }
+                *rules = host_rules;
+                session.start_pending = true;
+            }
+            NetMessage::EndTurn {
+                turn: peer_turn,
+                checksum,
+                mut orders,
+            } => {
+                if peer_turn != turn.current_turn() {
+                    warn!(
+                        "Other player ended turn {} during turn {}",
+                        peer_turn,
+                        turn.current_turn()
+                    );
+                }
+                // Each player only gives commands for their own country.
+                let peer_country = session.peer_country.clone();
+                orders.retain(|order| {
+                    let own = Some(order.country()) == peer_country.as_deref();
+                    if !own {
+                        warn!("Ignoring a command for another country: {:?}", order);
+                    }
+                    own
+                });
+                session.peer_turn = Some(EndedTurn { checksum, orders });
+            }
+        }
+    }
+
+    if session.local_ready && session.local_turn.is_none() {
+        let ended = EndedTurn {
+            checksum: checksum.compute(),
+            orders: mem::take(&mut session.local_orders),
+        };
+        let message = NetMessage::EndTurn {
+            turn: turn.current_turn(),
+            checksum: ended.checksum,
+            orders: ended.orders.clone(),
+        };
+        if let Err(e) = session.send(&message) {
+            warn!("Failed to send the turn's orders: {}", e);
+        }
+        session.local_turn = Some(ended);
+    }
+
+    // The turn waits for the player to close the pause menu or answer the open offers.
+    if modals.open(&player, Some(&session)) {
+        return;
+    }
+    if let Some((host, client)) = session.take_turns() {
+        if host.checksum != client.checksum {
+            errors.write(GameError::new(
+                "Games out of sync",
+                format!(
+                    "The games of both players differed at the start of turn {}. Play goes on, \
+                     but from now on each player may see a different game.",
+                    turn.current_turn()
+                ),
+            ));
+        }
+        player_commands.write_batch(host.orders.into_iter().chain(client.orders));
+        next_game_state.set(GameState::Processing);
+    }
+}
+
+/// Hands the other player's country to them before the AI nation limit is applied, and tells the
+/// client to start. A host starting alone plays a normal single player game.
+fn start_multiplayer_game(
+    mut commands: Commands,
+    mut session: ResMut<NetSession>,
+    rules: Res<GameRules>,
+    mut player: ResMut<Player>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+) {
+    let Some(peer_country) = session.peer_country.clone() else {
+        info!("Nobody joined, starting a single player game");
+        commands.remove_resource::<NetSession>();
+        return;
+    };
+
+    player.remote_countries = find_country(&countries, &peer_country)
+        .into_iter()
+        .collect();
+    session.listener = None;
+    if session.role == NetRole::Host
+        && let Err(e) = session.send(&NetMessage::Start {
+            rules: rules.clone(),
+        })
+    {
+        warn!("Failed to start the other player's game: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::{EventScope, PendingEvent, Scripts};
+    use crate::test_utils::TestGame;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn connections_exchange_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        let mut host = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        let join = NetMessage::Join {
+            country: "France".to_string(),
+        };
+        let end_turn = NetMessage::EndTurn {
+            turn: 3,
+            checksum: 42,
+            orders: vec![PlayerCommand::MoveArmy {
+                country: "France".to_string(),
+                from: Hex::new(0, 0),
+                to: Hex::new(2, -1),
+            }],
+        };
+        client.send(&join).unwrap();
+        client.send(&end_turn).unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(host.receive().unwrap());
+            if received.len() == 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received, vec![join, end_turn]);
+
+        drop(client);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(host.receive().is_err());
+    }
+
+    #[test]
+    fn messages_larger_than_the_socket_buffer_arrive_whole() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        let mut host = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        let end_turn = NetMessage::EndTurn {
+            turn: 1,
+            checksum: 0,
+            orders: (0..100_000)
+                .map(|q| PlayerCommand::MoveArmy {
+                    country: "France".to_string(),
+                    from: Hex::new(q, 0),
+                    to: Hex::new(q + 1, 0),
+                })
+                .collect(),
+        };
+        client.send(&end_turn).unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            // Polling the client sends the rest as the host reads.
+            client.receive().unwrap();
+            received.extend(host.receive().unwrap());
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, vec![end_turn]);
+    }
+
+    #[test]
+    fn both_games_apply_the_host_orders_first() {
+        let join = |country: &str| PlayerCommand::JoinLeague {
+            country: country.to_string(),
+        };
+        let ended = |country: &str| EndedTurn {
+            checksum: 1,
+            orders: vec![join(country)],
+        };
+        let mut host = NetSession::new(NetRole::Host, None, None);
+        let mut client = NetSession::new(NetRole::Client, None, None);
+        host.local_turn = Some(ended("Spain"));
+        client.local_turn = Some(ended("France"));
+        assert!(host.take_turns().is_none());
+
+        host.peer_turn = Some(ended("France"));
+        client.peer_turn = Some(ended("Spain"));
+        for session in [&mut host, &mut client] {
+            let (first, second) = session.take_turns().unwrap();
+            assert_eq!(first.orders, vec![join("Spain")]);
+            assert_eq!(second.orders, vec![join("France")]);
+        }
+    }
+
+    fn country_named(game: &mut TestGame, name: &str) -> Entity {
+        game.world_mut()
+            .query::<(Entity, &DisplayName)>()
+            .iter(game.world())
+            .find(|(_, country)| country.0 == name)
+            .map(|(entity, _)| entity)
+            .expect("the country should exist")
+    }
+
+    /// A game of France, played by the host, against Spain, played by the client.
+    fn two_player_games() -> (TestGame, TestGame) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        let host = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        let setup = |role, connection, own: &str, peer: &str| {
+            let mut game = TestGame::new();
+            let france = game.spawn_country("France");
+            let spain = game.spawn_country("Spain");
+            game.spawn_province("Paris", Hex::new(0, 0), Some(france));
+            game.spawn_province("Madrid", Hex::new(1, 0), Some(spain));
+            *game.world_mut().resource_mut::<Player>() = Player {
+                country: Some(country_named(&mut game, own)),
+                remote_countries: vec![country_named(&mut game, peer)],
+            };
+            let mut session = NetSession::new(role, None, Some(connection));
+            session.peer_country = Some(peer.to_string());
+            game.world_mut().insert_resource(session);
+            game
+        };
+        (
+            setup(NetRole::Host, host, "France", "Spain"),
+            setup(NetRole::Client, client, "Spain", "France"),
+        )
+    }
+
+    /// Runs both games until both are at the turn, or gives up after a while.
+    fn play_until_turn(host: &mut TestGame, client: &mut TestGame, turn: u32) {
+        let current = |game: &TestGame| game.world().resource::<Turn>().current_turn();
+        for _ in 0..100 {
+            host.app.update();
+            client.app.update();
+            if current(host) == turn && current(client) == turn {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!(
+            "the games are at turns {} and {} instead of {}",
+            current(host),
+            current(client),
+            turn
+        );
+    }
+
+    #[test]
+    fn answered_events_dont_hold_up_the_turn() {
+        let (mut host, mut client) = two_player_games();
+        for game in [&mut host, &mut client] {
+            let plague = game
+                .world()
+                .resource::<Scripts>()
+                .scripts
+                .iter()
+                .find(|script| script.name == "Plague")
+                .cloned()
+                .expect("the plague event should be loaded");
+            let france = country_named(game, "France");
+            game.world_mut().spawn(PendingEvent {
+                country: france,
+                script: plague,
+                scope: EventScope {
+                    country: "France".to_string(),
+                    province: "Paris".to_string(),
+                },
+            });
+        }
+
+        let mut session = host.world_mut().resource_mut::<NetSession>();
+        session.give(PlayerCommand::ChooseEventOption {
+            country: "France".to_string(),
+            event: "Plague".to_string(),
+            option: 0,
+        });
+        session.end_turn();
+        client.world_mut().resource_mut::<NetSession>().end_turn();
+
+        play_until_turn(&mut host, &mut client, 1);
+        for game in [&mut host, &mut client] {
+            assert_eq!(game.count::<PendingEvent>(), 0);
+        }
+    }
+
+    #[test]
+    fn checksums_only_differ_between_different_games() {
+        let checksum = |game: &mut TestGame| {
+            game.world_mut()
+                .run_system_once(|checksum: GameChecksum| checksum.compute())
+                .unwrap()
+        };
+        let mut host = TestGame::new();
+        let france = host.spawn_country("France");
+        let spain = host.spawn_country("Spain");
+        host.spawn_province("Paris", Hex::new(0, 0), Some(france));
+        host.spawn_army(spain, Hex::new(0, 0), 3000);
+        // The same game, with entities spawned in another order.
+        let mut client = TestGame::new();
+        let spain = client.spawn_country("Spain");
+        client.spawn_army(spain, Hex::new(0, 0), 3000);
+        let france = client.spawn_country("France");
+        client.spawn_province("Paris", Hex::new(0, 0), Some(france));
+        assert_eq!(checksum(&mut host), checksum(&mut client));
+
+        client
+            .world_mut()
+            .get_mut::<Coffer>(france)
+            .unwrap()
+            .add_ducats(1.0);
+        assert_ne!(checksum(&mut host), checksum(&mut client));
+    }
+}
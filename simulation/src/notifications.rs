@@ -0,0 +1,49 @@
+﻿use crate::hex::Hex;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Maximum number of notifications kept on screen, older ones are dropped first.
+const MAX_NOTIFICATIONS: usize = 5;
+
+/// What a notification's "Go to" button should select and center the camera on. Armies keep the
+/// hex they were at, so the province can be shown instead once the army is gone.
+#[derive(Clone, Copy)]
+pub enum NotificationTarget {
+    Army { army: Entity, location: Hex },
+    Province(Entity),
+}
+
+pub struct Notification {
+    pub title: String,
+    pub text: String,
+    pub target: Option<NotificationTarget>,
+}
+
+/// Resource holding popups waiting to be dismissed by the player.
+#[derive(Resource, Default)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, notification: Notification) {
+        if self.queue.len() >= MAX_NOTIFICATIONS {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(notification);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Notifications from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Notification> + ExactSizeIterator {
+        self.queue.iter()
+    }
+
+    /// Removes the notification at the index, as counted by [`Notifications::iter`].
+    pub fn dismiss(&mut self, index: usize) {
+        self.queue.remove(index);
+    }
+}
@@ -1,18 +1,30 @@
 ﻿use crate::country::{Country, DisplayName};
+use crate::world::GenerateWorld;
 use bevy::prelude::*;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Player::default())
-            .add_systems(PostStartup, setup_player);
+        app.insert_resource(Player::default()).add_systems(
+            GenerateWorld,
+            setup_player.after(crate::country::assign_province_ownership),
+        );
     }
 }
 
 #[derive(Resource, Default)]
-pub(crate) struct Player {
-    pub(crate) country: Option<Entity>,
+pub struct Player {
+    pub country: Option<Entity>,
+    /// Countries played by other players in a multiplayer game. The AI leaves them alone.
+    pub remote_countries: Vec<Entity>,
+}
+
+impl Player {
+    /// Whether the country is played by a human, here or on the other end of a multiplayer game.
+    pub fn is_human(&self, country: Entity) -> bool {
+        self.country == Some(country) || self.remote_countries.contains(&country)
+    }
 }
 
 fn setup_player(
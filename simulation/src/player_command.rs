@@ -0,0 +1,1709 @@
+﻿use crate::army::{
+    ActivePath, Army, ArmyComposition, ArmyHexMap, Following, HexPos, InBattle, MergeApproved,
+    MoveArmyEvent, PendingMerge, REGIMENT_SIZE, spawn_army,
+};
+use crate::buildings::{Building, BuildingType, has_building, spawn_building};
+use crate::colonization::{COLONIZE_COST, ColonialRange};
+use crate::country::{Coffer, Country, DisplayName, MapColor, colors_clash};
+use crate::diplomacy::{
+    AcceptProvinceOfferEvent, AllianceRequestEvent, Alliances, AnswerCallToArmsEvent,
+    BreakAllianceEvent, CallToArms, MilitaryAccessRequestEvent, ProvinceOffer, ProvinceOfferEvent,
+    ShareVisionEvent,
+};
+use crate::hex::Hex;
+use crate::manpower::Manpower;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::net::NetSession;
+use crate::notifications::{Notification, Notifications};
+use crate::player::Player;
+use crate::releasables::{ReleasableNations, Releasables};
+use crate::religion::{Faith, LeagueMember, Leagues};
+use crate::scripting::{EventOptionChosen, PendingEvent};
+use crate::trade::{Embarked, FLEET_COST, Fleet, FleetHexMap, SeaChart, ShipType, spawn_fleet};
+use crate::turns::Turn;
+use crate::units::{UnitRegistry, UnitType, recruitment_blocked};
+use crate::war::{
+    AcceptPeaceEvent, Conquered, DeclareWarEvent, Occupied, PeaceDemands, PeaceOffer,
+    PeaceOfferEvent, Release, SiegeProgress, Vassal, War, Wars, get_war_between,
+};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub struct PlayerCommandPlugin;
+
+impl Plugin for PlayerCommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlayerCommand>().add_systems(
+            Update,
+            execute_player_commands
+                .before(crate::army::army_movement_system)
+                .before(crate::war::handle_declare_war)
+                .before(crate::war::handle_peace_offers)
+                .before(crate::war::handle_accept_peace)
+                .before(crate::diplomacy::handle_accept_province_offer)
+                .before(crate::diplomacy::handle_call_to_arms_answers),
+        );
+    }
+}
+
+/// Action a player takes in the game. The UI, the other player of a multiplayer game and scripts
+/// all act through these, and [`execute_player_commands`] is the only place applying them.
+/// Countries, provinces and armies are named by what stays the same across games: country names,
+/// province hexes and the army's owner and hex.
+#[derive(Message, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PlayerCommand {
+    /// Moves the army. Moving it onto a neighboring transport of the country boards it, and moving
+    /// an embarked army onto a neighboring land hex lands it.
+    MoveArmy {
+        country: String,
+        from: Hex,
+        to: Hex,
+    },
+    /// Orders the army to follow the friendly army standing on `target`, or to stop following
+    /// when `None`.
+    AttachArmy {
+        country: String,
+        army: Hex,
+        target: Option<Hex>,
+    },
+    /// Cancels the army's march and stops it following another army.
+    StopArmy {
+        country: String,
+        army: Hex,
+    },
+    /// Sends the army to merge into another army of the country, without asking on arrival.
+    MergeArmy {
+        country: String,
+        army: Hex,
+        target: Hex,
+    },
+    /// Detaches soldiers from the army into a new army, which marches off to a free neighboring
+    /// hex during the turn.
+    SplitArmy {
+        country: String,
+        army: Hex,
+        detachment: ArmyComposition,
+    },
+    Recruit {
+        country: String,
+        province: Hex,
+        unit: UnitType,
+    },
+    Build {
+        country: String,
+        province: Hex,
+        building: BuildingType,
+    },
+    DeclareWar {
+        country: String,
+        target: String,
+    },
+    /// Offers peace to an enemy, demanding the provinces and the other terms of [`PeaceDemands`].
+    OfferPeace {
+        country: String,
+        target: String,
+        provinces: Vec<Hex>,
+        #[serde(default)]
+        vassalize: bool,
+        /// Captive nation the target gives its provinces back to, or releasable nation it gives
+        /// up its provinces to.
+        #[serde(default)]
+        release: Option<String>,
+        #[serde(default)]
+        break_alliances: bool,
+    },
+    /// Accepts or declines the peace offer the country got from `from`.
+    AnswerPeace {
+        country: String,
+        from: String,
+        accept: bool,
+    },
+    /// Offers the province to the target country at peace, as a gift when the price is zero.
+    OfferProvince {
+        country: String,
+        target: String,
+        province: Hex,
+        price: f32,
+    },
+    /// Accepts or declines the offer of the province the country got.
+    AnswerProvinceOffer {
+        country: String,
+        province: Hex,
+        accept: bool,
+    },
+    /// Asks the target country to let the country's armies march through its land.
+    RequestMilitaryAccess {
+        country: String,
+        target: String,
+    },
+    /// Asks the target country to become the country's ally.
+    ProposeAlliance {
+        country: String,
+        target: String,
+    },
+    BreakAlliance {
+        country: String,
+        target: String,
+    },
+    /// Joins the war the ally `from` calls the country to against `enemy`, or declines and ends
+    /// the alliance.
+    AnswerCallToArms {
+        country: String,
+        from: String,
+        enemy: String,
+        accept: bool,
+    },
+    /// Starts or stops sharing vision with an ally.
+    ShareVision {
+        country: String,
+        target: String,
+        shared: bool,
+    },
+    BuildFleet {
+        country: String,
+        province: Hex,
+        /// Missing in commands recorded before transports existed, those built galleys.
+        #[serde(default)]
+        ships: ShipType,
+    },
+    MoveFleet {
+        country: String,
+        from: Hex,
+        to: Hex,
+    },
+    SetPrivateering {
+        country: String,
+        fleet: Hex,
+        privateering: bool,
+    },
+    Colonize {
+        country: String,
+        province: Hex,
+    },
+    JoinLeague {
+        country: String,
+    },
+    /// Offers peace on the condition the target tolerates the country's faith, ending the league
+    /// war.
+    EnforceTolerance {
+        country: String,
+        target: String,
+    },
+    /// Picks an option of the country's pending event, by its index.
+    ChooseEventOption {
+        country: String,
+        event: String,
+        option: usize,
+    },
+    /// Paints the country in another sRGB map color, unless it's nearly the color of another
+    /// country.
+    SetMapColor {
+        country: String,
+        color: [f32; 3],
+    },
+}
+
+impl PlayerCommand {
+    /// Name of the country giving the command.
+    pub fn country(&self) -> &str {
+        match self {
+            PlayerCommand::MoveArmy { country, .. }
+            | PlayerCommand::AttachArmy { country, .. }
+            | PlayerCommand::StopArmy { country, .. }
+            | PlayerCommand::MergeArmy { country, .. }
+            | PlayerCommand::SplitArmy { country, .. }
+            | PlayerCommand::Recruit { country, .. }
+            | PlayerCommand::Build { country, .. }
+            | PlayerCommand::DeclareWar { country, .. }
+            | PlayerCommand::OfferPeace { country, .. }
+            | PlayerCommand::AnswerPeace { country, .. }
+            | PlayerCommand::OfferProvince { country, .. }
+            | PlayerCommand::AnswerProvinceOffer { country, .. }
+            | PlayerCommand::RequestMilitaryAccess { country, .. }
+            | PlayerCommand::ProposeAlliance { country, .. }
+            | PlayerCommand::BreakAlliance { country, .. }
+            | PlayerCommand::AnswerCallToArms { country, .. }
+            | PlayerCommand::ShareVision { country, .. }
+            | PlayerCommand::BuildFleet { country, .. }
+            | PlayerCommand::MoveFleet { country, .. }
+            | PlayerCommand::SetPrivateering { country, .. }
+            | PlayerCommand::Colonize { country, .. }
+            | PlayerCommand::JoinLeague { country }
+            | PlayerCommand::EnforceTolerance { country, .. }
+            | PlayerCommand::ChooseEventOption { country, .. }
+            | PlayerCommand::SetMapColor { country, .. } => country,
+        }
+    }
+}
+
+/// Gives [`PlayerCommand`]s on behalf of the UI, which knows countries, armies and provinces by
+/// their entities.
+#[derive(SystemParam)]
+pub struct PlayerCommands<'w, 's> {
+    writer: MessageWriter<'w, PlayerCommand>,
+    countries: Query<'w, 's, &'static DisplayName, With<Country>>,
+    armies: Query<'w, 's, (&'static HexPos, &'static Owner), With<Army>>,
+    fleets: Query<'w, 's, (&'static Fleet, &'static Owner)>,
+    provinces: Query<'w, 's, &'static Province>,
+    peace_offers: Query<'w, 's, &'static PeaceOffer>,
+    province_offers: Query<'w, 's, &'static ProvinceOffer>,
+    calls_to_arms: Query<'w, 's, &'static CallToArms>,
+    releasables: Res<'w, Releasables>,
+    session: Option<ResMut<'w, NetSession>>,
+}
+
+impl PlayerCommands<'_, '_> {
+    /// Sends the command, or in a multiplayer game holds it back until both players ended the turn.
+    fn give(&mut self, command: PlayerCommand) {
+        match &mut self.session {
+            Some(session) => session.give(command),
+            None => {
+                self.writer.write(command);
+            }
+        }
+    }
+
+    /// Whether the local player of a multiplayer game gave a command that waits for the end of
+    /// the turn.
+    pub fn queued(&self, command: impl Fn(&PlayerCommand) -> bool) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|session| session.queued().any(command))
+    }
+
+    pub fn country_name(&self, country: Entity) -> Option<String> {
+        self.countries.get(country).ok().map(|name| name.0.clone())
+    }
+
+    fn province_hex(&self, province: Entity) -> Option<Hex> {
+        self.provinces
+            .get(province)
+            .ok()
+            .map(|province| *province.get_hex())
+    }
+
+    pub fn move_army(&mut self, army: Entity, to: Hex) {
+        let Ok((pos, owner)) = self.armies.get(army) else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::MoveArmy {
+                country,
+                from: pos.0,
+                to,
+            });
+        }
+    }
+
+    pub fn stop_army(&mut self, army: Entity) {
+        let Ok((pos, owner)) = self.armies.get(army) else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::StopArmy {
+                country,
+                army: pos.0,
+            });
+        }
+    }
+
+    pub fn merge_army(&mut self, army: Entity, target: Entity) {
+        let (Ok((pos, owner)), Ok((target_pos, _))) =
+            (self.armies.get(army), self.armies.get(target))
+        else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::MergeArmy {
+                country,
+                army: pos.0,
+                target: target_pos.0,
+            });
+        }
+    }
+
+    pub fn split_army(&mut self, army: Entity, detachment: ArmyComposition) {
+        let Ok((pos, owner)) = self.armies.get(army) else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::SplitArmy {
+                country,
+                army: pos.0,
+                detachment,
+            });
+        }
+    }
+
+    pub fn attach_army(&mut self, army: Entity, target: Option<Entity>) {
+        let Ok((pos, owner)) = self.armies.get(army) else {
+            return;
+        };
+        let target = match target.map(|target| self.armies.get(target)) {
+            Some(Ok((target_pos, _))) => Some(target_pos.0),
+            Some(Err(_)) => return,
+            None => None,
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::AttachArmy {
+                country,
+                army: pos.0,
+                target,
+            });
+        }
+    }
+
+    pub fn recruit(&mut self, country: Entity, province: Entity, unit: UnitType) {
+        if let (Some(country), Some(province)) =
+            (self.country_name(country), self.province_hex(province))
+        {
+            self.give(PlayerCommand::Recruit {
+                country,
+                province,
+                unit,
+            });
+        }
+    }
+
+    pub fn build(&mut self, country: Entity, province: Entity, building: BuildingType) {
+        if let (Some(country), Some(province)) =
+            (self.country_name(country), self.province_hex(province))
+        {
+            self.give(PlayerCommand::Build {
+                country,
+                province,
+                building,
+            });
+        }
+    }
+
+    pub fn build_fleet(&mut self, country: Entity, province: Entity, ships: ShipType) {
+        if let (Some(country), Some(province)) =
+            (self.country_name(country), self.province_hex(province))
+        {
+            self.give(PlayerCommand::BuildFleet {
+                country,
+                province,
+                ships,
+            });
+        }
+    }
+
+    pub fn move_fleet(&mut self, fleet: Entity, to: Hex) {
+        let Ok((fleet, owner)) = self.fleets.get(fleet) else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::MoveFleet {
+                country,
+                from: fleet.hex,
+                to,
+            });
+        }
+    }
+
+    pub fn set_privateering(&mut self, fleet: Entity, privateering: bool) {
+        let Ok((fleet, owner)) = self.fleets.get(fleet) else {
+            return;
+        };
+        if let Some(country) = self.country_name(owner.0) {
+            self.give(PlayerCommand::SetPrivateering {
+                country,
+                fleet: fleet.hex,
+                privateering,
+            });
+        }
+    }
+
+    pub fn colonize(&mut self, country: Entity, province: Entity) {
+        if let (Some(country), Some(province)) =
+            (self.country_name(country), self.province_hex(province))
+        {
+            self.give(PlayerCommand::Colonize { country, province });
+        }
+    }
+
+    pub fn join_league(&mut self, country: Entity) {
+        if let Some(country) = self.country_name(country) {
+            self.give(PlayerCommand::JoinLeague { country });
+        }
+    }
+
+    pub fn enforce_tolerance(&mut self, country: Entity, target: Entity) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::EnforceTolerance { country, target });
+        }
+    }
+
+    pub fn choose_event_option(&mut self, country: Entity, event: &str, option: usize) {
+        if let Some(country) = self.country_name(country) {
+            self.give(PlayerCommand::ChooseEventOption {
+                country,
+                event: event.to_string(),
+                option,
+            });
+        }
+    }
+
+    pub fn set_map_color(&mut self, country: Entity, color: [f32; 3]) {
+        if let Some(country) = self.country_name(country) {
+            self.give(PlayerCommand::SetMapColor { country, color });
+        }
+    }
+
+    pub fn declare_war(&mut self, country: Entity, target: Entity) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::DeclareWar { country, target });
+        }
+    }
+
+    pub fn offer_peace(
+        &mut self,
+        country: Entity,
+        target: Entity,
+        provinces: impl IntoIterator<Item = Entity>,
+        demands: PeaceDemands,
+    ) {
+        let provinces = provinces
+            .into_iter()
+            .filter_map(|province| self.province_hex(province))
+            .collect();
+        let release = demands.release.and_then(|release| match release {
+            Release::Captive(captive) => self.country_name(captive),
+            Release::Nation(nation) => self
+                .releasables
+                .get(nation)
+                .map(|def| def.country.name.clone()),
+        });
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::OfferPeace {
+                country,
+                target,
+                provinces,
+                vassalize: demands.vassalize,
+                release,
+                break_alliances: demands.break_alliances,
+            });
+        }
+    }
+
+    /// Answers a peace offer made to the country.
+    pub fn answer_peace(&mut self, offer: Entity, accept: bool) {
+        let Ok(offer) = self.peace_offers.get(offer) else {
+            return;
+        };
+        if let (Some(country), Some(from)) =
+            (self.country_name(offer.to), self.country_name(offer.from))
+        {
+            self.give(PlayerCommand::AnswerPeace {
+                country,
+                from,
+                accept,
+            });
+        }
+    }
+
+    /// Answers an offer of a province made to the country.
+    pub fn answer_province_offer(&mut self, offer: Entity, accept: bool) {
+        let Ok(offer) = self.province_offers.get(offer) else {
+            return;
+        };
+        if let (Some(country), Some(province)) = (
+            self.country_name(offer.to),
+            self.province_hex(offer.province),
+        ) {
+            self.give(PlayerCommand::AnswerProvinceOffer {
+                country,
+                province,
+                accept,
+            });
+        }
+    }
+
+    pub fn request_military_access(&mut self, country: Entity, target: Entity) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::RequestMilitaryAccess { country, target });
+        }
+    }
+
+    pub fn propose_alliance(&mut self, country: Entity, target: Entity) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::ProposeAlliance { country, target });
+        }
+    }
+
+    pub fn break_alliance(&mut self, country: Entity, target: Entity) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::BreakAlliance { country, target });
+        }
+    }
+
+    /// Answers a call to arms made to the country.
+    pub fn answer_call_to_arms(&mut self, call: Entity, accept: bool) {
+        let Ok(call) = self.calls_to_arms.get(call) else {
+            return;
+        };
+        if let (Some(country), Some(from), Some(enemy)) = (
+            self.country_name(call.to),
+            self.country_name(call.from),
+            self.country_name(call.enemy),
+        ) {
+            self.give(PlayerCommand::AnswerCallToArms {
+                country,
+                from,
+                enemy,
+                accept,
+            });
+        }
+    }
+
+    pub fn share_vision(&mut self, country: Entity, target: Entity, shared: bool) {
+        if let (Some(country), Some(target)) =
+            (self.country_name(country), self.country_name(target))
+        {
+            self.give(PlayerCommand::ShareVision {
+                country,
+                target,
+                shared,
+            });
+        }
+    }
+
+    pub fn offer_province(
+        &mut self,
+        country: Entity,
+        target: Entity,
+        province: Entity,
+        price: f32,
+    ) {
+        if let (Some(country), Some(target), Some(province)) = (
+            self.country_name(country),
+            self.country_name(target),
+            self.province_hex(province),
+        ) {
+            self.give(PlayerCommand::OfferProvince {
+                country,
+                target,
+                province,
+                price,
+            });
+        }
+    }
+}
+
+/// Applies every command given this frame. Commands are checked against the current state of the
+/// game, as one given by the other player of a multiplayer game may no longer be possible here.
+pub fn execute_player_commands(
+    mut player_commands: MessageReader<PlayerCommand>,
+    mut executor: CommandExecutor,
+) {
+    executor.ordered_buildings.clear();
+    executor.boarded_transports.clear();
+    executor.answered.clear();
+    for command in player_commands.read() {
+        if let Err(e) = executor.execute(command) {
+            warn!("Can't execute {:?}: {}", command, e);
+        }
+    }
+}
+
+/// The part of the game [`PlayerCommand`]s change.
+#[derive(SystemParam)]
+pub struct CommandExecutor<'w, 's> {
+    commands: Commands<'w, 's>,
+    countries: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static DisplayName,
+            &'static mut MapColor,
+            &'static mut Coffer,
+        ),
+        With<Country>,
+    >,
+    province_hex_map: Res<'w, ProvinceHexMap>,
+    provinces: Query<'w, 's, (Option<&'static Owner>, Option<&'static Children>), With<Province>>,
+    terrain: Query<'w, 's, &'static Province>,
+    occupied: Query<'w, 's, (), With<Occupied>>,
+    sieges: Query<'w, 's, (), With<SiegeProgress>>,
+    buildings: Query<'w, 's, &'static Building>,
+    army_hex_map: Res<'w, ArmyHexMap>,
+    armies: Query<'w, 's, (&'static Owner, &'static mut ArmyComposition), With<Army>>,
+    manpower: Query<'w, 's, &'static mut Manpower>,
+    fleets: Query<'w, 's, (Entity, &'static Owner, &'static mut Fleet)>,
+    fleet_hex_map: Res<'w, FleetHexMap>,
+    embarked: Query<'w, 's, &'static Embarked>,
+    in_battle: Query<'w, 's, (), With<InBattle>>,
+    sea_chart: Res<'w, SeaChart>,
+    colonial_range: ColonialRange<'w, 's>,
+    faiths: Query<'w, 's, (&'static Faith, Has<LeagueMember>)>,
+    leagues: Res<'w, Leagues>,
+    wars: Res<'w, Wars>,
+    war_query: Query<'w, 's, (Entity, &'static War)>,
+    vassals: Query<'w, 's, &'static Vassal>,
+    conquered: Query<'w, 's, (&'static Owner, &'static Conquered)>,
+    alliances: Query<'w, 's, &'static Alliances>,
+    nations: ReleasableNations<'w, 's>,
+    units: Res<'w, UnitRegistry>,
+    turn: Res<'w, Turn>,
+    move_events: MessageWriter<'w, MoveArmyEvent>,
+    war_events: MessageWriter<'w, DeclareWarEvent>,
+    peace_events: MessageWriter<'w, PeaceOfferEvent>,
+    province_offers: MessageWriter<'w, ProvinceOfferEvent>,
+    access_requests: MessageWriter<'w, MilitaryAccessRequestEvent>,
+    alliance_requests: MessageWriter<'w, AllianceRequestEvent>,
+    broken_alliances: MessageWriter<'w, BreakAllianceEvent>,
+    shared_vision: MessageWriter<'w, ShareVisionEvent>,
+    event_choices: MessageWriter<'w, EventOptionChosen>,
+    player: Res<'w, Player>,
+    notifications: ResMut<'w, Notifications>,
+    /// Buildings ordered this frame, they are only spawned once the frame's commands are applied.
+    ordered_buildings: Local<'s, Vec<(Entity, BuildingType)>>,
+    /// Transports boarded this frame, for the same reason.
+    boarded_transports: Local<'s, Vec<Entity>>,
+    pending_events: Query<'w, 's, (Entity, &'static PendingEvent)>,
+    peace_offers: Query<'w, 's, (Entity, &'static PeaceOffer)>,
+    accept_peace_events: MessageWriter<'w, AcceptPeaceEvent>,
+    offered_provinces: Query<'w, 's, (Entity, &'static ProvinceOffer)>,
+    accept_province_events: MessageWriter<'w, AcceptProvinceOfferEvent>,
+    calls_to_arms: Query<'w, 's, (Entity, &'static CallToArms)>,
+    call_to_arms_answers: MessageWriter<'w, AnswerCallToArmsEvent>,
+    /// Events and offers answered this frame, they are only resolved once the frame's commands are
+    /// applied.
+    answered: Local<'s, Vec<Entity>>,
+}
+
+impl CommandExecutor<'_, '_> {
+    pub fn execute(&mut self, command: &PlayerCommand) -> Result<(), String> {
+        let country = self.find_country(command.country())?;
+        match command {
+            PlayerCommand::MoveArmy { from, to, .. } => self.move_army(country, *from, *to),
+            PlayerCommand::AttachArmy { army, target, .. } => {
+                self.attach_army(country, *army, *target)
+            }
+            PlayerCommand::StopArmy { army, .. } => self.stop_army(country, *army),
+            PlayerCommand::MergeArmy { army, target, .. } => {
+                self.merge_army(country, *army, *target)
+            }
+            PlayerCommand::SplitArmy {
+                army, detachment, ..
+            } => self.split_army(country, *army, detachment),
+            PlayerCommand::Recruit { province, unit, .. } => {
+                self.recruit(country, *province, unit.clone())
+            }
+            PlayerCommand::Build {
+                province, building, ..
+            } => self.build(country, *province, *building),
+            PlayerCommand::DeclareWar { target, .. } => {
+                let target = self.find_country(target)?;
+                self.war_events.write(DeclareWarEvent::new(country, target));
+                Ok(())
+            }
+            PlayerCommand::OfferPeace {
+                target,
+                provinces,
+                vassalize,
+                release,
+                break_alliances,
+                ..
+            } => {
+                let target = self.find_country(target)?;
+                let demands = self.peace_demands(
+                    country,
+                    target,
+                    *vassalize,
+                    release.as_deref(),
+                    *break_alliances,
+                )?;
+                self.offer_peace(country, target, provinces, demands)
+            }
+            PlayerCommand::AnswerPeace { from, accept, .. } => {
+                let from = self.find_country(from)?;
+                self.answer_peace(country, from, *accept)
+            }
+            PlayerCommand::OfferProvince {
+                target,
+                province,
+                price,
+                ..
+            } => self.offer_province(country, target, *province, *price),
+            PlayerCommand::AnswerProvinceOffer {
+                province, accept, ..
+            } => self.answer_province_offer(country, *province, *accept),
+            PlayerCommand::RequestMilitaryAccess { target, .. } => {
+                let target = self.find_country(target)?;
+                if target == country {
+                    return Err("a country can't ask itself for military access".to_string());
+                }
+                self.access_requests.write(MilitaryAccessRequestEvent {
+                    from: country,
+                    to: target,
+                });
+                Ok(())
+            }
+            PlayerCommand::ProposeAlliance { target, .. } => {
+                let target = self.find_country(target)?;
+                if target == country {
+                    return Err("a country can't ally with itself".to_string());
+                }
+                self.alliance_requests.write(AllianceRequestEvent {
+                    from: country,
+                    to: target,
+                });
+                Ok(())
+            }
+            PlayerCommand::BreakAlliance { target, .. } => {
+                let target = self.find_country(target)?;
+                self.broken_alliances.write(BreakAllianceEvent {
+                    from: country,
+                    to: target,
+                });
+                Ok(())
+            }
+            PlayerCommand::AnswerCallToArms {
+                from,
+                enemy,
+                accept,
+                ..
+            } => {
+                let from = self.find_country(from)?;
+                let enemy = self.find_country(enemy)?;
+                self.answer_call_to_arms(country, from, enemy, *accept)
+            }
+            PlayerCommand::ShareVision { target, shared, .. } => {
+                let target = self.find_country(target)?;
+                self.shared_vision.write(ShareVisionEvent {
+                    from: country,
+                    to: target,
+                    shared: *shared,
+                });
+                Ok(())
+            }
+            PlayerCommand::BuildFleet {
+                province, ships, ..
+            } => self.build_fleet(country, *province, *ships),
+            PlayerCommand::MoveFleet { from, to, .. } => {
+                if !self.sea_chart.is_sea(to) {
+                    return Err(format!("fleets can't sail to {:?}", to));
+                }
+                self.country_fleet(country, *from)?.destination = Some(*to);
+                Ok(())
+            }
+            PlayerCommand::SetPrivateering {
+                fleet,
+                privateering,
+                ..
+            } => {
+                let mut fleet = self.country_fleet(country, *fleet)?;
+                if fleet.ships != ShipType::Galley {
+                    return Err("only galleys can go privateering".to_string());
+                }
+                fleet.privateering = *privateering;
+                Ok(())
+            }
+            PlayerCommand::Colonize { province, .. } => self.colonize(country, *province),
+            PlayerCommand::JoinLeague { .. } => self.join_league(country),
+            PlayerCommand::EnforceTolerance { target, .. } => {
+                self.enforce_tolerance(country, target)
+            }
+            PlayerCommand::ChooseEventOption { event, option, .. } => {
+                self.choose_event_option(country, event, *option)
+            }
+            PlayerCommand::SetMapColor { color, .. } => self.set_map_color(country, *color),
+        }
+    }
+
+    fn find_country(&self, name: &str) -> Result<Entity, String> {
+        self.countries
+            .iter()
+            .find(|(_, country_name, _, _)| country_name.0 == name)
+            .map(|(entity, _, _, _)| entity)
+            .ok_or_else(|| format!("unknown country {}", name))
+    }
+
+    /// Nations are released into the country of their name, or created if it's not on the map.
+    fn find_release(&self, name: &str) -> Result<Release, String> {
+        if let Ok(captive) = self.find_country(name) {
+            return Ok(Release::Captive(captive));
+        }
+        self.nations
+            .defs
+            .find(name)
+            .map(Release::Nation)
+            .ok_or_else(|| format!("unknown nation {}", name))
+    }
+
+    fn find_province(&self, hex: Hex) -> Result<Entity, String> {
+        self.province_hex_map
+            .get_entity(&hex)
+            .copied()
+            .ok_or_else(|| format!("no province at {:?}", hex))
+    }
+
+    /// Returns the province, checking the country owns it.
+    fn owned_province(&self, country: Entity, hex: Hex) -> Result<Entity, String> {
+        let province = self.find_province(hex)?;
+        match self.provinces.get(province) {
+            Ok((Some(owner), _)) if owner.0 == country => Ok(province),
+            _ => Err("the province belongs to another country".to_string()),
+        }
+    }
+
+    /// Every purchase goes through here, so that commands given in the same frame can't spend
+    /// the same ducats twice. The local player is told when the treasury falls short.
+    fn pay(&mut self, country: Entity, cost: f32) -> Result<(), String> {
+        let (_, _, _, mut coffer) = self
+            .countries
+            .get_mut(country)
+            .map_err(|_| "the country has no treasury".to_string())?;
+        coffer.spend(cost).map_err(|e| {
+            if self.player.country == Some(country) {
+                self.notifications.push(Notification {
+                    title: "💰 Insufficient funds".to_string(),
+                    text: format!("The treasury can't pay for it: {}.", e),
+                    target: None,
+                });
+            }
+            e.to_string()
+        })
+    }
+
+    /// Fails unless the country has the manpower to raise a regiment.
+    fn check_manpower(&mut self, country: Entity) -> Result<(), String> {
+        let available = self.manpower.get(country).map_or(0, |m| m.0);
+        if available >= REGIMENT_SIZE {
+            return Ok(());
+        }
+        if self.player.country == Some(country) {
+            self.notifications.push(Notification {
+                title: "👥 Insufficient manpower".to_string(),
+                text: format!(
+                    "A regiment needs {} men, only {} are available.",
+                    REGIMENT_SIZE, available
+                ),
+                target: None,
+            });
+        }
+        Err(format!(
+            "{} manpower needed, {} available",
+            REGIMENT_SIZE, available
+        ))
+    }
+
+    fn draw_manpower(&mut self, country: Entity) {
+        if let Ok(mut manpower) = self.manpower.get_mut(country) {
+            manpower.0 -= REGIMENT_SIZE;
+        }
+    }
+
+    fn country_army(&self, country: Entity, hex: Hex) -> Result<Entity, String> {
+        self.army_hex_map
+            .get(&HexPos::new(hex))
+            .copied()
+            .filter(|&army| {
+                self.armies
+                    .get(army)
+                    .is_ok_and(|(owner, _)| owner.0 == country)
+            })
+            .ok_or_else(|| format!("no army of the country at {:?}", hex))
+    }
+
+    fn move_army(&mut self, country: Entity, from: Hex, to: Hex) -> Result<(), String> {
+        let army = self.country_army(country, from)?;
+        if self.embarked.contains(army) {
+            return self.land_army(army, from, to);
+        }
+        if self.sea_chart.is_sea(&to) {
+            return self.embark_army(country, army, from, to);
+        }
+        // A direct order replaces following another army, and asks again before merging.
+        self.commands
+            .entity(army)
+            .remove::<(Following, MergeApproved, PendingMerge)>();
+        self.move_events
+            .write(MoveArmyEvent::new(army, HexPos::new(to)));
+        Ok(())
+    }
+
+    /// Armies board a transport of their country on a neighboring sea hex, one army a transport.
+    fn embark_army(
+        &mut self,
+        country: Entity,
+        army: Entity,
+        from: Hex,
+        to: Hex,
+    ) -> Result<(), String> {
+        if from.distance(&to) != 1 {
+            return Err("armies can only board a transport next to them".to_string());
+        }
+        let transport = self
+            .fleet_hex_map
+            .fleets_at(&to)
+            .iter()
+            .copied()
+            .find(|&fleet| {
+                self.fleets.get(fleet).is_ok_and(|(_, owner, fleet)| {
+                    owner.0 == country && fleet.ships == ShipType::Transport
+                })
+            })
+            .ok_or_else(|| format!("no transport of the country at {:?}", to))?;
+        let loaded = self.boarded_transports.contains(&transport)
+            || self
+                .embarked
+                .iter()
+                .any(|embarked| embarked.fleet == transport);
+        if loaded {
+            return Err("the transport already carries an army".to_string());
+        }
+        self.boarded_transports.push(transport);
+        self.commands
+            .entity(army)
+            .remove::<(ActivePath, Following)>()
+            .insert((Embarked { fleet: transport }, HexPos::new(to)));
+        Ok(())
+    }
+
+    /// Embarked armies land on a passable hex next to their transport, they march off the ship
+    /// during the next turn.
+    fn land_army(&mut self, army: Entity, from: Hex, to: Hex) -> Result<(), String> {
+        let passable = self
+            .find_province(to)
+            .ok()
+            .and_then(|province| self.terrain.get(province).ok())
+            .is_some_and(|province| province.is_passable());
+        if from.distance(&to) != 1 || !passable {
+            return Err("armies at sea can only land on a neighboring land hex".to_string());
+        }
+        self.commands
+            .entity(army)
+            .remove::<Following>()
+            .insert(ActivePath {
+                path: VecDeque::from([to]),
+            });
+        Ok(())
+    }
+
+    fn stop_army(&mut self, country: Entity, hex: Hex) -> Result<(), String> {
+        let army = self.country_army(country, hex)?;
+        self.commands
+            .entity(army)
+            .remove::<(ActivePath, Following, MergeApproved, PendingMerge)>();
+        Ok(())
+    }
+
+    fn merge_army(&mut self, country: Entity, hex: Hex, target: Hex) -> Result<(), String> {
+        let army = self.country_army(country, hex)?;
+        if self.country_army(country, target)? == army {
+            return Err("an army can't merge into itself".to_string());
+        }
+        self.commands
+            .entity(army)
+            .remove::<(Following, PendingMerge)>()
+            .insert(MergeApproved);
+        self.move_events
+            .write(MoveArmyEvent::new(army, HexPos::new(target)));
+        Ok(())
+    }
+
+    /// The detachment starts out on the army's hex and marches to the first free passable hex
+    /// next to it, so the two armies don't merge back. The army keeps some soldiers of its own.
+    fn split_army(
+        &mut self,
+        country: Entity,
+        hex: Hex,
+        detachment: &ArmyComposition,
+    ) -> Result<(), String> {
+        let army = self.country_army(country, hex)?;
+        if self.embarked.contains(army) || self.in_battle.contains(army) {
+            return Err("armies at sea or in battle can't split".to_string());
+        }
+        let Ok((_, composition)) = self.armies.get(army) else {
+            return Err(format!("no army of the country at {:?}", hex));
+        };
+        let available = detachment
+            .iter()
+            .all(|(unit, soldiers)| soldiers <= composition.get(unit));
+        if !available || detachment.total_size() == 0 {
+            return Err("the army doesn't have those soldiers".to_string());
+        }
+        if detachment.total_size() == composition.total_size() {
+            return Err("the army has to keep some soldiers".to_string());
+        }
+        let destination = hex
+            .neighbors()
+            .into_iter()
+            .find(|neighbor| {
+                let passable = self
+                    .find_province(*neighbor)
+                    .ok()
+                    .and_then(|province| self.terrain.get(province).ok())
+                    .is_some_and(|province| province.is_passable());
+                passable
+                    && self
+                        .army_hex_map
+                        .armies_at(&HexPos::new(*neighbor))
+                        .is_empty()
+            })
+            .ok_or_else(|| "no free hex next to the army to march the detachment to".to_string())?;
+
+        if let Ok((_, mut composition)) = self.armies.get_mut(army) {
+            for (unit, soldiers) in detachment.iter() {
+                composition.remove(unit, soldiers);
+            }
+        }
+        let detached = spawn_army(&mut self.commands, hex, country, detachment.clone());
+        self.commands.entity(detached).insert(ActivePath {
+            path: VecDeque::from([destination]),
+        });
+        Ok(())
+    }
+
+    /// Only armies of the country or of countries it is at peace with can be followed.
+    fn attach_army(
+        &mut self,
+        country: Entity,
+        hex: Hex,
+        target: Option<Hex>,
+    ) -> Result<(), String> {
+        let army = self.country_army(country, hex)?;
+        let Some(target_hex) = target else {
+            self.commands.entity(army).remove::<Following>();
+            return Ok(());
+        };
+        let target = self
+            .army_hex_map
+            .get(&HexPos::new(target_hex))
+            .copied()
+            .filter(|&target| target != army)
+            .ok_or_else(|| format!("no army to follow at {:?}", target_hex))?;
+        let Ok((target_owner, _)) = self.armies.get(target) else {
+            return Err(format!("no army to follow at {:?}", target_hex));
+        };
+        if get_war_between(country, target_owner.0, &self.wars, &self.war_query).is_some() {
+            return Err("enemy armies can't be followed".to_string());
+        }
+        self.commands.entity(army).insert(Following { target });
+        self.move_events
+            .write(MoveArmyEvent::next_to(army, HexPos::new(target_hex)));
+        Ok(())
+    }
+
+    fn recruit(&mut self, country: Entity, hex: Hex, unit: UnitType) -> Result<(), String> {
+        let province = self.owned_province(country, hex)?;
+        let def = self
+            .units
+            .get(&unit)
+            .ok_or_else(|| format!("unknown unit type {}", unit))?;
+        if !def.is_available(self.turn.current_turn()) {
+            return Err(format!("{} can't be recruited yet", def.name));
+        }
+        if let Some(reason) = recruitment_blocked(
+            self.occupied.contains(province),
+            self.sieges.contains(province),
+        ) {
+            return Err(reason.to_string());
+        }
+        let children = self.provinces.get(province).ok().and_then(|(_, c)| c);
+        if def.needs_barracks && !has_building(children, &self.buildings, BuildingType::Barracks) {
+            return Err(format!("{} needs Barracks in the province", def.name));
+        }
+        let cost = def.cost;
+        let hex_pos = HexPos::new(hex);
+
+        match self.army_hex_map.get(&hex_pos).copied() {
+            Some(army) => {
+                if !self
+                    .armies
+                    .get(army)
+                    .is_ok_and(|(owner, _)| owner.0 == country)
+                {
+                    return Err("the tile is occupied by another army".to_string());
+                }
+                self.check_manpower(country)?;
+                self.pay(country, cost)?;
+                self.draw_manpower(country);
+                if let Ok((_, mut composition)) = self.armies.get_mut(army) {
+                    composition.add_unit(unit);
+                }
+            }
+            None => {
+                self.check_manpower(country)?;
+                self.pay(country, cost)?;
+                self.draw_manpower(country);
+                let mut composition = ArmyComposition::default();
+                composition.add_unit(unit);
+                spawn_army(&mut self.commands, hex_pos.0, country, composition);
+            }
+        }
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        country: Entity,
+        hex: Hex,
+        building_type: BuildingType,
+    ) -> Result<(), String> {
+        let province = self.owned_province(country, hex)?;
+        let children = self.provinces.get(province).ok().and_then(|(_, c)| c);
+        let already_built = has_building(children, &self.buildings, building_type);
+        if already_built || self.ordered_buildings.contains(&(province, building_type)) {
+            return Err(format!("{} is already built", building_type.name()));
+        }
+
+        self.pay(country, building_type.cost())?;
+        self.ordered_buildings.push((province, building_type));
+        spawn_building(&mut self.commands, province, building_type, country);
+        Ok(())
+    }
+
+    /// Returns a fleet of the country standing on the hex.
+    fn country_fleet(&mut self, country: Entity, hex: Hex) -> Result<Mut<'_, Fleet>, String> {
+        self.fleets
+            .iter_mut()
+            .find(|(_, owner, fleet)| owner.0 == country && fleet.hex == hex)
+            .map(|(_, _, fleet)| fleet)
+            .ok_or_else(|| format!("no fleet of the country at {:?}", hex))
+    }
+
+    fn build_fleet(&mut self, country: Entity, hex: Hex, ships: ShipType) -> Result<(), String> {
+        self.owned_province(country, hex)?;
+        let harbor = self
+            .sea_chart
+            .harbor(&hex)
+            .ok_or_else(|| "the province has no port".to_string())?;
+        self.pay(country, FLEET_COST)?;
+        spawn_fleet(&mut self.commands, harbor, ships, country);
+        Ok(())
+    }
+
+    fn colonize(&mut self, country: Entity, hex: Hex) -> Result<(), String> {
+        let province = self.find_province(hex)?;
+        if !self.colonial_range.can_colonize(country, province) {
+            return Err("the province is out of colonial range".to_string());
+        }
+        self.pay(country, COLONIZE_COST)?;
+        self.commands.entity(province).insert(Owner(country));
+        Ok(())
+    }
+
+    fn offer_peace(
+        &mut self,
+        country: Entity,
+        target: Entity,
+        provinces: &[Hex],
+        demands: PeaceDemands,
+    ) -> Result<(), String> {
+        let war_entity = get_war_between(country, target, &self.wars, &self.war_query)
+            .ok_or_else(|| "the countries aren't at war".to_string())?;
+        let provinces_to_cede = provinces
+            .iter()
+            .map(|&hex| self.find_province(hex))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.peace_events.write(PeaceOfferEvent {
+            from: country,
+            to: target,
+            war_entity,
+            provinces_to_cede,
+            demands,
+            enforce_tolerance: false,
+        });
+        Ok(())
+    }
+
+    /// Offers are answered once, like events. Declined offers are simply dropped.
+    fn answer_peace(&mut self, country: Entity, from: Entity, accept: bool) -> Result<(), String> {
+        let offer = self
+            .peace_offers
+            .iter()
+            .find(|(_, offer)| offer.from == from && offer.to == country)
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| "the country has no peace offer from there".to_string())?;
+        if self.answered.contains(&offer) {
+            return Err("the peace offer is already answered".to_string());
+        }
+        self.answered.push(offer);
+        if accept {
+            self.accept_peace_events.write(AcceptPeaceEvent {
+                peace_offer_entity: offer,
+            });
+        } else {
+            self.commands.entity(offer).despawn();
+        }
+        Ok(())
+    }
+
+    /// A country can't be made a vassal twice, nor the overlord of the country demanding it. Only
+    /// captive nations whose provinces the target holds can be released, and releasable nations
+    /// not on the map yet whose provinces it holds.
+    fn peace_demands(
+        &self,
+        country: Entity,
+        target: Entity,
+        vassalize: bool,
+        release: Option<&str>,
+        break_alliances: bool,
+    ) -> Result<PeaceDemands, String> {
+        if vassalize {
+            if self.vassals.contains(target) {
+                return Err("the target already is a vassal".to_string());
+            }
+            if self
+                .vassals
+                .get(country)
+                .is_ok_and(|vassal| vassal.overlord == target)
+            {
+                return Err("a vassal can't make its overlord a vassal".to_string());
+            }
+        }
+        let release = release.map(|name| self.find_release(name)).transpose()?;
+        if let Some(Release::Nation(nation)) = release {
+            self.nations.check(nation, target)?;
+        }
+        if let Some(Release::Captive(captive)) = release {
+            if self
+                .provinces
+                .iter()
+                .any(|(owner, _)| owner.is_some_and(|o| o.0 == captive))
+            {
+                return Err("only nations without land can be released".to_string());
+            }
+            if !self
+                .conquered
+                .iter()
+                .any(|(owner, conquered)| owner.0 == target && conquered.from == captive)
+            {
+                return Err("the target holds none of the nation's provinces".to_string());
+            }
+        }
+        if break_alliances
+            && !self
+                .alliances
+                .get(target)
+                .is_ok_and(|alliances| alliances.allies().next().is_some())
+        {
+            return Err("the target has no alliances to break".to_string());
+        }
+        Ok(PeaceDemands {
+            vassalize,
+            release,
+            break_alliances,
+        })
+    }
+
+    /// Provinces can only be traded with countries at peace, and not while they are occupied.
+    fn offer_province(
+        &mut self,
+        country: Entity,
+        target: &str,
+        hex: Hex,
+        price: f32,
+    ) -> Result<(), String> {
+        let target = self.find_country(target)?;
+        if target == country {
+            return Err("a province can't be offered to its owner".to_string());
+        }
+        if get_war_between(country, target, &self.wars, &self.war_query).is_some() {
+            return Err("provinces can't be traded with an enemy".to_string());
+        }
+        if !price.is_finite() || price < 0.0 {
+            return Err(format!("invalid price {}", price));
+        }
+        let province = self.owned_province(country, hex)?;
+        if self.occupied.contains(province) {
+            return Err("the province is occupied".to_string());
+        }
+        self.province_offers.write(ProvinceOfferEvent {
+            from: country,
+            to: target,
+            province,
+            price,
+        });
+        Ok(())
+    }
+
+    /// Offers are answered once, like peace offers.
+    fn answer_province_offer(
+        &mut self,
+        country: Entity,
+        hex: Hex,
+        accept: bool,
+    ) -> Result<(), String> {
+        let province = self.find_province(hex)?;
+        let offer = self
+            .offered_provinces
+            .iter()
+            .find(|(_, offer)| offer.to == country && offer.province == province)
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| "the province isn't offered to the country".to_string())?;
+        if self.answered.contains(&offer) {
+            return Err("the province offer is already answered".to_string());
+        }
+        self.answered.push(offer);
+        if accept {
+            self.accept_province_events.write(AcceptProvinceOfferEvent {
+                offer_entity: offer,
+            });
+        } else {
+            self.commands.entity(offer).despawn();
+        }
+        Ok(())
+    }
+
+    /// Calls are answered once, like peace offers. Declining is still sent to
+    /// [`crate::diplomacy::handle_call_to_arms_answers`], which ends the alliance.
+    fn answer_call_to_arms(
+        &mut self,
+        country: Entity,
+        from: Entity,
+        enemy: Entity,
+        accept: bool,
+    ) -> Result<(), String> {
+        let call = self
+            .calls_to_arms
+            .iter()
+            .find(|(_, call)| call.to == country && call.from == from && call.enemy == enemy)
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| "the country wasn't called to arms in that war".to_string())?;
+        if self.answered.contains(&call) {
+            return Err("the call to arms is already answered".to_string());
+        }
+        self.answered.push(call);
+        self.call_to_arms_answers.write(AnswerCallToArmsEvent {
+            call,
+            accepted: accept,
+        });
+        Ok(())
+    }
+
+    fn join_league(&mut self, country: Entity) -> Result<(), String> {
+        if !self.leagues.can_join(self.turn.current_turn()) {
+            return Err(
+                "leagues can't be joined during a league war or religious peace".to_string(),
+            );
+        }
+        match self.faiths.get(country) {
+            Ok((_, false)) => {
+                self.commands.entity(country).insert(LeagueMember);
+                Ok(())
+            }
+            Ok((_, true)) => Err("the country already is in its league".to_string()),
+            Err(_) => Err("the country has no faith".to_string()),
+        }
+    }
+
+    /// Events are answered once: answers to an event that is gone, or that was answered earlier
+    /// in the frame, are refused.
+    fn choose_event_option(
+        &mut self,
+        country: Entity,
+        event: &str,
+        option: usize,
+    ) -> Result<(), String> {
+        let (pending, options) = self
+            .pending_events
+            .iter()
+            .find(|(_, pending)| pending.country == country && pending.script.name == event)
+            .map(|(entity, pending)| (entity, pending.script.options.len()))
+            .ok_or_else(|| format!("the country has no pending event {}", event))?;
+        if self.answered.contains(&pending) {
+            return Err(format!("the event {} is already answered", event));
+        }
+        if option >= options {
+            return Err(format!("the event {} has no option {}", event, option));
+        }
+        self.answered.push(pending);
+        self.event_choices.write(EventOptionChosen {
+            country,
+            event: event.to_string(),
+            option,
+        });
+        Ok(())
+    }
+
+    fn set_map_color(&mut self, country: Entity, [r, g, b]: [f32; 3]) -> Result<(), String> {
+        let color = Color::srgb(r, g, b);
+        for (other, name, other_color, _) in &self.countries {
+            if other != country && colors_clash(color, other_color.0) {
+                return Err(format!("the color is nearly the one of {}", name.0));
+            }
+        }
+        if let Ok((_, _, mut map_color, _)) = self.countries.get_mut(country) {
+            map_color.0 = color;
+        }
+        Ok(())
+    }
+
+    fn enforce_tolerance(&mut self, country: Entity, target: &str) -> Result<(), String> {
+        let target = self.find_country(target)?;
+        let league_faith = |country: Entity| {
+            self.faiths
+                .get(country)
+                .ok()
+                .filter(|(_, member)| *member)
+                .map(|(faith, _)| *faith)
+        };
+        if !self
+            .leagues
+            .are_league_enemies(league_faith(country), league_faith(target))
+        {
+            return Err("the countries aren't fighting a league war".to_string());
+        }
+        let war_entity = get_war_between(country, target, &self.wars, &self.war_query)
+            .ok_or_else(|| "the countries aren't at war".to_string())?;
+        self.peace_events.write(PeaceOfferEvent {
+            from: country,
+            to: target,
+            war_entity,
+            provinces_to_cede: Vec::new(),
+            demands: PeaceDemands::default(),
+            enforce_tolerance: true,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn commands_round_trip_through_json() {
+        let command = PlayerCommand::Recruit {
+            country: "France".to_string(),
+            province: Hex::new(3, -1),
+            unit: UnitType::new("cavalry"),
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PlayerCommand>(&json).unwrap(),
+            command
+        );
+    }
+
+    #[test]
+    fn recruiting_pays_for_the_unit() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        game.spawn_province("Paris", Hex::new(0, 0), Some(country));
+        let missing = 15.0 - game.ducats(country);
+        game.world_mut()
+            .get_mut::<Coffer>(country)
+            .unwrap()
+            .add_ducats(missing);
+
+        for _ in 0..2 {
+            game.world_mut().write_message(PlayerCommand::Recruit {
+                country: "France".to_string(),
+                province: Hex::new(0, 0),
+                unit: UnitType::new("infantry"),
+            });
+            game.app.update();
+        }
+
+        // The second recruitment can't be paid for.
+        assert_eq!(game.ducats(country), 5.0);
+        assert_eq!(game.count::<Army>(), 1);
+    }
+
+    #[test]
+    fn recruiting_draws_on_manpower() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        game.spawn_province("Paris", Hex::new(0, 0), Some(country));
+        game.world_mut()
+            .get_mut::<Coffer>(country)
+            .unwrap()
+            .add_ducats(100.0);
+        game.world_mut().get_mut::<Manpower>(country).unwrap().0 = REGIMENT_SIZE + 500;
+
+        for _ in 0..2 {
+            game.world_mut().write_message(PlayerCommand::Recruit {
+                country: "France".to_string(),
+                province: Hex::new(0, 0),
+                unit: UnitType::new("infantry"),
+            });
+            game.app.update();
+        }
+
+        // The second regiment lacks the men, and isn't paid for.
+        assert_eq!(game.get::<Manpower>(country), Some(&Manpower(500)));
+        assert_eq!(game.ducats(country), 90.0);
+        assert_eq!(game.count::<Army>(), 1);
+    }
+
+    #[test]
+    fn split_armies_march_off_to_a_free_neighbor() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        game.spawn_provinces(3, Some(country));
+        game.spawn_army(country, Hex::new(0, 0), 500);
+        let army = game.spawn_army(country, Hex::new(1, 0), 3 * REGIMENT_SIZE);
+        let infantry = UnitType::new("infantry");
+        let split = |game: &mut TestGame, soldiers| {
+            game.world_mut().write_message(PlayerCommand::SplitArmy {
+                country: "France".to_string(),
+                army: Hex::new(1, 0),
+                detachment: ArmyComposition::default().with(UnitType::new("infantry"), soldiers),
+            });
+            game.app.update();
+        };
+
+        // The army can't be split off whole.
+        split(&mut game, 3 * REGIMENT_SIZE);
+        assert_eq!(game.count::<Army>(), 2);
+
+        split(&mut game, REGIMENT_SIZE);
+        assert_eq!(game.count::<Army>(), 3);
+        assert_eq!(
+            game.get::<ArmyComposition>(army).unwrap().get(&infantry),
+            2 * REGIMENT_SIZE
+        );
+
+        game.end_turn();
+        let armies_at = |game: &TestGame, q| {
+            let hex_map = game.world().resource::<ArmyHexMap>();
+            hex_map.armies_at(&HexPos::new(Hex::new(q, 0))).to_vec()
+        };
+        assert_eq!(armies_at(&game, 1), vec![army]);
+        let detached = armies_at(&game, 2);
+        assert_eq!(detached.len(), 1);
+        let detachment = game.get::<ArmyComposition>(detached[0]).unwrap();
+        assert_eq!(detachment.get(&infantry), REGIMENT_SIZE);
+    }
+
+    #[test]
+    fn buildings_ordered_twice_in_a_frame_are_paid_once() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        game.spawn_province("Paris", Hex::new(0, 0), Some(country));
+        let cost = BuildingType::Fort.cost();
+        let missing = 3.0 * cost - game.ducats(country);
+        game.world_mut()
+            .get_mut::<Coffer>(country)
+            .unwrap()
+            .add_ducats(missing);
+
+        for _ in 0..2 {
+            game.world_mut().write_message(PlayerCommand::Build {
+                country: "France".to_string(),
+                province: Hex::new(0, 0),
+                building: BuildingType::Fort,
+            });
+        }
+        game.app.update();
+
+        assert_eq!(game.ducats(country), 2.0 * cost);
+        assert_eq!(game.count::<Building>(), 1);
+    }
+
+    #[test]
+    fn troops_are_only_raised_in_safe_provinces() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        let enemy = game.spawn_country("England");
+        let paris = game.spawn_province("Paris", Hex::new(0, 0), Some(country));
+        let calais = game.spawn_province("Calais", Hex::new(1, 0), Some(country));
+        let lyon = game.spawn_province("Lyon", Hex::new(2, 0), Some(country));
+        let missing = 100.0 - game.ducats(country);
+        game.world_mut()
+            .get_mut::<Coffer>(country)
+            .unwrap()
+            .add_ducats(missing);
+        game.world_mut().entity_mut(paris).insert(SiegeProgress {
+            besiegers: vec![enemy],
+            soldiers: 3000,
+            required: 3000,
+            progress: 0,
+            progress_required: 1,
+        });
+        game.world_mut()
+            .entity_mut(calais)
+            .insert(Occupied { occupier: enemy });
+        let recruit = |game: &mut TestGame, q: i32, unit: &str| {
+            game.world_mut().write_message(PlayerCommand::Recruit {
+                country: "France".to_string(),
+                province: Hex::new(q, 0),
+                unit: UnitType::new(unit),
+            });
+            game.app.update();
+        };
+
+        recruit(&mut game, 0, "infantry");
+        recruit(&mut game, 1, "infantry");
+        // Cavalry needs Barracks.
+        recruit(&mut game, 2, "cavalry");
+        assert_eq!(game.count::<Army>(), 0);
+        assert_eq!(game.ducats(country), 100.0);
+
+        recruit(&mut game, 2, "infantry");
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Barracks,
+            },
+            ChildOf(lyon),
+        ));
+        recruit(&mut game, 2, "cavalry");
+        assert_eq!(game.count::<Army>(), 1);
+        assert_eq!(game.ducats(country), 65.0);
+    }
+
+    #[test]
+    fn stopped_armies_drop_their_march() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        for q in 0..3 {
+            game.spawn_province("Province", Hex::new(q, 0), Some(country));
+        }
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.move_army(army, Hex::new(2, 0));
+        assert!(game.get::<ActivePath>(army).is_some());
+
+        game.world_mut().write_message(PlayerCommand::StopArmy {
+            country: "France".to_string(),
+            army: Hex::new(0, 0),
+        });
+        game.app.update();
+        assert!(game.get::<ActivePath>(army).is_none());
+    }
+
+    #[test]
+    fn countries_only_pick_map_colors_that_stand_out() {
+        let mut game = TestGame::new();
+        let france = game.spawn_country("France");
+        let castile = game.spawn_country("Castile");
+        let set_color = |game: &mut TestGame, country: &str, color: [f32; 3]| {
+            game.world_mut().write_message(PlayerCommand::SetMapColor {
+                country: country.to_string(),
+                color,
+            });
+            game.app.update();
+        };
+
+        set_color(&mut game, "France", [0.1, 0.2, 0.8]);
+        assert_eq!(
+            game.get::<MapColor>(france).unwrap().0,
+            Color::srgb(0.1, 0.2, 0.8)
+        );
+
+        // Castile can't take nearly the same blue.
+        set_color(&mut game, "Castile", [0.12, 0.2, 0.8]);
+        assert_eq!(game.get::<MapColor>(castile).unwrap().0, Color::WHITE);
+    }
+}
@@ -0,0 +1,265 @@
+﻿use crate::army::spawn_army;
+use crate::country::{Country, CountryBundle, DisplayName, FlagFile, MapColor, color_apart};
+use crate::map::{CountryDef, Owner, Province, ReleasableDef};
+use crate::notifications::{Notification, NotificationTarget, Notifications};
+use crate::player::Player;
+use crate::units::UnitRegistry;
+use crate::war::cede_province;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+pub struct ReleasablesPlugin;
+
+impl Plugin for ReleasablesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Releasables>()
+            .add_message::<ReleaseNationEvent>()
+            .add_systems(
+                Update,
+                release_nations.after(crate::war::handle_accept_peace),
+            )
+            .add_systems(Update, assign_missing_flags.after(release_nations));
+    }
+}
+
+/// Nations of the map file that can be released during the game, see [`ReleasableDef`].
+/// Nations are referred to by their index in here.
+#[derive(Resource, Default)]
+pub struct Releasables(pub Vec<ReleasableDef>);
+
+impl Releasables {
+    pub fn get(&self, nation: usize) -> Option<&ReleasableDef> {
+        self.0.get(nation)
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|def| def.country.name == name)
+    }
+
+    pub fn name(&self, nation: usize) -> &str {
+        self.get(nation)
+            .map_or("Unknown", |def| def.country.name.as_str())
+    }
+}
+
+/// Makes `from` release the nation, giving up the nation's provinces it owns.
+#[derive(Message)]
+pub struct ReleaseNationEvent {
+    pub nation: usize,
+    pub from: Entity,
+}
+
+/// The releasable nations along with who holds their provinces.
+#[derive(SystemParam)]
+pub struct ReleasableNations<'w, 's> {
+    pub defs: Res<'w, Releasables>,
+    provinces: Query<'w, 's, (Entity, &'static Province, &'static Owner)>,
+    countries: Query<'w, 's, &'static DisplayName, With<Country>>,
+}
+
+impl ReleasableNations<'_, '_> {
+    /// Whether a country of the nation's name is on the map already.
+    pub fn exists(&self, nation: usize) -> bool {
+        let name = self.defs.name(nation);
+        self.countries.iter().any(|country| country.0 == name)
+    }
+
+    /// Provinces of the nation `owner` holds, its capital first if it holds it.
+    pub fn provinces(&self, nation: usize, owner: Entity) -> Vec<Entity> {
+        let Some(def) = self.defs.get(nation) else {
+            return Vec::new();
+        };
+        def.provinces
+            .iter()
+            .filter_map(|key| {
+                self.provinces
+                    .iter()
+                    .find(|(_, province, o)| province.key() == key && o.0 == owner)
+                    .map(|(entity, _, _)| entity)
+            })
+            .collect()
+    }
+
+    /// Nations `country` could release: those not on the map holding some of their provinces.
+    pub fn releasable_by(&self, country: Entity) -> Vec<usize> {
+        (0..self.defs.0.len())
+            .filter(|&nation| !self.exists(nation) && !self.provinces(nation, country).is_empty())
+            .collect()
+    }
+
+    /// Checks that `from` can release the nation, see [`ReleasableNations::releasable_by`].
+    pub fn check(&self, nation: usize, from: Entity) -> Result<(), String> {
+        if self.exists(nation) {
+            return Err(format!("{} already exists", self.defs.name(nation)));
+        }
+        if self.provinces(nation, from).is_empty() {
+            return Err(format!(
+                "none of the provinces of {} can be given up",
+                self.defs.name(nation)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Spawns a country for the nation, without any land. Its flag is assigned by
+/// [`assign_missing_flags`].
+pub fn spawn_nation(commands: &mut Commands, def: &CountryDef, color: Color) -> Entity {
+    commands
+        .spawn(CountryBundle::new(&def.name, color))
+        .insert(def.faith)
+        .id()
+}
+
+/// Creates the released nations from the provinces given up for them, with the starting army in
+/// their capital.
+#[allow(clippy::too_many_arguments)]
+pub fn release_nations(
+    mut events: MessageReader<ReleaseNationEvent>,
+    mut commands: Commands,
+    nations: ReleasableNations,
+    colors: Query<&MapColor, With<Country>>,
+    units: Res<UnitRegistry>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    // Countries are only spawned once the commands are applied.
+    let mut released: Vec<(usize, Color)> = Vec::new();
+    for event in events.read() {
+        if released.iter().any(|(nation, _)| *nation == event.nation) {
+            continue;
+        }
+        if let Err(e) = nations.check(event.nation, event.from) {
+            warn!("Can't release nation {}: {}", event.nation, e);
+            continue;
+        }
+        let Some(def) = nations.defs.get(event.nation) else {
+            continue;
+        };
+        let provinces = nations.provinces(event.nation, event.from);
+        let taken = colors
+            .iter()
+            .map(|color| color.0)
+            .chain(released.iter().map(|(_, color)| *color));
+        let color = color_apart(&def.country, taken);
+        let country = spawn_nation(&mut commands, &def.country, color);
+        released.push((event.nation, color));
+
+        for &province in &provinces {
+            cede_province(&mut commands, province, country);
+        }
+        let capital = provinces[0];
+        if let Ok((_, province, _)) = nations.provinces.get(capital) {
+            spawn_army(
+                &mut commands,
+                *province.get_hex(),
+                country,
+                units.starting_army(),
+            );
+        }
+        info!("{} released by {:?}", def.country.name, event.from);
+
+        if player.country.is_some() {
+            let from = nations
+                .countries
+                .get(event.from)
+                .map_or("Unknown", |name| name.0.as_str());
+            notifications.push(Notification {
+                title: format!("🏳 {} released", def.country.name),
+                text: format!(
+                    "{} has given up {} province(s) to the newly independent {}.",
+                    from,
+                    provinces.len(),
+                    def.country.name
+                ),
+                target: Some(NotificationTarget::Province(capital)),
+            });
+        }
+    }
+}
+
+/// Assigns the flags of the countries created during the game, mods can replace them like the
+/// flags of the countries on the map.
+fn assign_missing_flags(
+    mut commands: Commands,
+    countries: Query<(Entity, &DisplayName), (With<Country>, Without<FlagFile>)>,
+    releasables: Res<Releasables>,
+) {
+    for (entity, name) in &countries {
+        if let Some(def) = releasables.find(&name.0).and_then(|n| releasables.get(n)) {
+            commands
+                .entity(entity)
+                .insert(FlagFile(def.country.flag.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::army::{Army, HexPos};
+    use crate::hex::Hex;
+    use crate::player_command::PlayerCommand;
+    use crate::religion::Faith;
+    use crate::test_utils::TestGame;
+    use crate::war::{Occupied, War};
+
+    #[test]
+    fn peace_deals_release_nations_from_their_provinces() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(4, Some(defender));
+        game.world_mut()
+            .insert_resource(Releasables(vec![ReleasableDef {
+                country: CountryDef {
+                    name: "Freedonia".to_string(),
+                    color: [0.5, 0.2, 0.7],
+                    flag: "flags/freedonia.png".to_string(),
+                    faith: Faith::Protestant,
+                },
+                provinces: vec!["Province 2".to_string(), "Province 3".to_string()],
+            }]));
+        game.declare_war(attacker, defender);
+        for &province in &provinces {
+            game.world_mut()
+                .entity_mut(province)
+                .insert(Occupied { occupier: attacker });
+        }
+
+        game.world_mut().write_message(PlayerCommand::OfferPeace {
+            country: "Attacker".to_string(),
+            target: "Defender".to_string(),
+            provinces: Vec::new(),
+            vassalize: false,
+            release: Some("Freedonia".to_string()),
+            break_alliances: false,
+        });
+        for _ in 0..3 {
+            game.app.update();
+        }
+
+        assert_eq!(game.count::<War>(), 0);
+        let (nation, faith) = game
+            .world_mut()
+            .query::<(Entity, &DisplayName, &Faith)>()
+            .iter(game.world())
+            .find(|(_, name, _)| name.0 == "Freedonia")
+            .map(|(entity, _, faith)| (entity, *faith))
+            .expect("the nation should have been released");
+        assert_eq!(faith, Faith::Protestant);
+        assert_eq!(game.get::<Owner>(provinces[1]).unwrap().0, defender);
+        assert_eq!(game.get::<Owner>(provinces[2]).unwrap().0, nation);
+        assert_eq!(game.get::<Owner>(provinces[3]).unwrap().0, nation);
+        assert!(game.get::<Occupied>(provinces[2]).is_none());
+        let army_hexes: Vec<Hex> = game
+            .world_mut()
+            .query_filtered::<(&Owner, &HexPos), With<Army>>()
+            .iter(game.world())
+            .filter(|(owner, _)| owner.0 == nation)
+            .map(|(_, pos)| pos.0)
+            .collect();
+        assert_eq!(army_hexes, vec![Hex::new(2, 0)]);
+    }
+}
@@ -0,0 +1,321 @@
+﻿use crate::country::{Country, DisplayName};
+use crate::map::{Owner, Province};
+use crate::modifiers::{CountryModifiers, aggression};
+use crate::notifications::{Notification, Notifications};
+use crate::player::Player;
+use crate::rules::GameRng;
+use crate::turns::{GameState, Turn, TurnSet};
+use crate::war::{DeclareWarEvent, Occupied, War, WarRelations, Wars};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub struct ReligionPlugin;
+
+impl Plugin for ReligionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leagues::default())
+            .add_message::<ToleranceEnforcedEvent>()
+            .add_systems(
+                OnEnter(GameState::Processing),
+                advance_leagues
+                    .after(crate::weather::update_weather)
+                    .before(crate::army::move_active_armies)
+                    .in_set(TurnSet::Resolve),
+            )
+            .add_systems(
+                Update,
+                enforce_tolerance.after(crate::war::handle_accept_peace),
+            );
+    }
+}
+
+/// Chance an AI country outside of its faith's league joins it, rolled every turn and scaled by
+/// the country's aggression.
+const AI_JOIN_LEAGUE_CHANCE: f64 = 0.1;
+/// Members both leagues need before tension between them starts building.
+const MIN_LEAGUE_MEMBERS: usize = 2;
+/// Tension at which the leagues go to war, it grows by one every turn both leagues stand.
+pub const LEAGUE_WAR_TENSION: u32 = 10;
+/// Turns after a league war during which no league can form.
+const RELIGIOUS_PEACE_TURNS: u32 = 30;
+
+/// Faith of a country, given by the map file.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Faith {
+    #[default]
+    Catholic,
+    Protestant,
+}
+
+impl Faith {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Faith::Catholic => "Catholic",
+            Faith::Protestant => "Protestant",
+        }
+    }
+
+    /// Name of the league the countries of the faith form.
+    pub fn league_name(&self) -> &'static str {
+        match self {
+            Faith::Catholic => "Catholic League",
+            Faith::Protestant => "Protestant Union",
+        }
+    }
+}
+
+/// Marks a country as a member of the league of its faith.
+#[derive(Component)]
+pub struct LeagueMember;
+
+/// State of the rivalry between the religious leagues.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Leagues {
+    pub tension: u32,
+    /// Whether the leagues are fighting a league war.
+    pub at_war: bool,
+    /// Turn before which no league can form, set when a league war ends.
+    pub religious_peace_until: u32,
+}
+
+impl Leagues {
+    pub fn can_join(&self, turn: u32) -> bool {
+        !self.at_war && turn >= self.religious_peace_until
+    }
+
+    /// Whether two countries fight on opposite sides of the league war, given the faiths of
+    /// those of them in a league.
+    pub fn are_league_enemies(&self, a: Option<Faith>, b: Option<Faith>) -> bool {
+        self.at_war && matches!((a, b), (Some(a), Some(b)) if a != b)
+    }
+}
+
+/// Sent when a league war ends with the loser accepting to tolerate the winner's faith.
+#[derive(Message)]
+pub struct ToleranceEnforcedEvent {
+    pub winner: Entity,
+    pub loser: Entity,
+}
+
+/// Lets AI countries join their league, builds tension while both leagues stand and starts the
+/// league war once it boils over: every member of one league at war with every member of the
+/// other.
+#[allow(clippy::too_many_arguments)]
+pub fn advance_leagues(
+    mut commands: Commands,
+    mut leagues: ResMut<Leagues>,
+    mut rng: ResMut<GameRng>,
+    turn: Res<Turn>,
+    player: Res<Player>,
+    countries: Query<
+        (
+            Entity,
+            &DisplayName,
+            &Faith,
+            Has<LeagueMember>,
+            Option<&CountryModifiers>,
+        ),
+        With<Country>,
+    >,
+    provinces: Query<&Owner, With<Province>>,
+    mut war_events: MessageWriter<DeclareWarEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    if !leagues.can_join(turn.current_turn()) {
+        return;
+    }
+
+    // Sorted, so that every peer of a multiplayer game rolls the same way.
+    let mut countries: Vec<_> = countries.iter().collect();
+    countries.sort_by(|(_, a, _, _, _), (_, b, _, _, _)| a.0.cmp(&b.0));
+
+    let mut members: Vec<(Entity, Faith)> = Vec::new();
+    for &(country, name, faith, member, modifiers) in &countries {
+        let chance = (AI_JOIN_LEAGUE_CHANCE * f64::from(aggression(modifiers))).clamp(0.0, 1.0);
+        let joins = !member && !player.is_human(country) && rng.random_bool(chance);
+        if joins {
+            info!("{} joins the {}", name.0, faith.league_name());
+            commands.entity(country).insert(LeagueMember);
+        }
+        if member || joins {
+            members.push((country, *faith));
+        }
+    }
+
+    let (catholics, protestants): (Vec<_>, Vec<_>) = members
+        .into_iter()
+        .partition(|(_, faith)| *faith == Faith::Catholic);
+    if catholics.len() < MIN_LEAGUE_MEMBERS || protestants.len() < MIN_LEAGUE_MEMBERS {
+        return;
+    }
+    leagues.tension += 1;
+    if leagues.tension < LEAGUE_WAR_TENSION {
+        return;
+    }
+
+    // The league holding more provinces strikes first.
+    let strength = |league: &[(Entity, Faith)]| {
+        provinces
+            .iter()
+            .filter(|owner| league.iter().any(|(country, _)| *country == owner.0))
+            .count()
+    };
+    let (attackers, defenders) = if strength(&catholics) >= strength(&protestants) {
+        (catholics, protestants)
+    } else {
+        (protestants, catholics)
+    };
+    for &(attacker, _) in &attackers {
+        for &(defender, _) in &defenders {
+            war_events.write(DeclareWarEvent::new(attacker, defender));
+        }
+    }
+    leagues.at_war = true;
+    leagues.tension = 0;
+
+    let attacker_faith = attackers[0].1;
+    info!("The {} goes to war", attacker_faith.league_name());
+    if player.country.is_some_and(|country| {
+        attackers
+            .iter()
+            .chain(&defenders)
+            .any(|(c, _)| *c == country)
+    }) {
+        notifications.push(Notification {
+            title: "✝ League war".to_string(),
+            text: format!(
+                "The {} has declared war on the {}. Enforce tolerance on an enemy to end it.",
+                attacker_faith.league_name(),
+                defenders[0].1.league_name()
+            ),
+            target: None,
+        });
+    }
+}
+
+/// Ends the league war once a member accepted to tolerate the winner's faith: every war between
+/// the leagues ends, occupied provinces return and the leagues disband for a religious peace.
+#[allow(clippy::too_many_arguments)]
+pub fn enforce_tolerance(
+    mut commands: Commands,
+    mut events: MessageReader<ToleranceEnforcedEvent>,
+    mut leagues: ResMut<Leagues>,
+    mut wars: ResMut<Wars>,
+    turn: Res<Turn>,
+    war_query: Query<&War>,
+    members: Query<(Entity, &Faith), With<LeagueMember>>,
+    mut war_relations: Query<&mut WarRelations>,
+    occupied_provinces: Query<(Entity, &Occupied, &Owner)>,
+    player: Res<Player>,
+    names: Query<&DisplayName>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    if !leagues.at_war {
+        return;
+    }
+    let faith_of = |country: Entity| members.get(country).ok().map(|(_, faith)| *faith);
+    let league_enemies =
+        |a: Entity, b: Entity| leagues.are_league_enemies(faith_of(a), faith_of(b));
+
+    for war_entity in wars.active_wars.clone() {
+        let Ok(war) = war_query.get(war_entity) else {
+            continue;
+        };
+        if !league_enemies(war.attacker, war.defender) {
+            continue;
+        }
+        for (country, enemy) in [(war.attacker, war.defender), (war.defender, war.attacker)] {
+            if let Ok(mut relations) = war_relations.get_mut(country) {
+                relations.remove_enemy(enemy);
+            }
+        }
+        wars.remove_war(war_entity);
+        commands.entity(war_entity).despawn();
+    }
+    for (province, occupied, owner) in &occupied_provinces {
+        if league_enemies(occupied.occupier, owner.0) {
+            commands.entity(province).remove::<Occupied>();
+        }
+    }
+    for (country, _) in &members {
+        commands.entity(country).remove::<LeagueMember>();
+    }
+    *leagues = Leagues {
+        religious_peace_until: turn.current_turn() + RELIGIOUS_PEACE_TURNS,
+        ..default()
+    };
+
+    let name = |country: Entity| names.get(country).map_or("Unknown", |name| name.0.as_str());
+    info!(
+        "{} enforced tolerance on {}, the league war is over",
+        name(event.winner),
+        name(event.loser)
+    );
+    if player.country.is_some() {
+        notifications.push(Notification {
+            title: "☮ Religious peace".to_string(),
+            text: format!(
+                "{} has forced {} to tolerate its faith. The leagues disband for {} turns.",
+                name(event.winner),
+                name(event.loser),
+                RELIGIOUS_PEACE_TURNS
+            ),
+            target: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+
+    fn spawn_member(game: &mut TestGame, name: &str, faith: Faith) -> Entity {
+        let country = game.spawn_country(name);
+        game.world_mut()
+            .entity_mut(country)
+            .insert((faith, LeagueMember));
+        country
+    }
+
+    #[test]
+    fn tolerance_ends_every_war_between_the_leagues() {
+        let mut game = TestGame::new();
+        let catholics = [
+            spawn_member(&mut game, "Spain", Faith::Catholic),
+            spawn_member(&mut game, "France", Faith::Catholic),
+        ];
+        let protestants = [
+            spawn_member(&mut game, "Britain", Faith::Protestant),
+            spawn_member(&mut game, "Germany", Faith::Protestant),
+        ];
+
+        game.end_turns(LEAGUE_WAR_TENSION - 1);
+        assert_eq!(game.count::<War>(), 0);
+        game.end_turn();
+        game.app.update();
+        assert_eq!(game.count::<War>(), 4);
+        assert!(game.world().resource::<Leagues>().at_war);
+
+        game.world_mut().write_message(ToleranceEnforcedEvent {
+            winner: catholics[0],
+            loser: protestants[0],
+        });
+        game.app.update();
+        assert_eq!(game.count::<War>(), 0);
+        assert_eq!(game.count::<LeagueMember>(), 0);
+        let relations = game.get::<WarRelations>(catholics[1]).unwrap();
+        assert!(!relations.is_at_war_with(protestants[1]));
+        assert!(
+            !game
+                .world()
+                .resource::<Leagues>()
+                .can_join(LEAGUE_WAR_TENSION)
+        );
+    }
+}
@@ -0,0 +1,561 @@
+﻿use crate::army::{Army, ArmyComposition, spawn_army};
+use crate::country::{Country, DisplayName};
+use crate::map::{MAP_FILE_PATH, Owner, Province};
+use crate::menu::MenuState;
+use crate::modifiers::{CountryModifier, CountryModifiers};
+use crate::mods::VirtualFs;
+use crate::notifications::{Notification, Notifications};
+use crate::player::Player;
+use crate::savegame::{IRONMAN_SLOT, SaveGameEvent};
+use crate::turns::{GameState, Turn, TurnSet};
+use crate::units::UnitRegistry;
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
+pub struct GameRulesPlugin;
+
+impl Plugin for GameRulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameRules::default())
+            .insert_resource(GameEnded::default())
+            .insert_resource(PlayerDefeat::default())
+            .insert_resource(GameRng::default())
+            .add_systems(
+                OnTransition {
+                    exited: MenuState::GameSetup,
+                    entered: MenuState::InGame,
+                },
+                (
+                    reseed_game_rng,
+                    apply_ai_nation_limit,
+                    apply_rule_modifiers,
+                    grant_free_manpower,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnEnter(GameState::Processing),
+                (
+                    reseed_game_rng
+                        .before(crate::turns::handle_new_turn)
+                        .before(crate::army::move_active_armies)
+                        .before(crate::army::resolve_battles),
+                    // Also picks up the rules of a loaded game.
+                    apply_rule_modifiers
+                        .before(crate::turns::handle_new_turn)
+                        .before(crate::religion::advance_leagues),
+                )
+                    .in_set(TurnSet::Resolve),
+            )
+            .add_systems(
+                OnEnter(GameState::PlayerTurn),
+                (
+                    ironman_autosave,
+                    check_victory_conditions,
+                    check_player_defeat,
+                )
+                    .in_set(TurnSet::Begin)
+                    .run_if(in_state(MenuState::InGame)),
+            );
+    }
+}
+
+/// Directory scanned for selectable maps in the game setup screen.
+const MAPS_DIRECTORY: &str = "maps";
+
+/// Name of the country modifier given by the difficulty.
+const DIFFICULTY_MODIFIER: &str = "Difficulty";
+/// Name of the country modifier given by a country's handicap.
+const HANDICAP_MODIFIER: &str = "Handicap";
+
+/// How forgiving the AI is. Affects how readily it accepts peace deals and how much income AI
+/// countries get.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn all() -> [Difficulty; 3] {
+        [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard]
+    }
+
+    /// Largest share of its provinces an AI country is willing to give up in a peace deal.
+    pub fn ai_max_province_loss(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 0.3,
+            Difficulty::Hard => 0.15,
+        }
+    }
+
+    /// Added to the income multiplier of every AI country.
+    pub fn ai_income_bonus(&self) -> f32 {
+        match self {
+            Difficulty::Easy => -0.2,
+            Difficulty::Normal => 0.0,
+            Difficulty::Hard => 0.25,
+        }
+    }
+}
+
+/// How an AI country weighs the war it is in when offered peace.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Personality {
+    #[default]
+    Balanced,
+    /// Fights on long after the war is lost.
+    Stubborn,
+    /// Gives in as soon as the war turns against it.
+    Cautious,
+}
+
+impl Personality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Personality::Balanced => "Balanced",
+            Personality::Stubborn => "Stubborn",
+            Personality::Cautious => "Cautious",
+        }
+    }
+
+    pub fn all() -> [Personality; 3] {
+        [
+            Personality::Balanced,
+            Personality::Stubborn,
+            Personality::Cautious,
+        ]
+    }
+
+    pub fn peace_weights(&self) -> PeaceWeights {
+        match self {
+            Personality::Balanced => PeaceWeights {
+                army_strength: 0.5,
+                occupation: 1.0,
+                exhaustion: 0.5,
+                capital_threat: 0.5,
+            },
+            Personality::Stubborn => PeaceWeights {
+                army_strength: 0.25,
+                occupation: 0.5,
+                exhaustion: 0.25,
+                capital_threat: 0.25,
+            },
+            Personality::Cautious => PeaceWeights {
+                army_strength: 1.0,
+                occupation: 1.5,
+                exhaustion: 1.0,
+                capital_threat: 1.0,
+            },
+        }
+    }
+}
+
+/// How much each part of the military situation makes an AI country give up more provinces for
+/// peace, see [`crate::war::MilitarySituation`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PeaceWeights {
+    /// Weight of the enemy's armies outnumbering the country's.
+    pub army_strength: f32,
+    /// Weight of the share of the country's provinces under occupation.
+    pub occupation: f32,
+    /// Weight of the country's war exhaustion.
+    pub exhaustion: f32,
+    /// Added while enemy armies stand near the country's capital.
+    pub capital_threat: f32,
+}
+
+/// Bonuses or penalties of a single country, set in the game setup screen on top of the
+/// difficulty.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handicap {
+    /// Added to the country's income multiplier, 0.25 is a quarter more income.
+    pub income_bonus: f32,
+    /// Infantry soldiers the country gets for free when the game starts.
+    pub free_manpower: u32,
+    /// How readily the country's AI joins wars and refuses peace, 1.0 is the usual.
+    pub aggression: f32,
+    /// How the country's AI weighs its military situation when offered peace.
+    pub personality: Personality,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Self {
+            income_bonus: 0.0,
+            free_manpower: 0,
+            aggression: 1.0,
+            personality: Personality::default(),
+        }
+    }
+}
+
+/// Rules chosen in the game setup screen. Stored in save files so they survive a reload.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    pub map_path: String,
+    pub seed: u64,
+    /// Number of AI countries kept in the game, `None` keeps every country of the map.
+    pub ai_nations: Option<usize>,
+    pub difficulty: Difficulty,
+    /// Ironman games autosave every turn and can't be reloaded from the pause menu.
+    pub ironman: bool,
+    /// Win by owning every ownable province on the map.
+    pub conquest_victory: bool,
+    /// The game ends after this many turns.
+    pub turn_limit: Option<u32>,
+    /// Handicaps per country name, countries missing from the map are ignored.
+    pub handicaps: BTreeMap<String, Handicap>,
+    /// Idle armies of the warring sides this many hexes from a battle join it, 0 turns
+    /// reinforcements off.
+    pub reinforcement_radius: u32,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            map_path: MAP_FILE_PATH.to_string(),
+            seed: rand::random(),
+            ai_nations: None,
+            difficulty: Difficulty::default(),
+            ironman: false,
+            conquest_victory: true,
+            turn_limit: None,
+            handicaps: BTreeMap::new(),
+            reinforcement_radius: 1,
+        }
+    }
+}
+
+/// Random number generator used by the simulation (AI nation limit, combat, ...) instead of the
+/// thread RNG. It's reseeded from the game seed whenever a game starts and at the start of every
+/// turn processing, so a game replays exactly the same from the same seed or the same save.
+#[derive(Resource)]
+pub struct GameRng(StdRng);
+
+impl GameRng {
+    pub fn for_turn(seed: u64, turn: u32) -> Self {
+        // Spread consecutive turns over the seed space so neighbouring turns don't get related streams.
+        let turn_salt = u64::from(turn).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        Self(StdRng::seed_from_u64(seed ^ turn_salt))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::for_turn(GameRules::default().seed, 0)
+    }
+}
+
+impl Deref for GameRng {
+    type Target = StdRng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Resource set once a victory condition was met, so the end of the game is only announced once.
+#[derive(Resource, Default)]
+pub struct GameEnded(pub bool);
+
+/// How the player's country fares, tracked until it falls. `stats` is set once it has lost all
+/// its provinces and armies, which brings up the defeat screen.
+#[derive(Resource, Default)]
+pub struct PlayerDefeat {
+    peak_provinces: usize,
+    pub stats: Option<DefeatStats>,
+}
+
+pub struct DefeatStats {
+    pub country: String,
+    pub turns_survived: u32,
+    pub peak_provinces: usize,
+}
+
+/// Lists the map files that can be picked in the game setup screen, including the ones added by
+/// mods, sorted by path.
+pub fn available_maps(vfs: &VirtualFs) -> Vec<String> {
+    let maps = vfs.list(MAPS_DIRECTORY, "json");
+    if maps.is_empty() {
+        warn!("No maps found in {}", MAPS_DIRECTORY);
+        return vec![MAP_FILE_PATH.to_string()];
+    }
+    maps
+}
+
+/// Removes AI countries beyond the configured limit. Which ones stay is decided by the game seed,
+/// so the same rules always produce the same set of opponents. Provinces of removed countries
+/// become unowned and their armies disband.
+pub fn apply_ai_nation_limit(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    player: Res<Player>,
+    mut rng: ResMut<GameRng>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    provinces: Query<(Entity, &Owner), With<Province>>,
+    armies: Query<(Entity, &Owner), With<Army>>,
+) {
+    let Some(limit) = rules.ai_nations else {
+        return;
+    };
+
+    let mut ai_countries: Vec<(Entity, &DisplayName)> = countries
+        .iter()
+        .filter(|(entity, _)| !player.is_human(*entity))
+        .collect();
+    if ai_countries.len() <= limit {
+        return;
+    }
+
+    // Sort first so the shuffle doesn't depend on query iteration order.
+    ai_countries.sort_by(|(_, a), (_, b)| a.0.cmp(&b.0));
+    ai_countries.shuffle(&mut **rng);
+
+    for &(country, name) in &ai_countries[limit..] {
+        info!("Removing {} from the game (AI nation limit)", name.0);
+        for (province, owner) in provinces.iter() {
+            if owner.0 == country {
+                commands.entity(province).remove::<Owner>();
+            }
+        }
+        for (army, owner) in armies.iter() {
+            if owner.0 == country {
+                commands.entity(army).despawn();
+            }
+        }
+        commands.entity(country).despawn();
+    }
+}
+
+/// Gives every country the country modifiers of the rules: the difficulty's bonus for AI
+/// countries and the handicaps.
+pub fn apply_rule_modifiers(
+    rules: Res<GameRules>,
+    player: Res<Player>,
+    mut countries: Query<(Entity, &DisplayName, &mut CountryModifiers), With<Country>>,
+) {
+    for (country, name, mut modifiers) in &mut countries {
+        let bonus = rules.difficulty.ai_income_bonus();
+        if player.is_human(country) || bonus == 0.0 {
+            modifiers.remove(DIFFICULTY_MODIFIER);
+        } else {
+            modifiers.set(CountryModifier {
+                name: DIFFICULTY_MODIFIER.to_string(),
+                income: bonus,
+                aggression: 1.0,
+            });
+        }
+
+        match rules.handicaps.get(&name.0) {
+            Some(handicap) => modifiers.set(CountryModifier {
+                name: HANDICAP_MODIFIER.to_string(),
+                income: handicap.income_bonus,
+                aggression: handicap.aggression,
+            }),
+            None => modifiers.remove(HANDICAP_MODIFIER),
+        }
+    }
+}
+
+/// Spawns the free manpower of the handicaps as an infantry army in each country's first
+/// province.
+pub fn grant_free_manpower(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    units: Res<UnitRegistry>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    provinces: Query<(&Province, &Owner)>,
+) {
+    let Some(infantry) = units.basic_infantry() else {
+        return;
+    };
+
+    for (country, name) in &countries {
+        let Some(handicap) = rules.handicaps.get(&name.0) else {
+            continue;
+        };
+        if handicap.free_manpower == 0 {
+            continue;
+        }
+        let first_province = provinces
+            .iter()
+            .filter(|(_, owner)| owner.0 == country)
+            .map(|(province, _)| *province.get_hex())
+            .min_by_key(|hex| (hex.q(), hex.r()));
+        let Some(hex) = first_province else {
+            continue;
+        };
+        info!(
+            "{} gets {} free soldiers (handicap)",
+            name.0, handicap.free_manpower
+        );
+        spawn_army(
+            &mut commands,
+            hex,
+            country,
+            ArmyComposition::default().with(infantry.clone(), handicap.free_manpower),
+        );
+    }
+}
+
+/// Restarts the [`GameRng`] stream for the current game seed and turn.
+pub fn reseed_game_rng(rules: Res<GameRules>, turn: Res<Turn>, mut rng: ResMut<GameRng>) {
+    *rng = GameRng::for_turn(rules.seed, turn.current_turn());
+}
+
+fn ironman_autosave(
+    rules: Res<GameRules>,
+    turn: Res<Turn>,
+    mut save_events: MessageWriter<SaveGameEvent>,
+) {
+    if rules.ironman && turn.current_turn() > 0 {
+        save_events.write(SaveGameEvent::new(IRONMAN_SLOT));
+    }
+}
+
+/// Announces the end of the game once one of the enabled victory conditions is met.
+fn check_victory_conditions(
+    rules: Res<GameRules>,
+    turn: Res<Turn>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+    mut game_ended: ResMut<GameEnded>,
+    provinces: Query<(&Province, Option<&Owner>)>,
+) {
+    if game_ended.0 {
+        return;
+    }
+
+    if let Some(limit) = rules.turn_limit
+        && turn.current_turn() >= limit
+    {
+        game_ended.0 = true;
+        notifications.push(Notification {
+            title: "⌛ Turn limit reached".to_string(),
+            text: format!("The game has ended after {} turns.", limit),
+            target: None,
+        });
+        return;
+    }
+
+    if !rules.conquest_victory {
+        return;
+    }
+    let Some(player_country) = player.country else {
+        return;
+    };
+    let conquered = provinces
+        .iter()
+        .filter(|(province, _)| province.is_ownable())
+        .all(|(_, owner)| owner.is_some_and(|o| o.0 == player_country));
+    if conquered {
+        game_ended.0 = true;
+        notifications.push(Notification {
+            title: "👑 Victory".to_string(),
+            text: "Every province of the known world is under our rule.".to_string(),
+            target: None,
+        });
+    }
+}
+
+/// Keeps track of the most provinces the player's country held, and ends its game once it has
+/// neither provinces nor armies left.
+fn check_player_defeat(
+    player: Res<Player>,
+    turn: Res<Turn>,
+    mut defeat: ResMut<PlayerDefeat>,
+    provinces: Query<&Owner, With<Province>>,
+    armies: Query<&Owner, With<Army>>,
+    names: Query<&DisplayName>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+    if defeat.stats.is_some() {
+        return;
+    }
+    let owned = provinces
+        .iter()
+        .filter(|owner| owner.0 == player_country)
+        .count();
+    defeat.peak_provinces = defeat.peak_provinces.max(owned);
+    if owned > 0 || armies.iter().any(|owner| owner.0 == player_country) {
+        return;
+    }
+
+    let country = names
+        .get(player_country)
+        .map_or("Unknown", |n| n.0.as_str())
+        .to_string();
+    info!("{} has fallen on turn {}", country, turn.current_turn());
+    defeat.stats = Some(DefeatStats {
+        country,
+        turns_survived: turn.current_turn(),
+        peak_provinces: defeat.peak_provinces,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::test_utils::TestGame;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn player_is_defeated_without_provinces_and_armies() {
+        let mut game = TestGame::new();
+        // Only checked in a running game, so the check follows each turn by hand here.
+        let end_turn = |game: &mut TestGame| {
+            game.end_turn();
+            game.world_mut()
+                .run_system_once(check_player_defeat)
+                .unwrap();
+        };
+        let country = game.spawn_country("Country");
+        let provinces = game.spawn_provinces(2, Some(country));
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+
+        end_turn(&mut game);
+        for province in provinces {
+            game.world_mut().entity_mut(province).remove::<Owner>();
+        }
+        end_turn(&mut game);
+        // The army fights on.
+        assert!(game.world().resource::<PlayerDefeat>().stats.is_none());
+
+        game.world_mut().despawn(army);
+        end_turn(&mut game);
+        let defeat = game.world().resource::<PlayerDefeat>();
+        let stats = defeat.stats.as_ref().unwrap();
+        assert_eq!(stats.country, "Country");
+        assert_eq!(stats.peak_provinces, 2);
+        assert_eq!(stats.turns_survived, 3);
+    }
+}
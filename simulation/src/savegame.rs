@@ -0,0 +1,1724 @@
+﻿use crate::army::{
+    ActivePath, Army, ArmyComposition, Battle, Following, HexPos, InBattle, Movement, spawn_army,
+};
+use crate::buildings::{Building, BuildingType, spawn_building};
+use crate::country::{Coffer, Country, DisplayName, MapColor, color_apart};
+use crate::diplomacy::{Alliances, MilitaryAccess};
+use crate::errors::{GameError, report_errors};
+use crate::forts::{Garrison, Sortie};
+use crate::hex::Hex;
+use crate::manpower::{Manpower, STARTING_MANPOWER};
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::modifiers::{Devastation, Modifier, Modifiers, Unrest};
+use crate::player::Player;
+use crate::releasables::{Releasables, spawn_nation};
+use crate::religion::{LeagueMember, Leagues};
+use crate::rules::{GameRng, GameRules};
+use crate::scoring::Scoreboard;
+use crate::storage;
+use crate::trade::{Embarked, Fleet, ShipType, spawn_fleet};
+use crate::turns::Turn;
+use crate::war::{
+    Conquered, Liberation, Occupied, SiegeProgress, Vassal, War, WarExhaustion, WarRelations,
+    WarScore, Wars,
+};
+use crate::weather::Weather;
+use crate::world::reset_session;
+use bevy::ecs::system::{SystemParam, SystemState};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        migrate_legacy_save();
+        app.insert_resource(SaveSlots::load())
+            .add_message::<SaveGameEvent>()
+            .add_message::<LoadGameEvent>()
+            .add_message::<DeleteSaveEvent>()
+            .add_systems(Update, handle_save_game.pipe(report_errors))
+            .add_systems(Update, handle_load_game.pipe(report_errors))
+            .add_systems(Update, handle_delete_save.pipe(report_errors));
+    }
+}
+
+/// Directory of the save slots, a file for each.
+const SAVES_DIR: &str = "saves";
+/// Single save file of older versions, moved into a slot of the same name.
+const LEGACY_SAVE_FILE_PATH: &str = "savegame.json";
+const LEGACY_SLOT: &str = "savegame";
+/// Slot ironman games autosave to.
+pub const IRONMAN_SLOT: &str = "Ironman";
+/// Longest name a save slot can have.
+const MAX_SLOT_NAME_LENGTH: usize = 40;
+
+/// Saves the game into the slot, overwriting what was saved there.
+#[derive(Event, Message)]
+pub struct SaveGameEvent {
+    pub slot: String,
+}
+
+impl SaveGameEvent {
+    pub fn new(slot: &str) -> Self {
+        Self {
+            slot: slot.to_string(),
+        }
+    }
+}
+
+#[derive(Event, Message)]
+pub struct LoadGameEvent {
+    pub slot: String,
+}
+
+impl LoadGameEvent {
+    pub fn new(slot: &str) -> Self {
+        Self {
+            slot: slot.to_string(),
+        }
+    }
+}
+
+#[derive(Event, Message)]
+pub struct DeleteSaveEvent {
+    pub slot: String,
+}
+
+// ============================================================================
+// SAVE SLOTS
+// ============================================================================
+
+/// What the load screen shows of a save, read from the start of the save file.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveSlot {
+    #[serde(skip)]
+    pub name: String,
+    pub turn: u32,
+    #[serde(rename = "player_country_name")]
+    pub country: Option<String>,
+    /// Seconds since the Unix epoch, 0 in saves made before it was recorded.
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+/// Every save slot, the most recent first. Kept up to date as games are saved and deleted, so the
+/// menus don't read the saves every frame.
+#[derive(Resource, Default)]
+pub struct SaveSlots(pub Vec<SaveSlot>);
+
+impl SaveSlots {
+    fn load() -> Self {
+        let mut slots: Vec<SaveSlot> = storage::list(SAVES_DIR)
+            .iter()
+            .filter_map(|file| file.strip_suffix(".json"))
+            .filter_map(|name| {
+                let content = storage::read(&slot_path(name)).ok()?;
+                match serde_json::from_str::<SaveSlot>(&content) {
+                    Ok(slot) => Some(SaveSlot {
+                        name: name.to_string(),
+                        ..slot
+                    }),
+                    Err(e) => {
+                        warn!("Skipping damaged save {}: {}", name, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        slots.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then(a.name.cmp(&b.name)));
+        Self(slots)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|slot| slot.name == name)
+    }
+}
+
+fn slot_path(slot: &str) -> String {
+    format!("{}/{}.json", SAVES_DIR, slot)
+}
+
+/// Name of a save slot typed by the player, without the characters a file name can't have.
+/// `None` if nothing is left.
+pub fn slot_name(input: &str) -> Option<String> {
+    let name: String = input
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .take(MAX_SLOT_NAME_LENGTH)
+        .collect();
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Moves the save of older versions into a slot, so it shows up on the load screen.
+fn migrate_legacy_save() {
+    if !storage::exists(LEGACY_SAVE_FILE_PATH) || storage::exists(&slot_path(LEGACY_SLOT)) {
+        return;
+    }
+    let moved = storage::read(LEGACY_SAVE_FILE_PATH)
+        .and_then(|content| storage::write(&slot_path(LEGACY_SLOT), &content))
+        .and_then(|()| storage::remove(LEGACY_SAVE_FILE_PATH));
+    match moved {
+        Ok(()) => info!("Moved {} into the save slots", LEGACY_SAVE_FILE_PATH),
+        Err(e) => warn!("Failed to move {}: {}", LEGACY_SAVE_FILE_PATH, e),
+    }
+}
+
+/// Date and time of a save as shown in the menus, in UTC.
+pub fn format_timestamp(secs: u64) -> String {
+    // Days to the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let minutes = secs % 86_400 / 60;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+// ============================================================================
+// SAVE DATA STRUCTURES
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    pub turn: u32,
+    pub player_country_name: Option<String>,
+    pub countries: Vec<CountrySaveData>,
+    pub provinces: Vec<ProvinceSaveData>,
+    pub armies: Vec<ArmySaveData>,
+    /// Missing in saves made before fleets were saved, those load without fleets.
+    #[serde(default)]
+    pub fleets: Vec<FleetSaveData>,
+    pub wars: Vec<WarSaveData>,
+    /// Battles being fought. Missing in saves made before battles were saved, their armies load
+    /// out of battle.
+    #[serde(default)]
+    pub battles: Vec<BattleSaveData>,
+    /// Missing in saves made before game rules existed, those load with the default rules.
+    #[serde(default)]
+    pub rules: GameRules,
+    /// Missing in saves made before the leagues were saved, those load without tension.
+    #[serde(default)]
+    pub leagues: Leagues,
+    /// Missing in saves made before the weather was saved, those load with clear skies.
+    #[serde(default)]
+    pub weather: Weather,
+    /// Battles and wars won so far. Missing in saves made before the scores were saved, those
+    /// load with nothing won.
+    #[serde(default)]
+    pub scoreboard: Scoreboard,
+    /// Seconds since the Unix epoch, see [`SaveSlot::saved_at`].
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CountrySaveData {
+    pub name: String,
+    pub coffer: f32,
+    /// Missing in saves made before manpower existed, those load with the starting pool.
+    #[serde(default = "starting_manpower")]
+    pub manpower: u32,
+    #[serde(default)]
+    pub war_exhaustion: f32,
+    /// Country it is a vassal of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlord: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allies: Vec<String>,
+    /// Allies sharing their vision with the country, see [`Alliances`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_vision: Vec<String>,
+    /// Countries that granted it military access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub military_access: Vec<String>,
+    /// Whether it is a member of the league of its faith.
+    #[serde(default)]
+    pub league_member: bool,
+    /// Map color in sRGB, as the player may have picked it. Missing in saves made before map
+    /// colors were saved, those keep the colors of the map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_color: Option<[f32; 3]>,
+}
+
+fn starting_manpower() -> u32 {
+    STARTING_MANPOWER
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProvinceSaveData {
+    #[serde(flatten)]
+    pub hex: Hex,
+    pub owner: Option<String>,
+    pub occupier: Option<String>,
+    /// Country the province was taken from in a peace, see [`Conquered`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conquered_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub siege: Option<SiegeSaveData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buildings: Vec<BuildingType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub unrest: u32,
+    #[serde(default)]
+    pub devastation: u32,
+    /// Soldiers left in the garrison of its fort, see [`Garrison`]. Missing while the garrison has
+    /// never fought.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub garrison: Option<u32>,
+    /// Turns the owner's side has been retaking the occupied province, see [`Liberation`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liberation: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SiegeSaveData {
+    /// The leader of the siege first, see [`SiegeProgress::besiegers`].
+    pub besiegers: Vec<String>,
+    pub soldiers: u32,
+    pub required: u32,
+    pub progress: u32,
+    pub progress_required: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArmySaveData {
+    #[serde(flatten)]
+    pub hex: Hex,
+    pub owner: String,
+    /// Soldiers per unit type id, stored next to the other fields.
+    #[serde(flatten)]
+    pub composition: ArmyComposition,
+    /// Hexes the army still marches through. Missing in saves made before marching orders were
+    /// saved, those armies load standing still.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<Hex>,
+    /// Hex of the friendly army it follows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub following: Option<Hex>,
+    /// Owner of the army it follows, which can be an ally. Saves made before armies could follow
+    /// those of other countries leave it out, their armies follow one of their own country.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub following_owner: Option<String>,
+    /// Transport the army is aboard, by its index in [`SaveData::fleets`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embarked: Option<usize>,
+    /// Movement points saved up towards the next hex of its path, see [`Movement`].
+    #[serde(default)]
+    pub movement: u32,
+    /// Hex of the fort the army sallied out of, see [`Sortie`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sortie: Option<Hex>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FleetSaveData {
+    #[serde(flatten)]
+    pub hex: Hex,
+    pub owner: String,
+    pub ships: ShipType,
+    #[serde(default)]
+    pub privateering: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<Hex>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WarSaveData {
+    pub attacker: String,
+    pub defender: String,
+    #[serde(default)]
+    pub goal: Option<Hex>,
+    #[serde(default)]
+    pub started: u32,
+    /// The attacker's battle and ticking war score, see [`WarScore`].
+    #[serde(default)]
+    pub battle_score: i32,
+    #[serde(default)]
+    pub ticking_score: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BattleSaveData {
+    pub location: Hex,
+    pub attacked_from: Hex,
+    pub attacker_country: String,
+    pub defender_country: String,
+    /// Armies of each side, by their index in [`SaveData::armies`]. Several armies can stand on
+    /// the hex of a battle, so they can't be told apart by their hex.
+    pub attackers: Vec<usize>,
+    pub defenders: Vec<usize>,
+    pub round: u32,
+    pub last_damage_attacker: u32,
+    pub last_damage_defender: u32,
+}
+
+// ============================================================================
+// SAVE GAME
+// ============================================================================
+
+/// Armies with everything that is saved about them.
+type SavedArmies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static HexPos,
+        &'static Owner,
+        &'static ArmyComposition,
+        Option<&'static ActivePath>,
+        Option<&'static Following>,
+        Option<&'static Embarked>,
+        (Option<&'static Movement>, Option<&'static Sortie>),
+    ),
+    With<Army>,
+>;
+
+/// Fleets with everything that is saved about them.
+type SavedFleets<'w, 's> = Query<'w, 's, (Entity, &'static Fleet, &'static Owner)>;
+
+/// Provinces with everything that is saved about them, their buildings are among the children.
+type SavedProvinces<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Province,
+        Option<&'static Owner>,
+        Option<&'static Occupied>,
+        Option<&'static Conquered>,
+        Option<&'static SiegeProgress>,
+        Option<&'static Children>,
+        (
+            Option<&'static Modifiers>,
+            Option<&'static Unrest>,
+            Option<&'static Devastation>,
+            Option<&'static Garrison>,
+            Option<&'static Liberation>,
+        ),
+    ),
+>;
+
+/// What is saved about a country besides its coffer.
+type CountryState<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Manpower>,
+        Option<&'static WarExhaustion>,
+        Option<&'static Vassal>,
+        Option<&'static Alliances>,
+        Option<&'static MilitaryAccess>,
+        Has<LeagueMember>,
+        Option<&'static MapColor>,
+    ),
+>;
+
+/// The part of the running game that goes into a save.
+#[derive(SystemParam)]
+struct SaveSource<'w, 's> {
+    turn: Res<'w, Turn>,
+    player: Res<'w, Player>,
+    rules: Res<'w, GameRules>,
+    countries: Query<'w, 's, (Entity, &'static DisplayName, &'static Coffer), With<Country>>,
+    country_state: CountryState<'w, 's>,
+    leagues: Res<'w, Leagues>,
+    weather: Res<'w, Weather>,
+    scoreboard: Res<'w, Scoreboard>,
+    provinces: SavedProvinces<'w, 's>,
+    buildings: Query<'w, 's, &'static Building>,
+    armies: SavedArmies<'w, 's>,
+    fleets: SavedFleets<'w, 's>,
+    battles: Query<'w, 's, &'static Battle>,
+    wars: Res<'w, Wars>,
+    war_query: Query<'w, 's, (&'static War, Option<&'static WarScore>)>,
+}
+
+impl SaveSource<'_, '_> {
+    fn save_data(&self) -> SaveData {
+        let country_names = build_country_names(&self.countries);
+        SaveData {
+            turn: self.turn.current_turn(),
+            player_country_name: get_player_country_name(&self.player, &self.countries),
+            countries: collect_countries_data(&self.countries, &self.country_state, &country_names),
+            provinces: collect_provinces_data(&self.provinces, &self.buildings, &country_names),
+            armies: collect_armies_data(
+                &self.armies,
+                &self.fleets,
+                &self.provinces,
+                &country_names,
+            ),
+            fleets: collect_fleets_data(&self.fleets, &country_names),
+            wars: collect_wars_data(&self.wars, &self.war_query, &self.provinces, &country_names),
+            battles: collect_battles_data(&self.battles, &self.armies, &country_names),
+            rules: self.rules.clone(),
+            leagues: self.leagues.clone(),
+            weather: self.weather.clone(),
+            scoreboard: self.scoreboard.clone(),
+            saved_at: storage::now(),
+        }
+    }
+}
+
+fn handle_save_game(
+    mut events: MessageReader<SaveGameEvent>,
+    mut slots: ResMut<SaveSlots>,
+    source: SaveSource,
+) -> Result<(), GameError> {
+    for event in events.read() {
+        info!("Saving game to {}...", event.slot);
+        let written = write_save_file(&event.slot, &source.save_data());
+        *slots = SaveSlots::load();
+        written?;
+    }
+    Ok(())
+}
+
+fn build_country_names(
+    countries: &Query<(Entity, &DisplayName, &Coffer), With<Country>>,
+) -> HashMap<Entity, String> {
+    countries
+        .iter()
+        .map(|(e, name, _)| (e, name.0.clone()))
+        .collect()
+}
+
+fn get_player_country_name(
+    player: &Res<Player>,
+    countries: &Query<(Entity, &DisplayName, &Coffer), With<Country>>,
+) -> Option<String> {
+    player
+        .country
+        .and_then(|e| countries.get(e).ok().map(|(_, name, _)| name.0.clone()))
+}
+
+fn collect_countries_data(
+    countries: &Query<(Entity, &DisplayName, &Coffer), With<Country>>,
+    country_state: &CountryState,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<CountrySaveData> {
+    let names = |countries: &mut dyn Iterator<Item = Entity>| -> Vec<String> {
+        let mut names: Vec<String> = countries
+            .filter_map(|country| country_names.get(&country).cloned())
+            .collect();
+        // Sets have no order, sorting keeps the saves of the same game alike.
+        names.sort();
+        names
+    };
+    countries
+        .iter()
+        .map(|(entity, name, coffer)| {
+            let (manpower, exhaustion, vassal, alliances, access, league_member, map_color) =
+                country_state.get(entity).unwrap_or_default();
+            CountrySaveData {
+                name: name.0.clone(),
+                coffer: coffer.get_ducats(),
+                manpower: manpower.map_or(0, |m| m.0),
+                war_exhaustion: exhaustion.map_or(0.0, |e| e.0),
+                overlord: vassal.and_then(|v| country_names.get(&v.overlord).cloned()),
+                allies: alliances.map_or_else(Vec::new, |a| names(&mut a.allies())),
+                shared_vision: alliances.map_or_else(Vec::new, |a| names(&mut a.vision_sharers())),
+                military_access: access.map_or_else(Vec::new, |a| names(&mut a.granted_by())),
+                league_member,
+                map_color: map_color.map(|color| color.0.to_srgba().to_f32_array_no_alpha()),
+            }
+        })
+        .collect()
+}
+
+fn collect_provinces_data(
+    provinces: &SavedProvinces,
+    buildings: &Query<&Building>,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<ProvinceSaveData> {
+    let name = |country: Entity| country_names.get(&country).cloned();
+    provinces
+        .iter()
+        .map(
+            |(
+                _,
+                prov,
+                owner,
+                occupied,
+                conquered,
+                siege,
+                children,
+                (modifiers, unrest, devastation, garrison, liberation),
+            )| ProvinceSaveData {
+                hex: *prov.get_hex(),
+                owner: owner.and_then(|o| name(o.0)),
+                occupier: occupied.and_then(|o| name(o.occupier)),
+                conquered_from: conquered.and_then(|c| name(c.from)),
+                siege: siege.map(|siege| SiegeSaveData {
+                    besiegers: siege.besiegers.iter().filter_map(|&c| name(c)).collect(),
+                    soldiers: siege.soldiers,
+                    required: siege.required,
+                    progress: siege.progress,
+                    progress_required: siege.progress_required,
+                }),
+                buildings: children
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&child| buildings.get(child).ok())
+                    .map(|building| building.building_type)
+                    .collect(),
+                modifiers: modifiers.map_or_else(Vec::new, |m| m.0.clone()),
+                unrest: unrest.map_or(0, |u| u.0),
+                devastation: devastation.map_or(0, |d| d.0),
+                garrison: garrison.map(|g| g.0),
+                liberation: liberation.map(|l| l.progress),
+            },
+        )
+        .collect()
+}
+
+/// Whether the army or fleet goes into the save, only those of the countries saved do.
+fn is_saved_army(owner: &Owner, country_names: &HashMap<Entity, String>) -> bool {
+    country_names.contains_key(&owner.0)
+}
+
+fn collect_armies_data(
+    armies: &SavedArmies,
+    fleets: &SavedFleets,
+    provinces: &SavedProvinces,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<ArmySaveData> {
+    // Same order as in collect_fleets_data.
+    let fleet_indices: HashMap<Entity, usize> = fleets
+        .iter()
+        .filter(|(_, _, owner)| is_saved_army(owner, country_names))
+        .enumerate()
+        .map(|(index, (fleet, ..))| (fleet, index))
+        .collect();
+    armies
+        .iter()
+        .filter(|(_, _, owner, ..)| is_saved_army(owner, country_names))
+        .map(
+            |(_, pos, owner, comp, active_path, following, embarked, (movement, sortie))| {
+                // Armies are told apart by their owner and hex, like in player commands.
+                let target = following
+                    .and_then(|following| armies.get(following.target).ok())
+                    .filter(|(_, _, target_owner, ..)| is_saved_army(target_owner, country_names));
+                ArmySaveData {
+                    hex: pos.0,
+                    owner: country_names[&owner.0].clone(),
+                    composition: comp.clone(),
+                    path: active_path
+                        .map(|active_path| active_path.path.iter().copied().collect())
+                        .unwrap_or_default(),
+                    following: target.map(|(_, target_pos, ..)| target_pos.0),
+                    following_owner: target
+                        .map(|(_, _, target_owner, ..)| country_names[&target_owner.0].clone()),
+                    embarked: embarked
+                        .and_then(|embarked| fleet_indices.get(&embarked.fleet).copied()),
+                    movement: movement.map_or(0, |m| m.points),
+                    sortie: sortie
+                        .and_then(|sortie| provinces.get(sortie.province).ok())
+                        .map(|(_, province, ..)| *province.get_hex()),
+                }
+            },
+        )
+        .collect()
+}
+
+fn collect_fleets_data(
+    fleets: &SavedFleets,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<FleetSaveData> {
+    fleets
+        .iter()
+        .filter(|(_, _, owner)| is_saved_army(owner, country_names))
+        .map(|(_, fleet, owner)| FleetSaveData {
+            hex: fleet.hex,
+            owner: country_names[&owner.0].clone(),
+            ships: fleet.ships,
+            privateering: fleet.privateering,
+            destination: fleet.destination,
+        })
+        .collect()
+}
+
+fn collect_wars_data(
+    wars: &Res<Wars>,
+    war_query: &Query<(&War, Option<&WarScore>)>,
+    provinces: &SavedProvinces,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<WarSaveData> {
+    wars.active_wars
+        .iter()
+        .filter_map(|&war_entity| {
+            war_query.get(war_entity).ok().and_then(|(war, score)| {
+                Some(WarSaveData {
+                    attacker: country_names.get(&war.attacker)?.clone(),
+                    defender: country_names.get(&war.defender)?.clone(),
+                    goal: war
+                        .goal
+                        .and_then(|goal| provinces.get(goal).ok())
+                        .map(|(_, province, ..)| *province.get_hex()),
+                    started: war.started,
+                    battle_score: score.map_or(0, |score| score.battles),
+                    ticking_score: score.map_or(0, |score| score.ticking),
+                })
+            })
+        })
+        .collect()
+}
+
+fn collect_battles_data(
+    battles: &Query<&Battle>,
+    armies: &SavedArmies,
+    country_names: &HashMap<Entity, String>,
+) -> Vec<BattleSaveData> {
+    // Same order as in collect_armies_data.
+    let army_indices: HashMap<Entity, usize> = armies
+        .iter()
+        .filter(|(_, _, owner, ..)| is_saved_army(owner, country_names))
+        .enumerate()
+        .map(|(index, (army, ..))| (army, index))
+        .collect();
+    let indices = |armies: &[Entity]| -> Vec<usize> {
+        armies
+            .iter()
+            .filter_map(|army| army_indices.get(army).copied())
+            .collect()
+    };
+    battles
+        .iter()
+        .filter_map(|battle| {
+            Some(BattleSaveData {
+                location: battle.location,
+                attacked_from: battle.attacked_from,
+                attacker_country: country_names.get(&battle.attacker_country)?.clone(),
+                defender_country: country_names.get(&battle.defender_country)?.clone(),
+                attackers: indices(&battle.attackers),
+                defenders: indices(&battle.defenders),
+                round: battle.round,
+                last_damage_attacker: battle.last_damage_attacker,
+                last_damage_defender: battle.last_damage_defender,
+            })
+        })
+        .collect()
+}
+
+fn write_save_file(slot: &str, save_data: &SaveData) -> Result<(), GameError> {
+    let path = slot_path(slot);
+    let json = serde_json::to_string_pretty(save_data).map_err(|e| {
+        GameError::new(
+            "Saving failed",
+            format!("Could not serialize the game: {}", e),
+        )
+    })?;
+    storage::write(&path, &json)
+        .map_err(|e| GameError::new("Saving failed", format!("Could not write {}: {}", path, e)))?;
+    info!("Game saved to {}", path);
+    Ok(())
+}
+
+fn handle_delete_save(
+    mut events: MessageReader<DeleteSaveEvent>,
+    mut slots: ResMut<SaveSlots>,
+) -> Result<(), GameError> {
+    for event in events.read() {
+        let path = slot_path(&event.slot);
+        let removed = storage::remove(&path);
+        *slots = SaveSlots::load();
+        removed.map_err(|e| {
+            GameError::new(
+                "Deleting failed",
+                format!("Could not delete {}: {}", path, e),
+            )
+        })?;
+        info!("Deleted save {}", event.slot);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// LOAD GAME
+// ============================================================================
+
+/// Entities of the running game a loaded save replaces.
+#[derive(SystemParam)]
+struct ReplacedEntities<'w, 's> {
+    armies: Query<'w, 's, Entity, With<Army>>,
+    fleets: Query<'w, 's, Entity, With<Fleet>>,
+    battles: Query<'w, 's, Entity, With<Battle>>,
+    buildings: Query<'w, 's, Entity, With<Building>>,
+    wars: Query<'w, 's, Entity, With<War>>,
+}
+
+/// The part of the running game a save is loaded into.
+#[derive(SystemParam)]
+struct LoadTarget<'w, 's> {
+    commands: Commands<'w, 's>,
+    turn: ResMut<'w, Turn>,
+    player: ResMut<'w, Player>,
+    countries: Query<'w, 's, (Entity, &'static DisplayName, &'static MapColor), With<Country>>,
+    replaced: ReplacedEntities<'w, 's>,
+    wars: ResMut<'w, Wars>,
+    province_map: Res<'w, ProvinceHexMap>,
+    rules: ResMut<'w, GameRules>,
+    rng: ResMut<'w, GameRng>,
+    leagues: ResMut<'w, Leagues>,
+    weather: ResMut<'w, Weather>,
+    scoreboard: ResMut<'w, Scoreboard>,
+    releasables: Res<'w, Releasables>,
+}
+
+impl LoadTarget<'_, '_> {
+    fn load(&mut self, save_data: &SaveData) {
+        let (mut country_lookup, mut country_colors) = build_country_lookups(&self.countries);
+
+        if save_data.rules.map_path != self.rules.map_path {
+            warn!(
+                "Save was made on map {} but {} is loaded",
+                save_data.rules.map_path, self.rules.map_path
+            );
+        }
+        *self.rules = save_data.rules.clone();
+        *self.leagues = save_data.leagues.clone();
+        *self.weather = save_data.weather.clone();
+        *self.scoreboard = save_data.scoreboard.clone();
+        let commands = &mut self.commands;
+        remove_countries_missing_from_save(commands, save_data, &country_lookup);
+        restore_released_nations(
+            commands,
+            save_data,
+            &self.releasables,
+            &mut country_lookup,
+            &mut country_colors,
+        );
+        restore_turn_and_player(save_data, &mut self.turn, &mut self.player, &country_lookup);
+        *self.rng = GameRng::for_turn(self.rules.seed, self.turn.current_turn());
+        restore_countries(commands, save_data, &country_lookup);
+        restore_provinces(
+            commands,
+            save_data,
+            &self.province_map,
+            &self.replaced.buildings,
+            &country_lookup,
+        );
+        let fleets = restore_fleets(
+            commands,
+            &save_data.fleets,
+            &self.replaced.fleets,
+            &country_lookup,
+        );
+        let armies = restore_armies(
+            commands,
+            &save_data.armies,
+            &fleets,
+            &self.replaced.armies,
+            &self.province_map,
+            &country_lookup,
+        );
+        restore_battles(
+            commands,
+            &save_data.battles,
+            &self.replaced.battles,
+            &armies,
+            &country_lookup,
+        );
+        restore_wars(
+            commands,
+            save_data,
+            &self.replaced.wars,
+            &mut self.wars,
+            &self.province_map,
+            &country_lookup,
+        );
+    }
+}
+
+fn handle_load_game(
+    world: &mut World,
+    events: &mut SystemState<MessageReader<LoadGameEvent>>,
+) -> Result<(), GameError> {
+    let slots: Vec<String> = events
+        .get_mut(world)
+        .read()
+        .map(|event| event.slot.clone())
+        .collect();
+    for slot in slots {
+        info!("Loading game from {}...", slot);
+        let save_data = read_save_file(&slot)?;
+        load_save(world, &save_data);
+        info!("Game loaded successfully!");
+    }
+    Ok(())
+}
+
+/// Replaces the running game with the saved one, starting from the same clean session a new game
+/// starts from, see [`reset_session`].
+fn load_save(world: &mut World, save_data: &SaveData) {
+    reset_session(world);
+    let mut target = SystemState::<LoadTarget>::new(world);
+    target.get_mut(world).load(save_data);
+    target.apply(world);
+}
+
+fn read_save_file(slot: &str) -> Result<SaveData, GameError> {
+    let path = slot_path(slot);
+    let content = storage::read(&path)
+        .map_err(|e| GameError::new("Loading failed", format!("Could not read {}: {}", path, e)))?;
+    serde_json::from_str(&content).map_err(|e| {
+        GameError::new(
+            "Corrupt save",
+            format!(
+                "The save file {} is damaged and can't be loaded: {}",
+                path, e
+            ),
+        )
+    })
+}
+
+fn build_country_lookups(
+    countries: &Query<(Entity, &DisplayName, &MapColor), With<Country>>,
+) -> (HashMap<String, Entity>, HashMap<String, Color>) {
+    let lookup = countries
+        .iter()
+        .map(|(e, name, _)| (name.0.clone(), e))
+        .collect();
+    let colors = countries
+        .iter()
+        .map(|(_, name, color)| (name.0.clone(), color.0))
+        .collect();
+    (lookup, colors)
+}
+
+fn restore_turn_and_player(
+    save_data: &SaveData,
+    turn: &mut ResMut<Turn>,
+    player: &mut ResMut<Player>,
+    country_lookup: &HashMap<String, Entity>,
+) {
+    turn.set(save_data.turn);
+    player.country = save_data
+        .player_country_name
+        .as_ref()
+        .and_then(|name| country_lookup.get(name).copied());
+}
+
+/// Countries dropped by the AI nation limit aren't in the save, so they must not come back.
+fn remove_countries_missing_from_save(
+    commands: &mut Commands,
+    save_data: &SaveData,
+    country_lookup: &HashMap<String, Entity>,
+) {
+    for (name, &entity) in country_lookup {
+        if !save_data.countries.iter().any(|c| &c.name == name) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Nations released during the saved game are created again, their provinces and armies are
+/// restored along with everyone else's.
+fn restore_released_nations(
+    commands: &mut Commands,
+    save_data: &SaveData,
+    releasables: &Releasables,
+    country_lookup: &mut HashMap<String, Entity>,
+    country_colors: &mut HashMap<String, Color>,
+) {
+    for country_save in &save_data.countries {
+        if country_lookup.contains_key(&country_save.name) {
+            continue;
+        }
+        let Some(def) = releasables
+            .find(&country_save.name)
+            .and_then(|nation| releasables.get(nation))
+        else {
+            warn!("Country {} of the save isn't on the map", country_save.name);
+            continue;
+        };
+        let color = color_apart(&def.country, country_colors.values().copied());
+        let entity = spawn_nation(commands, &def.country, color);
+        country_lookup.insert(def.country.name.clone(), entity);
+        country_colors.insert(def.country.name.clone(), color);
+    }
+}
+
+fn restore_countries(
+    commands: &mut Commands,
+    save_data: &SaveData,
+    country_lookup: &HashMap<String, Entity>,
+) {
+    let lookup = |names: &[String]| -> HashSet<Entity> {
+        names
+            .iter()
+            .filter_map(|name| country_lookup.get(name).copied())
+            .collect()
+    };
+    for country_save in &save_data.countries {
+        if let Some(&entity) = country_lookup.get(&country_save.name) {
+            commands
+                .entity(entity)
+                .remove::<(Vassal, Alliances, MilitaryAccess, LeagueMember)>()
+                .insert((
+                    Coffer(country_save.coffer),
+                    Manpower(country_save.manpower),
+                    WarExhaustion(country_save.war_exhaustion),
+                ));
+            if let Some(overlord_name) = &country_save.overlord
+                && let Some(&overlord) = country_lookup.get(overlord_name)
+            {
+                commands.entity(entity).insert(Vassal { overlord });
+            }
+            let allies = lookup(&country_save.allies);
+            if !allies.is_empty() {
+                let shared_vision = lookup(&country_save.shared_vision);
+                commands
+                    .entity(entity)
+                    .insert(Alliances::new(allies, shared_vision));
+            }
+            let granted_by = lookup(&country_save.military_access);
+            if !granted_by.is_empty() {
+                commands
+                    .entity(entity)
+                    .insert(MilitaryAccess::new(granted_by));
+            }
+            if country_save.league_member {
+                commands.entity(entity).insert(LeagueMember);
+            }
+            if let Some([r, g, b]) = country_save.map_color {
+                commands
+                    .entity(entity)
+                    .insert(MapColor(Color::srgb(r, g, b)));
+            }
+        }
+    }
+}
+
+fn restore_provinces(
+    commands: &mut Commands,
+    save_data: &SaveData,
+    province_map: &Res<ProvinceHexMap>,
+    buildings: &Query<Entity, With<Building>>,
+    country_lookup: &HashMap<String, Entity>,
+) {
+    for building in buildings.iter() {
+        commands.entity(building).despawn();
+    }
+
+    for prov_save in &save_data.provinces {
+        let hex = prov_save.hex;
+        if let Some(&prov_entity) = province_map.get_entity(&hex) {
+            commands.entity(prov_entity).remove::<(
+                Owner,
+                Occupied,
+                Conquered,
+                SiegeProgress,
+                Modifiers,
+                Unrest,
+                Devastation,
+                Garrison,
+                Liberation,
+            )>();
+            if !prov_save.modifiers.is_empty() {
+                commands
+                    .entity(prov_entity)
+                    .insert(Modifiers(prov_save.modifiers.clone()));
+            }
+            if prov_save.unrest > 0 {
+                commands
+                    .entity(prov_entity)
+                    .insert(Unrest(prov_save.unrest));
+            }
+            if prov_save.devastation > 0 {
+                commands
+                    .entity(prov_entity)
+                    .insert(Devastation(prov_save.devastation));
+            }
+            if let Some(soldiers) = prov_save.garrison {
+                commands.entity(prov_entity).insert(Garrison(soldiers));
+            }
+            if let Some(progress) = prov_save.liberation {
+                commands.entity(prov_entity).insert(Liberation { progress });
+            }
+
+            if let Some(owner_name) = &prov_save.owner
+                && let Some(&owner_entity) = country_lookup.get(owner_name)
+            {
+                commands.entity(prov_entity).insert(Owner(owner_entity));
+                for &building_type in &prov_save.buildings {
+                    spawn_building(commands, prov_entity, building_type, owner_entity);
+                }
+            }
+
+            if let Some(from_name) = &prov_save.conquered_from
+                && let Some(&from) = country_lookup.get(from_name)
+            {
+                commands.entity(prov_entity).insert(Conquered { from });
+            }
+
+            if let Some(siege_save) = &prov_save.siege {
+                let besiegers: Vec<Entity> = siege_save
+                    .besiegers
+                    .iter()
+                    .filter_map(|name| country_lookup.get(name).copied())
+                    .collect();
+                if !besiegers.is_empty() {
+                    commands.entity(prov_entity).insert(SiegeProgress {
+                        besiegers,
+                        soldiers: siege_save.soldiers,
+                        required: siege_save.required,
+                        progress: siege_save.progress,
+                        progress_required: siege_save.progress_required,
+                    });
+                }
+            }
+
+            if let Some(occupier_name) = &prov_save.occupier
+                && let Some(&occupier_entity) = country_lookup.get(occupier_name)
+            {
+                commands.entity(prov_entity).insert(Occupied {
+                    occupier: occupier_entity,
+                });
+            }
+        }
+    }
+}
+
+/// Spawns the saved fleets, returning the fleets spawned in the order they were saved in.
+fn restore_fleets(
+    commands: &mut Commands,
+    saved_fleets: &[FleetSaveData],
+    fleets: &Query<Entity, With<Fleet>>,
+    country_lookup: &HashMap<String, Entity>,
+) -> Vec<Option<Entity>> {
+    for fleet_entity in fleets.iter() {
+        commands.entity(fleet_entity).despawn();
+    }
+
+    saved_fleets
+        .iter()
+        .map(|fleet_save| {
+            let &owner = country_lookup.get(&fleet_save.owner)?;
+            let fleet = spawn_fleet(commands, fleet_save.hex, fleet_save.ships, owner);
+            commands.entity(fleet).insert(Fleet {
+                privateering: fleet_save.privateering,
+                destination: fleet_save.destination,
+                ..Fleet::new(fleet_save.hex, fleet_save.ships)
+            });
+            Some(fleet)
+        })
+        .collect()
+}
+
+/// Spawns the saved armies, returning the armies spawned in the order they were saved in.
+/// Armies aboard a transport go back aboard it.
+fn restore_armies(
+    commands: &mut Commands,
+    saved_armies: &[ArmySaveData],
+    fleets: &[Option<Entity>],
+    armies: &Query<Entity, With<Army>>,
+    province_map: &ProvinceHexMap,
+    country_lookup: &HashMap<String, Entity>,
+) -> Vec<Option<Entity>> {
+    for army_entity in armies.iter() {
+        commands.entity(army_entity).despawn();
+    }
+
+    let armies: Vec<Option<Entity>> = saved_armies
+        .iter()
+        .map(|army_save| spawn_army_from_save(commands, army_save, country_lookup))
+        .collect();
+    let spawned: Vec<(Entity, &ArmySaveData)> = armies
+        .iter()
+        .zip(saved_armies)
+        .filter_map(|(army, army_save)| army.map(|army| (army, army_save)))
+        .collect();
+    for (army, army_save) in &spawned {
+        if let Some(fleet) = army_save
+            .embarked
+            .and_then(|index| fleets.get(index).copied().flatten())
+        {
+            commands.entity(*army).insert(Embarked { fleet });
+        }
+        if army_save.movement > 0 {
+            commands.entity(*army).insert(Movement {
+                points: army_save.movement,
+            });
+        }
+        if let Some(&province) = army_save
+            .sortie
+            .and_then(|hex| province_map.get_entity(&hex))
+        {
+            commands.entity(*army).insert(Sortie { province });
+        }
+    }
+    restore_army_orders(commands, &spawned);
+    armies
+}
+
+/// Gives the loaded armies their marching orders back, once every army exists to be followed.
+fn restore_army_orders(commands: &mut Commands, spawned: &[(Entity, &ArmySaveData)]) {
+    let by_hex: HashMap<(&str, Hex), Entity> = spawned
+        .iter()
+        .map(|(army, army_save)| ((army_save.owner.as_str(), army_save.hex), *army))
+        .collect();
+    for (army, army_save) in spawned {
+        if !army_save.path.is_empty() {
+            commands.entity(*army).insert(ActivePath {
+                path: VecDeque::from(army_save.path.clone()),
+            });
+        }
+        let target_owner = army_save
+            .following_owner
+            .as_ref()
+            .unwrap_or(&army_save.owner);
+        if let Some(target_hex) = army_save.following
+            && let Some(&target) = by_hex.get(&(target_owner.as_str(), target_hex))
+        {
+            commands.entity(*army).insert(Following { target });
+        }
+    }
+}
+
+fn spawn_army_from_save(
+    commands: &mut Commands,
+    army_save: &ArmySaveData,
+    country_lookup: &HashMap<String, Entity>,
+) -> Option<Entity> {
+    let &owner = country_lookup.get(&army_save.owner)?;
+    let composition = army_save.composition.clone();
+    Some(spawn_army(commands, army_save.hex, owner, composition))
+}
+
+/// Battles none of whose armies could be loaded are dropped.
+fn restore_battles(
+    commands: &mut Commands,
+    saved_battles: &[BattleSaveData],
+    battles: &Query<Entity, With<Battle>>,
+    armies: &[Option<Entity>],
+    country_lookup: &HashMap<String, Entity>,
+) {
+    for battle_entity in battles.iter() {
+        commands.entity(battle_entity).despawn();
+    }
+
+    let side = |indices: &[usize]| -> Vec<Entity> {
+        indices
+            .iter()
+            .filter_map(|&index| armies.get(index).copied().flatten())
+            .collect()
+    };
+    for battle_save in saved_battles {
+        let (attackers, defenders) = (side(&battle_save.attackers), side(&battle_save.defenders));
+        let (Some(&attacker_country), Some(&defender_country)) = (
+            country_lookup.get(&battle_save.attacker_country),
+            country_lookup.get(&battle_save.defender_country),
+        ) else {
+            continue;
+        };
+        if attackers.is_empty() && defenders.is_empty() {
+            continue;
+        }
+        let battle_entity = commands
+            .spawn(Battle {
+                attackers: attackers.clone(),
+                defenders: defenders.clone(),
+                attacker_country,
+                defender_country,
+                location: battle_save.location,
+                attacked_from: battle_save.attacked_from,
+                round: battle_save.round,
+                last_damage_attacker: battle_save.last_damage_attacker,
+                last_damage_defender: battle_save.last_damage_defender,
+            })
+            .id();
+        for army in attackers.into_iter().chain(defenders) {
+            commands.entity(army).insert(InBattle { battle_entity });
+        }
+    }
+}
+
+/// Every country gets the enemies of all the saved wars it takes part in, and none besides.
+fn restore_wars(
+    commands: &mut Commands,
+    save_data: &SaveData,
+    war_entities: &Query<Entity, With<War>>,
+    wars: &mut ResMut<Wars>,
+    province_map: &ProvinceHexMap,
+    country_lookup: &HashMap<String, Entity>,
+) {
+    for war_entity in war_entities.iter() {
+        commands.entity(war_entity).despawn();
+    }
+    wars.active_wars.clear();
+
+    let mut enemies: HashMap<Entity, HashSet<Entity>> = HashMap::new();
+    for war_save in &save_data.wars {
+        if let Some((attacker, defender)) =
+            create_war_from_save(commands, war_save, wars, province_map, country_lookup)
+        {
+            enemies.entry(attacker).or_default().insert(defender);
+            enemies.entry(defender).or_default().insert(attacker);
+        }
+    }
+    let saved_countries = save_data
+        .countries
+        .iter()
+        .filter_map(|country_save| country_lookup.get(&country_save.name));
+    for &country in saved_countries {
+        commands.entity(country).insert(WarRelations {
+            at_war_with: enemies.remove(&country).unwrap_or_default(),
+        });
+    }
+}
+
+fn create_war_from_save(
+    commands: &mut Commands,
+    war_save: &WarSaveData,
+    wars: &mut ResMut<Wars>,
+    province_map: &ProvinceHexMap,
+    country_lookup: &HashMap<String, Entity>,
+) -> Option<(Entity, Entity)> {
+    let (Some(&attacker), Some(&defender)) = (
+        country_lookup.get(&war_save.attacker),
+        country_lookup.get(&war_save.defender),
+    ) else {
+        return None;
+    };
+    let goal = war_save
+        .goal
+        .and_then(|hex| province_map.get_entity(&hex).copied());
+    let war_entity = commands
+        .spawn((
+            War {
+                attacker,
+                defender,
+                goal,
+                started: war_save.started,
+            },
+            WarScore {
+                battles: war_save.battle_score,
+                ticking: war_save.ticking_score,
+            },
+        ))
+        .id();
+    wars.active_wars.push(war_entity);
+    Some((attacker, defender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diplomacy::CallToArms;
+    use crate::rules::GameEnded;
+    use crate::test_utils::TestGame;
+    use crate::trade::SeaChart;
+    use crate::units::UnitType;
+    use crate::weather::{WeatherFront, WeatherKind};
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// The game as it would be written to a save file.
+    fn save_game(game: &mut TestGame) -> String {
+        game.world_mut()
+            .run_system_once(|source: SaveSource| {
+                serde_json::to_string(&source.save_data()).unwrap()
+            })
+            .unwrap()
+    }
+
+    fn load_game(game: &mut TestGame, json: &str) {
+        let save_data: SaveData = serde_json::from_str(json).unwrap();
+        load_save(game.world_mut(), &save_data);
+        game.app.update();
+    }
+
+    fn army_at(game: &mut TestGame, hex: Hex) -> Entity {
+        game.world_mut()
+            .query_filtered::<(Entity, &HexPos), With<Army>>()
+            .iter(game.world())
+            .find(|(_, pos)| pos.0 == hex)
+            .map(|(army, _)| army)
+            .expect("an army should stand on the hex")
+    }
+
+    #[test]
+    fn armies_keep_their_soldiers_and_orders_through_save_and_load() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("France");
+        game.spawn_provinces(6, Some(country));
+        let leader = game.spawn_army(country, Hex::new(2, 0), 3000);
+        let follower = game.spawn_army(country, Hex::new(0, 0), 2000);
+        game.world_mut()
+            .get_mut::<ArmyComposition>(leader)
+            .unwrap()
+            .add_unit(UnitType::new("cavalry"));
+        game.move_army(leader, Hex::new(5, 0));
+        game.world_mut()
+            .entity_mut(follower)
+            .insert(Following { target: leader });
+        let composition = game.get::<ArmyComposition>(leader).unwrap().clone();
+        let path = game.get::<ActivePath>(leader).unwrap().path.clone();
+
+        let json = save_game(&mut game);
+        load_game(&mut game, &json);
+
+        assert_eq!(game.count::<Army>(), 2);
+        assert!(game.get::<HexPos>(leader).is_none());
+        let leader = army_at(&mut game, Hex::new(2, 0));
+        let follower = army_at(&mut game, Hex::new(0, 0));
+        assert_eq!(game.get::<ArmyComposition>(leader), Some(&composition));
+        assert_eq!(game.get::<ActivePath>(leader).unwrap().path, path);
+        assert_eq!(game.get::<Following>(follower).unwrap().target, leader);
+        assert!(game.get::<ActivePath>(follower).is_none());
+    }
+
+    #[test]
+    fn diplomacy_and_province_state_replace_those_of_the_running_game_on_load() {
+        let mut game = TestGame::new();
+        let france = game.spawn_country("France");
+        let savoy = game.spawn_country("Savoy");
+        let milan = game.spawn_country("Milan");
+        let provinces = game.spawn_provinces(2, Some(france));
+        let allied = |ally: Entity| Alliances::new(HashSet::from([ally]), HashSet::new());
+        game.world_mut().entity_mut(france).insert(allied(savoy));
+        game.world_mut()
+            .entity_mut(savoy)
+            .insert((allied(france), MilitaryAccess::new(HashSet::from([france]))));
+        game.world_mut().entity_mut(provinces[0]).insert((
+            Modifiers(vec![Modifier {
+                name: "Plague".to_string(),
+                income: -0.5,
+                turns_left: 3,
+            }]),
+            Unrest(2),
+            Devastation(20),
+        ));
+        game.world_mut().resource_mut::<Leagues>().tension = 40;
+        let blue = Color::srgb(0.2, 0.3, 0.8);
+        game.world_mut().entity_mut(france).insert(MapColor(blue));
+        let json = save_game(&mut game);
+
+        // What happens in the running game after the save was made is undone by loading it.
+        game.world_mut().entity_mut(france).insert(Alliances::new(
+            HashSet::from([milan]),
+            HashSet::from([milan]),
+        ));
+        game.world_mut().entity_mut(milan).insert(allied(france));
+        game.world_mut()
+            .entity_mut(provinces[0])
+            .remove::<(Modifiers, Unrest, Devastation)>();
+        game.world_mut().entity_mut(provinces[1]).insert(Unrest(5));
+        game.world_mut()
+            .spawn((Fleet::new(Hex::new(3, 0), ShipType::Galley), Owner(milan)));
+        game.world_mut().resource_mut::<Leagues>().tension = 0;
+        game.world_mut()
+            .entity_mut(france)
+            .insert(MapColor(Color::WHITE));
+        load_game(&mut game, &json);
+
+        let alliances = game.get::<Alliances>(france).unwrap();
+        assert!(alliances.is_allied_with(savoy));
+        assert!(!alliances.is_allied_with(milan));
+        assert!(!alliances.shares_vision_with(savoy));
+        assert!(game.get::<Alliances>(milan).is_none());
+        assert!(
+            game.get::<MilitaryAccess>(savoy)
+                .unwrap()
+                .has_access_to(france)
+        );
+        assert_eq!(game.count::<Fleet>(), 0);
+        assert_eq!(
+            game.get::<Modifiers>(provinces[0]).unwrap().0[0].name,
+            "Plague"
+        );
+        assert_eq!(game.get::<Unrest>(provinces[0]).unwrap().0, 2);
+        assert_eq!(game.get::<Devastation>(provinces[0]).unwrap().0, 20);
+        assert!(game.get::<Unrest>(provinces[1]).is_none());
+        assert_eq!(game.world().resource::<Leagues>().tension, 40);
+        assert_eq!(game.get::<MapColor>(france).unwrap().0, blue);
+    }
+
+    #[test]
+    fn embarked_armies_stay_aboard_their_transport_through_save_and_load() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Venice");
+        game.spawn_province("Venice", Hex::new(0, 0), Some(country));
+        let sea = (1..=3).map(|q| Hex::new(q, 0)).collect();
+        game.world_mut()
+            .insert_resource(SeaChart::new(sea, [Hex::new(0, 0)]));
+        let transport = game
+            .world_mut()
+            .spawn((
+                Fleet {
+                    destination: Some(Hex::new(3, 0)),
+                    ..Fleet::new(Hex::new(1, 0), ShipType::Transport)
+                },
+                Owner(country),
+            ))
+            .id();
+        let army = game.spawn_army(country, Hex::new(1, 0), 3000);
+        game.world_mut()
+            .entity_mut(army)
+            .insert(Embarked { fleet: transport });
+
+        let json = save_game(&mut game);
+        load_game(&mut game, &json);
+
+        assert_eq!(game.count::<Fleet>(), 1);
+        let (transport, fleet) = game
+            .world_mut()
+            .query::<(Entity, &Fleet)>()
+            .single(game.world())
+            .map(|(transport, fleet)| (transport, (fleet.hex, fleet.ships, fleet.destination)))
+            .unwrap();
+        assert_eq!(
+            fleet,
+            (Hex::new(1, 0), ShipType::Transport, Some(Hex::new(3, 0)))
+        );
+        let army = army_at(&mut game, Hex::new(1, 0));
+        assert_eq!(game.get::<Embarked>(army).unwrap().fleet, transport);
+    }
+
+    #[test]
+    fn armies_keep_following_allied_armies_through_save_and_load() {
+        let mut game = TestGame::new();
+        let france = game.spawn_country("France");
+        let savoy = game.spawn_country("Savoy");
+        game.spawn_provinces(4, Some(france));
+        // Both countries have an army on the leader's hex, only the French one is followed.
+        let leader = game.spawn_army(france, Hex::new(2, 0), 3000);
+        game.spawn_army(savoy, Hex::new(2, 0), 1000);
+        let follower = game.spawn_army(savoy, Hex::new(0, 0), 2000);
+        game.world_mut()
+            .entity_mut(follower)
+            .insert(Following { target: leader });
+
+        let json = save_game(&mut game);
+        load_game(&mut game, &json);
+
+        let leader = game
+            .world_mut()
+            .query_filtered::<(Entity, &HexPos, &Owner), With<Army>>()
+            .iter(game.world())
+            .find(|(_, pos, owner)| pos.0 == Hex::new(2, 0) && owner.0 == france)
+            .map(|(army, ..)| army)
+            .expect("the French army should have been loaded");
+        let follower = army_at(&mut game, Hex::new(0, 0));
+        assert_eq!(game.get::<Following>(follower).unwrap().target, leader);
+    }
+
+    #[test]
+    fn battles_sieges_and_buildings_resume_after_loading() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let provinces = game.spawn_provinces(3, Some(defender));
+        let war = game.declare_war(attacker, defender);
+        game.world_mut().get_mut::<WarScore>(war).unwrap().battles = 2;
+        game.world_mut()
+            .get_mut::<WarExhaustion>(defender)
+            .unwrap()
+            .0 = 7.5;
+        let first = game.spawn_army(attacker, Hex::new(1, 0), 3000);
+        let second = game.spawn_army(attacker, Hex::new(1, 0), 1000);
+        let defending = game.spawn_army(defender, Hex::new(1, 0), 2000);
+        let battle = game
+            .world_mut()
+            .spawn(Battle {
+                attackers: vec![first, second],
+                defenders: vec![defending],
+                attacker_country: attacker,
+                defender_country: defender,
+                location: Hex::new(1, 0),
+                attacked_from: Hex::new(0, 0),
+                round: 3,
+                last_damage_attacker: 120,
+                last_damage_defender: 80,
+            })
+            .id();
+        for army in [first, second, defending] {
+            game.world_mut().entity_mut(army).insert(InBattle {
+                battle_entity: battle,
+            });
+        }
+        game.world_mut()
+            .entity_mut(provinces[2])
+            .insert(SiegeProgress {
+                besiegers: vec![attacker],
+                soldiers: 3000,
+                required: 1000,
+                progress: 2,
+                progress_required: 5,
+            });
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Market,
+            },
+            Owner(defender),
+            ChildOf(provinces[0]),
+        ));
+
+        let json = save_game(&mut game);
+        load_game(&mut game, &json);
+
+        let (battle, attackers, defenders) = game
+            .world_mut()
+            .query::<(Entity, &Battle)>()
+            .single(game.world())
+            .map(|(entity, battle)| (entity, battle.attackers.clone(), battle.defenders.clone()))
+            .unwrap();
+        let loaded = game.get::<Battle>(battle).unwrap();
+        assert_eq!((loaded.round, loaded.attacked_from), (3, Hex::new(0, 0)));
+        assert_eq!(loaded.last_damage_attacker, 120);
+        let soldiers = |game: &TestGame, armies: &[Entity]| -> Vec<u32> {
+            armies
+                .iter()
+                .map(|&army| game.get::<ArmyComposition>(army).unwrap().total_size())
+                .collect()
+        };
+        assert_eq!(soldiers(&game, &attackers), vec![3000, 1000]);
+        assert_eq!(soldiers(&game, &defenders), vec![2000]);
+        for army in attackers.iter().chain(&defenders) {
+            assert_eq!(game.get::<InBattle>(*army).unwrap().battle_entity, battle);
+        }
+
+        let siege = game.get::<SiegeProgress>(provinces[2]).unwrap();
+        assert_eq!(siege.besiegers, vec![attacker]);
+        assert_eq!((siege.progress, siege.progress_required), (2, 5));
+        let buildings: Vec<(BuildingType, Entity, Entity)> = game
+            .world_mut()
+            .query::<(&Building, &Owner, &ChildOf)>()
+            .iter(game.world())
+            .map(|(building, owner, parent)| (building.building_type, owner.0, parent.parent()))
+            .collect();
+        assert_eq!(
+            buildings,
+            vec![(BuildingType::Market, defender, provinces[0])]
+        );
+
+        let war = game.world().resource::<Wars>().active_wars[0];
+        assert_eq!(game.get::<WarScore>(war).unwrap().battles, 2);
+        assert_eq!(game.get::<WarExhaustion>(defender).unwrap().0, 7.5);
+        assert!(
+            game.get::<WarRelations>(attacker)
+                .unwrap()
+                .is_at_war_with(defender)
+        );
+    }
+
+    #[test]
+    fn sieges_liberations_and_saved_up_movement_survive_a_round_trip() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let provinces = game.spawn_provinces(4, Some(defender));
+        game.declare_war(attacker, defender);
+        let fort = provinces[1];
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Fort,
+            },
+            Owner(defender),
+            ChildOf(fort),
+        ));
+        game.world_mut().entity_mut(fort).insert((
+            SiegeProgress {
+                besiegers: vec![attacker],
+                soldiers: 4000,
+                required: 2000,
+                progress: 1,
+                progress_required: 4,
+            },
+            Garrison(0),
+        ));
+        let sortie = game.spawn_army(defender, Hex::new(1, 0), 1000);
+        game.world_mut()
+            .entity_mut(sortie)
+            .insert(Sortie { province: fort });
+        game.world_mut()
+            .entity_mut(provinces[2])
+            .insert((Occupied { occupier: attacker }, Liberation { progress: 1 }));
+        let marching = game.spawn_army(attacker, Hex::new(0, 0), 3000);
+        game.move_army(marching, Hex::new(3, 0));
+        game.world_mut()
+            .entity_mut(marching)
+            .insert(Movement { points: 2 });
+        game.world_mut().resource_mut::<Weather>().fronts = vec![WeatherFront {
+            kind: WeatherKind::HeavyRain,
+            center: Hex::new(2, 0),
+            radius: 1,
+            turns_left: 3,
+        }];
+        let json = save_game(&mut game);
+
+        // What the running game gathered after the save was made doesn't survive loading it.
+        game.world_mut()
+            .entity_mut(provinces[3])
+            .insert((Garrison(500), Liberation { progress: 1 }));
+        game.world_mut().spawn(CallToArms {
+            from: defender,
+            to: attacker,
+            enemy: defender,
+        });
+        game.world_mut().resource_mut::<Weather>().fronts.clear();
+        game.world_mut().insert_resource(GameEnded(true));
+        load_game(&mut game, &json);
+
+        let siege = game.get::<SiegeProgress>(fort).unwrap();
+        assert_eq!(
+            (siege.besiegers.clone(), siege.progress),
+            (vec![attacker], 1)
+        );
+        assert_eq!(game.get::<Garrison>(fort).unwrap().0, 0);
+        let sortie = army_at(&mut game, Hex::new(1, 0));
+        assert_eq!(game.get::<Sortie>(sortie).unwrap().province, fort);
+        assert_eq!(game.get::<Liberation>(provinces[2]).unwrap().progress, 1);
+        assert!(game.get::<Garrison>(provinces[3]).is_none());
+        assert!(game.get::<Liberation>(provinces[3]).is_none());
+        let marching = army_at(&mut game, Hex::new(0, 0));
+        assert_eq!(
+            game.get::<Movement>(marching),
+            Some(&Movement { points: 2 })
+        );
+        assert!(game.get::<ActivePath>(marching).is_some());
+        let fronts = &game.world().resource::<Weather>().fronts;
+        assert_eq!(fronts.len(), 1);
+        assert_eq!(fronts[0].kind, WeatherKind::HeavyRain);
+        assert_eq!(game.count::<CallToArms>(), 0);
+        assert!(!game.world().resource::<GameEnded>().0);
+    }
+
+    #[test]
+    fn save_times_show_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00 UTC");
+    }
+
+    #[test]
+    fn slot_names_keep_only_file_name_characters() {
+        assert_eq!(
+            slot_name("  Byzantium 1453! "),
+            Some("Byzantium 1453".to_string())
+        );
+        assert_eq!(slot_name("../../etc"), Some("etc".to_string()));
+        assert_eq!(slot_name("???"), None);
+    }
+}
@@ -0,0 +1,287 @@
+﻿use crate::army::{BattleEndedEvent, BattleSide};
+use crate::buildings::{Building, Income};
+use crate::country::{Country, DisplayName, MapColor};
+use crate::map::{Owner, Province};
+use crate::menu::MenuState;
+use crate::rules::GameRules;
+use crate::storage;
+use crate::turns::{GameState, Turn, TurnSet};
+use crate::war::{Occupied, WarWonEvent};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Scoreboard::default())
+            .add_systems(Update, (tally_battles, tally_wars))
+            .add_systems(
+                OnEnter(GameState::PlayerTurn),
+                (compute_final_scores, export_final_scores)
+                    .chain()
+                    .in_set(TurnSet::Begin)
+                    .run_if(in_state(MenuState::InGame)),
+            );
+    }
+}
+
+const RESULTS_FILE_PATH: &str = "results.json";
+
+/// Points per province a country owns.
+const PROVINCE_POINTS: u32 = 10;
+/// Points per building in the country's provinces.
+const BUILDING_POINTS: u32 = 5;
+/// Points per ducat of income the country's provinces bring in each turn.
+const INCOME_POINTS: f32 = 2.0;
+/// Points per battle won.
+const BATTLE_POINTS: u32 = 2;
+/// Points per war won.
+const WAR_POINTS: u32 = 25;
+
+/// Battles and wars won by each country this game, by country name so that they carry over into
+/// saves, and the final standings once the turn limit is reached.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Scoreboard {
+    #[serde(default)]
+    battles_won: BTreeMap<String, u32>,
+    #[serde(default)]
+    wars_won: BTreeMap<String, u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    results: Option<FinalScores>,
+    #[serde(skip)]
+    exported: bool,
+    /// Whether the end screen is shown, the player can close it and keep playing.
+    #[serde(skip)]
+    shown: bool,
+}
+
+/// Standings at the end of a game with a turn limit, best first. Exported for tooling as
+/// [`RESULTS_FILE_PATH`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinalScores {
+    pub turn: u32,
+    pub countries: Vec<CountryScore>,
+}
+
+/// Points a country scored in each category.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CountryScore {
+    pub country: String,
+    /// Provinces and buildings.
+    pub development: u32,
+    pub income: u32,
+    /// Battles won.
+    pub prestige: u32,
+    pub wars_won: u32,
+    pub total: u32,
+    #[serde(skip)]
+    pub color: [u8; 3],
+}
+
+impl Scoreboard {
+    /// Final standings while the end screen is shown.
+    pub fn shown_results(&self) -> Option<&FinalScores> {
+        self.results.as_ref().filter(|_| self.shown)
+    }
+
+    /// Closes the end screen, the game goes on without it.
+    pub fn hide(&mut self) {
+        self.shown = false;
+    }
+}
+
+fn tally_battles(
+    mut events: MessageReader<BattleEndedEvent>,
+    mut scoreboard: ResMut<Scoreboard>,
+    names: Query<&DisplayName>,
+) {
+    for event in events.read() {
+        let winner = match event.winner {
+            Some(BattleSide::Attacker) => event.attacker_country,
+            Some(BattleSide::Defender) => event.defender_country,
+            None => continue,
+        };
+        if let Ok(name) = names.get(winner) {
+            *scoreboard.battles_won.entry(name.0.clone()).or_default() += 1;
+        }
+    }
+}
+
+fn tally_wars(
+    mut events: MessageReader<WarWonEvent>,
+    mut scoreboard: ResMut<Scoreboard>,
+    names: Query<&DisplayName>,
+) {
+    for event in events.read() {
+        if let Ok(name) = names.get(event.winner) {
+            *scoreboard.wars_won.entry(name.0.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// Scores every country once the turn limit is reached, on its provinces and buildings, the
+/// income of its provinces, and the battles and wars it won.
+pub fn compute_final_scores(
+    rules: Res<GameRules>,
+    turn: Res<Turn>,
+    mut scoreboard: ResMut<Scoreboard>,
+    countries: Query<(Entity, &DisplayName, &MapColor), With<Country>>,
+    provinces: Query<(&Owner, &Income, Option<&Children>, Has<Occupied>), With<Province>>,
+    buildings: Query<(), With<Building>>,
+) {
+    let limit_reached = rules
+        .turn_limit
+        .is_some_and(|limit| turn.current_turn() >= limit);
+    if !limit_reached || scoreboard.results.is_some() {
+        return;
+    }
+
+    let mut development: HashMap<Entity, u32> = HashMap::new();
+    let mut income: HashMap<Entity, f32> = HashMap::new();
+    for (owner, province_income, children, occupied) in &provinces {
+        let built = children.map_or(0, |children| {
+            children
+                .iter()
+                .filter(|&child| buildings.contains(child))
+                .count() as u32
+        });
+        *development.entry(owner.0).or_default() += PROVINCE_POINTS + built * BUILDING_POINTS;
+        if !occupied {
+            *income.entry(owner.0).or_default() += province_income.get();
+        }
+    }
+
+    let mut scores: Vec<CountryScore> = countries
+        .iter()
+        .map(|(country, name, color)| {
+            let development = development.get(&country).copied().unwrap_or(0);
+            let income = (income.get(&country).copied().unwrap_or(0.0) * INCOME_POINTS).round();
+            let prestige = scoreboard.battles_won.get(&name.0).copied().unwrap_or(0);
+            let wars_won = scoreboard.wars_won.get(&name.0).copied().unwrap_or(0);
+            let mut score = CountryScore {
+                country: name.0.clone(),
+                development,
+                income: income.max(0.0) as u32,
+                prestige: prestige * BATTLE_POINTS,
+                wars_won: wars_won * WAR_POINTS,
+                total: 0,
+                color: color.0.to_srgba().to_u8_array_no_alpha(),
+            };
+            score.total = score.development + score.income + score.prestige + score.wars_won;
+            score
+        })
+        .collect();
+    scores.sort_by(|a, b| {
+        b.total
+            .cmp(&a.total)
+            .then_with(|| a.country.cmp(&b.country))
+    });
+
+    if let Some(winner) = scores.first() {
+        info!(
+            "{} wins on score with {} points",
+            winner.country, winner.total
+        );
+    }
+    scoreboard.results = Some(FinalScores {
+        turn: turn.current_turn(),
+        countries: scores,
+    });
+    scoreboard.shown = true;
+}
+
+/// Writes the final standings to [`RESULTS_FILE_PATH`] once.
+fn export_final_scores(mut scoreboard: ResMut<Scoreboard>) {
+    if scoreboard.exported {
+        return;
+    }
+    let Some(results) = &scoreboard.results else {
+        return;
+    };
+    match serde_json::to_string_pretty(results) {
+        Ok(json) => match storage::write(RESULTS_FILE_PATH, &json) {
+            Ok(()) => info!("Final scores written to {}", RESULTS_FILE_PATH),
+            Err(e) => error!("Failed to write {}: {}", RESULTS_FILE_PATH, e),
+        },
+        Err(e) => error!("Failed to serialize the final scores: {}", e),
+    }
+    scoreboard.exported = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::Hex;
+    use crate::test_utils::TestGame;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn countries_are_ranked_on_score_at_the_turn_limit() {
+        let mut game = TestGame::new();
+        game.world_mut().resource_mut::<GameRules>().turn_limit = Some(2);
+        // Only scored in a running game, so the scores follow each turn by hand here.
+        let end_turn = |game: &mut TestGame| {
+            game.end_turn();
+            game.world_mut()
+                .run_system_once(compute_final_scores)
+                .unwrap();
+        };
+        let large = game.spawn_country("Large");
+        let victor = game.spawn_country("Victor");
+        game.spawn_provinces(3, Some(large));
+        game.spawn_province("Capital", Hex::new(0, 1), Some(victor));
+        game.world_mut().write_message(WarWonEvent {
+            winner: victor,
+            loser: large,
+        });
+
+        end_turn(&mut game);
+        assert!(game.world().resource::<Scoreboard>().results.is_none());
+
+        end_turn(&mut game);
+        let scoreboard = game.world().resource::<Scoreboard>();
+        let results = scoreboard.results.as_ref().unwrap();
+        assert_eq!(results.turn, 2);
+        let names: Vec<&str> = results
+            .countries
+            .iter()
+            .map(|score| score.country.as_str())
+            .collect();
+        assert_eq!(names, ["Victor", "Large"]);
+        let victor = &results.countries[0];
+        assert_eq!(victor.development, PROVINCE_POINTS);
+        assert_eq!(victor.wars_won, WAR_POINTS);
+        assert_eq!(results.countries[1].development, 3 * PROVINCE_POINTS);
+    }
+
+    #[test]
+    fn wins_carry_over_to_the_country_of_the_same_name_in_a_loaded_game() {
+        let mut game = TestGame::new();
+        game.world_mut().resource_mut::<GameRules>().turn_limit = Some(1);
+        let victor = game.spawn_country("Victor");
+        let loser = game.spawn_country("Loser");
+        game.world_mut().write_message(WarWonEvent {
+            winner: victor,
+            loser,
+        });
+        game.app.update();
+        let saved = serde_json::to_string(game.world().resource::<Scoreboard>()).unwrap();
+
+        // Loading a save spawns countries anew, they are found again by their names.
+        game.world_mut().despawn(victor);
+        game.spawn_country("Victor");
+        game.world_mut()
+            .insert_resource(serde_json::from_str::<Scoreboard>(&saved).unwrap());
+        game.end_turn();
+        game.world_mut()
+            .run_system_once(compute_final_scores)
+            .unwrap();
+
+        let scoreboard = game.world().resource::<Scoreboard>();
+        let results = scoreboard.results.as_ref().unwrap();
+        assert_eq!(results.countries[0].country, "Victor");
+        assert_eq!(results.countries[0].wars_won, WAR_POINTS);
+    }
+}
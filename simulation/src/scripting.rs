@@ -0,0 +1,1143 @@
+﻿use crate::army::{Army, ArmyComposition, HexPos, apply_attrition, spawn_army};
+use crate::country::{Coffer, Country, DisplayName, MapColor};
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::menu::MenuState;
+use crate::modifiers::{Modifier, Modifiers, Unrest};
+use crate::mods::VirtualFs;
+use crate::notifications::{Notification, NotificationTarget, Notifications};
+use crate::player::Player;
+use crate::player_command::PlayerCommand;
+use crate::releasables::{ReleasableNations, ReleaseNationEvent};
+use crate::rules::GameRng;
+use crate::turns::{GameState, Turn, TurnSet};
+use crate::units::UnitRegistry;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::Rng;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Scope};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let scripts = load_scripts(app.world().resource::<VirtualFs>());
+        app.insert_resource(scripts)
+            .add_message::<EventOptionChosen>()
+            .add_systems(Update, hot_reload_scripts)
+            .add_systems(
+                OnEnter(GameState::PlayerTurn),
+                (run_turn_scripts, fire_random_events)
+                    .chain()
+                    .in_set(TurnSet::Begin)
+                    .run_if(in_state(MenuState::InGame)),
+            )
+            .add_systems(
+                Update,
+                resolve_event_choices.after(crate::player_command::execute_player_commands),
+            );
+    }
+}
+
+/// Directory scanned for scripts, in the base game and every enabled mod. Every `.rhai` file in
+/// it holds one script.
+const SCRIPTS_DIRECTORY: &str = "scripts";
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// How often the scripts directory is checked for changes, in seconds.
+const SCRIPT_RELOAD_INTERVAL: f32 = 1.0;
+
+/// Operations a script may take each time it is called, so a script stuck in a loop can't hang
+/// the game.
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+
+/// Chance every country has each turn to be struck by one of the random events.
+const RANDOM_EVENT_CHANCE: f64 = 0.15;
+
+/// Names scripts use for the country and province a random event happens to.
+const THIS_COUNTRY: &str = "this";
+const THIS_PROVINCE: &str = "this_province";
+
+/// Functions a script may define: whether it runs, what it does, and what the option picked in an
+/// event does.
+const CONDITION_FN: &str = "condition";
+const EFFECTS_FN: &str = "effects";
+const CHOOSE_FN: &str = "choose";
+
+/// A Rhai script reacting to the passing of turns. The file defines the functions it needs and
+/// ends with a map describing the script, see [`ScriptHeader`]:
+///
+/// ```rhai
+/// fn condition() { ducats("France") <= 20.0 }
+/// fn effects() { add_ducats("France", 15.0); }
+///
+/// #{ name: "French war chest", trigger: #{ every_n_turns: 10 } }
+/// ```
+///
+/// When its trigger fires and its `condition` holds, its `effects` are applied through the
+/// [`ScriptApi`]. See [`ScriptEngine::new`] for the functions scripts can call.
+///
+/// Scripts with a random trigger are events: they happen to a country and one of its provinces,
+/// called `this` and `this_province` in the script, and let it pick one of their options. The
+/// index of the option picked is passed to `choose`.
+#[derive(Clone, Debug)]
+pub struct Script {
+    pub name: String,
+    pub enabled: bool,
+    pub trigger: ScriptTrigger,
+    pub description: String,
+    pub options: Vec<String>,
+    ast: Arc<AST>,
+}
+
+impl Script {
+    fn defines(&self, function: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == function)
+    }
+}
+
+/// The map a script file ends with.
+#[derive(Deserialize)]
+struct ScriptHeader {
+    name: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    trigger: ScriptTrigger,
+    /// Text shown to the player, `{country}` and `{province}` are replaced by the event's.
+    #[serde(default)]
+    description: String,
+    /// Names of the options of an event.
+    #[serde(default)]
+    options: Vec<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptTrigger {
+    EveryTurn,
+    OnTurn(u32),
+    EveryNTurns(u32),
+    /// Picked among the other random events with this weight, see [`fire_random_events`].
+    Random(u32),
+}
+
+impl ScriptTrigger {
+    fn fires_on(&self, turn: u32) -> bool {
+        match *self {
+            ScriptTrigger::EveryTurn => true,
+            ScriptTrigger::OnTurn(on_turn) => turn == on_turn,
+            ScriptTrigger::EveryNTurns(n) => turn > 0 && turn.is_multiple_of(n),
+            ScriptTrigger::Random(_) => false,
+        }
+    }
+}
+
+/// Effect of a script on the game, queued while the script runs.
+#[derive(Clone, Debug)]
+pub enum ScriptAction {
+    AddDucats {
+        country: String,
+        amount: f32,
+    },
+    /// Soldiers of each unit type are given by the unit type's id, e.g. `#{ infantry: 5000 }`.
+    SpawnArmy {
+        country: String,
+        province: String,
+        composition: ArmyComposition,
+    },
+    DeclareWar {
+        attacker: String,
+        defender: String,
+    },
+    Notify {
+        title: String,
+        text: String,
+        province: Option<String>,
+    },
+    /// Changes the province's income by `income` times its base income for a number of turns.
+    AddModifier {
+        province: String,
+        name: String,
+        income: f32,
+        turns: u32,
+    },
+    AddUnrest {
+        province: String,
+        amount: u32,
+    },
+    /// Armies standing in the province lose this share of their soldiers.
+    ArmyLosses {
+        province: String,
+        share: f32,
+    },
+    /// The country gives up the provinces of a releasable nation, creating it.
+    ReleaseNation {
+        country: String,
+        nation: String,
+    },
+}
+
+impl ScriptAction {
+    fn apply(&self, api: &mut ScriptApi) -> Result<(), String> {
+        match self {
+            ScriptAction::AddDucats { country, amount } => api.add_ducats(country, *amount),
+            ScriptAction::SpawnArmy {
+                country,
+                province,
+                composition,
+            } => api.spawn_army(country, province, composition.clone()),
+            ScriptAction::DeclareWar { attacker, defender } => api.declare_war(attacker, defender),
+            ScriptAction::Notify {
+                title,
+                text,
+                province,
+            } => api.notify(title, text, province.as_deref()),
+            ScriptAction::AddModifier {
+                province,
+                name,
+                income,
+                turns,
+            } => api.add_modifier(
+                province,
+                Modifier {
+                    name: name.clone(),
+                    income: *income,
+                    turns_left: *turns,
+                },
+            ),
+            ScriptAction::AddUnrest { province, amount } => api.add_unrest(province, *amount),
+            ScriptAction::ArmyLosses { province, share } => api.army_losses(province, *share),
+            ScriptAction::ReleaseNation { country, nation } => api.release_nation(country, nation),
+        }
+    }
+}
+
+/// The game as scripts see it, taken before they run. Scripts can't change the game while they
+/// run, the effects they queue are applied once they are done.
+#[derive(Default, Clone, Debug)]
+pub struct GameView {
+    turn: u32,
+    scope: EventScope,
+    ducats: HashMap<String, f32>,
+    /// Owner of every province, by its key and by the name it goes by.
+    owners: HashMap<String, Option<String>>,
+    /// Countries with the nations they could release.
+    releasable: HashSet<(String, String)>,
+}
+
+impl GameView {
+    fn ducats(&self, country: &str) -> Result<f32, String> {
+        self.ducats
+            .get(self.scope.resolve(country))
+            .copied()
+            .ok_or_else(|| format!("unknown country {}", country))
+    }
+
+    fn owner(&self, province: &str) -> Result<Option<&str>, String> {
+        self.owners
+            .get(self.scope.resolve(province))
+            .map(Option::as_deref)
+            .ok_or_else(|| format!("unknown province {}", province))
+    }
+
+    fn can_release(&self, country: &str, nation: &str) -> bool {
+        let country = self.scope.resolve(country).to_string();
+        self.releasable.contains(&(country, nation.to_string()))
+    }
+}
+
+/// What the functions scripts call share: the view they read and the effects they queue.
+#[derive(Default)]
+struct ScriptContext {
+    view: GameView,
+    effects: Vec<ScriptAction>,
+}
+
+#[derive(Clone, Default)]
+struct SharedContext(Arc<Mutex<ScriptContext>>);
+
+impl SharedContext {
+    fn read<R>(&self, f: impl FnOnce(&GameView) -> R) -> R {
+        f(&self.0.lock().unwrap().view)
+    }
+
+    fn queue(&self, action: ScriptAction) {
+        self.0.lock().unwrap().effects.push(action);
+    }
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Amounts can be written as whole numbers as well as decimals.
+fn number(value: Dynamic) -> ScriptResult<f32> {
+    value
+        .as_float()
+        .map(|amount| amount as f32)
+        .or_else(|_| value.as_int().map(|amount| amount as f32))
+        .map_err(|found| format!("expected a number, found {}", found).into())
+}
+
+fn count(value: i64) -> ScriptResult<u32> {
+    u32::try_from(value).map_err(|_| format!("expected a positive number, found {}", value).into())
+}
+
+/// Rhai engine running the scripts, with the game API registered.
+pub struct ScriptEngine {
+    engine: Engine,
+    context: SharedContext,
+}
+
+impl ScriptEngine {
+    /// Scripts read the game with `turn()`, `country_exists(country)`, `ducats(country)`,
+    /// `owner(province)`, `owns(country, province)` and `can_release(country, nation)`, and change
+    /// it with `add_ducats(country, amount)`, `spawn_army(country, province, units)`,
+    /// `declare_war(attacker, defender)`, `notify(title, text[, province])`,
+    /// `add_modifier(province, name, income, turns)`, `add_unrest(province, amount)`,
+    /// `army_losses(province, share)` and `release_nation(country, nation)`.
+    ///
+    /// Scripts can't import other files, they only reach the game through these functions.
+    fn new() -> Self {
+        let context = SharedContext::default();
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.on_print(|text| info!("Script: {}", text));
+
+        let ctx = context.clone();
+        engine.register_fn("turn", move || ctx.read(|view| i64::from(view.turn)));
+        let ctx = context.clone();
+        engine.register_fn("country_exists", move |country: &str| {
+            ctx.read(|view| view.ducats(country).is_ok())
+        });
+        let ctx = context.clone();
+        engine.register_fn("ducats", move |country: &str| -> ScriptResult<f64> {
+            ctx.read(|view| view.ducats(country).map(f64::from).map_err(Into::into))
+        });
+        let ctx = context.clone();
+        engine.register_fn("owner", move |province: &str| -> ScriptResult<String> {
+            ctx.read(|view| {
+                let owner = view.owner(province)?;
+                Ok(owner.unwrap_or_default().to_string())
+            })
+        });
+        let ctx = context.clone();
+        engine.register_fn(
+            "owns",
+            move |country: &str, province: &str| -> ScriptResult<bool> {
+                ctx.read(|view| {
+                    let owner = view.owner(province)?;
+                    Ok(owner == Some(view.scope.resolve(country)))
+                })
+            },
+        );
+        let ctx = context.clone();
+        engine.register_fn("can_release", move |country: &str, nation: &str| {
+            ctx.read(|view| view.can_release(country, nation))
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("add_ducats", move |country: &str, amount: Dynamic| {
+            ctx.queue(ScriptAction::AddDucats {
+                country: country.to_string(),
+                amount: number(amount)?,
+            });
+            ScriptResult::Ok(())
+        });
+        let ctx = context.clone();
+        engine.register_fn(
+            "spawn_army",
+            move |country: &str, province: &str, units: rhai::Map| {
+                ctx.queue(ScriptAction::SpawnArmy {
+                    country: country.to_string(),
+                    province: province.to_string(),
+                    composition: rhai::serde::from_dynamic(&units.into())?,
+                });
+                ScriptResult::Ok(())
+            },
+        );
+        let ctx = context.clone();
+        engine.register_fn("declare_war", move |attacker: &str, defender: &str| {
+            ctx.queue(ScriptAction::DeclareWar {
+                attacker: attacker.to_string(),
+                defender: defender.to_string(),
+            });
+        });
+        let ctx = context.clone();
+        engine.register_fn("notify", move |title: &str, text: &str| {
+            ctx.queue(ScriptAction::Notify {
+                title: title.to_string(),
+                text: text.to_string(),
+                province: None,
+            });
+        });
+        let ctx = context.clone();
+        engine.register_fn("notify", move |title: &str, text: &str, province: &str| {
+            ctx.queue(ScriptAction::Notify {
+                title: title.to_string(),
+                text: text.to_string(),
+                province: Some(province.to_string()),
+            });
+        });
+        let ctx = context.clone();
+        engine.register_fn(
+            "add_modifier",
+            move |province: &str, name: &str, income: Dynamic, turns: i64| {
+                ctx.queue(ScriptAction::AddModifier {
+                    province: province.to_string(),
+                    name: name.to_string(),
+                    income: number(income)?,
+                    turns: count(turns)?,
+                });
+                ScriptResult::Ok(())
+            },
+        );
+        let ctx = context.clone();
+        engine.register_fn("add_unrest", move |province: &str, amount: i64| {
+            ctx.queue(ScriptAction::AddUnrest {
+                province: province.to_string(),
+                amount: count(amount)?,
+            });
+            ScriptResult::Ok(())
+        });
+        let ctx = context.clone();
+        engine.register_fn("army_losses", move |province: &str, share: Dynamic| {
+            ctx.queue(ScriptAction::ArmyLosses {
+                province: province.to_string(),
+                share: number(share)?,
+            });
+            ScriptResult::Ok(())
+        });
+        let ctx = context.clone();
+        engine.register_fn("release_nation", move |country: &str, nation: &str| {
+            ctx.queue(ScriptAction::ReleaseNation {
+                country: country.to_string(),
+                nation: nation.to_string(),
+            });
+        });
+
+        Self { engine, context }
+    }
+
+    /// Compiles the script and reads the map it ends with.
+    fn compile(&self, source: &str) -> Result<Script, String> {
+        let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+        let header = self.engine.eval_ast::<Dynamic>(&ast);
+        // Effects queued at the top level of the file ran while loading it, not on a turn.
+        self.context.0.lock().unwrap().effects.clear();
+        let header: ScriptHeader = rhai::serde::from_dynamic(&header.map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        Ok(Script {
+            name: header.name,
+            enabled: header.enabled,
+            trigger: header.trigger,
+            description: header.description,
+            options: header.options,
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// Sets what the scripts called next see of the game.
+    fn show(&self, view: GameView) {
+        self.context.0.lock().unwrap().view = view;
+    }
+
+    /// Calls the script's function, returning what it returned and the effects it queued. `None`
+    /// if the script doesn't define the function.
+    fn call(
+        &self,
+        script: &Script,
+        function: &str,
+        args: impl FuncArgs,
+    ) -> Result<Option<(Dynamic, Vec<ScriptAction>)>, String> {
+        if !script.defines(function) {
+            return Ok(None);
+        }
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &script.ast,
+            function,
+            args,
+        );
+        let effects = std::mem::take(&mut self.context.0.lock().unwrap().effects);
+        result
+            .map(|value| Some((value, effects)))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Whether the script's condition holds, scripts without one always run.
+    fn condition_holds(&self, script: &Script) -> bool {
+        match self.call(script, CONDITION_FN, ()) {
+            Ok(None) => true,
+            Ok(Some((value, _))) => value.as_bool().unwrap_or_else(|found| {
+                warn!("Script {}: condition returned {}", script.name, found);
+                false
+            }),
+            Err(e) => {
+                warn!("Script {}: {}", script.name, e);
+                false
+            }
+        }
+    }
+
+    /// Runs the script's function and applies the effects it queued.
+    fn run(&self, script: &Script, function: &str, args: impl FuncArgs, api: &mut ScriptApi) {
+        let effects = match self.call(script, function, args) {
+            Ok(result) => result.map(|(_, effects)| effects).unwrap_or_default(),
+            Err(e) => {
+                warn!("Script {}: {}", script.name, e);
+                return;
+            }
+        };
+        for action in &effects {
+            if let Err(e) = action.apply(api) {
+                warn!("Script {}: {}", script.name, e);
+            }
+        }
+        if !effects.is_empty() {
+            self.show(api.view());
+        }
+    }
+}
+
+/// Scripts loaded from [`SCRIPTS_DIRECTORY`], with the modification times used for hot reloading.
+#[derive(Resource)]
+pub struct Scripts {
+    pub scripts: Vec<Script>,
+    engine: ScriptEngine,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+/// Script files with the real path they are read from, so enabling a mod that replaces a script
+/// counts as a change too.
+fn script_files(vfs: &VirtualFs) -> HashMap<PathBuf, SystemTime> {
+    vfs.list(SCRIPTS_DIRECTORY, SCRIPT_EXTENSION)
+        .into_iter()
+        .filter_map(|path| {
+            let file = vfs.resolve(&path)?;
+            let modified = fs::metadata(&file).and_then(|m| m.modified()).ok()?;
+            Some((file, modified))
+        })
+        .collect()
+}
+
+/// Loads every script of [`SCRIPTS_DIRECTORY`], sorted by file name so they always run in the
+/// same order. Invalid scripts are skipped.
+fn load_scripts(vfs: &VirtualFs) -> Scripts {
+    let modified = script_files(vfs);
+    let engine = ScriptEngine::new();
+
+    let scripts = vfs
+        .list(SCRIPTS_DIRECTORY, SCRIPT_EXTENSION)
+        .into_iter()
+        .filter_map(|path| {
+            let content = vfs.read_to_string(&path).ok()?;
+            engine
+                .compile(&content)
+                .map_err(|e| warn!("Failed to load script {}: {}", path, e))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    info!("Loaded {} scripts", scripts.len());
+
+    Scripts {
+        scripts,
+        engine,
+        modified,
+    }
+}
+
+/// Reloads the scripts whenever a file in the scripts directory was added, changed or removed.
+fn hot_reload_scripts(
+    mut scripts: ResMut<Scripts>,
+    vfs: Res<VirtualFs>,
+    time: Res<Time>,
+    mut since_check: Local<f32>,
+) {
+    *since_check += time.delta_secs();
+    if *since_check < SCRIPT_RELOAD_INTERVAL {
+        return;
+    }
+    *since_check = 0.0;
+
+    if script_files(&vfs) != scripts.modified {
+        *scripts = load_scripts(&vfs);
+    }
+}
+
+fn run_turn_scripts(scripts: Res<Scripts>, mut api: ScriptApi) {
+    let turn = api.turn.current_turn();
+    *api.scope = EventScope::default();
+    scripts.engine.show(api.view());
+    for script in &scripts.scripts {
+        if !script.enabled || !script.trigger.fires_on(turn) {
+            continue;
+        }
+        if !scripts.engine.condition_holds(script) {
+            continue;
+        }
+        info!("Running script {}", script.name);
+        scripts.engine.run(script, EFFECTS_FN, (), &mut api);
+    }
+}
+
+/// Country and province a random event happens to.
+#[derive(Default, Clone, Debug)]
+pub struct EventScope {
+    pub country: String,
+    pub province: String,
+}
+
+/// A random event waiting for a human player to pick one of its options.
+#[derive(Component)]
+pub struct PendingEvent {
+    pub country: Entity,
+    pub script: Script,
+    pub scope: EventScope,
+}
+
+impl EventScope {
+    /// Resolves the names standing for the event's country and province.
+    fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        match name {
+            THIS_COUNTRY => &self.country,
+            THIS_PROVINCE => &self.province,
+            _ => name,
+        }
+    }
+
+    /// Replaces `{country}` and `{province}` in the text by the event's.
+    pub fn fill_in(&self, text: &str) -> String {
+        text.replace("{country}", &self.country)
+            .replace("{province}", &self.province)
+    }
+}
+
+/// Sent when the player of a country picked an option of its pending event.
+#[derive(Message)]
+pub struct EventOptionChosen {
+    pub country: Entity,
+    pub event: String,
+    pub option: usize,
+}
+
+/// Picks the event the roll lands on, each event covering as many numbers as its weight.
+fn pick_weighted<'a>(events: &[(&'a Script, u32)], mut roll: u32) -> Option<&'a Script> {
+    for &(script, weight) in events {
+        if roll < weight {
+            return Some(script);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+/// Strikes some countries with a random event in one of their provinces. AI countries take the
+/// first option right away, human players get to choose in a popup.
+fn fire_random_events(
+    scripts: Res<Scripts>,
+    mut api: ScriptApi,
+    mut rng: ResMut<GameRng>,
+    player: Res<Player>,
+    pending_events: Query<&PendingEvent>,
+) {
+    let events: Vec<(&Script, u32)> = scripts
+        .scripts
+        .iter()
+        .filter(|script| script.enabled)
+        .filter_map(|script| match script.trigger {
+            ScriptTrigger::Random(weight) if weight > 0 => Some((script, weight)),
+            _ => None,
+        })
+        .collect();
+    if events.is_empty() {
+        return;
+    }
+
+    // Sorted, so that every peer of a multiplayer game rolls the same events.
+    let mut countries: Vec<(Entity, String)> = api
+        .countries
+        .iter()
+        .map(|(entity, name, _, _)| (entity, name.0.clone()))
+        .collect();
+    countries.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    let mut view = api.view();
+    for (country, country_name) in countries {
+        if pending_events.iter().any(|event| event.country == country)
+            || !rng.random_bool(RANDOM_EVENT_CHANCE)
+        {
+            continue;
+        }
+        let mut provinces: Vec<(Hex, String)> = api
+            .provinces
+            .iter()
+            .filter(|(_, _, owner)| owner.is_some_and(|owner| owner.0 == country))
+            .map(|(_, province, _)| (*province.get_hex(), province.name().to_string()))
+            .collect();
+        if provinces.is_empty() {
+            continue;
+        }
+        provinces.sort_by_key(|(hex, _)| (hex.q(), hex.r()));
+        let (_, province) = provinces.swap_remove(rng.random_range(0..provinces.len()));
+
+        *api.scope = EventScope {
+            country: country_name,
+            province,
+        };
+        view.scope = api.scope.clone();
+        scripts.engine.show(view.clone());
+        let possible: Vec<(&Script, u32)> = events
+            .iter()
+            .copied()
+            .filter(|(script, _)| scripts.engine.condition_holds(script))
+            .collect();
+        let total = possible.iter().map(|(_, weight)| weight).sum::<u32>();
+        if total == 0 {
+            continue;
+        }
+        let Some(script) = pick_weighted(&possible, rng.random_range(0..total)) else {
+            continue;
+        };
+
+        info!(
+            "Event {} happens to {} in {}",
+            script.name, api.scope.country, api.scope.province
+        );
+        scripts.engine.run(script, EFFECTS_FN, (), &mut api);
+        if player.is_human(country) && !script.options.is_empty() {
+            api.commands.spawn(PendingEvent {
+                country,
+                script: script.clone(),
+                scope: api.scope.clone(),
+            });
+        } else if !script.options.is_empty() {
+            scripts.engine.run(script, CHOOSE_FN, (0_i64,), &mut api);
+        }
+        view = api.view();
+    }
+}
+
+/// Applies the options players picked for their pending events.
+fn resolve_event_choices(
+    mut choices: MessageReader<EventOptionChosen>,
+    pending_events: Query<(Entity, &PendingEvent)>,
+    scripts: Res<Scripts>,
+    mut api: ScriptApi,
+) {
+    for choice in choices.read() {
+        let Some((entity, event)) = pending_events.iter().find(|(_, event)| {
+            event.country == choice.country && event.script.name == choice.event
+        }) else {
+            continue;
+        };
+        if choice.option >= event.script.options.len() {
+            continue;
+        }
+        *api.scope = event.scope.clone();
+        scripts.engine.show(api.view());
+        let option = choice.option as i64;
+        scripts
+            .engine
+            .run(&event.script, CHOOSE_FN, (option,), &mut api);
+        api.commands.entity(entity).despawn();
+    }
+}
+
+/// The part of the game scripts can read and change. Countries and provinces are referred to by
+/// name, every effect of a script goes through here.
+#[derive(SystemParam)]
+pub struct ScriptApi<'w, 's> {
+    commands: Commands<'w, 's>,
+    turn: Res<'w, Turn>,
+    countries: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static DisplayName,
+            &'static MapColor,
+            &'static mut Coffer,
+        ),
+        With<Country>,
+    >,
+    provinces: Query<'w, 's, (Entity, &'static Province, Option<&'static Owner>)>,
+    province_effects: Query<
+        'w,
+        's,
+        (Option<&'static mut Modifiers>, Option<&'static mut Unrest>),
+        With<Province>,
+    >,
+    armies: Query<'w, 's, (&'static HexPos, &'static mut ArmyComposition), With<Army>>,
+    notifications: ResMut<'w, Notifications>,
+    units: Res<'w, UnitRegistry>,
+    player_commands: MessageWriter<'w, PlayerCommand>,
+    nations: ReleasableNations<'w, 's>,
+    released_nations: MessageWriter<'w, ReleaseNationEvent>,
+    /// The event being run, naming what `this` and `this_province` stand for.
+    scope: Local<'s, EventScope>,
+}
+
+impl ScriptApi<'_, '_> {
+    fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.scope.resolve(name)
+    }
+
+    /// The game as scripts see it while they run.
+    fn view(&self) -> GameView {
+        let names: HashMap<Entity, &str> = self
+            .countries
+            .iter()
+            .map(|(entity, name, _, _)| (entity, name.0.as_str()))
+            .collect();
+        let owners = self
+            .provinces
+            .iter()
+            .flat_map(|(_, province, owner)| {
+                let owner = owner
+                    .and_then(|owner| names.get(&owner.0))
+                    .map(|name| name.to_string());
+                // Keys come last, so they win over a name another province goes by.
+                [
+                    (province.name().to_string(), owner.clone()),
+                    (province.key().to_string(), owner),
+                ]
+            })
+            .collect();
+        let releasable = self
+            .countries
+            .iter()
+            .flat_map(|(country, name, _, _)| {
+                self.nations
+                    .releasable_by(country)
+                    .into_iter()
+                    .map(move |nation| (name.0.clone(), self.nations.defs.name(nation).to_string()))
+            })
+            .collect();
+        GameView {
+            turn: self.turn.current_turn(),
+            scope: self.scope.clone(),
+            ducats: self
+                .countries
+                .iter()
+                .map(|(_, name, _, coffer)| (name.0.clone(), coffer.get_ducats()))
+                .collect(),
+            owners,
+            releasable,
+        }
+    }
+
+    pub fn country(&self, name: &str) -> Option<Entity> {
+        let name = self.resolve(name);
+        self.countries
+            .iter()
+            .find(|(_, country_name, _, _)| country_name.0 == name)
+            .map(|(entity, _, _, _)| entity)
+    }
+
+    /// Returns the province's entity, hex and owner. Provinces are found by their key as well as
+    /// by the name they go by under their current owner.
+    pub fn province(&self, name: &str) -> Option<(Entity, Hex, Option<Entity>)> {
+        let name = self.resolve(name);
+        self.provinces
+            .iter()
+            .find(|(_, province, _)| province.key() == name || province.name() == name)
+            .map(|(entity, province, owner)| (entity, *province.get_hex(), owner.map(|o| o.0)))
+    }
+
+    fn find_country(&self, name: &str) -> Result<Entity, String> {
+        self.country(name)
+            .ok_or_else(|| format!("unknown country {}", name))
+    }
+
+    fn find_province(&self, name: &str) -> Result<(Entity, Hex, Option<Entity>), String> {
+        self.province(name)
+            .ok_or_else(|| format!("unknown province {}", name))
+    }
+
+    pub fn add_ducats(&mut self, country: &str, amount: f32) -> Result<(), String> {
+        let country = self.find_country(country)?;
+        if let Ok((_, _, _, mut coffer)) = self.countries.get_mut(country) {
+            coffer.add_ducats(amount);
+        }
+        Ok(())
+    }
+
+    pub fn spawn_army(
+        &mut self,
+        country: &str,
+        province: &str,
+        composition: ArmyComposition,
+    ) -> Result<(), String> {
+        let owner = self.find_country(country)?;
+        let (_, hex, _) = self.find_province(province)?;
+        if composition.total_size() == 0 {
+            return Err("can't spawn an empty army".to_string());
+        }
+        if let Some((unit, _)) = composition
+            .iter()
+            .find(|(unit, _)| self.units.get(unit).is_none())
+        {
+            return Err(format!("unknown unit type {}", unit));
+        }
+        spawn_army(&mut self.commands, hex, owner, composition);
+        Ok(())
+    }
+
+    pub fn declare_war(&mut self, attacker: &str, defender: &str) -> Result<(), String> {
+        self.find_country(attacker)?;
+        self.find_country(defender)?;
+        self.player_commands.write(PlayerCommand::DeclareWar {
+            country: self.resolve(attacker).to_string(),
+            target: self.resolve(defender).to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn add_modifier(&mut self, province: &str, modifier: Modifier) -> Result<(), String> {
+        let (entity, _, _) = self.find_province(province)?;
+        match self.province_effects.get_mut(entity) {
+            Ok((Some(mut modifiers), _)) => modifiers.0.push(modifier),
+            _ => {
+                self.commands
+                    .entity(entity)
+                    .insert(Modifiers(vec![modifier]));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_unrest(&mut self, province: &str, amount: u32) -> Result<(), String> {
+        let (entity, _, _) = self.find_province(province)?;
+        match self.province_effects.get_mut(entity) {
+            Ok((_, Some(mut unrest))) => unrest.0 += amount,
+            _ => {
+                self.commands.entity(entity).insert(Unrest(amount));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn army_losses(&mut self, province: &str, share: f32) -> Result<(), String> {
+        let (_, hex, _) = self.find_province(province)?;
+        for (pos, composition) in &mut self.armies {
+            if pos.0 == hex {
+                apply_attrition(composition, share);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the releasable nation and checks the country can release it.
+    fn can_release(&self, country: &str, nation: &str) -> Result<(Entity, usize), String> {
+        let country = self.find_country(country)?;
+        let nation = self
+            .nations
+            .defs
+            .find(nation)
+            .ok_or_else(|| format!("unknown nation {}", nation))?;
+        self.nations.check(nation, country)?;
+        Ok((country, nation))
+    }
+
+    pub fn release_nation(&mut self, country: &str, nation: &str) -> Result<(), String> {
+        let (from, nation) = self.can_release(country, nation)?;
+        self.released_nations
+            .write(ReleaseNationEvent { nation, from });
+        Ok(())
+    }
+
+    pub fn notify(
+        &mut self,
+        title: &str,
+        text: &str,
+        province: Option<&str>,
+    ) -> Result<(), String> {
+        let target = match province {
+            Some(name) => {
+                let (entity, _, _) = self.find_province(name)?;
+                Some(NotificationTarget::Province(entity))
+            }
+            None => None,
+        };
+        self.notifications.push(Notification {
+            title: self.scope.fill_in(title),
+            text: self.scope.fill_in(text),
+            target,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+    use crate::units::UnitType;
+
+    /// Reads a file of the game data, at the workspace root.
+    fn read(file: &str) -> String {
+        fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join(file),
+        )
+        .unwrap()
+    }
+
+    fn load(file: &str) -> Script {
+        ScriptEngine::new().compile(&read(file)).unwrap()
+    }
+
+    #[test]
+    fn example_script_loads() {
+        let script = load("assets/scripts/example_war_chest.rhai");
+        assert!(!script.enabled);
+        assert_eq!(script.trigger, ScriptTrigger::EveryNTurns(10));
+        assert!(script.defines(CONDITION_FN));
+        assert!(script.defines(EFFECTS_FN));
+    }
+
+    #[test]
+    fn conditions_read_the_game_as_shown_to_scripts() {
+        let engine = ScriptEngine::new();
+        let content = read("assets/scripts/example_war_chest.rhai");
+        let script = engine.compile(&content).unwrap();
+        let view = |ducats: f32| GameView {
+            ducats: HashMap::from([("France".to_string(), ducats)]),
+            ..default()
+        };
+
+        engine.show(view(10.0));
+        assert!(engine.condition_holds(&script));
+        engine.show(view(30.0));
+        assert!(!engine.condition_holds(&script));
+        engine.show(GameView::default());
+        assert!(!engine.condition_holds(&script));
+    }
+
+    #[test]
+    fn scripts_stuck_in_a_loop_are_stopped() {
+        let engine = ScriptEngine::new();
+        let script = engine
+            .compile(
+                r#"
+                fn effects() { loop { add_ducats("France", 1); } }
+                #{ name: "Money printer", trigger: "every_turn" }
+                "#,
+            )
+            .unwrap();
+        assert!(engine.call(&script, EFFECTS_FN, ()).is_err());
+    }
+
+    #[test]
+    fn scripts_cant_import_files() {
+        let source = r#"
+            import "assets/scripts/example_war_chest" as chest;
+            #{ name: "Importer", trigger: "every_turn" }
+        "#;
+        assert!(ScriptEngine::new().compile(source).is_err());
+    }
+
+    #[test]
+    fn spawned_armies_name_unit_types_by_id() {
+        let engine = ScriptEngine::new();
+        let script = engine
+            .compile(
+                r#"
+                fn effects() { spawn_army("France", "Paris", #{ infantry: 3000 }); }
+                #{ name: "Levy", trigger: "every_turn" }
+                "#,
+            )
+            .unwrap();
+        let (_, effects) = engine.call(&script, EFFECTS_FN, ()).unwrap().unwrap();
+        let [ScriptAction::SpawnArmy { composition, .. }] = effects.as_slice() else {
+            panic!("expected a spawn_army effect, got {:?}", effects);
+        };
+        assert_eq!(composition.get(&UnitType::new("infantry")), 3000);
+        assert_eq!(composition.total_size(), 3000);
+    }
+
+    #[test]
+    fn triggers_fire_on_their_turns() {
+        assert!(ScriptTrigger::EveryTurn.fires_on(7));
+        assert!(ScriptTrigger::OnTurn(3).fires_on(3));
+        assert!(!ScriptTrigger::OnTurn(3).fires_on(4));
+        assert!(ScriptTrigger::EveryNTurns(5).fires_on(10));
+        assert!(!ScriptTrigger::EveryNTurns(5).fires_on(0));
+        assert!(!ScriptTrigger::EveryNTurns(0).fires_on(3));
+    }
+
+    #[test]
+    fn random_events_load_with_options() {
+        for event in [
+            "plague",
+            "good_harvest",
+            "heir_born",
+            "comet_sighted",
+            "scottish_independence",
+        ] {
+            let script = load(&format!("assets/scripts/{}.rhai", event));
+            assert!(matches!(script.trigger, ScriptTrigger::Random(weight) if weight > 0));
+            assert!(!script.trigger.fires_on(1));
+            assert_eq!(script.options.len(), 2);
+            assert!(script.defines(CHOOSE_FN));
+        }
+    }
+
+    #[test]
+    fn events_are_only_answered_once() {
+        let mut game = TestGame::new();
+        let france = game.spawn_country("France");
+        let paris = game.spawn_province("Paris", Hex::new(0, 0), Some(france));
+        game.world_mut().spawn(PendingEvent {
+            country: france,
+            script: load("assets/scripts/plague.rhai"),
+            scope: EventScope {
+                country: "France".to_string(),
+                province: "Paris".to_string(),
+            },
+        });
+        let seal_the_gates = || PlayerCommand::ChooseEventOption {
+            country: "France".to_string(),
+            event: "Plague".to_string(),
+            option: 1,
+        };
+
+        game.world_mut().write_message(seal_the_gates());
+        game.world_mut().write_message(seal_the_gates());
+        game.app.update();
+        game.world_mut().write_message(seal_the_gates());
+        game.app.update();
+
+        assert_eq!(game.count::<PendingEvent>(), 0);
+        assert_eq!(game.get::<Unrest>(paris).map(|unrest| unrest.0), Some(3));
+    }
+
+    #[test]
+    fn weighted_picks_cover_each_event_by_its_weight() {
+        let engine = ScriptEngine::new();
+        let event = |name: &str| {
+            let header = format!(r#"#{{ name: "{}", trigger: #{{ random: 1 }} }}"#, name);
+            engine.compile(&header).unwrap()
+        };
+        let (plague, harvest) = (event("Plague"), event("Good harvest"));
+        let events = [(&plague, 1), (&harvest, 3)];
+        assert_eq!(pick_weighted(&events, 0).unwrap().name, "Plague");
+        assert_eq!(pick_weighted(&events, 1).unwrap().name, "Good harvest");
+        assert_eq!(pick_weighted(&events, 3).unwrap().name, "Good harvest");
+        assert!(pick_weighted(&events, 4).is_none());
+    }
+}
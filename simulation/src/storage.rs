@@ -0,0 +1,115 @@
+﻿//! Persistent storage for the player's own files: save games, settings and the mod load order.
+//! Native builds keep them as files in the working directory, web builds in the browser's
+//! localStorage, which is the only storage a page can write to synchronously.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn read(name: &str) -> io::Result<String> {
+        fs::read_to_string(name)
+    }
+
+    /// Writes the file, creating the directory it is in when needed.
+    pub fn write(name: &str, content: &str) -> io::Result<()> {
+        if let Some(dir) = Path::new(name).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(name, content)
+    }
+
+    pub fn exists(name: &str) -> bool {
+        Path::new(name).exists()
+    }
+
+    pub fn remove(name: &str) -> io::Result<()> {
+        fs::remove_file(name)
+    }
+
+    /// Names of the files in the directory, none if it doesn't exist.
+    pub fn list(dir: &str) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+
+    /// Seconds since the Unix epoch.
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::io;
+
+    /// Prefix of every key, so the game's entries don't clash with other pages on the same origin.
+    const KEY_PREFIX: &str = "eu6/";
+
+    fn local_storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::other("localStorage is not available"))
+    }
+
+    pub fn read(name: &str) -> io::Result<String> {
+        local_storage()?
+            .get_item(&format!("{}{}", KEY_PREFIX, name))
+            .map_err(|e| io::Error::other(format!("{:?}", e)))?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+    }
+
+    pub fn write(name: &str, content: &str) -> io::Result<()> {
+        local_storage()?
+            .set_item(&format!("{}{}", KEY_PREFIX, name), content)
+            .map_err(|e| io::Error::other(format!("{:?}", e)))
+    }
+
+    pub fn exists(name: &str) -> bool {
+        local_storage()
+            .ok()
+            .and_then(|storage| storage.get_item(&format!("{}{}", KEY_PREFIX, name)).ok())
+            .flatten()
+            .is_some()
+    }
+
+    pub fn remove(name: &str) -> io::Result<()> {
+        local_storage()?
+            .remove_item(&format!("{}{}", KEY_PREFIX, name))
+            .map_err(|e| io::Error::other(format!("{:?}", e)))
+    }
+
+    /// Names of the entries directly under `dir/`, the keys have no real directories.
+    pub fn list(dir: &str) -> Vec<String> {
+        let Ok(storage) = local_storage() else {
+            return Vec::new();
+        };
+        let prefix = format!("{}{}/", KEY_PREFIX, dir);
+        let len = storage.length().unwrap_or(0);
+        (0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .filter(|name| !name.contains('/'))
+            .collect()
+    }
+
+    /// Seconds since the Unix epoch, `SystemTime` isn't available in the browser.
+    pub fn now() -> u64 {
+        (web_sys::js_sys::Date::now() / 1000.0) as u64
+    }
+}
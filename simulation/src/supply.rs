@@ -0,0 +1,298 @@
+﻿use crate::army::{Army, ArmyComposition, HexPos, REGIMENT_SIZE, apply_attrition};
+use crate::buildings::{Building, BuildingType};
+use crate::hex::Hex;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::turns::{GameState, TurnSet};
+use crate::war::{Occupied, WarRelations};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub struct SupplyPlugin;
+
+impl Plugin for SupplyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Processing),
+            (
+                apply_supply_attrition,
+                apply_local_attrition.before(crate::war::liberate_provinces),
+            )
+                .after(crate::army::move_active_armies)
+                .before(crate::army::resolve_battles)
+                .in_set(TurnSet::Resolve),
+        );
+    }
+}
+
+/// Hexes an army can be supplied from its country's provinces, through passable terrain.
+const SUPPLY_RANGE: u32 = 2;
+/// Supply range from a province with a [`BuildingType::Depot`].
+const DEPOT_SUPPLY_RANGE: u32 = 5;
+/// Share of soldiers an army loses per turn it has spent out of supply.
+const ATTRITION_PER_TURN_OUT_OF_SUPPLY: f32 = 0.02;
+/// Cap of the out of supply attrition.
+const MAX_SUPPLY_ATTRITION: f32 = 0.1;
+/// Share of soldiers an army loses per turn in a province held by an enemy.
+const HOSTILE_ATTRITION: f32 = 0.01;
+/// Attrition for every supply limit's worth of soldiers stacked in a province beyond its limit.
+const OVER_SUPPLY_LIMIT_ATTRITION: f32 = 0.05;
+/// Cap of the attrition an army suffers from the province it stands in.
+const MAX_LOCAL_ATTRITION: f32 = 0.1;
+
+/// Marks an army beyond its country's supply range, with the number of turns it has been cut
+/// off. Removed once the army is back in supply.
+#[derive(Component)]
+pub struct OutOfSupply {
+    pub turns: u32,
+}
+
+impl OutOfSupply {
+    /// Share of its soldiers the army loses this turn, growing the longer it is cut off.
+    fn attrition(&self) -> f32 {
+        (self.turns as f32 * ATTRITION_PER_TURN_OUT_OF_SUPPLY).min(MAX_SUPPLY_ATTRITION)
+    }
+}
+
+/// Traces the supply lines of countries from the provinces they control.
+#[derive(SystemParam)]
+pub struct SupplyLines<'w, 's> {
+    provinces: Query<
+        'w,
+        's,
+        (
+            &'static Province,
+            Option<&'static Owner>,
+            Option<&'static Occupied>,
+            Option<&'static Children>,
+        ),
+    >,
+    buildings: Query<'w, 's, &'static Building>,
+}
+
+impl SupplyLines<'_, '_> {
+    /// Hexes the country's armies are supplied on: every passable hex within supply range of a
+    /// province it controls, counted in steps over passable terrain. A country controls the
+    /// provinces it owns that aren't occupied, and the ones it occupies.
+    pub fn reach(&self, country: Entity) -> HashSet<Hex> {
+        let mut passable = HashSet::new();
+        let mut range: HashMap<Hex, u32> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for (province, owner, occupied, children) in &self.provinces {
+            let hex = *province.get_hex();
+            if province.is_passable() {
+                passable.insert(hex);
+            }
+            let controller = occupied.map(|o| o.occupier).or(owner.map(|o| o.0));
+            if controller != Some(country) {
+                continue;
+            }
+            let has_depot = children.is_some_and(|children| {
+                children.iter().any(|child| {
+                    self.buildings
+                        .get(child)
+                        .is_ok_and(|b| b.building_type == BuildingType::Depot)
+                })
+            });
+            range.insert(
+                hex,
+                if has_depot {
+                    DEPOT_SUPPLY_RANGE
+                } else {
+                    SUPPLY_RANGE
+                },
+            );
+            queue.push_back(hex);
+        }
+
+        while let Some(hex) = queue.pop_front() {
+            let left = range[&hex];
+            if left == 0 {
+                continue;
+            }
+            for neighbor in hex.neighbors() {
+                if !passable.contains(&neighbor) || range.get(&neighbor) >= Some(&(left - 1)) {
+                    continue;
+                }
+                range.insert(neighbor, left - 1);
+                queue.push_back(neighbor);
+            }
+        }
+        range.into_keys().collect()
+    }
+}
+
+/// Tracks which armies are out of supply after they moved, and makes them lose soldiers.
+pub fn apply_supply_attrition(
+    mut commands: Commands,
+    supply_lines: SupplyLines,
+    mut armies: Query<
+        (
+            Entity,
+            &HexPos,
+            &Owner,
+            &mut ArmyComposition,
+            Option<&mut OutOfSupply>,
+        ),
+        With<Army>,
+    >,
+) {
+    let mut reaches: HashMap<Entity, HashSet<Hex>> = HashMap::new();
+    for (entity, pos, owner, composition, out_of_supply) in &mut armies {
+        let reach = reaches
+            .entry(owner.0)
+            .or_insert_with(|| supply_lines.reach(owner.0));
+        if reach.contains(&pos.0) {
+            if out_of_supply.is_some() {
+                commands.entity(entity).remove::<OutOfSupply>();
+            }
+            continue;
+        }
+
+        let turns = out_of_supply.as_ref().map_or(0, |o| o.turns) + 1;
+        let status = OutOfSupply { turns };
+        apply_attrition(composition, status.attrition());
+        match out_of_supply {
+            Some(mut out_of_supply) => *out_of_supply = status,
+            None => {
+                commands.entity(entity).insert(status);
+            }
+        }
+    }
+}
+
+/// Share of its soldiers an army loses this turn from the province it stands in: from harsh
+/// terrain, from being in enemy territory, and from the armies there going over the province's
+/// supply limit.
+fn local_attrition(province: &Province, hostile: bool, stacked: u32) -> f32 {
+    let terrain = province.terrain();
+    let mut attrition = terrain.attrition;
+    if hostile {
+        attrition += HOSTILE_ATTRITION;
+    }
+    let limit = terrain.supply_limit * REGIMENT_SIZE;
+    if stacked > limit {
+        let excess = (stacked - limit) as f32 / limit.max(1) as f32;
+        attrition += excess * OVER_SUPPLY_LIMIT_ATTRITION;
+    }
+    attrition.min(MAX_LOCAL_ATTRITION)
+}
+
+/// Makes armies in harsh terrain, enemy territory or provinces stacked beyond their supply limit
+/// lose soldiers. Every army in a province counts towards its supply limit.
+pub fn apply_local_attrition(
+    province_hex_map: Res<ProvinceHexMap>,
+    provinces: Query<(&Province, Option<&Owner>, Option<&Occupied>)>,
+    relations: Query<&WarRelations>,
+    mut armies: Query<(&HexPos, &Owner, &mut ArmyComposition), With<Army>>,
+) {
+    let mut stacked: HashMap<Hex, u32> = HashMap::new();
+    for (pos, _, composition) in &armies {
+        *stacked.entry(pos.0).or_default() += composition.total_size();
+    }
+
+    for (pos, owner, composition) in &mut armies {
+        let Some((province, province_owner, occupied)) = province_hex_map
+            .get_entity(&pos.0)
+            .and_then(|&province| provinces.get(province).ok())
+        else {
+            continue;
+        };
+        if !province.is_passable() {
+            continue;
+        }
+        let controller = occupied.map(|o| o.occupier).or(province_owner.map(|o| o.0));
+        let hostile = controller.is_some_and(|controller| {
+            relations
+                .get(owner.0)
+                .is_ok_and(|relations| relations.is_at_war_with(controller))
+        });
+        apply_attrition(
+            composition,
+            local_attrition(province, hostile, stacked[&pos.0]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Terrain;
+    use crate::test_utils::TestGame;
+    use crate::units::UnitType;
+
+    fn soldiers(game: &TestGame, army: Entity) -> u32 {
+        game.get::<ArmyComposition>(army)
+            .unwrap()
+            .get(&UnitType::new("infantry"))
+    }
+
+    #[test]
+    fn armies_beyond_supply_range_lose_more_soldiers_every_turn() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        for q in 1..=2 {
+            game.spawn_province("Wilds", Hex::new(q, 0), None);
+        }
+        let supplied = game.spawn_army(country, Hex::new(2, 0), 1000);
+        // Too far from any province for the weather to reach it.
+        let cut_off = game.spawn_army(country, Hex::new(5, 0), 1000);
+
+        game.end_turn();
+        assert_eq!(soldiers(&game, supplied), 1000);
+        assert!(game.get::<OutOfSupply>(supplied).is_none());
+        assert_eq!(soldiers(&game, cut_off), 980);
+
+        game.end_turn();
+        assert_eq!(game.get::<OutOfSupply>(cut_off).unwrap().turns, 2);
+        assert_eq!(soldiers(&game, cut_off), 980 - 39);
+    }
+
+    #[test]
+    fn depots_extend_supply_range() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let home = game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        for q in 1..=5 {
+            game.spawn_province("Wilds", Hex::new(q, 0), None);
+        }
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Depot,
+            },
+            ChildOf(home),
+        ));
+        let army = game.spawn_army(country, Hex::new(4, 0), 1000);
+
+        game.end_turn();
+        assert!(game.get::<OutOfSupply>(army).is_none());
+        assert_eq!(soldiers(&game, army), 1000);
+    }
+
+    #[test]
+    fn harsh_terrain_enemy_land_and_large_stacks_cost_soldiers() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let enemy = game.spawn_country("Enemy");
+        // The armies stand in supply, but off their own land where they would be reinforced.
+        game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        game.spawn_province("Camp", Hex::new(-1, 0), None);
+        let desert = game.spawn_province("Desert", Hex::new(1, 0), None);
+        game.world_mut()
+            .get_mut::<Province>(desert)
+            .unwrap()
+            .set_terrain(Terrain::Desert.def());
+        game.spawn_province("Border", Hex::new(2, 0), Some(enemy));
+        game.declare_war(country, enemy);
+        let stack = game.spawn_army(country, Hex::new(-1, 0), 15 * REGIMENT_SIZE);
+        let in_desert = game.spawn_army(country, Hex::new(1, 0), 1000);
+        let in_enemy_land = game.spawn_army(country, Hex::new(2, 0), 1000);
+
+        game.end_turn();
+        assert_eq!(soldiers(&game, in_desert), 980);
+        assert_eq!(soldiers(&game, in_enemy_land), 990);
+        // Half a supply limit over it.
+        assert_eq!(soldiers(&game, stack), 15 * REGIMENT_SIZE - 375);
+    }
+}
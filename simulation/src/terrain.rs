@@ -0,0 +1,296 @@
+﻿use crate::map::Province;
+use crate::mods::VirtualFs;
+use crate::units::UnitClass;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        let terrains = TerrainRegistry::load(app.world().resource::<VirtualFs>());
+        app.insert_resource(terrains)
+            .add_systems(
+                Update,
+                reload_terrains.run_if(resource_changed::<VirtualFs>),
+            )
+            .add_systems(
+                Update,
+                apply_terrain_definitions
+                    .after(reload_terrains)
+                    .run_if(resource_changed::<TerrainRegistry>),
+            );
+    }
+}
+
+/// Picks up the definitions of newly enabled or disabled mods.
+fn reload_terrains(mut terrains: ResMut<TerrainRegistry>, vfs: Res<VirtualFs>) {
+    *terrains = TerrainRegistry::load(&vfs);
+}
+
+/// Updates the terrain of every province on the map to its current definition.
+fn apply_terrain_definitions(terrains: Res<TerrainRegistry>, mut provinces: Query<&mut Province>) {
+    for mut province in &mut provinces {
+        if let Some(def) = terrains.get(&province.terrain().id)
+            && def != province.terrain()
+        {
+            province.set_terrain(def.clone());
+        }
+    }
+}
+
+/// File with the terrain definitions, in the base game or a mod.
+pub const TERRAIN_FILE_PATH: &str = "terrain.ron";
+
+/// Terrain types built into the game. Their definitions are used when [`TERRAIN_FILE_PATH`]
+/// can't be read or doesn't define them, so a mod only has to list the terrain it adds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Terrain {
+    Plains,
+    Hills,
+    Mountains,
+    Forest,
+    Desert,
+    Wasteland,
+    Sea,
+}
+
+impl Display for Terrain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terrain_str = match self {
+            Terrain::Plains => "Plains",
+            Terrain::Hills => "Hills",
+            Terrain::Mountains => "Mountains",
+            Terrain::Forest => "Forest",
+            Terrain::Desert => "Desert",
+            Terrain::Wasteland => "Wasteland",
+            Terrain::Sea => "Sea",
+        };
+        write!(f, "{}", terrain_str)
+    }
+}
+
+impl Terrain {
+    const ALL: [Terrain; 7] = [
+        Terrain::Plains,
+        Terrain::Hills,
+        Terrain::Mountains,
+        Terrain::Forest,
+        Terrain::Desert,
+        Terrain::Wasteland,
+        Terrain::Sea,
+    ];
+
+    /// Built-in definition of the terrain, which map files refer to by its name.
+    pub fn def(self) -> TerrainDef {
+        let (color, income, elevation, movement_cost, defender_bonus, cavalry, artillery) =
+            match self {
+                // Grass green, open terrain ideal for cavalry
+                Terrain::Plains => ([0.46, 0.79, 0.26], 0.2, 150.0, 1, 1.0, 1.2, 1.0),
+                // Muted brown, high ground advantage and good firing positions
+                Terrain::Hills => ([0.58, 0.44, 0.27], 0.16, 700.0, 2, 1.25, 0.8, 1.2),
+                // Slate gray, strong defensive terrain that armies cross slowly
+                Terrain::Mountains => ([0.45, 0.45, 0.5], 0.1, 2200.0, 3, 1.5, 0.5, 0.7),
+                // Deep dark green, trees block charges and line of sight
+                Terrain::Forest => ([0.07, 0.31, 0.12], 0.14, 300.0, 2, 1.2, 0.6, 0.6),
+                // Sandy yellow/tan, exposed with clear sightlines
+                Terrain::Desert => ([0.93, 0.79, 0.48], 0.5, 400.0, 1, 0.9, 1.1, 1.1),
+                // Barren grayish-brown, rough ground
+                Terrain::Wasteland => ([0.55, 0.50, 0.45], 0.0, 900.0, 2, 1.0, 0.9, 0.9),
+                // Ocean blue
+                Terrain::Sea => ([0.0, 0.53, 0.74], 0.0, 0.0, 1, 1.0, 0.0, 0.0),
+            };
+        // Share of soldiers lost every turn, and regiments a province can supply
+        let (attrition, supply_limit) = match self {
+            Terrain::Plains => (0.0, 10),
+            Terrain::Hills => (0.0, 6),
+            Terrain::Mountains => (0.02, 3),
+            Terrain::Forest => (0.0, 6),
+            Terrain::Desert => (0.02, 3),
+            Terrain::Wasteland => (0.03, 1),
+            Terrain::Sea => (0.0, 0),
+        };
+        let settled = !matches!(self, Terrain::Sea | Terrain::Wasteland);
+        TerrainDef {
+            id: self.to_string(),
+            name: self.to_string(),
+            color,
+            income,
+            elevation,
+            passable: settled,
+            movement_cost,
+            ownable: settled,
+            navigable: self == Terrain::Sea,
+            defender_bonus,
+            attrition,
+            supply_limit,
+            unit_modifiers: BTreeMap::from([
+                (UnitClass::Cavalry, cavalry),
+                (UnitClass::Artillery, artillery),
+            ]),
+        }
+    }
+}
+
+/// Converts an u8 value to a Terrain variant for simple terrain assignment.
+impl From<u8> for Terrain {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Terrain::Plains,
+            1 => Terrain::Hills,
+            2 => Terrain::Mountains,
+            3 => Terrain::Forest,
+            4 => Terrain::Desert,
+            5 => Terrain::Wasteland,
+            _ => Terrain::Sea,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct TerrainDef {
+    /// Name map files use for the terrain.
+    pub id: String,
+    pub name: String,
+    /// Color of the terrain map mode, in sRGB.
+    pub color: [f32; 3],
+    /// Ducats a province of this terrain yields every turn, unless the map overrides it.
+    pub income: f32,
+    /// Typical elevation in meters, for provinces the map doesn't give one.
+    #[serde(default)]
+    pub elevation: f32,
+    /// Whether armies can enter the terrain.
+    pub passable: bool,
+    /// Movement points an army spends to enter a province of the terrain.
+    #[serde(default = "default_movement_cost")]
+    pub movement_cost: u32,
+    /// Whether countries can own and conquer provinces of this terrain.
+    pub ownable: bool,
+    /// Whether fleets can sail the terrain. Provinces next to it are ports.
+    #[serde(default)]
+    pub navigable: bool,
+    /// Multiplier of the defender's damage, values below 1.0 benefit the attacker.
+    pub defender_bonus: f32,
+    /// Share of their soldiers armies in a province of the terrain lose every turn.
+    #[serde(default)]
+    pub attrition: f32,
+    /// Regiments a province of the terrain can supply, armies stacked beyond it lose soldiers.
+    #[serde(default = "default_supply_limit")]
+    pub supply_limit: u32,
+    /// Damage multiplier of each unit class, 1.0 for the classes not listed.
+    #[serde(default)]
+    pub unit_modifiers: BTreeMap<UnitClass, f32>,
+}
+
+impl TerrainDef {
+    pub fn color(&self) -> Color {
+        let [r, g, b] = self.color;
+        Color::srgb(r, g, b)
+    }
+
+    pub fn unit_modifier(&self, class: UnitClass) -> f32 {
+        self.unit_modifiers.get(&class).copied().unwrap_or(1.0)
+    }
+}
+
+fn default_movement_cost() -> u32 {
+    1
+}
+
+fn default_supply_limit() -> u32 {
+    8
+}
+
+#[derive(Deserialize)]
+struct TerrainFile {
+    terrains: Vec<TerrainDef>,
+}
+
+/// Every terrain type of the game: the ones of [`TERRAIN_FILE_PATH`], followed by the built-in
+/// ones it doesn't redefine.
+#[derive(Resource, Clone, Debug)]
+pub struct TerrainRegistry {
+    terrains: Vec<TerrainDef>,
+}
+
+impl TerrainRegistry {
+    /// Loads the definitions from the topmost mod providing them, falling back to the built-in
+    /// ones when the file is missing or invalid.
+    pub fn load(vfs: &VirtualFs) -> Self {
+        let loaded = vfs
+            .read_to_string(TERRAIN_FILE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|content| Self::parse(&content));
+        match loaded {
+            Ok(terrains) => terrains,
+            Err(e) => {
+                warn!("Failed to load {}: {}", TERRAIN_FILE_PATH, e);
+                Self::built_in()
+            }
+        }
+    }
+
+    fn built_in() -> Self {
+        Self {
+            terrains: Terrain::ALL.map(Terrain::def).to_vec(),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let file: TerrainFile = ron::from_str(content).map_err(|e| e.to_string())?;
+        let mut terrains = file.terrains;
+        for (i, def) in terrains.iter().enumerate() {
+            if terrains[..i].iter().any(|other| other.id == def.id) {
+                return Err(format!("terrain {} is defined twice", def.id));
+            }
+        }
+        for built_in in Self::built_in().terrains {
+            if !terrains.iter().any(|def| def.id == built_in.id) {
+                terrains.push(built_in);
+            }
+        }
+        Ok(Self { terrains })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TerrainDef> {
+        self.terrains.iter().find(|def| def.id == id)
+    }
+
+    /// Definition of the terrain a map refers to, plains when it isn't defined.
+    pub fn resolve(&self, id: &str) -> TerrainDef {
+        self.get(id).cloned().unwrap_or_else(|| {
+            warn!("Unknown terrain {}, using {}", id, Terrain::Plains);
+            Terrain::Plains.def()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terrain_file_matches_built_in_terrain() {
+        let terrains = TerrainRegistry::parse(include_str!("../../assets/terrain.ron")).unwrap();
+        assert_eq!(terrains.terrains, TerrainRegistry::built_in().terrains);
+    }
+
+    const MARSH: &str = r#"(id: "Marsh", name: "Marsh", color: (0.3, 0.4, 0.3), income: 0.05,
+        passable: true, ownable: true, defender_bonus: 1.1, unit_modifiers: { Cavalry: 0.5 })"#;
+
+    #[test]
+    fn mods_can_add_terrain_next_to_the_built_in_ones() {
+        let terrains = TerrainRegistry::parse(&format!("(terrains: [{}])", MARSH)).unwrap();
+        let marsh = terrains.get("Marsh").unwrap();
+        assert_eq!(marsh.unit_modifier(UnitClass::Cavalry), 0.5);
+        assert_eq!(marsh.unit_modifier(UnitClass::Infantry), 1.0);
+        assert_eq!(marsh.movement_cost, 1);
+        assert_eq!(terrains.get("Hills"), Some(&Terrain::Hills.def()));
+        assert_eq!(terrains.resolve("Jungle"), Terrain::Plains.def());
+
+        let duplicated = format!("(terrains: [{0}, {0}])", MARSH);
+        assert!(TerrainRegistry::parse(&duplicated).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+﻿//! Headless harness for exercising the game simulation from `cargo test`. The game's
+//! [`SimulationPlugins`] run on top of Bevy's minimal plugins, so no window, renderer or egui
+//! context is needed.
+
+use crate::SimulationPlugins;
+use crate::army::{Army, ArmyComposition, HexPos, MoveArmyEvent};
+use crate::country::{Coffer, CountryBundle};
+use crate::hex::Hex;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::mods::VirtualFs;
+use crate::player::Player;
+use crate::rules::{GameRng, GameRules};
+use crate::terrain::Terrain;
+use crate::turns::GameState;
+use crate::units::{UnitRegistry, UnitType};
+use crate::war::{DeclareWarEvent, PeaceDemands, PeaceOfferEvent, War};
+use crate::world::GenerateWorld;
+use bevy::prelude::*;
+
+/// Seed used by every test game, so that battles play out the same on every run.
+pub const TEST_SEED: u64 = 42;
+
+/// Small game world driven turn by turn from tests.
+pub struct TestGame {
+    pub app: App,
+}
+
+impl Default for TestGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestGame {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, SimulationPlugins))
+            // Tests spawn a small world of their own instead of the map's.
+            .add_schedule(Schedule::new(GenerateWorld))
+            .insert_resource(GameRules {
+                seed: TEST_SEED,
+                ..default()
+            })
+            .insert_resource(GameRng::for_turn(TEST_SEED, 0))
+            // The base game only, whatever mods are enabled on this machine.
+            .insert_resource(VirtualFs::new(&[]))
+            .insert_resource(UnitRegistry::load(&VirtualFs::new(&[])));
+        Self { app }
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    pub fn spawn_country(&mut self, name: &str) -> Entity {
+        self.world_mut()
+            .spawn(CountryBundle::new(name, Color::WHITE))
+            .id()
+    }
+
+    /// Spawns a plains province at the hex, owned by `owner` if given.
+    pub fn spawn_province(&mut self, name: &str, hex: Hex, owner: Option<Entity>) -> Entity {
+        let province = Province::new(name, hex, Terrain::Plains.def());
+        let income = crate::buildings::Income::new(province.base_income());
+        let mut entity = self.world_mut().spawn((province, income));
+        if let Some(owner) = owner {
+            entity.insert(Owner(owner));
+        }
+        let entity = entity.id();
+        self.world_mut()
+            .resource_mut::<ProvinceHexMap>()
+            .insert(hex, entity);
+        entity
+    }
+
+    /// Spawns a row of provinces from `(0, 0)` eastwards, all owned by `owner`.
+    pub fn spawn_provinces(&mut self, count: i32, owner: Option<Entity>) -> Vec<Entity> {
+        (0..count)
+            .map(|q| self.spawn_province(&format!("Province {}", q), Hex::new(q, 0), owner))
+            .collect()
+    }
+
+    pub fn spawn_army(&mut self, owner: Entity, hex: Hex, infantry: u32) -> Entity {
+        let army = self
+            .world_mut()
+            .spawn((
+                Army {},
+                HexPos(hex),
+                Owner(owner),
+                ArmyComposition::default().with(UnitType::new("infantry"), infantry),
+            ))
+            .id();
+        // Let the hex map pick the army up.
+        self.app.update();
+        army
+    }
+
+    pub fn declare_war(&mut self, attacker: Entity, defender: Entity) -> Entity {
+        self.world_mut()
+            .write_message(DeclareWarEvent::new(attacker, defender));
+        self.app.update();
+        self.world_mut()
+            .query::<(Entity, &War)>()
+            .iter(self.world())
+            .find(|(_, war)| war.attacker == attacker && war.defender == defender)
+            .map(|(entity, _)| entity)
+            .expect("war should have been declared")
+    }
+
+    pub fn move_army(&mut self, army: Entity, to: Hex) {
+        self.world_mut()
+            .write_message(MoveArmyEvent::new(army, HexPos(to)));
+        self.app.update();
+    }
+
+    /// Ends the turn and runs the frames needed to get back to the player's turn.
+    pub fn end_turn(&mut self) {
+        self.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Processing);
+        // One frame each to process the turn, play the enemy turn and return to the player's turn.
+        self.app.update();
+        self.app.update();
+        self.app.update();
+    }
+
+    pub fn end_turns(&mut self, turns: u32) {
+        for _ in 0..turns {
+            self.end_turn();
+        }
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.world().get::<T>(entity)
+    }
+
+    pub fn ducats(&self, country: Entity) -> f32 {
+        self.get::<Coffer>(country)
+            .expect("country should have a coffer")
+            .get_ducats()
+    }
+
+    pub fn count<T: Component>(&mut self) -> usize {
+        self.world_mut()
+            .query_filtered::<(), With<T>>()
+            .iter(self.world())
+            .count()
+    }
+}
+
+/// Game where the player is at war with `nations` AI nations and has just offered peace to each of
+/// them, the offers waiting for an answer in the next frame.
+pub fn peace_offered_to_ai_nations(nations: usize) -> TestGame {
+    const PROVINCES_PER_NATION: i32 = 20;
+
+    let mut game = TestGame::new();
+    let player = game.spawn_country("Player");
+    game.world_mut().resource_mut::<Player>().country = Some(player);
+    let mut offers = Vec::new();
+    for nation in 0..nations as i32 {
+        let ai = game.spawn_country(&format!("AI {}", nation));
+        let provinces: Vec<Entity> = (0..PROVINCES_PER_NATION)
+            .map(|q| game.spawn_province("", Hex::new(q, nation), Some(ai)))
+            .collect();
+        let war = game.declare_war(player, ai);
+        offers.push(PeaceOfferEvent {
+            from: player,
+            to: ai,
+            war_entity: war,
+            provinces_to_cede: provinces[..(nation as usize % 10)].to_vec(),
+            demands: PeaceDemands::default(),
+            enforce_tolerance: false,
+        });
+    }
+    // Sent once every war is declared, so that they all arrive in the same frame.
+    game.world_mut().write_message_batch(offers);
+    game.app.update();
+    game
+}
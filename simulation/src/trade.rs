@@ -0,0 +1,592 @@
+﻿use crate::army::{ActivePath, Army, ArmyHexMap, HexPos};
+use crate::buildings::Income;
+use crate::country::Coffer;
+use crate::hex::Hex;
+use crate::map::{Owner, Province, ProvinceHexMap};
+use crate::notifications::{Notification, Notifications};
+use crate::player::Player;
+use crate::turns::{GameState, TurnSet};
+use crate::war::{WarRelations, are_at_war};
+use crate::world::GenerateWorld;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub struct TradePlugin;
+
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SeaChart::default())
+            .insert_resource(FleetHexMap::default())
+            .add_systems(
+                GenerateWorld,
+                chart_sea_lanes.after(crate::map::MapGeneration),
+            )
+            .add_systems(
+                OnEnter(GameState::Processing),
+                (
+                    sail_fleets,
+                    sink_transports,
+                    carry_embarked_armies,
+                    collect_sea_trade,
+                )
+                    .chain()
+                    .before(crate::turns::handle_new_turn)
+                    .before(crate::army::move_active_armies)
+                    .in_set(TurnSet::Resolve),
+            )
+            .add_systems(
+                OnEnter(GameState::Processing),
+                land_armies
+                    .after(crate::army::move_active_armies)
+                    .in_set(TurnSet::Resolve),
+            )
+            .add_systems(Update, sync_fleet_hex_map);
+    }
+}
+
+/// Most sea hexes a lane crosses between its two ports.
+const MAX_LANE_LENGTH: u32 = 6;
+/// Share of the combined income of its two ports a lane carries every turn.
+const LANE_VALUE_SHARE: f32 = 0.5;
+/// Share of a lane's value each privateer fleet on it takes, up to all of it.
+const PRIVATEER_RAID_SHARE: f32 = 0.25;
+/// Sea hexes a fleet sails per turn.
+const FLEET_MOVEMENT: usize = 3;
+pub const FLEET_COST: f32 = 20.0;
+const FLEET_UPKEEP: f32 = 0.02;
+
+/// Sea route between two ports, carrying trade between them every turn.
+#[derive(Clone, Debug)]
+pub struct SeaLane {
+    pub ports: [Hex; 2],
+    /// Sea hexes crossed from the first port to the second.
+    pub path: Vec<Hex>,
+    /// Ducats the lane carried last turn.
+    pub value: f32,
+    /// Ducats privateers took from the lane last turn.
+    pub raided: f32,
+}
+
+/// Sea hexes, ports and the lanes between them, charted when the world is generated.
+#[derive(Resource, Default)]
+pub struct SeaChart {
+    sea: HashSet<Hex>,
+    ports: HashSet<Hex>,
+    pub lanes: Vec<SeaLane>,
+}
+
+impl SeaChart {
+    /// Charts a lane between every two ports at most [`MAX_LANE_LENGTH`] sea hexes apart. Ports
+    /// are the ownable provinces next to `sea`.
+    pub fn new(sea: HashSet<Hex>, ownable: impl IntoIterator<Item = Hex>) -> Self {
+        let mut ports: Vec<Hex> = ownable
+            .into_iter()
+            .filter(|hex| hex.neighbors().iter().any(|n| sea.contains(n)))
+            .collect();
+        // Lanes are charted in the same order for every player of a game.
+        ports.sort_by_key(|hex| (hex.q(), hex.r()));
+        let index: HashMap<Hex, usize> =
+            ports.iter().enumerate().map(|(i, &hex)| (hex, i)).collect();
+
+        let mut lanes = Vec::new();
+        for (i, &port) in ports.iter().enumerate() {
+            let mut connected = HashSet::new();
+            let mut previous: HashMap<Hex, Option<Hex>> = HashMap::new();
+            let mut queue = VecDeque::new();
+            for start in port.neighbors().into_iter().filter(|n| sea.contains(n)) {
+                previous.insert(start, None);
+                queue.push_back((start, 1));
+            }
+
+            while let Some((hex, length)) = queue.pop_front() {
+                for neighbor in hex.neighbors() {
+                    if let Some(&j) = index.get(&neighbor)
+                        && j > i
+                        && connected.insert(j)
+                    {
+                        let mut path = vec![hex];
+                        while let Some(Some(before)) = previous.get(path.last().unwrap()) {
+                            path.push(*before);
+                        }
+                        path.reverse();
+                        lanes.push(SeaLane {
+                            ports: [port, neighbor],
+                            path,
+                            value: 0.0,
+                            raided: 0.0,
+                        });
+                    }
+                    if length < MAX_LANE_LENGTH
+                        && sea.contains(&neighbor)
+                        && !previous.contains_key(&neighbor)
+                    {
+                        previous.insert(neighbor, Some(hex));
+                        queue.push_back((neighbor, length + 1));
+                    }
+                }
+            }
+        }
+
+        Self {
+            sea,
+            ports: ports.into_iter().collect(),
+            lanes,
+        }
+    }
+
+    pub fn is_port(&self, hex: &Hex) -> bool {
+        self.ports.contains(hex)
+    }
+
+    pub fn is_sea(&self, hex: &Hex) -> bool {
+        self.sea.contains(hex)
+    }
+
+    /// Sea hex next to the port where its fleets are launched.
+    pub fn harbor(&self, port: &Hex) -> Option<Hex> {
+        port.neighbors().into_iter().find(|n| self.sea.contains(n))
+    }
+
+    pub fn lanes_through(&self, hex: &Hex) -> impl Iterator<Item = &SeaLane> {
+        self.lanes
+            .iter()
+            .filter(move |lane| lane.path.contains(hex))
+    }
+
+    /// Shortest way over sea from `from` to `to`, without `from` itself.
+    fn sea_route(&self, from: Hex, to: Hex) -> Option<VecDeque<Hex>> {
+        let mut previous = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(hex) = queue.pop_front() {
+            if hex == to {
+                let mut route = VecDeque::new();
+                let mut current = to;
+                while current != from {
+                    route.push_front(current);
+                    current = previous[&current];
+                }
+                return Some(route);
+            }
+            for neighbor in hex.neighbors() {
+                if self.sea.contains(&neighbor) && !previous.contains_key(&neighbor) {
+                    previous.insert(neighbor, hex);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Kind of ships a fleet is made of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShipType {
+    /// Warships, they raid sea lanes and sink the transports of enemies they meet.
+    #[default]
+    Galley,
+    /// Carry an army across the sea.
+    Transport,
+}
+
+impl ShipType {
+    pub const ALL: [ShipType; 2] = [ShipType::Galley, ShipType::Transport];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShipType::Galley => "Galleys",
+            ShipType::Transport => "Transports",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ShipType::Galley => "Can be sent privateering on sea lanes and sink enemy transports",
+            ShipType::Transport => "Carries an army boarding from a neighboring hex overseas",
+        }
+    }
+
+    /// Color the ships of a country with the map color are drawn in. Transports are drawn lighter
+    /// than warships.
+    pub fn color(&self, country_color: Color) -> Color {
+        match self {
+            ShipType::Galley => country_color.darker(0.2),
+            ShipType::Transport => country_color.lighter(0.1),
+        }
+    }
+}
+
+/// A country's fleet, standing on a sea hex.
+#[derive(Component)]
+pub struct Fleet {
+    pub hex: Hex,
+    pub ships: ShipType,
+    /// Whether the fleet raids the foreign sea lanes it lies on.
+    pub privateering: bool,
+    /// Sea hex the fleet is sailing to.
+    pub destination: Option<Hex>,
+}
+
+impl Fleet {
+    pub fn new(hex: Hex, ships: ShipType) -> Self {
+        Self {
+            hex,
+            ships,
+            privateering: false,
+            destination: None,
+        }
+    }
+}
+
+/// Army aboard a transport fleet. It stands on the fleet's sea hex and sails with it, until it is
+/// ordered to land on a neighboring hex and marches off the ship during the next turn.
+#[derive(Component)]
+pub struct Embarked {
+    pub fleet: Entity,
+}
+
+/// Index of fleets by sea hex, derived from [`Fleet::hex`] by [`sync_fleet_hex_map`] like the
+/// [`ArmyHexMap`] is for armies.
+#[derive(Resource, Default)]
+pub struct FleetHexMap {
+    tiles: HashMap<Hex, Vec<Entity>>,
+    positions: HashMap<Entity, Hex>,
+}
+
+impl FleetHexMap {
+    /// Records the fleet at the given hex, removing it from where it was before.
+    fn track(&mut self, fleet: Entity, hex: Hex) {
+        self.forget(fleet);
+        self.tiles.entry(hex).or_default().push(fleet);
+        self.positions.insert(fleet, hex);
+    }
+
+    /// Removes the fleet from the index.
+    fn forget(&mut self, fleet: Entity) {
+        let Some(hex) = self.positions.remove(&fleet) else {
+            return;
+        };
+        if let Some(fleets) = self.tiles.get_mut(&hex) {
+            fleets.retain(|&f| f != fleet);
+            if fleets.is_empty() {
+                self.tiles.remove(&hex);
+            }
+        }
+    }
+
+    /// Returns every fleet on the sea hex, in the order they arrived.
+    pub fn fleets_at(&self, hex: &Hex) -> &[Entity] {
+        self.tiles.get(hex).map_or(&[], Vec::as_slice)
+    }
+}
+
+pub fn sync_fleet_hex_map(
+    mut fleet_hex_map: ResMut<FleetHexMap>,
+    moved_fleets: Query<(Entity, &Fleet), Changed<Fleet>>,
+    mut removed_fleets: RemovedComponents<Fleet>,
+) {
+    for fleet in removed_fleets.read() {
+        fleet_hex_map.forget(fleet);
+    }
+    for (entity, fleet) in &moved_fleets {
+        // Fleets also change when they take up or give up privateering.
+        if fleet_hex_map.positions.get(&entity) != Some(&fleet.hex) {
+            fleet_hex_map.track(entity, fleet.hex);
+        }
+    }
+}
+
+fn chart_sea_lanes(mut commands: Commands, provinces: Query<&Province>) {
+    let sea = provinces
+        .iter()
+        .filter(|province| province.is_navigable())
+        .map(|province| *province.get_hex())
+        .collect();
+    let ownable = provinces
+        .iter()
+        .filter(|province| province.is_ownable())
+        .map(|province| *province.get_hex());
+    let chart = SeaChart::new(sea, ownable);
+    info!("Charted {} sea lanes", chart.lanes.len());
+    commands.insert_resource(chart);
+}
+
+pub fn spawn_fleet(commands: &mut Commands, hex: Hex, ships: ShipType, owner: Entity) -> Entity {
+    commands.spawn((Fleet::new(hex, ships), Owner(owner))).id()
+}
+
+/// Sails fleets up to [`FLEET_MOVEMENT`] hexes towards their destination.
+pub fn sail_fleets(chart: Res<SeaChart>, mut fleets: Query<&mut Fleet>) {
+    for mut fleet in &mut fleets {
+        let Some(destination) = fleet.destination else {
+            continue;
+        };
+        let Some(route) = chart.sea_route(fleet.hex, destination) else {
+            fleet.destination = None;
+            continue;
+        };
+        if let Some(&hex) = route.iter().take(FLEET_MOVEMENT).next_back() {
+            fleet.hex = hex;
+        }
+        if fleet.hex == destination {
+            fleet.destination = None;
+        }
+    }
+}
+
+/// Galleys sink the enemy transports sharing their sea hex, with the armies aboard.
+pub fn sink_transports(
+    mut commands: Commands,
+    fleets: Query<(Entity, &Fleet, &Owner)>,
+    war_relations: Query<&WarRelations>,
+    player: Res<Player>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for (transport, fleet, owner) in &fleets {
+        if fleet.ships != ShipType::Transport {
+            continue;
+        }
+        let attacked = fleets.iter().any(|(_, galleys, galleys_owner)| {
+            galleys.ships == ShipType::Galley
+                && galleys.hex == fleet.hex
+                && are_at_war(owner.0, galleys_owner.0, &war_relations)
+        });
+        if !attacked {
+            continue;
+        }
+        info!("Transport {:?} sunk at {:?}", transport, fleet.hex);
+        commands.entity(transport).despawn();
+        if player.country == Some(owner.0) {
+            notifications.push(Notification {
+                title: "⚓ Transports sunk".to_string(),
+                text: "Enemy galleys caught our transports at sea and sent them to the bottom, \
+                       with every soldier aboard."
+                    .to_string(),
+                target: None,
+            });
+        }
+    }
+}
+
+/// Moves the embarked armies along with their transports. Armies whose transport sank drown, the
+/// ones landing this turn stay behind to march off the ship.
+pub fn carry_embarked_armies(
+    mut commands: Commands,
+    mut army_hex_map: ResMut<ArmyHexMap>,
+    mut armies: Query<(Entity, &Embarked, &mut HexPos, Has<ActivePath>), With<Army>>,
+    fleets: Query<&Fleet>,
+) {
+    for (army, embarked, mut pos, landing) in &mut armies {
+        let Ok(fleet) = fleets.get(embarked.fleet) else {
+            info!("Army {:?} went down with its transport", army);
+            commands.entity(army).despawn();
+            continue;
+        };
+        if !landing && pos.0 != fleet.hex {
+            pos.0 = fleet.hex;
+            army_hex_map.track(army, *pos);
+        }
+    }
+}
+
+/// Armies that marched off their transport onto land are no longer aboard.
+pub fn land_armies(
+    mut commands: Commands,
+    chart: Res<SeaChart>,
+    armies: Query<(Entity, &HexPos), (With<Army>, With<Embarked>)>,
+) {
+    for (army, pos) in &armies {
+        if !chart.is_sea(&pos.0) {
+            info!("Army {:?} landed at {:?}", army, pos.0);
+            commands.entity(army).remove::<Embarked>();
+        }
+    }
+}
+
+/// Pays the trade of every sea lane to the owners of its ports, less what privateers take from
+/// it, and has countries pay for their fleets.
+pub fn collect_sea_trade(
+    mut chart: ResMut<SeaChart>,
+    hex_map: Res<ProvinceHexMap>,
+    provinces: Query<(&Income, Option<&Owner>), With<Province>>,
+    fleets: Query<(&Fleet, &Owner)>,
+    mut coffers: Query<&mut Coffer>,
+) {
+    let mut earnings: HashMap<Entity, f32> = HashMap::new();
+    for lane in &mut chart.lanes {
+        let ports = lane.ports.map(|hex| {
+            hex_map
+                .get_entity(&hex)
+                .and_then(|&province| provinces.get(province).ok())
+        });
+        let owners = ports.map(|port| port.and_then(|(_, owner)| owner).map(|owner| owner.0));
+        lane.value = LANE_VALUE_SHARE
+            * ports
+                .iter()
+                .flatten()
+                .map(|(income, _)| income.get())
+                .sum::<f32>();
+
+        let raiders: Vec<Entity> = fleets
+            .iter()
+            .filter(|(fleet, owner)| {
+                fleet.privateering
+                    && lane.path.contains(&fleet.hex)
+                    && !owners.contains(&Some(owner.0))
+            })
+            .map(|(_, owner)| owner.0)
+            .collect();
+        let raided_share = (raiders.len() as f32 * PRIVATEER_RAID_SHARE).min(1.0);
+        lane.raided = lane.value * raided_share;
+        for raider in &raiders {
+            *earnings.entry(*raider).or_default() += lane.raided / raiders.len() as f32;
+        }
+        for owner in owners.into_iter().flatten() {
+            *earnings.entry(owner).or_default() += (lane.value - lane.raided) / 2.0;
+        }
+    }
+
+    for (_, owner) in &fleets {
+        *earnings.entry(owner.0).or_default() -= FLEET_UPKEEP;
+    }
+    for (country, ducats) in earnings {
+        if let Ok(mut coffer) = coffers.get_mut(country) {
+            coffer.add_ducats(ducats);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player_command::PlayerCommand;
+    use crate::terrain::Terrain;
+    use crate::test_utils::TestGame;
+
+    #[test]
+    fn lanes_connect_ports_across_the_sea() {
+        let sea = (1..=3).map(|q| Hex::new(q, 0)).collect();
+        let chart = SeaChart::new(sea, [Hex::new(0, 0), Hex::new(4, 0), Hex::new(9, 0)]);
+        assert!(chart.is_port(&Hex::new(4, 0)));
+        assert!(!chart.is_port(&Hex::new(9, 0)));
+        assert_eq!(chart.lanes.len(), 1);
+        assert_eq!(chart.lanes[0].ports, [Hex::new(0, 0), Hex::new(4, 0)]);
+        assert_eq!(
+            chart.lanes[0].path,
+            vec![Hex::new(1, 0), Hex::new(2, 0), Hex::new(3, 0)]
+        );
+    }
+
+    #[test]
+    fn privateers_take_a_share_of_foreign_sea_trade() {
+        let mut game = TestGame::new();
+        let merchant = game.spawn_country("Merchant");
+        let raider = game.spawn_country("Raider");
+        game.spawn_province("West", Hex::new(0, 0), Some(merchant));
+        game.spawn_province("East", Hex::new(4, 0), Some(merchant));
+        let sea = (1..=3).map(|q| Hex::new(q, 0)).collect();
+        game.world_mut()
+            .insert_resource(SeaChart::new(sea, [Hex::new(0, 0), Hex::new(4, 0)]));
+        let fleet = game
+            .world_mut()
+            .spawn((Fleet::new(Hex::new(1, 0), ShipType::Galley), Owner(raider)))
+            .id();
+        game.world_mut()
+            .get_mut::<Fleet>(fleet)
+            .unwrap()
+            .privateering = true;
+        let income = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        let value = LANE_VALUE_SHARE * 2.0 * income;
+        let raided = value * PRIVATEER_RAID_SHARE;
+        assert!((game.ducats(merchant) - (2.0 * income + value - raided)).abs() < 1e-4);
+        assert!((game.ducats(raider) - (raided - FLEET_UPKEEP)).abs() < 1e-4);
+    }
+
+    /// Land at both ends of a strait of three sea hexes, with a transport of the country at its
+    /// western end.
+    fn strait_game() -> (TestGame, Entity, Entity) {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_province("Home", Hex::new(0, 0), Some(country));
+        game.spawn_province("Overseas", Hex::new(4, 0), None);
+        let sea = (1..=3).map(|q| Hex::new(q, 0)).collect();
+        game.world_mut()
+            .insert_resource(SeaChart::new(sea, [Hex::new(0, 0), Hex::new(4, 0)]));
+        let transport = game
+            .world_mut()
+            .spawn((
+                Fleet::new(Hex::new(1, 0), ShipType::Transport),
+                Owner(country),
+            ))
+            .id();
+        (game, country, transport)
+    }
+
+    fn order(game: &mut TestGame, command: PlayerCommand) {
+        game.world_mut().write_message(command);
+        game.app.update();
+        game.app.update();
+    }
+
+    #[test]
+    fn armies_cross_the_sea_aboard_transports() {
+        let (mut game, country, transport) = strait_game();
+        let army = game.spawn_army(country, Hex::new(0, 0), 3000);
+        let move_army = |from: Hex, to: Hex| PlayerCommand::MoveArmy {
+            country: "Country".to_string(),
+            from,
+            to,
+        };
+
+        order(&mut game, move_army(Hex::new(0, 0), Hex::new(1, 0)));
+        assert_eq!(game.get::<Embarked>(army).unwrap().fleet, transport);
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(1, 0))));
+
+        order(
+            &mut game,
+            PlayerCommand::MoveFleet {
+                country: "Country".to_string(),
+                from: Hex::new(1, 0),
+                to: Hex::new(3, 0),
+            },
+        );
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(3, 0))));
+
+        // Armies only land next to their transport.
+        order(&mut game, move_army(Hex::new(3, 0), Hex::new(0, 0)));
+        assert!(game.get::<ActivePath>(army).is_none());
+        order(&mut game, move_army(Hex::new(3, 0), Hex::new(4, 0)));
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army), Some(&HexPos(Hex::new(4, 0))));
+        assert!(game.get::<Embarked>(army).is_none());
+    }
+
+    #[test]
+    fn enemy_galleys_sink_transports_with_the_army_aboard() {
+        let (mut game, country, _) = strait_game();
+        let enemy = game.spawn_country("Enemy");
+        game.spawn_army(country, Hex::new(0, 0), 3000);
+        order(
+            &mut game,
+            PlayerCommand::MoveArmy {
+                country: "Country".to_string(),
+                from: Hex::new(0, 0),
+                to: Hex::new(1, 0),
+            },
+        );
+        game.world_mut()
+            .spawn((Fleet::new(Hex::new(1, 0), ShipType::Galley), Owner(enemy)));
+
+        // At peace, the fleets pass each other by.
+        game.end_turn();
+        assert_eq!(game.count::<Army>(), 1);
+
+        game.declare_war(enemy, country);
+        game.end_turn();
+        assert_eq!(game.count::<Fleet>(), 1);
+        assert_eq!(game.count::<Army>(), 0);
+    }
+}
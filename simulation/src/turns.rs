@@ -0,0 +1,336 @@
+use crate::army::{Army, ArmyComposition};
+use crate::buildings::Income;
+use crate::country::{Coffer, DisplayName};
+use crate::diplomacy::{CallToArms, ProvinceOffer};
+use crate::map::{Owner, Province};
+use crate::menu::PauseMenuOpen;
+use crate::modifiers::{Devastation, IncomeSources, Modifiers, Unrest};
+use crate::net::NetSession;
+use crate::player::Player;
+use crate::player_command::PlayerCommand;
+use crate::scripting::PendingEvent;
+use crate::units::UnitRegistry;
+use crate::war::{Occupied, PeaceOffer, VASSAL_TRIBUTE, Vassal};
+use bevy::ecs::system::SystemParam;
+use bevy::log::info;
+use bevy::prelude::{
+    Children, Entity, NextState, Plugin, Query, Res, ResMut, Resource, States, SystemSet, With,
+};
+use std::collections::HashMap;
+
+pub struct TurnsPlugin;
+
+impl Plugin for TurnsPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        use bevy::prelude::*;
+        app.insert_resource(Turn::default())
+            .init_state::<GameState>()
+            .add_systems(
+                OnEnter(GameState::Processing),
+                (
+                    handle_new_turn.in_set(TurnSet::Resolve),
+                    start_enemy_turn.after(TurnSet::Resolve),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::EnemyTurn),
+                start_player_turn.after(TurnSet::EnemyMoves),
+            );
+    }
+}
+
+/// Resource for keeping track of current turn. Only cosmetic for now (or forever?).
+#[derive(Resource, Default)]
+pub struct Turn {
+    current_turn: u32,
+}
+
+impl Turn {
+    pub fn advance(&mut self) {
+        self.current_turn += 1;
+    }
+
+    pub fn current_turn(&self) -> u32 {
+        self.current_turn
+    }
+
+    pub fn set(&mut self, turn: u32) {
+        self.current_turn = turn;
+    }
+}
+
+/// Different states the game can be in. A turn goes from the player's turn through processing and
+/// the enemy turn back to the player's turn, one state per frame.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum GameState {
+    #[default]
+    /// Player's turn, waiting for input.
+    PlayerTurn,
+    /// The ended turn is resolved: income, movement, battles and sieges.
+    Processing,
+    /// The AI countries plan their moves for the next turn.
+    EnemyTurn,
+}
+
+/// Steps of a turn, run as the game enters or leaves the [`GameState`]s. Plugins put their turn
+/// systems in these sets, and the turn only moves on to the next state once its set is done.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TurnSet {
+    /// Runs as the player ends the turn, on the transition from [`GameState::PlayerTurn`] to
+    /// [`GameState::Processing`]. Resetting the world back to the player's turn doesn't run it.
+    End,
+    /// Runs on entering [`GameState::Processing`].
+    Resolve,
+    /// Runs on entering [`GameState::EnemyTurn`].
+    EnemyMoves,
+    /// Runs on entering [`GameState::PlayerTurn`], as the player's next turn starts.
+    Begin,
+}
+
+/// Windows the player has to close before the game moves on: the pause menu, and the offers and
+/// events waiting for the player's answer. In a multiplayer game an answer waits for the end of the
+/// turn, the window it answers no longer holds the game up meanwhile.
+#[derive(SystemParam)]
+pub struct Modals<'w, 's> {
+    pause_menu: Res<'w, PauseMenuOpen>,
+    peace_offers: Query<'w, 's, &'static PeaceOffer>,
+    province_offers: Query<'w, 's, &'static ProvinceOffer>,
+    calls_to_arms: Query<'w, 's, &'static CallToArms>,
+    events: Query<'w, 's, &'static PendingEvent>,
+    names: Query<'w, 's, &'static DisplayName>,
+    provinces: Query<'w, 's, &'static Province>,
+}
+
+impl Modals<'_, '_> {
+    pub fn open(&self, player: &Player, session: Option<&NetSession>) -> bool {
+        if self.pause_menu.0 {
+            return true;
+        }
+        let Some(country) = player.country else {
+            return false;
+        };
+        let name = |country: Entity| self.names.get(country).ok().map(|name| &name.0);
+        let hex = |province: Entity| self.provinces.get(province).ok().map(|p| *p.get_hex());
+        let peace_offer = self.peace_offers.iter().any(|offer| {
+            offer.to == country
+                && !answered(session, |command| {
+                    matches!(command, PlayerCommand::AnswerPeace { from, .. }
+                        if Some(from) == name(offer.from))
+                })
+        });
+        let province_offer = self.province_offers.iter().any(|offer| {
+            offer.to == country
+                && !answered(session, |command| {
+                    matches!(command, PlayerCommand::AnswerProvinceOffer { province, .. }
+                        if Some(*province) == hex(offer.province))
+                })
+        });
+        let call_to_arms = self.calls_to_arms.iter().any(|call| {
+            call.to == country
+                && !answered(session, |command| {
+                    matches!(command, PlayerCommand::AnswerCallToArms { from, enemy, .. }
+                        if Some(from) == name(call.from) && Some(enemy) == name(call.enemy))
+                })
+        });
+        let event = self.events.iter().any(|event| {
+            event.country == country
+                && !answered(session, |command| {
+                    matches!(command, PlayerCommand::ChooseEventOption { event: name, .. }
+                        if *name == event.script.name)
+                })
+        });
+        peace_offer || province_offer || call_to_arms || event
+    }
+}
+
+/// Whether the local player of a multiplayer game gave an answer that waits for the end of the
+/// turn.
+fn answered(session: Option<&NetSession>, answer: impl Fn(&PlayerCommand) -> bool) -> bool {
+    session.is_some_and(|session| session.queued().any(answer))
+}
+
+/// Run condition of the systems that move the game world on, such as turn processing and army
+/// animations, which are suspended while a [`Modals`] window is open.
+pub fn simulation_running(
+    modals: Modals,
+    player: Res<Player>,
+    session: Option<Res<NetSession>>,
+) -> bool {
+    !modals.open(&player, session.as_deref())
+}
+
+/// Moves on to the enemy turn once the ended turn is resolved.
+pub fn start_enemy_turn(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::EnemyTurn);
+}
+
+/// Hands the turn back to the player once the AI countries made their moves.
+pub fn start_player_turn(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::PlayerTurn);
+}
+
+/// Pays out the income of provinces and the upkeep of armies and advances the turn counter.
+/// Vassals pay [`VASSAL_TRIBUTE`] of their income to their overlords.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn handle_new_turn(
+    mut turn: ResMut<Turn>,
+    provinces: Query<
+        (
+            &Income,
+            &Owner,
+            Option<&Children>,
+            Option<&Modifiers>,
+            Option<&Unrest>,
+            Option<&Occupied>,
+            Option<&Devastation>,
+        ),
+        With<Province>,
+    >,
+    income_sources: IncomeSources,
+    armies: Query<(&ArmyComposition, &Owner), With<Army>>,
+    units: Res<UnitRegistry>,
+    vassals: Query<(Entity, &Vassal)>,
+    mut coffers: Query<&mut Coffer>,
+) {
+    info!("Ending turn {}", turn.current_turn);
+
+    // Those aren't necessarily countries since e.g. rebels can have incomes (but are they owners? IDK).
+    let mut faction_incomes = HashMap::new();
+
+    // Sum up income for each faction from each province and its buildings. Occupied provinces pay
+    // their occupier instead of their owner.
+    for (income, owner, children, modifiers, unrest, occupied, devastation) in provinces.iter() {
+        let income = income_sources.province(
+            owner.0,
+            income,
+            children,
+            modifiers,
+            unrest,
+            occupied,
+            devastation,
+        );
+        *faction_incomes.entry(income.collector).or_default() += income.total();
+    }
+
+    // Armies are paid for from the same income.
+    for (composition, owner) in armies.iter() {
+        *faction_incomes.entry(owner.0).or_default() -= units.upkeep(composition);
+    }
+
+    for (vassal, serves) in &vassals {
+        let income: f32 = faction_incomes.get(&vassal).copied().unwrap_or_default();
+        if income > 0.0 {
+            let tribute = income * VASSAL_TRIBUTE;
+            *faction_incomes.entry(vassal).or_default() -= tribute;
+            *faction_incomes.entry(serves.overlord).or_default() += tribute;
+        }
+    }
+
+    for (faction, faction_entity) in faction_incomes.into_iter() {
+        if let Ok(mut coffer) = coffers.get_mut(faction) {
+            coffer.add_ducats(faction_entity);
+        }
+    }
+
+    turn.advance();
+    info!("Starting turn {}", turn.current_turn);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulation_running;
+    use crate::army::REGIMENT_SIZE;
+    use crate::diplomacy::CallToArms;
+    use crate::hex::Hex;
+    use crate::map::Province;
+    use crate::menu::PauseMenuOpen;
+    use crate::player::Player;
+    use crate::terrain::Terrain;
+    use crate::test_utils::TestGame;
+    use crate::units::{UnitRegistry, UnitType};
+    use crate::war::{VASSAL_TRIBUTE, Vassal};
+
+    #[test]
+    fn owned_provinces_pay_income_every_turn() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_provinces(2, Some(country));
+        game.spawn_province("Unowned", Hex::new(5, 5), None);
+        let per_province = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        assert_eq!(game.ducats(country), 2.0 * per_province);
+
+        game.end_turns(2);
+        assert!((game.ducats(country) - 6.0 * per_province).abs() < 1e-4);
+    }
+
+    #[test]
+    fn armies_cost_upkeep_every_turn() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        game.spawn_army(country, Hex::new(0, 0), 2 * REGIMENT_SIZE);
+        let upkeep = game
+            .world()
+            .resource::<UnitRegistry>()
+            .get(&UnitType::new("infantry"))
+            .unwrap()
+            .upkeep;
+
+        game.end_turn();
+        assert!((game.ducats(country) + 2.0 * upkeep).abs() < 1e-4);
+    }
+
+    #[test]
+    fn vassals_pay_tribute_to_their_overlords() {
+        let mut game = TestGame::new();
+        let overlord = game.spawn_country("Overlord");
+        let vassal = game.spawn_country("Vassal");
+        game.world_mut()
+            .entity_mut(vassal)
+            .insert(Vassal { overlord });
+        game.spawn_provinces(2, Some(vassal));
+        let per_province = Province::new("", Hex::new(0, 0), Terrain::Plains.def()).base_income();
+
+        game.end_turn();
+        let tribute = 2.0 * per_province * VASSAL_TRIBUTE;
+        assert!((game.ducats(overlord) - tribute).abs() < 1e-4);
+        assert!((game.ducats(vassal) - (2.0 * per_province - tribute)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ending_a_turn_advances_the_counter() {
+        let mut game = TestGame::new();
+        game.end_turns(3);
+        assert_eq!(game.world().resource::<super::Turn>().current_turn(), 3);
+    }
+
+    #[test]
+    fn pause_menu_and_offers_to_the_player_pause_the_simulation() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let ally = game.spawn_country("Ally");
+        let enemy = game.spawn_country("Enemy");
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+        let running = |game: &mut TestGame| {
+            game.world_mut()
+                .run_system_cached(simulation_running)
+                .unwrap()
+        };
+        assert!(running(&mut game));
+
+        let call = game
+            .world_mut()
+            .spawn(CallToArms {
+                from: ally,
+                to: country,
+                enemy,
+            })
+            .id();
+        assert!(!running(&mut game));
+
+        game.world_mut().despawn(call);
+        game.world_mut().resource_mut::<PauseMenuOpen>().0 = true;
+        assert!(!running(&mut game));
+    }
+}
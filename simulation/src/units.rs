@@ -0,0 +1,319 @@
+﻿use crate::army::{ArmyComposition, REGIMENT_SIZE};
+use crate::mods::VirtualFs;
+use crate::terrain::TerrainDef;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub struct UnitsPlugin;
+
+impl Plugin for UnitsPlugin {
+    fn build(&self, app: &mut App) {
+        let units = UnitRegistry::load(app.world().resource::<VirtualFs>());
+        app.insert_resource(units)
+            .add_systems(Update, reload_units.run_if(resource_changed::<VirtualFs>));
+    }
+}
+
+/// Picks up the definitions of newly enabled or disabled mods.
+fn reload_units(mut units: ResMut<UnitRegistry>, vfs: Res<VirtualFs>) {
+    *units = UnitRegistry::load(&vfs);
+}
+
+/// File with the unit definitions, in the base game or a mod.
+pub const UNITS_FILE_PATH: &str = "units.ron";
+
+/// Hit points of a regiment whose definition doesn't give any.
+const DEFAULT_REGIMENT_HIT_POINTS: f32 = 20_000.0;
+
+/// Definitions shipped with the game, used when [`UNITS_FILE_PATH`] can't be read.
+const BUILT_IN_UNITS: &str = include_str!("../../assets/units.ron");
+
+/// Identifier of a unit type, the `id` of its definition in [`UNITS_FILE_PATH`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnitType(String);
+
+impl UnitType {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn new(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl fmt::Display for UnitType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Which of the terrain's unit modifiers applies to a unit.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum UnitClass {
+    Infantry,
+    Cavalry,
+    Artillery,
+}
+
+impl UnitClass {
+    pub fn terrain_modifier(&self, terrain: &TerrainDef) -> f32 {
+        terrain.unit_modifier(*self)
+    }
+
+    /// How much of the battle damage the class takes for its hit points, artillery stands behind
+    /// the lines.
+    pub fn exposure(&self) -> f32 {
+        match self {
+            UnitClass::Infantry | UnitClass::Cavalry => 1.0,
+            UnitClass::Artillery => 0.75,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnitDef {
+    pub id: UnitType,
+    pub name: String,
+    pub class: UnitClass,
+    /// Ducats paid to recruit a regiment.
+    pub cost: f32,
+    /// Damage each soldier deals per battle round, before terrain modifiers.
+    pub damage: f32,
+    /// Damage a regiment takes before all of its soldiers are dead.
+    #[serde(default = "default_regiment_hit_points")]
+    pub hit_points: f32,
+    /// Movement points the unit marches per turn, spent on the terrain of the provinces it enters.
+    pub movement: u32,
+    /// Ducats paid every turn for each regiment.
+    pub upkeep: f32,
+    /// First turn the unit can be recruited on.
+    #[serde(default)]
+    pub available_from_turn: u32,
+    /// Whether the unit is only recruited in provinces with Barracks.
+    #[serde(default)]
+    pub needs_barracks: bool,
+}
+
+fn default_regiment_hit_points() -> f32 {
+    DEFAULT_REGIMENT_HIT_POINTS
+}
+
+impl UnitDef {
+    pub fn is_available(&self, turn: u32) -> bool {
+        turn >= self.available_from_turn
+    }
+}
+
+/// Why a province can't raise any troops, if anything stops it.
+pub fn recruitment_blocked(occupied: bool, besieged: bool) -> Option<&'static str> {
+    if occupied {
+        Some("Occupied provinces can't raise troops")
+    } else if besieged {
+        Some("Besieged provinces can't raise troops")
+    } else {
+        None
+    }
+}
+
+/// Every unit type of the game, in the order of [`UNITS_FILE_PATH`]. Attrition casualties are taken
+/// from the unit types listed first.
+#[derive(Resource, Deserialize, Clone, Debug)]
+pub struct UnitRegistry {
+    units: Vec<UnitDef>,
+    /// Regiments of each unit type every country starts the game with.
+    starting_army: BTreeMap<UnitType, u32>,
+}
+
+impl UnitRegistry {
+    /// Loads the definitions from the topmost mod providing them, falling back to the built-in
+    /// ones when the file is missing or invalid.
+    pub fn load(vfs: &VirtualFs) -> Self {
+        let loaded = vfs
+            .read_to_string(UNITS_FILE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|content| Self::parse(&content));
+        match loaded {
+            Ok(units) => units,
+            Err(e) => {
+                warn!("Failed to load {}: {}", UNITS_FILE_PATH, e);
+                Self::parse(BUILT_IN_UNITS).expect("built-in unit definitions should parse")
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let units: Self = ron::from_str(content).map_err(|e| e.to_string())?;
+        if units.units.is_empty() {
+            return Err("no unit types defined".to_string());
+        }
+        if let Some(unit) = units
+            .starting_army
+            .keys()
+            .find(|unit| units.get(unit).is_none())
+        {
+            return Err(format!("starting army has unknown unit type {}", unit));
+        }
+        Ok(units)
+    }
+
+    pub fn get(&self, unit: &UnitType) -> Option<&UnitDef> {
+        self.units.iter().find(|def| &def.id == unit)
+    }
+
+    pub fn units(&self) -> &[UnitDef] {
+        &self.units
+    }
+
+    /// First infantry available from the start, raised for garrisons and free manpower.
+    pub fn basic_infantry(&self) -> Option<&UnitType> {
+        self.units
+            .iter()
+            .find(|unit| unit.class == UnitClass::Infantry && unit.is_available(0))
+            .map(|unit| &unit.id)
+    }
+
+    /// Display name of the unit type, or its id when it isn't defined (e.g. from a removed mod).
+    pub fn name<'a>(&'a self, unit: &'a UnitType) -> &'a str {
+        self.get(unit).map_or(&unit.0, |def| def.name.as_str())
+    }
+
+    pub fn starting_army(&self) -> ArmyComposition {
+        self.starting_army
+            .iter()
+            .fold(ArmyComposition::default(), |army, (unit, regiments)| {
+                army.with(unit.clone(), regiments * REGIMENT_SIZE)
+            })
+    }
+
+    /// Movement points the army marches per turn, those of its slowest unit type.
+    pub fn movement(&self, army: &ArmyComposition) -> u32 {
+        army.iter()
+            .filter_map(|(unit, _)| self.get(unit))
+            .map(|def| def.movement)
+            .min()
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Ducats the army costs every turn.
+    pub fn upkeep(&self, army: &ArmyComposition) -> f32 {
+        army.iter()
+            .filter_map(|(unit, soldiers)| {
+                let def = self.get(unit)?;
+                Some(def.upkeep * soldiers as f32 / REGIMENT_SIZE as f32)
+            })
+            .sum()
+    }
+
+    /// Damage the army deals in a battle round on the terrain.
+    pub fn damage(&self, army: &ArmyComposition, terrain: &TerrainDef) -> f32 {
+        army.iter()
+            .filter_map(|(unit, soldiers)| {
+                let def = self.get(unit)?;
+                Some(soldiers as f32 * def.damage * def.class.terrain_modifier(terrain))
+            })
+            .sum()
+    }
+
+    /// Damage a soldier of the unit type takes before dying.
+    pub fn soldier_hit_points(&self, unit: &UnitType) -> f32 {
+        self.get(unit)
+            .map_or(DEFAULT_REGIMENT_HIT_POINTS, |def| def.hit_points)
+            / REGIMENT_SIZE as f32
+    }
+
+    /// Hit points of the soldiers of a unit type, weighted by how exposed the unit is in battle.
+    pub fn exposed_hit_points(&self, unit: &UnitType, soldiers: u32) -> f32 {
+        let exposure = self.get(unit).map_or(1.0, |def| def.class.exposure());
+        soldiers as f32 * self.soldier_hit_points(unit) * exposure
+    }
+
+    /// Damage the army takes before all of its soldiers are dead.
+    pub fn hit_points(&self, army: &ArmyComposition) -> f32 {
+        army.iter()
+            .map(|(unit, soldiers)| soldiers as f32 * self.soldier_hit_points(unit))
+            .sum()
+    }
+
+    /// Unit types in the order they take casualties: defined ones in registry order, then unknown
+    /// ones.
+    pub fn casualty_order(&self, army: &ArmyComposition) -> Vec<UnitType> {
+        let mut order: Vec<UnitType> = self
+            .units
+            .iter()
+            .map(|def| def.id.clone())
+            .filter(|unit| army.get(unit) > 0)
+            .collect();
+        order.extend(
+            army.iter()
+                .map(|(unit, _)| unit.clone())
+                .filter(|unit| self.get(unit).is_none()),
+        );
+        order
+    }
+
+    /// Class with the most soldiers in the army, shown as the army's icon on the map.
+    pub fn dominant_class(&self, army: &ArmyComposition) -> Option<UnitClass> {
+        let mut soldiers: BTreeMap<UnitClass, u32> = BTreeMap::new();
+        for (unit, count) in army.iter() {
+            if let Some(def) = self.get(unit) {
+                *soldiers.entry(def.class).or_default() += count;
+            }
+        }
+        soldiers
+            .into_iter()
+            .max_by_key(|&(_, count)| count)
+            .map(|(class, _)| class)
+    }
+
+    /// The army's soldiers per unit type, e.g. "Infantry 10000 · Cavalry 2000".
+    pub fn describe(&self, army: &ArmyComposition) -> String {
+        army.iter()
+            .map(|(unit, soldiers)| format!("{} {}", self.name(unit), soldiers))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_file_parses() {
+        let units = UnitRegistry::parse(BUILT_IN_UNITS).unwrap();
+        let infantry = UnitType::new("infantry");
+        assert_eq!(units.name(&infantry), "Infantry");
+        assert_eq!(units.starting_army().get(&infantry), 10 * REGIMENT_SIZE);
+        assert_eq!(
+            units.describe(&ArmyComposition::default().with(infantry, 3000)),
+            "Infantry 3000"
+        );
+        assert_eq!(
+            units.dominant_class(&units.starting_army()),
+            Some(UnitClass::Infantry)
+        );
+        let cavalry = ArmyComposition::default()
+            .with(UnitType::new("infantry"), 1000)
+            .with(UnitType::new("cavalry"), 3000);
+        assert_eq!(units.dominant_class(&cavalry), Some(UnitClass::Cavalry));
+    }
+
+    #[test]
+    fn unknown_starting_units_are_rejected() {
+        let content = r#"(
+            units: [(id: "pikemen", name: "Pikemen", class: Infantry, cost: 5.0, damage: 0.4,
+                     movement: 1, upkeep: 0.01, available_from_turn: 20)],
+            starting_army: { "musketeers": 1 },
+        )"#;
+        assert!(UnitRegistry::parse(content).is_err());
+
+        let content = content.replace("musketeers", "pikemen");
+        let units = UnitRegistry::parse(&content).unwrap();
+        let pikemen = units.get(&UnitType::new("pikemen")).unwrap();
+        assert!(!pikemen.is_available(19));
+        assert!(pikemen.is_available(20));
+        assert_eq!(pikemen.hit_points, DEFAULT_REGIMENT_HIT_POINTS);
+    }
+}
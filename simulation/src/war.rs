@@ -0,0 +1,2065 @@
+﻿use crate::army::{Army, ArmyComposition, BattleEndedEvent, BattleSide, HexPos, REGIMENT_SIZE};
+use crate::buildings::Building;
+use crate::country::{Country, DisplayName};
+use crate::diplomacy::{Alliances, BreakAllianceEvent, call_allies_to_arms};
+use crate::forts::{FORT_GARRISON, fort_level};
+use crate::hex::Hex;
+use crate::map::{Owner, Province};
+use crate::modifiers::{CountryModifier, CountryModifiers, aggression};
+use crate::player::Player;
+use crate::releasables::{ReleasableNations, ReleaseNationEvent};
+use crate::religion::ToleranceEnforcedEvent;
+use crate::rules::{Difficulty, GameRules, PeaceWeights};
+use crate::turns::{GameState, Turn, TurnSet};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::Parallel;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub struct WarPlugin;
+
+impl Plugin for WarPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wars::default())
+            .add_message::<DeclareWarEvent>()
+            .add_message::<PeaceOfferEvent>()
+            .add_message::<AcceptPeaceEvent>()
+            .add_message::<PeaceAnsweredEvent>()
+            .add_message::<SiegeCompletedEvent>()
+            .add_message::<WarWonEvent>()
+            .add_systems(Update, handle_declare_war)
+            .add_systems(Update, handle_peace_offers)
+            .add_systems(Update, handle_accept_peace)
+            .add_systems(Update, ai_handle_peace_offers)
+            .add_systems(Update, record_battle_war_scores)
+            .add_systems(
+                OnEnter(GameState::Processing),
+                (
+                    liberate_provinces.after(crate::army::move_active_armies),
+                    update_siege_progress.after(liberate_provinces),
+                    (tick_war_scores, update_war_exhaustion).after(update_siege_progress),
+                )
+                    .in_set(TurnSet::Resolve),
+            );
+    }
+}
+
+// ============================================================================
+// SIEGE SYSTEM
+// ============================================================================
+
+/// Soldiers each country has standing in each province.
+type ProvinceForces = HashMap<Entity, BTreeMap<Entity, u32>>;
+
+/// System to update siege progress and check for occupation. Every country at war with a
+/// province's owner with armies in it takes part in the siege, which only progresses while they
+/// are [`siege_soldiers_required`] strong together, and progresses faster the larger they are.
+#[allow(clippy::type_complexity)]
+pub fn update_siege_progress(
+    mut commands: Commands,
+    mut siege_provinces: Query<(Entity, &mut SiegeProgress, Option<&Occupied>)>,
+    armies: Query<(&HexPos, &Owner, &ArmyComposition), With<Army>>,
+    provinces: Query<(&Province, &Owner, Option<&Children>), Without<Occupied>>,
+    buildings: Query<&Building>,
+    province_hex_map: Res<crate::map::ProvinceHexMap>,
+    war_relations: Query<&WarRelations>,
+) {
+    let mut forces = ProvinceForces::new();
+    for (pos, owner, composition) in &armies {
+        if let Some(&province) = province_hex_map.get_entity(&pos.0) {
+            *forces
+                .entry(province)
+                .or_default()
+                .entry(owner.0)
+                .or_default() += composition.total_size();
+        }
+    }
+
+    update_existing_sieges(
+        &mut commands,
+        &mut siege_provinces,
+        &forces,
+        &provinces,
+        &buildings,
+        &war_relations,
+    );
+    check_for_new_sieges(
+        &mut commands,
+        &forces,
+        &provinces,
+        &buildings,
+        &war_relations,
+        &siege_provinces,
+    );
+}
+
+#[allow(clippy::type_complexity)]
+fn update_existing_sieges(
+    commands: &mut Commands,
+    siege_provinces: &mut Query<(Entity, &mut SiegeProgress, Option<&Occupied>)>,
+    forces: &ProvinceForces,
+    provinces: &Query<(&Province, &Owner, Option<&Children>), Without<Occupied>>,
+    buildings: &Query<&Building>,
+    war_relations: &Query<&WarRelations>,
+) {
+    for (province_entity, mut siege, maybe_occupied) in siege_provinces.iter_mut() {
+        let Ok((_, owner, children)) = provinces.get(province_entity) else {
+            commands.entity(province_entity).remove::<SiegeProgress>();
+            continue;
+        };
+        if maybe_occupied.is_some() {
+            commands.entity(province_entity).remove::<SiegeProgress>();
+            continue;
+        }
+
+        let besiegers = besiegers_of(forces.get(&province_entity), owner.0, war_relations);
+        if besiegers.is_empty() {
+            lift_siege(commands, province_entity);
+            continue;
+        }
+        let level = fort_level(children, buildings);
+        let leader = siege.leader();
+        siege.set_besiegers(&besiegers);
+        if siege.leader() != leader {
+            info!(
+                "{:?} takes over the siege of {:?} from {:?}",
+                siege.leader(),
+                province_entity,
+                leader
+            );
+        }
+        siege.required = siege_soldiers_required(level);
+        siege.progress_required = siege_progress_required(level);
+        advance_siege(commands, province_entity, &mut siege);
+    }
+}
+
+/// Countries with armies in a province that are at war with its owner, with their soldiers. The
+/// largest force comes first.
+fn besiegers_of(
+    force: Option<&BTreeMap<Entity, u32>>,
+    owner: Entity,
+    war_relations: &Query<&WarRelations>,
+) -> Vec<(Entity, u32)> {
+    let mut besiegers: Vec<(Entity, u32)> = force
+        .into_iter()
+        .flatten()
+        .filter(|&(&country, _)| are_at_war(country, owner, war_relations))
+        .map(|(&country, &soldiers)| (country, soldiers))
+        .collect();
+    besiegers.sort_by_key(|&(country, soldiers)| (std::cmp::Reverse(soldiers), country));
+    besiegers
+}
+
+/// Soldiers needed to besiege a province with forts of the level.
+pub fn siege_soldiers_required(fort_level: u32) -> u32 {
+    MIN_SIEGE_SOLDIERS + fort_level * SIEGE_SOLDIERS_PER_FORT_LEVEL
+}
+
+/// Progress needed to take a province with forts of the level. Provinces without forts fall to a
+/// quick siege of a single turn.
+fn siege_progress_required(fort_level: u32) -> u32 {
+    if fort_level == 0 {
+        QUICK_SIEGE_TURNS
+    } else {
+        SIEGE_TURNS_REQUIRED
+    }
+}
+
+/// Progress a siege makes in a turn, a step for every time the besiegers have the required
+/// soldiers.
+fn siege_speed(soldiers: u32, required: u32) -> u32 {
+    (soldiers / required.max(1)).min(MAX_SIEGE_SPEED)
+}
+
+fn advance_siege(commands: &mut Commands, province_entity: Entity, siege: &mut SiegeProgress) {
+    let speed = siege_speed(siege.soldiers, siege.required);
+    if speed == 0 {
+        info!(
+            "Siege of {:?} stalls with {}/{} soldiers",
+            province_entity, siege.soldiers, siege.required
+        );
+        return;
+    }
+    siege.progress += speed;
+    info!(
+        "Siege progress on {:?}: {}/{}",
+        province_entity, siege.progress, siege.progress_required
+    );
+
+    if siege.progress >= siege.progress_required {
+        let occupier = siege.leader();
+        commands
+            .entity(province_entity)
+            .remove::<SiegeProgress>()
+            .insert(Occupied { occupier });
+        commands.write_message(SiegeCompletedEvent {
+            province: province_entity,
+            occupier,
+        });
+        info!(
+            "Province {:?} occupied by {:?} after siege!",
+            province_entity, occupier
+        );
+    }
+}
+
+fn lift_siege(commands: &mut Commands, province_entity: Entity) {
+    info!("Siege on {:?} lifted - army left", province_entity);
+    commands.entity(province_entity).remove::<SiegeProgress>();
+}
+
+#[allow(clippy::type_complexity)]
+fn check_for_new_sieges(
+    commands: &mut Commands,
+    forces: &ProvinceForces,
+    provinces: &Query<(&Province, &Owner, Option<&Children>), Without<Occupied>>,
+    buildings: &Query<&Building>,
+    war_relations: &Query<&WarRelations>,
+    siege_provinces: &Query<(Entity, &mut SiegeProgress, Option<&Occupied>)>,
+) {
+    for (&province_entity, force) in forces {
+        if siege_provinces.contains(province_entity) {
+            continue;
+        }
+        let Ok((province, owner, children)) = provinces.get(province_entity) else {
+            continue;
+        };
+        let besiegers = besiegers_of(Some(force), owner.0, war_relations);
+        if besiegers.is_empty() {
+            continue;
+        }
+        let level = fort_level(children, buildings);
+        let mut siege = SiegeProgress {
+            besiegers: Vec::new(),
+            soldiers: 0,
+            required: siege_soldiers_required(level),
+            progress: 0,
+            progress_required: siege_progress_required(level),
+        };
+        siege.set_besiegers(&besiegers);
+        if siege.soldiers < siege.required {
+            continue;
+        }
+        // Progress starts next turn, the besiegers have to hold the province for a turn.
+        info!(
+            "Siege started on {} by {:?}",
+            province.name(),
+            siege.besiegers
+        );
+        commands.entity(province_entity).insert(siege);
+    }
+}
+
+/// Ends the occupation of provinces their owner's side holds for [`LIBERATION_TURNS`] with no
+/// enemy army in them. The owner's side is the owner and every other country at war with the
+/// occupier, but not with the owner.
+#[allow(clippy::type_complexity)]
+pub fn liberate_provinces(
+    mut commands: Commands,
+    mut occupied_provinces: Query<
+        (Entity, &Owner, &Occupied, Option<&mut Liberation>),
+        With<Province>,
+    >,
+    freed: Query<Entity, (With<Liberation>, Without<Occupied>)>,
+    armies: Query<(&HexPos, &Owner), With<Army>>,
+    province_hex_map: Res<crate::map::ProvinceHexMap>,
+    war_relations: Query<&WarRelations>,
+) {
+    for province_entity in &freed {
+        commands.entity(province_entity).remove::<Liberation>();
+    }
+
+    let mut present: HashMap<Entity, HashSet<Entity>> = HashMap::new();
+    for (pos, owner) in &armies {
+        if let Some(&province) = province_hex_map.get_entity(&pos.0) {
+            present.entry(province).or_default().insert(owner.0);
+        }
+    }
+
+    for (province_entity, owner, occupied, liberation) in &mut occupied_provinces {
+        let countries = present.get(&province_entity);
+        let friendly = |country: Entity| {
+            country == owner.0
+                || (are_at_war(country, occupied.occupier, &war_relations)
+                    && !are_at_war(country, owner.0, &war_relations))
+        };
+        let held = countries.is_some_and(|countries| {
+            countries.iter().any(|&country| friendly(country))
+                && !countries
+                    .iter()
+                    .any(|&country| are_at_war(country, owner.0, &war_relations))
+        });
+
+        match liberation {
+            _ if !held => {
+                commands.entity(province_entity).remove::<Liberation>();
+            }
+            // Progress starts next turn, like a siege.
+            None => {
+                commands
+                    .entity(province_entity)
+                    .insert(Liberation { progress: 0 });
+            }
+            Some(mut liberation) => {
+                liberation.progress += 1;
+                if liberation.progress >= LIBERATION_TURNS {
+                    commands
+                        .entity(province_entity)
+                        .remove::<(Occupied, Liberation)>();
+                    info!(
+                        "Province {:?} retaken from {:?}",
+                        province_entity, occupied.occupier
+                    );
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// WAR SCORE AND EXHAUSTION
+// ============================================================================
+
+/// Counts the battles won in every war towards its [`WarScore`].
+pub fn record_battle_war_scores(
+    mut events: MessageReader<BattleEndedEvent>,
+    mut wars: Query<(&War, &mut WarScore)>,
+) {
+    for event in events.read() {
+        let (winner, loser) = match event.winner {
+            Some(BattleSide::Attacker) => (event.attacker_country, event.defender_country),
+            Some(BattleSide::Defender) => (event.defender_country, event.attacker_country),
+            None => continue,
+        };
+        for (war, mut score) in &mut wars {
+            if war.attacker == winner && war.defender == loser {
+                score.battles += 1;
+            } else if war.defender == winner && war.attacker == loser {
+                score.battles -= 1;
+            }
+        }
+    }
+}
+
+/// Ticks the war score of every war towards the side holding its war goal, the attacker once it
+/// occupies or owns the goal and the defender as long as it holds on to it.
+pub fn tick_war_scores(
+    mut wars: Query<(&War, &mut WarScore)>,
+    provinces: Query<(Option<&Owner>, Option<&Occupied>), With<Province>>,
+) {
+    for (war, mut score) in &mut wars {
+        let holder = war
+            .goal
+            .and_then(|goal| provinces.get(goal).ok())
+            .and_then(|(owner, occupied)| occupied.map(|o| o.occupier).or(owner.map(|o| o.0)));
+        let tick = match holder {
+            Some(holder) if holder == war.attacker => 1,
+            Some(holder) if holder == war.defender => -1,
+            _ => continue,
+        };
+        score.ticking = (score.ticking + tick).clamp(-MAX_TICKING_WAR_SCORE, MAX_TICKING_WAR_SCORE);
+    }
+}
+
+/// Wears down the countries at war and lets the others recover. War exhaustion costs income
+/// through a country modifier.
+pub fn update_war_exhaustion(
+    mut countries: Query<
+        (
+            Option<&WarRelations>,
+            &mut WarExhaustion,
+            &mut CountryModifiers,
+        ),
+        With<Country>,
+    >,
+) {
+    for (relations, mut exhaustion, mut modifiers) in &mut countries {
+        let at_war = relations.is_some_and(|relations| !relations.at_war_with.is_empty());
+        exhaustion.0 = if at_war {
+            (exhaustion.0 + WAR_EXHAUSTION_PER_TURN).min(MAX_WAR_EXHAUSTION)
+        } else {
+            (exhaustion.0 - WAR_EXHAUSTION_RECOVERY).max(0.0)
+        };
+        if exhaustion.0 > 0.0 {
+            modifiers.set(CountryModifier {
+                name: WAR_EXHAUSTION_MODIFIER.to_string(),
+                income: -exhaustion.0 * WAR_EXHAUSTION_INCOME_PENALTY,
+                aggression: 1.0,
+            });
+        } else {
+            modifiers.remove(WAR_EXHAUSTION_MODIFIER);
+        }
+    }
+}
+
+/// Relations of countries at war and the scores of their wars.
+#[derive(SystemParam)]
+pub struct WarStatus<'w, 's> {
+    pub relations: Query<'w, 's, &'static WarRelations>,
+    wars: Res<'w, Wars>,
+    scores: Query<'w, 's, (Entity, &'static War, &'static WarScore)>,
+    provinces: Query<
+        'w,
+        's,
+        (
+            &'static Owner,
+            Option<&'static Occupied>,
+            Option<&'static Conquered>,
+        ),
+        With<Province>,
+    >,
+    vassals: Query<'w, 's, &'static Vassal>,
+    alliances: Query<'w, 's, &'static Alliances>,
+    names: Query<'w, 's, &'static DisplayName>,
+    nations: ReleasableNations<'w, 's>,
+}
+
+impl WarStatus<'_, '_> {
+    /// War score of `country` in its war against `enemy`, `None` if they aren't at war.
+    pub fn score(&self, country: Entity, enemy: Entity) -> Option<i32> {
+        let war_entity = self.wars.active_wars.iter().copied().find(|&war_entity| {
+            self.scores.get(war_entity).is_ok_and(|(_, war, _)| {
+                (war.attacker == country && war.defender == enemy)
+                    || (war.attacker == enemy && war.defender == country)
+            })
+        })?;
+        let (_, war, score) = self.scores.get(war_entity).ok()?;
+        let occupied_share = |owner: Entity, occupier: Entity| {
+            let owned: Vec<_> = self
+                .provinces
+                .iter()
+                .filter(|(o, _, _)| o.0 == owner)
+                .collect();
+            let occupied = owned
+                .iter()
+                .filter(|(_, occupation, _)| occupation.is_some_and(|o| o.occupier == occupier))
+                .count();
+            occupied as f32 / owned.len().max(1) as f32
+        };
+        Some(score.of(
+            war,
+            country,
+            occupied_share(war.defender, war.attacker),
+            occupied_share(war.attacker, war.defender),
+        ))
+    }
+
+    /// Country `country` serves, if it is a [`Vassal`].
+    pub fn overlord(&self, country: Entity) -> Option<Entity> {
+        self.vassals.get(country).ok().map(|vassal| vassal.overlord)
+    }
+
+    pub fn has_allies(&self, country: Entity) -> bool {
+        self.alliances
+            .get(country)
+            .is_ok_and(|alliances| alliances.allies().next().is_some())
+    }
+
+    pub fn name(&self, country: Entity) -> &str {
+        self.names
+            .get(country)
+            .map_or("Unknown", |name| name.0.as_str())
+    }
+
+    /// Captive nations `country` could be made to release: countries without land of their own
+    /// whose [`Conquered`] provinces it holds.
+    pub fn captives(&self, country: Entity) -> Vec<Entity> {
+        let landed: HashSet<Entity> = self.provinces.iter().map(|(owner, _, _)| owner.0).collect();
+        let mut captives: Vec<Entity> = self
+            .provinces
+            .iter()
+            .filter(|(owner, _, _)| owner.0 == country)
+            .filter_map(|(_, _, conquered)| conquered.map(|c| c.from))
+            .filter(|from| !landed.contains(from))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        captives.sort();
+        captives
+    }
+
+    /// Nations `country` could be made to release: its captives and the releasable nations whose
+    /// provinces it holds.
+    pub fn releases(&self, country: Entity) -> Vec<Release> {
+        let captives = self.captives(country).into_iter().map(Release::Captive);
+        let nations = self.nations.releasable_by(country).into_iter();
+        captives.chain(nations.map(Release::Nation)).collect()
+    }
+
+    pub fn release_name(&self, release: Release) -> &str {
+        match release {
+            Release::Captive(captive) => self.name(captive),
+            Release::Nation(nation) => self.nations.defs.name(nation),
+        }
+    }
+}
+
+// ============================================================================
+// DATA STRUCTURES
+// ============================================================================
+
+#[derive(Component)]
+pub struct War {
+    pub attacker: Entity,
+    pub defender: Entity,
+    /// Province the attacker went to war for, see [`pick_war_goal`].
+    pub goal: Option<Entity>,
+    /// Turn the war was declared.
+    pub started: u32,
+}
+
+/// Who is winning a war, counted for the attacker. The battles and the turns either side held the
+/// war goal are recorded here, occupations count as they stand, see [`WarScore::of`].
+#[derive(Component, Default)]
+pub struct WarScore {
+    /// Battles the attacker won minus the battles the defender won.
+    pub battles: i32,
+    /// Turns the attacker held the war goal minus the turns the defender did, up to
+    /// [`MAX_TICKING_WAR_SCORE`] either way.
+    pub ticking: i32,
+}
+
+impl WarScore {
+    /// War score of the side of `country`, from -100 when it's utterly beaten up to 100. The
+    /// occupations are the shares of the defender's and of the attacker's provinces the other
+    /// side occupies.
+    pub fn of(
+        &self,
+        war: &War,
+        country: Entity,
+        defender_occupied: f32,
+        attacker_occupied: f32,
+    ) -> i32 {
+        let occupation = (defender_occupied - attacker_occupied) * OCCUPATION_WAR_SCORE;
+        let score = (self.battles * BATTLE_WAR_SCORE + self.ticking + occupation.round() as i32)
+            .clamp(-100, 100);
+        if country == war.defender {
+            -score
+        } else {
+            score
+        }
+    }
+}
+
+/// How weary a country is of war. It grows by [`WAR_EXHAUSTION_PER_TURN`] every turn the country
+/// is at war and costs it [`WAR_EXHAUSTION_INCOME_PENALTY`] of its income per point.
+#[derive(Component, Default)]
+pub struct WarExhaustion(pub f32);
+
+impl WarExhaustion {
+    /// Share of [`MAX_WAR_EXHAUSTION`] the country has reached.
+    pub fn share(&self) -> f32 {
+        (self.0 / MAX_WAR_EXHAUSTION).clamp(0.0, 1.0)
+    }
+}
+
+/// Country made to serve another by a peace, see [`PeaceDemands::vassalize`]. It pays
+/// [`VASSAL_TRIBUTE`] of its income to its overlord every turn.
+#[derive(Component)]
+pub struct Vassal {
+    pub overlord: Entity,
+}
+
+/// Province ceded by `from` in a peace. Once `from` has no land left it is a captive nation, which
+/// a later peace can release with these provinces.
+#[derive(Component)]
+pub struct Conquered {
+    pub from: Entity,
+}
+
+#[derive(Resource, Default)]
+pub struct Wars {
+    pub active_wars: Vec<Entity>,
+}
+
+impl Wars {
+    pub fn add_war(&mut self, war_entity: Entity) {
+        self.active_wars.push(war_entity);
+    }
+
+    pub fn remove_war(&mut self, war_entity: Entity) {
+        self.active_wars.retain(|&e| e != war_entity);
+    }
+}
+
+#[derive(Component, Default)]
+pub struct WarRelations {
+    pub at_war_with: HashSet<Entity>,
+}
+
+impl WarRelations {
+    pub fn is_at_war_with(&self, other: Entity) -> bool {
+        self.at_war_with.contains(&other)
+    }
+
+    pub fn add_enemy(&mut self, enemy: Entity) {
+        self.at_war_with.insert(enemy);
+    }
+
+    pub fn remove_enemy(&mut self, enemy: Entity) {
+        self.at_war_with.remove(&enemy);
+    }
+}
+
+#[derive(Component)]
+pub struct Occupied {
+    pub occupier: Entity,
+}
+
+/// Siege laid to a province by the countries at war with its owner.
+#[derive(Component)]
+pub struct SiegeProgress {
+    /// Countries taking part, never empty. The first one leads the siege and occupies the
+    /// province when it falls.
+    pub besiegers: Vec<Entity>,
+    /// Soldiers of all besiegers together.
+    pub soldiers: u32,
+    /// Soldiers needed for the siege to progress.
+    pub required: u32,
+    pub progress: u32,
+    /// Progress needed for the province to fall.
+    pub progress_required: u32,
+}
+
+impl SiegeProgress {
+    pub fn leader(&self) -> Entity {
+        self.besiegers[0]
+    }
+
+    pub fn is_besieged_by(&self, country: Entity) -> bool {
+        self.besiegers.contains(&country)
+    }
+
+    /// Updates the countries taking part, the leader keeps leading as long as it stays. When it
+    /// leaves, the largest besieger left takes over the siege with the progress made so far.
+    fn set_besiegers(&mut self, besiegers: &[(Entity, u32)]) {
+        let leader = self.besiegers.first().copied();
+        let mut countries: Vec<Entity> = besiegers.iter().map(|&(country, _)| country).collect();
+        if let Some(index) = leader.and_then(|leader| countries.iter().position(|&c| c == leader)) {
+            countries[..=index].rotate_right(1);
+        }
+        self.besiegers = countries;
+        self.soldiers = besiegers.iter().map(|&(_, soldiers)| soldiers).sum();
+    }
+}
+
+/// Progress a siege needs for a fortified province to fall.
+const SIEGE_TURNS_REQUIRED: u32 = 3;
+/// Progress a siege needs for a province without forts to fall.
+const QUICK_SIEGE_TURNS: u32 = 1;
+
+/// Owner's side retaking an occupied province, see [`liberate_provinces`].
+#[derive(Component)]
+pub struct Liberation {
+    pub progress: u32,
+}
+
+/// Turns the owner's side has to hold an occupied province to free it.
+pub const LIBERATION_TURNS: u32 = 2;
+/// Most progress a siege makes in a turn, however many soldiers besiege the province.
+const MAX_SIEGE_SPEED: u32 = 2;
+/// Soldiers needed to besiege a province without a fort.
+const MIN_SIEGE_SOLDIERS: u32 = REGIMENT_SIZE;
+/// Soldiers needed on top of [`MIN_SIEGE_SOLDIERS`] for every fort, enough to outnumber its
+/// garrison.
+const SIEGE_SOLDIERS_PER_FORT_LEVEL: u32 = FORT_GARRISON;
+/// War score of every battle won.
+const BATTLE_WAR_SCORE: i32 = 5;
+/// War score of occupying all of the enemy's provinces.
+const OCCUPATION_WAR_SCORE: f32 = 100.0;
+/// Highest war score either side gets from holding the war goal, a point per turn.
+const MAX_TICKING_WAR_SCORE: i32 = 25;
+/// War exhaustion a country gains every turn it's at war.
+const WAR_EXHAUSTION_PER_TURN: f32 = 1.0;
+/// War exhaustion a country recovers from every turn of peace.
+const WAR_EXHAUSTION_RECOVERY: f32 = 2.0;
+/// War exhaustion at which a country is as weary of war as it gets.
+const MAX_WAR_EXHAUSTION: f32 = 20.0;
+/// Share of its income a country loses per point of war exhaustion.
+const WAR_EXHAUSTION_INCOME_PENALTY: f32 = 0.01;
+/// Name of the country modifier war exhaustion costs income through.
+const WAR_EXHAUSTION_MODIFIER: &str = "War exhaustion";
+/// Share of its income a vassal pays its overlord.
+pub const VASSAL_TRIBUTE: f32 = 0.25;
+/// War score of making the loser a vassal.
+pub const VASSALIZE_WAR_SCORE: i32 = 60;
+/// War score of releasing a nation.
+pub const RELEASE_WAR_SCORE: i32 = 30;
+/// War score of making the loser break its alliances.
+pub const BREAK_ALLIANCES_WAR_SCORE: i32 = 15;
+/// How much less willing an AI country is to accept a peace making it a vassal, in percentage
+/// points.
+const VASSALIZE_RELUCTANCE: f32 = 30.0;
+/// The same for releasing a nation.
+const RELEASE_RELUCTANCE: f32 = 15.0;
+/// The same for breaking its alliances.
+const BREAK_ALLIANCES_RELUCTANCE: f32 = 10.0;
+
+/// Terms of a peace besides the provinces ceded, each costing [`PeaceDemands::war_score`].
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct PeaceDemands {
+    /// The loser becomes the winner's [`Vassal`].
+    pub vassalize: bool,
+    /// Nation the loser gives up its provinces to, see [`WarStatus::releases`].
+    pub release: Option<Release>,
+    /// The loser breaks all of its alliances.
+    pub break_alliances: bool,
+}
+
+impl PeaceDemands {
+    pub fn any(&self) -> bool {
+        self.vassalize || self.release.is_some() || self.break_alliances
+    }
+
+    /// War score the demands cost, on top of the provinces demanded.
+    pub fn war_score(&self) -> i32 {
+        [
+            (self.vassalize, VASSALIZE_WAR_SCORE),
+            (self.release.is_some(), RELEASE_WAR_SCORE),
+            (self.break_alliances, BREAK_ALLIANCES_WAR_SCORE),
+        ]
+        .into_iter()
+        .filter(|(demanded, _)| *demanded)
+        .map(|(_, cost)| cost)
+        .sum()
+    }
+}
+
+/// Nation a peace makes the loser release.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Release {
+    /// Captive nation given back the provinces taken from it, see [`WarStatus::captives`].
+    Captive(Entity),
+    /// Releasable nation created from its provinces, by its index in [`Releasables`].
+    Nation(usize),
+}
+
+#[derive(Component)]
+pub struct PeaceOffer {
+    pub from: Entity,
+    pub to: Entity,
+    pub war_entity: Entity,
+    pub provinces_to_cede: Vec<Entity>,
+    pub demands: PeaceDemands,
+    /// Ends the whole league war, see [`crate::religion::enforce_tolerance`].
+    pub enforce_tolerance: bool,
+}
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+#[derive(Message)]
+pub struct DeclareWarEvent {
+    pub attacker: Entity,
+    pub defender: Entity,
+    /// Whether the defender calls its allies to arms.
+    pub calls_allies: bool,
+}
+
+impl DeclareWarEvent {
+    pub fn new(attacker: Entity, defender: Entity) -> Self {
+        Self {
+            attacker,
+            defender,
+            calls_allies: true,
+        }
+    }
+
+    /// War of an ally answering a call to arms. The enemy's allies aren't called in turn, or a
+    /// single war could drag in every alliance on the map.
+    pub fn joining(ally: Entity, enemy: Entity) -> Self {
+        Self {
+            attacker: ally,
+            defender: enemy,
+            calls_allies: false,
+        }
+    }
+}
+
+/// Message sent when an AI country answers a peace offer, with its reasons.
+#[derive(Message)]
+pub struct PeaceAnsweredEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub acceptance: Acceptance,
+}
+
+#[derive(Message)]
+pub struct PeaceOfferEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub war_entity: Entity,
+    pub provinces_to_cede: Vec<Entity>,
+    pub demands: PeaceDemands,
+    /// Ends the whole league war, see [`crate::religion::enforce_tolerance`].
+    pub enforce_tolerance: bool,
+}
+
+#[derive(Message)]
+pub struct AcceptPeaceEvent {
+    pub peace_offer_entity: Entity,
+}
+
+/// Message sent when a peace ends a war with provinces ceded or tolerance enforced, rather than a
+/// white peace.
+#[derive(Message)]
+pub struct WarWonEvent {
+    pub winner: Entity,
+    pub loser: Entity,
+}
+
+/// Message sent when a siege finishes and the province becomes occupied.
+#[derive(Message)]
+pub struct SiegeCompletedEvent {
+    pub province: Entity,
+    pub occupier: Entity,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+pub fn are_at_war(
+    country1: Entity,
+    country2: Entity,
+    war_relations: &Query<&WarRelations>,
+) -> bool {
+    war_relations
+        .get(country1)
+        .map(|r| r.is_at_war_with(country2))
+        .unwrap_or(false)
+}
+
+pub fn get_war_between(
+    country1: Entity,
+    country2: Entity,
+    wars: &Res<Wars>,
+    war_query: &Query<(Entity, &War)>,
+) -> Option<Entity> {
+    wars.active_wars.iter().find_map(|&war_entity| {
+        war_query.get(war_entity).ok().and_then(|(_, war)| {
+            let matches = (war.attacker == country1 && war.defender == country2)
+                || (war.attacker == country2 && war.defender == country1);
+            matches.then_some(war_entity)
+        })
+    })
+}
+
+// ============================================================================
+// WAR DECLARATION
+// ============================================================================
+
+pub fn handle_declare_war(
+    mut commands: Commands,
+    mut events: MessageReader<DeclareWarEvent>,
+    mut wars: ResMut<Wars>,
+    mut war_relations: Query<&mut WarRelations>,
+    provinces: Query<(Entity, &Province, &Owner)>,
+    alliances: Query<&Alliances>,
+    turn: Res<Turn>,
+) {
+    for event in events.read() {
+        if !validate_war_declaration(&event, &war_relations) {
+            continue;
+        }
+        if event.calls_allies {
+            call_allies_to_arms(&mut commands, &alliances, &war_relations, &event);
+        }
+        let goal = pick_war_goal(&provinces, event.attacker, event.defender);
+        let war_entity = create_war(&mut commands, &event, goal, turn.current_turn());
+        wars.add_war(war_entity);
+        update_war_relations(&mut commands, &mut war_relations, &event);
+        info!("War declared: {:?} vs {:?}", event.attacker, event.defender);
+    }
+}
+
+fn validate_war_declaration(
+    event: &DeclareWarEvent,
+    war_relations: &Query<&mut WarRelations>,
+) -> bool {
+    if event.attacker == event.defender {
+        warn!("Cannot declare war on yourself!");
+        return false;
+    }
+    if let Ok(relations) = war_relations.get(event.attacker) {
+        if relations.is_at_war_with(event.defender) {
+            info!(
+                "Countries {:?} and {:?} are already at war",
+                event.attacker, event.defender
+            );
+            return false;
+        }
+    }
+    true
+}
+
+/// The defender's province closest to the attacker's land, the one a border war would be fought
+/// over. Ties are broken by position so that the pick is deterministic.
+pub fn pick_war_goal(
+    provinces: &Query<(Entity, &Province, &Owner)>,
+    attacker: Entity,
+    defender: Entity,
+) -> Option<Entity> {
+    let attacker_land: Vec<Hex> = provinces
+        .iter()
+        .filter(|(_, _, owner)| owner.0 == attacker)
+        .map(|(_, province, _)| *province.get_hex())
+        .collect();
+    provinces
+        .iter()
+        .filter(|(_, province, owner)| owner.0 == defender && province.is_ownable())
+        .min_by_key(|(_, province, _)| {
+            let hex = province.get_hex();
+            let distance = attacker_land
+                .iter()
+                .map(|own| own.distance(hex))
+                .min()
+                .unwrap_or(i32::MAX);
+            (distance, hex.q(), hex.r())
+        })
+        .map(|(entity, _, _)| entity)
+}
+
+fn create_war(
+    commands: &mut Commands,
+    event: &DeclareWarEvent,
+    goal: Option<Entity>,
+    started: u32,
+) -> Entity {
+    commands
+        .spawn((
+            War {
+                attacker: event.attacker,
+                defender: event.defender,
+                goal,
+                started,
+            },
+            WarScore::default(),
+        ))
+        .id()
+}
+
+fn update_war_relations(
+    commands: &mut Commands,
+    war_relations: &mut Query<&mut WarRelations>,
+    event: &DeclareWarEvent,
+) {
+    add_war_relation(commands, war_relations, event.attacker, event.defender);
+    add_war_relation(commands, war_relations, event.defender, event.attacker);
+}
+
+fn add_war_relation(
+    commands: &mut Commands,
+    war_relations: &mut Query<&mut WarRelations>,
+    country: Entity,
+    enemy: Entity,
+) {
+    if let Ok(mut relations) = war_relations.get_mut(country) {
+        relations.add_enemy(enemy);
+    } else {
+        let mut relations = WarRelations::default();
+        relations.add_enemy(enemy);
+        commands.entity(country).insert(relations);
+    }
+}
+
+// ============================================================================
+// PEACE OFFERS
+// ============================================================================
+
+/// A country has a single offer waiting for each country it offered peace to, the latest, so
+/// answers can name the offer by its sender.
+pub fn handle_peace_offers(
+    mut commands: Commands,
+    mut events: MessageReader<PeaceOfferEvent>,
+    peace_offers: Query<(Entity, &PeaceOffer)>,
+) {
+    let mut latest: Vec<&PeaceOfferEvent> = Vec::new();
+    for event in events.read() {
+        latest.retain(|offer| (offer.from, offer.to) != (event.from, event.to));
+        latest.push(event);
+    }
+    for event in latest {
+        for (entity, offer) in &peace_offers {
+            if (offer.from, offer.to) == (event.from, event.to) {
+                commands.entity(entity).despawn();
+            }
+        }
+        commands.spawn(PeaceOffer {
+            from: event.from,
+            to: event.to,
+            war_entity: event.war_entity,
+            provinces_to_cede: event.provinces_to_cede.clone(),
+            demands: event.demands,
+            enforce_tolerance: event.enforce_tolerance,
+        });
+        info!("Peace offer sent from {:?} to {:?}", event.from, event.to);
+    }
+}
+
+/// Answers the peace offers sent to AI countries. The offers are evaluated in parallel on the
+/// compute task pool, the decisions are then applied one after another.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn ai_handle_peace_offers(
+    mut commands: Commands,
+    peace_offers: Query<(Entity, &PeaceOffer)>,
+    player: Res<Player>,
+    mut accept_peace_events: MessageWriter<AcceptPeaceEvent>,
+    mut answered_events: MessageWriter<PeaceAnsweredEvent>,
+    provinces: Query<&Owner, With<Province>>,
+    territory: Query<(&Province, &Owner)>,
+    occupations: Query<(Entity, &Owner, &Occupied)>,
+    armies: Query<(&HexPos, &Owner, &ArmyComposition), With<Army>>,
+    war_scores: Query<(&War, &WarScore)>,
+    war_relations: Query<&WarRelations>,
+    country_modifiers: Query<&CountryModifiers>,
+    exhaustion: Query<&WarExhaustion>,
+    names: Query<&DisplayName>,
+    rules: Res<GameRules>,
+    mut decisions: Local<Parallel<Vec<(Entity, Acceptance)>>>,
+) {
+    if peace_offers.is_empty() {
+        return;
+    }
+    let province_counts = count_provinces_per_country(&provinces);
+    let overview = WarOverview::new(&territory, &occupations, &armies);
+
+    peace_offers.par_iter().for_each(|(offer_entity, offer)| {
+        if player.is_human(offer.to) {
+            return;
+        }
+        let acceptance = if offer.enforce_tolerance {
+            // Only a beaten country gives in to the enemy's faith.
+            let beaten = occupations
+                .iter()
+                .any(|(_, owner, occupied)| owner.0 == offer.to && occupied.occupier == offer.from);
+            let mut acceptance = Acceptance::default();
+            if beaten {
+                acceptance.add("Beaten by the enemy", 100.0);
+            } else {
+                acceptance.add("None of our land is occupied", -100.0);
+            }
+            acceptance
+        } else {
+            let recipient_aggression = aggression(country_modifiers.get(offer.to).ok());
+            let personality = names
+                .get(offer.to)
+                .ok()
+                .and_then(|name| rules.handicaps.get(&name.0))
+                .map(|handicap| handicap.personality)
+                .unwrap_or_default();
+            let situation = overview.situation(
+                offer.to,
+                war_relations.get(offer.to).ok(),
+                &province_counts,
+                exhaustion.get(offer.to).map_or(0.0, WarExhaustion::share),
+            );
+            let enemy_war_score = war_scores.get(offer.war_entity).map_or(0, |(war, score)| {
+                overview.war_score(war, score, offer.from, &province_counts)
+            });
+            let occupied_demands = offer
+                .provinces_to_cede
+                .iter()
+                .filter(|&&province| {
+                    provinces
+                        .get(province)
+                        .is_ok_and(|owner| owner.0 == offer.to)
+                        && overview.occupier(province) == Some(offer.from)
+                })
+                .count();
+            evaluate_peace_offer(
+                offer,
+                &provinces,
+                &province_counts,
+                rules.difficulty,
+                recipient_aggression,
+                &situation,
+                &personality.peace_weights(),
+                enemy_war_score,
+                occupied_demands,
+            )
+        };
+        decisions
+            .borrow_local_mut()
+            .push((offer_entity, acceptance));
+    });
+
+    // Threads finish in any order, sort so the decisions are applied deterministically. Entity
+    // numbering differs between the games of a multiplayer session, the country names don't.
+    let mut ordered_decisions = Vec::new();
+    decisions.drain_into(&mut ordered_decisions);
+    let name = |country: Entity| names.get(country).map_or("", |name| name.0.as_str());
+    ordered_decisions.sort_by_cached_key(|(offer_entity, _)| {
+        peace_offers
+            .get(*offer_entity)
+            .map_or(("", ""), |(_, offer)| (name(offer.to), name(offer.from)))
+    });
+    for (offer_entity, acceptance) in ordered_decisions {
+        if let Ok((_, offer)) = peace_offers.get(offer_entity) {
+            apply_ai_peace_decision(
+                &mut commands,
+                offer_entity,
+                offer,
+                acceptance.accepted(),
+                &mut accept_peace_events,
+            );
+            answered_events.write(PeaceAnsweredEvent {
+                from: offer.from,
+                to: offer.to,
+                acceptance,
+            });
+        }
+    }
+}
+
+fn count_provinces_per_country(
+    provinces: &Query<&Owner, With<Province>>,
+) -> HashMap<Entity, usize> {
+    let mut counts = HashMap::new();
+    for owner in provinces.iter() {
+        *counts.entry(owner.0).or_default() += 1;
+    }
+    counts
+}
+
+/// War score a country worn out by war lets its enemy win peace demands without.
+const EXHAUSTION_CONCESSION: f32 = 25.0;
+/// Distance from its capital within which enemy armies threaten an AI country.
+const CAPITAL_THREAT_RADIUS: i32 = 2;
+
+/// Where an AI country stands in its wars, weighed by its [`PeaceWeights`] when offered peace.
+pub struct MilitarySituation {
+    /// Share of the soldiers of the country and its enemies that are the country's, 0.5 when
+    /// neither side has any.
+    pub strength_share: f32,
+    /// Share of the country's provinces under occupation.
+    pub occupied_share: f32,
+    /// Share of [`MAX_WAR_EXHAUSTION`] the country's [`WarExhaustion`] reached.
+    pub exhaustion: f32,
+    /// Whether enemy armies stand within [`CAPITAL_THREAT_RADIUS`] of the country's capital.
+    pub capital_threatened: bool,
+}
+
+impl MilitarySituation {
+    /// Adds the reasons the situation gives to accept peace, as shares of the `base` share of its
+    /// provinces the country gives up anyway.
+    fn add_reasons(&self, weights: &PeaceWeights, base: f32, acceptance: &mut Acceptance) {
+        acceptance.add(
+            "Army strength",
+            base * weights.army_strength * (1.0 - 2.0 * self.strength_share),
+        );
+        acceptance.add(
+            "Occupied provinces",
+            base * weights.occupation * self.occupied_share,
+        );
+        acceptance.add(
+            "War exhaustion",
+            base * weights.exhaustion * self.exhaustion,
+        );
+        if self.capital_threatened {
+            acceptance.add("Capital threatened", base * weights.capital_threat);
+        }
+    }
+}
+
+/// One reason for or against an AI country accepting a peace offer, in percentage points.
+pub struct AcceptanceReason {
+    pub label: String,
+    pub value: f32,
+}
+
+/// Why an AI country accepts or refuses a peace offer. It accepts when the reasons add up to more
+/// than zero.
+#[derive(Default)]
+pub struct Acceptance(pub Vec<AcceptanceReason>);
+
+impl Acceptance {
+    fn add(&mut self, label: impl Into<String>, value: f32) {
+        self.0.push(AcceptanceReason {
+            label: label.into(),
+            value,
+        });
+    }
+
+    pub fn total(&self) -> f32 {
+        self.0.iter().map(|reason| reason.value).sum()
+    }
+
+    pub fn accepted(&self) -> bool {
+        self.total() > 0.0
+    }
+
+    /// One line per reason, e.g. "+20 Capital threatened". Reasons that round to nothing are
+    /// left out.
+    pub fn describe(&self) -> String {
+        self.0
+            .iter()
+            .filter(|reason| reason.value.round() != 0.0)
+            .map(|reason| format!("{:+.0} {}", reason.value, reason.label))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Armies, occupations and capitals of every country, gathered once for all peace offers.
+struct WarOverview {
+    soldiers: HashMap<Entity, u32>,
+    army_positions: Vec<(Entity, Hex)>,
+    occupied: HashMap<Entity, usize>,
+    /// Provinces occupied per owner and occupier.
+    occupied_by: HashMap<(Entity, Entity), usize>,
+    occupiers: HashMap<Entity, Entity>,
+    capitals: HashMap<Entity, Hex>,
+}
+
+impl WarOverview {
+    #[allow(clippy::type_complexity)]
+    fn new(
+        territory: &Query<(&Province, &Owner)>,
+        occupations: &Query<(Entity, &Owner, &Occupied)>,
+        armies: &Query<(&HexPos, &Owner, &ArmyComposition), With<Army>>,
+    ) -> Self {
+        let mut soldiers = HashMap::new();
+        let mut army_positions = Vec::new();
+        for (pos, owner, composition) in armies {
+            *soldiers.entry(owner.0).or_default() += composition.total_size();
+            army_positions.push((owner.0, pos.0));
+        }
+        let mut occupied = HashMap::new();
+        let mut occupied_by = HashMap::new();
+        let mut occupiers = HashMap::new();
+        for (province, owner, occupation) in occupations {
+            *occupied.entry(owner.0).or_default() += 1;
+            *occupied_by
+                .entry((owner.0, occupation.occupier))
+                .or_default() += 1;
+            occupiers.insert(province, occupation.occupier);
+        }
+        Self {
+            soldiers,
+            army_positions,
+            occupied,
+            occupied_by,
+            occupiers,
+            capitals: crate::map::capitals(territory),
+        }
+    }
+
+    fn occupier(&self, province: Entity) -> Option<Entity> {
+        self.occupiers.get(&province).copied()
+    }
+
+    /// War score of `country` in the war, see [`WarScore::of`].
+    fn war_score(
+        &self,
+        war: &War,
+        score: &WarScore,
+        country: Entity,
+        province_counts: &HashMap<Entity, usize>,
+    ) -> i32 {
+        let occupied_share = |owner: Entity, occupier: Entity| {
+            let occupied = self
+                .occupied_by
+                .get(&(owner, occupier))
+                .copied()
+                .unwrap_or(0);
+            occupied as f32 / province_counts.get(&owner).copied().unwrap_or(0).max(1) as f32
+        };
+        score.of(
+            war,
+            country,
+            occupied_share(war.defender, war.attacker),
+            occupied_share(war.attacker, war.defender),
+        )
+    }
+
+    fn situation(
+        &self,
+        country: Entity,
+        relations: Option<&WarRelations>,
+        province_counts: &HashMap<Entity, usize>,
+        exhaustion: f32,
+    ) -> MilitarySituation {
+        let is_enemy = |other: Entity| relations.is_some_and(|r| r.is_at_war_with(other));
+        let own = self.soldiers.get(&country).copied().unwrap_or(0) as f32;
+        let enemy: f32 = self
+            .soldiers
+            .iter()
+            .filter(|&(&other, _)| is_enemy(other))
+            .map(|(_, &soldiers)| soldiers as f32)
+            .sum();
+        let strength_share = if own + enemy > 0.0 {
+            own / (own + enemy)
+        } else {
+            0.5
+        };
+
+        let provinces = province_counts.get(&country).copied().unwrap_or(0);
+        let occupied = self.occupied.get(&country).copied().unwrap_or(0);
+        let occupied_share = occupied as f32 / provinces.max(1) as f32;
+
+        let capital_threatened = self.capitals.get(&country).is_some_and(|capital| {
+            self.army_positions.iter().any(|(owner, hex)| {
+                is_enemy(*owner) && hex.distance(capital) <= CAPITAL_THREAT_RADIUS
+            })
+        });
+
+        MilitarySituation {
+            strength_share,
+            occupied_share,
+            exhaustion,
+            capital_threatened,
+        }
+    }
+}
+
+fn apply_ai_peace_decision(
+    commands: &mut Commands,
+    offer_entity: Entity,
+    offer: &PeaceOffer,
+    accepted: bool,
+    accept_peace_events: &mut MessageWriter<AcceptPeaceEvent>,
+) {
+    if accepted {
+        info!(
+            "AI country {:?} accepts peace offer from {:?}",
+            offer.to, offer.from
+        );
+        accept_peace_events.write(AcceptPeaceEvent {
+            peace_offer_entity: offer_entity,
+        });
+    } else {
+        info!(
+            "AI country {:?} rejects peace offer from {:?}",
+            offer.to, offer.from
+        );
+        commands.entity(offer_entity).despawn();
+    }
+}
+
+/// Weighs a peace offer to an AI country. On top of the reasons to give in, the provinces demanded
+/// that the enemy doesn't occupy yet and the other [`PeaceDemands`] have to be won with war score,
+/// `enemy_war_score` being the enemy's and `occupied_demands` the number of demanded provinces it
+/// occupies.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_peace_offer(
+    offer: &PeaceOffer,
+    provinces: &Query<&Owner, With<Province>>,
+    province_counts: &HashMap<Entity, usize>,
+    difficulty: Difficulty,
+    aggression: f32,
+    situation: &MilitarySituation,
+    weights: &PeaceWeights,
+    enemy_war_score: i32,
+    occupied_demands: usize,
+) -> Acceptance {
+    let mut acceptance = Acceptance::default();
+    let demands = &offer.demands;
+    let provinces_demanded = offer.provinces_to_cede.len();
+    if provinces_demanded == 0 && !demands.any() {
+        acceptance.add("No demands", 100.0);
+        return acceptance;
+    }
+
+    let provinces_from_recipient = count_provinces_from_recipient(offer, provinces);
+    if provinces_from_recipient <= 2 && !demands.any() {
+        acceptance.add("Small demands", 100.0);
+        return acceptance;
+    }
+
+    let total_ai_provinces = province_counts.get(&offer.to).copied().unwrap_or(0);
+    if total_ai_provinces == 0 {
+        acceptance.add("No provinces left to give", -100.0);
+        return acceptance;
+    }
+    // Aggressive countries hold out for better terms, losing ones give in to worse.
+    let base = 100.0 * difficulty.ai_max_province_loss() / aggression.max(f32::EPSILON);
+    acceptance.add("Base willingness", base);
+    situation.add_reasons(weights, base, &mut acceptance);
+    let loss_ratio = provinces_from_recipient as f32 / total_ai_provinces as f32;
+    acceptance.add(
+        format!(
+            "Demands {} of {} provinces",
+            provinces_from_recipient, total_ai_provinces
+        ),
+        -100.0 * loss_ratio,
+    );
+    if demands.vassalize {
+        acceptance.add("Would become a vassal", -VASSALIZE_RELUCTANCE);
+    }
+    if demands.release.is_some() {
+        acceptance.add("Releases a nation", -RELEASE_RELUCTANCE);
+    }
+    if demands.break_alliances {
+        acceptance.add("Breaks its alliances", -BREAK_ALLIANCES_RELUCTANCE);
+    }
+    // Every province not taken yet costs its share of the war score, and every other demand its
+    // own. A war-weary country lets some of it go.
+    let unoccupied = provinces_from_recipient.saturating_sub(occupied_demands);
+    let needed = 100.0 * unoccupied as f32 / total_ai_provinces as f32 + demands.war_score() as f32;
+    let available = enemy_war_score as f32 + EXHAUSTION_CONCESSION * situation.exhaustion;
+    if needed > available {
+        acceptance.add(
+            format!(
+                "War score of {} where {:.0} is needed",
+                enemy_war_score, needed
+            ),
+            -100.0,
+        );
+    }
+    acceptance
+}
+
+fn count_provinces_from_recipient(
+    offer: &PeaceOffer,
+    provinces: &Query<&Owner, With<Province>>,
+) -> usize {
+    offer
+        .provinces_to_cede
+        .iter()
+        .filter(|&&prov| {
+            provinces
+                .get(prov)
+                .map(|owner| owner.0 == offer.to)
+                .unwrap_or(false)
+        })
+        .count()
+}
+
+// ============================================================================
+// ACCEPT PEACE
+// ============================================================================
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_accept_peace(
+    mut commands: Commands,
+    mut events: MessageReader<AcceptPeaceEvent>,
+    mut wars: ResMut<Wars>,
+    mut war_relations: Query<&mut WarRelations>,
+    peace_offers: Query<&PeaceOffer>,
+    war_query: Query<&War>,
+    occupied_provinces: Query<(Entity, &Occupied)>,
+    subject_terms: SubjectTerms,
+) {
+    for event in events.read() {
+        process_peace_acceptance(
+            &mut commands,
+            event,
+            &mut wars,
+            &mut war_relations,
+            &peace_offers,
+            &war_query,
+            &occupied_provinces,
+            &subject_terms,
+        );
+    }
+}
+
+/// What the [`PeaceDemands`] of an accepted peace act on.
+#[derive(SystemParam)]
+pub struct SubjectTerms<'w, 's> {
+    conquered: Query<'w, 's, (Entity, &'static Owner, &'static Conquered)>,
+    alliances: Query<'w, 's, &'static Alliances>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_peace_acceptance(
+    commands: &mut Commands,
+    event: &AcceptPeaceEvent,
+    wars: &mut ResMut<Wars>,
+    war_relations: &mut Query<&mut WarRelations>,
+    peace_offers: &Query<&PeaceOffer>,
+    war_query: &Query<&War>,
+    occupied_provinces: &Query<(Entity, &Occupied)>,
+    subject_terms: &SubjectTerms,
+) {
+    let Ok(peace_offer) = peace_offers.get(event.peace_offer_entity) else {
+        warn!(
+            "Peace offer entity not found: {:?}",
+            event.peace_offer_entity
+        );
+        return;
+    };
+
+    let Ok(war) = war_query.get(peace_offer.war_entity) else {
+        warn!("War entity not found: {:?}", peace_offer.war_entity);
+        commands.entity(event.peace_offer_entity).despawn();
+        return;
+    };
+
+    execute_peace_terms(
+        commands,
+        peace_offer,
+        war,
+        wars,
+        war_relations,
+        occupied_provinces,
+    );
+    execute_peace_demands(commands, peace_offer, subject_terms);
+    cleanup_peace_entities(
+        commands,
+        wars,
+        peace_offer.war_entity,
+        event.peace_offer_entity,
+    );
+    info!(
+        "Peace accepted between {:?} and {:?}",
+        war.attacker, war.defender
+    );
+}
+
+fn execute_peace_terms(
+    commands: &mut Commands,
+    peace_offer: &PeaceOffer,
+    war: &War,
+    _wars: &mut ResMut<Wars>,
+    war_relations: &mut Query<&mut WarRelations>,
+    occupied_provinces: &Query<(Entity, &Occupied)>,
+) {
+    transfer_provinces(commands, peace_offer);
+    clear_occupations(commands, war, occupied_provinces);
+    remove_war_relations(war_relations, war);
+    if peace_offer.enforce_tolerance
+        || !peace_offer.provinces_to_cede.is_empty()
+        || peace_offer.demands.any()
+    {
+        commands.write_message(WarWonEvent {
+            winner: peace_offer.from,
+            loser: peace_offer.to,
+        });
+    }
+    if peace_offer.enforce_tolerance {
+        commands.write_message(ToleranceEnforcedEvent {
+            winner: peace_offer.from,
+            loser: peace_offer.to,
+        });
+    }
+}
+
+fn transfer_provinces(commands: &mut Commands, peace_offer: &PeaceOffer) {
+    for &province_entity in &peace_offer.provinces_to_cede {
+        cede_province(commands, province_entity, peace_offer.from);
+        commands.entity(province_entity).insert(Conquered {
+            from: peace_offer.to,
+        });
+    }
+}
+
+/// Carries out the terms of an accepted peace besides the provinces ceded.
+fn execute_peace_demands(commands: &mut Commands, peace_offer: &PeaceOffer, terms: &SubjectTerms) {
+    let (winner, loser) = (peace_offer.from, peace_offer.to);
+    match peace_offer.demands.release {
+        Some(Release::Captive(captive)) => {
+            for (province_entity, owner, conquered) in &terms.conquered {
+                if owner.0 == loser && conquered.from == captive {
+                    cede_province(commands, province_entity, captive);
+                }
+            }
+            info!("{:?} released by {:?}", captive, loser);
+        }
+        Some(Release::Nation(nation)) => {
+            commands.write_message(ReleaseNationEvent {
+                nation,
+                from: loser,
+            });
+        }
+        None => {}
+    }
+    if peace_offer.demands.break_alliances
+        && let Ok(alliances) = terms.alliances.get(loser)
+    {
+        for ally in alliances.allies() {
+            commands.write_message(BreakAllianceEvent {
+                from: loser,
+                to: ally,
+            });
+        }
+    }
+    if peace_offer.demands.vassalize {
+        commands.entity(loser).insert(Vassal { overlord: winner });
+        info!("{:?} becomes a vassal of {:?}", loser, winner);
+    }
+}
+
+/// Hands a province over to another country, ending any occupation of it. Whoever it was
+/// [`Conquered`] from loses their claim to it.
+pub fn cede_province(commands: &mut Commands, province_entity: Entity, to: Entity) {
+    commands
+        .entity(province_entity)
+        .remove::<(Occupied, Conquered)>()
+        .insert(Owner(to));
+    info!("Province {:?} ceded to {:?}", province_entity, to);
+}
+
+fn clear_occupations(
+    commands: &mut Commands,
+    war: &War,
+    occupied_provinces: &Query<(Entity, &Occupied)>,
+) {
+    for (province_entity, occupied) in occupied_provinces.iter() {
+        if occupied.occupier == war.attacker || occupied.occupier == war.defender {
+            commands.entity(province_entity).remove::<Occupied>();
+        }
+    }
+}
+
+fn remove_war_relations(war_relations: &mut Query<&mut WarRelations>, war: &War) {
+    if let Ok(mut relations) = war_relations.get_mut(war.attacker) {
+        relations.remove_enemy(war.defender);
+    }
+    if let Ok(mut relations) = war_relations.get_mut(war.defender) {
+        relations.remove_enemy(war.attacker);
+    }
+}
+
+fn cleanup_peace_entities(
+    commands: &mut Commands,
+    wars: &mut ResMut<Wars>,
+    war_entity: Entity,
+    offer_entity: Entity,
+) {
+    wars.remove_war(war_entity);
+    commands.entity(war_entity).despawn();
+    commands.entity(offer_entity).despawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buildings::BuildingType;
+    use crate::hex::Hex;
+    use crate::player_command::PlayerCommand;
+    use crate::rules::{Handicap, Personality};
+    use crate::test_utils::{TestGame, peace_offered_to_ai_nations};
+
+    fn spawn_fort(game: &mut TestGame, owner: Option<Entity>) -> Entity {
+        let province = game.spawn_province("Fort", Hex::new(0, 0), owner);
+        game.world_mut().spawn((
+            Building {
+                building_type: BuildingType::Fort,
+            },
+            ChildOf(province),
+        ));
+        province
+    }
+
+    #[test]
+    fn siege_occupies_enemy_province() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let province = spawn_fort(&mut game, Some(defender));
+        // With soldiers to spare for the attrition of the siege.
+        game.spawn_army(
+            attacker,
+            Hex::new(0, 0),
+            siege_soldiers_required(1) + 2 * REGIMENT_SIZE,
+        );
+        game.declare_war(attacker, defender);
+
+        // The siege starts the turn the army arrives and progresses the turns after.
+        game.end_turns(SIEGE_TURNS_REQUIRED);
+        assert!(game.get::<Occupied>(province).is_none());
+        assert!(game.get::<SiegeProgress>(province).is_some());
+
+        game.end_turn();
+        let occupied = game
+            .get::<Occupied>(province)
+            .expect("province should be occupied");
+        assert_eq!(occupied.occupier, attacker);
+    }
+
+    #[test]
+    fn undefended_provinces_take_a_turn_to_occupy_and_can_be_retaken() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(defender));
+        let army = game.spawn_army(attacker, Hex::new(0, 0), 2 * REGIMENT_SIZE);
+        game.declare_war(attacker, defender);
+
+        game.end_turn();
+        assert!(game.get::<Occupied>(province).is_none());
+        game.end_turn();
+        assert_eq!(game.get::<Occupied>(province).unwrap().occupier, attacker);
+
+        // The owner's army marching back in ends the occupation.
+        game.world_mut().entity_mut(army).despawn();
+        game.spawn_army(defender, Hex::new(0, 0), REGIMENT_SIZE);
+        game.end_turns(LIBERATION_TURNS);
+        assert!(game.get::<Occupied>(province).is_some());
+        game.end_turn();
+        assert!(game.get::<Occupied>(province).is_none());
+        assert!(game.get::<Liberation>(province).is_none());
+    }
+
+    #[test]
+    fn co_belligerents_liberate_occupied_provinces() {
+        let mut game = TestGame::new();
+        let occupier = game.spawn_country("Occupier");
+        let owner = game.spawn_country("Owner");
+        let ally = game.spawn_country("Ally");
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(owner));
+        game.world_mut()
+            .entity_mut(province)
+            .insert(Occupied { occupier });
+        game.declare_war(occupier, owner);
+        game.declare_war(ally, occupier);
+
+        // Neutral armies don't free anything.
+        let neutral = game.spawn_country("Neutral");
+        let army = game.spawn_army(neutral, Hex::new(0, 0), REGIMENT_SIZE);
+        game.end_turns(LIBERATION_TURNS + 1);
+        assert!(game.get::<Liberation>(province).is_none());
+
+        game.world_mut().entity_mut(army).despawn();
+        game.spawn_army(ally, Hex::new(0, 0), REGIMENT_SIZE);
+        game.end_turn();
+        assert_eq!(game.get::<Liberation>(province).unwrap().progress, 0);
+        game.end_turns(LIBERATION_TURNS);
+        assert!(game.get::<Occupied>(province).is_none());
+    }
+
+    #[test]
+    fn sieges_need_enough_soldiers_for_the_forts() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let province = spawn_fort(&mut game, Some(defender));
+        game.spawn_army(attacker, Hex::new(0, 0), REGIMENT_SIZE);
+        game.declare_war(attacker, defender);
+
+        game.end_turns(SIEGE_TURNS_REQUIRED);
+        assert!(game.get::<SiegeProgress>(province).is_none());
+
+        // Reinforced past the garrison, the siege starts.
+        game.spawn_army(attacker, Hex::new(0, 0), siege_soldiers_required(1));
+        game.end_turn();
+        let siege = game.get::<SiegeProgress>(province).unwrap();
+        assert_eq!(siege.required, siege_soldiers_required(1));
+        assert_eq!(siege.progress, 0);
+    }
+
+    #[test]
+    fn allies_besiege_together_and_faster() {
+        let mut game = TestGame::new();
+        let first = game.spawn_country("First");
+        let second = game.spawn_country("Second");
+        let defender = game.spawn_country("Defender");
+        let province = spawn_fort(&mut game, Some(defender));
+        let required = siege_soldiers_required(1);
+        game.spawn_army(first, Hex::new(0, 0), required + 2 * REGIMENT_SIZE);
+        game.spawn_army(second, Hex::new(0, 0), required - REGIMENT_SIZE);
+        game.declare_war(first, defender);
+        game.declare_war(second, defender);
+
+        game.end_turns(2);
+        let siege = game.get::<SiegeProgress>(province).unwrap();
+        assert_eq!(siege.besiegers, vec![first, second]);
+        assert_eq!(siege.progress, 2);
+
+        // The largest besieger leads the siege and takes the province.
+        game.end_turn();
+        assert_eq!(game.get::<Occupied>(province).unwrap().occupier, first);
+    }
+
+    #[test]
+    fn co_belligerents_carry_on_a_siege_its_leader_leaves() {
+        let mut game = TestGame::new();
+        let first = game.spawn_country("First");
+        let second = game.spawn_country("Second");
+        let defender = game.spawn_country("Defender");
+        let province = spawn_fort(&mut game, Some(defender));
+        let required = siege_soldiers_required(1);
+        let leader = game.spawn_army(first, Hex::new(0, 0), required);
+        game.spawn_army(second, Hex::new(0, 0), required - REGIMENT_SIZE);
+        game.declare_war(first, defender);
+        game.declare_war(second, defender);
+
+        game.end_turns(2);
+        assert_eq!(game.get::<SiegeProgress>(province).unwrap().leader(), first);
+
+        // Too weak on its own, the remaining besieger holds the siege without progressing.
+        game.world_mut().entity_mut(leader).despawn();
+        game.end_turn();
+        let siege = game.get::<SiegeProgress>(province).unwrap();
+        assert_eq!(siege.besiegers, vec![second]);
+        assert_eq!(siege.progress, 1);
+
+        game.spawn_army(second, Hex::new(0, 0), 3 * REGIMENT_SIZE);
+        game.end_turns(SIEGE_TURNS_REQUIRED - 1);
+        assert_eq!(game.get::<Occupied>(province).unwrap().occupier, second);
+    }
+
+    #[test]
+    fn no_siege_without_war() {
+        let mut game = TestGame::new();
+        let visitor = game.spawn_country("Visitor");
+        let owner = game.spawn_country("Owner");
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(owner));
+        game.spawn_army(visitor, Hex::new(0, 0), 10);
+
+        game.end_turns(SIEGE_TURNS_REQUIRED);
+        assert!(game.get::<SiegeProgress>(province).is_none());
+        assert!(game.get::<Occupied>(province).is_none());
+    }
+
+    #[test]
+    fn war_goal_is_the_closest_enemy_province() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.spawn_province("Home", Hex::new(0, 0), Some(attacker));
+        game.spawn_province("Capital", Hex::new(3, 0), Some(defender));
+        let border = game.spawn_province("Border", Hex::new(1, 0), Some(defender));
+
+        let war = game.declare_war(attacker, defender);
+        assert_eq!(game.get::<War>(war).unwrap().goal, Some(border));
+    }
+
+    fn offer_peace(game: &mut TestGame, from: Entity, to: Entity, war: Entity, cede: Vec<Entity>) {
+        game.world_mut().write_message(PeaceOfferEvent {
+            from,
+            to,
+            war_entity: war,
+            provinces_to_cede: cede,
+            demands: PeaceDemands::default(),
+            enforce_tolerance: false,
+        });
+        // Spawn the offer, let the AI answer it, then apply the answer.
+        for _ in 0..3 {
+            game.app.update();
+        }
+    }
+
+    #[test]
+    fn ai_accepts_small_peace_demands() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(3, Some(defender));
+        let war = game.declare_war(attacker, defender);
+
+        offer_peace(&mut game, attacker, defender, war, vec![provinces[0]]);
+
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, attacker);
+        assert_eq!(game.get::<Owner>(provinces[1]).unwrap().0, defender);
+        assert_eq!(game.count::<War>(), 0);
+        assert_eq!(game.count::<PeaceOffer>(), 0);
+        assert!(game.world().resource::<Wars>().active_wars.is_empty());
+        let relations = game.get::<WarRelations>(attacker).unwrap();
+        assert!(!relations.is_at_war_with(defender));
+    }
+
+    #[test]
+    fn players_answer_peace_offers_through_commands() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(defender);
+        let provinces = game.spawn_provinces(3, Some(defender));
+        let war = game.declare_war(attacker, defender);
+        let answer = |accept| PlayerCommand::AnswerPeace {
+            country: "Defender".to_string(),
+            from: "Attacker".to_string(),
+            accept,
+        };
+
+        offer_peace(&mut game, attacker, defender, war, vec![provinces[0]]);
+        game.world_mut().write_message(answer(false));
+        game.app.update();
+        assert_eq!(game.count::<PeaceOffer>(), 0);
+        assert_eq!(game.count::<War>(), 1);
+
+        offer_peace(&mut game, attacker, defender, war, vec![provinces[0]]);
+        // Answering twice in a frame only counts the first answer.
+        game.world_mut().write_message(answer(true));
+        game.world_mut().write_message(answer(false));
+        game.app.update();
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, attacker);
+        assert_eq!(game.count::<War>(), 0);
+    }
+
+    #[test]
+    fn ai_rejects_excessive_peace_demands() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(10, Some(defender));
+        let war = game.declare_war(attacker, defender);
+
+        offer_peace(&mut game, attacker, defender, war, provinces[..5].to_vec());
+
+        assert!(
+            provinces
+                .iter()
+                .all(|&p| game.get::<Owner>(p).unwrap().0 == defender)
+        );
+        assert_eq!(game.count::<War>(), 1);
+        assert_eq!(game.count::<PeaceOffer>(), 0);
+    }
+
+    #[test]
+    fn beaten_countries_become_vassals_and_release_their_captives() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let captive = game.spawn_country("Captive");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(4, Some(defender));
+        game.world_mut()
+            .entity_mut(provinces[3])
+            .insert(Conquered { from: captive });
+        let war = game.declare_war(attacker, defender);
+        let offer = |game: &mut TestGame| {
+            game.world_mut().write_message(PeaceOfferEvent {
+                from: attacker,
+                to: defender,
+                war_entity: war,
+                provinces_to_cede: Vec::new(),
+                demands: PeaceDemands {
+                    vassalize: true,
+                    release: Some(Release::Captive(captive)),
+                    break_alliances: false,
+                },
+                enforce_tolerance: false,
+            });
+            for _ in 0..3 {
+                game.app.update();
+            }
+        };
+
+        // Without war score to pay for them, the demands are refused.
+        offer(&mut game);
+        assert_eq!(game.count::<War>(), 1);
+        assert!(game.get::<Vassal>(defender).is_none());
+
+        for &province in &provinces {
+            game.world_mut()
+                .entity_mut(province)
+                .insert(Occupied { occupier: attacker });
+        }
+        offer(&mut game);
+        assert_eq!(game.count::<War>(), 0);
+        assert_eq!(game.get::<Vassal>(defender).unwrap().overlord, attacker);
+        assert_eq!(game.get::<Owner>(provinces[3]).unwrap().0, captive);
+        assert!(game.get::<Conquered>(provinces[3]).is_none());
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, defender);
+    }
+
+    #[test]
+    fn ai_explains_refused_peace_offers() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(10, Some(defender));
+        let war = game.declare_war(attacker, defender);
+
+        offer_peace(&mut game, attacker, defender, war, provinces[..5].to_vec());
+
+        let answers: Vec<PeaceAnsweredEvent> = game
+            .world_mut()
+            .resource_mut::<Messages<PeaceAnsweredEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(answers.len(), 1);
+        let acceptance = &answers[0].acceptance;
+        assert!(!acceptance.accepted());
+        assert_eq!(
+            acceptance.describe(),
+            "+30 Base willingness\n-50 Demands 5 of 10 provinces\n-100 War score of 0 where 50 is needed"
+        );
+    }
+
+    #[test]
+    fn ai_only_gives_up_land_the_enemy_won_war_score_for() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(12, Some(defender));
+        let war = game.declare_war(attacker, defender);
+
+        offer_peace(&mut game, attacker, defender, war, provinces[..3].to_vec());
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, defender);
+
+        // Six battles won make up for the quarter of its provinces it's asked to give up.
+        game.world_mut().get_mut::<WarScore>(war).unwrap().battles = 6;
+        offer_peace(&mut game, attacker, defender, war, provinces[..3].to_vec());
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, attacker);
+    }
+
+    #[test]
+    fn war_exhaustion_grows_at_war_and_costs_income() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        let goal = game.spawn_province("Goal", Hex::new(0, 0), Some(defender));
+        let war = game.declare_war(attacker, defender);
+        assert_eq!(game.get::<War>(war).unwrap().goal, Some(goal));
+
+        game.end_turns(3);
+        assert_eq!(game.get::<WarExhaustion>(attacker).unwrap().0, 3.0);
+        let modifiers = game.get::<CountryModifiers>(defender).unwrap();
+        assert!((modifiers.income() + 0.03).abs() < 1e-6);
+        // The defender holding the war goal wins the war while time passes.
+        assert_eq!(game.get::<WarScore>(war).unwrap().ticking, -3);
+        let score = game.get::<WarScore>(war).unwrap();
+        let war_data = game.get::<War>(war).unwrap();
+        assert_eq!(score.of(war_data, defender, 0.0, 0.0), 3);
+    }
+
+    #[test]
+    fn ai_gives_up_more_when_losing_the_war() {
+        let mut game = TestGame::new();
+        let attacker = game.spawn_country("Attacker");
+        let defender = game.spawn_country("Defender");
+        game.world_mut().resource_mut::<Player>().country = Some(attacker);
+        let provinces = game.spawn_provinces(10, Some(defender));
+        let war = game.declare_war(attacker, defender);
+        for &province in &provinces[..3] {
+            game.world_mut()
+                .entity_mut(province)
+                .insert(Occupied { occupier: attacker });
+        }
+        game.spawn_army(attacker, Hex::new(0, 0), 10 * REGIMENT_SIZE);
+
+        // A stubborn defender holds out even with its land overrun.
+        let handicap = Handicap {
+            personality: Personality::Stubborn,
+            ..default()
+        };
+        game.world_mut()
+            .resource_mut::<GameRules>()
+            .handicaps
+            .insert("Defender".to_string(), handicap);
+        offer_peace(&mut game, attacker, defender, war, provinces[..5].to_vec());
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, defender);
+
+        game.world_mut()
+            .resource_mut::<GameRules>()
+            .handicaps
+            .clear();
+        offer_peace(&mut game, attacker, defender, war, provinces[..5].to_vec());
+        assert_eq!(game.get::<Owner>(provinces[0]).unwrap().0, attacker);
+        assert_eq!(game.count::<War>(), 0);
+    }
+
+    const PEACE_OFFER_NATIONS: usize = 32;
+
+    /// 32 AI nations answer the peace offers made to them in the same frame.
+    #[test]
+    fn ai_nations_answer_every_peace_offer_at_once() {
+        let mut game = peace_offered_to_ai_nations(PEACE_OFFER_NATIONS);
+        assert_eq!(game.count::<PeaceOffer>(), PEACE_OFFER_NATIONS);
+
+        game.app.update();
+        game.app.update();
+        assert_eq!(game.count::<PeaceOffer>(), 0);
+    }
+}
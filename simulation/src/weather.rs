@@ -0,0 +1,220 @@
+﻿use crate::army::{Army, ArmyComposition, HexPos, apply_attrition};
+use crate::hex::Hex;
+use crate::map::Province;
+use crate::rules::GameRng;
+use crate::turns::{GameState, TurnSet};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Weather::default())
+            .add_systems(
+                OnEnter(GameState::Processing),
+                update_weather
+                    .after(crate::rules::reseed_game_rng)
+                    .before(crate::army::move_active_armies)
+                    .in_set(TurnSet::Resolve),
+            )
+            .add_systems(
+                OnEnter(GameState::Processing),
+                apply_weather_attrition
+                    .after(crate::army::move_active_armies)
+                    .before(crate::army::resolve_battles)
+                    .in_set(TurnSet::Resolve),
+            );
+    }
+}
+
+/// Chances of a new weather front forming, rolled this many times every turn.
+const NEW_FRONT_CHANCE: f64 = 0.4;
+const NEW_FRONT_ROLLS: u32 = 3;
+/// Most fronts over the map at the same time.
+const MAX_FRONTS: usize = 8;
+/// Elevation in meters above which fronts over land bring blizzards instead of rain.
+const BLIZZARD_ELEVATION: f32 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WeatherKind {
+    /// Forms over impassable lowlands such as the sea.
+    Storm,
+    /// Forms over high ground.
+    Blizzard,
+    HeavyRain,
+}
+
+impl WeatherKind {
+    /// Weather that forms over the province.
+    fn over(province: &Province) -> Self {
+        if province.elevation() >= BLIZZARD_ELEVATION {
+            WeatherKind::Blizzard
+        } else if !province.is_passable() {
+            WeatherKind::Storm
+        } else {
+            WeatherKind::HeavyRain
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherKind::Storm => "Storm",
+            WeatherKind::Blizzard => "Blizzard",
+            WeatherKind::HeavyRain => "Heavy rain",
+        }
+    }
+
+    /// Color of the weather in the weather map mode.
+    pub fn color(&self) -> Color {
+        match self {
+            WeatherKind::Storm => Color::srgb(0.25, 0.2, 0.5),
+            WeatherKind::Blizzard => Color::srgb(0.95, 0.95, 1.0),
+            WeatherKind::HeavyRain => Color::srgb(0.4, 0.48, 0.62),
+        }
+    }
+
+    /// Whether armies stop instead of marching out of or into the weather.
+    fn halts_movement(&self) -> bool {
+        matches!(self, WeatherKind::Storm | WeatherKind::Blizzard)
+    }
+
+    /// Share of an army's soldiers lost every turn spent in the weather.
+    fn attrition(&self) -> f32 {
+        match self {
+            WeatherKind::Storm => 0.05,
+            WeatherKind::Blizzard => 0.03,
+            WeatherKind::HeavyRain => 0.01,
+        }
+    }
+
+    /// Multiplier of the damage both sides deal in battles fought in the weather.
+    fn combat_modifier(&self) -> f32 {
+        match self {
+            WeatherKind::Storm => 0.6,
+            WeatherKind::Blizzard => 0.7,
+            WeatherKind::HeavyRain => 0.8,
+        }
+    }
+}
+
+/// Weather covering the hexes within `radius` of `center` for the next `turns_left` turns.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeatherFront {
+    pub kind: WeatherKind,
+    pub center: Hex,
+    pub radius: i32,
+    pub turns_left: u32,
+}
+
+/// Weather fronts currently over the map. Hexes not covered by any have clear skies.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Weather {
+    pub fronts: Vec<WeatherFront>,
+}
+
+impl Weather {
+    /// Weather at the hex, the oldest front wins where fronts overlap.
+    pub fn at(&self, hex: Hex) -> Option<WeatherKind> {
+        self.fronts
+            .iter()
+            .find(|front| front.center.distance(&hex) <= front.radius)
+            .map(|front| front.kind)
+    }
+
+    pub fn halts_movement(&self, hex: Hex) -> bool {
+        self.at(hex).is_some_and(|kind| kind.halts_movement())
+    }
+
+    pub fn combat_modifier(&self, hex: Hex) -> f32 {
+        self.at(hex).map_or(1.0, |kind| kind.combat_modifier())
+    }
+}
+
+/// Moves the weather on by a turn: expired fronts clear up and new ones form over random
+/// provinces. Draws from [`GameRng`], so every player of a game sees the same weather.
+pub fn update_weather(
+    mut weather: ResMut<Weather>,
+    mut rng: ResMut<GameRng>,
+    provinces: Query<&Province>,
+) {
+    weather.fronts.retain_mut(|front| {
+        front.turns_left -= 1;
+        front.turns_left > 0
+    });
+
+    // Query order isn't guaranteed to match between players, the map's order is.
+    let mut provinces: Vec<&Province> = provinces.iter().collect();
+    provinces.sort_by_key(|province| (province.get_hex().q(), province.get_hex().r()));
+    if provinces.is_empty() {
+        return;
+    }
+
+    for _ in 0..NEW_FRONT_ROLLS {
+        if weather.fronts.len() >= MAX_FRONTS || !rng.random_bool(NEW_FRONT_CHANCE) {
+            continue;
+        }
+        let province = provinces[rng.random_range(0..provinces.len())];
+        let front = WeatherFront {
+            kind: WeatherKind::over(province),
+            center: *province.get_hex(),
+            radius: rng.random_range(1..=2),
+            turns_left: rng.random_range(1..=3),
+        };
+        info!("{} forming at {:?}", front.kind.name(), front.center);
+        weather.fronts.push(front);
+    }
+}
+
+/// Armies caught in bad weather lose soldiers, spread over their unit types like battle
+/// casualties.
+pub fn apply_weather_attrition(
+    weather: Res<Weather>,
+    mut armies: Query<(&HexPos, &mut ArmyComposition), With<Army>>,
+) {
+    for (pos, composition) in &mut armies {
+        let Some(kind) = weather.at(pos.0) else {
+            continue;
+        };
+        apply_attrition(composition, kind.attrition());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestGame;
+    use crate::units::UnitType;
+
+    #[test]
+    fn blizzards_halt_armies_and_cost_soldiers() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        // Supplied from next door, but not reinforced on land of its own.
+        game.spawn_provinces(3, None);
+        game.spawn_province("Home", Hex::new(0, 1), Some(country));
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.world_mut()
+            .resource_mut::<Weather>()
+            .fronts
+            .push(WeatherFront {
+                kind: WeatherKind::Blizzard,
+                center: Hex::new(1, 0),
+                radius: 0,
+                turns_left: 3,
+            });
+
+        game.move_army(army, Hex::new(2, 0));
+        game.end_turn();
+        assert_eq!(game.get::<HexPos>(army).unwrap().0, Hex::new(0, 0));
+
+        game.world_mut().resource_mut::<Weather>().fronts[0].center = Hex::new(0, 0);
+        game.end_turn();
+        let soldiers = game
+            .get::<ArmyComposition>(army)
+            .unwrap()
+            .get(&UnitType::new("infantry"));
+        assert_eq!(soldiers, 970);
+    }
+}
@@ -0,0 +1,161 @@
+﻿use crate::army::{Army, ArmyHexMap, Battle};
+use crate::country::{Country, DisplayName};
+use crate::diplomacy::{CallToArms, ProvinceOffer};
+use crate::errors::GameError;
+use crate::map::{Province, ProvinceHexMap};
+use crate::menu::{MenuState, PauseMenuOpen};
+use crate::notifications::Notifications;
+use crate::player::Player;
+use crate::religion::Leagues;
+use crate::rules::{GameEnded, GameRules, PlayerDefeat};
+use crate::scoring::Scoreboard;
+use crate::scripting::PendingEvent;
+use crate::trade::Fleet;
+use crate::turns::{GameState, Turn};
+use crate::war::{PeaceOffer, War, Wars};
+use crate::weather::Weather;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        // Already added by the default plugins in the game, headless apps only bring the minimal
+        // ones.
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+        // Read and written all over the simulation, the presentation plugins show them to the
+        // player.
+        app.init_state::<MenuState>()
+            .add_message::<GameError>()
+            .init_resource::<Notifications>()
+            .insert_resource(PauseMenuOpen(false))
+            .init_schedule(GenerateWorld)
+            .init_schedule(ResetGame)
+            .add_message::<RegenerateWorldEvent>()
+            .add_systems(Startup, run_world_generation)
+            .add_systems(
+                OnTransition {
+                    exited: MenuState::InGame,
+                    entered: MenuState::MainMenu,
+                },
+                reset_world,
+            )
+            .add_systems(
+                Update,
+                regenerate_world.run_if(on_message::<RegenerateWorldEvent>),
+            );
+    }
+}
+
+/// Schedule holding every system that builds the game world from the map file: provinces,
+/// countries, ownership, starting armies and the player's country. Runs once at startup and
+/// again whenever the world is regenerated.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GenerateWorld;
+
+/// Schedule run whenever the game session is torn down, before a new world is generated or a save
+/// is loaded over it. The presentation plugins add systems to it dropping what they keep about the
+/// old session, like the selection and drafted orders.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResetGame;
+
+/// Message requesting the world to be rebuilt from scratch, e.g. after a different map was
+/// picked in the game setup screen.
+#[derive(Message)]
+pub struct RegenerateWorldEvent;
+
+fn run_world_generation(world: &mut World) {
+    world.run_schedule(GenerateWorld);
+}
+
+/// Despawns the generated world and builds it again from the current [`crate::rules::GameRules`].
+/// The player keeps their country if one with the same name exists on the new map, otherwise
+/// they are sent back to the country selection if they had picked one.
+fn regenerate_world(world: &mut World) {
+    let player_country_name = world
+        .resource::<Player>()
+        .country
+        .and_then(|country| world.get::<DisplayName>(country))
+        .map(|name| name.0.clone());
+
+    despawn_generated_world(world);
+    reset_session(world);
+    world.run_schedule(GenerateWorld);
+
+    let kept_country = player_country_name.and_then(|name| {
+        world
+            .query_filtered::<(Entity, &DisplayName), With<Country>>()
+            .iter(world)
+            .find(|(_, country_name)| country_name.0 == name)
+            .map(|(entity, _)| entity)
+    });
+    match kept_country {
+        Some(country) => world.resource_mut::<Player>().country = Some(country),
+        None if world.resource::<Player>().country.is_none() => {}
+        None => world
+            .resource_mut::<NextState<MenuState>>()
+            .set(MenuState::CountrySelection),
+    }
+}
+
+/// Tears down everything a game session created and generates a fresh world, so that starting
+/// a new game after returning to the main menu begins from a clean state.
+fn reset_world(world: &mut World) {
+    despawn_generated_world(world);
+    reset_session(world);
+    world.insert_resource(Player::default());
+    world.insert_resource(GameRules::default());
+
+    world.run_schedule(GenerateWorld);
+    info!("World reset for a new game");
+}
+
+/// Drops what a game session gathered on top of its world: wars, battles, offers and events
+/// waiting for an answer, and the turn, weather and scores. Runs before a new world is generated
+/// and before a save is loaded, so that nothing of the old session leaks into the next one. The
+/// presentation catches up in [`ResetGame`].
+pub fn reset_session(world: &mut World) {
+    despawn_all::<War>(world);
+    despawn_all::<PeaceOffer>(world);
+    despawn_all::<ProvinceOffer>(world);
+    despawn_all::<CallToArms>(world);
+    despawn_all::<Battle>(world);
+    despawn_all::<PendingEvent>(world);
+
+    world.insert_resource(Wars::default());
+    world.insert_resource(Turn::default());
+    world.insert_resource(Notifications::default());
+    world.insert_resource(GameEnded::default());
+    world.insert_resource(PlayerDefeat::default());
+    world.insert_resource(Scoreboard::default());
+    world.insert_resource(Weather::default());
+    world.insert_resource(Leagues::default());
+    world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::PlayerTurn);
+    world.run_schedule(ResetGame);
+}
+
+/// Despawns everything built by [`GenerateWorld`] and clears the lookups pointing at it.
+fn despawn_generated_world(world: &mut World) {
+    despawn_all::<Province>(world);
+    despawn_all::<Army>(world);
+    despawn_all::<Fleet>(world);
+    despawn_all::<Country>(world);
+    world.resource_mut::<ProvinceHexMap>().clear();
+    world.resource_mut::<ArmyHexMap>().clear();
+}
+
+fn despawn_all<T: Component>(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<T>>()
+        .iter(world)
+        .collect();
+    for entity in entities {
+        world.despawn(entity);
+    }
+}
@@ -0,0 +1,388 @@
+﻿use crate::egui_common::UiTheme;
+use crate::settings::{Settings, dragged_to};
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::{BattleEndedEvent, BattleSide};
+use eu6_simulation::country::Coffer;
+use eu6_simulation::map::{Owner, Province};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::player::Player;
+use eu6_simulation::religion::ToleranceEnforcedEvent;
+use eu6_simulation::rules::GameRules;
+use eu6_simulation::storage;
+use eu6_simulation::turns::{GameState, Turn, TurnSet};
+use eu6_simulation::war::WarWonEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_achievements())
+            .insert_resource(WarRecord::default())
+            .insert_resource(AchievementToasts::default())
+            .insert_resource(AchievementsWindowOpen(false))
+            .add_systems(Update, (record_battles, record_victories))
+            .add_systems(
+                OnEnter(GameState::PlayerTurn),
+                check_achievements
+                    .in_set(TurnSet::Begin)
+                    .run_if(in_state(MenuState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                (
+                    display_achievement_toasts,
+                    display_achievements_window.run_if(in_state(MenuState::MainMenu)),
+                ),
+            );
+    }
+}
+
+const ACHIEVEMENTS_FILE_PATH: &str = "achievements.json";
+
+/// How long an unlock toast stays on screen, in seconds.
+const TOAST_SECONDS: f32 = 5.0;
+/// Name the achievement gallery's layout is kept under in the settings.
+const ACHIEVEMENTS_WINDOW: &str = "achievements";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Achievement {
+    Conqueror,
+    FlawlessVictory,
+    DefenderOfTheFaith,
+    FullCoffers,
+    Survivor,
+}
+
+impl Achievement {
+    pub(crate) fn all() -> [Achievement; 5] {
+        [
+            Achievement::Conqueror,
+            Achievement::FlawlessVictory,
+            Achievement::DefenderOfTheFaith,
+            Achievement::FullCoffers,
+            Achievement::Survivor,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Achievement::Conqueror => "Conqueror",
+            Achievement::FlawlessVictory => "Flawless Victory",
+            Achievement::DefenderOfTheFaith => "Defender of the Faith",
+            Achievement::FullCoffers => "Full Coffers",
+            Achievement::Survivor => "Survivor",
+        }
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            Achievement::Conqueror => "Own 50 provinces",
+            Achievement::FlawlessVictory => "Win a war without losing a battle",
+            Achievement::DefenderOfTheFaith => "Enforce tolerance to end a league war",
+            Achievement::FullCoffers => "Have 1000 ducats in the treasury",
+            Achievement::Survivor => "Reach turn 100",
+        }
+    }
+
+    fn is_earned(&self, stats: &PlayerStats) -> bool {
+        match self {
+            Achievement::Conqueror => stats.provinces >= 50,
+            Achievement::FlawlessVictory => stats.record.flawless_victories > 0,
+            Achievement::DefenderOfTheFaith => stats.record.league_wars_won > 0,
+            Achievement::FullCoffers => stats.ducats >= 1000.0,
+            Achievement::Survivor => stats.turn >= 100,
+        }
+    }
+}
+
+/// Achievements unlocked in any game so far, kept in [`ACHIEVEMENTS_FILE_PATH`].
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub(crate) struct UnlockedAchievements {
+    unlocked: Vec<Achievement>,
+}
+
+impl UnlockedAchievements {
+    pub(crate) fn contains(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+}
+
+/// The player's record in the wars of the current game.
+#[derive(Resource, Default)]
+pub(crate) struct WarRecord {
+    /// Countries the player lost a battle against in the current war with them.
+    lost_against: HashSet<Entity>,
+    flawless_victories: u32,
+    league_wars_won: u32,
+}
+
+impl WarRecord {
+    fn lose_battle(&mut self, enemy: Entity) {
+        self.lost_against.insert(enemy);
+    }
+
+    /// Records a won war, flawless unless a battle against the enemy was lost.
+    fn win_war(&mut self, enemy: Entity) {
+        if !self.lost_against.remove(&enemy) {
+            self.flawless_victories += 1;
+        }
+    }
+}
+
+struct PlayerStats<'a> {
+    provinces: usize,
+    ducats: f32,
+    turn: u32,
+    record: &'a WarRecord,
+}
+
+/// Unlocks waiting to be shown, with the seconds they have been shown for.
+#[derive(Resource, Default)]
+pub(crate) struct AchievementToasts(VecDeque<(Achievement, f32)>);
+
+/// Resource telling whether the achievement gallery is shown.
+#[derive(Resource)]
+pub(crate) struct AchievementsWindowOpen(pub(crate) bool);
+
+fn load_achievements() -> UnlockedAchievements {
+    let Ok(content) = storage::read(ACHIEVEMENTS_FILE_PATH) else {
+        return UnlockedAchievements::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Failed to parse achievements file: {}", e);
+        UnlockedAchievements::default()
+    })
+}
+
+fn save_achievements(achievements: &UnlockedAchievements) {
+    match serde_json::to_string_pretty(achievements) {
+        Ok(json) => {
+            if let Err(e) = storage::write(ACHIEVEMENTS_FILE_PATH, &json) {
+                error!("Failed to write achievements file: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to serialize achievements: {}", e),
+    }
+}
+
+fn record_battles(
+    mut events: MessageReader<BattleEndedEvent>,
+    player: Res<Player>,
+    mut record: ResMut<WarRecord>,
+) {
+    let Some(country) = player.country else {
+        return;
+    };
+    for event in events.read() {
+        match event.winner {
+            Some(BattleSide::Attacker) if event.defender_country == country => {
+                record.lose_battle(event.attacker_country)
+            }
+            Some(BattleSide::Defender) if event.attacker_country == country => {
+                record.lose_battle(event.defender_country)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record_victories(
+    mut wars_won: MessageReader<WarWonEvent>,
+    mut tolerance: MessageReader<ToleranceEnforcedEvent>,
+    player: Res<Player>,
+    mut record: ResMut<WarRecord>,
+) {
+    let Some(country) = player.country else {
+        return;
+    };
+    for event in wars_won.read() {
+        if event.winner == country {
+            record.win_war(event.loser);
+        } else if event.loser == country {
+            record.lost_against.remove(&event.winner);
+        }
+    }
+    for event in tolerance.read() {
+        if event.winner == country {
+            record.league_wars_won += 1;
+        }
+    }
+}
+
+/// Unlocks the achievements the player earned this turn. Only ironman games count, as reloading
+/// would make them trivial.
+#[allow(clippy::too_many_arguments)]
+fn check_achievements(
+    rules: Res<GameRules>,
+    player: Res<Player>,
+    turn: Res<Turn>,
+    record: Res<WarRecord>,
+    provinces: Query<&Owner, With<Province>>,
+    coffers: Query<&Coffer>,
+    mut achievements: ResMut<UnlockedAchievements>,
+    mut toasts: ResMut<AchievementToasts>,
+) {
+    if !rules.ironman {
+        return;
+    }
+    let Some(country) = player.country else {
+        return;
+    };
+    let stats = PlayerStats {
+        provinces: provinces.iter().filter(|owner| owner.0 == country).count(),
+        ducats: coffers
+            .get(country)
+            .map_or(0.0, |coffer| coffer.get_ducats()),
+        turn: turn.current_turn(),
+        record: &record,
+    };
+
+    let earned: Vec<Achievement> = Achievement::all()
+        .into_iter()
+        .filter(|achievement| !achievements.contains(*achievement))
+        .filter(|achievement| achievement.is_earned(&stats))
+        .collect();
+    if earned.is_empty() {
+        return;
+    }
+    for achievement in earned {
+        info!("Achievement unlocked: {}", achievement.name());
+        achievements.unlocked.push(achievement);
+        toasts.0.push_back((achievement, 0.0));
+    }
+    save_achievements(&achievements);
+}
+
+fn display_achievement_toasts(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    time: Res<Time>,
+    mut toasts: ResMut<AchievementToasts>,
+) {
+    let Some((achievement, shown_for)) = toasts.0.front_mut() else {
+        return;
+    };
+    *shown_for += time.delta_secs();
+    if *shown_for > TOAST_SECONDS {
+        toasts.0.pop_front();
+        return;
+    }
+    let achievement = *achievement;
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    egui::Window::new("Achievement unlocked")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_BOTTOM, [0.0, -40.0])
+        .resizable(false)
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.label(RichText::new("🏆 Achievement unlocked").color(Color32::GOLD));
+            ui.label(RichText::new(achievement.name()).strong());
+            ui.label(achievement.description());
+        });
+}
+
+fn display_achievements_window(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut settings: ResMut<Settings>,
+    achievements: Res<UnlockedAchievements>,
+    mut window_open: ResMut<AchievementsWindowOpen>,
+) {
+    if !window_open.0 {
+        return;
+    }
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let window = egui::Window::new("Achievements")
+        .frame(theme.frame())
+        .title_bar(false)
+        .resizable(false)
+        .default_width(320.0)
+        .order(egui::Order::Foreground);
+    let shown = settings
+        .place_window(
+            window,
+            ACHIEVEMENTS_WINDOW,
+            ctx,
+            Align2::CENTER_CENTER,
+            [0.0, 0.0],
+        )
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("🏆 Achievements");
+                if theme.close_button(ui) {
+                    window_open.0 = false;
+                }
+            });
+            ui.label(
+                RichText::new("Only ironman games unlock achievements")
+                    .color(Color32::LIGHT_GRAY)
+                    .italics(),
+            );
+            ui.separator();
+
+            egui::Grid::new("achievements")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    for achievement in Achievement::all() {
+                        let (icon, color) = if achievements.contains(achievement) {
+                            ("🏆", Color32::GOLD)
+                        } else {
+                            ("🔒", Color32::DARK_GRAY)
+                        };
+                        ui.label(
+                            RichText::new(format!("{} {}", icon, achievement.name())).color(color),
+                        );
+                        ui.label(
+                            RichText::new(achievement.description()).color(Color32::LIGHT_GRAY),
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+
+    if let Some(pos) = dragged_to(&shown) {
+        settings.remember_window_pos(ACHIEVEMENTS_WINDOW, pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_wars_without_lost_battles_are_flawless() {
+        let mut world = World::new();
+        let (rival, neighbor) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let mut record = WarRecord::default();
+
+        record.lose_battle(rival);
+        record.win_war(rival);
+        assert_eq!(record.flawless_victories, 0);
+
+        // The next war against the rival starts with a clean slate.
+        record.win_war(rival);
+        record.win_war(neighbor);
+        assert_eq!(record.flawless_victories, 2);
+        assert!(Achievement::FlawlessVictory.is_earned(&PlayerStats {
+            provinces: 1,
+            ducats: 0.0,
+            turn: 1,
+            record: &record,
+        }));
+    }
+}
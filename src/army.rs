@@ -1,56 +1,85 @@
-﻿use crate::consts;
-use crate::country::{Country, MapColor};
-use crate::hex::Hex;
-use crate::map::{InteractionState, Owner, Province, ProvinceHexMap};
-use crate::player::Player;
+﻿use crate::egui_common::UiTheme;
+use crate::layout::{CameraControl, CameraFocusEvent};
+use crate::map::InteractionState;
+use crate::selection::Selection;
+use crate::settings::{Settings, WindowLayout, dragged_to};
 use bevy::ecs::error::Result;
+use bevy::ecs::system::SystemParam;
+use bevy::math::curve::{Curve, EaseFunction};
 use bevy::mesh::Mesh;
 use bevy::prelude::*;
 use bevy::sprite::Sprite;
 use bevy_egui::egui::{Align2, Color32, RichText};
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
-use pathfinding::prelude::bfs;
-use rand::Rng;
-use std::collections::{HashMap, VecDeque};
-
-pub struct ArmyPlugin;
-
-impl Plugin for ArmyPlugin {
+use bevy_egui::input::EguiWantsInput;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::{
+    ActivePath, ArmiesMergedEvent, Army, ArmyComposition, ArmyHexMap, Battle, Following, HexPos,
+    InBattle, MarchPreferences, Movement, Pathing, PendingMerge, turns_to_march,
+};
+use eu6_simulation::consts;
+use eu6_simulation::country::MapColor;
+use eu6_simulation::hex::Hex;
+use eu6_simulation::map::{Owner, Province, ProvinceHexMap};
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::PlayerCommands;
+use eu6_simulation::supply::OutOfSupply;
+use eu6_simulation::terrain::Terrain;
+use eu6_simulation::units::{UnitClass, UnitRegistry, UnitType};
+use eu6_simulation::weather::Weather;
+use std::collections::{BTreeMap, HashSet};
+
+pub struct ArmyUiPlugin;
+
+impl Plugin for ArmyUiPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ArmyHexMap::default())
-            .insert_resource(SelectedArmy::default())
-            .add_message::<MoveArmyEvent>()
+        app.insert_resource(SelectedArmy::default())
+            .insert_resource(ArmyReach::default())
+            .insert_resource(SplitDraft::default())
+            .insert_resource(ArmyListOpen(false))
+            .init_resource::<ArmySymbols>()
+            .add_systems(Startup, restore_army_list)
             .add_systems(
-                Startup,
-                spawn_initial_armies.after(crate::country::assign_province_ownership),
+                Update,
+                remember_army_list.run_if(resource_changed::<ArmyListOpen>),
             )
-            .add_systems(Update, army_movement_system)
-            .add_systems(Update, draw_path_gizmos) // Add this for visualization
-            .add_systems(Update, handle_army_interaction_changed)
-            .add_systems(Update, handle_army_composition_changed)
-            .add_systems(EguiPrimaryContextPass, display_army_panel)
-            .add_systems(EguiPrimaryContextPass, display_battle_panel)
-            .add_systems(Update, resolve_battles);
-    }
-}
-
-/// Resource mapping hex positions to army entities. One army per hex - stacking = auto-merge.
-#[derive(Resource, Default)]
-pub(crate) struct ArmyHexMap {
-    pub(crate) tiles: HashMap<HexPos, Entity>,
-}
-
-impl ArmyHexMap {
-    pub(crate) fn insert(&mut self, pos: HexPos, army: Entity) {
-        self.tiles.insert(pos, army);
-    }
-
-    pub(crate) fn remove(&mut self, pos: &HexPos) {
-        self.tiles.remove(pos);
-    }
-
-    pub(crate) fn get(&self, pos: &HexPos) -> Option<&Entity> {
-        self.tiles.get(pos)
+            .add_systems(
+                Update,
+                apply_march_preferences.run_if(resource_changed::<Settings>),
+            )
+            .add_systems(
+                Update,
+                (toggle_army_list, army_keyboard_orders)
+                    .run_if(in_state(eu6_simulation::menu::MenuState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    spawn_army_visuals,
+                    (
+                        sync_army_transforms,
+                        handle_army_interaction_changed,
+                        handle_army_composition_changed,
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                animate_army_movement.run_if(eu6_simulation::turns::simulation_running),
+            )
+            .add_systems(Update, (update_army_reach, select_merged_armies))
+            .add_systems(Update, draw_army_symbols)
+            .add_systems(Update, draw_path_gizmos)
+            .add_systems(Update, draw_reach_outline.after(update_army_reach))
+            .add_systems(
+                EguiPrimaryContextPass,
+                (display_army_panel, display_battle_panel),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                (display_army_list, display_pending_merges)
+                    .run_if(in_state(eu6_simulation::menu::MenuState::InGame)),
+            );
     }
 }
 
@@ -73,605 +102,200 @@ impl SelectedArmy {
     }
 }
 
-#[derive(Component)]
-pub(crate) struct ActivePath {
-    pub(crate) path: VecDeque<Hex>,
+/// Hexes the selected army can march to this turn, tinted on the map, and the hexes it can attack
+/// this turn, outlined around them. Empty while no army is selected.
+#[derive(Resource, Default, PartialEq)]
+pub(crate) struct ArmyReach {
+    pub(crate) reachable: HashSet<Hex>,
+    pub(crate) attack_range: HashSet<Hex>,
 }
 
-#[derive(Component)]
-pub(crate) struct Army {}
-#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug)]
-pub(crate) struct HexPos(pub(crate) Hex);
-
-impl HexPos {
-    pub(crate) fn new(hex: Hex) -> Self {
-        Self(hex)
-    }
-}
-#[derive(Component, Copy, Clone)]
-pub(crate) struct ArmyComposition {
-    pub(crate) infantry: u32,
-    pub(crate) cavalry: u32,
-    pub(crate) artillery: u32,
-}
+/// Resource telling whether the list of the player's armies is currently shown.
+#[derive(Resource)]
+pub(crate) struct ArmyListOpen(pub(crate) bool);
 
-#[derive(PartialEq, Copy, Clone)]
-pub(crate) enum UnitType {
-    Infantry,
-    Cavalry,
-    Artillery,
-}
+/// Name the army list's layout is kept under in the settings.
+const ARMY_LIST_WINDOW: &str = "army_list";
 
-impl UnitType {
-    pub(crate) fn cost(&self) -> f32 {
-        match self {
-            UnitType::Infantry => 10.0,
-            UnitType::Cavalry => 25.0,
-            UnitType::Artillery => 30.0,
-        }
-    }
-
-    pub(crate) fn name(&self) -> &'static str {
-        match self {
-            UnitType::Infantry => "Infantry",
-            UnitType::Cavalry => "Cavalry",
-            UnitType::Artillery => "Artillery",
-        }
-    }
-
-    pub(crate) fn all() -> [UnitType; 3] {
-        [UnitType::Infantry, UnitType::Cavalry, UnitType::Artillery]
-    }
-}
-
-pub(crate) const REGIMENT_SIZE: u32 = 1000;
-
-impl ArmyComposition {
-    pub(crate) fn total_size(&self) -> u32 {
-        self.infantry + self.cavalry + self.artillery
-    }
-
-    pub(crate) fn add(&mut self, other: &ArmyComposition) {
-        self.infantry += other.infantry;
-        self.cavalry += other.cavalry;
-        self.artillery += other.artillery;
-    }
-
-    pub(crate) fn add_unit(&mut self, unit: UnitType) {
-        match unit {
-            UnitType::Infantry => self.infantry += REGIMENT_SIZE,
-            UnitType::Cavalry => self.cavalry += REGIMENT_SIZE,
-            UnitType::Artillery => self.artillery += REGIMENT_SIZE,
-        }
-    }
+/// Soldiers of each unit type the player picked to detach from the selected army, see
+/// [`PlayerCommand::SplitArmy`](eu6_simulation::player_command::PlayerCommand::SplitArmy).
+#[derive(Resource, Default)]
+pub(crate) struct SplitDraft {
+    army: Option<Entity>,
+    detachment: BTreeMap<UnitType, u32>,
 }
 
-pub(crate) const MIN_DAMAGE: u32 = 5;
-
 #[derive(Component)]
 pub(crate) struct ArmyLabel(pub(crate) String);
 
 #[derive(Component)]
 pub(crate) struct SelectedRing {}
 
+/// Symbol of a unit class on an army's banner, shown when the class has the most soldiers in the
+/// army.
 #[derive(Component)]
-pub(crate) struct InBattle {
-    pub(crate) battle_entity: Entity,
-}
-
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub(crate) enum BattleSide {
-    Attacker,
-    Defender,
-}
-
-#[derive(Component)]
-pub(crate) struct Battle {
-    /// All armies on the attacking side
-    pub(crate) attackers: Vec<Entity>,
-    /// All armies on the defending side
-    pub(crate) defenders: Vec<Entity>,
-    /// Country that initiated the attack
-    pub(crate) attacker_country: Entity,
-    /// Country that is defending
-    pub(crate) defender_country: Entity,
-    pub(crate) location: Hex,
-    pub(crate) round: u32,
-    pub(crate) last_damage_attacker: u32,
-    pub(crate) last_damage_defender: u32,
-}
-
-#[derive(Bundle)]
-pub(crate) struct ArmyBundle {
-    pub(crate) marker: Army,
-    pub(crate) pos: HexPos,
-    pub(crate) owner: Owner,
-    pub(crate) composition: ArmyComposition,
-    pub(crate) interaction_state: InteractionState,
-    pub(crate) transform: Transform,
-    pub(crate) visibility: Visibility,
-    pub(crate) sprite: Sprite,
-    pub(crate) pickable: Pickable,
-}
+pub(crate) struct ArmyIcon(UnitClass);
 
-#[derive(Message)]
-pub(crate) struct MoveArmyEvent {
-    pub(crate) army: Entity,
-    pub(crate) to: HexPos,
+/// Bar under an army's banner showing how many of its soldiers are left, out of the most it ever
+/// had.
+#[derive(Component, Default)]
+pub(crate) struct StrengthBar {
+    peak: u32,
 }
 
-impl MoveArmyEvent {
-    pub(crate) fn new(army: Entity, to: HexPos) -> Self {
-        Self { army, to }
-    }
-}
-
-pub(crate) fn army_movement_system(
-    mut commands: Commands,
-    mut move_events: MessageReader<MoveArmyEvent>,
-    army_hex_map: ResMut<ArmyHexMap>,
-    province_map: Res<ProvinceHexMap>,
-    provinces: Query<&Province>,
-) -> Result {
-    for event in move_events.read() {
-        let from_pos = army_hex_map
-            .tiles
-            .iter()
-            .find_map(|(pos, &army)| if army == event.army { Some(*pos) } else { None });
-
-        let from_pos = match from_pos {
-            Some(pos) => pos,
-            None => {
-                warn!(
-                    "Army movement event for unknown army entity: {:?}",
-                    event.army
-                );
-                continue;
-            }
-        };
+const BANNER_SIZE: Vec2 = Vec2::new(40.0, 30.0);
 
-        if from_pos == event.to {
-            continue;
-        }
+const STRENGTH_BAR_HEIGHT: f32 = 5.0;
 
-        // Calculate path
-        let path = bfs(
-            &from_pos.0,
-            |p| {
-                let neighbors: Vec<Hex> = p
-                    .neighbors()
-                    .into_iter()
-                    .filter(|n| {
-                        if let Some(&entity) = province_map.get_entity(n)
-                            && let Ok(province) = provinces.get(entity)
-                        {
-                            return province.is_passable();
-                        }
-                        false
-                    })
-                    .collect();
-                neighbors
-            },
-            |p| *p == event.to.0,
-        );
+/// Time it takes an army to march from one hex to the next on screen, in seconds.
+const ARMY_MOVE_ANIMATION_DURATION: f32 = 0.35;
 
-        if let Some(path) = path {
-            let mut deck = VecDeque::from(path);
-            deck.pop_front(); // Remove current position
-            if !deck.is_empty() {
-                commands
-                    .entity(event.army)
-                    .insert(ActivePath { path: deck.clone() });
-                info!(
-                    "Army {:?} started moving to {:?}, path length: {}",
-                    event.army,
-                    event.to,
-                    deck.len()
-                );
-            }
-        } else {
-            warn!(
-                "No path found for army {:?} from {:?} to {:?}",
-                event.army, from_pos, event.to
-            );
-        }
-    }
-    Ok(())
+/// Component animating an army's [`Transform`] towards its new hex. Only cosmetic, the army's
+/// [`HexPos`] is updated as soon as it moves.
+#[derive(Component)]
+pub(crate) struct MovementAnimation {
+    from: Vec3,
+    to: Vec3,
+    elapsed: f32,
 }
 
-pub(crate) fn move_active_armies(
+/// Draws newly spawned armies as a banner in their country's color, with their size, the symbol of
+/// their main unit class, a strength bar and the selection ring.
+fn spawn_army_visuals(
     mut commands: Commands,
-    mut army_hex_map: ResMut<ArmyHexMap>,
-    mut armies_query: Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    mut selected_army: ResMut<SelectedArmy>,
-    war_relations: Query<&crate::war::WarRelations>,
-    mut battles: Query<&mut Battle>,
-    _province_map: Res<ProvinceHexMap>,
+    armies: Query<(Entity, &HexPos, &Owner, &ArmyComposition), Added<Army>>,
+    colors: Query<&MapColor>,
 ) {
-    let movers: Vec<Entity> = armies_query
-        .iter()
-        .filter_map(|(e, _, _, _, _, path, _)| path.is_some().then_some(e))
-        .collect();
-
-    for entity in movers {
-        process_army_movement(
-            &mut commands,
-            &mut army_hex_map,
-            &mut armies_query,
-            &mut selected_army,
-            &war_relations,
-            &mut battles,
-            entity,
-        );
-    }
-}
-
-fn process_army_movement(
-    commands: &mut Commands,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    selected_army: &mut ResMut<SelectedArmy>,
-    war_relations: &Query<&crate::war::WarRelations>,
-    battles: &mut Query<&mut Battle>,
-    entity: Entity,
-) {
-    let Some((next_hex, old_pos)) = get_next_move(armies_query, commands, entity) else {
-        return;
-    };
-    let next_pos = HexPos(next_hex);
-
-    if try_join_battle(
-        commands,
-        army_hex_map,
-        armies_query,
-        war_relations,
-        battles,
-        entity,
-        next_hex,
-        old_pos,
-    ) {
-        return;
-    }
-
-    if try_handle_collision(
-        commands,
-        army_hex_map,
-        armies_query,
-        selected_army,
-        war_relations,
-        entity,
-        next_hex,
-        next_pos,
-        old_pos,
-    ) {
-        return;
-    }
-
-    execute_movement(
-        commands,
-        army_hex_map,
-        armies_query,
-        entity,
-        next_hex,
-        next_pos,
-        old_pos,
-    );
-}
-
-fn get_next_move(
-    armies_query: &Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    commands: &mut Commands,
-    entity: Entity,
-) -> Option<(Hex, HexPos)> {
-    if let Ok((_, _, _, _, pos, Some(active_path), _)) = armies_query.get(entity) {
-        if let Some(h) = active_path.path.front() {
-            Some((*h, *pos))
-        } else {
-            commands.entity(entity).remove::<ActivePath>();
-            None
-        }
-    } else {
-        None
-    }
-}
-
-fn try_join_battle(
-    commands: &mut Commands,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    war_relations: &Query<&crate::war::WarRelations>,
-    battles: &mut Query<&mut Battle>,
-    entity: Entity,
-    next_hex: Hex,
-    old_pos: HexPos,
-) -> bool {
-    let battle_at_location = find_battle_at_location(armies_query, battles, next_hex);
-
-    let Some(battle_entity) = battle_at_location else {
-        return false;
-    };
-
-    let Ok((_, _, owner, _, _, _, _)) = armies_query.get(entity) else {
-        return false;
-    };
-    let Ok(mut battle) = battles.get_mut(battle_entity) else {
-        return false;
-    };
-
-    let owner_entity = owner.0;
-    let side = determine_battle_side(owner_entity, &battle, war_relations);
-
-    let Some(side) = side else {
-        return false;
-    };
-
-    info!(
-        "Army {:?} joins battle at {:?} on {:?} side",
-        entity, next_hex, side
-    );
+    for (army, pos, owner, composition) in &armies {
+        let owner_color = colors.get(owner.0).map_or(Color::WHITE, |color| color.0);
+        let size = composition.total_size().to_string();
+        commands
+            .entity(army)
+            .insert((
+                InteractionState::None,
+                Transform::from_translation(pos.0.axial_to_world(consts::HEX_SIZE).extend(5.0)),
+                Visibility::Visible,
+                Sprite {
+                    color: owner_color.darker(0.2),
+                    custom_size: Some(BANNER_SIZE),
+                    ..default()
+                },
+                // Armies are picked by their hex through the province below, see `ArmyPicker`.
+                Pickable::IGNORE,
+            ))
+            .with_children(|parent| {
+                // Label for displaying army size.
+                parent.spawn((
+                    Text2d::new(size.clone()),
+                    TextFont {
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextLayout::new_with_justify(Justify::Center),
+                    Transform::from_xyz(0.0, BANNER_SIZE.y / 2.0 + 12.0, 0.1),
+                    ArmyLabel(size),
+                    Visibility::Visible,
+                ));
 
-    match side {
-        BattleSide::Attacker => battle.attackers.push(entity),
-        BattleSide::Defender => battle.defenders.push(entity),
-    }
+                // Icons start hidden, `handle_army_composition_changed` shows the dominant class.
+                for class in [
+                    UnitClass::Infantry,
+                    UnitClass::Cavalry,
+                    UnitClass::Artillery,
+                ] {
+                    parent.spawn((
+                        ArmyIcon(class),
+                        Transform::from_xyz(0.0, 0.0, 0.1),
+                        Visibility::Hidden,
+                    ));
+                }
 
-    commands.entity(entity).remove::<ActivePath>();
-    commands.entity(entity).insert(InBattle { battle_entity });
-    army_hex_map.remove(&old_pos);
+                let bar_y = -(BANNER_SIZE.y + STRENGTH_BAR_HEIGHT) / 2.0 - 1.0;
+                parent.spawn((
+                    Sprite {
+                        color: Color::srgba(0.0, 0.0, 0.0, 0.7),
+                        custom_size: Some(Vec2::new(BANNER_SIZE.x, STRENGTH_BAR_HEIGHT)),
+                        ..default()
+                    },
+                    Transform::from_xyz(0.0, bar_y, 0.1),
+                    Pickable::IGNORE,
+                ));
+                parent.spawn((
+                    Sprite::default(),
+                    Transform::from_xyz(0.0, bar_y, 0.2),
+                    StrengthBar::default(),
+                    Pickable::IGNORE,
+                ));
 
-    if let Ok((_, mut transform, _, _, mut pos, _, _)) = armies_query.get_mut(entity) {
-        *pos = HexPos(next_hex);
-        transform.translation = next_hex.axial_to_world(consts::HEX_SIZE).extend(5.0);
+                parent.spawn((
+                    Transform::from_xyz(0.0, 0.0, -0.1),
+                    Visibility::Hidden,
+                    SelectedRing {},
+                    Pickable::IGNORE,
+                ));
+            });
     }
-    true
 }
 
-fn find_battle_at_location(
-    armies_query: &Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    battles: &Query<&mut Battle>,
-    hex: Hex,
-) -> Option<Entity> {
-    for (_, _, _, _, _, _, maybe_in_battle) in armies_query.iter() {
-        if let Some(in_battle) = maybe_in_battle {
-            if let Ok(battle) = battles.get(in_battle.battle_entity) {
-                if battle.location == hex {
-                    return Some(in_battle.battle_entity);
-                }
-            }
+/// Keeps armies selected when they merge into another army, by selecting the army they merged
+/// into.
+fn select_merged_armies(mut merges: MessageReader<ArmiesMergedEvent>, mut selection: Selection) {
+    for merge in merges.read() {
+        if selection.army() == Some(merge.source) {
+            selection.select_army(merge.target);
         }
     }
-    None
 }
 
-fn determine_battle_side(
-    owner: Entity,
-    battle: &Battle,
-    war_relations: &Query<&crate::war::WarRelations>,
-) -> Option<BattleSide> {
-    if owner == battle.attacker_country {
-        Some(BattleSide::Attacker)
-    } else if owner == battle.defender_country {
-        Some(BattleSide::Defender)
-    } else if crate::war::are_at_war(owner, battle.defender_country, war_relations) {
-        Some(BattleSide::Attacker)
-    } else if crate::war::are_at_war(owner, battle.attacker_country, war_relations) {
-        Some(BattleSide::Defender)
-    } else {
-        None
-    }
-}
-
-fn try_handle_collision(
-    commands: &mut Commands,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    selected_army: &mut ResMut<SelectedArmy>,
-    war_relations: &Query<&crate::war::WarRelations>,
-    entity: Entity,
-    next_hex: Hex,
-    next_pos: HexPos,
-    old_pos: HexPos,
-) -> bool {
-    let Some(&occupant_entity) = army_hex_map.get(&next_pos) else {
-        return false;
-    };
-
-    if armies_query.get(occupant_entity).is_err() {
-        army_hex_map.remove(&next_pos);
-        return false;
-    }
-
-    let Ok(
-        [
-            (e1, _, owner1, comp1, _, _, _),
-            (e2, _, owner2, mut comp2, _, _, _),
-        ],
-    ) = armies_query.get_many_mut([entity, occupant_entity])
-    else {
-        return true;
-    };
-
-    if owner1.0 == owner2.0 {
-        merge_armies(
-            commands,
-            army_hex_map,
-            selected_army,
-            e1,
-            e2,
-            &comp1,
-            &mut comp2,
-            old_pos,
-        );
-        return true;
-    }
-
-    if !crate::war::are_at_war(owner1.0, owner2.0, war_relations) {
-        info!(
-            "Cannot attack: {:?} and {:?} are not at war",
-            owner1.0, owner2.0
-        );
-        commands.entity(e1).remove::<ActivePath>();
-        return true;
-    }
-
-    start_battle(commands, e1, e2, owner1.0, owner2.0, next_hex);
-    true
+/// Passes the player's preferences on marching armies from the settings to the simulation.
+fn apply_march_preferences(settings: Res<Settings>, mut preferences: ResMut<MarchPreferences>) {
+    preferences.march_around_enemies = settings.march_around_enemies;
+    preferences.confirm_army_merges = settings.confirm_army_merges;
 }
 
-fn merge_armies(
-    commands: &mut Commands,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    selected_army: &mut ResMut<SelectedArmy>,
-    source: Entity,
-    target: Entity,
-    source_comp: &ArmyComposition,
-    target_comp: &mut ArmyComposition,
-    old_pos: HexPos,
+/// Places armies on the map according to their [`HexPos`]. Newly spawned armies are placed right
+/// away, moving ones get a [`MovementAnimation`] unless animations are disabled in the settings.
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_army_transforms(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut armies: Query<(Entity, Ref<HexPos>, &mut Transform), (With<Army>, Changed<HexPos>)>,
 ) {
-    info!("Merging army {:?} into {:?}", source, target);
-    target_comp.add(source_comp);
-    army_hex_map.remove(&old_pos);
-    commands.entity(source).despawn();
-
-    if selected_army.get() == Some(source) {
-        selected_army.set(target);
-        commands.entity(target).insert(InteractionState::Selected);
+    for (army, pos, mut transform) in &mut armies {
+        let target = pos.0.axial_to_world(consts::HEX_SIZE).extend(5.0);
+        if pos.is_added() || !settings.animate_army_movement {
+            transform.translation = target;
+            commands.entity(army).remove::<MovementAnimation>();
+        } else {
+            commands.entity(army).insert(MovementAnimation {
+                from: transform.translation,
+                to: target,
+                elapsed: 0.0,
+            });
+        }
     }
 }
 
-fn start_battle(
-    commands: &mut Commands,
-    attacker: Entity,
-    defender: Entity,
-    attacker_country: Entity,
-    defender_country: Entity,
-    location: Hex,
-) {
-    info!(
-        "Battle started between {:?} and {:?} at {:?}",
-        attacker, defender, location
-    );
-    commands.entity(attacker).remove::<ActivePath>();
-
-    let battle_id = commands
-        .spawn(Battle {
-            attackers: vec![attacker],
-            defenders: vec![defender],
-            attacker_country,
-            defender_country,
-            location,
-            round: 0,
-            last_damage_attacker: 0,
-            last_damage_defender: 0,
-        })
-        .id();
-
-    commands.entity(attacker).insert(InBattle {
-        battle_entity: battle_id,
-    });
-    commands.entity(defender).insert(InBattle {
-        battle_entity: battle_id,
-    });
-}
-
-fn execute_movement(
-    commands: &mut Commands,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<
-        (
-            Entity,
-            &mut Transform,
-            &Owner,
-            &mut ArmyComposition,
-            &mut HexPos,
-            Option<&mut ActivePath>,
-            Option<&InBattle>,
-        ),
-        With<Army>,
-    >,
-    entity: Entity,
-    next_hex: Hex,
-    next_pos: HexPos,
-    old_pos: HexPos,
+/// Advances army movement animations on the fixed timestep, so they run at the same speed
+/// regardless of the frame rate.
+fn animate_army_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut armies: Query<(Entity, &mut MovementAnimation, &mut Transform)>,
 ) {
-    if let Ok((_, mut transform, _, _, mut pos, Some(mut active_path), _)) =
-        armies_query.get_mut(entity)
-    {
-        active_path.path.pop_front();
-        army_hex_map.remove(&old_pos);
-        army_hex_map.insert(next_pos, entity);
-        *pos = next_pos;
-        transform.translation = next_hex.axial_to_world(consts::HEX_SIZE).extend(5.0);
-
-        if active_path.path.is_empty() {
-            commands.entity(entity).remove::<ActivePath>();
-            info!("Army {:?} arrived at destination {:?}", entity, next_pos);
+    for (army, mut animation, mut transform) in &mut armies {
+        animation.elapsed += time.delta_secs();
+        let t = if settings.animate_army_movement {
+            (animation.elapsed / ARMY_MOVE_ANIMATION_DURATION).min(1.0)
+        } else {
+            1.0
+        };
+        transform.translation = animation
+            .from
+            .lerp(animation.to, EaseFunction::SineInOut.sample_clamped(t));
+        if t >= 1.0 {
+            commands.entity(army).remove::<MovementAnimation>();
         }
     }
 }
@@ -710,143 +334,220 @@ fn draw_path_gizmos(
     }
 }
 
-pub(crate) fn spawn_army(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    position: Hex,
-    owner: Entity,
-    owner_color: Color,
-    composition: ArmyComposition,
-) -> Entity {
-    let ring_mesh = meshes.add(Circle::new(25.0));
-    let ring_material = materials.add(Color::srgba(1.0, 1.0, 0.0, 0.4));
-
-    commands
-        .spawn((ArmyBundle {
-            marker: Army {},
-            pos: HexPos(position),
-            owner: Owner(owner),
-            composition,
-            interaction_state: InteractionState::None,
-            transform: Transform::from_translation(
-                position.axial_to_world(consts::HEX_SIZE).extend(5.0),
-            ),
-            visibility: Visibility::Visible,
-            sprite: Sprite {
-                color: owner_color.darker(0.2),
-                custom_size: Some(Vec2::new(40.0, 30.0)),
-                ..default()
-            },
-            pickable: Pickable::default(),
-        },))
-        .with_children(|parent| {
-            // Label for displaying army size.
-            parent.spawn((
-                Text2d::new(composition.total_size().to_string()),
-                TextFont {
-                    font_size: 18.0,
-                    ..default()
-                },
-                TextLayout::new_with_justify(Justify::Center),
-                ArmyLabel(composition.total_size().to_string()),
-                Visibility::Visible,
-            ));
-
-            parent.spawn((
-                Mesh2d(ring_mesh),
-                MeshMaterial2d(ring_material),
-                Transform::from_xyz(0.0, 0.0, -0.1),
-                Visibility::Hidden,
-                SelectedRing {},
-            ));
-        })
-        .observe(handle_army_click)
-        .id()
-}
-
-pub(crate) fn spawn_initial_armies(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut army_hex_map: ResMut<ArmyHexMap>,
-    countries: Query<(Entity, &MapColor), With<Country>>,
-    provinces: Query<(&Owner, &Province)>,
+/// Finds the hexes the selected army can reach with the movement points it has this turn. The
+/// march stops at other armies, so hexes behind them are out of reach, and hexes held by enemies
+/// can be attacked but not marched through.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn update_army_reach(
+    selected_army: Res<SelectedArmy>,
+    armies: Query<
+        (
+            &HexPos,
+            &Owner,
+            &ArmyComposition,
+            Option<&Movement>,
+            Has<InBattle>,
+        ),
+        With<Army>,
+    >,
+    owners: Query<&Owner, With<Army>>,
+    army_map: Res<ArmyHexMap>,
+    war_relations: Query<&eu6_simulation::war::WarRelations>,
+    units: Res<UnitRegistry>,
+    weather: Res<Weather>,
+    pathing: Pathing,
+    mut army_reach: ResMut<ArmyReach>,
 ) {
-    let mut country_provinces: HashMap<Entity, Vec<Hex>> = HashMap::new();
+    let mut reach = ArmyReach::default();
+    if let Some(army) = selected_army.get()
+        && let Ok((pos, owner, composition, movement, in_battle)) = armies.get(army)
+        && !in_battle
+    {
+        let army_map = &*army_map;
+        let others = move |hex: &Hex| {
+            army_map
+                .armies_at(&HexPos(*hex))
+                .iter()
+                .copied()
+                .filter(move |&other| other != army)
+        };
+        let hostile = |hex: &Hex| {
+            others(hex).any(|other| {
+                owners.get(other).is_ok_and(|other_owner| {
+                    war_relations
+                        .get(owner.0)
+                        .is_ok_and(|relations| relations.is_at_war_with(other_owner.0))
+                })
+            })
+        };
+        let points = units.movement(composition) + movement.map_or(0, |m| m.points);
+        let hexes = pathing.reach(
+            pos.0,
+            owner.0,
+            points,
+            |hex| !weather.halts_movement(*hex),
+            |hex| others(hex).next().is_some(),
+        );
+        reach.reachable = hexes
+            .keys()
+            .filter(|&&hex| hex != pos.0 && !hostile(&hex))
+            .copied()
+            .collect();
+        reach.attack_range = hexes.into_keys().collect();
+    }
+    if *army_reach != reach {
+        *army_reach = reach;
+    }
+}
 
-    for (owner, province) in provinces.iter() {
-        country_provinces
-            .entry(owner.0)
-            .or_default()
-            .push(*province.get_hex());
+/// Outlines the hexes the selected army can attack this turn.
+fn draw_reach_outline(mut gizmos: Gizmos, army_reach: Res<ArmyReach>) {
+    let color = Color::srgb(1.0, 0.35, 0.2);
+    for hex in &army_reach.attack_range {
+        for neighbor in hex.neighbors() {
+            if !army_reach.attack_range.contains(&neighbor) {
+                let (start, end) = hex.shared_edge(&neighbor, consts::HEX_SIZE);
+                gizmos.line_2d(start, end, color);
+            }
+        }
     }
+}
 
-    for (country, map_color) in countries.iter() {
-        if let Some(province_hexes) = country_provinces.get(&country)
-            && let Some(&start_hex) = province_hexes.first()
-        {
-            let army = spawn_army(
-                &mut commands,
-                &mut meshes,
-                &mut materials,
-                start_hex,
-                country,
-                map_color.0,
-                ArmyComposition {
-                    infantry: 10 * REGIMENT_SIZE,
-                    cavalry: 2 * REGIMENT_SIZE,
-                    artillery: REGIMENT_SIZE,
-                },
-            );
-            army_hex_map.insert(HexPos(start_hex), army);
+/// Meshes and materials of the unit class symbols and the selection ring, shared by every army.
+#[derive(Resource)]
+struct ArmySymbols {
+    ring_mesh: Handle<Mesh>,
+    ring_material: Handle<ColorMaterial>,
+    stroke_mesh: Handle<Mesh>,
+    dot_mesh: Handle<Mesh>,
+    icon_material: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ArmySymbols {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let ring_mesh = meshes.add(Circle::new(25.0));
+        let stroke_mesh = meshes.add(Rectangle::new(BANNER_SIZE.length() - 6.0, 3.0));
+        let dot_mesh = meshes.add(Circle::new(6.0));
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        Self {
+            ring_mesh,
+            ring_material: materials.add(Color::srgba(1.0, 1.0, 0.0, 0.4)),
+            stroke_mesh,
+            dot_mesh,
+            icon_material: materials.add(Color::srgba(1.0, 1.0, 1.0, 0.9)),
         }
     }
 }
 
-fn handle_army_click(
-    click: On<Pointer<Click>>,
-    mut selected: ResMut<SelectedArmy>,
+/// Draws the unit class symbols and the selection ring of newly spawned armies. Unit classes are
+/// drawn with map symbols: a cross for infantry, a slash for cavalry and a dot for artillery.
+fn draw_army_symbols(
     mut commands: Commands,
-    player: Res<Player>,
-    owners: Query<&Owner>,
+    symbols: Res<ArmySymbols>,
+    icons: Query<(Entity, &ArmyIcon), Added<ArmyIcon>>,
+    rings: Query<Entity, Added<SelectedRing>>,
 ) {
-    if click.button != PointerButton::Primary {
-        return;
+    let diagonal = BANNER_SIZE.y.atan2(BANNER_SIZE.x);
+    for (entity, icon) in &icons {
+        let strokes: &[f32] = match icon.0 {
+            UnitClass::Infantry => &[diagonal, -diagonal],
+            UnitClass::Cavalry => &[diagonal],
+            UnitClass::Artillery => &[],
+        };
+        commands.entity(entity).with_children(|icon_entity| {
+            for &angle in strokes {
+                icon_entity.spawn((
+                    Mesh2d(symbols.stroke_mesh.clone()),
+                    MeshMaterial2d(symbols.icon_material.clone()),
+                    Transform::from_rotation(Quat::from_rotation_z(angle)),
+                    Pickable::IGNORE,
+                ));
+            }
+            if icon.0 == UnitClass::Artillery {
+                icon_entity.spawn((
+                    Mesh2d(symbols.dot_mesh.clone()),
+                    MeshMaterial2d(symbols.icon_material.clone()),
+                    Pickable::IGNORE,
+                ));
+            }
+        });
     }
+    for ring in &rings {
+        commands.entity(ring).insert((
+            Mesh2d(symbols.ring_mesh.clone()),
+            MeshMaterial2d(symbols.ring_material.clone()),
+        ));
+    }
+}
 
-    info!("Army clicked: {:?}", click.entity);
-    let clicked_entity = click.entity;
+/// What a click on a hex does to the army selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArmyPick {
+    Select(Entity),
+    Deselect,
+}
 
-    if let Ok(owner) = owners.get(clicked_entity)
-        && Some(owner.0) != player.country
-    {
-        info!("Cannot select army of another country");
-        return;
-    }
+/// Picks armies by the hex they stand on. Army banners overlap the neighboring hexes at some zoom
+/// levels and would steal their clicks, so they aren't pickable and province clicks are routed
+/// through here instead. The player's own armies take priority over the province below them,
+/// unless Alt is held to click through to the province. Ordering an army onto another with Ctrl
+/// held merges them without asking.
+#[derive(SystemParam)]
+pub(crate) struct ArmyPicker<'w, 's> {
+    player: Res<'w, Player>,
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    army_map: Res<'w, ArmyHexMap>,
+    owners: Query<'w, 's, &'static Owner, With<Army>>,
+}
 
-    if let Some(prev_entity) = selected.get() {
-        if prev_entity == clicked_entity {
-            // Checking if entity still exists is nice but here if clicked_entity exists, and prev == clicked, then prev exists.
-            commands.entity(prev_entity).insert(InteractionState::None);
-            selected.clear();
-            return;
+impl ArmyPicker<'_, '_> {
+    /// Army selection made by a primary click on the hex, `None` if the click is meant for the
+    /// province. Clicking stacked armies selects them in turn and clicking the selected army (the
+    /// last one of a stack) deselects it.
+    pub(crate) fn pick(&self, hex: Hex, selected: Option<Entity>) -> Option<ArmyPick> {
+        if self
+            .keyboard
+            .any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+        {
+            return None;
         }
-        // SAFETY CHECK: Only issue commands if prev_entity still exists.
-        if owners.contains(prev_entity) {
-            commands.entity(prev_entity).insert(InteractionState::None);
-        } else {
-            // It's dead. Just ignore.
-            warn!(
-                "Previously selected army {:?} no longer exists.",
-                prev_entity
-            );
+        let own_armies = self.own_armies(hex);
+        let first = *own_armies.first()?;
+        let pick = match selected.and_then(|army| own_armies.iter().position(|&a| a == army)) {
+            Some(index) if index + 1 < own_armies.len() => ArmyPick::Select(own_armies[index + 1]),
+            Some(_) => ArmyPick::Deselect,
+            None => ArmyPick::Select(first),
+        };
+        Some(pick)
+    }
+
+    /// Army of the player on the hex that `army` should merge into when ordered there, `None`
+    /// unless Ctrl is held.
+    pub(crate) fn merge_target(&self, army: Entity, hex: Hex) -> Option<Entity> {
+        if !self
+            .keyboard
+            .any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        {
+            return None;
         }
+        self.own_armies(hex)
+            .into_iter()
+            .find(|&other| other != army)
+    }
+
+    fn own_armies(&self, hex: Hex) -> Vec<Entity> {
+        self.army_map
+            .armies_at(&HexPos(hex))
+            .iter()
+            .copied()
+            .filter(|&army| {
+                self.owners
+                    .get(army)
+                    .is_ok_and(|owner| Some(owner.0) == self.player.country)
+            })
+            .collect()
     }
-    commands
-        .entity(clicked_entity)
-        .insert(InteractionState::Selected);
-    selected.set(clicked_entity);
 }
 
 pub(crate) fn handle_army_interaction_changed(
@@ -869,36 +570,114 @@ pub(crate) fn handle_army_interaction_changed(
     }
 }
 
+/// Updates the size label, the unit class icon and the strength bar of armies whose soldiers
+/// changed, or which were just drawn.
+#[allow(clippy::type_complexity)]
 pub(crate) fn handle_army_composition_changed(
-    army_query: Query<(&ArmyComposition, &Children), (With<Army>, Changed<ArmyComposition>)>,
+    army_query: Query<
+        (&ArmyComposition, &Children),
+        (
+            With<Army>,
+            Or<(Changed<ArmyComposition>, Changed<Children>)>,
+        ),
+    >,
     mut label_query: Query<(&mut ArmyLabel, &mut Text2d)>,
+    mut icon_query: Query<(&ArmyIcon, &mut Visibility)>,
+    mut bar_query: Query<(&mut StrengthBar, &mut Sprite, &mut Transform)>,
+    units: Res<UnitRegistry>,
 ) {
     for (composition, children) in &army_query {
+        let size = composition.total_size();
+        let dominant_class = units.dominant_class(composition);
         for &child in children {
             if let Ok((mut label, mut text)) = label_query.get_mut(child) {
-                let size_str = composition.total_size().to_string();
+                let size_str = size.to_string();
                 label.0 = size_str.clone();
                 *text = Text2d::new(size_str);
+            } else if let Ok((icon, mut visibility)) = icon_query.get_mut(child) {
+                visibility.set_if_neq(if Some(icon.0) == dominant_class {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                });
+            } else if let Ok((mut bar, mut sprite, mut transform)) = bar_query.get_mut(child) {
+                bar.peak = bar.peak.max(size);
+                let strength = if bar.peak > 0 {
+                    size as f32 / bar.peak as f32
+                } else {
+                    0.0
+                };
+                let width = BANNER_SIZE.x * strength;
+                sprite.custom_size = Some(Vec2::new(width, STRENGTH_BAR_HEIGHT));
+                sprite.color = if strength > 0.66 {
+                    Color::srgb(0.3, 0.8, 0.3)
+                } else if strength > 0.33 {
+                    Color::srgb(0.9, 0.8, 0.2)
+                } else {
+                    Color::srgb(0.9, 0.3, 0.2)
+                };
+                // Keep the bar flush with the left edge of the banner as it shrinks.
+                transform.translation.x = (width - BANNER_SIZE.x) / 2.0;
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub(crate) fn display_army_panel(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut camera_control: ResMut<CameraControl>,
     mut commands: Commands,
     mut selected_army: ResMut<SelectedArmy>,
-    armies: Query<(Entity, &ArmyComposition, &Owner), With<Army>>,
-    countries: Query<&crate::country::DisplayName>,
+    armies: Query<
+        (
+            Entity,
+            &ArmyComposition,
+            &Owner,
+            Option<&OutOfSupply>,
+            Option<&ActivePath>,
+        ),
+        With<Army>,
+    >,
+    army_map: Res<ArmyHexMap>,
+    countries: Query<&eu6_simulation::country::DisplayName>,
+    units: Res<UnitRegistry>,
+    player: Res<Player>,
+    mut split_draft: ResMut<SplitDraft>,
+    mut player_commands: PlayerCommands,
 ) {
     let Some(army_entity) = selected_army.get() else {
         return;
     };
 
-    let Ok((entity, composition, owner)) = armies.get(army_entity) else {
+    let Ok((entity, composition, owner, out_of_supply, active_path)) = armies.get(army_entity)
+    else {
         return;
     };
 
+    // The first army of the same country on the path is where the march ends up merging.
+    let merge_preview = active_path.and_then(|active| {
+        active.path.iter().find_map(|hex| {
+            army_map
+                .armies_at(&HexPos(*hex))
+                .iter()
+                .find_map(|&other| {
+                    armies
+                        .get(other)
+                        .ok()
+                        .filter(|(other, _, other_owner, ..)| {
+                            *other != entity && other_owner.0 == owner.0
+                        })
+                })
+                .map(|(_, other_composition, ..)| {
+                    let mut merged = other_composition.clone();
+                    merged.add(composition);
+                    merged
+                })
+        })
+    });
+
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
         Err(_) => return,
@@ -909,17 +688,15 @@ pub(crate) fn display_army_panel(
         .map(|d| d.0.as_str())
         .unwrap_or("Unknown");
 
-    egui::Window::new("Army")
-        .frame(crate::egui_common::default_frame())
-        .title_bar(false)
-        .anchor(Align2::RIGHT_TOP, [-20.0, 20.0])
+    theme
+        .side_panel("Army", Align2::RIGHT_TOP)
         .resizable(false)
         .default_width(200.0)
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.heading("Army Info");
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if crate::egui_common::close_button(ui) {
+                    if theme.close_button(ui) {
                         commands.entity(entity).insert(InteractionState::None);
                         selected_army.clear();
                     }
@@ -932,23 +709,27 @@ pub(crate) fn display_army_panel(
                 ui.label(RichText::new(owner_name).color(Color32::from_rgb(100, 200, 255)));
             });
 
+            if let Some(out_of_supply) = out_of_supply {
+                ui.label(
+                    RichText::new(format!("Out of supply for {} turns", out_of_supply.turns))
+                        .color(Color32::from_rgb(255, 100, 100)),
+                )
+                .on_hover_text("Armies cut off from their supply lines lose soldiers every turn");
+            }
+
+            ui.checkbox(&mut camera_control.follow_army, "Follow with camera (F)");
+
             ui.add_space(5.0);
             ui.label(RichText::new("Composition").strong());
 
             egui::Grid::new("army_comp_grid")
                 .num_columns(2)
                 .show(ui, |ui| {
-                    ui.label("Infantry:");
-                    ui.label(composition.infantry.to_string());
-                    ui.end_row();
-
-                    ui.label("Cavalry:");
-                    ui.label(composition.cavalry.to_string());
-                    ui.end_row();
-
-                    ui.label("Artillery:");
-                    ui.label(composition.artillery.to_string());
-                    ui.end_row();
+                    for (unit, soldiers) in composition.iter() {
+                        ui.label(format!("{}:", units.name(unit)));
+                        ui.label(soldiers.to_string());
+                        ui.end_row();
+                    }
 
                     ui.separator();
                     ui.end_row();
@@ -957,18 +738,491 @@ pub(crate) fn display_army_panel(
                     ui.label(RichText::new(composition.total_size().to_string()).strong());
                     ui.end_row();
                 });
+
+            if let Some(merged) = merge_preview {
+                ui.add_space(5.0);
+                ui.label(RichText::new("Merges on arrival into").strong());
+                ui.label(format!(
+                    "{} ({} soldiers)",
+                    units.describe(&merged),
+                    merged.total_size()
+                ));
+            }
+
+            if player.country == Some(owner.0)
+                && let Some(detachment) =
+                    draw_split_section(ui, entity, composition, &units, &mut split_draft)
+            {
+                player_commands.split_army(entity, detachment);
+            }
+        });
+}
+
+/// Sliders picking the soldiers of each unit type to detach from the army, returning the
+/// detachment once the player splits it off.
+fn draw_split_section(
+    ui: &mut egui::Ui,
+    army: Entity,
+    composition: &ArmyComposition,
+    units: &UnitRegistry,
+    draft: &mut SplitDraft,
+) -> Option<ArmyComposition> {
+    if draft.army != Some(army) {
+        draft.army = Some(army);
+        draft.detachment.clear();
+    }
+
+    ui.add_space(5.0);
+    ui.label(RichText::new("Split").strong());
+    let mut detachment = ArmyComposition::default();
+    for (unit, soldiers) in composition.iter() {
+        let picked = draft.detachment.entry(unit.clone()).or_default();
+        *picked = (*picked).min(soldiers);
+        ui.add(egui::Slider::new(picked, 0..=soldiers).text(units.name(unit)));
+        detachment = detachment.with(unit.clone(), *picked);
+    }
+
+    let size = detachment.total_size();
+    let can_split = size > 0 && size < composition.total_size();
+    let clicked = ui
+        .add_enabled(
+            can_split,
+            egui::Button::new(format!("Detach {} soldiers", size)),
+        )
+        .on_disabled_hover_text("Pick some soldiers, the army has to keep the rest")
+        .clicked();
+    if !clicked {
+        return None;
+    }
+    draft.detachment.clear();
+    Some(detachment)
+}
+
+/// Toggles the army list with the L key.
+fn toggle_army_list(keyboard: Res<ButtonInput<KeyCode>>, mut army_list: ResMut<ArmyListOpen>) {
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        army_list.0 = !army_list.0;
+    }
+}
+
+/// Keys stepping the selected army to one of its neighboring hexes, with the direction of
+/// [`Hex::neighbor`] they step in. The numpad keys lie around the army like its neighbors do.
+const STEP_KEYS: [(KeyCode, usize); 8] = [
+    (KeyCode::Numpad3, 0),
+    (KeyCode::Numpad6, 1),
+    (KeyCode::ArrowRight, 1),
+    (KeyCode::Numpad9, 2),
+    (KeyCode::Numpad7, 3),
+    (KeyCode::Numpad4, 4),
+    (KeyCode::ArrowLeft, 4),
+    (KeyCode::Numpad1, 5),
+];
+
+/// Direction the up or down arrow steps in from the hex. No hex lies straight above or below
+/// another, so the arrows zigzag between the two diagonals and repeated presses march straight up
+/// or down the screen.
+fn vertical_step(keyboard: &ButtonInput<KeyCode>, hex: &Hex) -> Option<usize> {
+    let even_row = hex.r().rem_euclid(2) == 0;
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        Some(if even_row { 2 } else { 3 })
+    } else if keyboard.just_pressed(KeyCode::ArrowDown) {
+        Some(if even_row { 0 } else { 5 })
+    } else {
+        None
+    }
+}
+
+/// Keyboard orders for the player's armies, easier than right clicks on small hexes far zoomed
+/// out. The arrow and numpad keys extend the selected army's march by a hex, S stops it and Tab
+/// (Shift+Tab backwards) selects the next army and centers the camera on it.
+fn army_keyboard_orders(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    egui_input: Res<EguiWantsInput>,
+    player: Res<Player>,
+    mut selection: Selection,
+    mut player_commands: PlayerCommands,
+    armies: Query<(Entity, &HexPos, &Owner, Option<&ActivePath>), With<Army>>,
+    mut focus_events: MessageWriter<CameraFocusEvent>,
+) {
+    if egui_input.wants_any_keyboard_input() {
+        return;
+    }
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let mut own_armies: Vec<_> = armies
+            .iter()
+            .filter(|(_, _, owner, _)| owner.0 == player_country)
+            .map(|(entity, pos, ..)| (entity, pos.0))
+            .collect();
+        own_armies.sort_unstable_by_key(|(entity, _)| *entity);
+        if own_armies.is_empty() {
+            return;
+        }
+        let current = selection
+            .army()
+            .and_then(|army| own_armies.iter().position(|(entity, _)| *entity == army));
+        let backwards = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let count = own_armies.len();
+        let next = match current {
+            Some(index) if backwards => (index + count - 1) % count,
+            Some(index) => (index + 1) % count,
+            None if backwards => count - 1,
+            None => 0,
+        };
+        let (army, hex) = own_armies[next];
+        selection.select_army(army);
+        focus_events.write(CameraFocusEvent::new(hex.axial_to_world(consts::HEX_SIZE)));
+        return;
+    }
+
+    let Some((army, pos, owner, active_path)) =
+        selection.army().and_then(|army| armies.get(army).ok())
+    else {
+        return;
+    };
+    if owner.0 != player_country {
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        player_commands.stop_army(army);
+        return;
+    }
+
+    // Steps are taken from the end of the current march, so that presses add up to a route.
+    let from = active_path
+        .and_then(|active_path| active_path.path.back())
+        .copied()
+        .unwrap_or(pos.0);
+    let direction = STEP_KEYS
+        .iter()
+        .find(|(key, _)| keyboard.just_pressed(*key))
+        .map(|(_, direction)| *direction)
+        .or_else(|| vertical_step(&keyboard, &from));
+    if let Some(direction) = direction {
+        player_commands.move_army(army, from.neighbor(direction));
+    }
+}
+
+/// Opens the army list if it was left open in the last session.
+fn restore_army_list(settings: Res<Settings>, mut army_list: ResMut<ArmyListOpen>) {
+    army_list.0 = settings.window(ARMY_LIST_WINDOW).open;
+}
+
+fn remember_army_list(army_list: Res<ArmyListOpen>, mut settings: ResMut<Settings>) {
+    let layout = settings.window(ARMY_LIST_WINDOW);
+    if layout.open != army_list.0 {
+        settings.remember_window(
+            ARMY_LIST_WINDOW,
+            WindowLayout {
+                open: army_list.0,
+                ..layout
+            },
+        );
+    }
+}
+
+/// Window listing every army of the player with its location and current order. Armies can be
+/// selected and sent to one of the player's provinces straight from the list. It can be dragged
+/// around, and opens where it was left.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn display_army_list(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut settings: ResMut<Settings>,
+    mut army_list: ResMut<ArmyListOpen>,
+    mut selection: Selection,
+    mut player_commands: PlayerCommands,
+    player: Res<Player>,
+    province_map: Res<ProvinceHexMap>,
+    armies: Query<
+        (
+            Entity,
+            &ArmyComposition,
+            &HexPos,
+            &Owner,
+            Option<&ActivePath>,
+            Option<&InBattle>,
+            Option<&Following>,
+            Option<&Movement>,
+        ),
+        With<Army>,
+    >,
+    provinces: Query<(&Province, Option<&Owner>)>,
+    units: Res<UnitRegistry>,
+) {
+    if !army_list.0 {
+        return;
+    }
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let province_name = |hex: &Hex| {
+        province_map
+            .get_entity(hex)
+            .and_then(|&entity| provinces.get(entity).ok())
+            .map(|(province, _)| province.name().to_string())
+            .unwrap_or_else(|| "Unknown".to_string())
+    };
+    let movement_cost = |hex: &Hex| {
+        province_map
+            .get_entity(hex)
+            .and_then(|&entity| provinces.get(entity).ok())
+            .map_or(1, |(province, _)| province.terrain().movement_cost)
+    };
+
+    let mut player_armies: Vec<_> = armies
+        .iter()
+        .filter(|(_, _, _, owner, ..)| owner.0 == player_country)
+        .collect();
+    player_armies.sort_by_key(|(entity, ..)| *entity);
+
+    let mut destinations: Vec<(&str, Hex)> = provinces
+        .iter()
+        .filter(|(province, owner)| {
+            province.is_passable() && owner.is_some_and(|o| o.0 == player_country)
+        })
+        .map(|(province, _)| (province.name(), *province.get_hex()))
+        .collect();
+    destinations.sort_by_key(|(name, _)| *name);
+
+    let mut close = false;
+
+    let window = egui::Window::new("Armies")
+        .frame(theme.frame())
+        .title_bar(false)
+        .resizable(false)
+        .default_width(320.0);
+    // Until it is moved the list sits in the bottom left corner, above the turn window.
+    let window = settings.place_window(
+        window,
+        ARMY_LIST_WINDOW,
+        ctx,
+        Align2::LEFT_BOTTOM,
+        [20.0, -80.0],
+    );
+
+    let shown = window.show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.heading(format!("Armies ({})", player_armies.len()));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if theme.close_button(ui) {
+                    close = true;
+                }
+            });
+        });
+        ui.separator();
+
+        if player_armies.is_empty() {
+            ui.label(RichText::new("You have no armies.").italics());
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                for (entity, composition, pos, _, active_path, in_battle, following, movement) in
+                    &player_armies
+                {
+                    let speed = units.movement(composition);
+                    let saved = movement.map_or(0, |movement| movement.points);
+                    let is_selected = selection.army() == Some(*entity);
+
+                    ui.horizontal(|ui| {
+                        let label =
+                            format!("⚔ {} ({})", province_name(&pos.0), composition.total_size());
+                        if ui.selectable_label(is_selected, label).clicked() {
+                            if is_selected {
+                                selection.clear_army();
+                            } else {
+                                selection.select_army(*entity);
+                            }
+                        }
+                        if in_battle.is_some() {
+                            ui.label(
+                                RichText::new("In battle")
+                                    .small()
+                                    .strong()
+                                    .color(Color32::RED),
+                            );
+                        }
+                    });
+
+                    ui.label(
+                        RichText::new(units.describe(composition))
+                            .small()
+                            .color(Color32::LIGHT_GRAY),
+                    );
+                    let mut movement_text = format!("Movement: {} per turn", speed);
+                    if saved > 0 {
+                        movement_text += &format!(", {} saved for the next province", saved);
+                    }
+                    ui.label(
+                        RichText::new(movement_text)
+                            .small()
+                            .color(Color32::LIGHT_GRAY),
+                    );
+
+                    ui.horizontal(|ui| {
+                        let followed_pos = following
+                            .and_then(|following| armies.get(following.target).ok())
+                            .map(|(_, _, target_pos, ..)| target_pos.0);
+                        let destination = active_path.and_then(|p| p.path.back());
+                        let turns = active_path.map_or(0, |p| {
+                            turns_to_march(p.path.iter().map(movement_cost), speed, saved)
+                        });
+                        let order = match (followed_pos, destination) {
+                            (Some(target_pos), _) => {
+                                format!("Following army at {}", province_name(&target_pos))
+                            }
+                            (None, Some(destination)) => format!(
+                                "Marching to {} ({} turns)",
+                                province_name(destination),
+                                turns
+                            ),
+                            (None, None) => "Idle".to_string(),
+                        };
+                        ui.label(RichText::new(order).small());
+
+                        ui.add_enabled_ui(in_battle.is_none(), |ui| {
+                            egui::ComboBox::from_id_salt(("army_move_order", *entity))
+                                .selected_text("Move to…")
+                                .width(120.0)
+                                .show_ui(ui, |ui| {
+                                    for (name, hex) in &destinations {
+                                        if *hex != pos.0
+                                            && ui.selectable_label(false, *name).clicked()
+                                        {
+                                            player_commands.move_army(*entity, *hex);
+                                        }
+                                    }
+                                });
+                            egui::ComboBox::from_id_salt(("army_attach_order", *entity))
+                                .selected_text("Attach to…")
+                                .width(120.0)
+                                .show_ui(ui, |ui| {
+                                    if following.is_some()
+                                        && ui.selectable_label(false, "Detach").clicked()
+                                    {
+                                        player_commands.attach_army(*entity, None);
+                                    }
+                                    for (target, _, target_pos, ..) in &player_armies {
+                                        let label = format!("⚔ {}", province_name(&target_pos.0));
+                                        if target != entity
+                                            && ui.selectable_label(false, label).clicked()
+                                        {
+                                            player_commands.attach_army(*entity, Some(*target));
+                                        }
+                                    }
+                                });
+                        });
+                    });
+                    ui.separator();
+                }
+            });
+    });
+
+    if let Some(pos) = dragged_to(&shown) {
+        settings.remember_window_pos(ARMY_LIST_WINDOW, pos);
+    }
+
+    if close {
+        army_list.0 = false;
+    }
+}
+
+/// Window asking the player whether to merge armies that stopped next to another of their armies,
+/// see [`PendingMerge`]. It shows what the merged army would be made of.
+fn display_pending_merges(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut player_commands: PlayerCommands,
+    player: Res<Player>,
+    pending: Query<(Entity, &PendingMerge, &HexPos, &Owner, &ArmyComposition)>,
+    targets: Query<(&HexPos, &ArmyComposition), With<Army>>,
+    units: Res<UnitRegistry>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+    let mut merges: Vec<_> = pending
+        .iter()
+        .filter(|(_, _, _, owner, _)| owner.0 == player_country)
+        .filter_map(|(army, merge, pos, _, composition)| {
+            let (target_pos, target_composition) = targets.get(merge.into).ok()?;
+            if pos.0.distance(&target_pos.0) > 1 {
+                return None;
+            }
+            let mut merged = target_composition.clone();
+            merged.add(composition);
+            Some((army, merge.into, composition, merged))
+        })
+        .collect();
+    if merges.is_empty() {
+        return;
+    }
+    merges.sort_by_key(|(army, ..)| *army);
+
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Merge armies")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 80.0])
+        .resizable(false)
+        .default_width(320.0)
+        .show(ctx, |ui| {
+            ui.heading("Merge armies?");
+            ui.separator();
+            for (army, target, composition, merged) in &merges {
+                ui.label(format!(
+                    "An army of {} soldiers reached another of your armies.",
+                    composition.total_size()
+                ));
+                ui.label(
+                    RichText::new(format!(
+                        "Merged: {} ({} soldiers)",
+                        units.describe(merged),
+                        merged.total_size()
+                    ))
+                    .small()
+                    .color(Color32::LIGHT_GRAY),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Merge").clicked() {
+                        player_commands.merge_army(*army, *target);
+                    }
+                    if ui.button("Keep apart").clicked() {
+                        player_commands.stop_army(*army);
+                    }
+                });
+                ui.separator();
+            }
         });
 }
 
 pub(crate) fn display_battle_panel(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
     mut commands: Commands,
     mut selected_army: ResMut<SelectedArmy>,
     armies: Query<(&ArmyComposition, &Owner, Option<&InBattle>), With<Army>>,
     battles: Query<&Battle>,
-    countries: Query<&crate::country::DisplayName>,
+    countries: Query<&eu6_simulation::country::DisplayName>,
     province_map: Res<ProvinceHexMap>,
     provinces: Query<&Province>,
+    units: Res<UnitRegistry>,
 ) {
     let Some(selected_entity) = selected_army.get() else {
         return;
@@ -991,25 +1245,23 @@ pub(crate) fn display_battle_panel(
     let terrain = province_map
         .get_entity(&battle.location)
         .and_then(|&e| provinces.get(e).ok())
-        .map(|p| p.terrain())
-        .unwrap_or(crate::map::Terrain::Plains);
+        .map(|p| p.terrain().clone())
+        .unwrap_or_else(|| Terrain::Plains.def());
 
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
         Err(_) => return,
     };
 
-    egui::Window::new("Battle")
-        .frame(crate::egui_common::default_frame())
-        .title_bar(false)
-        .anchor(Align2::RIGHT_TOP, [-20.0, 20.0])
+    theme
+        .side_panel("Battle", Align2::RIGHT_TOP)
         .resizable(false)
         .default_width(300.0)
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.heading("⚔ Battle ⚔");
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if crate::egui_common::close_button(ui) {
+                    if theme.close_button(ui) {
                         commands
                             .entity(selected_entity)
                             .insert(InteractionState::None);
@@ -1021,8 +1273,8 @@ pub(crate) fn display_battle_panel(
 
             // Terrain info
             ui.horizontal(|ui| {
-                ui.label(format!("Terrain: {}", terrain));
-                let def_bonus = terrain.defender_bonus();
+                ui.label(format!("Terrain: {}", terrain.name));
+                let def_bonus = terrain.defender_bonus;
                 if def_bonus > 1.0 {
                     ui.label(
                         RichText::new(format!("(+{:.0}% def)", (def_bonus - 1.0) * 100.0))
@@ -1037,8 +1289,8 @@ pub(crate) fn display_battle_panel(
             });
 
             // Unit modifiers
-            let cav_mod = terrain.cavalry_modifier();
-            let art_mod = terrain.artillery_modifier();
+            let cav_mod = terrain.unit_modifier(UnitClass::Cavalry);
+            let art_mod = terrain.unit_modifier(UnitClass::Artillery);
             if cav_mod != 1.0 || art_mod != 1.0 {
                 ui.horizontal(|ui| {
                     if cav_mod != 1.0 {
@@ -1069,30 +1321,18 @@ pub(crate) fn display_battle_panel(
             ui.separator();
 
             // Calculate total strength for each side
-            let mut att_total = ArmyComposition {
-                infantry: 0,
-                cavalry: 0,
-                artillery: 0,
-            };
-            let mut def_total = ArmyComposition {
-                infantry: 0,
-                cavalry: 0,
-                artillery: 0,
-            };
+            let mut att_total = ArmyComposition::default();
+            let mut def_total = ArmyComposition::default();
 
             for &army_entity in &battle.attackers {
                 if let Ok((comp, _, _)) = armies.get(army_entity) {
-                    att_total.infantry += comp.infantry;
-                    att_total.cavalry += comp.cavalry;
-                    att_total.artillery += comp.artillery;
+                    att_total.add(comp);
                 }
             }
 
             for &army_entity in &battle.defenders {
                 if let Ok((comp, _, _)) = armies.get(army_entity) {
-                    def_total.infantry += comp.infantry;
-                    def_total.cavalry += comp.cavalry;
-                    def_total.artillery += comp.artillery;
+                    def_total.add(comp);
                 }
             }
 
@@ -1110,9 +1350,9 @@ pub(crate) fn display_battle_panel(
                         .unwrap_or("Unknown");
                     ui.label(format!("{} ({})", attacker_name, battle.attackers.len()));
                     ui.add_space(4.0);
-                    ui.label(format!("Inf: {}", att_total.infantry));
-                    ui.label(format!("Cav: {}", att_total.cavalry));
-                    ui.label(format!("Art: {}", att_total.artillery));
+                    for (unit, soldiers) in att_total.iter() {
+                        ui.label(format!("{}: {}", units.name(unit), soldiers));
+                    }
                     ui.label(RichText::new(format!("Total: {}", att_total.total_size())).strong());
                     ui.add_space(4.0);
                     ui.label(
@@ -1133,9 +1373,9 @@ pub(crate) fn display_battle_panel(
                         .unwrap_or("Unknown");
                     ui.label(format!("{} ({})", defender_name, battle.defenders.len()));
                     ui.add_space(4.0);
-                    ui.label(format!("Inf: {}", def_total.infantry));
-                    ui.label(format!("Cav: {}", def_total.cavalry));
-                    ui.label(format!("Art: {}", def_total.artillery));
+                    for (unit, soldiers) in def_total.iter() {
+                        ui.label(format!("{}: {}", units.name(unit), soldiers));
+                    }
                     ui.label(RichText::new(format!("Total: {}", def_total.total_size())).strong());
                     ui.add_space(4.0);
                     ui.label(
@@ -1147,298 +1387,87 @@ pub(crate) fn display_battle_panel(
         });
 }
 
-pub(crate) fn resolve_battles(
-    mut commands: Commands,
-    mut battles: Query<(Entity, &mut Battle)>,
-    mut armies: Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
-    mut army_hex_map: ResMut<ArmyHexMap>,
-    province_map: Res<ProvinceHexMap>,
-    provinces: Query<(&Province, &Owner)>,
-) {
-    for (battle_entity, mut battle) in battles.iter_mut() {
-        // Clean up dead armies from the battle
-        battle.attackers.retain(|&e| {
-            armies
-                .get(e)
-                .map(|(_, comp, _, _)| comp.total_size() > 0)
-                .unwrap_or(false)
-        });
-        battle.defenders.retain(|&e| {
-            armies
-                .get(e)
-                .map(|(_, comp, _, _)| comp.total_size() > 0)
-                .unwrap_or(false)
-        });
-
-        // Check if battle should end
-        if battle.attackers.is_empty() && battle.defenders.is_empty() {
-            info!(
-                "Battle at {:?} ended in mutual destruction after {} rounds",
-                battle.location, battle.round
-            );
-            commands.entity(battle_entity).despawn();
-            continue;
-        } else if battle.attackers.is_empty() {
-            info!(
-                "Defenders won battle at {:?} after {} rounds",
-                battle.location, battle.round
-            );
-            end_battle_multi(
-                &mut commands,
-                &mut armies,
-                &mut army_hex_map,
-                battle_entity,
-                &battle,
-                BattleSide::Defender,
-                &province_map,
-                &provinces,
-            );
-            continue;
-        } else if battle.defenders.is_empty() {
-            info!(
-                "Attackers won battle at {:?} after {} rounds",
-                battle.location, battle.round
-            );
-            end_battle_multi(
-                &mut commands,
-                &mut armies,
-                &mut army_hex_map,
-                battle_entity,
-                &battle,
-                BattleSide::Attacker,
-                &province_map,
-                &provinces,
-            );
-            continue;
-        }
-
-        // Get terrain at battle location for combat modifiers
-        let terrain = province_map
-            .get_entity(&battle.location)
-            .and_then(|&e| provinces.get(e).ok())
-            .map(|(p, _)| p.terrain())
-            .unwrap_or(crate::map::Terrain::Plains);
-
-        let defender_terrain_bonus = terrain.defender_bonus();
-        let cavalry_modifier = terrain.cavalry_modifier();
-        let artillery_modifier = terrain.artillery_modifier();
-
-        // Log terrain effects on first round
-        if battle.round == 0 {
-            info!(
-                "Battle at {:?} on {:?} terrain - Attackers: {} armies, Defenders: {} armies",
-                battle.location,
-                terrain,
-                battle.attackers.len(),
-                battle.defenders.len()
-            );
-        }
-
-        // Calculate combined strength for each side
-        fn calc_side_damage(
-            armies: &Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
-            army_list: &[Entity],
-            cavalry_mod: f32,
-            artillery_mod: f32,
-        ) -> f32 {
-            let mut total_damage = 0.0;
-            for &army_entity in army_list {
-                if let Ok((_, comp, _, _)) = armies.get(army_entity) {
-                    total_damage += (comp.infantry as f32 * 0.5)
-                        + (comp.cavalry as f32 * 1.0 * cavalry_mod)
-                        + (comp.artillery as f32 * 2.0 * artillery_mod);
-                }
-            }
-            total_damage
-        }
-
-        let mut rng = rand::rng();
-        let att_roll: f32 = rng.random_range(0.8..1.2);
-        let def_roll: f32 = rng.random_range(0.8..1.2);
-
-        let att_base_dmg = calc_side_damage(
-            &armies,
-            &battle.attackers,
-            cavalry_modifier,
-            artillery_modifier,
-        );
-        let def_base_dmg = calc_side_damage(
-            &armies,
-            &battle.defenders,
-            cavalry_modifier,
-            artillery_modifier,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use eu6_simulation::test_utils::TestGame;
+
+    #[test]
+    fn clicks_pick_own_armies_by_hex_unless_alt_is_held() {
+        let mut game = TestGame::new();
+        game.world_mut().init_resource::<ButtonInput<KeyCode>>();
+        let country = game.spawn_country("Country");
+        let enemy = game.spawn_country("Enemy");
+        game.world_mut().resource_mut::<Player>().country = Some(country);
+        let first = game.spawn_army(country, Hex::new(0, 0), 1000);
+        let second = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.spawn_army(enemy, Hex::new(1, 0), 1000);
+        game.app.update();
+
+        let pick = |game: &mut TestGame, hex: Hex, selected: Option<Entity>| {
+            game.world_mut()
+                .run_system_once(move |picker: ArmyPicker| picker.pick(hex, selected))
+                .unwrap()
+        };
+        let home = Hex::new(0, 0);
+        assert_eq!(pick(&mut game, home, None), Some(ArmyPick::Select(first)));
+        // Stacked armies are selected in turn, then the last click deselects.
+        assert_eq!(
+            pick(&mut game, home, Some(first)),
+            Some(ArmyPick::Select(second))
         );
-
-        // Apply terrain bonuses
-        let att_dmg = (att_base_dmg * att_roll / defender_terrain_bonus) as u32;
-        let def_dmg = (def_base_dmg * def_roll * defender_terrain_bonus) as u32;
-
-        // Distribute damage across armies on each side
-        fn apply_damage_to_side(
-            armies: &mut Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
-            army_list: &[Entity],
-            total_damage: u32,
-        ) -> u32 {
-            if army_list.is_empty() {
-                return 0;
-            }
-
-            let damage_per_army = total_damage / army_list.len() as u32;
-            let mut total_lost = 0;
-
-            for &army_entity in army_list {
-                if let Ok((_, mut comp, _, _)) = armies.get_mut(army_entity) {
-                    let lost = apply_damage_to_composition(&mut comp, damage_per_army.max(1));
-                    total_lost += lost;
-                }
-            }
-            total_lost
-        }
-
-        let att_lost = apply_damage_to_side(&mut armies, &battle.attackers, def_dmg);
-        let def_lost = apply_damage_to_side(&mut armies, &battle.defenders, att_dmg);
-
-        battle.last_damage_attacker = att_lost;
-        battle.last_damage_defender = def_lost;
-        battle.round += 1;
-
-        info!(
-            "Battle round {} at {:?}: Attackers lost {}, Defenders lost {}",
-            battle.round, battle.location, att_lost, def_lost
+        assert_eq!(
+            pick(&mut game, home, Some(second)),
+            Some(ArmyPick::Deselect)
         );
-
-        // Remove dead armies from hex map and despawn
-        let mut to_despawn = Vec::new();
-        for &army_entity in battle.attackers.iter().chain(battle.defenders.iter()) {
-            if let Ok((_, comp, _, _)) = armies.get(army_entity)
-                && comp.total_size() == 0
-            {
-                if let Some(pos) = army_hex_map
-                    .tiles
-                    .iter()
-                    .find_map(|(k, v)| if *v == army_entity { Some(*k) } else { None })
-                {
-                    army_hex_map.remove(&pos);
-                }
-                to_despawn.push(army_entity);
-            }
-        }
-        for army_entity in to_despawn {
-            commands.entity(army_entity).despawn();
-        }
-
-        // Battle continues next turn - don't end it here
-    }
-}
-
-fn apply_damage_to_composition(comp: &mut ArmyComposition, damage: u32) -> u32 {
-    let units_lost = damage / 20;
-    let mut remaining_to_kill = units_lost;
-
-    if remaining_to_kill == 0 && damage > 0 {
-        remaining_to_kill = damage.min(MIN_DAMAGE).min(comp.total_size());
-    }
-
-    let total = comp.total_size();
-    if remaining_to_kill > total {
-        remaining_to_kill = total;
-    }
-    if remaining_to_kill == 0 && total > 0 && damage > 0 {
-        remaining_to_kill = 1;
-    }
-
-    let actual_lost = remaining_to_kill;
-
-    // Distribute kills (Inf -> Cav -> Art)
-    let kill_inf = remaining_to_kill.min(comp.infantry);
-    comp.infantry -= kill_inf;
-    remaining_to_kill -= kill_inf;
-
-    let kill_cav = remaining_to_kill.min(comp.cavalry);
-    comp.cavalry -= kill_cav;
-    remaining_to_kill -= kill_cav;
-
-    let kill_art = remaining_to_kill.min(comp.artillery);
-    comp.artillery -= kill_art;
-
-    actual_lost
-}
-
-fn end_battle_multi(
-    commands: &mut Commands,
-    armies: &mut Query<(Entity, &mut ArmyComposition, &mut HexPos, &Owner)>,
-    army_hex_map: &mut ArmyHexMap,
-    battle_entity: Entity,
-    battle: &Battle,
-    winner_side: BattleSide,
-    province_map: &ProvinceHexMap,
-    provinces: &Query<(&Province, &Owner)>,
-) {
-    let battle_location = battle.location;
-    let winner_country = match winner_side {
-        BattleSide::Attacker => battle.attacker_country,
-        BattleSide::Defender => battle.defender_country,
-    };
-
-    let (winners, losers) = match winner_side {
-        BattleSide::Attacker => (&battle.attackers, &battle.defenders),
-        BattleSide::Defender => (&battle.defenders, &battle.attackers),
-    };
-
-    // Remove losers from hex map and despawn them
-    for &army_entity in losers {
-        // Find and remove from hex map
-        if let Some(pos) = army_hex_map
-            .tiles
-            .iter()
-            .find_map(|(k, v)| if *v == army_entity { Some(*k) } else { None })
-        {
-            army_hex_map.remove(&pos);
-            info!(
-                "Removed defeated army {:?} from hex map at {:?}",
-                army_entity, pos
-            );
-        }
-        commands.entity(army_entity).remove::<InBattle>();
-        commands.entity(army_entity).despawn();
-    }
-
-    // Remove InBattle from all surviving armies and position them
-    for &army_entity in winners {
-        commands.entity(army_entity).remove::<InBattle>();
-
-        // Move winner to battle location
-        if let Ok((_, _, mut pos, _)) = armies.get_mut(army_entity) {
-            // First remove from old position
-            army_hex_map.remove(&pos);
-            *pos = HexPos(battle_location);
-            commands
-                .entity(army_entity)
-                .insert(Transform::from_translation(
-                    battle_location.axial_to_world(consts::HEX_SIZE).extend(5.0),
-                ));
-        }
+        // Enemy armies and empty hexes leave the click to the province.
+        assert_eq!(pick(&mut game, Hex::new(1, 0), None), None);
+        assert_eq!(pick(&mut game, Hex::new(0, 1), None), None);
+
+        game.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::AltLeft);
+        assert_eq!(pick(&mut game, home, None), None);
     }
 
-    // Put one winner army on the hex map (others are "stacked")
-    if let Some(&first_winner) = winners.first() {
-        army_hex_map.insert(HexPos(battle_location), first_winner);
-        info!(
-            "Winner army {:?} placed at {:?}",
-            first_winner, battle_location
+    #[test]
+    fn selected_armies_show_how_far_they_reach_this_turn() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let enemy = game.spawn_country("Enemy");
+        let provinces = game.spawn_provinces(4, Some(country));
+        game.world_mut()
+            .get_mut::<Province>(provinces[2])
+            .unwrap()
+            .set_terrain(eu6_simulation::terrain::Terrain::Hills.def());
+        let army = game.spawn_army(country, Hex::new(0, 0), 1000);
+        game.world_mut()
+            .entity_mut(army)
+            .insert(Movement { points: 2 });
+        game.world_mut().init_resource::<ArmyReach>();
+        game.world_mut().insert_resource(SelectedArmy::default());
+        game.world_mut().resource_mut::<SelectedArmy>().set(army);
+        game.app.add_systems(Update, update_army_reach);
+
+        game.app.update();
+        let reach = game.world().resource::<ArmyReach>();
+        assert_eq!(
+            reach.reachable,
+            HashSet::from([Hex::new(1, 0), Hex::new(2, 0)])
+        );
+        assert!(reach.attack_range.contains(&Hex::new(0, 0)));
+        assert!(!reach.attack_range.contains(&Hex::new(3, 0)));
+
+        // Enemies in the way can be attacked, but the march ends there.
+        game.declare_war(country, enemy);
+        game.spawn_army(enemy, Hex::new(1, 0), 1000);
+        game.app.update();
+        let reach = game.world().resource::<ArmyReach>();
+        assert!(reach.reachable.is_empty());
+        assert_eq!(
+            reach.attack_range,
+            HashSet::from([Hex::new(0, 0), Hex::new(1, 0)])
         );
     }
-
-    // Occupy province if attackers won
-    if winner_side == BattleSide::Attacker
-        && let Some(&province_entity) = province_map.get_entity(&battle_location)
-    {
-        if let Ok((province, owner)) = provinces.get(province_entity) {
-            if province.is_ownable() && owner.0 != winner_country {
-                crate::war::occupy_province(commands, province_entity, winner_country);
-            }
-        }
-    }
-
-    commands.entity(battle_entity).despawn();
 }
@@ -0,0 +1,212 @@
+﻿use crate::army::SelectedArmy;
+use crate::map::SelectedProvince;
+use crate::settings::Settings;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use eu6_simulation::army::{BattleEndedEvent, BattleJoinedEvent, BattleStartedEvent};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::player::Player;
+use eu6_simulation::turns::{GameState, TurnSet};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlaySoundEvent>()
+            .add_systems(OnEnter(MenuState::MainMenu), play_menu_music)
+            .add_systems(OnEnter(MenuState::InGame), play_game_music)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::PlayerTurn,
+                    entered: GameState::Processing,
+                },
+                play_end_turn_sound.in_set(TurnSet::End),
+            )
+            .add_systems(Update, play_battle_sounds)
+            .add_systems(Update, play_selection_sounds)
+            .add_systems(Update, play_sound_effects)
+            .add_systems(Update, update_music_volume)
+            .add_systems(EguiPrimaryContextPass, play_ui_click_sounds);
+    }
+}
+
+const MENU_MUSIC_PATH: &str = "audio/music_menu.wav";
+const GAME_MUSIC_PATH: &str = "audio/music_game.wav";
+
+/// Short sound effects played in response to game and UI events.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum SoundEffect {
+    Click,
+    Select,
+    BattleStart,
+    BattleEnd,
+    EndTurn,
+}
+
+impl SoundEffect {
+    fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Click => "audio/click.wav",
+            SoundEffect::Select => "audio/select.wav",
+            SoundEffect::BattleStart => "audio/battle_start.wav",
+            SoundEffect::BattleEnd => "audio/battle_end.wav",
+            SoundEffect::EndTurn => "audio/end_turn.wav",
+        }
+    }
+}
+
+/// Message requesting a one-shot sound effect.
+#[derive(Message)]
+pub(crate) struct PlaySoundEvent(pub(crate) SoundEffect);
+
+/// Marker for the entity playing the current background track.
+#[derive(Component)]
+pub(crate) struct MusicTrack;
+
+fn play_menu_music(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    current: Query<Entity, With<MusicTrack>>,
+) {
+    switch_music(
+        commands,
+        &asset_server,
+        &settings,
+        &current,
+        MENU_MUSIC_PATH,
+    );
+}
+
+fn play_game_music(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    current: Query<Entity, With<MusicTrack>>,
+) {
+    switch_music(
+        commands,
+        &asset_server,
+        &settings,
+        &current,
+        GAME_MUSIC_PATH,
+    );
+}
+
+/// Stops whatever track is playing and starts looping the given one.
+fn switch_music(
+    mut commands: Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    current: &Query<Entity, With<MusicTrack>>,
+    path: &'static str,
+) {
+    for entity in current.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    commands.spawn((
+        AudioPlayer::new(asset_server.load(path)),
+        PlaybackSettings::LOOP.with_volume(Volume::Linear(settings.music_volume())),
+        MusicTrack,
+    ));
+}
+
+/// Keeps the music sink in sync with volume changes made in the settings window.
+fn update_music_volume(
+    settings: Res<Settings>,
+    mut sinks: Query<&mut AudioSink, With<MusicTrack>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut sink in sinks.iter_mut() {
+        sink.set_volume(Volume::Linear(settings.music_volume()));
+    }
+}
+
+fn play_sound_effects(
+    mut commands: Commands,
+    mut events: MessageReader<PlaySoundEvent>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    for PlaySoundEvent(effect) in events.read() {
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(effect.path())),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.effects_volume())),
+        ));
+    }
+}
+
+fn play_end_turn_sound(mut sounds: MessageWriter<PlaySoundEvent>) {
+    sounds.write(PlaySoundEvent(SoundEffect::EndTurn));
+}
+
+/// Plays stingers for battles the player takes part in. Other countries' battles stay silent so
+/// that AI wars don't turn into noise.
+fn play_battle_sounds(
+    mut started: MessageReader<BattleStartedEvent>,
+    mut joined: MessageReader<BattleJoinedEvent>,
+    mut ended: MessageReader<BattleEndedEvent>,
+    mut sounds: MessageWriter<PlaySoundEvent>,
+    player: Res<Player>,
+) {
+    let Some(player_country) = player.country else {
+        started.clear();
+        joined.clear();
+        ended.clear();
+        return;
+    };
+
+    let player_started = started
+        .read()
+        .any(|e| e.attacker_country == player_country || e.defender_country == player_country);
+    let player_joined = joined.read().any(|e| e.country == player_country);
+    if player_started || player_joined {
+        sounds.write(PlaySoundEvent(SoundEffect::BattleStart));
+    }
+    if ended
+        .read()
+        .any(|e| e.attacker_country == player_country || e.defender_country == player_country)
+    {
+        sounds.write(PlaySoundEvent(SoundEffect::BattleEnd));
+    }
+}
+
+fn play_selection_sounds(
+    selected_army: Res<SelectedArmy>,
+    selected_province: Res<SelectedProvince>,
+    mut sounds: MessageWriter<PlaySoundEvent>,
+) {
+    let army_selected = selected_army.is_changed() && selected_army.get().is_some();
+    let province_selected = selected_province.is_changed() && selected_province.get().is_some();
+
+    // Both resources are "changed" on their first frame, which isn't a player action.
+    if (army_selected || province_selected)
+        && !selected_army.is_added()
+        && !selected_province.is_added()
+    {
+        sounds.write(PlaySoundEvent(SoundEffect::Select));
+    }
+}
+
+/// Plays a click when the player clicks a button-like widget. Selectable labels drop the
+/// focusable flag and window backgrounds interact through their layer's "move" id, so neither
+/// of them makes a sound.
+fn play_ui_click_sounds(mut contexts: EguiContexts, mut sounds: MessageWriter<PlaySoundEvent>) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    let Some(clicked) = ctx.interaction_snapshot(|i| i.clicked) else {
+        return;
+    };
+    let Some(response) = ctx.read_response(clicked) else {
+        return;
+    };
+    let is_area_background = response.id == response.layer_id.id.with("move");
+    if response.sense.senses_click() && response.sense.is_focusable() && !is_area_background {
+        sounds.write(PlaySoundEvent(SoundEffect::Click));
+    }
+}
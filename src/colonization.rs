@@ -0,0 +1,39 @@
+﻿use bevy::prelude::*;
+use eu6_simulation::army::{Army, HexPos};
+use eu6_simulation::colonization::Explored;
+use eu6_simulation::trade::Fleet;
+
+pub struct ColonizationUiPlugin;
+
+impl Plugin for ColonizationUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            hide_unexplored_units.after(eu6_simulation::colonization::explore_map),
+        );
+    }
+}
+
+/// Hides the armies and fleets standing in terra incognita.
+fn hide_unexplored_units(
+    explored: Res<Explored>,
+    mut armies: Query<(&HexPos, &mut Visibility), With<Army>>,
+    mut fleets: Query<(&Fleet, &mut Visibility), Without<Army>>,
+) {
+    let positions = armies
+        .iter_mut()
+        .map(|(pos, visibility)| (pos.0, visibility))
+        .chain(
+            fleets
+                .iter_mut()
+                .map(|(fleet, visibility)| (fleet.hex, visibility)),
+        );
+    for (hex, mut visibility) in positions {
+        let shown = if explored.contains(&hex) {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
+    }
+}
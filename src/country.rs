@@ -1,31 +1,40 @@
-﻿use crate::egui_common;
-use crate::map::{MapData, Owner, Province};
-use crate::menu::MenuState;
-use crate::player::Player;
-use crate::war::{
-    draw_diplomacy_tab, DeclareWarEvent, Occupied, PeaceOfferEvent, War, WarRelations, Wars,
-};
+﻿use crate::diplomacy::Alliance;
+use crate::egui_common::UiTheme;
+use crate::layout::CameraFocusEvent;
+use crate::map::{InteractionState, SelectedProvince};
+use eu6_simulation::army::{Army, ArmyComposition};
+use eu6_simulation::buildings::{Building, Income};
+use eu6_simulation::consts;
+use eu6_simulation::diplomacy::{Alliances, MilitaryAccess};
+use eu6_simulation::manpower::Manpower;
+use eu6_simulation::map::{Owner, Province};
+
+use crate::war::{DiplomacyDrafts, PeaceDraft, draw_diplomacy_tab};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::modifiers::{Devastation, IncomeSources, Modifiers, Unrest};
+use eu6_simulation::mods::VirtualFs;
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::PlayerCommands;
+use eu6_simulation::religion::{Faith, LeagueMember, Leagues};
+use eu6_simulation::trade::Fleet;
+use eu6_simulation::turns::Turn;
+use eu6_simulation::war::{Occupied, WarStatus};
+
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::egui::{Color32, RichText, TextureId};
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass, EguiTextureHandle};
-use std::collections::{HashMap, HashSet};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, EguiTextureHandle, egui};
+use eu6_simulation::country::{Coffer, Country, DisplayName, FlagFile, MapColor, reload_countries};
+use std::collections::HashMap;
 
-pub struct CountryPlugin;
+pub struct CountryUiPlugin;
 
-impl Plugin for CountryPlugin {
+impl Plugin for CountryUiPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SelectedCountry::default())
             .insert_resource(CountryFlags::default())
-            .add_systems(
-                Startup,
-                setup_countries_from_map.after(crate::map::generate_map),
-            )
-            .add_systems(
-                Startup,
-                assign_province_ownership
-                    .after(crate::map::generate_map)
-                    .after(setup_countries_from_map),
-            )
+            .add_systems(Update, load_flags)
+            .add_systems(Update, recolor_units.after(reload_countries))
             .add_systems(
                 EguiPrimaryContextPass,
                 display_country_panel.run_if(in_state(MenuState::InGame)),
@@ -33,18 +42,7 @@ impl Plugin for CountryPlugin {
     }
 }
 
-/// Marker component for country entities. No data as I am trying to do ECS :P.
-#[derive(Component)]
-pub(crate) struct Country {}
-
-/// Components representing the name and map color of a faction. They are not attached to the
-/// country entity, as things like rebels may have names/colors but not be countries.
-#[derive(Component)]
-pub(crate) struct DisplayName(pub(crate) String);
-#[derive(Component)]
-pub(crate) struct MapColor(pub(crate) Color);
-
-/// Component storing the flag texture handle for a country
+/// Component storing the flag texture handle for a country, loaded from its [`FlagFile`].
 #[derive(Component)]
 pub(crate) struct Flag(pub(crate) Handle<Image>);
 
@@ -54,22 +52,11 @@ pub(crate) struct CountryFlags {
     pub(crate) textures: HashMap<Entity, TextureId>,
 }
 
-/// Component representing the amount of gold a country has.
-#[derive(Component)]
-pub(crate) struct Coffer(pub(crate) f32);
-
-impl Coffer {
-    pub(crate) fn add_ducats(&mut self, ducats: f32) {
-        self.0 += ducats;
-    }
-
-    pub(crate) fn remove_ducats(&mut self, ducats: f32) {
-        self.0 -= ducats;
-    }
-
-    pub(crate) fn get_ducats(&self) -> f32 {
-        self.0
-    }
+/// Flag textures of the countries and the images they are made from.
+#[derive(SystemParam)]
+pub(crate) struct FlagTextures<'w> {
+    country_flags: ResMut<'w, CountryFlags>,
+    images: Res<'w, Assets<Image>>,
 }
 
 #[derive(Resource, Default)]
@@ -91,141 +78,226 @@ impl SelectedCountry {
     }
 }
 
-#[derive(Bundle)]
-pub(crate) struct CountryBundle {
-    country: Country,
-    name: DisplayName,
-    color: MapColor,
-    coffer: Coffer,
-}
-
-impl CountryBundle {
-    fn new(name: &str, color: Color) -> Self {
-        CountryBundle {
-            country: Country {},
-            name: DisplayName(name.to_string()),
-            color: MapColor(color),
-            coffer: Coffer(0.0),
+/// Repaints the armies and fleets of the countries whose map color changed.
+fn recolor_units(
+    countries: Query<(Entity, &MapColor), (With<Country>, Changed<MapColor>)>,
+    mut armies: Query<(&Owner, &mut Sprite), With<Army>>,
+    mut fleets: Query<(&Owner, &Fleet, &mut Sprite), Without<Army>>,
+) {
+    for (country, color) in &countries {
+        for (_, mut sprite) in armies.iter_mut().filter(|(owner, _)| owner.0 == country) {
+            sprite.color = color.0.darker(0.2);
+        }
+        for (_, fleet, mut sprite) in fleets.iter_mut().filter(|(owner, ..)| owner.0 == country) {
+            sprite.color = fleet.ships.color(color.0);
         }
     }
 }
 
-/// Setup countries from map data - creates country entities based on what's in the map file
-pub(crate) fn setup_countries_from_map(
-    mut commands: Commands,
-    map_data: Res<MapData>,
-    asset_server: Res<AssetServer>,
-) {
-    info!(
-        "Setting up {} countries from map data",
-        map_data.countries.len()
-    );
+/// Enum for country panel tabs
+#[derive(Default, PartialEq, Clone, Copy)]
+pub(crate) enum CountryTab {
+    #[default]
+    Info,
+    Provinces,
+    Diplomacy,
+}
 
-    for country_def in &map_data.countries {
-        let color = Color::srgb(
-            country_def.color[0],
-            country_def.color[1],
-            country_def.color[2],
-        );
-
-        // Load flag texture
-        let flag_handle: Handle<Image> = asset_server.load(&country_def.flag);
-
-        let entity = commands
-            .spawn(CountryBundle::new(&country_def.name, color))
-            .insert(Flag(flag_handle))
-            .id();
-
-        info!(
-            "Created country: {} ({:?}) with flag: {}",
-            country_def.name, entity, country_def.flag
-        );
-    }
+/// A province as listed in the provinces tab of the country panel.
+struct ProvinceRow {
+    entity: Entity,
+    name: String,
+    terrain: String,
+    income: f32,
+    buildings: usize,
+    occupied: bool,
 }
 
-/// System to assign province ownership to countries based on map data.
-/// This runs after both countries and provinces have been spawned.
-pub(crate) fn assign_province_ownership(
-    mut commands: Commands,
-    provinces: Query<(Entity, &Province)>,
-    countries: Query<(Entity, &DisplayName), With<Country>>,
-    map_data: Res<MapData>,
-) {
-    // Create a lookup from country name to entity
-    let country_lookup: HashMap<&str, Entity> = countries
-        .iter()
-        .map(|(entity, name)| (name.0.as_str(), entity))
-        .collect();
-
-    if country_lookup.is_empty() {
-        warn!("No countries found for province assignment!");
-        return;
+/// Provinces and armies of the countries, for the provinces tab of the country panel, and what
+/// it takes to select a province from it.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub(crate) struct ProvinceLedger<'w, 's> {
+    commands: Commands<'w, 's>,
+    provinces: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Province,
+            &'static Owner,
+            &'static Income,
+            Option<&'static Children>,
+            Option<&'static Occupied>,
+            Option<&'static Modifiers>,
+            Option<&'static Unrest>,
+            Option<&'static Devastation>,
+        ),
+    >,
+    buildings: Query<'w, 's, (), With<Building>>,
+    income_sources: IncomeSources<'w, 's>,
+    armies: Query<'w, 's, (&'static ArmyComposition, &'static Owner), With<Army>>,
+    selected_province: ResMut<'w, SelectedProvince>,
+    focus_events: MessageWriter<'w, CameraFocusEvent>,
+}
+
+impl ProvinceLedger<'_, '_> {
+    /// Provinces of the country by name, with the income the country collects from them.
+    fn rows(&self, country: Entity) -> Vec<ProvinceRow> {
+        let mut rows: Vec<ProvinceRow> = self
+            .provinces
+            .iter()
+            .filter(|(_, _, owner, ..)| owner.0 == country)
+            .map(
+                |(
+                    entity,
+                    province,
+                    _,
+                    income,
+                    children,
+                    occupied,
+                    modifiers,
+                    unrest,
+                    devastation,
+                )| {
+                    let income = self.income_sources.province(
+                        country,
+                        income,
+                        children,
+                        modifiers,
+                        unrest,
+                        occupied,
+                        devastation,
+                    );
+                    ProvinceRow {
+                        entity,
+                        name: province.name().to_string(),
+                        terrain: province.terrain().name.clone(),
+                        income: income.collected_by(country),
+                        buildings: children
+                            .into_iter()
+                            .flatten()
+                            .filter(|&&child| self.buildings.contains(child))
+                            .count(),
+                        occupied: occupied.is_some(),
+                    }
+                },
+            )
+            .collect();
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+        rows
     }
 
-    // Assign provinces based on map data
-    for (province_entity, province) in provinces.iter() {
-        let hex = province.get_hex();
+    fn army_size(&self, country: Entity) -> u32 {
+        self.armies
+            .iter()
+            .filter(|(_, owner)| owner.0 == country)
+            .map(|(composition, _)| composition.total_size())
+            .sum()
+    }
 
-        // Look up the owner from map data
-        if let Some(owner_name) = map_data.province_owners.get(hex) {
-            if let Some(&owner_entity) = country_lookup.get(owner_name.as_str()) {
-                commands.entity(province_entity).insert(Owner(owner_entity));
-            } else {
-                warn!(
-                    "Unknown country '{}' for province '{}'",
-                    owner_name,
-                    province.name()
-                );
-            }
+    /// Selects the province and centers the camera on it.
+    fn select(&mut self, entity: Entity) {
+        let Ok((_, province, ..)) = self.provinces.get(entity) else {
+            return;
+        };
+        if let Some(prev) = self.selected_province.get()
+            && prev != entity
+        {
+            self.commands.entity(prev).insert(InteractionState::None);
         }
-        // If no owner in map data, province stays unowned
+        self.commands
+            .entity(entity)
+            .insert(InteractionState::Selected);
+        self.selected_province.set(entity);
+        self.focus_events.write(CameraFocusEvent::new(
+            province.get_hex().axial_to_world(consts::HEX_SIZE),
+        ));
     }
-
-    info!("Province ownership assigned from map data");
 }
 
-/// Enum for country panel tabs
-#[derive(Default, PartialEq, Clone, Copy)]
-pub(crate) enum CountryTab {
-    #[default]
-    Info,
-    Diplomacy,
+/// Faith and league of the shown country, and what the player can do about them.
+struct ReligionInfo {
+    faith: Faith,
+    in_league: bool,
+    /// Whether the player's country can join its league right now.
+    can_join: bool,
+    /// Whether the player fights the shown country in a league war.
+    league_war: bool,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn display_country_panel(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
     mut selected_country: ResMut<SelectedCountry>,
-    countries: Query<(Entity, &DisplayName, &Coffer, &MapColor, Option<&Flag>), With<Country>>,
+    countries: Query<
+        (
+            Entity,
+            &DisplayName,
+            &Coffer,
+            &Manpower,
+            &MapColor,
+            Option<&Flag>,
+        ),
+        With<Country>,
+    >,
     player: Res<Player>,
-    war_relations: Query<&WarRelations>,
-    wars: Res<Wars>,
-    war_query: Query<(Entity, &War)>,
-    mut declare_war_events: MessageWriter<DeclareWarEvent>,
-    mut peace_offer_events: MessageWriter<PeaceOfferEvent>,
+    war_status: WarStatus,
+    mut player_commands: PlayerCommands,
     provinces: Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
     mut current_tab: Local<CountryTab>,
-    mut selected_provinces_for_peace: Local<HashSet<Entity>>,
-    mut country_flags: ResMut<CountryFlags>,
-    images: Res<Assets<Image>>,
+    mut drafts: DiplomacyDrafts,
+    mut flags: FlagTextures,
+    faiths: Query<(&Faith, Has<LeagueMember>)>,
+    leagues: Res<Leagues>,
+    turn: Res<Turn>,
+    diplomatic_ties: Query<(Option<&MilitaryAccess>, Option<&Alliances>)>,
+    mut ledger: ProvinceLedger,
 ) {
     let Some(country) = selected_country.get() else {
-        selected_provinces_for_peace.clear();
+        drafts.peace.close();
         return;
     };
 
-    let Ok((country_entity, name, coffer, color, maybe_flag)) = countries.get(country) else {
+    let Ok((country_entity, name, coffer, manpower, color, maybe_flag)) = countries.get(country)
+    else {
         return;
     };
 
     let is_player = Some(country) == player.country;
     let player_country = player.country;
-    let flag_texture_id = get_flag_texture(
-        &mut contexts,
-        &mut country_flags,
-        &images,
-        country_entity,
-        maybe_flag,
-    );
+    let (faith, in_league) = faiths
+        .get(country)
+        .map_or((Faith::default(), false), |(f, m)| (*f, m));
+    let league_faith = |country: Entity| {
+        faiths
+            .get(country)
+            .ok()
+            .filter(|(_, member)| *member)
+            .map(|(faith, _)| *faith)
+    };
+    let religion = ReligionInfo {
+        faith,
+        in_league,
+        can_join: is_player && !in_league && leagues.can_join(turn.current_turn()),
+        league_war: player_country.is_some_and(|player_country| {
+            leagues.are_league_enemies(league_faith(player_country), league_faith(country))
+        }),
+    };
+    let (has_military_access, alliance) = player_country
+        .and_then(|player_country| diplomatic_ties.get(player_country).ok())
+        .map_or((false, None), |(access, alliances)| {
+            (
+                access.is_some_and(|a| a.has_access_to(country)),
+                alliances
+                    .filter(|a| a.is_allied_with(country))
+                    .map(|a| Alliance {
+                        shared_vision: a.shares_vision_with(country),
+                    }),
+            )
+        });
+    let flag_texture_id = get_flag_texture(&mut contexts, &mut flags, country_entity, maybe_flag);
 
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
@@ -234,41 +306,62 @@ pub(crate) fn display_country_panel(
 
     render_country_window(
         ctx,
+        &theme,
         &name.0,
         coffer,
+        manpower,
         color,
         is_player,
         player_country,
         country_entity,
         flag_texture_id,
         &mut selected_country,
-        &mut selected_provinces_for_peace,
+        &mut drafts,
         &mut current_tab,
-        &war_relations,
-        &wars,
-        &war_query,
-        &mut declare_war_events,
-        &mut peace_offer_events,
+        &war_status,
+        &mut player_commands,
         &provinces,
+        &religion,
+        has_military_access,
+        alliance,
+        &mut ledger,
     );
 }
 
+/// Loads the flag textures of new countries and of countries whose flag changed, mods can replace
+/// the flags of the base game.
+fn load_flags(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    vfs: Res<VirtualFs>,
+    mut country_flags: ResMut<CountryFlags>,
+    countries: Query<(Entity, &FlagFile), Changed<FlagFile>>,
+) {
+    for (entity, file) in &countries {
+        let flag: Handle<Image> = asset_server.load(vfs.asset_path(&file.0));
+        commands.entity(entity).insert(Flag(flag));
+        country_flags.textures.remove(&entity);
+    }
+}
+
 fn get_flag_texture(
     contexts: &mut EguiContexts,
-    country_flags: &mut ResMut<CountryFlags>,
-    images: &Res<Assets<Image>>,
+    flags: &mut FlagTextures,
     country_entity: Entity,
     maybe_flag: Option<&Flag>,
 ) -> Option<TextureId> {
     let flag = maybe_flag?;
 
-    if let Some(&texture_id) = country_flags.textures.get(&country_entity) {
+    if let Some(&texture_id) = flags.country_flags.textures.get(&country_entity) {
         return Some(texture_id);
     }
 
-    if images.get(&flag.0).is_some() {
+    if flags.images.get(&flag.0).is_some() {
         let texture_id = contexts.add_image(EguiTextureHandle::Strong(flag.0.clone()));
-        country_flags.textures.insert(country_entity, texture_id);
+        flags
+            .country_flags
+            .textures
+            .insert(country_entity, texture_id);
         return Some(texture_id);
     }
 
@@ -277,65 +370,70 @@ fn get_flag_texture(
 
 fn render_country_window(
     ctx: &egui::Context,
+    theme: &UiTheme,
     name: &str,
     coffer: &Coffer,
+    manpower: &Manpower,
     color: &MapColor,
     is_player: bool,
     player_country: Option<Entity>,
     country_entity: Entity,
     flag_texture_id: Option<TextureId>,
     selected_country: &mut ResMut<SelectedCountry>,
-    selected_provinces_for_peace: &mut Local<HashSet<Entity>>,
+    drafts: &mut DiplomacyDrafts,
     current_tab: &mut Local<CountryTab>,
-    war_relations: &Query<&WarRelations>,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-    declare_war_events: &mut MessageWriter<DeclareWarEvent>,
-    peace_offer_events: &mut MessageWriter<PeaceOfferEvent>,
+    war_status: &WarStatus,
+    player_commands: &mut PlayerCommands,
     provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
+    religion: &ReligionInfo,
+    has_military_access: bool,
+    alliance: Option<Alliance>,
+    ledger: &mut ProvinceLedger,
 ) {
-    egui::Window::new("Country")
-        .frame(egui_common::default_frame())
-        .title_bar(false)
-        .anchor(egui::Align2::RIGHT_TOP, [-20.0, 20.0])
+    theme
+        .side_panel("Country", egui::Align2::RIGHT_TOP)
         .resizable(false)
         .default_width(280.0)
         .show(ctx, |ui| {
             render_country_header(
                 ui,
+                theme,
                 name,
                 is_player,
                 flag_texture_id,
                 selected_country,
-                selected_provinces_for_peace,
+                &mut drafts.peace,
             );
             render_country_tabs(ui, current_tab, is_player, player_country.is_some());
             render_country_content(
                 ui,
                 coffer,
+                manpower,
                 color,
                 is_player,
                 player_country,
                 country_entity,
                 current_tab,
-                war_relations,
-                wars,
-                war_query,
-                declare_war_events,
-                peace_offer_events,
+                war_status,
+                player_commands,
                 provinces,
-                selected_provinces_for_peace,
+                drafts,
+                religion,
+                has_military_access,
+                alliance,
+                ledger,
             );
         });
 }
 
 fn render_country_header(
     ui: &mut egui::Ui,
+    theme: &UiTheme,
     name: &str,
     is_player: bool,
     flag_texture_id: Option<TextureId>,
     selected_country: &mut ResMut<SelectedCountry>,
-    selected_provinces_for_peace: &mut Local<HashSet<Entity>>,
+    peace_draft: &mut PeaceDraft,
 ) {
     ui.horizontal(|ui| {
         if let Some(texture_id) = flag_texture_id {
@@ -360,9 +458,9 @@ fn render_country_header(
         }
 
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-            if egui_common::close_button(ui) {
+            if theme.close_button(ui) {
                 selected_country.clear();
-                selected_provinces_for_peace.clear();
+                peace_draft.close();
             }
         });
     });
@@ -385,6 +483,12 @@ fn render_country_tabs(
         {
             **current_tab = CountryTab::Info;
         }
+        if ui
+            .selectable_label(**current_tab == CountryTab::Provinces, "🗺 Provinces")
+            .clicked()
+        {
+            **current_tab = CountryTab::Provinces;
+        }
         if show_diplomacy
             && ui
                 .selectable_label(**current_tab == CountryTab::Diplomacy, "⚔ Diplomacy")
@@ -400,41 +504,75 @@ fn render_country_tabs(
 fn render_country_content(
     ui: &mut egui::Ui,
     coffer: &Coffer,
+    manpower: &Manpower,
     color: &MapColor,
-    _is_player: bool,
+    is_player: bool,
     player_country: Option<Entity>,
     country_entity: Entity,
     current_tab: &mut Local<CountryTab>,
-    war_relations: &Query<&WarRelations>,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-    declare_war_events: &mut MessageWriter<DeclareWarEvent>,
-    peace_offer_events: &mut MessageWriter<PeaceOfferEvent>,
+    war_status: &WarStatus,
+    player_commands: &mut PlayerCommands,
     provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
-    selected_provinces_for_peace: &mut Local<HashSet<Entity>>,
+    drafts: &mut DiplomacyDrafts,
+    religion: &ReligionInfo,
+    has_military_access: bool,
+    alliance: Option<Alliance>,
+    ledger: &mut ProvinceLedger,
 ) {
     match **current_tab {
-        CountryTab::Info => render_info_tab(ui, coffer, color),
+        CountryTab::Info => {
+            drafts.peace.close();
+            render_info_tab(
+                ui,
+                coffer,
+                manpower,
+                color,
+                is_player.then_some(country_entity),
+                religion,
+                player_commands,
+            );
+            if religion.can_join
+                && ui
+                    .button(format!("✝ Join the {}", religion.faith.league_name()))
+                    .on_hover_text("League members go to war together against the other faith")
+                    .clicked()
+            {
+                player_commands.join_league(country_entity);
+            }
+        }
+        CountryTab::Provinces => {
+            drafts.peace.close();
+            render_provinces_tab(ui, country_entity, ledger);
+        }
         CountryTab::Diplomacy => {
             if let Some(player_country) = player_country {
                 draw_diplomacy_tab(
                     ui,
                     player_country,
                     country_entity,
-                    war_relations,
-                    wars,
-                    war_query,
-                    declare_war_events,
-                    peace_offer_events,
+                    religion.league_war,
+                    has_military_access,
+                    alliance,
+                    war_status,
+                    player_commands,
                     provinces,
-                    selected_provinces_for_peace,
+                    drafts,
                 );
             }
         }
     }
 }
 
-fn render_info_tab(ui: &mut egui::Ui, coffer: &Coffer, color: &MapColor) {
+/// Treasury, manpower, map color and faith of the country. The player's own country can pick its map color.
+fn render_info_tab(
+    ui: &mut egui::Ui,
+    coffer: &Coffer,
+    manpower: &Manpower,
+    color: &MapColor,
+    own_country: Option<Entity>,
+    religion: &ReligionInfo,
+    player_commands: &mut PlayerCommands,
+) {
     egui::Grid::new("country_stats")
         .num_columns(2)
         .spacing([20.0, 8.0])
@@ -443,9 +581,104 @@ fn render_info_tab(ui: &mut egui::Ui, coffer: &Coffer, color: &MapColor) {
             ui.label(RichText::new(format!("{:.2}g", coffer.0)).color(Color32::GOLD));
             ui.end_row();
 
+            ui.label(RichText::new("Manpower").color(Color32::LIGHT_GRAY))
+                .on_hover_text("Raises regiments and refills armies resting in our provinces");
+            ui.label(manpower.0.to_string());
+            ui.end_row();
+
             ui.label(RichText::new("Map Color").color(Color32::LIGHT_GRAY));
-            let [r, g, b] = color.0.to_srgba().to_f32_array_no_alpha();
-            ui.color_edit_button_rgb(&mut [r, g, b]);
+            ui.add_enabled_ui(own_country.is_some(), |ui| {
+                // The picker shows the color being picked, the country only takes it once the
+                // player lets go, instead of with every frame of a drag.
+                let preview = ui.make_persistent_id("map_color_preview");
+                let mut rgb = ui
+                    .data(|data| data.get_temp(preview))
+                    .unwrap_or_else(|| color.0.to_srgba().to_f32_array_no_alpha());
+                let response = ui
+                    .color_edit_button_rgb(&mut rgb)
+                    .on_hover_text("Countries in nearly the same color can't be told apart");
+                if response.changed() {
+                    ui.data_mut(|data| data.insert_temp(preview, rgb));
+                } else if let Some(country) = own_country
+                    && ui.data(|data| data.get_temp::<[f32; 3]>(preview)).is_some()
+                    && !ui.input(|input| input.pointer.any_down())
+                {
+                    player_commands.set_map_color(country, rgb);
+                    ui.data_mut(|data| data.remove::<[f32; 3]>(preview));
+                }
+            });
+            ui.end_row();
+
+            ui.label(RichText::new("Faith").color(Color32::LIGHT_GRAY));
+            ui.label(religion.faith.name());
+            ui.end_row();
+
+            if religion.in_league {
+                ui.label(RichText::new("League").color(Color32::LIGHT_GRAY));
+                ui.label(RichText::new(religion.faith.league_name()).color(Color32::GOLD));
+                ui.end_row();
+            }
+        });
+}
+
+/// Totals of the country and a list of its provinces, clicking one selects it.
+fn render_provinces_tab(ui: &mut egui::Ui, country: Entity, ledger: &mut ProvinceLedger) {
+    let rows = ledger.rows(country);
+    let occupied = rows.iter().filter(|row| row.occupied).count();
+    let income: f32 = rows.iter().map(|row| row.income).sum();
+
+    egui::Grid::new("country_totals")
+        .num_columns(2)
+        .spacing([20.0, 8.0])
+        .show(ui, |ui| {
+            ui.label(RichText::new("Provinces").color(Color32::LIGHT_GRAY));
+            if occupied > 0 {
+                ui.label(format!("{} ({} occupied)", rows.len(), occupied));
+            } else {
+                ui.label(rows.len().to_string());
+            }
             ui.end_row();
+
+            ui.label(RichText::new("Income").color(Color32::LIGHT_GRAY));
+            ui.label(RichText::new(format!("{:.2}g", income)).color(Color32::GOLD));
+            ui.end_row();
+
+            ui.label(RichText::new("Army size").color(Color32::LIGHT_GRAY));
+            ui.label(ledger.army_size(country).to_string());
+            ui.end_row();
+        });
+    ui.separator();
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("country_provinces")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in ["Province", "Terrain", "Income", "Buildings", ""] {
+                        ui.label(RichText::new(header).color(Color32::LIGHT_GRAY));
+                    }
+                    ui.end_row();
+
+                    for row in &rows {
+                        if ui.link(row.name.as_str()).clicked() {
+                            clicked = Some(row.entity);
+                        }
+                        ui.label(row.terrain.as_str());
+                        ui.label(RichText::new(format!("{:.2}g", row.income)).color(Color32::GOLD));
+                        ui.label(row.buildings.to_string());
+                        if row.occupied {
+                            ui.label(RichText::new("⚔ Occupied").color(Color32::RED));
+                        } else {
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+                });
         });
+    if let Some(province) = clicked {
+        ledger.select(province);
+    }
 }
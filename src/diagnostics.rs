@@ -0,0 +1,155 @@
+﻿use crate::egui_common::UiTheme;
+use crate::settings::{Settings, dragged_to};
+use bevy::diagnostic::{
+    DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    SystemInformationDiagnosticsPlugin,
+};
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::MOVE_ACTIVE_ARMIES_TIME;
+use eu6_simulation::army::{Army, Battle};
+use eu6_simulation::map::Province;
+
+pub struct DiagnosticsOverlayPlugin;
+
+impl Plugin for DiagnosticsOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            FrameTimeDiagnosticsPlugin::default(),
+            EntityCountDiagnosticsPlugin::default(),
+            SystemInformationDiagnosticsPlugin,
+        ))
+        .insert_resource(DiagnosticsOverlayOpen(false))
+        .add_systems(Update, toggle_diagnostics_overlay)
+        .add_systems(
+            EguiPrimaryContextPass,
+            display_diagnostics_overlay.run_if(|open: Res<DiagnosticsOverlayOpen>| open.0),
+        );
+    }
+}
+
+/// Time spent in [`crate::map::update_province_colors`], measured every frame.
+pub(crate) const UPDATE_PROVINCE_COLORS_TIME: DiagnosticPath =
+    DiagnosticPath::const_new("game/update_province_colors");
+
+/// Name the diagnostics overlay's layout is kept under in the settings.
+const DIAGNOSTICS_WINDOW: &str = "diagnostics";
+
+/// Resource telling whether the diagnostics overlay (F3) is shown.
+#[derive(Resource)]
+pub(crate) struct DiagnosticsOverlayOpen(pub(crate) bool);
+
+fn toggle_diagnostics_overlay(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DiagnosticsOverlayOpen>,
+) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        overlay.0 = !overlay.0;
+    }
+}
+
+fn display_diagnostics_overlay(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut settings: ResMut<Settings>,
+    diagnostics: Res<DiagnosticsStore>,
+    provinces: Query<(), With<Province>>,
+    armies: Query<(), With<Army>>,
+    battles: Query<(), With<Battle>>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let window = egui::Window::new("Diagnostics")
+        .frame(theme.frame())
+        .title_bar(false)
+        .resizable(false);
+    let shown = settings
+        .place_window(
+            window,
+            DIAGNOSTICS_WINDOW,
+            ctx,
+            Align2::RIGHT_TOP,
+            [-20.0, 80.0],
+        )
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new("Diagnostics (F3)")
+                    .strong()
+                    .color(Color32::GOLD),
+            );
+            ui.separator();
+            egui::Grid::new("diagnostics_grid")
+                .num_columns(2)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    let smoothed =
+                        |path: &DiagnosticPath| diagnostics.get(path).and_then(|d| d.smoothed());
+                    let latest =
+                        |path: &DiagnosticPath| diagnostics.get(path).and_then(|d| d.value());
+
+                    diagnostic_row(ui, "FPS", smoothed(&FrameTimeDiagnosticsPlugin::FPS), "");
+                    diagnostic_row(
+                        ui,
+                        "Frame time",
+                        smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME),
+                        " ms",
+                    );
+                    diagnostic_row(
+                        ui,
+                        "Entities",
+                        latest(&EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+                        "",
+                    );
+                    count_row(ui, "Provinces", provinces.iter().len());
+                    count_row(ui, "Armies", armies.iter().len());
+                    count_row(ui, "Battles", battles.iter().len());
+                    diagnostic_row(
+                        ui,
+                        "update_province_colors",
+                        smoothed(&UPDATE_PROVINCE_COLORS_TIME),
+                        " ms",
+                    );
+                    diagnostic_row(
+                        ui,
+                        "move_active_armies",
+                        latest(&MOVE_ACTIVE_ARMIES_TIME),
+                        " ms",
+                    );
+                    diagnostic_row(
+                        ui,
+                        "Process memory",
+                        latest(&SystemInformationDiagnosticsPlugin::PROCESS_MEM_USAGE),
+                        " GiB",
+                    );
+                    diagnostic_row(
+                        ui,
+                        "System memory",
+                        latest(&SystemInformationDiagnosticsPlugin::SYSTEM_MEM_USAGE),
+                        " %",
+                    );
+                });
+        });
+
+    if let Some(pos) = dragged_to(&shown) {
+        settings.remember_window_pos(DIAGNOSTICS_WINDOW, pos);
+    }
+}
+
+fn diagnostic_row(ui: &mut egui::Ui, label: &str, value: Option<f64>, suffix: &str) {
+    ui.label(RichText::new(label).color(Color32::LIGHT_GRAY));
+    match value {
+        Some(value) => ui.label(format!("{:.2}{}", value, suffix)),
+        None => ui.label("–"),
+    };
+    ui.end_row();
+}
+
+fn count_row(ui: &mut egui::Ui, label: &str, count: usize) {
+    ui.label(RichText::new(label).color(Color32::LIGHT_GRAY));
+    ui.label(count.to_string());
+    ui.end_row();
+}
@@ -0,0 +1,584 @@
+﻿use crate::country::SelectedCountry;
+use crate::egui_common::UiTheme;
+use crate::map::MapMode;
+use crate::settings::Settings;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::colonization::Explored;
+use eu6_simulation::consts;
+use eu6_simulation::country::DisplayName;
+use eu6_simulation::diplomacy::{Alliances, CallToArms, MilitaryAccess, ProvinceOffer};
+use eu6_simulation::map::{Owner, Province, capitals};
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::{PlayerCommand, PlayerCommands};
+use eu6_simulation::war::{Occupied, Vassal, WarRelations};
+
+pub struct DiplomacyUiPlugin;
+
+impl Plugin for DiplomacyUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ProvinceOfferDraft::default())
+            .init_resource::<DiplomacyMapFilters>()
+            .add_systems(
+                Update,
+                draw_relation_lines.run_if(resource_equals(MapMode::Diplomacy)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                (display_province_offers_panel, display_calls_to_arms_panel),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_diplomacy_filters_panel.run_if(resource_equals(MapMode::Diplomacy)),
+            );
+    }
+}
+
+/// Highest price the player can ask for a province.
+const MAX_PROVINCE_PRICE: f32 = 500.0;
+
+/// Relations shown by the diplomacy map mode, toggled with the buttons next to the map modes.
+#[derive(Resource)]
+pub(crate) struct DiplomacyMapFilters {
+    pub(crate) wars: bool,
+    pub(crate) alliances: bool,
+    /// Overlords and vassals.
+    pub(crate) subjects: bool,
+    /// Military access granted either way.
+    pub(crate) access: bool,
+}
+
+impl Default for DiplomacyMapFilters {
+    fn default() -> Self {
+        DiplomacyMapFilters {
+            wars: true,
+            alliances: true,
+            subjects: true,
+            access: true,
+        }
+    }
+}
+
+/// How a country stands with the country the diplomacy map mode is centered on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Relation {
+    /// The country the map is centered on.
+    Focus,
+    War,
+    Alliance,
+    Overlord,
+    Vassal,
+    Access,
+}
+
+impl Relation {
+    pub(crate) fn color(self) -> Color {
+        match self {
+            Relation::Focus => Color::srgb(0.95, 0.85, 0.3),
+            Relation::War => Color::srgb(0.85, 0.1, 0.1),
+            Relation::Alliance => Color::srgb(0.2, 0.45, 0.95),
+            Relation::Overlord => Color::srgb(0.55, 0.25, 0.8),
+            Relation::Vassal => Color::srgb(0.8, 0.55, 0.95),
+            Relation::Access => Color::srgb(0.3, 0.8, 0.4),
+        }
+    }
+}
+
+/// Relations of the country the diplomacy map mode is centered on, the selected country or else
+/// the player's.
+#[derive(SystemParam)]
+pub(crate) struct DiplomaticRelations<'w, 's> {
+    filters: Res<'w, DiplomacyMapFilters>,
+    selected: Res<'w, SelectedCountry>,
+    player: Res<'w, Player>,
+    countries: Query<
+        'w,
+        's,
+        (
+            Option<&'static WarRelations>,
+            Option<&'static Alliances>,
+            Option<&'static Vassal>,
+            Option<&'static MilitaryAccess>,
+        ),
+    >,
+}
+
+impl DiplomaticRelations<'_, '_> {
+    pub(crate) fn focus(&self) -> Option<Entity> {
+        self.selected.get().or(self.player.country)
+    }
+
+    /// How `country` stands with the focus, among the relations the filters show. A war outweighs
+    /// an alliance, which outweighs being overlord or vassal, which outweighs military access.
+    pub(crate) fn relation(&self, country: Entity) -> Option<Relation> {
+        let focus = self.focus()?;
+        if country == focus {
+            return Some(Relation::Focus);
+        }
+        let (wars, alliances, vassal, access) = self.countries.get(focus).ok()?;
+        let (_, _, their_vassal, their_access) = self.countries.get(country).ok()?;
+        let filters = &self.filters;
+        if filters.wars && wars.is_some_and(|wars| wars.is_at_war_with(country)) {
+            Some(Relation::War)
+        } else if filters.alliances && alliances.is_some_and(|a| a.is_allied_with(country)) {
+            Some(Relation::Alliance)
+        } else if filters.subjects && vassal.is_some_and(|v| v.overlord == country) {
+            Some(Relation::Overlord)
+        } else if filters.subjects && their_vassal.is_some_and(|v| v.overlord == focus) {
+            Some(Relation::Vassal)
+        } else if filters.access
+            && (access.is_some_and(|a| a.has_access_to(country))
+                || their_access.is_some_and(|a| a.has_access_to(focus)))
+        {
+            Some(Relation::Access)
+        } else {
+            None
+        }
+    }
+}
+
+/// The player's alliance with the country shown in the diplomacy tab.
+#[derive(Clone, Copy)]
+pub(crate) struct Alliance {
+    pub(crate) shared_vision: bool,
+}
+
+/// Province trade the player is putting together in the diplomacy tab.
+#[derive(Resource, Default)]
+pub(crate) struct ProvinceOfferDraft {
+    province: Option<Entity>,
+    price: f32,
+}
+
+/// Draws a line from the capital of the country the diplomacy map mode is centered on to the
+/// capitals of the countries it has a shown relation with.
+fn draw_relation_lines(
+    mut gizmos: Gizmos,
+    relations: DiplomaticRelations,
+    settings: Res<Settings>,
+    explored: Res<Explored>,
+    provinces: Query<(&Province, &Owner)>,
+) {
+    let Some(focus) = relations.focus() else {
+        return;
+    };
+    let capitals = capitals(provinces);
+    let Some(start) = capitals.get(&focus) else {
+        return;
+    };
+    let start = start.axial_to_world(consts::HEX_SIZE);
+    for (&country, capital) in &capitals {
+        if !explored.contains(capital) {
+            continue;
+        }
+        let Some(relation) = relations.relation(country) else {
+            continue;
+        };
+        if relation != Relation::Focus {
+            let color = settings.map_palette.apply(relation.color());
+            gizmos.line_2d(start, capital.axial_to_world(consts::HEX_SIZE), color);
+        }
+    }
+}
+
+// ============================================================================
+// UI - DIPLOMACY MAP MODE FILTERS
+// ============================================================================
+
+fn display_diplomacy_filters_panel(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
+    mut filters: ResMut<DiplomacyMapFilters>,
+    selected: Res<SelectedCountry>,
+    player: Res<Player>,
+    countries: Query<&DisplayName>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    let focus = selected
+        .get()
+        .or(player.country)
+        .and_then(|country| countries.get(country).ok());
+    let color = |relation: Relation| {
+        let [r, g, b, _] = settings
+            .map_palette
+            .apply(relation.color())
+            .to_srgba()
+            .to_u8_array();
+        Color32::from_rgb(r, g, b)
+    };
+
+    // Next to the column of map mode buttons
+    egui::Area::new(egui::Id::new("diplomacy_filters"))
+        .anchor(Align2::RIGHT_BOTTOM, [-60.0, 0.0])
+        .show(ctx, |ui| {
+            theme.frame().show(ui, |ui| {
+                if let Some(name) = focus {
+                    ui.label(RichText::new(format!("Relations of {}", name.0)).strong());
+                } else {
+                    ui.label("Select a country to see its relations");
+                }
+                ui.separator();
+                ui.toggle_value(
+                    &mut filters.wars,
+                    RichText::new("⚔ Wars").color(color(Relation::War)),
+                );
+                ui.toggle_value(
+                    &mut filters.alliances,
+                    RichText::new("🤝 Alliances").color(color(Relation::Alliance)),
+                );
+                ui.toggle_value(
+                    &mut filters.subjects,
+                    RichText::new("👑 Overlord and vassals").color(color(Relation::Vassal)),
+                );
+                ui.toggle_value(
+                    &mut filters.access,
+                    RichText::new("🚶 Military access").color(color(Relation::Access)),
+                );
+            });
+        });
+}
+
+// ============================================================================
+// UI - PROVINCE OFFERS PANEL
+// ============================================================================
+
+pub(crate) fn display_province_offers_panel(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    player: Res<Player>,
+    offers: Query<(Entity, &ProvinceOffer)>,
+    countries: Query<&DisplayName>,
+    provinces: Query<&Province>,
+    mut player_commands: PlayerCommands,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+    let player_offers: Vec<_> = offers
+        .iter()
+        .filter(|(_, offer)| offer.to == player_country)
+        .collect();
+    if player_offers.is_empty() {
+        return;
+    }
+
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Province Offers")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .default_width(350.0)
+        .show(ctx, |ui| {
+            ui.heading("🏰 Province Offer");
+            ui.separator();
+            for (offer_entity, offer) in player_offers {
+                let from_name = countries
+                    .get(offer.from)
+                    .map(|n| n.0.as_str())
+                    .unwrap_or("Unknown");
+                let province_name = provinces
+                    .get(offer.province)
+                    .map(|p| p.name())
+                    .unwrap_or("Unknown");
+                if offer.price > 0.0 {
+                    ui.label(format!(
+                        "{} offers to sell us {} for {:.0} ducats.",
+                        from_name, province_name, offer.price
+                    ));
+                } else {
+                    ui.label(format!(
+                        "{} offers us {} as a gift.",
+                        from_name, province_name
+                    ));
+                }
+                ui.add_space(8.0);
+                let hex = provinces.get(offer.province).map(|p| *p.get_hex()).ok();
+                let answered = player_commands.queued(|command| {
+                    matches!(command, PlayerCommand::AnswerProvinceOffer { province, .. }
+                        if Some(*province) == hex)
+                });
+                if answered {
+                    ui.label(
+                        RichText::new("Your answer is carried out when the turn ends.")
+                            .color(Color32::LIGHT_GRAY),
+                    );
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("✓ Accept").clicked() {
+                            player_commands.answer_province_offer(offer_entity, true);
+                        }
+                        if ui.button("✗ Decline").clicked() {
+                            player_commands.answer_province_offer(offer_entity, false);
+                        }
+                    });
+                }
+                ui.separator();
+            }
+        });
+}
+
+// ============================================================================
+// UI - CALLS TO ARMS PANEL
+// ============================================================================
+
+pub(crate) fn display_calls_to_arms_panel(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    player: Res<Player>,
+    calls: Query<(Entity, &CallToArms)>,
+    countries: Query<&DisplayName>,
+    mut player_commands: PlayerCommands,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+    let player_calls: Vec<_> = calls
+        .iter()
+        .filter(|(_, call)| call.to == player_country)
+        .collect();
+    if player_calls.is_empty() {
+        return;
+    }
+
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Calls to Arms")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .default_width(350.0)
+        .show(ctx, |ui| {
+            ui.heading("🤝 Call to Arms");
+            ui.separator();
+            for (call, call_to_arms) in player_calls {
+                let name = |country: Entity| {
+                    countries
+                        .get(country)
+                        .map(|n| n.0.as_str())
+                        .unwrap_or("Unknown")
+                };
+                ui.label(format!(
+                    "Our ally {} was attacked by {} and calls us to arms.",
+                    name(call_to_arms.from),
+                    name(call_to_arms.enemy)
+                ));
+                ui.add_space(8.0);
+                let ally = player_commands.country_name(call_to_arms.from);
+                let attacker = player_commands.country_name(call_to_arms.enemy);
+                let answered = player_commands.queued(|command| {
+                    matches!(command, PlayerCommand::AnswerCallToArms { from, enemy, .. }
+                        if Some(from) == ally.as_ref() && Some(enemy) == attacker.as_ref())
+                });
+                if answered {
+                    ui.label(
+                        RichText::new("Your answer is carried out when the turn ends.")
+                            .color(Color32::LIGHT_GRAY),
+                    );
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("⚔ Join the war").clicked() {
+                            player_commands.answer_call_to_arms(call, true);
+                        }
+                        if ui
+                            .button("✗ Decline")
+                            .on_hover_text("Declining ends the alliance")
+                            .clicked()
+                        {
+                            player_commands.answer_call_to_arms(call, false);
+                        }
+                    });
+                }
+                ui.separator();
+            }
+        });
+}
+
+// ============================================================================
+// UI - DIPLOMACY TAB
+// ============================================================================
+
+/// Shows whether the player's armies may march through the target country, or lets them ask.
+pub(crate) fn draw_military_access_section(
+    ui: &mut egui::Ui,
+    player_country: Entity,
+    target_country: Entity,
+    military_access: bool,
+    player_commands: &mut PlayerCommands,
+) {
+    if military_access {
+        ui.label(RichText::new("🛡 We have military access").color(Color32::GREEN));
+    } else if ui
+        .button("🛡 Request Military Access")
+        .on_hover_text("Countries let the armies of those sharing their faith through")
+        .clicked()
+    {
+        player_commands.request_military_access(player_country, target_country);
+    }
+}
+
+/// Shows whether the target country is the player's ally, and lets them form or break the
+/// alliance and share their vision.
+pub(crate) fn draw_alliance_section(
+    ui: &mut egui::Ui,
+    player_country: Entity,
+    target_country: Entity,
+    alliance: Option<Alliance>,
+    player_commands: &mut PlayerCommands,
+) {
+    if let Some(alliance) = alliance {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🤝 Allies").color(Color32::GREEN));
+            if ui.button("Break Alliance").clicked() {
+                player_commands.break_alliance(player_country, target_country);
+            }
+        });
+        let mut shared_vision = alliance.shared_vision;
+        if ui
+            .checkbox(&mut shared_vision, "👁 Share vision")
+            .on_hover_text("Allies sharing their vision explore the map together")
+            .changed()
+        {
+            player_commands.share_vision(player_country, target_country, shared_vision);
+        }
+    } else if ui
+        .button("🤝 Propose Alliance")
+        .on_hover_text("Countries ally with those sharing their faith. Allies are called to arms when war is declared on them")
+        .clicked()
+    {
+        player_commands.propose_alliance(player_country, target_country);
+    }
+}
+
+/// Lets the player sell or gift one of their unoccupied provinces to the target country.
+pub(crate) fn draw_province_offer_section(
+    ui: &mut egui::Ui,
+    player_country: Entity,
+    target_country: Entity,
+    provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
+    draft: &mut ProvinceOfferDraft,
+    player_commands: &mut PlayerCommands,
+) {
+    let mut own_provinces: Vec<(Entity, &str)> = provinces
+        .iter()
+        .filter(|(_, _, owner, occupied)| owner.0 == player_country && occupied.is_none())
+        .map(|(entity, province, _, _)| (entity, province.name()))
+        .collect();
+    if own_provinces.is_empty() {
+        return;
+    }
+    own_provinces.sort_unstable_by_key(|(_, name)| *name);
+
+    ui.separator();
+    ui.label(RichText::new("Province Trade:").strong());
+    let selected_name = draft
+        .province
+        .and_then(|selected| own_provinces.iter().find(|(entity, _)| *entity == selected))
+        .map(|(_, name)| *name);
+    if selected_name.is_none() {
+        // The province was lost or traded away since it was picked.
+        draft.province = None;
+    }
+    egui::ComboBox::from_id_salt("province_offer")
+        .selected_text(selected_name.unwrap_or("Choose a province"))
+        .show_ui(ui, |ui| {
+            for (entity, name) in &own_provinces {
+                ui.selectable_value(&mut draft.province, Some(*entity), *name);
+            }
+        });
+    ui.add(
+        egui::Slider::new(&mut draft.price, 0.0..=MAX_PROVINCE_PRICE)
+            .step_by(10.0)
+            .text("ducats"),
+    );
+
+    let label = if draft.price > 0.0 {
+        format!("💰 Sell for {:.0} ducats", draft.price)
+    } else {
+        "🎁 Gift".to_string()
+    };
+    if ui
+        .add_enabled(draft.province.is_some(), egui::Button::new(label))
+        .on_hover_text("They only pay what the province is worth to them")
+        .clicked()
+        && let Some(province) = draft.province.take()
+    {
+        player_commands.offer_province(player_country, target_country, province, draft.price);
+    }
+    if draft.price == 0.0 {
+        ui.label(RichText::new("A gift is always accepted").color(Color32::GRAY));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use eu6_simulation::test_utils::TestGame;
+    use std::collections::HashSet;
+
+    #[test]
+    fn diplomacy_map_shows_the_filtered_relations_of_the_selected_country() {
+        let mut game = TestGame::new();
+        let focus = game.spawn_country("Focus");
+        let enemy = game.spawn_country("Enemy");
+        let ally = game.spawn_country("Ally");
+        let vassal = game.spawn_country("Vassal");
+        let stranger = game.spawn_country("Stranger");
+        game.declare_war(focus, enemy);
+        // An ally at war with the focus is shown as an enemy.
+        let alliances = Alliances::new(HashSet::from([ally, enemy]), HashSet::new());
+        game.world_mut().entity_mut(focus).insert(alliances);
+        game.world_mut()
+            .entity_mut(vassal)
+            .insert(Vassal { overlord: focus });
+        game.world_mut().init_resource::<DiplomacyMapFilters>();
+        game.world_mut().insert_resource(SelectedCountry::default());
+        game.world_mut()
+            .resource_mut::<SelectedCountry>()
+            .select(focus);
+        let countries = [focus, enemy, ally, vassal, stranger];
+        let relations = |game: &mut TestGame| {
+            game.world_mut()
+                .run_system_once(move |relations: DiplomaticRelations| {
+                    countries.map(|country| relations.relation(country))
+                })
+                .unwrap()
+        };
+
+        assert_eq!(
+            relations(&mut game),
+            [
+                Some(Relation::Focus),
+                Some(Relation::War),
+                Some(Relation::Alliance),
+                Some(Relation::Vassal),
+                None
+            ]
+        );
+
+        game.world_mut().resource_mut::<DiplomacyMapFilters>().wars = false;
+        game.world_mut()
+            .resource_mut::<DiplomacyMapFilters>()
+            .subjects = false;
+        assert_eq!(
+            relations(&mut game),
+            [
+                Some(Relation::Focus),
+                Some(Relation::Alliance),
+                Some(Relation::Alliance),
+                None,
+                None
+            ]
+        );
+    }
+}
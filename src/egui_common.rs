@@ -1,28 +1,192 @@
-﻿use bevy_egui::egui;
+﻿use bevy::prelude::*;
 use bevy_egui::egui::Color32;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::mods::VirtualFs;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-/// Reusable stylized frame (basically a Flutter 'Container' widget) for usage in most egui
-/// widgets in game.
-pub(crate) fn default_frame() -> egui::Frame {
-    egui::Frame::new()
-        .fill(Color32::from_rgb(25, 35, 60))
-        .stroke(egui::Stroke::new(2.0, Color32::from_rgb(180, 150, 80)))
-        .inner_margin(egui::Margin::same(20))
-        .corner_radius(egui::CornerRadius::same(12))
-        .shadow(egui::Shadow {
-            offset: [4, 4],
-            blur: 10,
-            spread: 0,
-            color: Color32::from_black_alpha(150),
+pub struct UiThemePlugin;
+
+impl Plugin for UiThemePlugin {
+    fn build(&self, app: &mut App) {
+        let theme = load_ui_theme(app.world().resource::<VirtualFs>(), UI_THEME_FILE_PATH);
+        app.insert_resource(theme)
+            .add_systems(EguiPrimaryContextPass, apply_ui_theme);
+    }
+}
+
+const UI_THEME_FILE_PATH: &str = "ui_theme.json";
+
+/// Look of the game's egui panels, loaded from [`UI_THEME_FILE_PATH`] so the UI can be reskinned
+/// without recompiling. Colors are RGBA, missing fields fall back to the default theme.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct UiTheme {
+    pub(crate) panel_fill: [u8; 4],
+    pub(crate) panel_stroke_color: [u8; 4],
+    pub(crate) panel_stroke_width: f32,
+    pub(crate) panel_margin: i8,
+    pub(crate) panel_corner_radius: u8,
+    pub(crate) panel_shadow_alpha: u8,
+    /// Background of the full screen menus (main menu, country selection, game setup).
+    pub(crate) menu_background: [u8; 4],
+    pub(crate) close_button_fill: [u8; 4],
+    pub(crate) text_color: Option<[u8; 4]>,
+    pub(crate) body_font_size: f32,
+    pub(crate) heading_font_size: f32,
+    pub(crate) button_font_size: f32,
+    /// Optional TTF/OTF file used as the primary proportional font.
+    pub(crate) font_path: Option<String>,
+    /// Swaps the sides of the panels, copied from the player's settings rather than the theme file.
+    #[serde(skip)]
+    pub(crate) mirror_panels: bool,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            panel_fill: [25, 35, 60, 255],
+            panel_stroke_color: [180, 150, 80, 255],
+            panel_stroke_width: 2.0,
+            panel_margin: 20,
+            panel_corner_radius: 12,
+            panel_shadow_alpha: 150,
+            menu_background: [10, 10, 20, 255],
+            close_button_fill: [200, 50, 50, 255],
+            text_color: None,
+            body_font_size: 12.5,
+            heading_font_size: 18.0,
+            button_font_size: 12.5,
+            font_path: None,
+            mirror_panels: false,
+        }
+    }
+}
+
+impl UiTheme {
+    /// Reusable stylized frame (basically a Flutter 'Container' widget) for usage in most egui
+    /// widgets in game.
+    pub(crate) fn frame(&self) -> egui::Frame {
+        egui::Frame::new()
+            .fill(color(self.panel_fill))
+            .stroke(egui::Stroke::new(
+                self.panel_stroke_width,
+                color(self.panel_stroke_color),
+            ))
+            .inner_margin(egui::Margin::same(self.panel_margin))
+            .corner_radius(egui::CornerRadius::same(self.panel_corner_radius))
+            .shadow(egui::Shadow {
+                offset: [4, 4],
+                blur: 10,
+                spread: 0,
+                color: Color32::from_black_alpha(self.panel_shadow_alpha),
+            })
+    }
+
+    /// Stylized window of a panel anchored to a top corner of the screen, the opposite one when
+    /// the panels are mirrored.
+    pub(crate) fn side_panel(&self, title: &str, anchor: egui::Align2) -> egui::Window<'static> {
+        let x = match (anchor.x(), self.mirror_panels) {
+            (egui::Align::Min, true) => egui::Align::Max,
+            (egui::Align::Max, true) => egui::Align::Min,
+            (x, _) => x,
+        };
+        let x_offset = if x == egui::Align::Max { -20.0 } else { 20.0 };
+        egui::Window::new(title)
+            .frame(self.frame())
+            .title_bar(false)
+            .anchor(egui::Align2([x, anchor.y()]), [x_offset, 20.0])
+    }
+
+    /// Frame filling the whole screen behind the menus.
+    pub(crate) fn menu_frame(&self) -> egui::Frame {
+        egui::Frame::new().fill(color(self.menu_background))
+    }
+
+    /// Reusable stylized close button, adjusted to the right of the rect. Screen readers announce
+    /// it as "Close" rather than by its label.
+    pub(crate) fn close_button(&self, ui: &mut egui::Ui) -> bool {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let response = ui
+                .add(egui::Button::new("X").fill(color(self.close_button_fill)))
+                .on_hover_text("Close");
+            response.widget_info(|| {
+                egui::WidgetInfo::labeled(egui::WidgetType::Button, response.enabled(), "Close")
+            });
+            response.clicked()
         })
+        .inner
+    }
+
+    /// Applies the theme's fonts and text color to the egui style.
+    fn apply(&self, ctx: &egui::Context, vfs: &VirtualFs) {
+        ctx.style_mut(|style| {
+            for (text_style, size) in [
+                (egui::TextStyle::Body, self.body_font_size),
+                (egui::TextStyle::Heading, self.heading_font_size),
+                (egui::TextStyle::Button, self.button_font_size),
+            ] {
+                if let Some(font) = style.text_styles.get_mut(&text_style) {
+                    font.size = size;
+                }
+            }
+            style.visuals.override_text_color = self.text_color.map(color);
+        });
+
+        let mut fonts = egui::FontDefinitions::default();
+        if let Some(path) = &self.font_path {
+            match vfs.read(path) {
+                Ok(bytes) => {
+                    fonts.font_data.insert(
+                        "theme".to_string(),
+                        Arc::new(egui::FontData::from_owned(bytes)),
+                    );
+                    fonts
+                        .families
+                        .entry(egui::FontFamily::Proportional)
+                        .or_default()
+                        .insert(0, "theme".to_string());
+                }
+                Err(e) => warn!("Failed to load theme font {}: {}", path, e),
+            }
+        }
+        ctx.set_fonts(fonts);
+    }
 }
 
-/// Reusable stylized close button, adjusted to the right of the rect.
-pub(crate) fn close_button(ui: &mut egui::Ui) -> bool {
-    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-        ui.add(egui::Button::new("X").fill(Color32::from_rgb(200, 50, 50)))
-            .on_hover_text("Close")
-            .clicked()
+fn color([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Loads the theme file, falling back to the default theme if it is missing or invalid.
+pub(crate) fn load_ui_theme(vfs: &VirtualFs, path: &str) -> UiTheme {
+    let Ok(content) = vfs.read_to_string(path) else {
+        info!("No UI theme found at {}, using the default theme", path);
+        return UiTheme::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!(
+            "Failed to parse UI theme {}, using the default theme: {}",
+            path, e
+        );
+        UiTheme::default()
     })
-    .inner
+}
+
+/// Pushes the theme into egui whenever it changes. The egui context isn't available on the very
+/// first frames, so the theme is applied once it is.
+fn apply_ui_theme(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    vfs: Res<VirtualFs>,
+    mut applied: Local<bool>,
+) {
+    if *applied && !theme.is_changed() {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    theme.apply(ctx, &vfs);
+    *applied = true;
 }
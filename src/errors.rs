@@ -0,0 +1,83 @@
+﻿use crate::egui_common::UiTheme;
+use bevy::prelude::*;
+use bevy_egui::egui::{Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::errors::GameError;
+
+pub struct ErrorsPlugin;
+
+impl Plugin for ErrorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ErrorScreen::default())
+            .add_systems(Update, collect_errors)
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_error_screen.run_if(|screen: Res<ErrorScreen>| screen.error.is_some()),
+            );
+    }
+}
+
+/// Resource holding the error currently shown on the error screen.
+#[derive(Resource, Default)]
+pub(crate) struct ErrorScreen {
+    error: Option<GameError>,
+}
+
+fn collect_errors(mut errors: MessageReader<GameError>, mut screen: ResMut<ErrorScreen>) {
+    for err in errors.read() {
+        error!("{}: {}", err.title, err.details);
+        // A fatal error stays on screen, whatever happens after it is most likely caused by it.
+        if screen.error.as_ref().is_some_and(|shown| shown.fatal) {
+            continue;
+        }
+        screen.error = Some(err.clone());
+    }
+}
+
+fn display_error_screen(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut screen: ResMut<ErrorScreen>,
+    mut app_exit: MessageWriter<AppExit>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    let Some(err) = &screen.error else {
+        return;
+    };
+
+    let mut dismissed = false;
+    egui::Modal::new(egui::Id::new("error_screen"))
+        .frame(theme.frame())
+        .backdrop_color(Color32::from_black_alpha(if err.fatal { 230 } else { 150 }))
+        .show(ctx, |ui| {
+            ui.set_max_width(400.0);
+            ui.vertical_centered(|ui| {
+                ui.heading(RichText::new(format!("⚠ {}", err.title)).color(Color32::LIGHT_RED));
+                ui.separator();
+                // Long enough for every problem found in a map file
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| ui.label(&err.details));
+                ui.add_space(10.0);
+                if err.fatal {
+                    ui.label(
+                        RichText::new("The game can't continue.")
+                            .italics()
+                            .color(Color32::GRAY),
+                    );
+                    if ui.button("❌ Quit").clicked() {
+                        app_exit.write(AppExit::error());
+                    }
+                } else if ui.button("OK").clicked() {
+                    dismissed = true;
+                }
+            });
+        });
+
+    if dismissed {
+        screen.error = None;
+    }
+}
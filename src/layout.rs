@@ -1,29 +1,146 @@
-﻿use crate::map;
-use crate::map::MapMode;
+﻿use crate::army::SelectedArmy;
+use crate::map::{MapMode, switch_map_mode};
+use crate::settings::Settings;
 use bevy::camera::{Camera2d, Projection};
-use bevy::input::mouse::MouseWheel;
+use bevy::ecs::system::SystemParam;
 use bevy::input::ButtonInput;
+use bevy::input::mouse::{MouseButton, MouseScrollUnit, MouseWheel};
 use bevy::log::info;
-use bevy::math::Vec3;
+use bevy::math::curve::{Curve, EaseFunction};
+use bevy::math::{FloatExt, Vec2, Vec3};
+use bevy::prelude::{Commands, Resource, Window, Without};
 use bevy::prelude::{
-    KeyCode, MessageReader, Plugin, Query, Res, ResMut, Single, Time, Transform, With,
+    KeyCode, Message, MessageReader, Plugin, Query, Res, ResMut, Single, Time, Transform, With,
 };
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::EguiWantsInput;
+use eu6_simulation::army::Army;
+use eu6_simulation::consts;
+use eu6_simulation::map::{Owner, Province, capitals};
+use eu6_simulation::player::Player;
 
 pub struct LayoutPlugin;
 
 impl Plugin for LayoutPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         use bevy::prelude::*;
-        app.add_systems(Update, camera_keyboard_system)
-            .add_systems(Update, camera_zoom_system);
+        app.add_message::<CameraFocusEvent>()
+            .add_systems(Startup, setup_camera)
+            .insert_resource(CameraDrag::default())
+            .insert_resource(CameraBookmarks::default())
+            .insert_resource(CameraControl::default())
+            .add_systems(Update, camera_keyboard_system)
+            .add_systems(Update, camera_zoom_system)
+            .add_systems(Update, camera_drag_system)
+            .add_systems(Update, camera_edge_scroll_system)
+            .add_systems(Update, camera_bookmark_system)
+            .add_systems(Update, camera_focus_system)
+            .add_systems(Update, camera_glide_system);
     }
 }
 
-/// System to handle keyboard input for moving the camera.
+fn setup_camera(mut commands: Commands) {
+    info!("Setting up camera");
+    commands.spawn(Camera2d);
+}
+
+/// Message requesting the camera to center on the given world position.
+#[derive(Message)]
+pub(crate) struct CameraFocusEvent {
+    pub(crate) position: Vec2,
+}
+
+impl CameraFocusEvent {
+    pub(crate) fn new(position: Vec2) -> Self {
+        Self { position }
+    }
+}
+
+/// Distance in logical pixels the cursor has to travel with a button held before it counts as a
+/// drag rather than a click.
+const DRAG_THRESHOLD: f32 = 5.0;
+
+/// Resource tracking the camera being dragged with the middle or right mouse button.
+#[derive(Resource, Default)]
+pub(crate) struct CameraDrag {
+    last_cursor: Option<Vec2>,
+    distance: f32,
+}
+
+impl CameraDrag {
+    /// Whether the current press moved far enough to be a drag. Right clicks that turned into a
+    /// drag shouldn't also issue a move order.
+    pub(crate) fn is_dragging(&self) -> bool {
+        self.distance > DRAG_THRESHOLD
+    }
+}
+
+/// Whether the pointer is over an egui window. Clicks there belong to the UI, and the map and army
+/// picking underneath has to ignore them.
+#[derive(SystemParam)]
+pub(crate) struct UiPointer<'w> {
+    egui_input: Option<Res<'w, EguiWantsInput>>,
+}
+
+impl UiPointer<'_> {
+    pub(crate) fn is_over_ui(&self) -> bool {
+        self.egui_input
+            .as_ref()
+            .is_some_and(|input| input.wants_any_pointer_input())
+    }
+}
+
+/// Duration in seconds of eased camera moves, e.g. when jumping to a notification or bookmark.
+const GLIDE_DURATION: f32 = 0.4;
+/// How quickly the camera catches up with a followed army, higher is snappier.
+const FOLLOW_SHARPNESS: f32 = 8.0;
+
+/// Eased camera move in progress.
+#[derive(Clone, Copy)]
+struct CameraGlide {
+    from: Vec2,
+    to: Vec2,
+    from_scale: f32,
+    to_scale: f32,
+    elapsed: f32,
+}
+
+/// Resource driving programmatic camera movement: eased jumps and following the selected army.
+/// Both stop as soon as the player moves the camera by hand.
+#[derive(Resource, Default)]
+pub(crate) struct CameraControl {
+    glide: Option<CameraGlide>,
+    /// Keep the selected army centered on screen.
+    pub(crate) follow_army: bool,
+}
+
+impl CameraControl {
+    fn glide_to(&mut self, from: Vec2, to: Vec2, from_scale: f32, to_scale: f32) {
+        self.glide = Some(CameraGlide {
+            from,
+            to,
+            from_scale,
+            to_scale,
+            elapsed: 0.0,
+        });
+        self.follow_army = false;
+    }
+
+    /// Stops every programmatic movement, called when the player pans the camera.
+    fn cancel(&mut self) {
+        self.glide = None;
+        self.follow_army = false;
+    }
+}
+
+/// System to handle keyboard input for moving the camera. S stops the selected army instead of
+/// panning down while an army is selected.
 pub(crate) fn camera_keyboard_system(
     keyboard: Res<ButtonInput<KeyCode>>,
+    selected_army: Res<SelectedArmy>,
     mut query: Query<&mut Transform, With<Camera2d>>,
     mut map_mode: ResMut<MapMode>,
+    mut control: ResMut<CameraControl>,
     time: Res<Time>,
 ) {
     let mut movement = Vec3::ZERO;
@@ -38,13 +155,21 @@ pub(crate) fn camera_keyboard_system(
     if keyboard.pressed(KeyCode::KeyD) {
         movement.x += speed;
     }
-    if keyboard.pressed(KeyCode::KeyS) {
+    if keyboard.pressed(KeyCode::KeyS) && selected_army.selected.is_none() {
         movement.y -= speed;
     }
 
     if keyboard.just_pressed(KeyCode::KeyM) {
         info!("Switching map mode");
-        map::switch_map_mode(&mut map_mode);
+        switch_map_mode(&mut map_mode);
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        control.follow_army = !control.follow_army;
+    }
+
+    if movement != Vec3::ZERO {
+        control.cancel();
     }
 
     for mut transform in &mut query {
@@ -52,19 +177,301 @@ pub(crate) fn camera_keyboard_system(
     }
 }
 
-/// System to handle mouse wheel events and zoom the camera in/out.
+/// Width in logical pixels of the band along the window edges that triggers edge scrolling.
+const EDGE_SCROLL_MARGIN: f32 = 10.0;
+
+/// System to pan the camera while the cursor rests near one of the window edges.
+pub(crate) fn camera_edge_scroll_system(
+    settings: Res<Settings>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut query: Query<&mut Transform, With<Camera2d>>,
+    mut control: ResMut<CameraControl>,
+    time: Res<Time>,
+) {
+    if !settings.edge_scrolling || !window.focused {
+        return;
+    }
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    let mut direction = Vec2::ZERO;
+    if cursor.x < EDGE_SCROLL_MARGIN {
+        direction.x -= 1.0;
+    }
+    if cursor.x > window.width() - EDGE_SCROLL_MARGIN {
+        direction.x += 1.0;
+    }
+    // Screen space y grows downwards, world space y upwards.
+    if cursor.y < EDGE_SCROLL_MARGIN {
+        direction.y += 1.0;
+    }
+    if cursor.y > window.height() - EDGE_SCROLL_MARGIN {
+        direction.y -= 1.0;
+    }
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    control.cancel();
+    let movement = direction.normalize() * settings.edge_scroll_speed * time.delta_secs();
+    for mut transform in &mut query {
+        transform.translation += movement.extend(0.0);
+    }
+}
+
+/// Smallest allowed projection scale, i.e. the closest the camera can zoom in.
+const MIN_ZOOM: f32 = 0.25;
+/// Largest allowed projection scale, i.e. the furthest the camera can zoom out.
+const MAX_ZOOM: f32 = 4.0;
+/// Fraction of the current scale changed by one line of scrolling.
+const ZOOM_STEP: f32 = 0.1;
+/// Pixel based scroll devices (touchpads) report this many pixels per line.
+const PIXELS_PER_LINE: f32 = 100.0;
+
+/// System to handle mouse wheel events and zoom the camera in/out. Zooming keeps the world
+/// position under the cursor in place, so the camera moves toward whatever the cursor points at.
 pub(crate) fn camera_zoom_system(
     mut scroll_events: MessageReader<MouseWheel>,
-    mut projection: Single<&mut Projection, With<Camera2d>>,
+    egui_input: Res<EguiWantsInput>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut control: ResMut<CameraControl>,
+    camera: Single<(&mut Transform, &mut Projection), With<Camera2d>>,
 ) {
+    // Scrolling over a panel scrolls the panel, not the map.
+    if egui_input.wants_any_pointer_input() {
+        scroll_events.clear();
+        return;
+    }
+
+    let (mut transform, mut projection) = camera.into_inner();
     for event in scroll_events.read() {
         // Since we are using 2d camera, the projection is Orthographic.
         let Projection::Orthographic(perspective) = projection.as_mut() else {
             return;
         };
 
-        // The event's y value contains 1.0 for scroll up and -1.0 for scroll down, so we subtract it
-        // to make scrolling up decrease the scale (zoom in) and scrolling down increase the scale (zoom out).
-        perspective.scale -= event.y * 0.1;
+        // Zooming only interrupts eased moves, a followed army stays followed.
+        control.glide = None;
+
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+        // Scrolling up gives positive values and should decrease the scale (zoom in).
+        let old_scale = perspective.scale;
+        let new_scale = (old_scale * (1.0 - lines * ZOOM_STEP)).clamp(MIN_ZOOM, MAX_ZOOM);
+        perspective.scale = new_scale;
+
+        let Some(cursor) = window.cursor_position() else {
+            continue;
+        };
+        // Offset of the cursor from the screen center, in world space at scale 1.
+        let offset = (cursor - window.size() / 2.0) * Vec2::new(1.0, -1.0);
+        let shift = offset * (old_scale - new_scale);
+        transform.translation += shift.extend(0.0);
+    }
+}
+
+/// System to pan the camera by dragging with the middle or right mouse button. The cursor delta is
+/// scaled by the current zoom, so the map stays under the cursor while dragging.
+pub(crate) fn camera_drag_system(
+    mouse: Res<ButtonInput<MouseButton>>,
+    egui_input: Res<EguiWantsInput>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut drag: ResMut<CameraDrag>,
+    mut control: ResMut<CameraControl>,
+    camera: Single<(&mut Transform, &Projection), With<Camera2d>>,
+) {
+    let buttons = [MouseButton::Middle, MouseButton::Right];
+    if !mouse.any_pressed(buttons) {
+        if drag.last_cursor.is_some() {
+            *drag = CameraDrag::default();
+        }
+        return;
+    }
+
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    if mouse.any_just_pressed(buttons) {
+        // Drags starting over a panel belong to the UI.
+        if !egui_input.wants_any_pointer_input() {
+            drag.last_cursor = Some(cursor);
+        }
+        return;
+    }
+
+    let Some(last_cursor) = drag.last_cursor else {
+        return;
+    };
+    let delta = cursor - last_cursor;
+    drag.last_cursor = Some(cursor);
+    drag.distance += delta.length();
+    if !drag.is_dragging() {
+        return;
     }
+    control.cancel();
+
+    let (mut transform, projection) = camera.into_inner();
+    let scale = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.0,
+    };
+    // Screen space y grows downwards, world space y upwards.
+    transform.translation.x -= delta.x * scale;
+    transform.translation.y += delta.y * scale;
+}
+
+/// Camera position and zoom saved in a bookmark slot.
+#[derive(Clone, Copy)]
+pub(crate) struct CameraBookmark {
+    position: Vec2,
+    scale: f32,
+}
+
+/// Resource holding the camera bookmarks stored with Ctrl+1..9.
+#[derive(Resource, Default)]
+pub(crate) struct CameraBookmarks {
+    slots: [Option<CameraBookmark>; 9],
+}
+
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// System storing camera bookmarks with Ctrl+1..9 and recalling them with 1..9. The 0 key jumps
+/// to the player's capital. Recalling is disabled while an army is selected.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn camera_bookmark_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    egui_input: Res<EguiWantsInput>,
+    selected_army: Res<SelectedArmy>,
+    player: Res<Player>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut control: ResMut<CameraControl>,
+    camera: Single<(&Transform, &Projection), With<Camera2d>>,
+    provinces: Query<(&Province, &Owner)>,
+) {
+    if egui_input.wants_any_keyboard_input() {
+        return;
+    }
+
+    let (transform, projection) = camera.into_inner();
+    let Projection::Orthographic(orthographic) = projection else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    for (slot, key) in BOOKMARK_KEYS.iter().enumerate() {
+        if !keyboard.just_pressed(*key) {
+            continue;
+        }
+        if ctrl {
+            info!("Storing camera bookmark {}", slot + 1);
+            bookmarks.slots[slot] = Some(CameraBookmark {
+                position,
+                scale: orthographic.scale,
+            });
+        } else if selected_army.get().is_none()
+            && let Some(bookmark) = bookmarks.slots[slot]
+        {
+            control.glide_to(
+                position,
+                bookmark.position,
+                orthographic.scale,
+                bookmark.scale,
+            );
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Digit0)
+        && !ctrl
+        && selected_army.get().is_none()
+        && let Some(player_country) = player.country
+        && let Some(capital) = capitals(provinces).get(&player_country)
+    {
+        let capital = capital.axial_to_world(consts::HEX_SIZE);
+        control.glide_to(position, capital, orthographic.scale, orthographic.scale);
+    }
+}
+
+/// System to center the camera on positions requested via [`CameraFocusEvent`].
+pub(crate) fn camera_focus_system(
+    mut focus_events: MessageReader<CameraFocusEvent>,
+    mut control: ResMut<CameraControl>,
+    camera: Single<(&Transform, &Projection), With<Camera2d>>,
+) {
+    // Only the latest request matters if several arrive in the same frame.
+    let Some(event) = focus_events.read().last() else {
+        return;
+    };
+
+    let (transform, projection) = camera.into_inner();
+    let scale = match projection {
+        Projection::Orthographic(orthographic) => orthographic.scale,
+        _ => 1.0,
+    };
+    control.glide_to(
+        transform.translation.truncate(),
+        event.position,
+        scale,
+        scale,
+    );
+}
+
+/// System moving the camera along an eased glide, or keeping the selected army centered when
+/// follow mode is on.
+pub(crate) fn camera_glide_system(
+    mut control: ResMut<CameraControl>,
+    selected_army: Res<SelectedArmy>,
+    armies: Query<&Transform, (With<Army>, Without<Camera2d>)>,
+    camera: Single<(&mut Transform, &mut Projection), With<Camera2d>>,
+    time: Res<Time>,
+) {
+    let (mut transform, mut projection) = camera.into_inner();
+    let Projection::Orthographic(orthographic) = projection.as_mut() else {
+        return;
+    };
+
+    if let Some(glide) = control.glide.as_mut() {
+        glide.elapsed += time.delta_secs();
+        let t = (glide.elapsed / GLIDE_DURATION).min(1.0);
+        let eased = EaseFunction::CubicInOut.sample_clamped(t);
+
+        let position = glide.from.lerp(glide.to, eased);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        orthographic.scale = glide.from_scale.lerp(glide.to_scale, eased);
+
+        if t >= 1.0 {
+            control.glide = None;
+        }
+        return;
+    }
+
+    if !control.follow_army {
+        return;
+    }
+    let Some(army_transform) = selected_army.get().and_then(|army| armies.get(army).ok()) else {
+        control.follow_army = false;
+        return;
+    };
+
+    // Exponential smoothing, independent of the frame rate.
+    let target = army_transform.translation.truncate();
+    let current = transform.translation.truncate();
+    let position = current.lerp(target, 1.0 - (-FOLLOW_SHARPNESS * time.delta_secs()).exp());
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
 }
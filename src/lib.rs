@@ -0,0 +1,90 @@
+﻿//! The game's interface as plugins: menus, windows, the map, sound and overlays around the
+//! simulation of the `eu6_simulation` crate. The binary only opens the window around them.
+
+mod achievements;
+mod army;
+mod audio;
+mod colonization;
+mod country;
+mod diagnostics;
+mod diplomacy;
+mod egui_common;
+mod errors;
+mod layout;
+mod map;
+mod menu;
+mod mods;
+mod net;
+mod notifications;
+mod scoring;
+mod scripting;
+mod selection;
+mod settings;
+mod supply;
+mod trade;
+mod turns;
+mod tutorial;
+mod war;
+mod world;
+
+use crate::achievements::AchievementsPlugin;
+use crate::army::ArmyUiPlugin;
+use crate::audio::SoundPlugin;
+use crate::colonization::ColonizationUiPlugin;
+use crate::country::CountryUiPlugin;
+use crate::diagnostics::DiagnosticsOverlayPlugin;
+use crate::diplomacy::DiplomacyUiPlugin;
+use crate::egui_common::UiThemePlugin;
+use crate::errors::ErrorsPlugin;
+use crate::layout::LayoutPlugin;
+use crate::map::MapUiPlugin;
+use crate::menu::MenuPlugin;
+use crate::mods::ModsUiPlugin;
+use crate::net::MultiplayerUiPlugin;
+use crate::notifications::NotificationsPlugin;
+use crate::scoring::ScoringUiPlugin;
+use crate::scripting::ScriptingUiPlugin;
+use crate::settings::SettingsPlugin;
+use crate::supply::SupplyUiPlugin;
+use crate::trade::TradeUiPlugin;
+use crate::turns::TurnsUiPlugin;
+use crate::tutorial::TutorialPlugin;
+use crate::war::WarUiPlugin;
+use crate::world::WorldUiPlugin;
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+pub use eu6_simulation::{MODS_ASSET_SOURCE, MODS_DIRECTORY, SimulationPlugins};
+
+/// Menus, windows, sound and overlays around the simulation. Needs the egui plugin.
+pub struct PresentationPlugins;
+
+impl PluginGroup for PresentationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(UiThemePlugin)
+            .add(ErrorsPlugin)
+            .add(LayoutPlugin)
+            .add(MenuPlugin)
+            .add(NotificationsPlugin)
+            .add(SettingsPlugin)
+            .add(AchievementsPlugin)
+            .add(SoundPlugin)
+            .add(TutorialPlugin)
+            .add(DiagnosticsOverlayPlugin)
+            .add(WorldUiPlugin)
+            .add(ModsUiPlugin)
+            .add(MapUiPlugin)
+            .add(CountryUiPlugin)
+            .add(ArmyUiPlugin)
+            .add(WarUiPlugin)
+            .add(DiplomacyUiPlugin)
+            .add(TurnsUiPlugin)
+            .add(SupplyUiPlugin)
+            .add(TradeUiPlugin)
+            .add(ColonizationUiPlugin)
+            .add(ScoringUiPlugin)
+            .add(ScriptingUiPlugin)
+            .add(MultiplayerUiPlugin)
+    }
+}
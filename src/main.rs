@@ -1,54 +1,23 @@
-mod army;
-mod buildings;
-mod consts;
-mod country;
-mod egui_common;
-mod hex;
-mod layout;
-mod map;
-mod menu;
-mod player;
-mod savegame;
-mod turns;
-mod war;
-
-use crate::army::ArmyPlugin;
-use crate::country::CountryPlugin;
-use crate::layout::LayoutPlugin;
-use crate::map::MapPlugin;
-use crate::menu::MenuPlugin;
-use crate::player::PlayerPlugin;
-use crate::savegame::SaveGamePlugin;
-use crate::turns::TurnsPlugin;
-use crate::war::WarPlugin;
+use bevy::asset::io::AssetSourceBuilder;
 use bevy::log::{Level, LogPlugin};
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
+use eu6::{MODS_ASSET_SOURCE, MODS_DIRECTORY, PresentationPlugins, SimulationPlugins};
 
 fn main() {
     App::new()
+        // Has to be registered before the asset plugin is built.
+        .register_asset_source(
+            MODS_ASSET_SOURCE,
+            AssetSourceBuilder::platform_default(MODS_DIRECTORY, None),
+        )
         .add_plugins(DefaultPlugins.set(LogPlugin {
             level: Level::INFO,
             ..default()
         }))
         .add_plugins(EguiPlugin::default())
         .add_plugins(MeshPickingPlugin)
-        .add_plugins((
-            MapPlugin,
-            CountryPlugin,
-            ArmyPlugin,
-            LayoutPlugin,
-            TurnsPlugin,
-            PlayerPlugin,
-            WarPlugin,
-            MenuPlugin,
-            SaveGamePlugin,
-        ))
-        .add_systems(Startup, setup_camera)
+        .add_plugins(SimulationPlugins)
+        .add_plugins(PresentationPlugins)
         .run();
 }
-
-fn setup_camera(mut commands: Commands) {
-    info!("Setting up camera");
-    commands.spawn(Camera2d);
-}
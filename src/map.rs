@@ -1,40 +1,71 @@
-﻿use crate::army::{
-    spawn_army, ArmyComposition, ArmyHexMap, HexPos, MoveArmyEvent, SelectedArmy, UnitType,
-};
-use crate::buildings::{Building, BuildingType, Income};
-use crate::country::{Coffer, DisplayName, MapColor, SelectedCountry};
-use crate::hex::Hex;
-use crate::player::Player;
-use crate::{consts, egui_common};
-use bevy::asset::Assets;
-use bevy::color::{Color, Mix};
-use bevy::log::info;
+﻿use crate::army::{ArmyPick, ArmyPicker, ArmyReach};
+use crate::diagnostics::UPDATE_PROVINCE_COLORS_TIME;
+use crate::diplomacy::DiplomaticRelations;
+use crate::egui_common::UiTheme;
+use crate::layout::{CameraDrag, UiPointer};
+use crate::selection::Selection;
+use crate::settings::Settings;
+use crate::supply::PlayerSupply;
+use crate::war::PeaceDraft;
+use bevy::asset::{Assets, Handle, RenderAssetUsages};
+use bevy::color::{Color, ColorToComponents, LinearRgba, Mix};
+use bevy::diagnostic::{Diagnostic, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::system::SystemParam;
+use bevy::image::Image;
 use bevy::mesh::{Mesh, Mesh2d};
 use bevy::picking::Pickable;
+use bevy::platform::time::Instant;
 use bevy::prelude::{
-    warn, Children, Click, ColorMaterial, Commands, Component, Entity, Local,
-    MeshMaterial2d, MessageWriter, On, Pointer, PointerButton, Query, RegularPolygon, ResMut, Resource,
-    Transform,
+    Added, Children, Circle, Click, ColorMaterial, Commands, Component, DetectChangesMut, Entity,
+    Local, MeshMaterial2d, On, Pointer, PointerButton, Query, Rectangle, RegularPolygon, ResMut,
+    Resource, Transform, Vec2,
 };
+use bevy::prelude::{FromWorld, Visibility, With, World};
 use bevy::prelude::{Res, Result};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
 use bevy_egui::egui::{Align2, Color32, RichText, Stroke};
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
-use serde::Deserialize;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::REGIMENT_SIZE;
+use eu6_simulation::buildings::{Building, BuildingType, Income, has_building};
+use eu6_simulation::colonization::{COLONIZE_COST, ColonialRange, Explored};
+use eu6_simulation::consts;
+use eu6_simulation::country::{Coffer, DisplayName, MapColor};
+use eu6_simulation::forts::has_fort;
+use eu6_simulation::manpower::Manpower;
+use eu6_simulation::map::{Owner, Province, capitals};
+use eu6_simulation::modifiers::{Devastation, IncomeSources, Modifiers, ProvinceIncome, Unrest};
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::PlayerCommands;
+use eu6_simulation::trade::{FLEET_COST, SeaChart, ShipType};
+use eu6_simulation::turns::Turn;
+use eu6_simulation::units::{UnitDef, UnitRegistry, recruitment_blocked};
+use eu6_simulation::weather::Weather;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fmt::Display;
 
-pub struct MapPlugin;
+pub struct MapUiPlugin;
 
-impl bevy::prelude::Plugin for MapPlugin {
+impl bevy::prelude::Plugin for MapUiPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         use bevy::prelude::*;
-        app.insert_resource(ProvinceHexMap::default())
-            .insert_resource(SelectedProvince::default())
+        app.insert_resource(SelectedProvince::default())
             .insert_resource(MapMode::default())
-            .add_systems(Startup, generate_map)
-            .add_systems(Update, update_province_colors)
-            .add_systems(EguiPrimaryContextPass, display_province_panel)
-            .add_systems(EguiPrimaryContextPass, display_map_modes_panel);
+            .init_resource::<ProvinceMeshes>()
+            .register_diagnostic(Diagnostic::new(UPDATE_PROVINCE_COLORS_TIME).with_suffix("ms"))
+            .add_systems(Update, draw_provinces)
+            .add_systems(
+                Update,
+                (
+                    update_province_colors,
+                    update_occupation_hatching,
+                    update_province_decals,
+                )
+                    .after(draw_provinces),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                (display_province_panel, display_map_modes_panel),
+            );
     }
 }
 
@@ -43,18 +74,125 @@ pub(crate) enum MapMode {
     #[default]
     Terrain,
     Political,
+    Weather,
+    Supply,
+    Trade,
+    Diplomacy,
 }
 
-/// Resource mapping hex coordinates to province entities. Allows clicking on hex tiles to find
-/// the corresponding province.
-#[derive(Resource, Default)]
-pub(crate) struct ProvinceHexMap {
-    tiles: HashMap<Hex, Entity>,
+/// Color palette used for countries on the political map. The colorblind-safe variants shift the
+/// map colors so that countries stay distinguishable for players with red-green color blindness.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(crate) enum MapPalette {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
 }
 
-impl ProvinceHexMap {
-    pub(crate) fn get_entity(&self, hex: &Hex) -> Option<&Entity> {
-        self.tiles.get(hex)
+impl MapPalette {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            MapPalette::Standard => "Standard",
+            MapPalette::Deuteranopia => "Deuteranopia",
+            MapPalette::Protanopia => "Protanopia",
+        }
+    }
+
+    pub(crate) fn all() -> [MapPalette; 3] {
+        [
+            MapPalette::Standard,
+            MapPalette::Deuteranopia,
+            MapPalette::Protanopia,
+        ]
+    }
+
+    /// Adjusts a country color for this palette. Colors are daltonized: the part of the color
+    /// lost to the deficiency is computed by simulating it, and moved into the channels that are
+    /// still perceived.
+    pub(crate) fn apply(&self, color: Color) -> Color {
+        // Machado et al. (2009) simulation matrices for full severity, in linear RGB.
+        let simulation = match self {
+            MapPalette::Standard => return color,
+            MapPalette::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            MapPalette::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+        };
+
+        let linear = color.to_linear();
+        let [r, g, b, a] = linear.to_f32_array();
+        let simulated = simulation.map(|row| row[0] * r + row[1] * g + row[2] * b);
+        let error = [r - simulated[0], g - simulated[1], b - simulated[2]];
+
+        // Shift the lost red information into green and blue.
+        let corrected = LinearRgba::new(
+            r,
+            (g + 0.7 * error[0] + error[1]).clamp(0.0, 1.0),
+            (b + 0.7 * error[0] + error[2]).clamp(0.0, 1.0),
+            a,
+        );
+        Color::from(corrected)
+    }
+}
+
+/// Marker for the striped overlay drawn over a province while it is occupied and occupation
+/// hatching is enabled in the settings. Spawned as a child of every province.
+#[derive(Component)]
+pub(crate) struct OccupationHatching;
+
+/// Income from which a province counts as highly developed, buildings included.
+const HIGH_DEVELOPMENT_INCOME: f32 = 10.0;
+
+/// Icon shown over a key province, so that it can be told apart without opening its panel. Every
+/// province gets a child of each kind, [`update_province_decals`] shows the ones that apply.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ProvinceDecal {
+    /// Crown over the capital of a country, see [`capitals`].
+    Capital,
+    /// Star over a province earning at least [`HIGH_DEVELOPMENT_INCOME`].
+    Developed,
+    /// Tower over a province with a fort.
+    Fort,
+}
+
+impl ProvinceDecal {
+    const ALL: [ProvinceDecal; 3] = [
+        ProvinceDecal::Capital,
+        ProvinceDecal::Developed,
+        ProvinceDecal::Fort,
+    ];
+
+    fn mesh(&self) -> Mesh {
+        match self {
+            ProvinceDecal::Capital => Mesh::from(RegularPolygon::new(8.0, 5)),
+            ProvinceDecal::Developed => Mesh::from(Circle::new(5.0)),
+            ProvinceDecal::Fort => Mesh::from(Rectangle::new(8.0, 12.0)),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            ProvinceDecal::Capital => Color::srgb(1.0, 0.84, 0.0),
+            ProvinceDecal::Developed => Color::srgb(0.45, 0.85, 1.0),
+            ProvinceDecal::Fort => Color::srgb(0.25, 0.25, 0.3),
+        }
+    }
+
+    /// Position in the hex, around the army standing in the middle.
+    fn offset(&self) -> Vec2 {
+        let offset = match self {
+            ProvinceDecal::Capital => Vec2::new(0.0, 0.6),
+            ProvinceDecal::Developed => Vec2::new(-0.5, -0.45),
+            ProvinceDecal::Fort => Vec2::new(0.5, -0.45),
+        };
+        offset * consts::HEX_SIZE
     }
 }
 
@@ -86,429 +224,315 @@ pub(crate) enum InteractionState {
     Selected,
 }
 
-#[derive(Component, PartialEq)]
-pub(crate) struct Owner(pub(crate) Entity);
-
-/// Component representing a province on the map.
-#[derive(Component)]
-pub(crate) struct Province {
-    name: String,
-    hex: Hex,
-    terrain: Terrain,
-}
-
-impl Province {
-    /// Returns the color associated with the province's terrain type.
-    fn color(&self) -> Color {
-        self.terrain.color()
-    }
-
-    /// Returns the name of the province.
-    pub(crate) fn name(&self) -> &str {
-        &self.name
-    }
-
-    /// Returns a reference to the hex coordinates of the province.
-    pub(crate) fn get_hex(&self) -> &Hex {
-        &self.hex
-    }
-
-    /// Returns the terrain type of the province.
-    pub(crate) fn terrain(&self) -> Terrain {
-        self.terrain
-    }
-
-    /// Determines if the province can be owned by a country based on its terrain type.
-    pub(crate) fn is_ownable(&self) -> bool {
-        !matches!(self.terrain, Terrain::Sea | Terrain::Wasteland)
-    }
-    pub(crate) fn is_passable(&self) -> bool {
-        !matches!(self.terrain, Terrain::Sea | Terrain::Wasteland)
-    }
-    pub(crate) fn base_income(&self) -> f32 {
-        self.terrain.base_income()
-    }
-}
-
-const COLOR_PLAINS: Color = Color::srgb(0.46, 0.79, 0.26); // Grass green
-const COLOR_HILLS: Color = Color::srgb(0.58, 0.44, 0.27); // Muted brown
-const COLOR_MOUNTAINS: Color = Color::srgb(0.45, 0.45, 0.5); // Slate gray
-const COLOR_FOREST: Color = Color::srgb(0.07, 0.31, 0.12); // Deep dark green
-const COLOR_DESERT: Color = Color::srgb(0.93, 0.79, 0.48); // Sandy yellow/tan
-const COLOR_WASTELAND: Color = Color::srgb(0.55, 0.50, 0.45); // Barren grayish-brown
-
-const COLOR_SEA: Color = Color::srgb(0.0, 0.53, 0.74); // Ocean blue
-
-/// Enum representing different terrain types for provinces.
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub(crate) enum Terrain {
-    Plains,
-    Hills,
-    Mountains,
-    Forest,
-    Desert,
-    Wasteland,
-    Sea,
-}
-
-impl Display for Terrain {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let terrain_str = match self {
-            Terrain::Plains => "Plains",
-            Terrain::Hills => "Hills",
-            Terrain::Mountains => "Mountains",
-            Terrain::Forest => "Forest",
-            Terrain::Desert => "Desert",
-            Terrain::Wasteland => "Wasteland",
-            Terrain::Sea => "Sea",
-        };
-        write!(f, "{}", terrain_str)
+/// Event handler for when a province is clicked. Manages selection and deselection of provinces,
+/// or picks the provinces to demand while a peace deal is drafted. Clicks on a hex holding one of
+/// the player's armies select the army, see [`ArmyPicker`].
+#[allow(clippy::too_many_arguments)]
+fn handle_province_click(
+    click: On<Pointer<Click>>,
+    mut selection: Selection,
+    camera_drag: Res<CameraDrag>,
+    ui_pointer: UiPointer,
+    mut peace_draft: ResMut<PeaceDraft>,
+    mut player_commands: PlayerCommands,
+    province: Query<&Province>,
+    control: Query<(&Owner, Option<&eu6_simulation::war::Occupied>)>,
+    army_picker: ArmyPicker,
+) -> Result {
+    // The click landed on a window above the map.
+    if ui_pointer.is_over_ui() {
+        return Ok(());
     }
-}
+    let clicked_entity = click.entity;
 
-impl Terrain {
-    const fn color(&self) -> Color {
-        match self {
-            Terrain::Plains => COLOR_PLAINS,
-            Terrain::Hills => COLOR_HILLS,
-            Terrain::Mountains => COLOR_MOUNTAINS,
-            Terrain::Forest => COLOR_FOREST,
-            Terrain::Desert => COLOR_DESERT,
-            Terrain::Wasteland => COLOR_WASTELAND,
-            Terrain::Sea => COLOR_SEA,
+    if let Some(fleet) = selection.fleet()
+        && click.button == PointerButton::Secondary
+        && !camera_drag.is_dragging()
+    {
+        let province = province.get(clicked_entity)?;
+        if province.is_navigable() {
+            player_commands.move_fleet(fleet, *province.get_hex());
         }
+        return Ok(());
     }
 
-    const fn base_income(&self) -> f32 {
-        match self {
-            Terrain::Plains => 0.2,
-            Terrain::Hills => 0.16,
-            Terrain::Mountains => 0.1,
-            Terrain::Forest => 0.14,
-            Terrain::Desert => 0.5,
-            Terrain::Wasteland => 0.0,
-            Terrain::Sea => 0.0,
+    if let Some(army) = selection.army()
+        && click.button == PointerButton::Secondary
+    {
+        // Right button was used to pan the camera, not to give an order.
+        if camera_drag.is_dragging() {
+            return Ok(());
         }
-    }
-
-    /// Returns the defensive bonus multiplier for this terrain.
-    /// Values > 1.0 benefit the defender, < 1.0 benefit the attacker.
-    pub(crate) const fn defender_bonus(&self) -> f32 {
-        match self {
-            Terrain::Plains => 1.0,    // No bonus - open terrain
-            Terrain::Hills => 1.25,    // +25% defense - high ground advantage
-            Terrain::Mountains => 1.5, // +50% defense - strong defensive terrain
-            Terrain::Forest => 1.2,    // +20% defense - cover and ambush potential
-            Terrain::Desert => 0.9,    // -10% defense - exposed, no cover
-            Terrain::Wasteland => 1.0, // No bonus
-            Terrain::Sea => 1.0,       // No bonus (shouldn't happen)
+        let province = province.get(clicked_entity)?;
+        // Orders onto the sea board a transport there.
+        if !province.is_passable() && !province.is_navigable() {
+            return Ok(());
         }
-    }
 
-    /// Returns the cavalry effectiveness multiplier for this terrain.
-    /// Values < 1.0 reduce cavalry damage.
-    pub(crate) const fn cavalry_modifier(&self) -> f32 {
-        match self {
-            Terrain::Plains => 1.2,    // +20% - ideal cavalry terrain
-            Terrain::Hills => 0.8,     // -20% - difficult to charge
-            Terrain::Mountains => 0.5, // -50% - very bad for cavalry
-            Terrain::Forest => 0.6,    // -40% - trees block charges
-            Terrain::Desert => 1.1,    // +10% - open terrain
-            Terrain::Wasteland => 0.9, // -10% - rough ground
-            Terrain::Sea => 0.0,       // No cavalry at sea
+        let hex = *province.get_hex();
+        if let Some(target) = army_picker.merge_target(army, hex) {
+            player_commands.merge_army(army, target);
+        } else {
+            player_commands.move_army(army, hex);
         }
+        return Ok(());
     }
 
-    /// Returns the artillery effectiveness multiplier for this terrain.
-    pub(crate) const fn artillery_modifier(&self) -> f32 {
-        match self {
-            Terrain::Plains => 1.0,    // Normal effectiveness
-            Terrain::Hills => 1.2,     // +20% - good firing positions
-            Terrain::Mountains => 0.7, // -30% - difficult positioning
-            Terrain::Forest => 0.6,    // -40% - blocked line of sight
-            Terrain::Desert => 1.1,    // +10% - clear sightlines
-            Terrain::Wasteland => 0.9, // -10%
-            Terrain::Sea => 0.0,       // No artillery at sea
-        }
+    if click.button != PointerButton::Primary {
+        return Ok(());
     }
-}
 
-/// Converts an u8 value to a Terrain variant for simple terrain assignment.
-impl From<u8> for Terrain {
-    fn from(value: u8) -> Self {
-        match value {
-            0 => Terrain::Plains,
-            1 => Terrain::Hills,
-            2 => Terrain::Mountains,
-            3 => Terrain::Forest,
-            4 => Terrain::Desert,
-            5 => Terrain::Wasteland,
-            _ => Terrain::Sea,
-        }
+    if let Ok((owner, occupied)) = control.get(clicked_entity)
+        && peace_draft.can_demand(owner, occupied)
+    {
+        peace_draft.toggle(clicked_entity);
+        return Ok(());
     }
-}
 
-impl Terrain {
-    fn from_str(s: &str) -> Self {
-        match s {
-            "Plains" => Terrain::Plains,
-            "Hills" => Terrain::Hills,
-            "Mountains" => Terrain::Mountains,
-            "Forest" => Terrain::Forest,
-            "Desert" => Terrain::Desert,
-            "Wasteland" => Terrain::Wasteland,
-            "Sea" => Terrain::Sea,
-            _ => Terrain::Plains,
+    let hex = *province.get(clicked_entity)?.get_hex();
+    match army_picker.pick(hex, selection.army()) {
+        Some(ArmyPick::Select(army)) => {
+            selection.select_army(army);
+            return Ok(());
+        }
+        Some(ArmyPick::Deselect) => {
+            selection.clear_army();
+            return Ok(());
         }
+        None => {}
     }
-}
 
-/// Path to the map file
-const MAP_FILE_PATH: &str = "assets/maps/map.json";
+    // Clicking the selected province again deselects it.
+    if selection.province() == Some(clicked_entity) {
+        selection.clear_province();
+    } else {
+        selection.select_province(clicked_entity);
+    }
 
-/// JSON structures for map loading
-#[derive(Deserialize)]
-struct MapFile {
-    countries: Vec<CountryDef>,
-    provinces: Vec<ProvinceDef>,
+    Ok(())
 }
 
-#[derive(Deserialize, Clone)]
-pub(crate) struct CountryDef {
-    pub(crate) name: String,
-    pub(crate) color: [f32; 3],
-    pub(crate) flag: String,
+/// Builds the striped overlay shown over occupied provinces. It is tinted with the occupier's
+/// color when shown.
+fn build_hatching_overlay() -> (OccupationHatching, Transform, Visibility, Pickable) {
+    (
+        OccupationHatching,
+        Transform::from_xyz(0.0, 0.0, 0.5),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+    )
 }
 
-#[derive(Deserialize)]
-struct ProvinceDef {
-    q: i32,
-    r: i32,
-    terrain: String,
-    name: String,
-    owner: Option<String>,
+/// Builds one of the icons of a province, hidden until it applies.
+fn build_province_decal(decal: ProvinceDecal) -> (ProvinceDecal, Transform, Visibility, Pickable) {
+    (
+        decal,
+        Transform::from_translation(decal.offset().extend(1.0)),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+    )
 }
 
-/// Resource storing loaded map data for use by other systems
-#[derive(Resource, Default)]
-pub(crate) struct MapData {
-    pub(crate) countries: Vec<CountryDef>,
-    pub(crate) province_owners: HashMap<Hex, String>,
+/// Meshes and textures shared by every province on the map.
+#[derive(Resource)]
+struct ProvinceMeshes {
+    hex: Handle<Mesh>,
+    hatching: Handle<Image>,
+    decals: HashMap<ProvinceDecal, (Handle<Mesh>, Handle<ColorMaterial>)>,
 }
 
-/// Load map from JSON file
-fn load_map_from_file() -> Option<MapFile> {
-    // Try multiple paths
-    let paths_to_try = [
-        MAP_FILE_PATH.to_string(),
-        format!("./{}", MAP_FILE_PATH),
-        format!("../{}", MAP_FILE_PATH),
-    ];
-
-    for path in &paths_to_try {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            // Remove BOM if present
-            let content = content.trim_start_matches('\u{feff}');
-
-            match serde_json::from_str(content) {
-                Ok(map) => {
-                    info!("Successfully loaded map from '{}'", path);
-                    return Some(map);
-                }
-                Err(e) => {
-                    warn!("Failed to parse map file '{}': {}", path, e);
-                }
-            }
+impl FromWorld for ProvinceMeshes {
+    fn from_world(world: &mut World) -> Self {
+        let hatching = world
+            .resource_mut::<Assets<Image>>()
+            .add(build_hatching_image());
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let hex = meshes.add(RegularPolygon::new(consts::HEX_SIZE, 6));
+        let decal_meshes: Vec<_> = ProvinceDecal::ALL
+            .iter()
+            .map(|decal| meshes.add(decal.mesh()))
+            .collect();
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let decals = ProvinceDecal::ALL
+            .into_iter()
+            .zip(decal_meshes)
+            .map(|(decal, mesh)| (decal, (mesh, materials.add(decal.color()))))
+            .collect();
+        Self {
+            hex,
+            hatching,
+            decals,
         }
     }
-
-    // Print current directory for debugging
-    if let Ok(cwd) = std::env::current_dir() {
-        warn!("Current working directory: {:?}", cwd);
-    }
-    warn!(
-        "Could not find or load map file. Tried paths: {:?}",
-        paths_to_try
-    );
-    None
 }
 
-/// System to generate a hex map of provinces at startup from JSON file.
-pub(crate) fn generate_map(
+/// Draws newly generated provinces as hex tiles, clickable and with their occupation hatching and
+/// their icons on top, and gives them meshes to be drawn with. Every province gets a material of
+/// its own, recolored by [`update_province_colors`].
+fn draw_provinces(
     mut commands: Commands,
-    mut hex_map: ResMut<ProvinceHexMap>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    province_meshes: Res<ProvinceMeshes>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    provinces: Query<(Entity, &Province), Added<Province>>,
+    hatchings: Query<Entity, Added<OccupationHatching>>,
+    decals: Query<(Entity, &ProvinceDecal), Added<ProvinceDecal>>,
 ) {
-    let map_file = match load_map_from_file() {
-        Some(m) => m,
-        None => {
-            panic!(
-                "Failed to load map file! The game requires a valid map at {}",
-                MAP_FILE_PATH
-            );
-        }
-    };
-
-    let mut province_owners = HashMap::new();
-
-    for prov_def in &map_file.provinces {
-        let hex = Hex::new(prov_def.q, prov_def.r);
-        let terrain = Terrain::from_str(&prov_def.terrain);
-
-        // Store owner info for later assignment
-        if let Some(owner) = &prov_def.owner {
-            province_owners.insert(hex, owner.clone());
-        }
-
-        let province = Province {
-            name: prov_def.name.clone(),
-            hex,
-            terrain,
-        };
-
-        let province_entity =
-            build_province_entity(&mut meshes, &mut materials, province, consts::HEX_SIZE);
-
-        let province_id = commands
-            .spawn(province_entity)
-            .observe(handle_province_click)
-            .id();
-
-        hex_map.tiles.insert(hex, province_id);
-    }
-
-    // Insert map data resource for use by country system
-    commands.insert_resource(MapData {
-        countries: map_file.countries,
-        province_owners,
-    });
-
-    info!("Map generation complete: {} provinces", hex_map.tiles.len());
-}
-
-/// Event handler for when a province is clicked. Manages selection and deselection of provinces.
-fn handle_province_click(
-    click: On<Pointer<Click>>,
-    mut selected_province: ResMut<SelectedProvince>,
-    selected_army: Res<SelectedArmy>,
-    mut army_event_messenger: MessageWriter<MoveArmyEvent>,
-    mut commands: Commands,
-    province: Query<&Province>,
-) -> Result {
-    let clicked_entity = click.entity;
-
-    if let Some(army) = selected_army.get()
-        && click.button == PointerButton::Secondary
-    {
-        let province = province.get(clicked_entity)?;
-        if !province.is_passable() {
-            return Ok(());
-        }
-
-        army_event_messenger.write(MoveArmyEvent::new(army, HexPos::new(*province.get_hex())));
-        return Ok(());
+    for (entity, province) in &provinces {
+        let hex = *province.get_hex();
+        commands
+            .entity(entity)
+            .insert((
+                Transform::from_translation(hex.axial_to_world(consts::HEX_SIZE).extend(0.0)),
+                InteractionState::None,
+                Pickable::default(),
+                Mesh2d(province_meshes.hex.clone()),
+                MeshMaterial2d(materials.add(province.color())),
+            ))
+            .with_child(build_hatching_overlay())
+            .with_children(|parent| {
+                for decal in ProvinceDecal::ALL {
+                    parent.spawn(build_province_decal(decal));
+                }
+            })
+            .observe(handle_province_click);
     }
-
-    if click.button != PointerButton::Primary {
-        return Ok(());
+    for entity in &hatchings {
+        commands.entity(entity).insert((
+            Mesh2d(province_meshes.hex.clone()),
+            MeshMaterial2d(materials.add(ColorMaterial {
+                texture: Some(province_meshes.hatching.clone()),
+                ..Default::default()
+            })),
+        ));
     }
-
-    // 1. Deselect the previous entity if it exists
-    if let Some(prev_entity) = selected_province.get() {
-        // If the user clicks the same hex, just deselect and return
-        if prev_entity == clicked_entity {
-            commands.entity(prev_entity).insert(InteractionState::None);
-            selected_province.clear();
-            return Ok(());
-        }
-
-        // Otherwise, reset the old one before selecting the new one
-        commands.entity(prev_entity).insert(InteractionState::None);
+    for (entity, decal) in &decals {
+        let (mesh, material) = &province_meshes.decals[decal];
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh.clone()), MeshMaterial2d(material.clone())));
     }
-
-    // 2. Select the new entity
-    commands
-        .entity(clicked_entity)
-        .insert(InteractionState::Selected);
-    selected_province.set(clicked_entity);
-
-    Ok(())
 }
 
-/// Builds the visual representation of a province as a hex tile.
-fn build_province_entity(
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    province: Province,
-    size: f32,
-) -> (
-    Province,
-    Mesh2d,
-    MeshMaterial2d<ColorMaterial>,
-    Transform,
-    InteractionState,
-    Income,
-    Pickable,
-) {
-    let mesh = Mesh::from(RegularPolygon::new(size, 6));
-    let mesh_handle = meshes.add(mesh);
-
-    let color = province.color();
-    let material_handle = materials.add(color);
+/// Builds a texture of white diagonal stripes on a transparent background.
+fn build_hatching_image() -> Image {
+    const SIZE: u32 = 64;
+    const STRIPE_PERIOD: u32 = 12;
 
-    let hex = province.hex;
-    let transform = Transform::from_translation(hex.axial_to_world(size).extend(0.0));
-
-    let income = Income::new(province.base_income());
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let alpha = if (x + y) % STRIPE_PERIOD < STRIPE_PERIOD / 2 {
+                255
+            } else {
+                0
+            };
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
 
-    (
-        province,
-        Mesh2d(mesh_handle),
-        MeshMaterial2d(material_handle),
-        transform,
-        InteractionState::None,
-        income,
-        Pickable::default(),
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
     )
 }
 
 /// System to update province visuals based on map mode and selection state.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn update_province_colors(
     mut materials: ResMut<Assets<ColorMaterial>>,
     map_mode: Res<MapMode>,
+    settings: Res<Settings>,
+    weather: Res<Weather>,
+    player_supply: Res<PlayerSupply>,
+    army_reach: Res<ArmyReach>,
+    sea_chart: Res<SeaChart>,
+    explored: Res<Explored>,
     query: Query<(
         &Province,
         Option<&Owner>,
-        Option<&crate::war::Occupied>,
-        Option<&crate::war::SiegeProgress>,
+        Option<&eu6_simulation::war::Occupied>,
+        Option<&eu6_simulation::war::SiegeProgress>,
         &MeshMaterial2d<ColorMaterial>,
         &InteractionState,
     )>,
     country_query: Query<&MapColor>,
+    relations: DiplomaticRelations,
+    mut diagnostics: Diagnostics,
 ) {
+    let start = Instant::now();
     let selection_mix = 0.4;
     let selection_color = Color::srgb(1.0, 0.9, 0.0);
     let occupation_mix = 0.5; // How much occupier color shows
     let siege_color = Color::srgb(0.3, 0.0, 0.0); // Dark red tint for sieges
     let siege_mix = 0.3;
+    let clear_sky_dimming = 0.4;
+    let supply_color = Color::srgb(0.2, 0.9, 0.3);
+    let supply_mix = 0.5;
+    let port_color = Color::srgb(0.95, 0.75, 0.2);
+    let lane_color = Color::srgb(0.85, 0.95, 1.0);
+    let raided_color = Color::srgb(0.8, 0.1, 0.1);
+    let trade_mix = 0.6;
+    let unexplored_color = Color::srgb(0.12, 0.12, 0.14);
+    let reach_color = Color::srgb(0.3, 0.6, 1.0);
+    let reach_mix = 0.35;
+    let relation_mix = 0.6;
 
     for (province, maybe_owner, maybe_occupied, maybe_siege, material, state) in &query {
         if let Some(mat) = materials.get_mut(&material.0) {
             let mut base_color = match *map_mode {
                 MapMode::Terrain => province.color(),
+                // Clear skies are darkened so the fronts stand out
+                MapMode::Weather => match weather.at(*province.get_hex()) {
+                    Some(kind) => kind.color(),
+                    None => province.color().mix(&Color::BLACK, clear_sky_dimming),
+                },
+                MapMode::Supply if player_supply.0.contains(province.get_hex()) => {
+                    province.color().mix(&supply_color, supply_mix)
+                }
+                MapMode::Supply => province.color().mix(&Color::BLACK, clear_sky_dimming),
+                MapMode::Trade if sea_chart.is_port(province.get_hex()) => {
+                    province.color().mix(&port_color, trade_mix)
+                }
+                MapMode::Trade => {
+                    let mut lanes = sea_chart.lanes_through(province.get_hex()).peekable();
+                    if lanes.peek().is_none() {
+                        province.color().mix(&Color::BLACK, clear_sky_dimming)
+                    } else if lanes.any(|lane| lane.raided > 0.0) {
+                        province.color().mix(&raided_color, trade_mix)
+                    } else {
+                        province.color().mix(&lane_color, trade_mix)
+                    }
+                }
+                // Countries without a shown relation are darkened like provinces without weather
+                MapMode::Diplomacy => match maybe_owner.and_then(|o| relations.relation(o.0)) {
+                    Some(relation) => {
+                        let relation_color = settings.map_palette.apply(relation.color());
+                        province.color().mix(&relation_color, relation_mix)
+                    }
+                    None => province.color().mix(&Color::BLACK, clear_sky_dimming),
+                },
                 MapMode::Political => {
                     if let Some(owner) = maybe_owner
                         && let Ok(map_color) = country_query.get(owner.0)
                     {
-                        let owner_color = map_color.0;
+                        let owner_color = settings.map_palette.apply(map_color.0);
 
-                        // If occupied, blend with occupier's color
+                        // If occupied, blend with occupier's color, unless the occupation is
+                        // shown with hatching instead
                         if let Some(occupied) = maybe_occupied
+                            && !settings.occupation_hatching
                             && let Ok(occupier_color) = country_query.get(occupied.occupier)
                         {
                             // Mix owner color with occupier color to show occupation
-                            owner_color.mix(&occupier_color.0, occupation_mix)
+                            let occupier_color = settings.map_palette.apply(occupier_color.0);
+                            owner_color.mix(&occupier_color, occupation_mix)
                         } else {
                             owner_color
                         }
@@ -523,91 +547,212 @@ pub(crate) fn update_province_colors(
                 base_color = base_color.mix(&siege_color, siege_mix);
             }
 
+            // Hexes the selected army can march to this turn
+            if army_reach.reachable.contains(province.get_hex()) {
+                base_color = base_color.mix(&reach_color, reach_mix);
+            }
+
+            if !explored.contains(province.get_hex()) {
+                base_color = unexplored_color;
+            }
+
             mat.color = match *state {
                 InteractionState::Selected => base_color.mix(&selection_color, selection_mix),
                 InteractionState::None => base_color,
             };
         }
     }
+
+    diagnostics.add_measurement(&UPDATE_PROVINCE_COLORS_TIME, || {
+        start.elapsed().as_secs_f64() * 1000.0
+    });
+}
+
+/// Shows the striped overlay in the occupier's color over occupied provinces on the political map,
+/// when occupation hatching is enabled in the settings.
+fn update_occupation_hatching(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    map_mode: Res<MapMode>,
+    settings: Res<Settings>,
+    provinces: Query<(Option<&eu6_simulation::war::Occupied>, &Children), With<Province>>,
+    mut overlays: Query<
+        (&MeshMaterial2d<ColorMaterial>, &mut Visibility),
+        With<OccupationHatching>,
+    >,
+    country_query: Query<&MapColor>,
+) {
+    let show_hatching = settings.occupation_hatching && *map_mode == MapMode::Political;
+
+    for (maybe_occupied, children) in &provinces {
+        let occupier_color = maybe_occupied
+            .filter(|_| show_hatching)
+            .and_then(|occupied| country_query.get(occupied.occupier).ok());
+
+        for &child in children {
+            let Ok((material, mut visibility)) = overlays.get_mut(child) else {
+                continue;
+            };
+            match occupier_color {
+                Some(color) => {
+                    *visibility = Visibility::Inherited;
+                    if let Some(mat) = materials.get_mut(&material.0) {
+                        mat.color = settings.map_palette.apply(color.0);
+                    }
+                }
+                None => {
+                    *visibility = Visibility::Hidden;
+                }
+            }
+        }
+    }
+}
+
+/// Shows the icons of capitals, highly developed provinces and forts on explored provinces.
+fn update_province_decals(
+    explored: Res<Explored>,
+    provinces: Query<(&Province, Option<&Owner>, &Income, &Children)>,
+    owned_provinces: Query<(&Province, &Owner)>,
+    buildings: Query<&Building>,
+    building_incomes: Query<&Income, With<Building>>,
+    mut decals: Query<(&ProvinceDecal, &mut Visibility)>,
+) {
+    let capitals = capitals(owned_provinces);
+
+    for (province, maybe_owner, income, children) in &provinces {
+        let visible = explored.contains(province.get_hex());
+        let is_capital =
+            maybe_owner.is_some_and(|owner| capitals.get(&owner.0) == Some(province.get_hex()));
+        let development = income.get()
+            + children
+                .iter()
+                .filter_map(|child| building_incomes.get(*child).ok())
+                .map(Income::get)
+                .sum::<f32>();
+        let fortified = has_fort(Some(children), &buildings);
+
+        for &child in children {
+            let Ok((decal, mut visibility)) = decals.get_mut(child) else {
+                continue;
+            };
+            let shown = visible
+                && match decal {
+                    ProvinceDecal::Capital => is_capital,
+                    ProvinceDecal::Developed => development >= HIGH_DEVELOPMENT_INCOME,
+                    ProvinceDecal::Fort => fortified,
+                };
+            visibility.set_if_neq(if shown {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+    }
 }
 
 pub(crate) fn switch_map_mode(map_mode: &mut ResMut<MapMode>) {
     **map_mode = match **map_mode {
         MapMode::Terrain => MapMode::Political,
-        MapMode::Political => MapMode::Terrain,
+        MapMode::Political => MapMode::Weather,
+        MapMode::Weather => MapMode::Supply,
+        MapMode::Supply => MapMode::Trade,
+        MapMode::Trade => MapMode::Diplomacy,
+        MapMode::Diplomacy => MapMode::Terrain,
     };
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub(crate) fn display_province_panel(
-    mut commands: Commands,
     mut contexts: EguiContexts,
-    mut selected_province: ResMut<SelectedProvince>,
-    mut selected_country: ResMut<SelectedCountry>,
+    theme: Res<UiTheme>,
+    mut selection: Selection,
     provinces: Query<(
         &Province,
         Option<&Owner>,
         Option<&Children>,
-        Option<&crate::war::Occupied>,
-        Option<&crate::war::SiegeProgress>,
+        Option<&eu6_simulation::war::Occupied>,
+        Option<&eu6_simulation::war::Liberation>,
+        Option<&eu6_simulation::war::SiegeProgress>,
+        Option<&Modifiers>,
+        Option<&Unrest>,
+        Option<&Income>,
+        Option<&Devastation>,
     )>,
     countries: Query<(&DisplayName, &MapColor)>,
     buildings: Query<&Building>,
-    mut coffers: Query<&mut Coffer>,
+    economy: ProvinceEconomy,
     mut current_tab: Local<ProvinceTab>,
     player: Res<Player>,
-    mut army_hex_map: ResMut<ArmyHexMap>,
-    mut armies_query: Query<(&Owner, &mut ArmyComposition)>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    units: Res<UnitRegistry>,
+    turn: Res<Turn>,
+    sea_chart: Res<SeaChart>,
+    colonial_range: ColonialRange,
+    mut player_commands: PlayerCommands,
 ) {
-    let Some(selected_id) = selected_province.get() else {
+    let Some(selected_id) = selection.province() else {
         return;
     };
-    let Ok((province, maybe_owner, maybe_children, maybe_occupied, maybe_siege)) =
-        provinces.get(selected_id)
+    let Ok((
+        province,
+        maybe_owner,
+        maybe_children,
+        maybe_occupied,
+        maybe_liberation,
+        maybe_siege,
+        maybe_modifiers,
+        maybe_unrest,
+        maybe_income,
+        maybe_devastation,
+    )) = provinces.get(selected_id)
     else {
         return;
     };
+    let income = maybe_owner.zip(maybe_income).map(|(owner, income)| {
+        economy.income_sources.province(
+            owner.0,
+            income,
+            maybe_children,
+            maybe_modifiers,
+            maybe_unrest,
+            maybe_occupied,
+            maybe_devastation,
+        )
+    });
 
     let owner_name = get_owner_name(maybe_owner, &countries);
     let is_player_owned = maybe_owner
         .map(|o| Some(o.0) == player.country)
         .unwrap_or(false);
+    let colonizer = player
+        .country
+        .filter(|_| maybe_owner.is_none() && province.is_ownable());
 
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
         Err(_) => return,
     };
 
-    egui::Window::new("Province")
-        .frame(egui_common::default_frame())
-        .title_bar(false)
-        .anchor(Align2::LEFT_TOP, [20.0, 20.0])
+    theme
+        .side_panel("Province", Align2::LEFT_TOP)
         .resizable(false)
         .default_width(250.0)
         .show(ctx, |ui| {
-            draw_province_header(
-                ui,
-                province,
-                &mut commands,
-                selected_id,
-                &mut selected_province,
-            );
+            draw_province_header(ui, &theme, province, &mut selection);
             draw_tab_selector(ui, &mut current_tab);
 
             match *current_tab {
                 ProvinceTab::Recruitment => draw_recruitment_tab(
                     ui,
-                    province,
+                    selected_id,
                     maybe_owner,
                     is_player_owned,
-                    &mut coffers,
-                    &countries,
-                    &mut army_hex_map,
-                    &mut armies_query,
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
+                    recruitment_blocked(maybe_occupied.is_some(), maybe_siege.is_some()),
+                    has_building(maybe_children, &buildings, BuildingType::Barracks),
+                    &economy.coffers,
+                    &economy.manpower,
+                    &units,
+                    turn.current_turn(),
+                    sea_chart.is_port(province.get_hex()),
+                    &mut player_commands,
                 ),
                 ProvinceTab::Buildings => draw_buildings_tab(
                     ui,
@@ -616,23 +761,48 @@ pub(crate) fn display_province_panel(
                     maybe_children,
                     is_player_owned,
                     &buildings,
-                    &mut coffers,
-                    &mut commands,
-                ),
-                ProvinceTab::Overview => draw_overview_tab(
-                    ui,
-                    province,
-                    &owner_name,
-                    maybe_owner,
-                    maybe_occupied,
-                    maybe_siege,
-                    &countries,
-                    &mut selected_country,
+                    &economy.coffers,
+                    &mut player_commands,
                 ),
+                ProvinceTab::Overview => {
+                    draw_overview_tab(
+                        ui,
+                        province,
+                        &owner_name,
+                        maybe_owner,
+                        maybe_occupied,
+                        maybe_liberation,
+                        maybe_siege,
+                        maybe_modifiers,
+                        maybe_unrest,
+                        maybe_devastation,
+                        income.as_ref(),
+                        &countries,
+                        &mut selection,
+                    );
+                    if let Some(country) = colonizer {
+                        draw_colonize_button(
+                            ui,
+                            selected_id,
+                            country,
+                            colonial_range.can_colonize(country, selected_id),
+                            &economy.coffers,
+                            &mut player_commands,
+                        );
+                    }
+                }
             }
         });
 }
 
+/// Treasuries, manpower and incomes, for the prices and the income shown in the province panel.
+#[derive(SystemParam)]
+pub(crate) struct ProvinceEconomy<'w, 's> {
+    coffers: Query<'w, 's, &'static Coffer>,
+    manpower: Query<'w, 's, &'static Manpower>,
+    income_sources: IncomeSources<'w, 's>,
+}
+
 fn get_owner_name(
     maybe_owner: Option<&Owner>,
     countries: &Query<(&DisplayName, &MapColor)>,
@@ -645,22 +815,20 @@ fn get_owner_name(
 
 fn draw_province_header(
     ui: &mut egui::Ui,
+    theme: &UiTheme,
     province: &Province,
-    commands: &mut Commands,
-    selected_id: Entity,
-    selected_province: &mut ResMut<SelectedProvince>,
+    selection: &mut Selection,
 ) {
     ui.horizontal(|ui| {
         ui.add(egui::Label::new(
-            RichText::new(&province.name)
+            RichText::new(province.name())
                 .font(egui::FontId::proportional(22.0))
                 .color(Color32::WHITE)
                 .strong(),
         ));
         ui.add_space(8.0);
-        if egui_common::close_button(ui) {
-            commands.entity(selected_id).insert(InteractionState::None);
-            selected_province.clear();
+        if theme.close_button(ui) {
+            selection.clear_province();
         }
     });
     ui.add_space(8.0);
@@ -705,27 +873,33 @@ fn draw_tab_selector(ui: &mut egui::Ui, current_tab: &mut Local<ProvinceTab>) {
     ui.add_space(8.0);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_recruitment_tab(
     ui: &mut egui::Ui,
-    province: &Province,
+    selected_id: Entity,
     maybe_owner: Option<&Owner>,
     is_player_owned: bool,
-    coffers: &mut Query<&mut Coffer>,
-    countries: &Query<(&DisplayName, &MapColor)>,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<(&Owner, &mut ArmyComposition)>,
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
+    blocked: Option<&str>,
+    has_barracks: bool,
+    coffers: &Query<&Coffer>,
+    manpower: &Query<&Manpower>,
+    units: &UnitRegistry,
+    turn: u32,
+    is_port: bool,
+    player_commands: &mut PlayerCommands,
 ) {
     let available_ducats = maybe_owner
         .and_then(|owner| coffers.get(owner.0).ok())
         .map(|coffer| coffer.get_ducats())
         .unwrap_or(0.0);
+    let available_manpower = maybe_owner
+        .and_then(|owner| manpower.get(owner.0).ok())
+        .map_or(0, |manpower| manpower.0);
 
     ui.heading(RichText::new("Recruitment").size(16.0));
     ui.add_space(4.0);
     ui.label(format!("Available ducats: {:.0}💰", available_ducats));
+    ui.label(format!("Available manpower: {}👥", available_manpower));
     ui.separator();
     ui.add_space(8.0);
 
@@ -743,44 +917,83 @@ fn draw_recruitment_tab(
         return;
     }
 
-    for unit_type in UnitType::all() {
+    if let Some(reason) = blocked {
+        ui.label(RichText::new(reason).italics().weak());
+        return;
+    }
+
+    for unit in units.units().iter().filter(|unit| unit.is_available(turn)) {
         draw_recruitment_button(
             ui,
-            province,
+            selected_id,
             maybe_owner.unwrap(),
-            unit_type,
+            unit,
             available_ducats,
-            coffers,
-            countries,
-            army_hex_map,
-            armies_query,
-            commands,
-            meshes,
-            materials,
+            available_manpower,
+            has_barracks,
+            player_commands,
         );
         ui.add_space(5.0);
     }
+
+    if is_port {
+        for ships in ShipType::ALL {
+            draw_fleet_button(
+                ui,
+                selected_id,
+                maybe_owner.unwrap(),
+                ships,
+                available_ducats,
+                player_commands,
+            );
+            ui.add_space(5.0);
+        }
+    }
+}
+
+fn draw_fleet_button(
+    ui: &mut egui::Ui,
+    selected_id: Entity,
+    owner: &Owner,
+    ships: ShipType,
+    available_ducats: f32,
+    player_commands: &mut PlayerCommands,
+) {
+    let can_afford = available_ducats >= FLEET_COST;
+    let button = egui::Button::new(format!("{} ({:.0}💰)", ships.name(), FLEET_COST))
+        .min_size(egui::vec2(200.0, 0.0))
+        .fill(if can_afford {
+            Color32::from_rgb(70, 70, 90)
+        } else {
+            Color32::from_rgb(80, 60, 60)
+        });
+    let response = ui.add_enabled(can_afford, button);
+    if response.clicked() {
+        player_commands.build_fleet(owner.0, selected_id, ships);
+    }
+    response.on_hover_text(format!(
+        "Launched next to the port. {}",
+        ships.description()
+    ));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_recruitment_button(
     ui: &mut egui::Ui,
-    province: &Province,
+    selected_id: Entity,
     owner: &Owner,
-    unit_type: UnitType,
+    unit: &UnitDef,
     available_ducats: f32,
-    coffers: &mut Query<&mut Coffer>,
-    countries: &Query<(&DisplayName, &MapColor)>,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<(&Owner, &mut ArmyComposition)>,
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
+    available_manpower: u32,
+    has_barracks: bool,
+    player_commands: &mut PlayerCommands,
 ) {
-    let cost = unit_type.cost();
-    let can_afford = available_ducats >= cost;
+    let missing_manpower = available_manpower < REGIMENT_SIZE;
+    let can_afford = available_ducats >= unit.cost && !missing_manpower;
+    let missing_barracks = unit.needs_barracks && !has_barracks;
 
     ui.horizontal(|ui| {
-        let button_text = format!("{} ({:.0}💰)", unit_type.name(), cost);
+        let button_text = format!("{} ({:.0}💰 {}👥)", unit.name, unit.cost, REGIMENT_SIZE);
         let button = egui::Button::new(button_text).min_size(egui::vec2(200.0, 0.0));
         let button = if !can_afford {
             button.fill(Color32::from_rgb(80, 60, 60))
@@ -788,63 +1001,22 @@ fn draw_recruitment_button(
             button.fill(Color32::from_rgb(70, 70, 90))
         };
 
-        if ui.add_enabled(can_afford, button).clicked() {
-            recruit_unit(
-                province,
-                owner,
-                unit_type,
-                cost,
-                coffers,
-                countries,
-                army_hex_map,
-                armies_query,
-                commands,
-                meshes,
-                materials,
-            );
+        let response = ui.add_enabled(can_afford && !missing_barracks, button);
+        if response.clicked() {
+            player_commands.recruit(owner.0, selected_id, unit.id.clone());
         }
-    });
-}
-
-fn recruit_unit(
-    province: &Province,
-    owner: &Owner,
-    unit_type: UnitType,
-    cost: f32,
-    coffers: &mut Query<&mut Coffer>,
-    countries: &Query<(&DisplayName, &MapColor)>,
-    army_hex_map: &mut ResMut<ArmyHexMap>,
-    armies_query: &mut Query<(&Owner, &mut ArmyComposition)>,
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-) {
-    let Ok(mut coffer) = coffers.get_mut(owner.0) else {
-        return;
-    };
-    let hex = *province.get_hex();
-    let hex_pos = HexPos::new(hex);
-
-    if let Some(&army_entity) = army_hex_map.get(&hex_pos) {
-        if let Ok((army_owner, mut comp)) = armies_query.get_mut(army_entity) {
-            if army_owner.0 == owner.0 {
-                coffer.remove_ducats(cost);
-                comp.add_unit(unit_type);
-            } else {
-                warn!("Cannot recruit: tile occupied by another army");
-            }
+        let stats = format!(
+            "Damage {} per soldier · Movement {} per turn · {}💰 upkeep per turn",
+            unit.damage, unit.movement, unit.upkeep
+        );
+        if missing_barracks {
+            response.on_disabled_hover_text(format!("Needs Barracks in the province\n{}", stats));
+        } else if missing_manpower {
+            response.on_disabled_hover_text(format!("Not enough manpower\n{}", stats));
+        } else {
+            response.on_hover_text(stats);
         }
-    } else if let Ok((_, map_color)) = countries.get(owner.0) {
-        coffer.remove_ducats(cost);
-        let mut comp = ArmyComposition {
-            infantry: 0,
-            cavalry: 0,
-            artillery: 0,
-        };
-        comp.add_unit(unit_type);
-        let army = spawn_army(commands, meshes, materials, hex, owner.0, map_color.0, comp);
-        army_hex_map.insert(hex_pos, army);
-    }
+    });
 }
 
 fn draw_buildings_tab(
@@ -854,8 +1026,8 @@ fn draw_buildings_tab(
     maybe_children: Option<&Children>,
     is_player_owned: bool,
     buildings: &Query<&Building>,
-    coffers: &mut Query<&mut Coffer>,
-    commands: &mut Commands,
+    coffers: &Query<&Coffer>,
+    player_commands: &mut PlayerCommands,
 ) {
     let existing_buildings: HashSet<BuildingType> = maybe_children
         .map(|children| {
@@ -892,8 +1064,7 @@ fn draw_buildings_tab(
             &existing_buildings,
             available_ducats,
             is_player_owned,
-            coffers,
-            commands,
+            player_commands,
         );
         ui.add_space(5.0);
     }
@@ -907,8 +1078,7 @@ fn draw_building_button(
     existing_buildings: &HashSet<BuildingType>,
     available_ducats: f32,
     is_player_owned: bool,
-    coffers: &mut Query<&mut Coffer>,
-    commands: &mut Commands,
+    player_commands: &mut PlayerCommands,
 ) {
     let already_built = existing_buildings.contains(&building_type);
     let can_afford = available_ducats >= building_type.cost();
@@ -934,16 +1104,7 @@ fn draw_building_button(
 
         if response.clicked() {
             if let Some(owner) = maybe_owner {
-                if let Ok(mut coffer) = coffers.get_mut(owner.0) {
-                    coffer.remove_ducats(building_type.cost());
-                    commands.entity(selected_id).with_children(|parent| {
-                        parent.spawn((
-                            Building { building_type },
-                            Income::new(building_type.income_bonus()),
-                            Owner(owner.0),
-                        ));
-                    });
-                }
+                player_commands.build(owner.0, selected_id, building_type);
             }
         }
 
@@ -953,32 +1114,70 @@ fn draw_building_button(
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_overview_tab(
     ui: &mut egui::Ui,
     province: &Province,
     owner_name: &str,
     maybe_owner: Option<&Owner>,
-    maybe_occupied: Option<&crate::war::Occupied>,
-    maybe_siege: Option<&crate::war::SiegeProgress>,
+    maybe_occupied: Option<&eu6_simulation::war::Occupied>,
+    maybe_liberation: Option<&eu6_simulation::war::Liberation>,
+    maybe_siege: Option<&eu6_simulation::war::SiegeProgress>,
+    maybe_modifiers: Option<&Modifiers>,
+    maybe_unrest: Option<&Unrest>,
+    maybe_devastation: Option<&Devastation>,
+    income: Option<&ProvinceIncome>,
     countries: &Query<(&DisplayName, &MapColor)>,
-    selected_country: &mut ResMut<SelectedCountry>,
+    selection: &mut Selection,
 ) {
     egui::Grid::new("province_stats")
         .num_columns(2)
         .spacing([20.0, 8.0])
         .show(ui, |ui| {
-            draw_owner_row(ui, owner_name, maybe_owner, selected_country);
+            draw_owner_row(ui, owner_name, maybe_owner, selection);
             draw_terrain_row(ui, province);
-            draw_occupation_row(ui, maybe_occupied, countries);
+            draw_income_row(ui, income, maybe_owner, countries);
+            draw_occupation_row(ui, maybe_occupied, maybe_liberation, countries);
             draw_siege_row(ui, maybe_siege, countries);
+            draw_devastation_row(ui, maybe_devastation);
+            draw_unrest_row(ui, maybe_unrest);
+            draw_modifier_rows(ui, maybe_modifiers);
         });
 }
 
+fn draw_colonize_button(
+    ui: &mut egui::Ui,
+    selected_id: Entity,
+    country: Entity,
+    in_range: bool,
+    coffers: &Query<&Coffer>,
+    player_commands: &mut PlayerCommands,
+) {
+    let can_afford = coffers
+        .get(country)
+        .is_ok_and(|coffer| coffer.get_ducats() >= COLONIZE_COST);
+
+    ui.add_space(8.0);
+    let button = egui::Button::new(format!("Colonize ({:.0}💰)", COLONIZE_COST))
+        .min_size(egui::vec2(200.0, 0.0));
+    let response = ui.add_enabled(in_range && can_afford, button);
+    if response.clicked() {
+        player_commands.colonize(country, selected_id);
+    }
+    if in_range {
+        response.on_hover_text("Settle the province for your country");
+    } else {
+        response.on_disabled_hover_text(
+            "Out of colonial range: too far over sea from your ports. Universities extend it",
+        );
+    }
+}
+
 fn draw_owner_row(
     ui: &mut egui::Ui,
     owner_name: &str,
     maybe_owner: Option<&Owner>,
-    selected_country: &mut ResMut<SelectedCountry>,
+    selection: &mut Selection,
 ) {
     ui.label(RichText::new("Owner").color(Color32::LIGHT_GRAY));
     if ui
@@ -990,7 +1189,7 @@ fn draw_owner_row(
         .clicked()
     {
         if let Some(owner) = maybe_owner {
-            selected_country.select(owner.0);
+            selection.select_country(owner.0);
         }
     }
     ui.end_row();
@@ -998,13 +1197,31 @@ fn draw_owner_row(
 
 fn draw_terrain_row(ui: &mut egui::Ui, province: &Province) {
     ui.label(RichText::new("Terrain").color(Color32::LIGHT_GRAY));
-    ui.label(RichText::new(province.terrain.to_string()).color(Color32::WHITE));
+    ui.label(RichText::new(province.terrain().name.as_str()).color(Color32::WHITE));
+    ui.end_row();
+    ui.label(RichText::new("Elevation").color(Color32::LIGHT_GRAY));
+    ui.label(RichText::new(format!("{:.0} m", province.elevation())).color(Color32::WHITE));
     ui.end_row();
+    ui.label(RichText::new("Supply limit").color(Color32::LIGHT_GRAY));
+    ui.label(
+        RichText::new(format!("{} regiments", province.terrain().supply_limit))
+            .color(Color32::WHITE),
+    );
+    ui.end_row();
+    if province.terrain().attrition > 0.0 {
+        ui.label(RichText::new("Attrition").color(Color32::LIGHT_GRAY));
+        ui.label(
+            RichText::new(format!("{:.0}%", province.terrain().attrition * 100.0))
+                .color(Color32::ORANGE),
+        );
+        ui.end_row();
+    }
 }
 
 fn draw_occupation_row(
     ui: &mut egui::Ui,
-    maybe_occupied: Option<&crate::war::Occupied>,
+    maybe_occupied: Option<&eu6_simulation::war::Occupied>,
+    maybe_liberation: Option<&eu6_simulation::war::Liberation>,
     countries: &Query<(&DisplayName, &MapColor)>,
 ) {
     if let Some(occupied) = maybe_occupied {
@@ -1015,30 +1232,124 @@ fn draw_occupation_row(
             .unwrap_or("Unknown");
         ui.label(RichText::new(format!("⚔ Occupied by {}", occupier_name)).color(Color32::RED));
         ui.end_row();
+        if let Some(liberation) = maybe_liberation {
+            ui.label("");
+            ui.label(
+                RichText::new(format!(
+                    "🏳 Being retaken ({}/{})",
+                    liberation.progress,
+                    eu6_simulation::war::LIBERATION_TURNS
+                ))
+                .color(Color32::LIGHT_GREEN),
+            );
+            ui.end_row();
+        }
     }
 }
 
 fn draw_siege_row(
     ui: &mut egui::Ui,
-    maybe_siege: Option<&crate::war::SiegeProgress>,
+    maybe_siege: Option<&eu6_simulation::war::SiegeProgress>,
     countries: &Query<(&DisplayName, &MapColor)>,
 ) {
     if let Some(siege) = maybe_siege {
         ui.label(RichText::new("Siege").color(Color32::LIGHT_GRAY));
-        let besieger_name = countries
-            .get(siege.besieger_country)
-            .map(|(n, _)| n.0.as_str())
-            .unwrap_or("Unknown");
+        let besieger_names: Vec<&str> = siege
+            .besiegers
+            .iter()
+            .map(|&besieger| {
+                countries
+                    .get(besieger)
+                    .map(|(n, _)| n.0.as_str())
+                    .unwrap_or("Unknown")
+            })
+            .collect();
         ui.label(
             RichText::new(format!(
                 "🏰 Under siege by {} ({}/{})",
-                besieger_name,
+                besieger_names.join(", "),
                 siege.progress,
-                crate::war::SIEGE_TURNS_REQUIRED
+                siege.progress_required
             ))
             .color(Color32::YELLOW),
         );
         ui.end_row();
+        if siege.soldiers < siege.required {
+            ui.label("");
+            ui.label(
+                RichText::new(format!(
+                    "Stalled, needs {} soldiers ({} present)",
+                    siege.required, siege.soldiers
+                ))
+                .color(Color32::GRAY),
+            );
+            ui.end_row();
+        }
+    }
+}
+
+fn draw_income_row(
+    ui: &mut egui::Ui,
+    income: Option<&ProvinceIncome>,
+    maybe_owner: Option<&Owner>,
+    countries: &Query<(&DisplayName, &MapColor)>,
+) {
+    let Some(income) = income else {
+        return;
+    };
+    ui.label(RichText::new("Income").color(Color32::LIGHT_GRAY));
+    let occupied = maybe_owner.is_some_and(|owner| owner.0 != income.collector);
+    let text = if occupied {
+        let occupier = countries
+            .get(income.collector)
+            .map(|(n, _)| n.0.as_str())
+            .unwrap_or("Unknown");
+        RichText::new(format!("{:.1}💰 to {}", income.total(), occupier)).color(Color32::RED)
+    } else {
+        RichText::new(format!("{:.1}💰", income.total())).color(Color32::WHITE)
+    };
+    ui.label(text).on_hover_text(format!(
+        "Province {:.1}💰\nBuildings {:.1}💰\nOccupation and devastation {:.0}%",
+        income.base,
+        income.buildings,
+        income.war_multiplier * 100.0
+    ));
+    ui.end_row();
+}
+
+fn draw_devastation_row(ui: &mut egui::Ui, maybe_devastation: Option<&Devastation>) {
+    if let Some(devastation) = maybe_devastation.filter(|devastation| devastation.0 > 0) {
+        ui.label(RichText::new("Devastation").color(Color32::LIGHT_GRAY));
+        ui.label(RichText::new(format!("🏚 {}% income lost", devastation.0)).color(Color32::RED));
+        ui.end_row();
+    }
+}
+
+fn draw_unrest_row(ui: &mut egui::Ui, maybe_unrest: Option<&Unrest>) {
+    if let Some(unrest) = maybe_unrest.filter(|unrest| unrest.0 > 0) {
+        ui.label(RichText::new("Unrest").color(Color32::LIGHT_GRAY));
+        ui.label(RichText::new(format!("🔥 {}", unrest.0)).color(Color32::RED));
+        ui.end_row();
+    }
+}
+
+fn draw_modifier_rows(ui: &mut egui::Ui, maybe_modifiers: Option<&Modifiers>) {
+    for modifier in maybe_modifiers.into_iter().flat_map(|m| &m.0) {
+        ui.label(RichText::new(modifier.name.as_str()).color(Color32::LIGHT_GRAY));
+        let color = if modifier.income < 0.0 {
+            Color32::RED
+        } else {
+            Color32::GREEN
+        };
+        ui.label(
+            RichText::new(format!(
+                "{:+.0}% income ({} turns)",
+                modifier.income * 100.0,
+                modifier.turns_left
+            ))
+            .color(color),
+        );
+        ui.end_row();
     }
 }
 
@@ -1072,7 +1383,7 @@ pub(crate) fn display_map_modes_panel(mut contexts: EguiContexts, mut map_mode:
                     [50.0, 50.0],
                     egui::Button::selectable(
                         *map_mode == MapMode::Political,
-                        RichText::new("🏁").font(font_id),
+                        RichText::new("🏁").font(font_id.clone()),
                     ),
                 )
                 .on_hover_text("Political")
@@ -1080,6 +1391,65 @@ pub(crate) fn display_map_modes_panel(mut contexts: EguiContexts, mut map_mode:
             {
                 *map_mode = MapMode::Political
             }
+
+            if ui
+                .add_sized(
+                    [50.0, 50.0],
+                    egui::Button::selectable(
+                        *map_mode == MapMode::Weather,
+                        RichText::new("🌧").font(font_id.clone()),
+                    ),
+                )
+                .on_hover_text("Weather: storms in purple, blizzards in white, heavy rain in gray")
+                .clicked()
+            {
+                *map_mode = MapMode::Weather
+            }
+
+            if ui
+                .add_sized(
+                    [50.0, 50.0],
+                    egui::Button::selectable(
+                        *map_mode == MapMode::Supply,
+                        RichText::new("📦").font(font_id.clone()),
+                    ),
+                )
+                .on_hover_text("Supply: where your armies are supplied, shown in green")
+                .clicked()
+            {
+                *map_mode = MapMode::Supply
+            }
+
+            if ui
+                .add_sized(
+                    [50.0, 50.0],
+                    egui::Button::selectable(
+                        *map_mode == MapMode::Trade,
+                        RichText::new("⚓").font(font_id.clone()),
+                    ),
+                )
+                .on_hover_text("Trade: ports in gold, sea lanes in white, raided lanes in red")
+                .clicked()
+            {
+                *map_mode = MapMode::Trade
+            }
+
+            if ui
+                .add_sized(
+                    [50.0, 50.0],
+                    egui::Button::selectable(
+                        *map_mode == MapMode::Diplomacy,
+                        RichText::new("🤝").font(font_id),
+                    ),
+                )
+                .on_hover_text(
+                    "Diplomacy: wars, alliances, vassals and military access of the selected \
+                     country, or of yours",
+                )
+                .clicked()
+            {
+                *map_mode = MapMode::Diplomacy
+            }
         });
 }
 
@@ -1,16 +1,35 @@
-﻿use crate::country::{Country, DisplayName, MapColor};
-use crate::player::Player;
-use crate::savegame::{save_exists, LoadGameEvent, SaveGameEvent};
+﻿use crate::achievements::AchievementsWindowOpen;
+use crate::egui_common::UiTheme;
+use crate::selection::Selection;
+use crate::settings::{Settings, SettingsWindowOpen};
+use crate::tutorial::Tutorial;
 use bevy::prelude::*;
 use bevy_egui::egui::{Color32, RichText};
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::REGIMENT_SIZE;
+use eu6_simulation::buildings::Income;
+use eu6_simulation::consts;
+use eu6_simulation::country::{Country, DisplayName, MapColor};
+use eu6_simulation::hex::Hex;
+use eu6_simulation::map::{Owner, Province};
+use eu6_simulation::menu::{MenuState, PauseMenuOpen};
+use eu6_simulation::mods::VirtualFs;
+use eu6_simulation::net::NetSession;
+use eu6_simulation::player::Player;
+use eu6_simulation::rules::{
+    Difficulty, GameRules, Handicap, Personality, PlayerDefeat, available_maps,
+};
+use eu6_simulation::savegame::{
+    DeleteSaveEvent, LoadGameEvent, SaveGameEvent, SaveSlots, format_timestamp, slot_name,
+};
+use eu6_simulation::world::RegenerateWorldEvent;
+use std::collections::{HashMap, HashSet};
 
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<MenuState>()
-            .insert_resource(PauseMenuOpen(false))
+        app.init_resource::<SaveSlotsWindow>()
             .add_systems(
                 EguiPrimaryContextPass,
                 display_main_menu.run_if(in_state(MenuState::MainMenu)),
@@ -19,10 +38,23 @@ impl Plugin for MenuPlugin {
                 EguiPrimaryContextPass,
                 display_country_selection.run_if(in_state(MenuState::CountrySelection)),
             )
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_game_setup.run_if(in_state(MenuState::GameSetup)),
+            )
             .add_systems(
                 EguiPrimaryContextPass,
                 display_pause_menu.run_if(in_state(MenuState::InGame)),
             )
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_defeat_screen.run_if(in_state(MenuState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_save_slots_window
+                    .run_if(in_state(MenuState::MainMenu).or(in_state(MenuState::InGame))),
+            )
             .add_systems(
                 Update,
                 handle_escape_key.run_if(in_state(MenuState::InGame)),
@@ -31,37 +63,72 @@ impl Plugin for MenuPlugin {
     }
 }
 
-#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
-pub enum MenuState {
-    #[default]
-    MainMenu,
-    CountrySelection,
-    InGame,
+#[derive(Clone, Copy, PartialEq)]
+enum SlotAction {
+    Save,
+    Load,
 }
 
-#[derive(Resource)]
-pub struct PauseMenuOpen(pub bool);
+/// The window listing the save slots, opened from the main menu to load and from the pause menu
+/// to save or load.
+#[derive(Resource, Default)]
+struct SaveSlotsWindow {
+    mode: Option<SlotAction>,
+    /// Name typed for a new save.
+    new_name: String,
+    /// Slot the player clicked delete on, waiting for them to confirm.
+    confirm_delete: Option<String>,
+}
 
-fn handle_escape_key(keyboard: Res<ButtonInput<KeyCode>>, mut pause_menu: ResMut<PauseMenuOpen>) {
-    if keyboard.just_pressed(KeyCode::Escape) {
-        pause_menu.0 = !pause_menu.0;
+impl SaveSlotsWindow {
+    fn open(&mut self, mode: SlotAction) {
+        *self = Self {
+            mode: Some(mode),
+            ..default()
+        };
     }
 }
 
+/// Escape closes the pause menu, otherwise deselects the top-most selection, and opens the pause
+/// menu once nothing is selected. The settings and save slot windows close themselves first.
+fn handle_escape_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut pause_menu: ResMut<PauseMenuOpen>,
+    settings_window: Res<SettingsWindowOpen>,
+    slots_window: Res<SaveSlotsWindow>,
+    mut selection: Selection,
+) {
+    if !keyboard.just_pressed(KeyCode::Escape) || settings_window.0 || slots_window.mode.is_some() {
+        return;
+    }
+    if pause_menu.0 {
+        pause_menu.0 = false;
+    } else if !selection.clear_top() {
+        pause_menu.0 = true;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn display_main_menu(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
     mut next_state: ResMut<NextState<MenuState>>,
-    mut load_events: MessageWriter<LoadGameEvent>,
+    slots: Res<SaveSlots>,
+    mut slots_window: ResMut<SaveSlotsWindow>,
+    mut settings_window: ResMut<SettingsWindowOpen>,
+    mut achievements_window: ResMut<AchievementsWindowOpen>,
+    mut app_exit: MessageWriter<AppExit>,
 ) {
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
         Err(_) => return,
     };
 
-    let has_save = save_exists();
+    let has_save = !slots.0.is_empty();
 
     egui::CentralPanel::default()
-        .frame(egui::Frame::new().fill(Color32::from_rgb(10, 10, 20)))
+        .frame(theme.menu_frame())
         .show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(100.0);
@@ -86,18 +153,17 @@ fn display_main_menu(
 
                 let button_size = egui::vec2(250.0, 50.0);
 
-                if ui
-                    .add_sized(
-                        button_size,
-                        egui::Button::new(
-                            RichText::new("🎮 New Game")
-                                .font(egui::FontId::proportional(24.0))
-                                .color(Color32::WHITE),
-                        )
-                        .fill(Color32::from_rgb(60, 80, 120)),
+                let new_game = ui.add_sized(
+                    button_size,
+                    egui::Button::new(
+                        RichText::new("🎮 New Game")
+                            .font(egui::FontId::proportional(24.0))
+                            .color(Color32::WHITE),
                     )
-                    .clicked()
-                {
+                    .fill(Color32::from_rgb(60, 80, 120)),
+                );
+                settings.focus_default(&new_game);
+                if new_game.clicked() {
                     next_state.set(MenuState::CountrySelection);
                 }
 
@@ -121,13 +187,62 @@ fn display_main_menu(
                 let load_response = ui.add_sized(button_size, load_button);
 
                 if has_save && load_response.clicked() {
-                    load_events.write(LoadGameEvent);
-                    next_state.set(MenuState::InGame);
-                    info!("Loading saved game...");
+                    slots_window.open(SlotAction::Load);
                 }
 
                 if !has_save {
-                    load_response.on_hover_text("No save file found");
+                    load_response.on_hover_text("No saved games found");
+                }
+
+                ui.add_space(20.0);
+
+                if ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("⚙ Settings")
+                                .font(egui::FontId::proportional(24.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 100)),
+                    )
+                    .clicked()
+                {
+                    settings_window.0 = true;
+                }
+
+                ui.add_space(20.0);
+
+                if ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("🏆 Achievements")
+                                .font(egui::FontId::proportional(24.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 100)),
+                    )
+                    .clicked()
+                {
+                    achievements_window.0 = true;
+                }
+
+                ui.add_space(20.0);
+
+                if ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("🧩 Mods")
+                                .font(egui::FontId::proportional(24.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 100)),
+                    )
+                    .clicked()
+                {
+                    next_state.set(MenuState::ModSelection);
                 }
 
                 ui.add_space(20.0);
@@ -144,25 +259,33 @@ fn display_main_menu(
                     )
                     .clicked()
                 {
-                    std::process::exit(0);
+                    app_exit.write(AppExit::Success);
                 }
             });
         });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn display_country_selection(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
     mut next_state: ResMut<NextState<MenuState>>,
     countries: Query<(Entity, &DisplayName, &MapColor), With<Country>>,
+    provinces: Query<(&Province, Option<&Owner>, &Income)>,
     mut player: ResMut<Player>,
+    mut hovered_country: Local<Option<Entity>>,
 ) {
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
         Err(_) => return,
     };
 
+    let overviews = build_country_overviews(&countries, &provinces);
+    let mut hovered_this_frame = None;
+
     egui::CentralPanel::default()
-        .frame(egui::Frame::new().fill(Color32::from_rgb(10, 10, 20)))
+        .frame(theme.menu_frame())
         .show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(50.0);
@@ -185,34 +308,71 @@ fn display_country_selection(
                             .color(Color32::GRAY),
                     );
                 } else {
-                    egui::Grid::new("country_grid")
-                        .num_columns(3)
-                        .spacing([20.0, 20.0])
-                        .show(ui, |ui| {
-                            for (i, (entity, name, map_color)) in countries_vec.iter().enumerate() {
-                                let color = map_color.0;
-                                let [r, g, b, _] = color.to_srgba().to_u8_array();
-                                let egui_color = Color32::from_rgb(r, g, b);
+                    ui.horizontal(|ui| {
+                        ui.add_space((ui.available_width() - 1000.0).max(0.0) / 2.0);
 
-                                let button = egui::Button::new(
-                                    RichText::new(&name.0)
+                        egui::Grid::new("country_grid")
+                            .num_columns(3)
+                            .spacing([20.0, 20.0])
+                            .show(ui, |ui| {
+                                for (i, (entity, name, map_color)) in
+                                    countries_vec.iter().enumerate()
+                                {
+                                    let overview = &overviews[entity];
+                                    let button = egui::Button::new(
+                                        RichText::new(format!(
+                                            "{}\n🏰 {}   💰 {:.1}",
+                                            name.0, overview.provinces, overview.income
+                                        ))
                                         .font(egui::FontId::proportional(20.0))
                                         .color(Color32::WHITE),
-                                )
-                                .fill(egui_color)
-                                .min_size(egui::vec2(180.0, 80.0));
+                                    )
+                                    .fill(to_egui_color(map_color.0))
+                                    .min_size(egui::vec2(180.0, 80.0));
+
+                                    let response = ui.add(button);
+                                    if i == 0 {
+                                        settings.focus_default(&response);
+                                    }
+                                    if response.hovered() || response.has_focus() {
+                                        hovered_this_frame = Some(*entity);
+                                    }
+                                    if response.clicked() {
+                                        player.country = Some(*entity);
+                                        info!("Player selected country: {}", name.0);
+                                        next_state.set(MenuState::GameSetup);
+                                    }
 
-                                if ui.add(button).clicked() {
-                                    player.country = Some(*entity);
-                                    info!("Player selected country: {}", name.0);
-                                    next_state.set(MenuState::InGame);
+                                    if (i + 1) % 3 == 0 {
+                                        ui.end_row();
+                                    }
                                 }
+                            });
 
-                                if (i + 1) % 3 == 0 {
-                                    ui.end_row();
+                        ui.add_space(40.0);
+
+                        ui.vertical(|ui| {
+                            let highlighted = hovered_country
+                                .and_then(|e| countries.get(e).ok())
+                                .map(|(e, _, color)| (e, to_egui_color(color.0)));
+                            draw_map_preview(ui, &provinces, highlighted);
+
+                            ui.add_space(10.0);
+
+                            match hovered_country.and_then(|e| countries.get(e).ok()) {
+                                Some((entity, name, _)) => {
+                                    draw_country_overview(ui, &name.0, &overviews[&entity]);
+                                }
+                                None => {
+                                    ui.label(
+                                        RichText::new("Hover a country to see its territory")
+                                            .color(Color32::GRAY)
+                                            .italics(),
+                                    );
                                 }
                             }
                         });
+                    });
                 }
 
                 ui.add_space(40.0);
@@ -228,19 +388,453 @@ fn display_country_selection(
                         .fill(Color32::from_rgb(80, 80, 80)),
                     )
                     .clicked()
+                    || settings.back_pressed(ui.ctx())
                 {
                     next_state.set(MenuState::MainMenu);
                 }
             });
         });
+
+    // Keep the last hovered country so the preview doesn't flicker between buttons.
+    if hovered_this_frame.is_some() {
+        *hovered_country = hovered_this_frame;
+    }
+}
+
+/// Starting position of a country as shown on the country selection screen.
+struct CountryOverview {
+    provinces: usize,
+    income: f32,
+    neighbors: Vec<String>,
 }
 
+fn build_country_overviews(
+    countries: &Query<(Entity, &DisplayName, &MapColor), With<Country>>,
+    provinces: &Query<(&Province, Option<&Owner>, &Income)>,
+) -> HashMap<Entity, CountryOverview> {
+    let owners: HashMap<Hex, Entity> = provinces
+        .iter()
+        .filter_map(|(province, owner, _)| owner.map(|o| (*province.get_hex(), o.0)))
+        .collect();
+
+    countries
+        .iter()
+        .map(|(entity, _, _)| {
+            let owned: Vec<_> = provinces
+                .iter()
+                .filter(|(_, owner, _)| owner.is_some_and(|o| o.0 == entity))
+                .collect();
+
+            let neighbor_countries: HashSet<Entity> = owned
+                .iter()
+                .flat_map(|(province, _, _)| province.get_hex().neighbors())
+                .filter_map(|hex| owners.get(&hex).copied())
+                .filter(|&owner| owner != entity)
+                .collect();
+            let mut neighbors: Vec<String> = neighbor_countries
+                .into_iter()
+                .filter_map(|e| countries.get(e).ok().map(|(_, name, _)| name.0.clone()))
+                .collect();
+            neighbors.sort();
+
+            let overview = CountryOverview {
+                provinces: owned.len(),
+                income: owned.iter().map(|(_, _, income)| income.get()).sum(),
+                neighbors,
+            };
+            (entity, overview)
+        })
+        .collect()
+}
+
+fn draw_country_overview(ui: &mut egui::Ui, name: &str, overview: &CountryOverview) {
+    ui.label(
+        RichText::new(name)
+            .font(egui::FontId::proportional(24.0))
+            .color(Color32::WHITE)
+            .strong(),
+    );
+    egui::Grid::new("country_overview")
+        .num_columns(2)
+        .spacing([20.0, 4.0])
+        .show(ui, |ui| {
+            ui.label(RichText::new("Provinces").color(Color32::LIGHT_GRAY));
+            ui.label(overview.provinces.to_string());
+            ui.end_row();
+
+            ui.label(RichText::new("Starting income").color(Color32::LIGHT_GRAY));
+            ui.label(format!("{:.1} ducats/turn", overview.income));
+            ui.end_row();
+
+            ui.label(RichText::new("Neighbors").color(Color32::LIGHT_GRAY));
+            if overview.neighbors.is_empty() {
+                ui.label("None");
+            } else {
+                ui.label(overview.neighbors.join(", "));
+            }
+            ui.end_row();
+        });
+}
+
+/// Size of the map preview on the country selection screen.
+const MAP_PREVIEW_SIZE: egui::Vec2 = egui::vec2(360.0, 280.0);
+/// Most free soldiers a handicap can give a country.
+const MAX_FREE_MANPOWER: u32 = 20 * REGIMENT_SIZE;
+
+/// Draws a small overview of the whole map with the highlighted country's provinces in its color.
+fn draw_map_preview(
+    ui: &mut egui::Ui,
+    provinces: &Query<(&Province, Option<&Owner>, &Income)>,
+    highlighted: Option<(Entity, Color32)>,
+) {
+    let (rect, _) = ui.allocate_exact_size(MAP_PREVIEW_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 6.0, Color32::from_rgb(20, 20, 30));
+
+    let positions: Vec<Vec2> = provinces
+        .iter()
+        .map(|(province, _, _)| province.get_hex().axial_to_world(consts::HEX_SIZE))
+        .collect();
+    let Some(min) = positions.iter().copied().reduce(Vec2::min) else {
+        return;
+    };
+    let max = positions.iter().copied().fold(min, Vec2::max);
+
+    // Leave room for a whole hex around the outermost centers.
+    let margin = consts::HEX_SIZE;
+    let extent = (max - min) + Vec2::splat(2.0 * margin);
+    let scale = (rect.width() / extent.x).min(rect.height() / extent.y);
+    let offset = egui::vec2(
+        (rect.width() - extent.x * scale) / 2.0,
+        (rect.height() - extent.y * scale) / 2.0,
+    );
+
+    for ((province, owner, _), world_pos) in provinces.iter().zip(positions) {
+        // World space has y pointing up, egui has it pointing down.
+        let center = rect.left_top()
+            + offset
+            + egui::vec2(
+                (world_pos.x - min.x + margin) * scale,
+                (max.y - world_pos.y + margin) * scale,
+            );
+        let color = match (highlighted, owner) {
+            (Some((country, color)), Some(owner)) if owner.0 == country => color,
+            _ if !province.is_ownable() => Color32::from_rgb(30, 50, 80),
+            (_, Some(_)) => Color32::from_gray(90),
+            (_, None) => Color32::from_gray(60),
+        };
+        let points = (0..6)
+            .map(|i| {
+                // Pointy top hexes have their first corner at 30 degrees.
+                let angle = (60.0 * i as f32 + 30.0).to_radians();
+                center + egui::vec2(angle.cos(), -angle.sin()) * consts::HEX_SIZE * scale * 0.95
+            })
+            .collect();
+        painter.add(egui::Shape::convex_polygon(
+            points,
+            color,
+            egui::Stroke::NONE,
+        ));
+    }
+}
+
+fn to_egui_color(color: Color) -> Color32 {
+    let [r, g, b, _] = color.to_srgba().to_u8_array();
+    Color32::from_rgb(r, g, b)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_game_setup(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<MenuState>>,
+    mut rules: ResMut<GameRules>,
+    mut tutorial: ResMut<Tutorial>,
+    mut regenerate_events: MessageWriter<RegenerateWorldEvent>,
+    player: Res<Player>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    provinces: Query<&Province>,
+    vfs: Res<VirtualFs>,
+    session: Option<Res<NetSession>>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let player_country_name = player
+        .country
+        .and_then(|e| countries.get(e).ok())
+        .map(|(_, name)| name.0.as_str())
+        .unwrap_or("-");
+    let max_ai_nations = countries.iter().count().saturating_sub(1);
+    let maps = available_maps(&vfs);
+    let mut country_names: Vec<&str> = countries.iter().map(|(_, name)| name.0.as_str()).collect();
+    country_names.sort_unstable();
+
+    egui::CentralPanel::default()
+        .frame(theme.menu_frame())
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+
+                ui.label(
+                    RichText::new("Game Setup")
+                        .font(egui::FontId::proportional(48.0))
+                        .color(Color32::WHITE)
+                        .strong(),
+                );
+
+                ui.add_space(10.0);
+
+                ui.label(
+                    RichText::new(format!("Playing as {}", player_country_name))
+                        .font(egui::FontId::proportional(20.0))
+                        .color(Color32::LIGHT_GRAY)
+                        .italics(),
+                );
+
+                ui.add_space(40.0);
+
+                egui::Grid::new("game_setup_grid")
+                    .num_columns(2)
+                    .spacing([40.0, 16.0])
+                    .show(ui, |ui| {
+                        setup_label(ui, "Map");
+                        let mut selected_map = rules.map_path.clone();
+                        egui::ComboBox::from_id_salt("map_choice")
+                            .selected_text(map_display_name(&selected_map))
+                            .show_ui(ui, |ui| {
+                                for map in &maps {
+                                    ui.selectable_value(
+                                        &mut selected_map,
+                                        map.clone(),
+                                        map_display_name(map),
+                                    );
+                                }
+                            });
+                        if selected_map != rules.map_path {
+                            info!("Switching map to {}", selected_map);
+                            rules.map_path = selected_map;
+                            regenerate_events.write(RegenerateWorldEvent);
+                        }
+                        ui.end_row();
+
+                        // Maps are hand-made files, so their size is fixed and only shown here.
+                        setup_label(ui, "Map size");
+                        ui.label(format!(
+                            "{} provinces, {} countries",
+                            provinces.iter().count(),
+                            countries.iter().count()
+                        ));
+                        ui.end_row();
+
+                        setup_label(ui, "Seed");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut rules.seed));
+                            if ui.button("🎲").on_hover_text("Random seed").clicked() {
+                                rules.seed = rand::random();
+                            }
+                        });
+                        ui.end_row();
+
+                        setup_label(ui, "AI nations");
+                        ui.horizontal(|ui| {
+                            let mut all_nations = rules.ai_nations.is_none();
+                            ui.checkbox(&mut all_nations, "All");
+                            if all_nations {
+                                rules.ai_nations = None;
+                            } else {
+                                let mut count = rules.ai_nations.unwrap_or(max_ai_nations);
+                                ui.add(egui::Slider::new(&mut count, 0..=max_ai_nations));
+                                rules.ai_nations = Some(count.min(max_ai_nations));
+                            }
+                        });
+                        ui.end_row();
+
+                        setup_label(ui, "Difficulty");
+                        ui.horizontal(|ui| {
+                            for difficulty in Difficulty::all() {
+                                ui.selectable_value(
+                                    &mut rules.difficulty,
+                                    difficulty,
+                                    difficulty.name(),
+                                );
+                            }
+                        });
+                        ui.end_row();
+
+                        setup_label(ui, "Ironman");
+                        ui.checkbox(&mut rules.ironman, "Autosave every turn, no reloading");
+                        ui.end_row();
+
+                        setup_label(ui, "Tutorial");
+                        ui.checkbox(&mut tutorial.enabled, "Guide me through the basics");
+                        ui.end_row();
+
+                        setup_label(ui, "Reinforcements");
+                        ui.add(
+                            egui::Slider::new(&mut rules.reinforcement_radius, 0..=3)
+                                .suffix(" hexes"),
+                        )
+                        .on_hover_text(
+                            "Idle armies this close to a battle join it, 0 turns it off",
+                        );
+                        ui.end_row();
+
+                        setup_label(ui, "Victory");
+                        ui.vertical(|ui| {
+                            ui.checkbox(&mut rules.conquest_victory, "Conquest of all provinces");
+                            ui.horizontal(|ui| {
+                                let mut has_limit = rules.turn_limit.is_some();
+                                ui.checkbox(&mut has_limit, "Turn limit");
+                                if has_limit {
+                                    let mut limit = rules.turn_limit.unwrap_or(100);
+                                    ui.add(egui::DragValue::new(&mut limit).range(1..=9999));
+                                    rules.turn_limit = Some(limit);
+                                } else {
+                                    rules.turn_limit = None;
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    });
+
+                ui.add_space(20.0);
+
+                egui::CollapsingHeader::new(
+                    RichText::new("Handicaps").strong().color(Color32::GOLD),
+                )
+                .show(ui, |ui| draw_handicaps(ui, &mut rules, &country_names));
+
+                ui.add_space(40.0);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(ui.available_width() / 2.0 - 160.0);
+
+                    if ui
+                        .add_sized(
+                            egui::vec2(150.0, 40.0),
+                            egui::Button::new(
+                                RichText::new("← Back")
+                                    .font(egui::FontId::proportional(18.0))
+                                    .color(Color32::WHITE),
+                            )
+                            .fill(Color32::from_rgb(80, 80, 80)),
+                        )
+                        .clicked()
+                        || settings.back_pressed(ui.ctx())
+                    {
+                        next_state.set(MenuState::CountrySelection);
+                    }
+
+                    ui.add_space(20.0);
+
+                    // In a multiplayer game only the host starts.
+                    let can_start = !session.as_ref().is_some_and(|s| s.is_client());
+                    let start = ui
+                        .add_enabled_ui(can_start, |ui| {
+                            ui.add_sized(
+                                egui::vec2(150.0, 40.0),
+                                egui::Button::new(
+                                    RichText::new("▶ Start Game")
+                                        .font(egui::FontId::proportional(18.0))
+                                        .color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(60, 120, 80)),
+                            )
+                        })
+                        .inner;
+                    settings.focus_default(&start);
+                    if start.clicked() {
+                        info!("Starting game with seed {}", rules.seed);
+                        next_state.set(MenuState::InGame);
+                    }
+                });
+            });
+        });
+}
+
+/// Per-country bonuses on top of the difficulty. Only countries with a changed handicap are
+/// kept in the rules.
+fn draw_handicaps(ui: &mut egui::Ui, rules: &mut GameRules, country_names: &[&str]) {
+    egui::Grid::new("handicaps_grid")
+        .num_columns(5)
+        .spacing([20.0, 8.0])
+        .show(ui, |ui| {
+            for header in [
+                "Country",
+                "Income",
+                "Free manpower",
+                "Aggression",
+                "Personality",
+            ] {
+                setup_label(ui, header);
+            }
+            ui.end_row();
+
+            for &name in country_names {
+                let mut handicap = rules.handicaps.get(name).cloned().unwrap_or_default();
+                ui.label(name);
+                let mut income_percent = (handicap.income_bonus * 100.0).round() as i32;
+                ui.add(egui::Slider::new(&mut income_percent, -50..=100).suffix("%"));
+                handicap.income_bonus = income_percent as f32 / 100.0;
+                ui.add(
+                    egui::Slider::new(&mut handicap.free_manpower, 0..=MAX_FREE_MANPOWER)
+                        .step_by(f64::from(REGIMENT_SIZE)),
+                );
+                ui.add(
+                    egui::Slider::new(&mut handicap.aggression, 0.25..=4.0)
+                        .logarithmic(true)
+                        .suffix("×"),
+                );
+                egui::ComboBox::from_id_salt(("personality", name))
+                    .selected_text(handicap.personality.name())
+                    .show_ui(ui, |ui| {
+                        for personality in Personality::all() {
+                            ui.selectable_value(
+                                &mut handicap.personality,
+                                personality,
+                                personality.name(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                if handicap == Handicap::default() {
+                    rules.handicaps.remove(name);
+                } else {
+                    rules.handicaps.insert(name.to_string(), handicap);
+                }
+            }
+        });
+}
+
+fn setup_label(ui: &mut egui::Ui, text: &str) {
+    ui.label(RichText::new(text).strong().color(Color32::GOLD));
+}
+
+/// Turns "maps/europe.json" into "europe".
+fn map_display_name(path: &str) -> &str {
+    path.rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .trim_end_matches(".json")
+}
+
+#[allow(clippy::too_many_arguments)]
 fn display_pause_menu(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
     mut pause_menu: ResMut<PauseMenuOpen>,
     mut next_state: ResMut<NextState<MenuState>>,
-    mut save_events: MessageWriter<SaveGameEvent>,
-    mut load_events: MessageWriter<LoadGameEvent>,
+    slots: Res<SaveSlots>,
+    mut slots_window: ResMut<SaveSlotsWindow>,
+    mut settings_window: ResMut<SettingsWindowOpen>,
+    rules: Res<GameRules>,
+    mut app_exit: MessageWriter<AppExit>,
 ) {
     if !pause_menu.0 {
         return;
@@ -251,7 +845,9 @@ fn display_pause_menu(
         Err(_) => return,
     };
 
-    let has_save = save_exists();
+    let has_save = !slots.0.is_empty();
+    // Ironman games can't go back to an earlier save.
+    let can_load = has_save && !rules.ironman;
 
     egui::Area::new(egui::Id::new("pause_overlay"))
         .fixed_pos(egui::pos2(0.0, 0.0))
@@ -271,12 +867,7 @@ fn display_pause_menu(
         .resizable(false)
         .title_bar(false)
         .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-        .frame(
-            egui::Frame::new()
-                .fill(Color32::from_rgb(30, 30, 40))
-                .inner_margin(30.0)
-                .corner_radius(10.0),
-        )
+        .frame(theme.frame())
         .show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.label(
@@ -290,18 +881,17 @@ fn display_pause_menu(
 
                 let button_size = egui::vec2(200.0, 45.0);
 
-                if ui
-                    .add_sized(
-                        button_size,
-                        egui::Button::new(
-                            RichText::new("▶ Resume")
-                                .font(egui::FontId::proportional(20.0))
-                                .color(Color32::WHITE),
-                        )
-                        .fill(Color32::from_rgb(60, 120, 80)),
+                let resume = ui.add_sized(
+                    button_size,
+                    egui::Button::new(
+                        RichText::new("▶ Resume")
+                            .font(egui::FontId::proportional(20.0))
+                            .color(Color32::WHITE),
                     )
-                    .clicked()
-                {
+                    .fill(Color32::from_rgb(60, 120, 80)),
+                );
+                settings.focus_default(&resume);
+                if resume.clicked() {
                     pause_menu.0 = false;
                 }
 
@@ -319,8 +909,7 @@ fn display_pause_menu(
                     )
                     .clicked()
                 {
-                    save_events.write(SaveGameEvent);
-                    info!("Game saved!");
+                    slots_window.open(SlotAction::Save);
                 }
 
                 ui.add_space(15.0);
@@ -328,13 +917,13 @@ fn display_pause_menu(
                 let load_button = egui::Button::new(
                     RichText::new("📂 Load Game")
                         .font(egui::FontId::proportional(20.0))
-                        .color(if has_save {
+                        .color(if can_load {
                             Color32::WHITE
                         } else {
                             Color32::DARK_GRAY
                         }),
                 )
-                .fill(if has_save {
+                .fill(if can_load {
                     Color32::from_rgb(60, 100, 80)
                 } else {
                     Color32::from_rgb(40, 40, 40)
@@ -342,14 +931,31 @@ fn display_pause_menu(
 
                 let load_response = ui.add_sized(button_size, load_button);
 
-                if has_save && load_response.clicked() {
-                    load_events.write(LoadGameEvent);
-                    pause_menu.0 = false;
-                    info!("Loading saved game...");
+                if can_load && load_response.clicked() {
+                    slots_window.open(SlotAction::Load);
                 }
 
-                if !has_save {
-                    load_response.on_hover_text("No save file found");
+                if rules.ironman {
+                    load_response.on_hover_text("Loading is disabled in ironman games");
+                } else if !has_save {
+                    load_response.on_hover_text("No saved games found");
+                }
+
+                ui.add_space(15.0);
+
+                if ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("⚙ Settings")
+                                .font(egui::FontId::proportional(20.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 100)),
+                    )
+                    .clicked()
+                {
+                    settings_window.0 = true;
                 }
 
                 ui.add_space(15.0);
@@ -384,7 +990,7 @@ fn display_pause_menu(
                     )
                     .clicked()
                 {
-                    std::process::exit(0);
+                    app_exit.write(AppExit::Success);
                 }
 
                 ui.add_space(10.0);
@@ -399,6 +1005,286 @@ fn display_pause_menu(
         });
 }
 
+/// Lists the save slots with the country, turn and time of each save. Saving goes to a new slot
+/// or overwrites one, loading from the main menu starts the game, and slots can be deleted after
+/// confirming.
+#[allow(clippy::too_many_arguments)]
+fn display_save_slots_window(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut window: ResMut<SaveSlotsWindow>,
+    slots: Res<SaveSlots>,
+    state: Res<State<MenuState>>,
+    mut next_state: ResMut<NextState<MenuState>>,
+    mut pause_menu: ResMut<PauseMenuOpen>,
+    mut save_events: MessageWriter<SaveGameEvent>,
+    mut load_events: MessageWriter<LoadGameEvent>,
+    mut delete_events: MessageWriter<DeleteSaveEvent>,
+) {
+    let Some(mode) = window.mode else {
+        return;
+    };
+
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let mut close = ctx.input(|input| input.key_pressed(egui::Key::Escape));
+    let mut save_to = None;
+    let mut load_from = None;
+
+    egui::Window::new("Save Slots")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .default_width(480.0)
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(match mode {
+                    SlotAction::Save => "💾 Save Game",
+                    SlotAction::Load => "📂 Load Game",
+                });
+                if theme.close_button(ui) {
+                    close = true;
+                }
+            });
+            ui.separator();
+
+            if mode == SlotAction::Save {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut window.new_name)
+                            .hint_text("Save name")
+                            .desired_width(300.0),
+                    );
+                    let name = slot_name(&window.new_name);
+                    let label = match &name {
+                        Some(name) if slots.contains(name) => "Overwrite",
+                        _ => "Save",
+                    };
+                    if ui
+                        .add_enabled(name.is_some(), egui::Button::new(label))
+                        .clicked()
+                    {
+                        save_to = name;
+                    }
+                });
+                ui.add_space(8.0);
+            }
+
+            if slots.0.is_empty() {
+                ui.label(
+                    RichText::new("No saved games")
+                        .italics()
+                        .color(Color32::GRAY),
+                );
+                return;
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(360.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("save_slots")
+                        .num_columns(5)
+                        .striped(true)
+                        .spacing([16.0, 6.0])
+                        .show(ui, |ui| {
+                            for slot in &slots.0 {
+                                ui.label(RichText::new(&slot.name).strong());
+                                ui.label(slot.country.as_deref().unwrap_or("Observer"));
+                                ui.label(format!("Turn {}", slot.turn));
+                                ui.label(if slot.saved_at > 0 {
+                                    format_timestamp(slot.saved_at)
+                                } else {
+                                    "Unknown date".to_string()
+                                });
+                                let confirming = window.confirm_delete.as_ref() == Some(&slot.name);
+                                let clicked =
+                                    ui.horizontal(|ui| slot_buttons(ui, mode, confirming)).inner;
+                                match clicked {
+                                    Some(SlotClick::Use) if mode == SlotAction::Save => {
+                                        save_to = Some(slot.name.clone());
+                                    }
+                                    Some(SlotClick::Use) => load_from = Some(slot.name.clone()),
+                                    Some(SlotClick::Delete) => {
+                                        window.confirm_delete = Some(slot.name.clone());
+                                    }
+                                    Some(SlotClick::ConfirmDelete) => {
+                                        delete_events.write(DeleteSaveEvent {
+                                            slot: slot.name.clone(),
+                                        });
+                                        window.confirm_delete = None;
+                                    }
+                                    Some(SlotClick::CancelDelete) => window.confirm_delete = None,
+                                    None => {}
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+    if let Some(slot) = save_to {
+        save_events.write(SaveGameEvent::new(&slot));
+        close = true;
+    }
+    if let Some(slot) = load_from {
+        load_events.write(LoadGameEvent::new(&slot));
+        if *state.get() == MenuState::MainMenu {
+            next_state.set(MenuState::InGame);
+        }
+        pause_menu.0 = false;
+        close = true;
+    }
+    if close {
+        window.mode = None;
+    }
+}
+
+enum SlotClick {
+    /// Overwrite or load the slot, depending on the window's mode.
+    Use,
+    Delete,
+    ConfirmDelete,
+    CancelDelete,
+}
+
+fn slot_buttons(ui: &mut egui::Ui, mode: SlotAction, confirming_delete: bool) -> Option<SlotClick> {
+    if confirming_delete {
+        ui.label(RichText::new("Delete?").color(Color32::LIGHT_RED));
+        if ui.button("Yes").clicked() {
+            return Some(SlotClick::ConfirmDelete);
+        }
+        if ui.button("No").clicked() {
+            return Some(SlotClick::CancelDelete);
+        }
+        return None;
+    }
+    let action = match mode {
+        SlotAction::Save => "Overwrite",
+        SlotAction::Load => "Load",
+    };
+    if ui.button(action).clicked() {
+        return Some(SlotClick::Use);
+    }
+    ui.button("🗑 Delete").clicked().then_some(SlotClick::Delete)
+}
+
+/// Shown once the player's country has fallen. The player can watch the rest of the game without
+/// a country, or go back to the main menu.
+fn display_defeat_screen(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut defeat: ResMut<PlayerDefeat>,
+    mut player: ResMut<Player>,
+    mut next_state: ResMut<NextState<MenuState>>,
+) {
+    let Some(stats) = &defeat.stats else {
+        return;
+    };
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    egui::Area::new(egui::Id::new("defeat_overlay"))
+        .fixed_pos(egui::pos2(0.0, 0.0))
+        .show(ctx, |ui| {
+            let screen_rect = ui
+                .ctx()
+                .input(|i| i.viewport().inner_rect.unwrap_or(egui::Rect::NOTHING));
+            ui.painter().rect_filled(
+                screen_rect,
+                0.0,
+                Color32::from_rgba_unmultiplied(0, 0, 0, 200),
+            );
+        });
+
+    let mut observe = false;
+    let mut main_menu = false;
+    egui::Window::new("Defeat")
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .frame(theme.frame())
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new("☠ DEFEAT")
+                        .font(egui::FontId::proportional(36.0))
+                        .color(Color32::from_rgb(200, 60, 60))
+                        .strong(),
+                );
+                ui.label(
+                    RichText::new(format!(
+                        "{} has lost its last province and army.",
+                        stats.country
+                    ))
+                    .color(Color32::LIGHT_GRAY),
+                );
+
+                ui.add_space(20.0);
+
+                egui::Grid::new("defeat_stats")
+                    .num_columns(2)
+                    .spacing([20.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Turns survived").color(Color32::LIGHT_GRAY));
+                        ui.label(stats.turns_survived.to_string());
+                        ui.end_row();
+
+                        ui.label(RichText::new("Most provinces held").color(Color32::LIGHT_GRAY));
+                        ui.label(stats.peak_provinces.to_string());
+                        ui.end_row();
+                    });
+
+                ui.add_space(30.0);
+
+                let button_size = egui::vec2(220.0, 45.0);
+
+                observe = ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("👁 Continue as Observer")
+                                .font(egui::FontId::proportional(20.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 120)),
+                    )
+                    .on_hover_text("Watch the rest of the game play out")
+                    .clicked();
+
+                ui.add_space(15.0);
+
+                main_menu = ui
+                    .add_sized(
+                        button_size,
+                        egui::Button::new(
+                            RichText::new("🏠 Main Menu")
+                                .font(egui::FontId::proportional(20.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(120, 100, 60)),
+                    )
+                    .clicked();
+            });
+        });
+
+    if observe {
+        player.country = None;
+        defeat.stats = None;
+    }
+    if main_menu {
+        defeat.stats = None;
+        next_state.set(MenuState::MainMenu);
+    }
+}
+
 fn hide_menu(mut pause_menu: ResMut<PauseMenuOpen>) {
     pause_menu.0 = false;
     info!("Game started - hiding menu");
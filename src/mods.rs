@@ -0,0 +1,154 @@
+﻿use crate::egui_common::UiTheme;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy_egui::egui::{Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::mods::{
+    AvailableMods, EnabledMods, MODS_DIRECTORY, VirtualFs, save_enabled_mods,
+};
+use eu6_simulation::world::RegenerateWorldEvent;
+
+pub struct ModsUiPlugin;
+
+impl Plugin for ModsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiPrimaryContextPass,
+            display_mod_selection.run_if(in_state(MenuState::ModSelection)),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_mod_selection(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    settings: Res<Settings>,
+    available: Res<AvailableMods>,
+    mut enabled: ResMut<EnabledMods>,
+    mut vfs: ResMut<VirtualFs>,
+    mut next_state: ResMut<NextState<MenuState>>,
+    mut regenerate_events: MessageWriter<RegenerateWorldEvent>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    egui::CentralPanel::default()
+        .frame(theme.menu_frame())
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+
+                ui.label(
+                    RichText::new("Mods")
+                        .font(egui::FontId::proportional(48.0))
+                        .color(Color32::WHITE)
+                        .strong(),
+                );
+
+                ui.add_space(10.0);
+
+                ui.label(
+                    RichText::new("Mods lower in the list override the ones above them")
+                        .font(egui::FontId::proportional(20.0))
+                        .color(Color32::LIGHT_GRAY)
+                        .italics(),
+                );
+
+                ui.add_space(40.0);
+
+                if available.0.is_empty() {
+                    ui.label(format!(
+                        "No mods found in the {} directory.",
+                        MODS_DIRECTORY
+                    ));
+                }
+
+                ui.set_max_width(600.0);
+                // Enabled mods in load order first, then the others by id.
+                let ordered = enabled
+                    .0
+                    .iter()
+                    .filter_map(|id| available.0.iter().find(|m| &m.id == id))
+                    .chain(available.0.iter().filter(|m| !enabled.0.contains(&m.id)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                for info in &ordered {
+                    let position = enabled.0.iter().position(|id| id == &info.id);
+                    ui.horizontal(|ui| {
+                        let mut is_enabled = position.is_some();
+                        if ui.checkbox(&mut is_enabled, "").changed() {
+                            if is_enabled {
+                                enabled.0.push(info.id.clone());
+                            } else {
+                                enabled.0.retain(|id| id != &info.id);
+                            }
+                        }
+                        ui.vertical(|ui| {
+                            ui.label(
+                                RichText::new(format!("{} {}", info.name, info.version))
+                                    .strong()
+                                    .color(Color32::GOLD),
+                            );
+                            if !info.description.is_empty() {
+                                ui.label(&info.description);
+                            }
+                        });
+                        if let Some(index) = position {
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .add_enabled(
+                                            index + 1 < enabled.0.len(),
+                                            egui::Button::new("⬇"),
+                                        )
+                                        .on_hover_text("Load later")
+                                        .clicked()
+                                    {
+                                        enabled.0.swap(index, index + 1);
+                                    }
+                                    if ui
+                                        .add_enabled(index > 0, egui::Button::new("⬆"))
+                                        .on_hover_text("Load earlier")
+                                        .clicked()
+                                    {
+                                        enabled.0.swap(index, index - 1);
+                                    }
+                                },
+                            );
+                        }
+                    });
+                    ui.separator();
+                }
+
+                ui.add_space(40.0);
+
+                if ui
+                    .add_sized(
+                        egui::vec2(200.0, 40.0),
+                        egui::Button::new(
+                            RichText::new("⬅ Back")
+                                .font(egui::FontId::proportional(20.0))
+                                .color(Color32::WHITE),
+                        )
+                        .fill(Color32::from_rgb(80, 80, 100)),
+                    )
+                    .clicked()
+                    || settings.back_pressed(ui.ctx())
+                {
+                    if vfs.mods() != enabled.0.as_slice() {
+                        info!("Applying mods: {:?}", enabled.0);
+                        save_enabled_mods(&enabled);
+                        *vfs = VirtualFs::new(&enabled.0);
+                        regenerate_events.write(RegenerateWorldEvent);
+                    }
+                    next_state.set(MenuState::MainMenu);
+                }
+            });
+        });
+}
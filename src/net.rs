@@ -0,0 +1,84 @@
+﻿use crate::egui_common::UiTheme;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::country::{Country, DisplayName};
+use eu6_simulation::errors::GameError;
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::net::{NET_PORT, NetSession, country_name};
+use eu6_simulation::player::Player;
+
+pub struct MultiplayerUiPlugin;
+
+impl Plugin for MultiplayerUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiPrimaryContextPass,
+            display_multiplayer_window.run_if(in_state(MenuState::GameSetup)),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_multiplayer_window(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    session: Option<Res<NetSession>>,
+    player: Res<Player>,
+    countries: Query<(Entity, &DisplayName), With<Country>>,
+    mut errors: MessageWriter<GameError>,
+    mut address: Local<String>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    if address.is_empty() {
+        *address = "127.0.0.1".to_string();
+    }
+
+    egui::Window::new("Multiplayer")
+        .frame(theme.frame())
+        .anchor(Align2::RIGHT_TOP, [-20.0, 20.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            if let Some(session) = &session {
+                ui.label(RichText::new(session.status()).color(Color32::LIGHT_GRAY));
+                if ui.button("Leave").clicked() {
+                    commands.remove_resource::<NetSession>();
+                }
+                return;
+            }
+
+            if ui.button("🖧 Host game").clicked() {
+                match NetSession::host() {
+                    Ok(session) => commands.insert_resource(session),
+                    Err(e) => {
+                        errors.write(GameError::new(
+                            "Couldn't host the game",
+                            format!("Port {} could not be opened: {}", NET_PORT, e),
+                        ));
+                    }
+                }
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Address");
+                ui.text_edit_singleline(&mut *address);
+            });
+            if ui.button("🔗 Join game").clicked() {
+                let country = country_name(&countries, player.country).unwrap_or_default();
+                match NetSession::join(&address, &country) {
+                    Ok(session) => commands.insert_resource(session),
+                    Err(e) => {
+                        errors.write(GameError::new(
+                            "Couldn't join the game",
+                            format!("No game found at {}: {}", address.as_str(), e),
+                        ));
+                    }
+                }
+            }
+        });
+}
@@ -0,0 +1,351 @@
+﻿use crate::egui_common::UiTheme;
+use crate::layout::CameraFocusEvent;
+use crate::selection::Selection;
+use crate::settings::{Settings, dragged_to};
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::{
+    Army, BattleEndedEvent, BattleJoinedEvent, BattleSide, BattleStartedEvent,
+};
+use eu6_simulation::consts;
+use eu6_simulation::country::DisplayName;
+use eu6_simulation::hex::Hex;
+use eu6_simulation::map::{Owner, Province, ProvinceHexMap};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::notifications::{Notification, NotificationTarget, Notifications};
+use eu6_simulation::player::Player;
+use eu6_simulation::war::{PeaceAnsweredEvent, SiegeCompletedEvent};
+
+pub struct NotificationsPlugin;
+
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, notify_battle_started)
+            .add_systems(Update, notify_battle_joined)
+            .add_systems(Update, notify_battle_ended)
+            .add_systems(Update, notify_siege_completed)
+            .add_systems(Update, notify_peace_answered)
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_notifications.run_if(in_state(MenuState::InGame)),
+            );
+    }
+}
+
+/// Name the notifications window's layout is kept under in the settings.
+const NOTIFICATIONS_WINDOW: &str = "notifications";
+
+// ============================================================================
+// COLLECTING
+// ============================================================================
+
+fn notify_battle_started(
+    mut events: MessageReader<BattleStartedEvent>,
+    mut notifications: ResMut<Notifications>,
+    player: Res<Player>,
+    province_map: Res<ProvinceHexMap>,
+    provinces: Query<&Province>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    for event in events.read() {
+        let player_army = if event.attacker_country == player_country {
+            event.attacker
+        } else if event.defender_country == player_country {
+            event.defender
+        } else {
+            continue;
+        };
+
+        notifications.push(Notification {
+            title: "⚔ Battle started".to_string(),
+            text: format!(
+                "Our army is fighting at {}.",
+                location_name(&event.location, &province_map, &provinces)
+            ),
+            target: Some(NotificationTarget::Army {
+                army: player_army,
+                location: event.location,
+            }),
+        });
+    }
+}
+
+fn notify_battle_joined(
+    mut events: MessageReader<BattleJoinedEvent>,
+    mut notifications: ResMut<Notifications>,
+    player: Res<Player>,
+    province_map: Res<ProvinceHexMap>,
+    provinces: Query<&Province>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    for event in events.read().filter(|e| e.country == player_country) {
+        notifications.push(Notification {
+            title: "⚔ Joined battle".to_string(),
+            text: format!(
+                "Our army has joined the fighting at {}.",
+                location_name(&event.location, &province_map, &provinces)
+            ),
+            target: Some(NotificationTarget::Army {
+                army: event.army,
+                location: event.location,
+            }),
+        });
+    }
+}
+
+fn notify_battle_ended(
+    mut events: MessageReader<BattleEndedEvent>,
+    mut notifications: ResMut<Notifications>,
+    player: Res<Player>,
+    province_map: Res<ProvinceHexMap>,
+    provinces: Query<&Province>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    for event in events.read() {
+        let player_side = if event.attacker_country == player_country {
+            BattleSide::Attacker
+        } else if event.defender_country == player_country {
+            BattleSide::Defender
+        } else {
+            continue;
+        };
+
+        let location = location_name(&event.location, &province_map, &provinces);
+        let (title, text) = match event.winner {
+            Some(side) if side == player_side => (
+                "🏆 Battle won",
+                format!("Our army was victorious at {}.", location),
+            ),
+            Some(_) => (
+                "💀 Battle lost",
+                format!("Our army was defeated at {}.", location),
+            ),
+            None => (
+                "💀 Battle ended",
+                format!("Both armies were destroyed at {}.", location),
+            ),
+        };
+
+        let target = match event.winner {
+            Some(side) if side == player_side => {
+                event
+                    .survivors
+                    .first()
+                    .map(|&army| NotificationTarget::Army {
+                        army,
+                        location: event.location,
+                    })
+            }
+            _ => None,
+        }
+        .or_else(|| {
+            province_map
+                .get_entity(&event.location)
+                .map(|&p| NotificationTarget::Province(p))
+        });
+
+        notifications.push(Notification {
+            title: title.to_string(),
+            text,
+            target,
+        });
+    }
+}
+
+fn notify_siege_completed(
+    mut events: MessageReader<SiegeCompletedEvent>,
+    mut notifications: ResMut<Notifications>,
+    player: Res<Player>,
+    provinces: Query<(&Province, &Owner)>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+
+    for event in events.read() {
+        let Ok((province, owner)) = provinces.get(event.province) else {
+            continue;
+        };
+
+        let (title, text) = if owner.0 == player_country {
+            (
+                "🏰 Province occupied",
+                format!("{} has been occupied by the enemy.", province.name()),
+            )
+        } else if event.occupier == player_country {
+            (
+                "🏰 Siege completed",
+                format!("Our troops have occupied {}.", province.name()),
+            )
+        } else {
+            continue;
+        };
+
+        notifications.push(Notification {
+            title: title.to_string(),
+            text,
+            target: Some(NotificationTarget::Province(event.province)),
+        });
+    }
+}
+
+/// Tells the player how an AI country answered their peace offer and why.
+fn notify_peace_answered(
+    mut events: MessageReader<PeaceAnsweredEvent>,
+    mut notifications: ResMut<Notifications>,
+    player: Res<Player>,
+    names: Query<&DisplayName>,
+) {
+    for event in events.read().filter(|e| player.country == Some(e.from)) {
+        let name = names.get(event.to).map_or("The enemy", |n| n.0.as_str());
+        let (title, answer) = if event.acceptance.accepted() {
+            ("🕊 Peace accepted", "accepts")
+        } else {
+            ("🕊 Peace refused", "refuses")
+        };
+        notifications.push(Notification {
+            title: title.to_string(),
+            text: format!(
+                "{} {} our peace offer:\n{}",
+                name,
+                answer,
+                event.acceptance.describe()
+            ),
+            target: None,
+        });
+    }
+}
+
+fn location_name(hex: &Hex, province_map: &ProvinceHexMap, provinces: &Query<&Province>) -> String {
+    province_map
+        .get_entity(hex)
+        .and_then(|&e| provinces.get(e).ok())
+        .map(|p| p.name().to_string())
+        .unwrap_or_else(|| format!("({}, {})", hex.q(), hex.r()))
+}
+
+// ============================================================================
+// UI
+// ============================================================================
+
+#[allow(clippy::too_many_arguments)]
+fn display_notifications(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut settings: ResMut<Settings>,
+    mut notifications: ResMut<Notifications>,
+    mut selection: Selection,
+    mut focus_events: MessageWriter<CameraFocusEvent>,
+    province_map: Res<ProvinceHexMap>,
+    armies: Query<&Transform, With<Army>>,
+    provinces: Query<&Province>,
+) {
+    if notifications.is_empty() {
+        return;
+    }
+
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let mut dismissed = None;
+    let mut go_to = None;
+
+    let window = egui::Window::new("Notifications")
+        .frame(theme.frame())
+        .title_bar(false)
+        .resizable(false)
+        .default_width(300.0);
+    let shown = settings
+        .place_window(
+            window,
+            NOTIFICATIONS_WINDOW,
+            ctx,
+            Align2::CENTER_TOP,
+            [0.0, 20.0],
+        )
+        .show(ctx, |ui| {
+            for (i, notification) in notifications.iter().enumerate().rev() {
+                ui.label(
+                    RichText::new(&notification.title)
+                        .strong()
+                        .color(Color32::GOLD),
+                );
+                ui.label(&notification.text);
+                ui.horizontal(|ui| {
+                    if let Some(target) = notification.target
+                        && ui.button("🔍 Go to").clicked()
+                    {
+                        go_to = Some(target);
+                        dismissed = Some(i);
+                    }
+                    if ui.button("✓ Dismiss").clicked() {
+                        dismissed = Some(i);
+                    }
+                });
+                ui.separator();
+            }
+        });
+
+    if let Some(pos) = dragged_to(&shown) {
+        settings.remember_window_pos(NOTIFICATIONS_WINDOW, pos);
+    }
+    if let Some(target) = go_to {
+        focus_target(
+            target,
+            &mut selection,
+            &mut focus_events,
+            &province_map,
+            &armies,
+            &provinces,
+        );
+    }
+    if let Some(i) = dismissed {
+        notifications.dismiss(i);
+    }
+}
+
+/// Selects the notification's target and centers the camera on it.
+fn focus_target(
+    target: NotificationTarget,
+    selection: &mut Selection,
+    focus_events: &mut MessageWriter<CameraFocusEvent>,
+    province_map: &ProvinceHexMap,
+    armies: &Query<&Transform, With<Army>>,
+    provinces: &Query<&Province>,
+) {
+    let province_entity = match target {
+        NotificationTarget::Army { army, location } => {
+            if let Ok(transform) = armies.get(army) {
+                selection.select_army(army);
+                focus_events.write(CameraFocusEvent::new(transform.translation.truncate()));
+                return;
+            }
+            // The army has been destroyed since the notification was created, show where it was.
+            match province_map.get_entity(&location) {
+                Some(&province_entity) => province_entity,
+                None => return,
+            }
+        }
+        NotificationTarget::Province(province_entity) => province_entity,
+    };
+
+    let Ok(province) = provinces.get(province_entity) else {
+        return;
+    };
+    selection.select_province(province_entity);
+    focus_events.write(CameraFocusEvent::new(
+        province.get_hex().axial_to_world(consts::HEX_SIZE),
+    ));
+}
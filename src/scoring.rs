@@ -0,0 +1,136 @@
+﻿use crate::egui_common::UiTheme;
+use bevy::prelude::*;
+use bevy_egui::egui::{Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::scoring::{CountryScore, Scoreboard};
+
+pub struct ScoringUiPlugin;
+
+impl Plugin for ScoringUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiPrimaryContextPass,
+            display_final_scores.run_if(in_state(MenuState::InGame)),
+        );
+    }
+}
+
+/// Colors of the score categories in the breakdown bars, in the order they are drawn.
+const CATEGORY_COLORS: [(&str, Color32); 4] = [
+    ("Development", Color32::from_rgb(90, 160, 90)),
+    ("Income", Color32::from_rgb(210, 180, 60)),
+    ("Prestige", Color32::from_rgb(110, 140, 210)),
+    ("Wars won", Color32::from_rgb(200, 80, 70)),
+];
+
+/// Ranked end screen, with a bar per country breaking its score down by category.
+fn display_final_scores(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut next_state: ResMut<NextState<MenuState>>,
+) {
+    let Some(results) = scoreboard.shown_results() else {
+        return;
+    };
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+
+    let best = results
+        .countries
+        .first()
+        .map_or(1, |score| score.total.max(1));
+    let mut close = false;
+    let mut main_menu = false;
+    egui::Window::new("Final Standings")
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .frame(theme.frame())
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new("🏆 FINAL STANDINGS")
+                        .font(egui::FontId::proportional(28.0))
+                        .color(Color32::GOLD)
+                        .strong(),
+                );
+                ui.label(
+                    RichText::new(format!("The game ended on turn {}.", results.turn))
+                        .color(Color32::LIGHT_GRAY),
+                );
+            });
+            ui.add_space(12.0);
+
+            ui.horizontal(|ui| {
+                for (category, color) in CATEGORY_COLORS {
+                    ui.label(RichText::new("■").color(color));
+                    ui.label(category);
+                    ui.add_space(6.0);
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("final_scores")
+                        .num_columns(4)
+                        .spacing([12.0, 6.0])
+                        .show(ui, |ui| {
+                            for (rank, score) in results.countries.iter().enumerate() {
+                                draw_score_row(ui, rank + 1, score, best);
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.add_space(12.0);
+            ui.horizontal(|ui| {
+                close = ui.button("▶ Keep Playing").clicked();
+                main_menu = ui.button("🏠 Main Menu").clicked();
+            });
+        });
+
+    if close || main_menu {
+        scoreboard.hide();
+    }
+    if main_menu {
+        next_state.set(MenuState::MainMenu);
+    }
+}
+
+fn draw_score_row(ui: &mut egui::Ui, rank: usize, score: &CountryScore, best: u32) {
+    const BAR_WIDTH: f32 = 240.0;
+    const BAR_HEIGHT: f32 = 14.0;
+
+    let [r, g, b] = score.color;
+    ui.label(format!("{}.", rank));
+    ui.label(RichText::new(&score.country).color(Color32::from_rgb(r, g, b)));
+    ui.label(RichText::new(score.total.to_string()).strong());
+
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(BAR_WIDTH, BAR_HEIGHT), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let mut left = rect.left();
+    let points = [
+        score.development,
+        score.income,
+        score.prestige,
+        score.wars_won,
+    ];
+    for (value, (_, color)) in points.iter().zip(CATEGORY_COLORS) {
+        let width = BAR_WIDTH * *value as f32 / best as f32;
+        let segment =
+            egui::Rect::from_min_size(egui::pos2(left, rect.top()), egui::vec2(width, BAR_HEIGHT));
+        painter.rect_filled(segment, 0.0, color);
+        left += width;
+    }
+    response.on_hover_text(format!(
+        "Development {} · Income {} · Prestige {} · Wars won {}",
+        score.development, score.income, score.prestige, score.wars_won
+    ));
+}
@@ -0,0 +1,72 @@
+﻿use crate::egui_common::UiTheme;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::{PlayerCommand, PlayerCommands};
+use eu6_simulation::scripting::PendingEvent;
+
+pub struct ScriptingUiPlugin;
+
+impl Plugin for ScriptingUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiPrimaryContextPass,
+            display_event_popup.run_if(in_state(MenuState::InGame)),
+        );
+    }
+}
+
+fn display_event_popup(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    player: Res<Player>,
+    pending_events: Query<&PendingEvent>,
+    mut player_commands: PlayerCommands,
+) {
+    let Some(event) = player
+        .country
+        .and_then(|country| pending_events.iter().find(|event| event.country == country))
+    else {
+        return;
+    };
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    egui::Window::new("Event")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .default_width(350.0)
+        .show(ctx, |ui| {
+            ui.heading(event.script.name.as_str());
+            ui.label(RichText::new(event.scope.province.as_str()).color(Color32::LIGHT_GRAY));
+            ui.separator();
+            ui.label(event.scope.fill_in(&event.script.description));
+            ui.add_space(12.0);
+            // Answers in a multiplayer game wait for the end of the turn, the event can't be
+            // answered twice meanwhile.
+            let answered = player_commands.queued(|command| {
+                matches!(command, PlayerCommand::ChooseEventOption { event: name, .. }
+                    if *name == event.script.name)
+            });
+            if answered {
+                ui.label(
+                    RichText::new("Your answer is carried out when the turn ends.")
+                        .color(Color32::LIGHT_GRAY),
+                );
+                return;
+            }
+            for (i, option) in event.script.options.iter().enumerate() {
+                let button = egui::Button::new(option.as_str())
+                    .min_size(egui::vec2(ui.available_width(), 0.0));
+                if ui.add(button).clicked() {
+                    player_commands.choose_event_option(event.country, &event.script.name, i);
+                }
+            }
+        });
+}
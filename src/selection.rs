@@ -0,0 +1,155 @@
+﻿use crate::army::SelectedArmy;
+use crate::country::SelectedCountry;
+use crate::map::{InteractionState, SelectedProvince};
+use crate::trade::SelectedFleet;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// Selects and deselects provinces, armies, fleets and countries in one place. Armies, fleets and
+/// countries open their panels in the same corner of the screen, so only one of them is selected
+/// at a time. The province panel has a side of its own and stays open beneath them.
+#[derive(SystemParam)]
+pub(crate) struct Selection<'w, 's> {
+    commands: Commands<'w, 's>,
+    province: ResMut<'w, SelectedProvince>,
+    army: ResMut<'w, SelectedArmy>,
+    fleet: ResMut<'w, SelectedFleet>,
+    country: ResMut<'w, SelectedCountry>,
+}
+
+impl Selection<'_, '_> {
+    pub(crate) fn province(&self) -> Option<Entity> {
+        self.province.get()
+    }
+
+    pub(crate) fn army(&self) -> Option<Entity> {
+        self.army.get()
+    }
+
+    pub(crate) fn fleet(&self) -> Option<Entity> {
+        self.fleet.get()
+    }
+
+    pub(crate) fn select_province(&mut self, province: Entity) {
+        self.clear_province();
+        self.commands
+            .entity(province)
+            .insert(InteractionState::Selected);
+        self.province.set(province);
+    }
+
+    pub(crate) fn clear_province(&mut self) {
+        if let Some(prev) = self.province.get() {
+            self.commands
+                .entity(prev)
+                .try_insert(InteractionState::None);
+            self.province.clear();
+        }
+    }
+
+    pub(crate) fn select_army(&mut self, army: Entity) {
+        self.clear_side_panels();
+        self.commands
+            .entity(army)
+            .insert(InteractionState::Selected);
+        self.army.set(army);
+    }
+
+    pub(crate) fn clear_army(&mut self) {
+        // The army may have been destroyed since it was selected.
+        if let Some(prev) = self.army.get() {
+            self.commands
+                .entity(prev)
+                .try_insert(InteractionState::None);
+            self.army.clear();
+        }
+    }
+
+    pub(crate) fn select_fleet(&mut self, fleet: Entity) {
+        self.clear_side_panels();
+        self.fleet.set(fleet);
+    }
+
+    pub(crate) fn clear_fleet(&mut self) {
+        if self.fleet.get().is_some() {
+            self.fleet.clear();
+        }
+    }
+
+    pub(crate) fn select_country(&mut self, country: Entity) {
+        self.clear_side_panels();
+        self.country.select(country);
+    }
+
+    /// Deselects whatever has its panel in the top right corner.
+    fn clear_side_panels(&mut self) {
+        self.clear_army();
+        self.clear_fleet();
+        if self.country.get().is_some() {
+            self.country.clear();
+        }
+    }
+
+    /// Deselects the top-most selection: the army, fleet or country, then the province beneath.
+    /// Returns whether anything was selected.
+    pub(crate) fn clear_top(&mut self) -> bool {
+        if self.army.get().is_some() || self.fleet.get().is_some() || self.country.get().is_some() {
+            self.clear_side_panels();
+        } else if self.province.get().is_some() {
+            self.clear_province();
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use eu6_simulation::hex::Hex;
+    use eu6_simulation::test_utils::TestGame;
+
+    #[test]
+    fn side_panels_take_turns_and_escape_clears_the_top_one() {
+        let mut game = TestGame::new();
+        let country = game.spawn_country("Country");
+        let province = game.spawn_province("Province", Hex::new(0, 0), Some(country));
+        let army = game.spawn_army(country, Hex::new(0, 0), 3000);
+        let world = game.world_mut();
+        world.entity_mut(province).insert(InteractionState::None);
+        world.entity_mut(army).insert(InteractionState::None);
+        world.insert_resource(SelectedProvince::default());
+        world.insert_resource(SelectedArmy::default());
+        world.insert_resource(SelectedFleet::default());
+        world.insert_resource(SelectedCountry::default());
+
+        game.world_mut()
+            .run_system_once(move |mut selection: Selection| {
+                selection.select_province(province);
+                selection.select_country(country);
+                selection.select_army(army);
+            })
+            .unwrap();
+        // The army replaced the country in the corner, the province stays selected.
+        assert_eq!(game.world().resource::<SelectedCountry>().get(), None);
+        assert_eq!(game.world().resource::<SelectedArmy>().get(), Some(army));
+        assert!(game.get::<InteractionState>(army) == Some(&InteractionState::Selected));
+
+        let clear_top = |game: &mut TestGame| {
+            game.world_mut()
+                .run_system_once(|mut selection: Selection| selection.clear_top())
+                .unwrap()
+        };
+        assert!(clear_top(&mut game));
+        assert_eq!(game.world().resource::<SelectedArmy>().get(), None);
+        assert_eq!(
+            game.world().resource::<SelectedProvince>().get(),
+            Some(province)
+        );
+        assert!(clear_top(&mut game));
+        assert!(game.get::<InteractionState>(province) == Some(&InteractionState::None));
+        assert!(!clear_top(&mut game));
+    }
+}
@@ -0,0 +1,409 @@
+﻿use crate::egui_common::UiTheme;
+use crate::map::MapPalette;
+use bevy::prelude::*;
+use bevy_egui::egui::{Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings())
+            .insert_resource(SettingsWindowOpen(false))
+            .add_systems(
+                EguiPrimaryContextPass,
+                (apply_accessibility_settings, display_settings_window),
+            )
+            .add_systems(
+                Update,
+                apply_panel_side.run_if(resource_changed::<Settings>),
+            );
+    }
+}
+
+const SETTINGS_FILE_PATH: &str = "settings.json";
+
+/// Zoom of the whole interface in large text mode.
+const LARGE_TEXT_ZOOM: f32 = 1.25;
+
+/// User preferences persisted between sessions. Missing fields in the settings file fall back to
+/// their defaults, so adding new options doesn't invalidate old files.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) master_volume: f32,
+    pub(crate) music_volume: f32,
+    pub(crate) effects_volume: f32,
+    pub(crate) map_palette: MapPalette,
+    /// Show occupied provinces with stripes in the occupier's color instead of mixing colors.
+    pub(crate) occupation_hatching: bool,
+    /// Animate armies marching between hexes instead of moving them instantly.
+    pub(crate) animate_army_movement: bool,
+    /// Let the player's armies march around enemy armies in their way instead of attacking them.
+    pub(crate) march_around_enemies: bool,
+    /// Stop the player's armies next to another of their armies they march into, and ask before
+    /// merging them. Holding Ctrl when giving the order merges them without asking.
+    pub(crate) confirm_army_merges: bool,
+    /// Pan the camera when the cursor rests near the window edges.
+    pub(crate) edge_scrolling: bool,
+    /// Edge scrolling speed in world units per second.
+    pub(crate) edge_scroll_speed: f32,
+    /// Scale the whole interface up so text is easier to read.
+    pub(crate) large_text: bool,
+    /// Focus the default button of menus, so they can be used with Tab, Enter and Escape alone.
+    pub(crate) keyboard_navigation: bool,
+    /// Describe the widgets under focus to screen readers, where egui supports it.
+    pub(crate) screen_reader: bool,
+    /// Show the province panel on the right and the country, army and fleet panels on the left,
+    /// for left-handed players.
+    pub(crate) mirror_panels: bool,
+    /// Moveable windows by name, as they were left in the last session.
+    pub(crate) windows: BTreeMap<String, WindowLayout>,
+}
+
+/// Whether a moveable window was open and where it was, see [`Settings::remember_window`].
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(default)]
+pub(crate) struct WindowLayout {
+    pub(crate) open: bool,
+    /// Top-left corner in points, `None` until the player moves the window.
+    pub(crate) pos: Option<[f32; 2]>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.8,
+            music_volume: 0.6,
+            effects_volume: 0.8,
+            map_palette: MapPalette::default(),
+            occupation_hatching: false,
+            animate_army_movement: true,
+            march_around_enemies: false,
+            confirm_army_merges: false,
+            edge_scrolling: true,
+            edge_scroll_speed: 500.0,
+            large_text: false,
+            keyboard_navigation: false,
+            screen_reader: false,
+            mirror_panels: false,
+            windows: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Final volume of background music after applying the master volume.
+    pub(crate) fn music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    /// Final volume of sound effects after applying the master volume.
+    pub(crate) fn effects_volume(&self) -> f32 {
+        self.master_volume * self.effects_volume
+    }
+
+    /// Focuses the menu's default widget while nothing else has focus, with keyboard navigation
+    /// on. Tab then moves on from it and Enter activates it.
+    pub(crate) fn focus_default(&self, response: &egui::Response) {
+        if self.keyboard_navigation && response.ctx.memory(|memory| memory.focused().is_none()) {
+            response.request_focus();
+        }
+    }
+
+    /// Whether Escape was pressed to go back from a menu, with keyboard navigation on.
+    pub(crate) fn back_pressed(&self, ctx: &egui::Context) -> bool {
+        self.keyboard_navigation && ctx.input(|input| input.key_pressed(egui::Key::Escape))
+    }
+
+    /// Layout the window was left in, closed and at its default position if it never changed.
+    pub(crate) fn window(&self, name: &str) -> WindowLayout {
+        self.windows.get(name).copied().unwrap_or_default()
+    }
+
+    /// Keeps the layout of a moveable window for the next session. The settings file is only
+    /// written when it changed.
+    pub(crate) fn remember_window(&mut self, name: &str, layout: WindowLayout) {
+        if self.window(name) != layout {
+            self.windows.insert(name.to_string(), layout);
+            save_settings(self);
+        }
+    }
+
+    /// Keeps where a moveable window was dragged to, see [`dragged_to`].
+    pub(crate) fn remember_window_pos(&mut self, name: &str, pos: [f32; 2]) {
+        let layout = WindowLayout {
+            pos: Some(pos),
+            ..self.window(name)
+        };
+        self.remember_window(name, layout);
+    }
+
+    /// Places a moveable window where the player left it. Until it is moved, its `align` corner
+    /// sits `offset` points away from the same corner of the screen.
+    pub(crate) fn place_window<'open>(
+        &self,
+        window: egui::Window<'open>,
+        name: &str,
+        ctx: &egui::Context,
+        align: egui::Align2,
+        offset: [f32; 2],
+    ) -> egui::Window<'open> {
+        if let Some(pos) = self.window(name).pos {
+            return window.default_pos(pos);
+        }
+        let size = ctx.input(|input| {
+            input
+                .viewport()
+                .inner_rect
+                .map_or(egui::vec2(1280.0, 720.0), |rect| rect.size())
+        });
+        let corner = align.pos_in_rect(&egui::Rect::from_min_size(egui::Pos2::ZERO, size));
+        window
+            .pivot(align)
+            .default_pos(corner + egui::vec2(offset[0], offset[1]))
+    }
+}
+
+/// Top-left corner of a window the player let go of this frame, for
+/// [`Settings::remember_window_pos`].
+pub(crate) fn dragged_to<R>(shown: &Option<egui::InnerResponse<R>>) -> Option<[f32; 2]> {
+    let shown = shown.as_ref()?;
+    let pos = shown.response.rect.min;
+    shown.response.drag_stopped().then_some([pos.x, pos.y])
+}
+
+/// Resource telling whether the settings window is currently shown.
+#[derive(Resource)]
+pub(crate) struct SettingsWindowOpen(pub(crate) bool);
+
+fn load_settings() -> Settings {
+    let Ok(content) = storage::read(SETTINGS_FILE_PATH) else {
+        info!("No settings file found, using defaults");
+        return Settings::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Failed to parse settings file, using defaults: {}", e);
+        Settings::default()
+    })
+}
+
+fn save_settings(settings: &Settings) {
+    match serde_json::to_string_pretty(settings) {
+        Ok(json) => {
+            if let Err(e) = storage::write(SETTINGS_FILE_PATH, &json) {
+                error!("Failed to write settings file: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to serialize settings: {}", e),
+    }
+}
+
+/// Pushes the accessibility settings into egui whenever they change. Like the UI theme, they are
+/// applied once the egui context is available.
+fn apply_accessibility_settings(
+    mut contexts: EguiContexts,
+    settings: Res<Settings>,
+    mut applied: Local<bool>,
+) {
+    if *applied && !settings.is_changed() {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    ctx.set_zoom_factor(if settings.large_text {
+        LARGE_TEXT_ZOOM
+    } else {
+        1.0
+    });
+    ctx.options_mut(|options| options.screen_reader = settings.screen_reader);
+    *applied = true;
+}
+
+/// The panels are drawn through [`UiTheme::side_panel`], so the theme carries which side they go.
+fn apply_panel_side(settings: Res<Settings>, mut theme: ResMut<UiTheme>) {
+    if theme.mirror_panels != settings.mirror_panels {
+        theme.mirror_panels = settings.mirror_panels;
+    }
+}
+
+fn display_settings_window(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut settings: ResMut<Settings>,
+    mut window_open: ResMut<SettingsWindowOpen>,
+    mut unsaved: Local<bool>,
+) {
+    if !window_open.0 {
+        // The window was closed from elsewhere while a slider was held.
+        if std::mem::take(&mut *unsaved) {
+            save_settings(&settings);
+        }
+        return;
+    }
+
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    // Work on a copy so change detection only fires when something was actually edited.
+    let mut edited = settings.clone();
+    let mut close = ctx.input(|input| input.key_pressed(egui::Key::Escape));
+
+    egui::Window::new("Settings")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .default_width(320.0)
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("⚙ Settings");
+                if theme.close_button(ui) {
+                    close = true;
+                }
+            });
+            ui.separator();
+
+            ui.label(RichText::new("Audio").strong().color(Color32::GOLD));
+            egui::Grid::new("audio_settings")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    volume_slider(ui, "Master", &mut edited.master_volume);
+                    volume_slider(ui, "Music", &mut edited.music_volume);
+                    volume_slider(ui, "Effects", &mut edited.effects_volume);
+                });
+
+            ui.add_space(8.0);
+            ui.label(RichText::new("Map").strong().color(Color32::GOLD));
+            egui::Grid::new("map_settings")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Palette").color(Color32::LIGHT_GRAY));
+                    egui::ComboBox::from_id_salt("map_palette")
+                        .selected_text(edited.map_palette.name())
+                        .show_ui(ui, |ui| {
+                            for palette in MapPalette::all() {
+                                ui.selectable_value(
+                                    &mut edited.map_palette,
+                                    palette,
+                                    palette.name(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Occupation");
+                    ui.checkbox(&mut edited.occupation_hatching, "Show as stripes")
+                        .labelled_by(label);
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Army movement");
+                    ui.checkbox(&mut edited.animate_army_movement, "Animate")
+                        .labelled_by(label);
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Enemies in the way");
+                    ui.checkbox(&mut edited.march_around_enemies, "March around")
+                        .labelled_by(label)
+                        .on_hover_text("Armies still attack enemies holding their destination");
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Merging armies");
+                    ui.checkbox(&mut edited.confirm_army_merges, "Ask first")
+                        .labelled_by(label)
+                        .on_hover_text("Hold Ctrl when giving the order to merge without asking");
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            ui.label(RichText::new("Camera").strong().color(Color32::GOLD));
+            egui::Grid::new("camera_settings")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    let label = setting_label(ui, "Edge scrolling");
+                    ui.checkbox(&mut edited.edge_scrolling, "")
+                        .labelled_by(label);
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Scroll speed");
+                    ui.add_enabled(
+                        edited.edge_scrolling,
+                        egui::Slider::new(&mut edited.edge_scroll_speed, 100.0..=1500.0)
+                            .show_value(false),
+                    )
+                    .labelled_by(label);
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            ui.label(RichText::new("Accessibility").strong().color(Color32::GOLD));
+            egui::Grid::new("accessibility_settings")
+                .num_columns(2)
+                .spacing([20.0, 8.0])
+                .show(ui, |ui| {
+                    let label = setting_label(ui, "Text size");
+                    ui.checkbox(&mut edited.large_text, "Larger text")
+                        .labelled_by(label);
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Keyboard navigation");
+                    ui.checkbox(&mut edited.keyboard_navigation, "Focus menus")
+                        .labelled_by(label)
+                        .on_hover_text(
+                            "Tab moves between buttons, Enter presses them and Escape goes back",
+                        );
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Screen reader");
+                    ui.checkbox(&mut edited.screen_reader, "Describe widgets")
+                        .labelled_by(label);
+                    ui.end_row();
+
+                    let label = setting_label(ui, "Side panels");
+                    ui.checkbox(&mut edited.mirror_panels, "Swap sides")
+                        .labelled_by(label)
+                        .on_hover_text("Province panel on the right, the other panels on the left");
+                    ui.end_row();
+                });
+        });
+
+    // Applied right away so the volume can be heard while its slider is dragged, but only written
+    // once the slider is let go, so nothing is lost if the game is closed with the window still
+    // open without writing the file every frame.
+    if edited != *settings {
+        *settings = edited;
+        *unsaved = true;
+    }
+    let dragging = ctx.input(|input| input.pointer.any_down());
+    if *unsaved && (!dragging || close) {
+        save_settings(&settings);
+        *unsaved = false;
+    }
+
+    if close {
+        window_open.0 = false;
+    }
+}
+
+/// Name of a setting in the first column of the settings grids. Screen readers announce the
+/// setting's widget by it.
+fn setting_label(ui: &mut egui::Ui, text: &str) -> egui::Id {
+    ui.label(RichText::new(text).color(Color32::LIGHT_GRAY)).id
+}
+
+fn volume_slider(ui: &mut egui::Ui, label: &str, value: &mut f32) {
+    let label = setting_label(ui, label);
+    ui.add(egui::Slider::new(value, 0.0..=1.0).show_value(false))
+        .labelled_by(label);
+    ui.end_row();
+}
@@ -0,0 +1,35 @@
+﻿use crate::map::MapMode;
+use bevy::prelude::*;
+use eu6_simulation::hex::Hex;
+use eu6_simulation::player::Player;
+use eu6_simulation::supply::SupplyLines;
+use std::collections::HashSet;
+
+pub struct SupplyUiPlugin;
+
+impl Plugin for SupplyUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerSupply::default()).add_systems(
+            Update,
+            update_player_supply.run_if(resource_equals(MapMode::Supply)),
+        );
+    }
+}
+
+/// Supply reach of the player's country, shown by the supply map mode.
+#[derive(Resource, Default)]
+pub(crate) struct PlayerSupply(pub(crate) HashSet<Hex>);
+
+fn update_player_supply(
+    player: Res<Player>,
+    supply_lines: SupplyLines,
+    mut player_supply: ResMut<PlayerSupply>,
+) {
+    let reach = player
+        .country
+        .map(|country| supply_lines.reach(country))
+        .unwrap_or_default();
+    if player_supply.0 != reach {
+        player_supply.0 = reach;
+    }
+}
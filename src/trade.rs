@@ -0,0 +1,194 @@
+﻿use crate::egui_common::UiTheme;
+use crate::layout::{CameraDrag, UiPointer};
+use crate::selection::Selection;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::consts;
+use eu6_simulation::country::{DisplayName, MapColor};
+use eu6_simulation::map::Owner;
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::PlayerCommands;
+use eu6_simulation::trade::{Embarked, Fleet, SeaChart, ShipType};
+
+pub struct TradeUiPlugin;
+
+impl Plugin for TradeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SelectedFleet::default())
+            .add_systems(Update, (spawn_fleet_visuals, sync_fleet_transforms).chain())
+            .add_systems(EguiPrimaryContextPass, display_fleet_panel);
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct SelectedFleet {
+    selected: Option<Entity>,
+}
+
+impl SelectedFleet {
+    pub(crate) fn set(&mut self, fleet: Entity) {
+        self.selected = Some(fleet);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.selected = None;
+    }
+
+    pub(crate) fn get(&self) -> Option<Entity> {
+        self.selected
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_fleet_click(
+    click: On<Pointer<Click>>,
+    mut selection: Selection,
+    player: Res<Player>,
+    owners: Query<&Owner, With<Fleet>>,
+    fleets: Query<&Fleet>,
+    ui_pointer: UiPointer,
+    camera_drag: Res<CameraDrag>,
+    mut player_commands: PlayerCommands,
+) {
+    if ui_pointer.is_over_ui() {
+        return;
+    }
+    // The fleet covers its sea hex, so right clicks with an army selected order it to board.
+    if click.button == PointerButton::Secondary
+        && !camera_drag.is_dragging()
+        && let Some(army) = selection.army()
+        && let Ok(fleet) = fleets.get(click.entity)
+    {
+        player_commands.move_army(army, fleet.hex);
+        return;
+    }
+    if click.button != PointerButton::Primary {
+        return;
+    }
+    if owners
+        .get(click.entity)
+        .is_ok_and(|owner| Some(owner.0) == player.country)
+    {
+        if selection.fleet() == Some(click.entity) {
+            selection.clear_fleet();
+        } else {
+            selection.select_fleet(click.entity);
+        }
+    }
+}
+
+/// Draws newly spawned fleets as a ship in their country's color, transports broader than
+/// warships.
+fn spawn_fleet_visuals(
+    mut commands: Commands,
+    fleets: Query<(Entity, &Fleet, &Owner), Added<Fleet>>,
+    colors: Query<&MapColor>,
+) {
+    for (entity, fleet, owner) in &fleets {
+        let color = colors.get(owner.0).map_or(Color::WHITE, |color| color.0);
+        let size = match fleet.ships {
+            ShipType::Galley => Vec2::new(44.0, 16.0),
+            ShipType::Transport => Vec2::new(36.0, 22.0),
+        };
+        commands
+            .entity(entity)
+            .insert((
+                Transform::from_translation(fleet.hex.axial_to_world(consts::HEX_SIZE).extend(5.0)),
+                Visibility::Visible,
+                Sprite {
+                    color: fleet.ships.color(color),
+                    custom_size: Some(size),
+                    ..default()
+                },
+                Pickable::default(),
+            ))
+            .observe(handle_fleet_click);
+    }
+}
+
+fn sync_fleet_transforms(mut fleets: Query<(&Fleet, &mut Transform), Changed<Fleet>>) {
+    for (fleet, mut transform) in &mut fleets {
+        transform.translation = fleet.hex.axial_to_world(consts::HEX_SIZE).extend(5.0);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_fleet_panel(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut selected_fleet: ResMut<SelectedFleet>,
+    chart: Res<SeaChart>,
+    fleets: Query<(Entity, &Fleet, &Owner)>,
+    embarked: Query<&Embarked>,
+    countries: Query<&DisplayName>,
+    mut player_commands: PlayerCommands,
+) {
+    let Some((entity, fleet, owner)) = selected_fleet
+        .get()
+        .and_then(|fleet| fleets.get(fleet).ok())
+    else {
+        return;
+    };
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let owner_name = countries
+        .get(owner.0)
+        .map(|d| d.0.as_str())
+        .unwrap_or("Unknown");
+
+    theme
+        .side_panel("Fleet", Align2::RIGHT_TOP)
+        .resizable(false)
+        .default_width(200.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Fleet Info");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if theme.close_button(ui) {
+                        selected_fleet.clear();
+                    }
+                });
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Owner:");
+                ui.label(RichText::new(owner_name).color(Color32::from_rgb(100, 200, 255)));
+            });
+            ui.label(fleet.ships.name())
+                .on_hover_text(fleet.ships.description());
+            if fleet.destination.is_some() {
+                ui.label(RichText::new("Under sail").italics());
+            }
+
+            match fleet.ships {
+                ShipType::Galley => {
+                    let mut privateering = fleet.privateering;
+                    if ui
+                        .checkbox(&mut privateering, "Privateering")
+                        .on_hover_text(
+                            "Raid the foreign sea lanes the fleet lies on for their trade",
+                        )
+                        .changed()
+                    {
+                        player_commands.set_privateering(entity, privateering);
+                    }
+                }
+                ShipType::Transport => {
+                    let loaded = embarked.iter().any(|embarked| embarked.fleet == entity);
+                    ui.label(if loaded {
+                        "Carrying an army"
+                    } else {
+                        "Empty, right-click it with a neighboring army selected to board"
+                    });
+                }
+            }
+
+            let lanes = chart.lanes_through(&fleet.hex).count();
+            ui.label(format!("Sea lanes here: {}", lanes));
+        });
+}
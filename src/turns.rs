@@ -1,109 +1,41 @@
-﻿use crate::buildings::Income;
-use crate::country::Coffer;
-use crate::map::Owner;
-use bevy::log::info;
-use bevy::prelude::{NextState, Plugin, Query, Res, ResMut, Resource, State, States};
+use crate::army::ArmyListOpen;
+use crate::egui_common::UiTheme;
+use bevy::prelude::{NextState, Plugin, Res, ResMut, State};
 use bevy_egui::egui::Align2;
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
-use std::collections::HashMap;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::net::NetSession;
+use eu6_simulation::player::Player;
+use eu6_simulation::turns::{GameState, Modals, Turn};
 
-pub struct TurnsPlugin;
+pub struct TurnsUiPlugin;
 
-impl Plugin for TurnsPlugin {
+impl Plugin for TurnsUiPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        use bevy::prelude::*;
-        app.insert_resource(Turn::default())
-            .init_state::<GameState>()
-            .add_systems(OnEnter(GameState::Processing), handle_new_turn)
-            .add_systems(
-                OnEnter(GameState::Processing),
-                crate::army::move_active_armies,
-            )
-            .add_systems(OnEnter(GameState::Processing), crate::army::resolve_battles)
-            .add_systems(
-                OnEnter(GameState::Processing),
-                crate::war::update_siege_progress.after(crate::army::move_active_armies),
-            )
-            .add_systems(EguiPrimaryContextPass, display_turn_button);
+        app.add_systems(EguiPrimaryContextPass, display_turn_button);
     }
 }
 
-/// Resource for keeping track of current turn. Only cosmetic for now (or forever?).
-#[derive(Resource, Default)]
-pub(crate) struct Turn {
-    current_turn: u32,
-}
-
-impl Turn {
-    pub(crate) fn advance(&mut self) {
-        self.current_turn += 1;
-    }
-
-    pub(crate) fn current_turn(&self) -> u32 {
-        self.current_turn
-    }
-
-    pub(crate) fn set(&mut self, turn: u32) {
-        self.current_turn = turn;
-    }
-}
-
-/// Different states the game can be in.
-#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
-pub(crate) enum GameState {
-    #[default]
-    /// Player's turn, waiting for input.
-    PlayerTurn,
-    /// AI turn, updating various systems.
-    Processing,
-}
-
-/// Handles updating resources, movements of armies (TBD) after the end of each turn.
-pub(crate) fn handle_new_turn(
-    mut turn: ResMut<Turn>,
-    mut next_state: ResMut<NextState<GameState>>,
-    incomes: Query<(&Income, &Owner)>,
-    mut coffers: Query<&mut Coffer>,
-) {
-    info!("Ending turn {}", turn.current_turn);
-
-    // Those aren't necessarily countries since e.g. rebels can have incomes (but are they owners? IDK).
-    // But the owner thing is nice since we can make building have owners and collect income the same
-    // way as base income from provinces.
-    let mut faction_incomes = HashMap::new();
-
-    // Sum up income for each faction from each source.
-    for (income, owner) in incomes.iter() {
-        faction_incomes
-            .entry(&owner.0)
-            .and_modify(|curr_income| *curr_income += income.get())
-            .or_insert(income.get());
-    }
-
-    for (faction, faction_entity) in faction_incomes.into_iter() {
-        if let Ok(mut coffer) = coffers.get_mut(*faction) {
-            coffer.add_ducats(faction_entity);
-        }
-    }
-
-    turn.advance();
-    info!("Starting turn {}", turn.current_turn);
-    next_state.set(GameState::PlayerTurn);
-}
-
-/// Egui system for showing 'End turn' button. Moves the system into [`GameState::Processing`] state.
+/// Egui system for showing 'End turn' button. Moves the system into [`GameState::Processing`] state,
+/// or in a multiplayer game waits for the other player to end the turn too. The turn can't be
+/// ended while a [`Modals`] window is open.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn display_turn_button(
     mut contexts: EguiContexts,
     turn: Res<Turn>,
     curr_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut army_list: ResMut<ArmyListOpen>,
+    theme: Res<UiTheme>,
+    session: Option<ResMut<NetSession>>,
+    modals: Modals,
+    player: Res<Player>,
 ) {
     let ctx = match contexts.ctx_mut() {
         Ok(ctx) => ctx,
         Err(_) => return,
     };
 
-    let frame = crate::egui_common::default_frame();
+    let frame = theme.frame();
 
     egui::Window::new("Turn")
         .frame(frame)
@@ -111,20 +43,42 @@ pub(crate) fn display_turn_button(
         .resizable(false)
         .default_width(150.0)
         .anchor(Align2::LEFT_BOTTOM, [20.0, -20.0])
-        .show(ctx, |ui| match curr_state.get() {
-            GameState::PlayerTurn => {
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                match curr_state.get() {
+                    GameState::PlayerTurn
+                        if session.as_ref().is_some_and(|s| s.waiting_for_peer()) =>
+                    {
+                        ui.spinner();
+                        ui.label("Waiting for the other player");
+                    }
+                    GameState::PlayerTurn => {
+                        if ui
+                            .add_enabled(
+                                !modals.open(&player, session.as_deref()),
+                                egui::Button::new(format!("End Turn ({})", turn.current_turn())),
+                            )
+                            .on_disabled_hover_text("Answer the open offers first")
+                            .clicked()
+                        {
+                            match session {
+                                Some(mut session) => session.end_turn(),
+                                None => next_state.set(GameState::Processing),
+                            }
+                        }
+                    }
+                    GameState::Processing | GameState::EnemyTurn => {
+                        ui.spinner();
+                    }
+                }
+
                 if ui
-                    .add(egui::Button::new(format!(
-                        "End Turn ({})",
-                        turn.current_turn
-                    )))
+                    .selectable_label(army_list.0, "⚔ Armies")
+                    .on_hover_text("Toggle the army list (L)")
                     .clicked()
                 {
-                    next_state.set(GameState::Processing);
+                    army_list.0 = !army_list.0;
                 }
-            }
-            GameState::Processing => {
-                ui.spinner();
-            }
+            });
         });
 }
@@ -0,0 +1,235 @@
+﻿use crate::egui_common::UiTheme;
+use crate::map::SelectedProvince;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, Color32, RichText};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::army::{Army, ArmyComposition, MoveArmyEvent};
+use eu6_simulation::buildings::Building;
+use eu6_simulation::map::Owner;
+use eu6_simulation::menu::MenuState;
+use eu6_simulation::player::Player;
+use eu6_simulation::war::{DeclareWarEvent, PeaceOfferEvent};
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tutorial::default())
+            .add_systems(
+                Update,
+                advance_tutorial.run_if(in_state(MenuState::InGame).and(tutorial_running)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                display_tutorial.run_if(in_state(MenuState::InGame).and(tutorial_running)),
+            );
+    }
+}
+
+/// Steps of the tutorial, in the order the player goes through them.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TutorialStep {
+    #[default]
+    SelectProvince,
+    Build,
+    Recruit,
+    MoveArmy,
+    DeclareWar,
+    MakePeace,
+    Finished,
+}
+
+impl TutorialStep {
+    fn next(&self) -> TutorialStep {
+        match self {
+            TutorialStep::SelectProvince => TutorialStep::Build,
+            TutorialStep::Build => TutorialStep::Recruit,
+            TutorialStep::Recruit => TutorialStep::MoveArmy,
+            TutorialStep::MoveArmy => TutorialStep::DeclareWar,
+            TutorialStep::DeclareWar => TutorialStep::MakePeace,
+            TutorialStep::MakePeace | TutorialStep::Finished => TutorialStep::Finished,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            TutorialStep::SelectProvince => "Your realm",
+            TutorialStep::Build => "Construction",
+            TutorialStep::Recruit => "Raising troops",
+            TutorialStep::MoveArmy => "On the march",
+            TutorialStep::DeclareWar => "Casus belli",
+            TutorialStep::MakePeace => "Making peace",
+            TutorialStep::Finished => "Tutorial complete",
+        }
+    }
+
+    fn instructions(&self) -> &'static str {
+        match self {
+            TutorialStep::SelectProvince => {
+                "Left-click one of your provinces to see its details. Switch to the political map \
+                 with M to see who owns what."
+            }
+            TutorialStep::Build => {
+                "Open the Buildings tab of the province panel and construct a building. Buildings \
+                 cost ducats but raise the province's income."
+            }
+            TutorialStep::Recruit => {
+                "Open the Recruitment tab and recruit a regiment. New regiments join the army \
+                 standing in the province, or form a new one."
+            }
+            TutorialStep::MoveArmy => {
+                "Click one of your armies to select it, then right-click a province to march \
+                 there. Movement happens when you end the turn."
+            }
+            TutorialStep::DeclareWar => {
+                "Click the owner of a neighbouring province to open their country panel, then \
+                 declare war from the Diplomacy tab."
+            }
+            TutorialStep::MakePeace => {
+                "Wars end at the negotiating table. Pick the provinces you want in the enemy's \
+                 Diplomacy tab and send a peace offer."
+            }
+            TutorialStep::Finished => {
+                "You know the basics now. Grow your realm, and may your coffers never run dry!"
+            }
+        }
+    }
+
+    /// Name of the window the step revolves around, it gets highlighted while the step is active.
+    fn highlighted_window(&self) -> Option<&'static str> {
+        match self {
+            TutorialStep::Build | TutorialStep::Recruit => Some("Province"),
+            TutorialStep::MoveArmy => Some("Army"),
+            TutorialStep::DeclareWar | TutorialStep::MakePeace => Some("Country"),
+            TutorialStep::SelectProvince | TutorialStep::Finished => None,
+        }
+    }
+}
+
+/// Resource driving the optional tutorial, enabled from the game setup screen.
+#[derive(Resource, Default)]
+pub(crate) struct Tutorial {
+    pub(crate) enabled: bool,
+    step: TutorialStep,
+}
+
+fn tutorial_running(tutorial: Res<Tutorial>) -> bool {
+    tutorial.enabled
+}
+
+/// Moves the tutorial forward once the player performed the action the current step asks for.
+#[allow(clippy::too_many_arguments)]
+fn advance_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    player: Res<Player>,
+    selected_province: Res<SelectedProvince>,
+    owners: Query<&Owner>,
+    new_buildings: Query<&ChildOf, Added<Building>>,
+    recruited: Query<&Owner, (With<Army>, Changed<ArmyComposition>)>,
+    mut move_events: MessageReader<MoveArmyEvent>,
+    mut war_events: MessageReader<DeclareWarEvent>,
+    mut peace_events: MessageReader<PeaceOfferEvent>,
+) {
+    let Some(player_country) = player.country else {
+        return;
+    };
+    let is_player = |entity: Entity| owners.get(entity).is_ok_and(|o| o.0 == player_country);
+
+    // Messages are read every frame so that actions done before reaching a step don't count.
+    let moved = move_events.read().any(|e| is_player(e.army));
+    let declared = war_events.read().any(|e| e.attacker == player_country);
+    let offered_peace = peace_events.read().any(|e| e.from == player_country);
+
+    let done = match tutorial.step {
+        TutorialStep::SelectProvince => selected_province.get().is_some_and(is_player),
+        TutorialStep::Build => new_buildings.iter().any(|c| is_player(c.parent())),
+        TutorialStep::Recruit => recruited.iter().any(|o| o.0 == player_country),
+        TutorialStep::MoveArmy => moved,
+        TutorialStep::DeclareWar => declared,
+        TutorialStep::MakePeace => offered_peace,
+        TutorialStep::Finished => false,
+    };
+
+    if done {
+        tutorial.step = tutorial.step.next();
+        info!("Tutorial advanced to {:?}", tutorial.step);
+    }
+}
+
+fn display_tutorial(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    mut tutorial: ResMut<Tutorial>,
+) {
+    let ctx = match contexts.ctx_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let step = tutorial.step;
+    let mut close = false;
+
+    egui::Window::new("Tutorial")
+        .frame(theme.frame())
+        .title_bar(false)
+        .anchor(Align2::CENTER_BOTTOM, [0.0, -20.0])
+        .resizable(false)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("📖 {}", step.title()))
+                        .strong()
+                        .color(Color32::GOLD),
+                );
+                if theme.close_button(ui) {
+                    close = true;
+                }
+            });
+            ui.separator();
+            ui.label(step.instructions());
+            ui.add_space(4.0);
+            if step == TutorialStep::Finished {
+                if ui.button("✓ Finish").clicked() {
+                    close = true;
+                }
+            } else if ui.button("Skip tutorial").clicked() {
+                close = true;
+            }
+        });
+
+    if let Some(window) = step.highlighted_window() {
+        highlight_window(ctx, window);
+    }
+
+    if close {
+        tutorial.enabled = false;
+    }
+}
+
+/// Draws a pulsing frame around the named window if it was on screen last frame.
+fn highlight_window(ctx: &egui::Context, window: &str) {
+    let id = egui::Id::new(window);
+    let layer = egui::LayerId::new(egui::Order::Middle, id);
+    let Some(rect) = ctx.memory(|m| {
+        m.areas()
+            .visible_last_frame(&layer)
+            .then(|| m.area_rect(id))
+            .flatten()
+    }) else {
+        return;
+    };
+
+    let pulse = (ctx.input(|i| i.time) * 4.0).sin() as f32 * 0.5 + 0.5;
+    let color = Color32::GOLD.gamma_multiply(0.4 + 0.6 * pulse);
+    ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("tutorial_highlight"),
+    ))
+    .rect_stroke(
+        rect.expand(4.0),
+        8.0,
+        egui::Stroke::new(3.0, color),
+        egui::StrokeKind::Outside,
+    );
+}
@@ -1,602 +1,49 @@
-﻿use crate::country::DisplayName;
-use crate::egui_common;
-use crate::map::{Owner, Province};
-use crate::player::Player;
+﻿use crate::diplomacy::{
+    Alliance, ProvinceOfferDraft, draw_alliance_section, draw_military_access_section,
+    draw_province_offer_section,
+};
+use crate::egui_common::UiTheme;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::egui::{Align2, Color32, RichText};
-use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use eu6_simulation::consts;
+use eu6_simulation::country::DisplayName;
+use eu6_simulation::map::{Owner, Province};
+use eu6_simulation::player::Player;
+use eu6_simulation::player_command::{PlayerCommand, PlayerCommands};
+use eu6_simulation::releasables::Releasables;
+use eu6_simulation::war::{
+    BREAK_ALLIANCES_WAR_SCORE, Occupied, PeaceDemands, PeaceOffer, RELEASE_WAR_SCORE, Release,
+    VASSAL_TRIBUTE, VASSALIZE_WAR_SCORE, War, WarStatus, Wars, get_war_between,
+};
 use std::collections::HashSet;
 
-pub struct WarPlugin;
+pub struct WarUiPlugin;
 
-impl Plugin for WarPlugin {
+impl Plugin for WarUiPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Wars::default())
-            .add_message::<DeclareWarEvent>()
-            .add_message::<PeaceOfferEvent>()
-            .add_message::<AcceptPeaceEvent>()
-            .add_systems(Update, handle_declare_war)
-            .add_systems(Update, handle_peace_offers)
-            .add_systems(Update, handle_accept_peace)
-            .add_systems(Update, ai_handle_peace_offers)
+        app.insert_resource(WarOverlay::default())
+            .insert_resource(PeaceDraft::default())
+            .add_systems(Update, (draw_war_overlay, draw_peace_draft))
             .add_systems(EguiPrimaryContextPass, display_peace_offers_panel);
     }
 }
 
-// ============================================================================
-// SIEGE SYSTEM
-// ============================================================================
-
-/// System to update siege progress and check for occupation.
-pub(crate) fn update_siege_progress(
-    mut commands: Commands,
-    mut siege_provinces: Query<(Entity, &mut SiegeProgress, &Owner, Option<&Occupied>)>,
-    armies: Query<(Entity, &crate::army::HexPos, &Owner), With<crate::army::Army>>,
-    provinces: Query<(Entity, &Province, &Owner), Without<Occupied>>,
-    province_hex_map: Res<crate::map::ProvinceHexMap>,
-    war_relations: Query<&WarRelations>,
-) {
-    update_existing_sieges(
-        &mut commands,
-        &mut siege_provinces,
-        &armies,
-        &province_hex_map,
-    );
-    check_for_new_sieges(
-        &mut commands,
-        &armies,
-        &provinces,
-        &province_hex_map,
-        &war_relations,
-        &siege_provinces,
-    );
-}
-
-fn update_existing_sieges(
-    commands: &mut Commands,
-    siege_provinces: &mut Query<(Entity, &mut SiegeProgress, &Owner, Option<&Occupied>)>,
-    armies: &Query<(Entity, &crate::army::HexPos, &Owner), With<crate::army::Army>>,
-    province_hex_map: &Res<crate::map::ProvinceHexMap>,
-) {
-    for (province_entity, mut siege, _, maybe_occupied) in siege_provinces.iter_mut() {
-        if maybe_occupied.is_some() {
-            commands.entity(province_entity).remove::<SiegeProgress>();
-            continue;
-        }
-
-        let army_still_present =
-            is_besieger_present(province_entity, &siege, armies, province_hex_map);
-
-        if army_still_present {
-            advance_siege(commands, province_entity, &mut siege);
-        } else {
-            lift_siege(commands, province_entity);
-        }
-    }
-}
-
-fn is_besieger_present(
-    province_entity: Entity,
-    siege: &SiegeProgress,
-    armies: &Query<(Entity, &crate::army::HexPos, &Owner), With<crate::army::Army>>,
-    province_hex_map: &Res<crate::map::ProvinceHexMap>,
-) -> bool {
-    armies.iter().any(|(_, pos, owner)| {
-        province_hex_map
-            .get_entity(&pos.0)
-            .map(|&prov| prov == province_entity && owner.0 == siege.besieger_country)
-            .unwrap_or(false)
-    })
-}
-
-fn advance_siege(commands: &mut Commands, province_entity: Entity, siege: &mut SiegeProgress) {
-    siege.progress += 1;
-    info!(
-        "Siege progress on {:?}: {}/{}",
-        province_entity, siege.progress, SIEGE_TURNS_REQUIRED
-    );
-
-    if siege.progress >= SIEGE_TURNS_REQUIRED {
-        commands
-            .entity(province_entity)
-            .remove::<SiegeProgress>()
-            .insert(Occupied {
-                occupier: siege.besieger_country,
-            });
-        info!(
-            "Province {:?} occupied by {:?} after siege!",
-            province_entity, siege.besieger_country
-        );
-    }
-}
-
-fn lift_siege(commands: &mut Commands, province_entity: Entity) {
-    info!("Siege on {:?} lifted - army left", province_entity);
-    commands.entity(province_entity).remove::<SiegeProgress>();
-}
-
-fn check_for_new_sieges(
-    commands: &mut Commands,
-    armies: &Query<(Entity, &crate::army::HexPos, &Owner), With<crate::army::Army>>,
-    provinces: &Query<(Entity, &Province, &Owner), Without<Occupied>>,
-    province_hex_map: &Res<crate::map::ProvinceHexMap>,
-    war_relations: &Query<&WarRelations>,
-    siege_provinces: &Query<(Entity, &mut SiegeProgress, &Owner, Option<&Occupied>)>,
-) {
-    for (_, army_pos, army_owner) in armies.iter() {
-        if let Some(&province_entity) = province_hex_map.get_entity(&army_pos.0) {
-            if siege_provinces.get(province_entity).is_ok() {
-                continue;
-            }
-            try_start_siege(
-                commands,
-                province_entity,
-                army_owner.0,
-                provinces,
-                war_relations,
-            );
-        }
-    }
-}
-
-fn try_start_siege(
-    commands: &mut Commands,
-    province_entity: Entity,
-    army_owner: Entity,
-    provinces: &Query<(Entity, &Province, &Owner), Without<Occupied>>,
-    war_relations: &Query<&WarRelations>,
-) {
-    if let Ok((_, province, province_owner)) = provinces.get(province_entity) {
-        if are_at_war(army_owner, province_owner.0, war_relations) {
-            commands.entity(province_entity).insert(SiegeProgress {
-                besieger_country: army_owner,
-                progress: 1,
-            });
-            info!("Siege started on {} by {:?}", province.name(), army_owner);
-        }
-    }
-}
-
-// ============================================================================
-// DATA STRUCTURES
-// ============================================================================
-
-#[derive(Component)]
-pub(crate) struct War {
-    pub(crate) attacker: Entity,
-    pub(crate) defender: Entity,
-}
-
-#[derive(Resource, Default)]
-pub(crate) struct Wars {
-    pub(crate) active_wars: Vec<Entity>,
-}
-
-impl Wars {
-    pub(crate) fn add_war(&mut self, war_entity: Entity) {
-        self.active_wars.push(war_entity);
-    }
-
-    pub(crate) fn remove_war(&mut self, war_entity: Entity) {
-        self.active_wars.retain(|&e| e != war_entity);
-    }
-}
-
-#[derive(Component, Default)]
-pub(crate) struct WarRelations {
-    pub(crate) at_war_with: HashSet<Entity>,
-}
-
-impl WarRelations {
-    pub(crate) fn is_at_war_with(&self, other: Entity) -> bool {
-        self.at_war_with.contains(&other)
-    }
-
-    pub(crate) fn add_enemy(&mut self, enemy: Entity) {
-        self.at_war_with.insert(enemy);
-    }
-
-    pub(crate) fn remove_enemy(&mut self, enemy: Entity) {
-        self.at_war_with.remove(&enemy);
-    }
-}
-
-#[derive(Component)]
-pub(crate) struct Occupied {
-    pub(crate) occupier: Entity,
-}
-
-#[derive(Component)]
-pub(crate) struct SiegeProgress {
-    pub(crate) besieger_country: Entity,
-    pub(crate) progress: u32,
-}
-
-pub(crate) const SIEGE_TURNS_REQUIRED: u32 = 3;
-
-#[derive(Component)]
-pub(crate) struct PeaceOffer {
-    pub(crate) from: Entity,
-    pub(crate) to: Entity,
-    pub(crate) war_entity: Entity,
-    pub(crate) provinces_to_cede: Vec<Entity>,
-}
-
-// ============================================================================
-// EVENTS
-// ============================================================================
-
-#[derive(Message)]
-pub(crate) struct DeclareWarEvent {
-    pub(crate) attacker: Entity,
-    pub(crate) defender: Entity,
-}
-
-impl DeclareWarEvent {
-    pub(crate) fn new(attacker: Entity, defender: Entity) -> Self {
-        Self { attacker, defender }
-    }
-}
-
-#[derive(Message)]
-pub(crate) struct PeaceOfferEvent {
-    pub(crate) from: Entity,
-    pub(crate) to: Entity,
-    pub(crate) war_entity: Entity,
-    pub(crate) provinces_to_cede: Vec<Entity>,
-}
-
-#[derive(Message)]
-pub(crate) struct AcceptPeaceEvent {
-    pub(crate) peace_offer_entity: Entity,
-}
-
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
-
-pub(crate) fn are_at_war(
-    country1: Entity,
-    country2: Entity,
-    war_relations: &Query<&WarRelations>,
-) -> bool {
-    war_relations
-        .get(country1)
-        .map(|r| r.is_at_war_with(country2))
-        .unwrap_or(false)
-}
-
-pub(crate) fn get_war_between(
-    country1: Entity,
-    country2: Entity,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-) -> Option<Entity> {
-    wars.active_wars.iter().find_map(|&war_entity| {
-        war_query.get(war_entity).ok().and_then(|(_, war)| {
-            let matches = (war.attacker == country1 && war.defender == country2)
-                || (war.attacker == country2 && war.defender == country1);
-            matches.then_some(war_entity)
-        })
-    })
-}
-
-pub(crate) fn occupy_province(commands: &mut Commands, province_entity: Entity, occupier: Entity) {
-    commands
-        .entity(province_entity)
-        .insert(Occupied { occupier });
-    info!("Province {:?} occupied by {:?}", province_entity, occupier);
-}
-
-// ============================================================================
-// WAR DECLARATION
-// ============================================================================
-
-fn handle_declare_war(
-    mut commands: Commands,
-    mut events: MessageReader<DeclareWarEvent>,
-    mut wars: ResMut<Wars>,
-    mut war_relations: Query<&mut WarRelations>,
-) {
-    for event in events.read() {
-        if !validate_war_declaration(&event, &war_relations) {
-            continue;
-        }
-        let war_entity = create_war(&mut commands, &event);
-        wars.add_war(war_entity);
-        update_war_relations(&mut commands, &mut war_relations, &event);
-        info!("War declared: {:?} vs {:?}", event.attacker, event.defender);
-    }
-}
-
-fn validate_war_declaration(
-    event: &DeclareWarEvent,
-    war_relations: &Query<&mut WarRelations>,
-) -> bool {
-    if event.attacker == event.defender {
-        warn!("Cannot declare war on yourself!");
-        return false;
-    }
-    if let Ok(relations) = war_relations.get(event.attacker) {
-        if relations.is_at_war_with(event.defender) {
-            info!(
-                "Countries {:?} and {:?} are already at war",
-                event.attacker, event.defender
-            );
-            return false;
-        }
-    }
-    true
-}
-
-fn create_war(commands: &mut Commands, event: &DeclareWarEvent) -> Entity {
-    commands
-        .spawn(War {
-            attacker: event.attacker,
-            defender: event.defender,
-        })
-        .id()
-}
-
-fn update_war_relations(
-    commands: &mut Commands,
-    war_relations: &mut Query<&mut WarRelations>,
-    event: &DeclareWarEvent,
-) {
-    add_war_relation(commands, war_relations, event.attacker, event.defender);
-    add_war_relation(commands, war_relations, event.defender, event.attacker);
-}
-
-fn add_war_relation(
-    commands: &mut Commands,
-    war_relations: &mut Query<&mut WarRelations>,
-    country: Entity,
-    enemy: Entity,
-) {
-    if let Ok(mut relations) = war_relations.get_mut(country) {
-        relations.add_enemy(enemy);
-    } else {
-        let mut relations = WarRelations::default();
-        relations.add_enemy(enemy);
-        commands.entity(country).insert(relations);
-    }
-}
-
-// ============================================================================
-// PEACE OFFERS
-// ============================================================================
-
-fn handle_peace_offers(mut commands: Commands, mut events: MessageReader<PeaceOfferEvent>) {
-    for event in events.read() {
-        commands.spawn(PeaceOffer {
-            from: event.from,
-            to: event.to,
-            war_entity: event.war_entity,
-            provinces_to_cede: event.provinces_to_cede.clone(),
-        });
-        info!("Peace offer sent from {:?} to {:?}", event.from, event.to);
-    }
-}
-
-fn ai_handle_peace_offers(
-    mut commands: Commands,
-    peace_offers: Query<(Entity, &PeaceOffer)>,
-    player: Res<Player>,
-    mut accept_peace_events: MessageWriter<AcceptPeaceEvent>,
-    provinces: Query<&Owner, With<Province>>,
-) {
-    for (offer_entity, offer) in peace_offers.iter() {
-        if Some(offer.to) == player.country {
-            continue;
-        }
-        process_ai_peace_decision(
-            &mut commands,
-            offer_entity,
-            offer,
-            &mut accept_peace_events,
-            &provinces,
-        );
-    }
-}
-
-fn process_ai_peace_decision(
-    commands: &mut Commands,
-    offer_entity: Entity,
-    offer: &PeaceOffer,
-    accept_peace_events: &mut MessageWriter<AcceptPeaceEvent>,
-    provinces: &Query<&Owner, With<Province>>,
-) {
-    if evaluate_peace_offer(offer, provinces) {
-        info!(
-            "AI country {:?} accepts peace offer from {:?}",
-            offer.to, offer.from
-        );
-        accept_peace_events.write(AcceptPeaceEvent {
-            peace_offer_entity: offer_entity,
-        });
-    } else {
-        info!(
-            "AI country {:?} rejects peace offer from {:?}",
-            offer.to, offer.from
-        );
-        commands.entity(offer_entity).despawn();
-    }
-}
-
-fn evaluate_peace_offer(offer: &PeaceOffer, provinces: &Query<&Owner, With<Province>>) -> bool {
-    let provinces_demanded = offer.provinces_to_cede.len();
-    if provinces_demanded == 0 {
-        return true;
-    }
-
-    let provinces_from_recipient = count_provinces_from_recipient(offer, provinces);
-    if provinces_from_recipient <= 2 {
-        return true;
-    }
-
-    let total_ai_provinces = provinces.iter().filter(|owner| owner.0 == offer.to).count();
-    if total_ai_provinces > 0 {
-        let loss_ratio = provinces_from_recipient as f32 / total_ai_provinces as f32;
-        return loss_ratio < 0.3;
-    }
-    false
-}
-
-fn count_provinces_from_recipient(
-    offer: &PeaceOffer,
-    provinces: &Query<&Owner, With<Province>>,
-) -> usize {
-    offer
-        .provinces_to_cede
-        .iter()
-        .filter(|&&prov| {
-            provinces
-                .get(prov)
-                .map(|owner| owner.0 == offer.to)
-                .unwrap_or(false)
-        })
-        .count()
-}
-
-// ============================================================================
-// ACCEPT PEACE
-// ============================================================================
-
-fn handle_accept_peace(
-    mut commands: Commands,
-    mut events: MessageReader<AcceptPeaceEvent>,
-    mut wars: ResMut<Wars>,
-    mut war_relations: Query<&mut WarRelations>,
-    peace_offers: Query<&PeaceOffer>,
-    war_query: Query<&War>,
-    occupied_provinces: Query<(Entity, &Occupied)>,
-) {
-    for event in events.read() {
-        process_peace_acceptance(
-            &mut commands,
-            event,
-            &mut wars,
-            &mut war_relations,
-            &peace_offers,
-            &war_query,
-            &occupied_provinces,
-        );
-    }
-}
-
-fn process_peace_acceptance(
-    commands: &mut Commands,
-    event: &AcceptPeaceEvent,
-    wars: &mut ResMut<Wars>,
-    war_relations: &mut Query<&mut WarRelations>,
-    peace_offers: &Query<&PeaceOffer>,
-    war_query: &Query<&War>,
-    occupied_provinces: &Query<(Entity, &Occupied)>,
-) {
-    let Ok(peace_offer) = peace_offers.get(event.peace_offer_entity) else {
-        warn!(
-            "Peace offer entity not found: {:?}",
-            event.peace_offer_entity
-        );
-        return;
-    };
-
-    let Ok(war) = war_query.get(peace_offer.war_entity) else {
-        warn!("War entity not found: {:?}", peace_offer.war_entity);
-        commands.entity(event.peace_offer_entity).despawn();
-        return;
-    };
-
-    execute_peace_terms(
-        commands,
-        peace_offer,
-        war,
-        wars,
-        war_relations,
-        occupied_provinces,
-    );
-    cleanup_peace_entities(
-        commands,
-        wars,
-        peace_offer.war_entity,
-        event.peace_offer_entity,
-    );
-    info!(
-        "Peace accepted between {:?} and {:?}",
-        war.attacker, war.defender
-    );
-}
-
-fn execute_peace_terms(
-    commands: &mut Commands,
-    peace_offer: &PeaceOffer,
-    war: &War,
-    _wars: &mut ResMut<Wars>,
-    war_relations: &mut Query<&mut WarRelations>,
-    occupied_provinces: &Query<(Entity, &Occupied)>,
-) {
-    transfer_provinces(commands, peace_offer);
-    clear_occupations(commands, war, occupied_provinces);
-    remove_war_relations(war_relations, war);
-}
-
-fn transfer_provinces(commands: &mut Commands, peace_offer: &PeaceOffer) {
-    for &province_entity in &peace_offer.provinces_to_cede {
-        commands
-            .entity(province_entity)
-            .remove::<Occupied>()
-            .insert(Owner(peace_offer.from));
-        info!(
-            "Province {:?} ceded to {:?}",
-            province_entity, peace_offer.from
-        );
-    }
-}
-
-fn clear_occupations(
-    commands: &mut Commands,
-    war: &War,
-    occupied_provinces: &Query<(Entity, &Occupied)>,
-) {
-    for (province_entity, occupied) in occupied_provinces.iter() {
-        if occupied.occupier == war.attacker || occupied.occupier == war.defender {
-            commands.entity(province_entity).remove::<Occupied>();
-        }
-    }
-}
-
-fn remove_war_relations(war_relations: &mut Query<&mut WarRelations>, war: &War) {
-    if let Ok(mut relations) = war_relations.get_mut(war.attacker) {
-        relations.remove_enemy(war.defender);
-    }
-    if let Ok(mut relations) = war_relations.get_mut(war.defender) {
-        relations.remove_enemy(war.attacker);
-    }
-}
-
-fn cleanup_peace_entities(
-    commands: &mut Commands,
-    wars: &mut ResMut<Wars>,
-    war_entity: Entity,
-    offer_entity: Entity,
-) {
-    wars.remove_war(war_entity);
-    commands.entity(war_entity).despawn();
-    commands.entity(offer_entity).despawn();
-}
-
 // ============================================================================
 // UI - PEACE OFFERS PANEL
 // ============================================================================
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn display_peace_offers_panel(
     mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
     player: Res<Player>,
     peace_offers: Query<(Entity, &PeaceOffer)>,
     countries: Query<&DisplayName>,
     provinces: Query<&Province>,
-    mut accept_peace_events: MessageWriter<AcceptPeaceEvent>,
-    mut commands: Commands,
+    releasables: Res<Releasables>,
+    mut player_commands: PlayerCommands,
 ) {
     let Some(player_country) = player.country else {
         return;
@@ -617,24 +64,26 @@ pub(crate) fn display_peace_offers_panel(
 
     render_peace_offers_window(
         ctx,
+        &theme,
         &player_offers,
         &countries,
         &provinces,
-        &mut accept_peace_events,
-        &mut commands,
+        &releasables,
+        &mut player_commands,
     );
 }
 
 fn render_peace_offers_window(
     ctx: &egui::Context,
+    theme: &UiTheme,
     player_offers: &[(Entity, &PeaceOffer)],
     countries: &Query<&DisplayName>,
     provinces: &Query<&Province>,
-    accept_peace_events: &mut MessageWriter<AcceptPeaceEvent>,
-    commands: &mut Commands,
+    releasables: &Releasables,
+    player_commands: &mut PlayerCommands,
 ) {
     egui::Window::new("Peace Offers")
-        .frame(egui_common::default_frame())
+        .frame(theme.frame())
         .title_bar(false)
         .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
         .resizable(false)
@@ -649,8 +98,8 @@ fn render_peace_offers_window(
                     offer,
                     countries,
                     provinces,
-                    accept_peace_events,
-                    commands,
+                    releasables,
+                    player_commands,
                 );
             }
         });
@@ -662,8 +111,8 @@ fn render_single_peace_offer(
     offer: &PeaceOffer,
     countries: &Query<&DisplayName>,
     provinces: &Query<&Province>,
-    accept_peace_events: &mut MessageWriter<AcceptPeaceEvent>,
-    commands: &mut Commands,
+    releasables: &Releasables,
+    player_commands: &mut PlayerCommands,
 ) {
     let from_name = countries
         .get(offer.from)
@@ -672,13 +121,22 @@ fn render_single_peace_offer(
     ui.label(format!("{} offers peace:", from_name));
     ui.add_space(8.0);
 
-    render_peace_terms(ui, offer, provinces);
-    render_peace_buttons(ui, offer_entity, accept_peace_events, commands);
+    render_peace_terms(ui, offer, countries, provinces, releasables);
+    render_peace_buttons(ui, offer_entity, offer, player_commands);
     ui.separator();
 }
 
-fn render_peace_terms(ui: &mut egui::Ui, offer: &PeaceOffer, provinces: &Query<&Province>) {
-    if offer.provinces_to_cede.is_empty() {
+fn render_peace_terms(
+    ui: &mut egui::Ui,
+    offer: &PeaceOffer,
+    countries: &Query<&DisplayName>,
+    provinces: &Query<&Province>,
+    releasables: &Releasables,
+) {
+    if offer.enforce_tolerance {
+        ui.label(RichText::new("Enforced tolerance").color(Color32::GOLD));
+        ui.label("Tolerate their faith, ending the league war.");
+    } else if offer.provinces_to_cede.is_empty() && !offer.demands.any() {
         ui.label(RichText::new("White Peace").color(Color32::YELLOW));
         ui.label("No territorial changes.");
     } else {
@@ -688,6 +146,21 @@ fn render_peace_terms(ui: &mut egui::Ui, offer: &PeaceOffer, provinces: &Query<&
                 ui.label(format!("  • {}", province.name()));
             }
         }
+        if offer.demands.vassalize {
+            ui.label("  • We become their vassal");
+        }
+        if let Some(release) = offer.demands.release {
+            let name = match release {
+                Release::Captive(captive) => {
+                    countries.get(captive).map_or("Unknown", |n| n.0.as_str())
+                }
+                Release::Nation(nation) => releasables.name(nation),
+            };
+            ui.label(format!("  • We release {}", name));
+        }
+        if offer.demands.break_alliances {
+            ui.label("  • We break all of our alliances");
+        }
     }
     ui.add_space(12.0);
 }
@@ -695,69 +168,296 @@ fn render_peace_terms(ui: &mut egui::Ui, offer: &PeaceOffer, provinces: &Query<&
 fn render_peace_buttons(
     ui: &mut egui::Ui,
     offer_entity: Entity,
-    accept_peace_events: &mut MessageWriter<AcceptPeaceEvent>,
-    commands: &mut Commands,
+    offer: &PeaceOffer,
+    player_commands: &mut PlayerCommands,
 ) {
+    let sender = player_commands.country_name(offer.from);
+    let answered = player_commands.queued(|command| {
+        matches!(command, PlayerCommand::AnswerPeace { from, .. } if Some(from) == sender.as_ref())
+    });
+    if answered {
+        ui.label(
+            RichText::new("Your answer is carried out when the turn ends.")
+                .color(Color32::LIGHT_GRAY),
+        );
+        return;
+    }
     ui.horizontal(|ui| {
         if ui.button("✓ Accept").clicked() {
-            accept_peace_events.write(AcceptPeaceEvent {
-                peace_offer_entity: offer_entity,
-            });
+            player_commands.answer_peace(offer_entity, true);
         }
         if ui.button("✗ Decline").clicked() {
-            commands.entity(offer_entity).despawn();
+            player_commands.answer_peace(offer_entity, false);
         }
     });
 }
 
+// ============================================================================
+// WAR OVERLAY
+// ============================================================================
+
+/// The player's war shown on the map, toggled from the diplomacy tab.
+#[derive(Resource, Default)]
+pub(crate) struct WarOverlay {
+    enemy: Option<Entity>,
+}
+
+impl WarOverlay {
+    pub(crate) fn is_shown(&self, enemy: Entity) -> bool {
+        self.enemy == Some(enemy)
+    }
+
+    pub(crate) fn toggle(&mut self, enemy: Entity) {
+        self.enemy = if self.is_shown(enemy) {
+            None
+        } else {
+            Some(enemy)
+        };
+    }
+}
+
+const WAR_GOAL_COLOR: Color = Color::srgb(1.0, 0.85, 0.0);
+
+const OUR_OCCUPATION_COLOR: Color = Color::srgb(0.0, 1.0, 0.0);
+
+const THEIR_OCCUPATION_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+
+/// Marks the goal of the shown war with a target and the provinces occupied by either side with
+/// a cross, green for the player's occupations and red for the enemy's as in the diplomacy tab.
+fn draw_war_overlay(
+    mut gizmos: Gizmos,
+    mut overlay: ResMut<WarOverlay>,
+    player: Res<Player>,
+    wars: Res<Wars>,
+    war_query: Query<(Entity, &War)>,
+    provinces: Query<(&Province, &Owner, Option<&Occupied>)>,
+) {
+    let (Some(enemy), Some(player_country)) = (overlay.enemy, player.country) else {
+        return;
+    };
+    let war = get_war_between(player_country, enemy, &wars, &war_query)
+        .and_then(|war_entity| war_query.get(war_entity).ok());
+    let Some((_, war)) = war else {
+        // The war is over.
+        overlay.enemy = None;
+        return;
+    };
+
+    if let Some(goal) = war.goal
+        && let Ok((province, _, _)) = provinces.get(goal)
+    {
+        let center = province.get_hex().axial_to_world(consts::HEX_SIZE);
+        gizmos.circle_2d(center, consts::HEX_SIZE * 0.6, WAR_GOAL_COLOR);
+        gizmos.circle_2d(center, consts::HEX_SIZE * 0.3, WAR_GOAL_COLOR);
+    }
+    for (province, owner, occupied) in &provinces {
+        let Some(occupied) = occupied else {
+            continue;
+        };
+        let color = if occupied.occupier == player_country && owner.0 == enemy {
+            OUR_OCCUPATION_COLOR
+        } else if occupied.occupier == enemy && owner.0 == player_country {
+            THEIR_OCCUPATION_COLOR
+        } else {
+            continue;
+        };
+        let center = province.get_hex().axial_to_world(consts::HEX_SIZE);
+        gizmos.cross_2d(center, consts::HEX_SIZE * 0.4, color);
+    }
+}
+
+// ============================================================================
+// PEACE DRAFT
+// ============================================================================
+
+/// Peace deal the player drafts while the diplomacy tab of a war is open. The provinces to
+/// demand are picked by clicking them on the map, the other demands in the tab.
+#[derive(Resource, Default)]
+pub(crate) struct PeaceDraft {
+    /// The player's country and the enemy, while the peace screen is open.
+    sides: Option<(Entity, Entity)>,
+    provinces: HashSet<Entity>,
+    demands: PeaceDemands,
+}
+
+impl PeaceDraft {
+    /// Opens the peace screen against `enemy`, starting a new draft if it was another war's.
+    pub(crate) fn open(&mut self, player_country: Entity, enemy: Entity) {
+        if self.sides != Some((player_country, enemy)) {
+            self.sides = Some((player_country, enemy));
+            self.provinces.clear();
+            self.demands = PeaceDemands::default();
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.sides = None;
+        self.provinces.clear();
+        self.demands = PeaceDemands::default();
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.sides.is_some()
+    }
+
+    /// Only the enemy's provinces occupied by the player can be demanded.
+    pub(crate) fn can_demand(&self, owner: &Owner, occupied: Option<&Occupied>) -> bool {
+        self.sides.is_some_and(|(player_country, enemy)| {
+            owner.0 == enemy && occupied.is_some_and(|o| o.occupier == player_country)
+        })
+    }
+
+    pub(crate) fn is_demanded(&self, province: Entity) -> bool {
+        self.provinces.contains(&province)
+    }
+
+    pub(crate) fn toggle(&mut self, province: Entity) {
+        if !self.provinces.remove(&province) {
+            self.provinces.insert(province);
+        }
+    }
+
+    fn demanded(&self) -> &HashSet<Entity> {
+        &self.provinces
+    }
+
+    fn take(&mut self) -> (HashSet<Entity>, PeaceDemands) {
+        (
+            std::mem::take(&mut self.provinces),
+            std::mem::take(&mut self.demands),
+        )
+    }
+}
+
+const DEMANDABLE_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+const DEMANDED_COLOR: Color = Color::srgb(1.0, 0.85, 0.0);
+
+/// Highlights the provinces that can be demanded in the open peace draft, and marks the ones
+/// already demanded.
+fn draw_peace_draft(
+    mut gizmos: Gizmos,
+    draft: Res<PeaceDraft>,
+    provinces: Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
+) {
+    if !draft.is_open() {
+        return;
+    }
+    for (entity, province, owner, occupied) in &provinces {
+        if !draft.can_demand(owner, occupied) {
+            continue;
+        }
+        let center = province.get_hex().axial_to_world(consts::HEX_SIZE);
+        gizmos.circle_2d(center, consts::HEX_SIZE * 0.7, DEMANDABLE_COLOR);
+        if draft.is_demanded(entity) {
+            gizmos.circle_2d(center, consts::HEX_SIZE * 0.5, DEMANDED_COLOR);
+            gizmos.circle_2d(center, consts::HEX_SIZE * 0.45, DEMANDED_COLOR);
+        }
+    }
+}
+
 // ============================================================================
 // UI - DIPLOMACY TAB
 // ============================================================================
 
+/// What the player puts together in the diplomacy tab, kept between frames.
+#[derive(SystemParam)]
+pub(crate) struct DiplomacyDrafts<'w> {
+    pub(crate) peace: ResMut<'w, PeaceDraft>,
+    pub(crate) war_overlay: ResMut<'w, WarOverlay>,
+    pub(crate) province_offer: ResMut<'w, ProvinceOfferDraft>,
+}
+
+/// Shows the player's relations with the target country. `league_war` is set when the two fight
+/// on opposite sides of a league war, which allows enforcing tolerance, `military_access` when the
+/// target lets the player's armies through, and `alliance` when the two are allies.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn draw_diplomacy_tab(
     ui: &mut egui::Ui,
     player_country: Entity,
     target_country: Entity,
-    war_relations: &Query<&WarRelations>,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-    declare_war_events: &mut MessageWriter<DeclareWarEvent>,
-    peace_offer_events: &mut MessageWriter<PeaceOfferEvent>,
+    league_war: bool,
+    military_access: bool,
+    alliance: Option<Alliance>,
+    war_status: &WarStatus,
+    player_commands: &mut PlayerCommands,
     provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
-    selected_provinces: &mut HashSet<Entity>,
+    drafts: &mut DiplomacyDrafts,
 ) {
-    let is_at_war = war_relations
+    let is_at_war = war_status
+        .relations
         .get(player_country)
         .map(|r| r.is_at_war_with(target_country))
         .unwrap_or(false);
 
+    if let Some(overlord) = war_status.overlord(target_country) {
+        ui.label(format!("Vassal of {}", war_status.name(overlord)))
+            .on_hover_text("Pays a share of its income to its overlord");
+    }
+
     if is_at_war {
+        drafts.peace.open(player_country, target_country);
         draw_war_diplomacy(
             ui,
             player_country,
             target_country,
-            wars,
-            war_query,
-            peace_offer_events,
+            league_war,
+            war_status,
+            player_commands,
             provinces,
-            selected_provinces,
+            &mut drafts.peace,
+            &mut drafts.war_overlay,
         );
     } else {
-        draw_peace_diplomacy(ui, player_country, target_country, declare_war_events);
+        drafts.peace.close();
+        draw_peace_diplomacy(
+            ui,
+            player_country,
+            target_country,
+            military_access,
+            alliance,
+            player_commands,
+            provinces,
+            &mut drafts.province_offer,
+        );
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_war_diplomacy(
     ui: &mut egui::Ui,
     player_country: Entity,
     target_country: Entity,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-    peace_offer_events: &mut MessageWriter<PeaceOfferEvent>,
+    league_war: bool,
+    war_status: &WarStatus,
+    player_commands: &mut PlayerCommands,
     provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
-    selected_provinces: &mut HashSet<Entity>,
+    peace_draft: &mut PeaceDraft,
+    war_overlay: &mut WarOverlay,
 ) {
-    ui.label(RichText::new("⚔ AT WAR").color(Color32::RED).strong());
+    let war_score = war_status.score(player_country, target_country);
+    ui.horizontal(|ui| {
+        ui.label(RichText::new("⚔ AT WAR").color(Color32::RED).strong());
+        if ui
+            .selectable_label(war_overlay.is_shown(target_country), "🗺 Show on map")
+            .on_hover_text("Mark the war goal and the occupied provinces of both sides")
+            .clicked()
+        {
+            war_overlay.toggle(target_country);
+        }
+    });
+    if let Some(war_score) = war_score {
+        let color = if war_score >= 0 {
+            Color32::GREEN
+        } else {
+            Color32::RED
+        };
+        ui.label(RichText::new(format!("War score: {:+}", war_score)).color(color))
+            .on_hover_text(
+                "Battles won, provinces occupied and turns holding the war goal. \
+                 Provinces we don't occupy can only be demanded with war score",
+            );
+    }
     ui.add_space(8.0);
 
     let our_occupied = get_occupied_by(provinces, target_country, player_country);
@@ -768,16 +468,14 @@ fn draw_war_diplomacy(
         "We occupy:",
         Color32::GREEN,
         &our_occupied,
-        selected_provinces,
-        true,
+        peace_draft.demanded(),
     );
     draw_occupied_list(
         ui,
         "They occupy:",
         Color32::RED,
         &their_occupied,
-        &mut HashSet::new(),
-        false,
+        &HashSet::new(),
     );
 
     ui.separator();
@@ -785,11 +483,24 @@ fn draw_war_diplomacy(
         ui,
         player_country,
         target_country,
-        wars,
-        war_query,
-        peace_offer_events,
-        selected_provinces,
+        war_score.unwrap_or(0),
+        war_status,
+        player_commands,
+        peace_draft,
     );
+    if league_war {
+        ui.add_space(4.0);
+        if ui
+            .button("✝ Enforce Tolerance")
+            .on_hover_text(
+                "Force them to tolerate our faith, ending the whole league war. \
+                 They only give in once we occupy some of their land",
+            )
+            .clicked()
+        {
+            player_commands.enforce_tolerance(player_country, target_country);
+        }
+    }
 }
 
 fn get_occupied_by(
@@ -806,13 +517,13 @@ fn get_occupied_by(
         .collect()
 }
 
+/// Lists the occupied provinces, marking the ones demanded in the peace draft.
 fn draw_occupied_list(
     ui: &mut egui::Ui,
     label: &str,
     color: Color32,
     occupied: &[(Entity, String)],
-    selected: &mut HashSet<Entity>,
-    selectable: bool,
+    demanded: &HashSet<Entity>,
 ) {
     if occupied.is_empty() {
         return;
@@ -820,18 +531,8 @@ fn draw_occupied_list(
 
     ui.label(RichText::new(label).color(color));
     for (entity, name) in occupied {
-        if selectable {
-            let is_selected = selected.contains(entity);
-            if ui
-                .selectable_label(is_selected, format!("  • {}", name))
-                .clicked()
-            {
-                if is_selected {
-                    selected.remove(entity);
-                } else {
-                    selected.insert(*entity);
-                }
-            }
+        if demanded.contains(entity) {
+            ui.label(RichText::new(format!("  ✔ {}", name)).color(Color32::GOLD));
         } else {
             ui.label(format!("  • {}", name));
         }
@@ -839,51 +540,177 @@ fn draw_occupied_list(
     ui.add_space(4.0);
 }
 
+/// Terms of the drafted peace. The provinces are picked on the map, the other demands here with
+/// the war score each costs.
+#[allow(clippy::too_many_arguments)]
 fn draw_peace_offer_section(
     ui: &mut egui::Ui,
     player_country: Entity,
     target_country: Entity,
-    wars: &Res<Wars>,
-    war_query: &Query<(Entity, &War)>,
-    peace_offer_events: &mut MessageWriter<PeaceOfferEvent>,
-    selected_provinces: &mut HashSet<Entity>,
+    war_score: i32,
+    war_status: &WarStatus,
+    player_commands: &mut PlayerCommands,
+    peace_draft: &mut PeaceDraft,
 ) {
     ui.label(RichText::new("Peace Terms:").strong());
 
-    if selected_provinces.is_empty() {
-        ui.label("White peace (select provinces above to demand them)");
+    let demanded = peace_draft.demanded().len();
+    if demanded == 0 {
+        ui.label("Click provinces we occupy on the map to demand them");
     } else {
-        ui.label(format!(
-            "Demanding {} province(s)",
-            selected_provinces.len()
-        ));
+        ui.label(format!("Demanding {} province(s)", demanded));
+    }
+
+    let demands = &mut peace_draft.demands;
+    let can_vassalize = war_status.overlord(target_country).is_none()
+        && war_status.overlord(player_country) != Some(target_country);
+    ui.add_enabled(
+        can_vassalize,
+        egui::Checkbox::new(
+            &mut demands.vassalize,
+            format!("Make them our vassal ({} war score)", VASSALIZE_WAR_SCORE),
+        ),
+    )
+    .on_hover_text(format!(
+        "They pay us {:.0}% of their income every turn",
+        VASSAL_TRIBUTE * 100.0
+    ))
+    .on_disabled_hover_text("They already serve an overlord");
+    ui.add_enabled(
+        war_status.has_allies(target_country),
+        egui::Checkbox::new(
+            &mut demands.break_alliances,
+            format!(
+                "Break their alliances ({} war score)",
+                BREAK_ALLIANCES_WAR_SCORE
+            ),
+        ),
+    )
+    .on_disabled_hover_text("They have no allies");
+
+    let releases = war_status.releases(target_country);
+    if releases.is_empty() {
+        demands.release = None;
+    } else {
+        ui.horizontal(|ui| {
+            ui.label(format!("Release ({} war score):", RELEASE_WAR_SCORE));
+            let selected = demands
+                .release
+                .map_or("Nobody", |release| war_status.release_name(release));
+            egui::ComboBox::from_id_salt("release_nation")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut demands.release, None, "Nobody");
+                    for &release in &releases {
+                        ui.selectable_value(
+                            &mut demands.release,
+                            Some(release),
+                            war_status.release_name(release),
+                        );
+                    }
+                });
+        })
+        .response
+        .on_hover_text(
+            "Give the provinces we took from a nation without land back to it, or make them \
+             give up the lands of a nation that has none yet",
+        );
+    }
+
+    let cost = demands.war_score();
+    if cost > 0 {
+        let color = if cost <= war_score {
+            Color32::GREEN
+        } else {
+            Color32::RED
+        };
+        ui.label(
+            RichText::new(format!("Costs {} of our {} war score", cost, war_score)).color(color),
+        );
+    } else if demanded == 0 {
+        ui.label("White peace");
     }
 
     ui.add_space(8.0);
 
     if ui.button("📜 Offer Peace").clicked() {
-        if let Some(war_entity) = get_war_between(player_country, target_country, wars, war_query) {
-            peace_offer_events.write(PeaceOfferEvent {
-                from: player_country,
-                to: target_country,
-                war_entity,
-                provinces_to_cede: selected_provinces.iter().copied().collect(),
-            });
-            selected_provinces.clear();
-        }
+        let (provinces, demands) = peace_draft.take();
+        player_commands.offer_peace(player_country, target_country, provinces, demands);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_peace_diplomacy(
     ui: &mut egui::Ui,
     player_country: Entity,
     target_country: Entity,
-    declare_war_events: &mut MessageWriter<DeclareWarEvent>,
+    military_access: bool,
+    alliance: Option<Alliance>,
+    player_commands: &mut PlayerCommands,
+    provinces: &Query<(Entity, &Province, &Owner, Option<&Occupied>)>,
+    province_offer: &mut ProvinceOfferDraft,
 ) {
     ui.label(RichText::new("☮ AT PEACE").color(Color32::GREEN).strong());
     ui.add_space(16.0);
 
-    if ui.button("⚔ Declare War").clicked() {
-        declare_war_events.write(DeclareWarEvent::new(player_country, target_country));
+    if ui
+        .add_enabled(alliance.is_none(), egui::Button::new("⚔ Declare War"))
+        .on_disabled_hover_text("Break the alliance first")
+        .clicked()
+    {
+        player_commands.declare_war(player_country, target_country);
+    }
+    draw_alliance_section(
+        ui,
+        player_country,
+        target_country,
+        alliance,
+        player_commands,
+    );
+    draw_military_access_section(
+        ui,
+        player_country,
+        target_country,
+        military_access,
+        player_commands,
+    );
+    draw_province_offer_section(
+        ui,
+        player_country,
+        target_country,
+        provinces,
+        province_offer,
+        player_commands,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eu6_simulation::hex::Hex;
+    use eu6_simulation::test_utils::TestGame;
+
+    #[test]
+    fn peace_draft_only_demands_our_occupations() {
+        let mut game = TestGame::new();
+        let player = game.spawn_country("Player");
+        let enemy = game.spawn_country("Enemy");
+        let other = game.spawn_country("Other");
+        let mut draft = PeaceDraft::default();
+        let ours = Some(&Occupied { occupier: player });
+        assert!(!draft.can_demand(&Owner(enemy), ours));
+
+        draft.open(player, enemy);
+        assert!(draft.can_demand(&Owner(enemy), ours));
+        assert!(!draft.can_demand(&Owner(enemy), None));
+        assert!(!draft.can_demand(&Owner(other), ours));
+        assert!(!draft.can_demand(&Owner(enemy), Some(&Occupied { occupier: other })));
+
+        // Switching to another war starts a new draft.
+        let province = game.spawn_province("Town", Hex::new(0, 0), Some(enemy));
+        draft.toggle(province);
+        assert!(draft.is_demanded(province));
+        draft.open(player, other);
+        assert!(!draft.is_demanded(province));
     }
 }
@@ -0,0 +1,37 @@
+﻿use crate::achievements::WarRecord;
+use crate::army::{SelectedArmy, SplitDraft};
+use crate::country::{CountryFlags, SelectedCountry};
+use crate::diplomacy::ProvinceOfferDraft;
+use crate::layout::CameraBookmarks;
+use crate::map::{MapMode, SelectedProvince};
+use crate::trade::SelectedFleet;
+use crate::tutorial::Tutorial;
+use crate::war::{PeaceDraft, WarOverlay};
+use bevy::prelude::*;
+use eu6_simulation::world::ResetGame;
+
+pub struct WorldUiPlugin;
+
+impl Plugin for WorldUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(ResetGame, reset_interface);
+    }
+}
+
+/// Drops the selection, drafted orders and everything else the interface kept about the world
+/// being torn down.
+fn reset_interface(world: &mut World) {
+    world.insert_resource(WarOverlay::default());
+    world.insert_resource(PeaceDraft::default());
+    world.insert_resource(ProvinceOfferDraft::default());
+    world.insert_resource(SplitDraft::default());
+    world.insert_resource(MapMode::default());
+    world.insert_resource(Tutorial::default());
+    world.insert_resource(CameraBookmarks::default());
+    world.insert_resource(WarRecord::default());
+    world.resource_mut::<CountryFlags>().textures.clear();
+    world.resource_mut::<SelectedProvince>().clear();
+    world.resource_mut::<SelectedArmy>().clear();
+    world.resource_mut::<SelectedFleet>().clear();
+    world.resource_mut::<SelectedCountry>().clear();
+}